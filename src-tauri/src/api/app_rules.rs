@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::utils::productivity::{ProductivityClassifier, AppRule, ProductivityCategory};
 use crate::api::client::ApiClient;
+use crate::sampling::app_focus::AppInfo;
+use crate::storage::{app_rules_cache, app_usage, database};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoteAppRule {
@@ -12,14 +14,33 @@ pub struct RemoteAppRule {
     pub category: String, // PRODUCTIVE, NEUTRAL, UNPRODUCTIVE
     pub priority: i32,
     pub is_active: bool,
+    /// No screenshots while a matching app is focused
+    #[serde(default)]
+    pub suppress_screenshots: bool,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Body of a rules sync response; `full` is false for deltas
+#[derive(Debug, Deserialize)]
+struct RulesUpdate {
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    full: bool,
+    #[serde(default)]
+    rules: Vec<RemoteAppRule>,
+    /// Ids of rules removed since the requested version
+    #[serde(default)]
+    deleted: Vec<String>,
+}
+
 pub struct AppRulesManager {
     classifier: ProductivityClassifier,
     last_sync: Option<chrono::DateTime<chrono::Utc>>,
     sync_interval: chrono::Duration,
+    /// Whether the classifier holds the cached rule set
+    loaded_from_cache: bool,
 }
 
 impl AppRulesManager {
@@ -28,46 +49,134 @@ impl AppRulesManager {
             classifier: ProductivityClassifier::with_default_rules(),
             last_sync: None,
             sync_interval: chrono::Duration::hours(1), // Sync every hour
+            loaded_from_cache: false,
         }
     }
 
+    /// Conditional sync: sends the validators and rules version of the last
+    /// sync, so an unchanged rule set is a 304 and changes can arrive as a delta
     pub async fn sync_rules_from_server(&mut self) -> Result<()> {
-        
+        let mut conn = database::get_connection()?;
+        let state = app_rules_cache::load_state(&conn)?;
+
+        let mut endpoint = "/api/app-rules".to_string();
+        let mut headers = Vec::new();
+        // Versions are opaque tokens; anything that would need escaping gets a full sync
+        if let Some(version) = state
+            .version
+            .as_ref()
+            .filter(|v| v.chars().all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c)))
+        {
+            endpoint = format!("{}?since={}", endpoint, version);
+        }
+        if let Some(etag) = &state.etag {
+            headers.push(("If-None-Match", etag.clone()));
+        }
+        if let Some(last_modified) = &state.last_modified {
+            headers.push(("If-Modified-Since", last_modified.clone()));
+        }
+
         let client = ApiClient::new().await?;
-        let response = client.get_with_auth("/api/app-rules").await?;
-        
-        if response.status().is_success() {
-            let remote_rules: Vec<RemoteAppRule> = response.json().await?;
-            
-            // Convert remote rules to local rules
-            let mut local_rules = Vec::new();
-            for remote_rule in remote_rules {
-                let category = match remote_rule.category.as_str() {
-                    "PRODUCTIVE" => ProductivityCategory::PRODUCTIVE,
-                    "UNPRODUCTIVE" => ProductivityCategory::UNPRODUCTIVE,
-                    _ => ProductivityCategory::NEUTRAL,
-                };
-                
-                let local_rule = AppRule {
-                    matcher_type: remote_rule.matcher_type,
-                    value: remote_rule.value,
-                    category,
-                    priority: remote_rule.priority,
-                    is_active: remote_rule.is_active,
-                };
-                
-                local_rules.push(local_rule);
-            }
-            
-            // Update classifier with new rules
-            self.classifier.clear_rules();
-            self.classifier.add_rules(local_rules);
-            
-            self.last_sync = Some(chrono::Utc::now());
+        let response = client.get_with_auth_headers(&endpoint, &headers).await?;
+        let status = response.status();
+
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            log::debug!("App rules unchanged (version {:?})", state.version);
+        } else if status.is_success() {
+            let header = |name: &str| {
+                response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+            };
+            let etag = header("etag");
+            let last_modified = header("last-modified");
+
+            let update: RulesUpdate = match response.json::<serde_json::Value>().await? {
+                // Servers without delta support return the full list
+                serde_json::Value::Array(rules) => RulesUpdate {
+                    version: None,
+                    full: true,
+                    rules: serde_json::from_value(serde_json::Value::Array(rules))?,
+                    deleted: Vec::new(),
+                },
+                body => serde_json::from_value(body)?,
+            };
+            // A delta only makes sense on top of what we have
+            let full = update.full || state.version.is_none();
+
+            let upserts = update
+                .rules
+                .iter()
+                .map(|rule| Ok((rule.id.clone(), serde_json::to_string(rule)?)))
+                .collect::<Result<Vec<_>>>()?;
+            app_rules_cache::apply(
+                &mut conn,
+                full,
+                &upserts,
+                &update.deleted,
+                &app_rules_cache::RulesSyncState { etag, last_modified, version: update.version },
+            )?;
+            log::info!(
+                "App rules synced: {} ({} changed, {} removed)",
+                if full { "full" } else { "delta" },
+                upserts.len(),
+                update.deleted.len()
+            );
+            self.loaded_from_cache = false;
         } else {
-            log::warn!("Failed to sync app rules from server: {}", response.status());
+            log::warn!("Failed to sync app rules from server: {}", status);
+            return Ok(());
         }
-        
+
+        if !self.loaded_from_cache {
+            self.load_cached_rules(&conn)?;
+        }
+        self.last_sync = Some(chrono::Utc::now());
+        Ok(())
+    }
+
+    /// Use the cached rule set from the last sync, or the bundled defaults
+    /// when nothing was synced yet (first run)
+    fn load_initial_rules(&mut self) -> Result<()> {
+        let conn = database::get_connection()?;
+        if app_rules_cache::load_rules(&conn)?.is_empty() {
+            log::info!("No synced app rules yet, using bundled defaults");
+            self.classifier.clear_rules();
+            self.classifier.add_rules(ProductivityClassifier::bundled_rules());
+            return Ok(());
+        }
+        self.load_cached_rules(&conn)
+    }
+
+    /// Replace the classifier's rules with the cached rule set
+    fn load_cached_rules(&mut self, conn: &rusqlite::Connection) -> Result<()> {
+        let mut local_rules = Vec::new();
+        for json in app_rules_cache::load_rules(conn)? {
+            let remote_rule: RemoteAppRule = match serde_json::from_str(&json) {
+                Ok(rule) => rule,
+                Err(e) => {
+                    log::warn!("Skipping unreadable cached app rule: {}", e);
+                    continue;
+                }
+            };
+            let category = match remote_rule.category.as_str() {
+                "PRODUCTIVE" => ProductivityCategory::PRODUCTIVE,
+                "UNPRODUCTIVE" => ProductivityCategory::UNPRODUCTIVE,
+                _ => ProductivityCategory::NEUTRAL,
+            };
+
+            local_rules.push(AppRule {
+                matcher_type: remote_rule.matcher_type,
+                value: remote_rule.value,
+                category,
+                priority: remote_rule.priority,
+                is_active: remote_rule.is_active,
+                suppress_screenshots: remote_rule.suppress_screenshots,
+            });
+        }
+
+        // Update classifier with new rules
+        self.classifier.clear_rules();
+        self.classifier.add_rules(local_rules);
+        self.loaded_from_cache = true;
         Ok(())
     }
 
@@ -82,6 +191,11 @@ impl AppRulesManager {
     }
 
     pub async fn auto_sync_if_needed(&mut self) -> Result<()> {
+        // Rules can wait until the machine is charged
+        if crate::sampling::power_state::is_battery_critical() {
+            log::debug!("Skipping app rule sync: battery critical");
+            return Ok(());
+        }
         if self.should_sync().await {
             if let Err(e) = self.sync_rules_from_server().await {
                 log::error!("Failed to auto-sync app rules: {}", e);
@@ -132,7 +246,9 @@ impl AppRulesManager {
         Ok(())
     }
 
-    pub async fn get_rule_statistics(&self) -> Result<RuleStatistics> {
+    /// Rule counts, plus time per category and per rule over the recorded
+    /// `usage` (re-classified against the current rules)
+    pub fn get_rule_statistics(&self, usage: &[app_usage::UsageGroup]) -> RuleStatistics {
         let rules = self.get_rules();
         let mut stats = RuleStatistics {
             total_rules: rules.len(),
@@ -144,6 +260,13 @@ impl AppRulesManager {
             glob_matchers: 0,
             regex_matchers: 0,
             domain_matchers: 0,
+            window_days: RULE_STATS_WINDOW_DAYS,
+            productive_seconds: 0,
+            neutral_seconds: 0,
+            unproductive_seconds: 0,
+            idle_seconds: 0,
+            unmatched_seconds: 0,
+            rule_usage: Vec::new(),
         };
         
         for rule in rules {
@@ -165,11 +288,71 @@ impl AppRulesManager {
                 _ => {}
             }
         }
-        
-        Ok(stats)
+
+        let mut per_rule: Vec<(i64, i64)> = vec![(0, 0); rules.len()];
+        for group in usage {
+            // Category as recorded at the time
+            if group.is_idle {
+                stats.idle_seconds += group.seconds;
+                continue;
+            }
+            match group.category {
+                ProductivityCategory::PRODUCTIVE => stats.productive_seconds += group.seconds,
+                ProductivityCategory::NEUTRAL => stats.neutral_seconds += group.seconds,
+                ProductivityCategory::UNPRODUCTIVE => stats.unproductive_seconds += group.seconds,
+            }
+
+            // Rule that classifies it now (domains aren't stored per session,
+            // so DOMAIN rules only match via the window title)
+            match self.classifier.matching_rule(
+                &group.app_name,
+                &group.app_id,
+                group.window_title.as_deref(),
+                None,
+                None,
+            ) {
+                Some((index, _)) => {
+                    per_rule[index].0 += group.seconds;
+                    per_rule[index].1 += group.sessions;
+                }
+                None => stats.unmatched_seconds += group.seconds,
+            }
+        }
+
+        stats.rule_usage = rules
+            .iter()
+            .zip(per_rule)
+            .filter(|(_, (seconds, _))| *seconds > 0)
+            .map(|(rule, (matched_seconds, match_count))| RuleUsage {
+                matcher_type: rule.matcher_type.clone(),
+                value: rule.value.clone(),
+                category: rule.category.clone(),
+                priority: rule.priority,
+                matched_seconds,
+                match_count,
+            })
+            .collect();
+        stats.rule_usage.sort_by(|a, b| b.matched_seconds.cmp(&a.matched_seconds));
+
+        stats
     }
 }
 
+/// Days of recorded usage covered by rule statistics
+const RULE_STATS_WINDOW_DAYS: i64 = 30;
+
+/// Time a rule decided the category for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleUsage {
+    pub matcher_type: String,
+    pub value: String,
+    pub category: ProductivityCategory,
+    pub priority: i32,
+    pub matched_seconds: i64,
+    /// App sessions the rule matched
+    pub match_count: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuleStatistics {
     pub total_rules: usize,
@@ -181,6 +364,16 @@ pub struct RuleStatistics {
     pub glob_matchers: usize,
     pub regex_matchers: usize,
     pub domain_matchers: usize,
+    /// Days of usage the time fields below cover
+    pub window_days: i64,
+    pub productive_seconds: i64,
+    pub neutral_seconds: i64,
+    pub unproductive_seconds: i64,
+    pub idle_seconds: i64,
+    /// Active time no rule matched (classified with the default category)
+    pub unmatched_seconds: i64,
+    /// Rules that matched recorded usage, most time first
+    pub rule_usage: Vec<RuleUsage>,
 }
 
 // Global app rules manager instance
@@ -214,11 +407,42 @@ pub async fn add_custom_rule(rule: AppRule) -> Result<()> {
 }
 
 pub async fn get_rule_statistics() -> Result<RuleStatistics> {
+    let since = chrono::Utc::now() - chrono::Duration::days(RULE_STATS_WINDOW_DAYS);
+    let usage = app_usage::usage_groups_since(since).await?;
     let manager = APP_RULES_MANAGER.lock().await;
-    manager.get_rule_statistics().await
+    Ok(manager.get_rule_statistics(&usage))
+}
+
+/// The screenshot suppression rule matching the app. Domains and titles are
+/// matched before the URL policy, since allowlist-only mode records
+/// non-approved sites as "other" and drops their titles.
+fn screenshot_suppression_rule<'a>(classifier: &'a ProductivityClassifier, app: &AppInfo) -> Option<&'a AppRule> {
+    classifier.screenshot_suppression_rule(
+        &app.name,
+        &app.app_id,
+        app.raw_window_title.as_deref().or(app.window_title.as_deref()),
+        app.raw_domain.as_deref().or(app.domain.as_deref()),
+        app.exe_path.as_deref(),
+    )
+}
+
+/// Name of the focused app when an app rule says no screenshots while it is
+/// focused; checked by the screenshot scheduler and job handler before capturing
+pub async fn screenshot_suppressed_by_focused_app() -> Option<String> {
+    let app = match crate::commands::get_current_app().await {
+        Ok(Some(app)) => app,
+        _ => return None,
+    };
+    let manager = APP_RULES_MANAGER.lock().await;
+    let rule = screenshot_suppression_rule(&manager.classifier, &app)?;
+    log::info!("Screenshot suppressed: {} matches {} rule '{}'", app.name, rule.matcher_type, rule.value);
+    Some(app.name)
 }
 
 pub async fn initialize_app_rules() -> Result<()> {
+    if let Err(e) = APP_RULES_MANAGER.lock().await.load_initial_rules() {
+        log::warn!("Failed to load cached app rules, using bundled defaults: {}", e);
+    }
     
     // Try to sync rules from server, but don't fail if it doesn't work
     if let Err(e) = sync_app_rules().await {
@@ -239,3 +463,73 @@ pub async fn initialize_app_rules() -> Result<()> {
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(app: &str, category: ProductivityCategory, is_idle: bool, seconds: i64) -> app_usage::UsageGroup {
+        app_usage::UsageGroup {
+            app_name: app.to_string(),
+            app_id: app.to_string(),
+            window_title: None,
+            category,
+            is_idle,
+            seconds,
+            sessions: 2,
+        }
+    }
+
+    #[test]
+    fn test_suppression_matches_domains_hidden_by_allowlist() {
+        let mut classifier = ProductivityClassifier::new();
+        classifier.add_rules(vec![AppRule {
+            matcher_type: "DOMAIN".to_string(),
+            value: "hr.example.com".to_string(),
+            category: ProductivityCategory::NEUTRAL,
+            priority: 100,
+            is_active: true,
+            suppress_screenshots: true,
+        }]);
+        let sanitizer = crate::utils::privacy::UrlSanitizer::new(false).with_allowlist(vec!["github.com".to_string()]);
+        let url_info = crate::sampling::browser_url::BrowserUrlInfo::from_url("https://hr.example.com/payroll".to_string());
+        let (url, domain) = url_info.sanitized(&sanitizer);
+        assert_eq!(domain.as_deref(), Some(crate::utils::privacy::OTHER_DOMAIN));
+
+        let app = AppInfo {
+            name: "Google Chrome".to_string(),
+            app_id: "com.google.Chrome".to_string(),
+            window_title: Some("Payroll - Google Chrome".to_string()),
+            url,
+            domain,
+            raw_domain: url_info.domain,
+            raw_window_title: None,
+            exe_path: None,
+            file_version: None,
+            pid: None,
+            virtual_desktop: None,
+        };
+        assert!(screenshot_suppression_rule(&classifier, &app).is_some());
+    }
+
+    #[test]
+    fn test_rule_statistics_attribute_time_to_rules() {
+        let manager = AppRulesManager::new();
+        let usage = vec![
+            group("code.exe", ProductivityCategory::PRODUCTIVE, false, 3600),
+            group("code.exe", ProductivityCategory::PRODUCTIVE, true, 300),
+            group("some-unknown-tool", ProductivityCategory::NEUTRAL, false, 120),
+        ];
+
+        let stats = manager.get_rule_statistics(&usage);
+        assert_eq!(stats.productive_seconds, 3600);
+        assert_eq!(stats.neutral_seconds, 120);
+        assert_eq!(stats.idle_seconds, 300);
+        assert_eq!(stats.unmatched_seconds, 120);
+
+        let top = &stats.rule_usage[0];
+        assert_eq!(top.value, "code.exe");
+        assert_eq!(top.matched_seconds, 3600);
+        assert_eq!(top.match_count, 2);
+    }
+}
@@ -0,0 +1,192 @@
+//! Upload bandwidth cap
+//!
+//! Syncing a big backlog (queued screenshots, offline events) over a metered
+//! hotspot would otherwise take all of the user's upstream. Upload bodies are
+//! wrapped in a stream that hands out chunks only as fast as a byte token
+//! bucket allows, and queue drains pace themselves through the same bucket.
+//!
+//! The cap comes from the policy (`upload_bandwidth_kbps`) and can be
+//! overridden per device; 0 means unlimited. Live heartbeats and events are
+//! not throttled.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use super::employee_settings::PolicySettings;
+use super::rate_limiter::TokenBucket;
+use crate::error::AgentError;
+use crate::storage::audit_log;
+
+/// `secure_store` setting holding the device override (kbit/s)
+const BANDWIDTH_LIMIT_KEY: &str = "upload_bandwidth_kbps";
+
+/// Largest chunk a throttled body is split into
+const MAX_CHUNK_SIZE: usize = 16 * 1024;
+
+#[derive(Default)]
+struct Limiter {
+    policy_kbps: u32,
+    user_kbps: Option<u32>,
+    /// Byte bucket holding one second of traffic; `None` when unlimited
+    bucket: Option<TokenBucket>,
+}
+
+impl Limiter {
+    fn effective_kbps(&self) -> u32 {
+        self.user_kbps.unwrap_or(self.policy_kbps)
+    }
+
+    fn reconfigure(&mut self) {
+        let kbps = self.effective_kbps();
+        if kbps == 0 {
+            if self.bucket.take().is_some() {
+                log::info!("Upload bandwidth cap removed");
+            }
+            return;
+        }
+        let bytes_per_sec = bytes_per_second(kbps);
+        match self.bucket.as_mut() {
+            Some(bucket) => bucket.reconfigure(bytes_per_sec, bytes_per_sec),
+            None => self.bucket = Some(TokenBucket::new(bytes_per_sec, bytes_per_sec)),
+        }
+        log::info!("Upload bandwidth capped at {} kbit/s", kbps);
+    }
+}
+
+fn bytes_per_second(kbps: u32) -> f64 {
+    kbps as f64 * 1000.0 / 8.0
+}
+
+static LIMITER: Mutex<Option<Limiter>> = Mutex::new(None);
+
+fn with_limiter<T>(f: impl FnOnce(&mut Limiter) -> T) -> T {
+    let mut guard = match LIMITER.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    f(guard.get_or_insert_with(Limiter::default))
+}
+
+fn is_limited() -> bool {
+    with_limiter(|limiter| limiter.bucket.is_some())
+}
+
+/// A quarter second of traffic, so chunks never exceed the bucket
+fn chunk_size() -> usize {
+    let kbps = with_limiter(|limiter| limiter.effective_kbps());
+    ((bytes_per_second(kbps) / 4.0) as usize).clamp(1, MAX_CHUNK_SIZE)
+}
+
+/// Wait until `bytes` may be sent under the cap (returns at once when unlimited)
+pub async fn consume(bytes: usize) {
+    loop {
+        let wait = with_limiter(|limiter| match limiter.bucket.as_mut() {
+            Some(bucket) => bucket.try_acquire_at(bytes as f64, Instant::now()).err(),
+            None => None,
+        });
+        match wait {
+            Some(wait) => tokio::time::sleep(wait).await,
+            None => return,
+        }
+    }
+}
+
+/// Upload body that is released chunk by chunk under the cap
+pub fn throttled_body(data: Vec<u8>) -> reqwest::Body {
+    if !is_limited() {
+        return reqwest::Body::from(data);
+    }
+    let stream = futures_util::stream::unfold((data, 0usize), |(data, offset)| async move {
+        if offset >= data.len() {
+            return None;
+        }
+        let end = (offset + chunk_size()).min(data.len());
+        consume(end - offset).await;
+        let chunk = data[offset..end].to_vec();
+        Some((Ok::<_, std::io::Error>(chunk), (data, end)))
+    });
+    reqwest::Body::wrap_stream(stream)
+}
+
+/// Apply the cap from the employee policy (called whenever settings are refreshed)
+pub fn apply_policy(policy: &PolicySettings) {
+    with_limiter(|limiter| {
+        if limiter.policy_kbps != policy.upload_bandwidth_kbps {
+            limiter.policy_kbps = policy.upload_bandwidth_kbps;
+            limiter.reconfigure();
+        }
+    });
+}
+
+/// Load the device override (called at startup)
+pub async fn load_user_limit() {
+    match crate::storage::secure_store::get_setting(BANDWIDTH_LIMIT_KEY).await {
+        // Cleared overrides are stored as empty strings
+        Ok(value) => {
+            let user_kbps = value.and_then(|v| v.trim().parse::<u32>().ok());
+            with_limiter(|limiter| {
+                limiter.user_kbps = user_kbps;
+                limiter.reconfigure();
+            });
+        }
+        Err(e) => log::warn!("Failed to read upload bandwidth limit: {}", e),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthLimit {
+    /// Cap in effect in kbit/s (0 = unlimited)
+    pub kbps: u32,
+    pub policy_kbps: u32,
+    pub user_kbps: Option<u32>,
+}
+
+#[tauri::command]
+pub async fn get_upload_bandwidth_limit() -> Result<BandwidthLimit, AgentError> {
+    Ok(with_limiter(|limiter| BandwidthLimit {
+        kbps: limiter.effective_kbps(),
+        policy_kbps: limiter.policy_kbps,
+        user_kbps: limiter.user_kbps,
+    }))
+}
+
+/// Cap uploads from this device at `kbps` kbit/s (0 = unlimited), or go back
+/// to the org default with `None`
+#[tauri::command]
+pub async fn set_upload_bandwidth_limit(kbps: Option<u32>) -> Result<BandwidthLimit, AgentError> {
+    let result = set_upload_bandwidth_limit_impl(kbps).await;
+    audit_log::record("set_upload_bandwidth_limit", &result);
+    result
+}
+
+async fn set_upload_bandwidth_limit_impl(kbps: Option<u32>) -> Result<BandwidthLimit, AgentError> {
+    let value = kbps.map(|k| k.to_string()).unwrap_or_default();
+    crate::storage::secure_store::store_setting(BANDWIDTH_LIMIT_KEY, &value)
+        .await
+        .map_err(|e| AgentError::Storage(format!("Failed to save upload bandwidth limit: {}", e)))?;
+    with_limiter(|limiter| {
+        limiter.user_kbps = kbps;
+        limiter.reconfigure();
+    });
+    get_upload_bandwidth_limit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_override_and_unlimited() {
+        let mut limiter = Limiter { policy_kbps: 800, ..Default::default() };
+        limiter.reconfigure();
+        assert!(limiter.bucket.is_some());
+        assert_eq!(bytes_per_second(800), 100_000.0);
+
+        limiter.user_kbps = Some(0);
+        limiter.reconfigure();
+        assert_eq!(limiter.effective_kbps(), 0);
+        assert!(limiter.bucket.is_none());
+    }
+}
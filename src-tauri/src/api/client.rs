@@ -1,9 +1,14 @@
 use anyhow::Result;
-use reqwest::{Client, Response};
+use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::io::Write;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::future::Future;
+use std::sync::OnceLock;
 use std::time::Duration;
 
+use crate::error::AgentError;
 use crate::storage::secure_store;
 
 use std::env;
@@ -40,94 +45,287 @@ pub struct DeviceInfo {
     pub platform: String,
 }
 
+/// Body encoding for ingest payloads.
+///
+/// JSON heartbeats every few seconds from thousands of agents add up, so when
+/// the backend advertises support (see `ingest_schema`) payloads are sent as
+/// MessagePack or gzip-compressed JSON instead. A 415 response switches the
+/// agent back to plain JSON and the request is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestEncoding {
+    Json = 0,
+    GzipJson = 1,
+    MessagePack = 2,
+}
+
+impl IngestEncoding {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => IngestEncoding::GzipJson,
+            2 => IngestEncoding::MessagePack,
+            _ => IngestEncoding::Json,
+        }
+    }
+
+    /// Most compact encoding among those the backend lists ("msgpack", "gzip")
+    fn preferred(accepted: &[String]) -> Self {
+        let accepts = |name: &str| accepted.iter().any(|a| a.eq_ignore_ascii_case(name));
+        if accepts("msgpack") {
+            IngestEncoding::MessagePack
+        } else if accepts("gzip") {
+            IngestEncoding::GzipJson
+        } else {
+            IngestEncoding::Json
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            IngestEncoding::MessagePack => "application/msgpack",
+            IngestEncoding::Json | IngestEncoding::GzipJson => "application/json",
+        }
+    }
+
+    fn encode(self, payload: &Value) -> Result<Vec<u8>> {
+        Ok(match self {
+            IngestEncoding::Json => serde_json::to_vec(payload)?,
+            IngestEncoding::GzipJson => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&serde_json::to_vec(payload)?)?;
+                encoder.finish()?
+            }
+            IngestEncoding::MessagePack => rmp_serde::to_vec_named(payload)?,
+        })
+    }
+}
+
+static INGEST_ENCODING: AtomicU8 = AtomicU8::new(IngestEncoding::Json as u8);
+
+/// Pick the ingest encoding from what the backend accepts (called after
+/// schema negotiation)
+pub fn set_accepted_encodings(accepted: &[String]) {
+    let encoding = IngestEncoding::preferred(accepted);
+    if IngestEncoding::from_u8(INGEST_ENCODING.swap(encoding as u8, Ordering::Relaxed)) != encoding {
+        log::info!("Ingest payload encoding: {:?}", encoding);
+    }
+}
+
+/// POST a (versioned, signed) ingest payload in the negotiated encoding,
+/// retrying as plain JSON if the server rejects the encoding
+async fn post_ingest(client: &Client, url: &str, device_token: &str, payload: &Value) -> Result<Response> {
+    let payload = super::ingest_schema::versioned(url, payload);
+    let encoding = IngestEncoding::from_u8(INGEST_ENCODING.load(Ordering::Relaxed));
+    let send = |encoding: IngestEncoding| {
+        let body = encoding.encode(&payload);
+        async move {
+            let body = body?;
+            let mut request = client
+                .post(url)
+                .timeout(REQUEST_TIMEOUT)
+                .header("Authorization", format!("Bearer {}", device_token))
+                .header("Content-Type", encoding.content_type());
+            if encoding == IngestEncoding::GzipJson {
+                request = request.header("Content-Encoding", "gzip");
+            }
+            // Signed over the bytes on the wire, whatever the encoding
+            if let Some(signature) = super::request_signing::sign(&body) {
+                request = request
+                    .header(super::request_signing::TIMESTAMP_HEADER, signature.timestamp)
+                    .header(super::request_signing::NONCE_HEADER, signature.nonce)
+                    .header(super::request_signing::SIGNATURE_HEADER, signature.signature);
+            }
+            Ok::<_, anyhow::Error>(request.body(body).send().await?)
+        }
+    };
+
+    let mut response = send(encoding).await?;
+    if response.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE && encoding != IngestEncoding::Json {
+        log::warn!("Server rejected {:?} ingest payload, falling back to JSON", encoding);
+        INGEST_ENCODING.store(IngestEncoding::Json as u8, Ordering::Relaxed);
+        response = send(IngestEncoding::Json).await?;
+    }
+    // A deleted device record shows up as every ingest call being rejected
+    super::device_repair::observe(response.status());
+    Ok(response)
+}
+
+/// Default timeout for API requests made through `ApiClient`
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+static SHARED_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Process-wide HTTP client.
+///
+/// `reqwest::Client` holds the connection pool, so building one per call
+/// meant a fresh TCP + TLS handshake for every heartbeat and event. All
+/// callers share this one instead (clones share the pool). No overall timeout
+/// is set here since some requests are long-lived streams; callers set
+/// `RequestBuilder::timeout` where they need one.
+pub fn shared_client() -> Client {
+    SHARED_CLIENT
+        .get_or_init(|| {
+            Client::builder()
+                .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
+                .connect_timeout(Duration::from_secs(10))
+                .pool_idle_timeout(Duration::from_secs(90))
+                .pool_max_idle_per_host(4)
+                .tcp_keepalive(Duration::from_secs(60))
+                .build()
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to build shared HTTP client, using defaults: {}", e);
+                    Client::new()
+                })
+        })
+        .clone()
+}
+
 pub struct ApiClient {
     client: Client,
     base_url: String,
 }
 
+async fn send(request: reqwest::RequestBuilder) -> Result<Response> {
+    Ok(request.send().await?)
+}
+
+/// The request never reached the server, so another endpoint can be tried
+/// without risking a duplicate
+fn is_connect_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_connect())
+}
+
+fn is_timeout(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout())
+}
+
 impl ApiClient {
     pub async fn new() -> Result<Self> {
-        
-
         let base_url = crate::storage::get_server_url().await?;
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-            .build()?;
+        Ok(Self { client: shared_client(), base_url })
+    }
 
-        Ok(Self { client, base_url })
+    /// Send to `endpoint` on the best available server, failing over to the
+    /// next configured server URL when one can't be connected to. Timeouts
+    /// and 5xx answers aren't retried elsewhere (the server may have acted on
+    /// the request) but steer later requests away from the endpoint.
+    async fn send_with_failover<F, Fut>(&self, endpoint: &str, request: F) -> Result<Response>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<Response>>,
+    {
+        let mut last_result = None;
+        for base_url in super::endpoints::candidates(&self.base_url) {
+            match request(format!("{}{}", base_url, endpoint)).await {
+                Ok(response) => {
+                    if super::endpoints::is_endpoint_failure(response.status()) {
+                        super::endpoints::record_failure(&base_url);
+                    } else {
+                        super::endpoints::record_success(&base_url);
+                    }
+                    return Ok(response);
+                }
+                Err(e) if is_connect_error(&e) => {
+                    super::endpoints::record_failure(&base_url);
+                    last_result = Some(Err(e));
+                }
+                Err(e) => {
+                    if is_timeout(&e) {
+                        super::endpoints::record_failure(&base_url);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        last_result.unwrap_or_else(|| Err(anyhow::anyhow!("No server URL configured")))
     }
 
     pub async fn get_with_auth(&self, endpoint: &str) -> Result<Response> {
-        let device_token = crate::storage::get_device_token().await
-            .map_err(|_| anyhow::anyhow!("No device token available"))?;
-        log::info!("Device token: {}", device_token);
-        let url = format!("{}{}", self.base_url, endpoint);
+        self.get_with_auth_headers(endpoint, &[]).await
+    }
 
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", device_token))
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
+    /// `get_with_auth` with extra request headers (e.g. conditional request validators)
+    pub async fn get_with_auth_headers(&self, endpoint: &str, headers: &[(&str, String)]) -> Result<Response> {
+        let device_token = crate::storage::get_device_token().await
+            .map_err(|_| AgentError::NotAuthenticated)?;
 
-        Ok(response)
+        self.send_with_failover(endpoint, |url| {
+            let mut request = self.client
+                .get(url)
+                .timeout(REQUEST_TIMEOUT)
+                .header("Authorization", format!("Bearer {}", device_token))
+                .header("Content-Type", "application/json");
+            for (name, value) in headers {
+                request = request.header(*name, value);
+            }
+            send(request)
+        })
+        .await
     }
 
     pub async fn post_with_auth(&self, endpoint: &str, body: &Value) -> Result<Response> {
         let device_token = crate::storage::get_device_token().await
-            .map_err(|_| anyhow::anyhow!("No device token available"))?;
-        let url = format!("{}{}", self.base_url, endpoint);
-
-        let response = self.client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", device_token))
-            .header("Content-Type", "application/json")
-            .json(body)
-            .send()
-            .await?;
+            .map_err(|_| AgentError::NotAuthenticated)?;
+        if endpoint.starts_with("/api/ingest/") {
+            let device_token = device_token.as_str();
+            return self
+                .send_with_failover(endpoint, |url| async move {
+                    post_ingest(&self.client, &url, device_token, body).await
+                })
+                .await;
+        }
 
-        Ok(response)
+        self.send_with_failover(endpoint, |url| {
+            send(
+                self.client
+                    .post(url)
+                    .timeout(REQUEST_TIMEOUT)
+                    .header("Authorization", format!("Bearer {}", device_token))
+                    .header("Content-Type", "application/json")
+                    .json(body)
+            )
+        })
+        .await
     }
 
     #[allow(dead_code)]
     pub async fn post(&self, endpoint: &str, body: &Value) -> Result<Response> {
-        let url = format!("{}{}", self.base_url, endpoint);
-
-        let response = self.client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .json(body)
-            .send()
-            .await?;
-
-        Ok(response)
+        self.send_with_failover(endpoint, |url| {
+            send(
+                self.client
+                    .post(url)
+                    .timeout(REQUEST_TIMEOUT)
+                    .header("Content-Type", "application/json")
+                    .json(body)
+            )
+        })
+        .await
     }
 
     #[allow(dead_code)]
     pub async fn put_with_auth(&self, endpoint: &str, body: &Value) -> Result<Response> {
         let device_token = secure_store::get_device_token().await?
-            .ok_or_else(|| anyhow::anyhow!("No device token available"))?;
-        let url = format!("{}{}", self.base_url, endpoint);
-
-        let response = self.client
-            .put(&url)
-            .header("Authorization", format!("Bearer {}", device_token))
-            .header("Content-Type", "application/json")
-            .json(body)
-            .send()
-            .await?;
-
-        Ok(response)
+            .ok_or(AgentError::NotAuthenticated)?;
+        self.send_with_failover(endpoint, |url| {
+            send(
+                self.client
+                    .put(url)
+                    .timeout(REQUEST_TIMEOUT)
+                    .header("Authorization", format!("Bearer {}", device_token))
+                    .header("Content-Type", "application/json")
+                    .json(body)
+            )
+        })
+        .await
     }
 
     #[allow(dead_code)]
     pub async fn upload_file(&self, presigned_url: &str, file_data: &[u8], content_type: &str) -> Result<Response> {
         let response = self.client
             .put(presigned_url)
+            .timeout(REQUEST_TIMEOUT)
             .header("Content-Type", content_type)
-            .body(file_data.to_vec())
+            .body(super::bandwidth::throttled_body(file_data.to_vec()))
             .send()
             .await?;
 
@@ -154,16 +352,11 @@ impl ApiClient {
 /// Standalone function to check for active session on the backend
 /// This can be called before the full API client is set up
 pub async fn check_backend_active_session(server_url: &str, device_token: &str) -> Result<ActiveSessionResponse> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(10))
-        .connect_timeout(Duration::from_secs(5))
-        .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-        .build()?;
-    
     let url = format!("{}/api/devices/active-session", server_url.trim_end_matches('/'));
     
-    let response = client
+    let response = shared_client()
         .get(&url)
+        .timeout(Duration::from_secs(10))
         .header("Authorization", format!("Bearer {}", device_token))
         .header("Content-Type", "application/json")
         .send()
@@ -179,3 +372,21 @@ pub async fn check_backend_active_session(server_url: &str, device_token: &str)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ingest_encodings() {
+        assert_eq!(IngestEncoding::preferred(&["gzip".into(), "msgpack".into()]), IngestEncoding::MessagePack);
+        assert_eq!(IngestEncoding::preferred(&["GZIP".into()]), IngestEncoding::GzipJson);
+        assert_eq!(IngestEncoding::preferred(&[]), IngestEncoding::Json);
+
+        let payload = serde_json::json!({ "status": "active", "idle_time_seconds": 3 });
+        let packed = IngestEncoding::MessagePack.encode(&payload).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Value>(&packed).unwrap(), payload);
+        let gzipped = IngestEncoding::GzipJson.encode(&payload).unwrap();
+        assert_eq!(&gzipped[..2], &[0x1f, 0x8b]);
+    }
+}
@@ -57,8 +57,8 @@ pub async fn upload_screenshot_file(
     employee_id: &str,
     device_id: &str,
 ) -> Result<CloudinaryUploadResult> {
-    // Read the file
-    let file_data = std::fs::read(file_path)?;
+    // Read and decrypt the file
+    let file_data = crate::screenshots::at_rest::read(file_path)?;
     let file_size = file_data.len();
     
     log::info!(
@@ -67,6 +67,8 @@ pub async fn upload_screenshot_file(
         file_size
     );
     
+    super::rate_limiter::acquire(super::rate_limiter::IngestKind::Screenshot).await;
+    
     let client = ApiClient::new().await?;
     let folder = get_cloudinary_folder(employee_id);
     
@@ -165,14 +167,12 @@ pub async fn upload_screenshot_file(
         .text("public_id", public_id.clone())
         .part(
             "file",
-            reqwest::multipart::Part::bytes(file_data)
+            reqwest::multipart::Part::stream_with_length(super::bandwidth::throttled_body(file_data), file_size as u64)
                 .file_name("screenshot.jpg")
                 .mime_str("image/jpeg")?,
         );
     
-    let http_client = reqwest::Client::builder()
-        .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-        .build()?;
+    let http_client = crate::api::client::shared_client();
     let upload_response = http_client
         .post(&upload_url)
         .multipart(form)
@@ -234,6 +234,7 @@ pub async fn record_screenshot(
     device_id: &str,
     cloudinary_result: &CloudinaryUploadResult,
     taken_at: chrono::DateTime<chrono::Utc>,
+    scale_factor: Option<f64>,
     is_auto: bool,
 ) -> Result<String> {
     let client = ApiClient::new().await?;
@@ -247,6 +248,7 @@ pub async fn record_screenshot(
         "height": cloudinary_result.height,
         "format": cloudinary_result.format,
         "bytes": cloudinary_result.bytes,
+        "scaleFactor": scale_factor,
         "isAuto": is_auto,
         "takenAt": taken_at.to_rfc3339()
     });
@@ -291,6 +293,7 @@ pub async fn upload_and_record_screenshot(
     employee_id: &str,
     device_id: &str,
     taken_at: chrono::DateTime<chrono::Utc>,
+    scale_factor: Option<f64>,
     is_auto: bool,
 ) -> Result<String> {
     log::info!(
@@ -310,6 +313,7 @@ pub async fn upload_and_record_screenshot(
         device_id,
         &cloudinary_result,
         taken_at,
+        scale_factor,
         is_auto,
     ).await?;
     
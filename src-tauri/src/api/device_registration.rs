@@ -5,15 +5,12 @@ use uuid::Uuid;
 use crate::api::client::ApiClient;
 
 pub async fn register_device(server_url: &str, email: &str, password: &str) -> Result<(String, String)> {
-    // Create a temporary client for registration
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-        .build()?;
+    let client = crate::api::client::shared_client();
 
     // First, authenticate to get user token
     let auth_response = client
         .post(&format!("{}/api/auth/login", server_url))
+        .timeout(std::time::Duration::from_secs(30))
         .json(&json!({
             "email": email,
             "password": password
@@ -42,11 +39,13 @@ pub async fn register_device(server_url: &str, email: &str, password: &str) -> R
         "name": device_name,
         "platform": std::env::consts::OS,
         "version": std::env::consts::ARCH,
-        "agent_version": env!("CARGO_PKG_VERSION")
+        "agent_version": env!("CARGO_PKG_VERSION"),
+        "environment": crate::utils::session_environment::detect()
     });
 
     let device_response = client
         .post(&format!("{}/api/devices/register", server_url))
+        .timeout(std::time::Duration::from_secs(30))
         .header("Authorization", format!("Bearer {}", user_token))
         .json(&device_data)
         .send()
@@ -0,0 +1,128 @@
+//! Repairing a device record deleted on the backend
+//!
+//! When an admin removes the device (or the backend loses it), every ingest
+//! call starts failing with 401/404 and the agent would keep queueing data
+//! until the user happened to log out. After `REPAIR_THRESHOLD` consecutive
+//! rejected ingest calls the device is registered again in the background
+//! with the cached employee and the stable device UUID, which the backend
+//! matches to the old record. If that fails too, the frontend gets
+//! `reauth-required` and the user is asked to sign in again.
+//!
+//! Queued events and heartbeats are kept throughout: the queue processor
+//! holds off while credentials are rejected instead of spending their
+//! retries, and sends them once a login or repair succeeds.
+
+use reqwest::StatusCode;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::error::AgentError;
+
+/// Consecutive rejected ingest calls before re-registering
+const REPAIR_THRESHOLD: u32 = 3;
+
+static REJECTED: AtomicU32 = AtomicU32::new(0);
+static REPAIRING: AtomicBool = AtomicBool::new(false);
+/// Repair failed; waiting for the user to sign in again
+static NEEDS_LOGIN: AtomicBool = AtomicBool::new(false);
+
+fn is_rejection(status: StatusCode) -> bool {
+    status == StatusCode::UNAUTHORIZED || status == StatusCode::NOT_FOUND
+}
+
+/// Record the status of an ingest call, starting a repair once credentials
+/// have been rejected `REPAIR_THRESHOLD` times in a row
+pub fn observe(status: StatusCode) {
+    if !is_rejection(status) {
+        if status.is_success() {
+            REJECTED.store(0, Ordering::SeqCst);
+        }
+        return;
+    }
+
+    let rejected = REJECTED.fetch_add(1, Ordering::SeqCst) + 1;
+    if rejected < REPAIR_THRESHOLD || NEEDS_LOGIN.load(Ordering::SeqCst) {
+        return;
+    }
+    if REPAIRING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    log::warn!("Ingest rejected {} times in a row ({}), re-registering device", rejected, status);
+    tauri::async_runtime::spawn(async {
+        let result = repair().await;
+        REPAIRING.store(false, Ordering::SeqCst);
+        match result {
+            Ok(()) => {
+                log::info!("Device re-registered, resuming sync");
+                let _ = reset();
+            }
+            Err(e) => {
+                log::error!("Device repair failed, asking the user to sign in again: {}", e);
+                NEEDS_LOGIN.store(true, Ordering::SeqCst);
+                crate::events::reauth_required(&e.to_string());
+                crate::events::notify("notification.reauth_required.title", "notification.reauth_required.body");
+            }
+        }
+    });
+}
+
+/// Register the device again with the cached session identity. Only the
+/// credentials are replaced; the work session and queues are left alone.
+async fn repair() -> Result<(), AgentError> {
+    let state = crate::storage::get_state_service().map_err(|e| AgentError::Internal(e.to_string()))?;
+    let snapshot = state.snapshot();
+    let (Some(email), Some(server_url), Some(employee_id)) = (snapshot.email, snapshot.server_url, snapshot.employee_id)
+    else {
+        return Err(AgentError::NotAuthenticated);
+    };
+
+    let client = super::client::shared_client();
+    let device = crate::commands::request_device_registration(&client, &server_url, &employee_id).await?;
+    crate::commands::store_credentials(&server_url, &email, &employee_id, &device.id, &device.token, &state).await;
+    super::request_signing::store_secret(device.signing_secret.as_deref()).await;
+    if device.has_no_license {
+        log::warn!("Re-registered device has no valid license");
+    }
+    Ok(())
+}
+
+/// Ingest credentials are being rejected; queued data should wait
+pub fn credentials_rejected() -> bool {
+    REPAIRING.load(Ordering::SeqCst) || NEEDS_LOGIN.load(Ordering::SeqCst)
+}
+
+/// New credentials were established (login, repair). Returns whether this
+/// login answers a `reauth-required` prompt, so the session carries on.
+pub fn reset() -> bool {
+    REJECTED.store(0, Ordering::SeqCst);
+    NEEDS_LOGIN.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{agent_env, MockBackend};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_repair_replaces_credentials_only() {
+        let backend = MockBackend::start().await;
+        let _env = agent_env(&backend).await;
+        Mock::given(method("POST"))
+            .and(path("/api/devices/employee-register"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "device": { "id": "repaired-device", "token": "repaired-token" }
+            })))
+            .with_priority(1)
+            .mount(&backend.server)
+            .await;
+        crate::storage::work_session::start_session().await.unwrap();
+
+        repair().await.unwrap();
+        let registration = &backend.received("/api/devices/employee-register").await[0];
+        assert_eq!(registration["employeeId"], "test-employee");
+        assert_eq!(crate::storage::get_device_token().await.unwrap(), "repaired-token");
+        // Still clocked in
+        assert!(crate::storage::work_session::is_session_active().await.unwrap());
+    }
+}
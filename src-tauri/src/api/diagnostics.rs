@@ -0,0 +1,130 @@
+//! Diagnostics upload
+//!
+//! Support can request logs from a device with a `diagnostics` job (or the
+//! `upload_logs` remote command) carrying a case ID and optionally the number
+//! of hours to collect. The agent gathers the matching lines from its log
+//! files together with a health report (versions, permissions, background
+//! services, queues, update state) and uploads them to the backend, so users
+//! don't have to find and email log files themselves.
+
+use anyhow::Result;
+use serde_json::Value;
+
+use super::client::ApiClient;
+use crate::utils::logging;
+
+/// Hours of logs collected when the job doesn't say
+const DEFAULT_LOG_HOURS: i64 = 24;
+
+/// Upper bound on requested hours
+const MAX_LOG_HOURS: i64 = 24 * 7;
+
+/// Only the newest log output is sent beyond this size
+const MAX_LOG_BYTES: usize = 2 * 1024 * 1024;
+
+/// Log lines from the last `hours`, newest output kept when over the size limit
+fn collect_logs(hours: i64) -> String {
+    let since = chrono::Utc::now() - chrono::Duration::hours(hours);
+    let mut lines = Vec::new();
+    for path in logging::log_files() {
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                // Files written by older versions may hold unredacted lines
+                lines.extend(logging::lines_since(&contents, since).into_iter().map(|line| logging::redact(line).into_owned()));
+            }
+            Err(e) => log::warn!("Failed to read log file {}: {}", path.display(), e),
+        }
+    }
+    truncate_to_newest(lines.join("\n"), MAX_LOG_BYTES)
+}
+
+fn truncate_to_newest(logs: String, max_bytes: usize) -> String {
+    if logs.len() <= max_bytes {
+        return logs;
+    }
+    let mut start = logs.len() - max_bytes;
+    while !logs.is_char_boundary(start) {
+        start += 1;
+    }
+    // Drop the partial first line
+    let start = logs[start..].find('\n').map(|i| start + i + 1).unwrap_or(start);
+    logs[start..].to_string()
+}
+
+/// Snapshot of the agent's state for support
+pub async fn health_report() -> Value {
+    let pending_events = crate::storage::offline_queue::get_pending_events()
+        .await
+        .map(|events| events.len())
+        .ok();
+    let pending_heartbeats = crate::storage::offline_queue::get_pending_heartbeats()
+        .await
+        .map(|heartbeats| heartbeats.len())
+        .ok();
+    let pending_screenshots = crate::storage::screenshot_queue::get_queue_count().await.ok();
+
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "platform": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "osVersion": sysinfo::System::long_os_version(),
+        "authenticated": crate::sampling::is_authenticated().await,
+        "clockedIn": crate::sampling::is_clocked_in().await,
+        "servicesPaused": crate::sampling::is_services_paused().await,
+        "services": crate::sampling::get_service_state().await,
+        "permissions": crate::permissions::get_permissions_status().await,
+        "screenshotsPaused": crate::permissions::watcher::screenshots_paused(),
+        "queues": {
+            "events": pending_events,
+            "heartbeats": pending_heartbeats,
+            "screenshots": pending_screenshots
+        },
+        "updateCheck": crate::update_check::get_update_check_status().await,
+        "updateLockout": crate::update_lockout::get_update_lockout_status().await,
+        "rollback": crate::update_rollback::get_rollback_status(),
+    })
+}
+
+/// Collect logs and the health report and upload them under `case_id`
+pub async fn upload_diagnostics(case_id: Option<&str>, hours: i64) -> Result<()> {
+    let hours = hours.clamp(1, MAX_LOG_HOURS);
+    let device_uuid = crate::storage::database::get_or_create_device_uuid()?;
+    let body = serde_json::json!({
+        "caseId": case_id,
+        "deviceUuid": device_uuid,
+        "collectedAt": chrono::Utc::now().to_rfc3339(),
+        "hours": hours,
+        "health": health_report().await,
+        "logs": collect_logs(hours),
+    });
+
+    let client = ApiClient::new().await?;
+    let response = client.post_with_auth("/api/desktop/diagnostics", &body).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow::anyhow!("Diagnostics upload failed: {} - {}", status, body));
+    }
+
+    log::info!("Uploaded diagnostics ({} hours, case {})", hours, case_id.unwrap_or("none"));
+    Ok(())
+}
+
+/// Handle a diagnostics job: `{"caseId": "...", "hours": 24}`
+pub async fn process_diagnostics_job(job: &Value) -> Result<()> {
+    let case_id = job["caseId"].as_str().or_else(|| job["case_id"].as_str());
+    let hours = job["hours"].as_i64().unwrap_or(DEFAULT_LOG_HOURS);
+    upload_diagnostics(case_id, hours).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_keeps_newest_whole_lines() {
+        let logs = "first line\nsecond line\nthird line".to_string();
+        assert_eq!(truncate_to_newest(logs.clone(), 100), logs);
+        assert_eq!(truncate_to_newest(logs, 15), "third line");
+    }
+}
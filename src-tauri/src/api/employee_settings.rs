@@ -8,9 +8,11 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 use chrono::{DateTime, Utc};
 
 use super::client::ApiClient;
+use crate::utils::privacy::UrlSanitizer;
 
 /// Default screenshot interval in minutes if not set
 pub const DEFAULT_SCREENSHOT_INTERVAL_MINUTES: i32 = 30;
@@ -32,6 +34,104 @@ pub struct PolicySettings {
     pub redact_titles: bool,
     /// Whether to store only domain for browser URLs (privacy mode)
     pub browser_domain_only: bool,
+    /// Sustained ingest request rate (requests per minute, 0 = default)
+    #[serde(default)]
+    pub ingest_rate_per_minute: i32,
+    /// Ingest burst size (0 = default)
+    #[serde(default)]
+    pub ingest_burst: i32,
+    /// App segments shorter than this (seconds) are merged into the previous one
+    #[serde(default)]
+    pub min_app_segment_s: i32,
+    /// Gap (seconds) tolerated when merging segments of the same app
+    #[serde(default)]
+    pub app_merge_gap_s: i32,
+    /// Whether to record per-window-title segments (which file/document) within apps
+    #[serde(default)]
+    pub track_window_segments: bool,
+    /// Allowlist-only URL capture: only domains in `url_allowlist` are recorded
+    #[serde(default)]
+    pub url_allowlist_only: bool,
+    /// Org-approved domains for allowlist-only mode
+    #[serde(default)]
+    pub url_allowlist: Vec<String>,
+    /// When automatic (e.g. mandatory) updates may be installed
+    #[serde(default)]
+    pub update_install_mode: crate::update_schedule::UpdateInstallMode,
+    /// Start of work hours, local "HH:MM"
+    #[serde(default)]
+    pub work_hours_start: Option<String>,
+    /// End of work hours, local "HH:MM"
+    #[serde(default)]
+    pub work_hours_end: Option<String>,
+    /// Work days as ISO weekdays (1 = Monday), empty = every day
+    #[serde(default)]
+    pub work_days: Vec<u32>,
+    /// Attach coarse location to clock events (the employee must also consent)
+    #[serde(default)]
+    pub location_enabled: bool,
+    /// Office public egress IP ranges in CIDR notation (see `location`)
+    #[serde(default)]
+    pub office_ip_ranges: Vec<String>,
+    /// Office internal LAN ranges in CIDR notation, matched against the
+    /// device's own address (see `location::office_network`)
+    #[serde(default)]
+    pub office_lan_ranges: Vec<String>,
+    /// Wi-Fi network names that count as the office
+    #[serde(default)]
+    pub office_ssids: Vec<String>,
+    /// Hours a stored session may be used without validating it online (0 = default)
+    #[serde(default)]
+    pub offline_validation_hours: u32,
+    /// Heartbeat interval while the user is active, in seconds (0 = default)
+    #[serde(default)]
+    pub heartbeat_min_interval_s: i32,
+    /// Longest heartbeat interval while idle or paused, in seconds (0 = default)
+    #[serde(default)]
+    pub heartbeat_max_interval_s: i32,
+    /// OCR redaction of sensitive text in screenshots (opt-in, per-pattern toggles)
+    #[serde(default)]
+    pub screenshot_redaction: crate::screenshots::redaction::RedactionPolicy,
+    /// Org default for keeping the machine awake while clocked in (employees may override)
+    #[serde(default)]
+    pub prevent_sleep: crate::sampling::sleep_guard::PreventSleepMode,
+    /// Cap for screenshot uploads and queue drains in kbit/s (0 = unlimited)
+    #[serde(default)]
+    pub upload_bandwidth_kbps: u32,
+    /// Rounding of report totals and exports (raw data is never rounded)
+    #[serde(default)]
+    pub time_rounding: super::reporting::TimeRounding,
+    /// Auto screenshots wait for the employee to accept, blur or reject them
+    #[serde(default)]
+    pub screenshot_review: bool,
+    /// Minutes the employee has to review a screenshot before it is uploaded
+    /// as is (0 = default)
+    #[serde(default)]
+    pub screenshot_review_minutes: u32,
+    /// How much app detail heartbeats carry (full / app_only / presence_only)
+    #[serde(default)]
+    pub heartbeat_detail: crate::sampling::heartbeat::HeartbeatDetail,
+    /// Take no screenshots while the employee is sharing their screen
+    #[serde(default)]
+    pub pause_screenshots_while_sharing: bool,
+    /// Most hours worked per day (0 = no limit)
+    #[serde(default)]
+    pub daily_hours_limit: f64,
+    /// Most hours worked per week, Monday to Sunday (0 = no limit)
+    #[serde(default)]
+    pub weekly_hours_limit: f64,
+    /// Minutes before a limit the employee is warned (0 = default)
+    #[serde(default)]
+    pub overtime_warning_minutes: u32,
+    /// Clock out automatically once a limit is exceeded
+    #[serde(default)]
+    pub overtime_auto_clock_out: bool,
+    /// Fallback server URLs (see `api::endpoints`)
+    #[serde(default)]
+    pub fallback_server_urls: Vec<String>,
+    /// Backend signature over `fallback_server_urls`
+    #[serde(default)]
+    pub fallback_server_urls_signature: Option<String>,
 }
 
 /// Employee screenshot settings
@@ -55,6 +155,38 @@ impl Default for EmployeeSettings {
                 count_idle_as_work: false,
                 redact_titles: false,
                 browser_domain_only: true, // Default to privacy-friendly mode
+                ingest_rate_per_minute: super::rate_limiter::DEFAULT_INGEST_RATE_PER_MINUTE,
+                ingest_burst: super::rate_limiter::DEFAULT_INGEST_BURST,
+                min_app_segment_s: crate::storage::app_usage::DEFAULT_MIN_SEGMENT_SECONDS as i32,
+                app_merge_gap_s: crate::storage::app_usage::DEFAULT_MERGE_GAP_SECONDS as i32,
+                track_window_segments: false, // Opt-in, window titles can be sensitive
+                url_allowlist_only: false,
+                url_allowlist: Vec::new(),
+                update_install_mode: crate::update_schedule::UpdateInstallMode::Immediate,
+                work_hours_start: None,
+                work_hours_end: None,
+                work_days: Vec::new(),
+                location_enabled: false,
+                office_ip_ranges: Vec::new(),
+                office_lan_ranges: Vec::new(),
+                office_ssids: Vec::new(),
+                offline_validation_hours: crate::storage::offline_validation::DEFAULT_OFFLINE_VALIDATION_HOURS,
+                heartbeat_min_interval_s: 0,
+                heartbeat_max_interval_s: 0,
+                screenshot_redaction: crate::screenshots::redaction::RedactionPolicy::default(),
+                prevent_sleep: crate::sampling::sleep_guard::PreventSleepMode::Off,
+                upload_bandwidth_kbps: 0,
+                time_rounding: super::reporting::TimeRounding::default(),
+                screenshot_review: false,
+                screenshot_review_minutes: 0,
+                heartbeat_detail: crate::sampling::heartbeat::HeartbeatDetail::Full,
+                pause_screenshots_while_sharing: false,
+                daily_hours_limit: 0.0,
+                weekly_hours_limit: 0.0,
+                overtime_warning_minutes: crate::sampling::overtime::DEFAULT_WARNING_MINUTES,
+                overtime_auto_clock_out: false,
+                fallback_server_urls: Vec::new(),
+                fallback_server_urls_signature: None,
             }),
             fetched_at: Utc::now(),
         }
@@ -121,10 +253,76 @@ async fn fetch_from_api() -> Result<EmployeeSettings> {
         redact_titles: bool,
         #[serde(default = "default_browser_domain_only")]
         browser_domain_only: bool,
+        #[serde(default)]
+        ingest_rate_per_minute: i32,
+        #[serde(default)]
+        ingest_burst: i32,
+        #[serde(default = "default_min_app_segment")]
+        min_app_segment_s: i32,
+        #[serde(default = "default_app_merge_gap")]
+        app_merge_gap_s: i32,
+        #[serde(default)]
+        track_window_segments: bool,
+        #[serde(default)]
+        url_allowlist_only: bool,
+        #[serde(default)]
+        url_allowlist: Vec<String>,
+        #[serde(default)]
+        update_install_mode: crate::update_schedule::UpdateInstallMode,
+        #[serde(default)]
+        work_hours_start: Option<String>,
+        #[serde(default)]
+        work_hours_end: Option<String>,
+        #[serde(default)]
+        work_days: Vec<u32>,
+        #[serde(default)]
+        location_enabled: bool,
+        #[serde(default)]
+        office_ip_ranges: Vec<String>,
+        #[serde(default)]
+        office_lan_ranges: Vec<String>,
+        #[serde(default)]
+        office_ssids: Vec<String>,
+        #[serde(default)]
+        offline_validation_hours: u32,
+        #[serde(default)]
+        heartbeat_min_interval_s: i32,
+        #[serde(default)]
+        heartbeat_max_interval_s: i32,
+        #[serde(default)]
+        screenshot_redaction: crate::screenshots::redaction::RedactionPolicy,
+        #[serde(default)]
+        prevent_sleep: crate::sampling::sleep_guard::PreventSleepMode,
+        #[serde(default)]
+        upload_bandwidth_kbps: u32,
+        #[serde(default)]
+        time_rounding: super::reporting::TimeRounding,
+        #[serde(default)]
+        screenshot_review: bool,
+        #[serde(default)]
+        screenshot_review_minutes: u32,
+        #[serde(default)]
+        heartbeat_detail: crate::sampling::heartbeat::HeartbeatDetail,
+        #[serde(default)]
+        pause_screenshots_while_sharing: bool,
+        #[serde(default)]
+        daily_hours_limit: f64,
+        #[serde(default)]
+        weekly_hours_limit: f64,
+        #[serde(default)]
+        overtime_warning_minutes: u32,
+        #[serde(default)]
+        overtime_auto_clock_out: bool,
+        #[serde(default)]
+        fallback_server_urls: Vec<String>,
+        #[serde(default)]
+        fallback_server_urls_signature: Option<String>,
     }
     
     fn default_idle_threshold() -> i32 { DEFAULT_IDLE_THRESHOLD_SECONDS }
     fn default_browser_domain_only() -> bool { true }
+    fn default_min_app_segment() -> i32 { crate::storage::app_usage::DEFAULT_MIN_SEGMENT_SECONDS as i32 }
+    fn default_app_merge_gap() -> i32 { crate::storage::app_usage::DEFAULT_MERGE_GAP_SECONDS as i32 }
     
     #[derive(Deserialize)]
     struct ApiResponse {
@@ -143,6 +341,38 @@ async fn fetch_from_api() -> Result<EmployeeSettings> {
         count_idle_as_work: p.count_idle_as_work,
         redact_titles: p.redact_titles,
         browser_domain_only: p.browser_domain_only,
+        ingest_rate_per_minute: p.ingest_rate_per_minute,
+        ingest_burst: p.ingest_burst,
+        min_app_segment_s: p.min_app_segment_s,
+        app_merge_gap_s: p.app_merge_gap_s,
+        track_window_segments: p.track_window_segments,
+        url_allowlist_only: p.url_allowlist_only,
+        url_allowlist: p.url_allowlist,
+        update_install_mode: p.update_install_mode,
+        work_hours_start: p.work_hours_start,
+        work_hours_end: p.work_hours_end,
+        work_days: p.work_days,
+        location_enabled: p.location_enabled,
+        office_ip_ranges: p.office_ip_ranges,
+        office_lan_ranges: p.office_lan_ranges,
+        office_ssids: p.office_ssids,
+        offline_validation_hours: p.offline_validation_hours,
+        heartbeat_min_interval_s: p.heartbeat_min_interval_s,
+        heartbeat_max_interval_s: p.heartbeat_max_interval_s,
+        screenshot_redaction: p.screenshot_redaction,
+        prevent_sleep: p.prevent_sleep,
+        upload_bandwidth_kbps: p.upload_bandwidth_kbps,
+        time_rounding: p.time_rounding,
+        screenshot_review: p.screenshot_review,
+        screenshot_review_minutes: p.screenshot_review_minutes,
+        heartbeat_detail: p.heartbeat_detail,
+        pause_screenshots_while_sharing: p.pause_screenshots_while_sharing,
+        daily_hours_limit: p.daily_hours_limit,
+        weekly_hours_limit: p.weekly_hours_limit,
+        overtime_warning_minutes: p.overtime_warning_minutes,
+        overtime_auto_clock_out: p.overtime_auto_clock_out,
+        fallback_server_urls: p.fallback_server_urls,
+        fallback_server_urls_signature: p.fallback_server_urls_signature,
     });
     
     let settings = EmployeeSettings {
@@ -154,6 +384,23 @@ async fn fetch_from_api() -> Result<EmployeeSettings> {
         fetched_at: Utc::now(),
     };
     
+    if let Some(policy) = &settings.policy {
+        super::rate_limiter::apply_policy(policy);
+        super::bandwidth::apply_policy(policy);
+        super::endpoints::apply_policy(policy).await;
+        crate::storage::app_usage::set_merge_policy(
+            crate::storage::app_usage::AppUsageMergePolicy::from_settings(policy),
+        ).await;
+        // Redacted titles must never be stored per window
+        crate::storage::app_usage::set_window_tracking(
+            policy.track_window_segments && !policy.redact_titles,
+        ).await;
+        if let Err(e) = crate::storage::offline_validation::remember_window(policy.offline_validation_hours) {
+            log::warn!("Failed to store offline validation window: {}", e);
+        }
+        crate::sampling::sleep_guard::refresh_with(policy).await;
+    }
+    
     log::info!(
         "Fetched employee settings: auto_screenshots={}, interval={}min, browser_domain_only={}",
         settings.auto_screenshots,
@@ -205,7 +452,6 @@ pub async fn get_employee_settings() -> Result<EmployeeSettings> {
 }
 
 /// Force refresh of employee settings
-#[allow(dead_code)]
 pub async fn refresh_settings() -> Result<EmployeeSettings> {
     let settings = fetch_from_api().await?;
     
@@ -217,6 +463,32 @@ pub async fn refresh_settings() -> Result<EmployeeSettings> {
     Ok(settings)
 }
 
+/// Settings changed on the backend (pushed over the license/settings stream).
+/// Bursts of pushes are coalesced into one refresh shortly after the first;
+/// fetching re-applies the policy, and services reading the cache pick up
+/// the new values on their next cycle.
+pub fn handle_settings_push() {
+    if SETTINGS_PUSH_PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async {
+        tokio::time::sleep(std::time::Duration::from_secs(SETTINGS_PUSH_DEBOUNCE_SECS)).await;
+        SETTINGS_PUSH_PENDING.store(false, Ordering::SeqCst);
+        match refresh_settings().await {
+            Ok(settings) => {
+                log::info!("Applied pushed employee settings");
+                crate::events::settings_updated(&settings);
+            }
+            Err(e) => log::warn!("Failed to refresh pushed employee settings: {}", e),
+        }
+    });
+}
+
+/// Delay before a pushed settings change is fetched, to coalesce bursts
+const SETTINGS_PUSH_DEBOUNCE_SECS: u64 = 2;
+
+static SETTINGS_PUSH_PENDING: AtomicBool = AtomicBool::new(false);
+
 /// Clear the settings cache (e.g., on logout)
 #[allow(dead_code)]
 pub async fn clear_cache() {
@@ -272,6 +544,25 @@ pub async fn is_browser_domain_only() -> bool {
     }
 }
 
+/// Build the URL sanitizer for the current policy (domain-only and allowlist-only modes)
+pub async fn get_url_sanitizer() -> UrlSanitizer {
+    match get_employee_settings().await {
+        Ok(settings) => {
+            let policy = settings.policy.unwrap_or_default();
+            let sanitizer = UrlSanitizer::new(policy.browser_domain_only);
+            if policy.url_allowlist_only {
+                sanitizer.with_allowlist(policy.url_allowlist)
+            } else {
+                sanitizer
+            }
+        }
+        Err(e) => {
+            log::warn!("Failed to get URL policy settings: {}", e);
+            UrlSanitizer::new(true) // Default to privacy-friendly mode on error
+        }
+    }
+}
+
 /// Get the policy settings, with defaults if not available
 #[allow(dead_code)]
 pub async fn get_policy_settings() -> PolicySettings {
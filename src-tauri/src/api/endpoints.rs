@@ -0,0 +1,256 @@
+//! Server URL failover
+//!
+//! Besides the server URL chosen at login, the org policy can list fallback
+//! URLs (e.g. regional endpoints). Requests carry the device token, so the
+//! list is only accepted from the policy and only when it's signed for this
+//! device: `fallbackServerUrlsSignature` is the hex HMAC-SHA256 of the URLs
+//! joined by newlines, keyed with the ingest signing secret (see
+//! `request_signing`). Only https URLs are used.
+//!
+//! `ApiClient` (which all API and ingest calls go through) tries the endpoint
+//! that last worked first and moves on to the next one only when a request
+//! could not connect, so nothing is sent twice. Endpoints that time out or
+//! answer 502/503/504 are marked unhealthy and skipped by later requests for
+//! a backoff period that grows with consecutive failures.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::api::employee_settings::PolicySettings;
+use crate::error::AgentError;
+
+/// `secure_store` setting holding the last verified fallback list
+const FALLBACK_URLS_KEY: &str = "fallback_server_urls";
+
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Default, Clone)]
+struct Health {
+    consecutive_failures: u32,
+    retry_after: Option<Instant>,
+}
+
+impl Health {
+    fn is_available(&self, now: Instant) -> bool {
+        self.retry_after.map_or(true, |until| now >= until)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Endpoints {
+    fallbacks: Vec<String>,
+    health: HashMap<String, Health>,
+    /// Endpoint that served the last successful request
+    active: Option<String>,
+}
+
+impl Endpoints {
+    /// Endpoints to try, in order: the sticky one, the primary, then
+    /// fallbacks, leaving out those backing off (unless all of them are)
+    fn candidates(&self, primary: &str, now: Instant) -> Vec<String> {
+        let mut all: Vec<String> = Vec::new();
+        for url in self.active.iter().map(String::as_str).chain([primary]).chain(self.fallbacks.iter().map(String::as_str)) {
+            let url = normalize(url);
+            if !url.is_empty() && !all.contains(&url) {
+                all.push(url);
+            }
+        }
+        let available: Vec<String> = all
+            .iter()
+            .filter(|url| self.health.get(*url).map_or(true, |h| h.is_available(now)))
+            .cloned()
+            .collect();
+        if available.is_empty() { all } else { available }
+    }
+
+    fn record_success(&mut self, url: &str) {
+        self.health.remove(url);
+        if self.active.as_deref() != Some(url) {
+            log::info!("Using server endpoint {}", url);
+            self.active = Some(url.to_string());
+        }
+    }
+
+    fn record_failure(&mut self, url: &str, now: Instant) {
+        let health = self.health.entry(url.to_string()).or_default();
+        health.consecutive_failures += 1;
+        let backoff = BASE_BACKOFF
+            .saturating_mul(1 << (health.consecutive_failures - 1).min(10))
+            .min(MAX_BACKOFF);
+        health.retry_after = Some(now + backoff);
+        if self.active.as_deref() == Some(url) {
+            self.active = None;
+        }
+        log::warn!("Server endpoint {} unhealthy ({} consecutive failures), backing off {:?}", url, health.consecutive_failures, backoff);
+    }
+}
+
+static ENDPOINTS: Mutex<Option<Endpoints>> = Mutex::new(None);
+
+fn with_endpoints<T>(f: impl FnOnce(&mut Endpoints) -> T) -> T {
+    let mut guard = match ENDPOINTS.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    f(guard.get_or_insert_with(Endpoints::default))
+}
+
+fn normalize(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
+
+fn is_valid_url(url: &str) -> bool {
+    url.starts_with("https://")
+}
+
+/// Fallback list and its signature, as received in the policy
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SignedUrls {
+    urls: Vec<String>,
+    signature: Option<String>,
+}
+
+impl SignedUrls {
+    /// The https URLs, if the signature holds for this device
+    fn verified(&self, verify: impl Fn(&[u8], &str) -> bool) -> Option<Vec<String>> {
+        let signature = self.signature.as_deref()?;
+        if !verify(self.urls.join("\n").as_bytes(), signature) {
+            return None;
+        }
+        Some(self.urls.iter().map(|url| normalize(url)).filter(|url| is_valid_url(url)).collect())
+    }
+}
+
+fn set_fallbacks(signed: &SignedUrls) -> bool {
+    if signed.urls.is_empty() {
+        with_endpoints(|endpoints| endpoints.fallbacks.clear());
+        return true;
+    }
+    match signed.verified(super::request_signing::verify) {
+        Some(urls) => {
+            log::info!("Fallback server URLs: {:?}", urls);
+            with_endpoints(|endpoints| endpoints.fallbacks = urls);
+            true
+        }
+        None => {
+            log::warn!("Ignoring fallback server URLs without a valid signature");
+            with_endpoints(|endpoints| endpoints.fallbacks.clear());
+            false
+        }
+    }
+}
+
+/// Whether a response means the endpoint itself is down rather than the
+/// request being wrong
+pub fn is_endpoint_failure(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 502 | 503 | 504)
+}
+
+/// Base URLs to try for a request, best first
+pub fn candidates(primary: &str) -> Vec<String> {
+    with_endpoints(|endpoints| endpoints.candidates(primary, Instant::now()))
+}
+
+pub fn record_success(url: &str) {
+    with_endpoints(|endpoints| endpoints.record_success(url));
+}
+
+pub fn record_failure(url: &str) {
+    with_endpoints(|endpoints| endpoints.record_failure(url, Instant::now()));
+}
+
+/// Load the fallback URLs of the last policy (called at startup, after the
+/// signing secret), so failover works before the policy can be fetched again
+pub async fn load_fallback_urls() {
+    match crate::storage::secure_store::get_setting(FALLBACK_URLS_KEY).await {
+        Ok(Some(value)) => match serde_json::from_str::<SignedUrls>(&value) {
+            Ok(signed) => {
+                set_fallbacks(&signed);
+            }
+            Err(e) => log::warn!("Ignoring stored fallback server URLs: {}", e),
+        },
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to read fallback server URLs: {}", e),
+    }
+}
+
+/// Take the fallback URLs from a freshly fetched policy
+pub async fn apply_policy(policy: &PolicySettings) {
+    let signed = SignedUrls {
+        urls: policy.fallback_server_urls.clone(),
+        signature: policy.fallback_server_urls_signature.clone(),
+    };
+    if !set_fallbacks(&signed) {
+        return;
+    }
+    match serde_json::to_string(&signed) {
+        Ok(json) => {
+            if let Err(e) = crate::storage::secure_store::store_setting(FALLBACK_URLS_KEY, &json).await {
+                log::warn!("Failed to save fallback server URLs: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to save fallback server URLs: {}", e),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerEndpoints {
+    pub fallback_urls: Vec<String>,
+    /// Endpoint that served the last successful request
+    pub active_url: Option<String>,
+}
+
+fn snapshot() -> ServerEndpoints {
+    with_endpoints(|endpoints| ServerEndpoints {
+        fallback_urls: endpoints.fallbacks.clone(),
+        active_url: endpoints.active.clone(),
+    })
+}
+
+#[tauri::command]
+pub async fn get_server_endpoints() -> Result<ServerEndpoints, AgentError> {
+    Ok(snapshot())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover_and_sticky_endpoint() {
+        let now = Instant::now();
+        let signed = SignedUrls {
+            urls: vec!["https://eu.example.com/".into(), "https://us.example.com".into(), "http://plain.example.com".into()],
+            signature: Some("signed".into()),
+        };
+        // Unsigned or forged lists are refused; plain http is dropped
+        assert_eq!(SignedUrls { signature: None, ..signed.clone() }.verified(|_, _| true), None);
+        assert_eq!(signed.verified(|_, _| false), None);
+        let mut endpoints = Endpoints {
+            fallbacks: signed.verified(|message, signature| {
+                signature == "signed" && message.starts_with(b"https://eu.example.com/\nhttps://us")
+            }).unwrap(),
+            ..Default::default()
+        };
+        let primary = "https://www.example.com";
+        assert_eq!(endpoints.candidates(primary, now), vec![primary, "https://eu.example.com", "https://us.example.com"]);
+
+        // Primary down: skipped while backing off, the fallback sticks
+        endpoints.record_failure(primary, now);
+        endpoints.record_success("https://eu.example.com");
+        assert_eq!(endpoints.candidates(primary, now), vec!["https://eu.example.com", "https://us.example.com"]);
+
+        // Backoff over: the sticky endpoint still comes first
+        let later = now + MAX_BACKOFF;
+        assert_eq!(endpoints.candidates(primary, later)[0], "https://eu.example.com");
+
+        // Everything down: try them all rather than nothing
+        endpoints.record_failure("https://eu.example.com", now);
+        endpoints.record_failure("https://us.example.com", now);
+        assert_eq!(endpoints.candidates(primary, now).len(), 3);
+    }
+}
@@ -0,0 +1,149 @@
+//! Ingest payload schema versioning
+//!
+//! Every payload sent to `/api/ingest/*` carries a top-level `schema_version`
+//! so payload formats can evolve without breaking older servers. At login the
+//! agent asks the backend which versions it accepts (`/api/ingest/schema`)
+//! and from then on sends the newest version both sides understand. Servers
+//! that predate the endpoint only accept version 1.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::sync::RwLock;
+
+use super::client::ApiClient;
+
+/// Heartbeat payload versions this agent can produce
+const HEARTBEAT_VERSIONS: &[u32] = &[1];
+
+/// Event (and audit/job result) payload versions this agent can produce
+const EVENT_VERSIONS: &[u32] = &[1];
+
+/// Version assumed for servers that don't publish what they accept
+const LEGACY_VERSION: u32 = 1;
+
+/// `secure_store` setting holding the negotiated versions
+const ACCEPTED_VERSIONS_KEY: &str = "ingest_schema_versions";
+
+const INGEST_PREFIX: &str = "/api/ingest/";
+
+/// Versions the backend accepts, per payload kind
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptedVersions {
+    #[serde(default)]
+    pub heartbeat: Vec<u32>,
+    #[serde(default)]
+    pub events: Vec<u32>,
+    /// Body encodings accepted besides JSON ("msgpack", "gzip")
+    #[serde(default)]
+    pub encodings: Vec<String>,
+}
+
+impl Default for AcceptedVersions {
+    fn default() -> Self {
+        Self {
+            heartbeat: vec![LEGACY_VERSION],
+            events: vec![LEGACY_VERSION],
+            encodings: Vec::new(),
+        }
+    }
+}
+
+static ACCEPTED: RwLock<Option<AcceptedVersions>> = RwLock::new(None);
+
+/// Newest version both sides support; the oldest we produce if there is none
+fn negotiate(ours: &[u32], theirs: &[u32]) -> u32 {
+    ours.iter()
+        .copied()
+        .filter(|version| theirs.contains(version))
+        .max()
+        .unwrap_or_else(|| ours.iter().copied().min().unwrap_or(LEGACY_VERSION))
+}
+
+fn accepted() -> AcceptedVersions {
+    match ACCEPTED.read() {
+        Ok(guard) => guard.clone().unwrap_or_default(),
+        Err(e) => e.into_inner().clone().unwrap_or_default(),
+    }
+}
+
+/// Schema version for a payload sent to `url` (an endpoint path or full URL)
+fn version_for(url: &str) -> u32 {
+    let accepted = accepted();
+    if url.contains("/api/ingest/heartbeat") {
+        negotiate(HEARTBEAT_VERSIONS, &accepted.heartbeat)
+    } else {
+        negotiate(EVENT_VERSIONS, &accepted.events)
+    }
+}
+
+/// Add `schema_version` to an ingest payload; other payloads are passed through
+pub fn versioned<'a>(url: &str, payload: &'a Value) -> Cow<'a, Value> {
+    if !url.contains(INGEST_PREFIX) || !payload.is_object() {
+        return Cow::Borrowed(payload);
+    }
+    let mut stamped = payload.clone();
+    stamped["schema_version"] = Value::from(version_for(url));
+    Cow::Owned(stamped)
+}
+
+/// Load the versions negotiated at the last login (called at startup)
+pub async fn load_accepted_versions() {
+    match crate::storage::secure_store::get_setting(ACCEPTED_VERSIONS_KEY).await {
+        Ok(Some(json)) => match serde_json::from_str::<AcceptedVersions>(&json) {
+            Ok(versions) => set_accepted(versions),
+            Err(e) => log::warn!("Ignoring invalid stored ingest schema versions: {}", e),
+        },
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to read ingest schema versions: {}", e),
+    }
+}
+
+fn set_accepted(versions: AcceptedVersions) {
+    super::client::set_accepted_encodings(&versions.encodings);
+    match ACCEPTED.write() {
+        Ok(mut guard) => *guard = Some(versions),
+        Err(e) => *e.into_inner() = Some(versions),
+    }
+}
+
+/// Ask the backend which payload versions it accepts and remember the answer
+pub async fn negotiate_with_backend() -> anyhow::Result<AcceptedVersions> {
+    let client = ApiClient::new().await?;
+    let response = client.get_with_auth("/api/ingest/schema").await?;
+    let versions = match response.status() {
+        status if status.is_success() => response.json::<AcceptedVersions>().await?,
+        // Servers from before versioning
+        reqwest::StatusCode::NOT_FOUND => AcceptedVersions::default(),
+        status => return Err(anyhow::anyhow!("Schema negotiation failed: {}", status)),
+    };
+
+    log::info!(
+        "Ingest schema versions: heartbeat v{} (server accepts {:?}), events v{} (server accepts {:?})",
+        negotiate(HEARTBEAT_VERSIONS, &versions.heartbeat),
+        versions.heartbeat,
+        negotiate(EVENT_VERSIONS, &versions.events),
+        versions.events
+    );
+    crate::storage::secure_store::store_setting(ACCEPTED_VERSIONS_KEY, &serde_json::to_string(&versions)?).await?;
+    set_accepted(versions.clone());
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_and_stamp() {
+        assert_eq!(negotiate(&[1, 2, 3], &[1, 2]), 2);
+        assert_eq!(negotiate(&[1, 2], &[1, 2, 5]), 2);
+        // No overlap: fall back to our oldest format
+        assert_eq!(negotiate(&[2, 3], &[1]), 2);
+
+        let payload = serde_json::json!({ "events": [] });
+        assert_eq!(versioned("https://x.test/api/ingest/events", &payload)["schema_version"], 1);
+        assert!(versioned("/api/devices/update", &payload).get("schema_version").is_none());
+    }
+}
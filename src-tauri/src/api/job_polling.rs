@@ -5,28 +5,29 @@ use serde_json::Value;
 
 use crate::api::client::ApiClient;
 use crate::api::cloudinary_upload;
+use crate::api::diagnostics;
+use crate::api::remote_commands::{self, RemoteCommand};
 use crate::screenshots::screen_capture;
 
-pub async fn start_job_polling(_app_handle: AppHandle) {
+/// Longest a screenshot job waits for a presentation to end
+const MAX_JOB_DEFERRAL_SECS: u64 = 15 * 60;
+
+pub async fn start_job_polling(app_handle: AppHandle) {
     let interval_seconds = crate::sampling::get_job_polling_interval();
 
     let mut interval = tokio::time::interval(Duration::from_secs(interval_seconds));
     let mut last_cursor: Option<String> = None;
     
+    // Runs for the whole app lifetime: remote commands (e.g. resume_tracking,
+    // upload_logs) must reach the device while paused or clocked out too.
+    // Screenshot jobs check `should_services_run` themselves.
     loop {
-        // Check if services should continue running (authenticated AND clocked in)
-        if !crate::sampling::should_services_run().await {
-            // Stop if user is not authenticated or not clocked in
-            if !crate::sampling::is_services_running().await {
-                break; // Service stopped completely
-            }
-            // Otherwise, just wait before checking again
+        if !crate::sampling::is_authenticated().await {
             interval.tick().await;
             continue;
         }
 
-        // Poll for jobs (only when authenticated and clocked in)
-        if let Err(e) = poll_jobs(&mut last_cursor).await {
+        if let Err(e) = poll_jobs(&app_handle, &mut last_cursor).await {
             log::error!("Failed to poll jobs: {}", e);
             // Wait a bit before retrying on error
             sleep(Duration::from_secs(10)).await;
@@ -37,7 +38,7 @@ pub async fn start_job_polling(_app_handle: AppHandle) {
 
 }
 
-async fn poll_jobs(last_cursor: &mut Option<String>) -> Result<()> {
+async fn poll_jobs(app_handle: &AppHandle, last_cursor: &mut Option<String>) -> Result<()> {
     let client = ApiClient::new().await?;
     
     let endpoint = if let Some(cursor) = last_cursor {
@@ -58,7 +59,7 @@ async fn poll_jobs(last_cursor: &mut Option<String>) -> Result<()> {
         }
         for job in jobs {
             log::info!("Processing job: {:?}", job);
-            if let Err(e) = process_job(job).await {
+            if let Err(e) = process_job(app_handle, job).await {
                 log::error!("Failed to process job: {}", e);
             }
         }
@@ -74,16 +75,24 @@ async fn poll_jobs(last_cursor: &mut Option<String>) -> Result<()> {
     Ok(())
 }
 
-async fn process_job(job: &Value) -> Result<()> {
+async fn process_job(app_handle: &AppHandle, job: &Value) -> Result<()> {
     let job_type = job["type"].as_str()
         .ok_or_else(|| anyhow::anyhow!("Job missing type"))?;
 
+    if let Some(command) = RemoteCommand::from_job(job) {
+        return remote_commands::process_command_job(app_handle, command, job).await;
+    }
+
     match job_type {
         "screenshot" => {
-            process_screenshot_job(job).await?;
+            if crate::sampling::presentation::is_presenting() {
+                defer_screenshot_job(job.clone());
+            } else {
+                process_screenshot_job(job).await?;
+            }
         }
         "diagnostics" => {
-            process_diagnostics_job(job).await?;
+            diagnostics::process_diagnostics_job(job).await?;
         }
         _ => {
             log::warn!("Unknown job type: {}", job_type);
@@ -93,6 +102,29 @@ async fn process_job(job: &Value) -> Result<()> {
     Ok(())
 }
 
+/// Run a screenshot job once presenting / Do Not Disturb ends, failing it if
+/// that takes longer than `MAX_JOB_DEFERRAL_SECS`
+fn defer_screenshot_job(job: Value) {
+    log::info!("Screenshot job {} deferred until presenting ends", job["id"]);
+    tokio::spawn(async move {
+        let started = tokio::time::Instant::now();
+        while crate::sampling::presentation::is_presenting() {
+            if started.elapsed() >= Duration::from_secs(MAX_JOB_DEFERRAL_SECS) {
+                if let Some(job_id) = job["id"].as_str() {
+                    if let Err(e) = send_screenshot_failed_event(job_id, "Employee was presenting").await {
+                        log::error!("Failed to send screenshot_failed event for job {}: {}", job_id, e);
+                    }
+                }
+                return;
+            }
+            sleep(Duration::from_secs(5)).await;
+        }
+        if let Err(e) = process_screenshot_job(&job).await {
+            log::error!("Deferred screenshot job failed: {}", e);
+        }
+    });
+}
+
 async fn process_screenshot_job(job: &Value) -> Result<()> {
     let job_id = job["id"].as_str()
         .ok_or_else(|| anyhow::anyhow!("Job missing id"))?;
@@ -120,6 +152,24 @@ async fn process_screenshot_job_inner(job_id: &str) -> Result<()> {
     let employee_id = crate::storage::get_employee_id().await
         .map_err(|_| anyhow::anyhow!("No employee ID available"))?;
     
+    // Only capture while clocked in and tracking isn't paused
+    if !crate::sampling::should_services_run().await {
+        return Err(anyhow::anyhow!("Tracking is not running"));
+    }
+    
+    // Fail with a clear error while Screen Recording is revoked
+    crate::permissions::watcher::ensure_screen_recording()?;
+    
+    // App rules can forbid screenshots while certain apps are focused
+    if let Some(app) = crate::api::app_rules::screenshot_suppressed_by_focused_app().await {
+        return Err(anyhow::anyhow!("Screenshots are disabled by app rule while {} is focused", app));
+    }
+    
+    // Shared screens may show other people's data
+    if crate::sampling::screen_sharing::screenshots_paused().await {
+        return Err(anyhow::anyhow!("Screenshots are paused while the screen is being shared"));
+    }
+    
     // Capture screenshot to file
     let screenshot_result = screen_capture::capture_screen_to_file().await?;
     
@@ -147,8 +197,7 @@ async fn process_screenshot_job_inner(job_id: &str) -> Result<()> {
     
     // Send screenshot_taken event with Cloudinary data
     let client = ApiClient::new().await?;
-    // Use format with Z suffix for Zod datetime validation compatibility
-    let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    let timestamp = crate::utils::time::event_timestamp();
     let event_data = serde_json::json!({
         "events": [{
             "type": "screenshot_taken",
@@ -161,6 +210,7 @@ async fn process_screenshot_job_inner(job_id: &str) -> Result<()> {
                 "height": cloudinary_result.height,
                 "format": cloudinary_result.format,
                 "bytes": cloudinary_result.bytes,
+                "scaleFactor": screenshot_result.scale_factor,
                 "auto": false
             }
         }]
@@ -185,8 +235,7 @@ async fn process_screenshot_job_inner(job_id: &str) -> Result<()> {
 
 async fn send_screenshot_failed_event(job_id: &str, error_message: &str) -> Result<()> {
     let client = ApiClient::new().await?;
-    // Use format with Z suffix for Zod datetime validation compatibility
-    let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    let timestamp = crate::utils::time::event_timestamp();
     let event_data = serde_json::json!({
         "events": [{
             "type": "screenshot_failed",
@@ -215,9 +264,3 @@ async fn send_screenshot_failed_event(job_id: &str, error_message: &str) -> Resu
     log::info!("Screenshot job {} marked as failed", job_id);
     Ok(())
 }
-
-async fn process_diagnostics_job(_job: &Value) -> Result<()> {
-    // TODO: Implement diagnostics collection
-    Ok(())
-}
-
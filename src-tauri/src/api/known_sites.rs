@@ -0,0 +1,117 @@
+//! Server-synced known site mapping
+//!
+//! Browser window titles often contain a site name ("GitHub", "Jira") rather
+//! than a domain. The name → domain mapping used by
+//! `utils::privacy::extract_domain_from_window_title` is loaded from the
+//! backend so new sites improve domain detection fleet-wide. The last synced
+//! mapping is cached in SQLite; the bundled table is used until a sync has
+//! succeeded at least once. Synced at startup, right after login (the
+//! startup sync fails until there are credentials) and then daily.
+
+use anyhow::Result;
+use chrono::Utc;
+use rusqlite::params;
+use serde::Deserialize;
+
+use crate::api::client::ApiClient;
+use crate::storage::database;
+use crate::utils::privacy;
+
+/// How often the mapping is refreshed
+const SYNC_INTERVAL_SECS: u64 = 24 * 3600; // Daily
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct KnownSite {
+    pub name: String,
+    pub domain: String,
+}
+
+/// Create the known_sites cache table (called from database::init)
+pub fn init_table(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS known_sites (
+            name TEXT PRIMARY KEY,
+            domain TEXT NOT NULL,
+            updated_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn load_cached_sites() -> Result<Vec<(String, String)>> {
+    let conn = database::get_connection()?;
+    let mut stmt = conn.prepare("SELECT name, domain FROM known_sites")?;
+    let sites = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(sites)
+}
+
+fn save_cached_sites(sites: &[KnownSite]) -> Result<()> {
+    let mut conn = database::get_connection()?;
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM known_sites", [])?;
+    let now = Utc::now();
+    for site in sites {
+        tx.execute(
+            "INSERT OR REPLACE INTO known_sites (name, domain, updated_at) VALUES (?1, ?2, ?3)",
+            params![site.name.to_lowercase(), site.domain.to_lowercase(), now],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Fetch the mapping from the backend, cache it and make it active
+pub async fn sync_known_sites() -> Result<usize> {
+    let client = ApiClient::new().await?;
+    let response = client.get_with_auth("/api/agent/known-sites").await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Failed to sync known sites: {}", response.status()));
+    }
+
+    let sites: Vec<KnownSite> = response.json().await?;
+    if sites.is_empty() {
+        log::warn!("Server returned an empty known site mapping, keeping the current one");
+        return Ok(0);
+    }
+
+    save_cached_sites(&sites)?;
+    privacy::set_known_sites(sites.iter().map(|s| (s.name.clone(), s.domain.clone())).collect());
+
+    log::info!("Synced {} known sites", sites.len());
+    Ok(sites.len())
+}
+
+pub async fn initialize_known_sites() -> Result<()> {
+    // Use the cached mapping first so detection works offline
+    match load_cached_sites() {
+        Ok(sites) if !sites.is_empty() => {
+            log::info!("Loaded {} known sites from cache", sites.len());
+            privacy::set_known_sites(sites);
+        }
+        Ok(_) => log::info!("No cached known sites, using bundled mapping ({} entries)", privacy::known_sites_count()),
+        Err(e) => log::warn!("Failed to load cached known sites: {}", e),
+    }
+
+    if let Err(e) = sync_known_sites().await {
+        log::warn!("Failed to sync known sites from server: {}", e);
+    }
+
+    // Start periodic refresh
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(SYNC_INTERVAL_SECS));
+        interval.tick().await; // First tick completes immediately
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = sync_known_sites().await {
+                log::error!("Failed to refresh known sites: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
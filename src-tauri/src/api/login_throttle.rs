@@ -0,0 +1,156 @@
+//! Login retry throttling
+//!
+//! The backend protects `/api/auth/employee-login` against brute force: it
+//! answers 429 with a `Retry-After` when an address or account is sending
+//! too many attempts, and 423 while an account is locked. The agent keeps
+//! track per email so it doesn't keep submitting into that: after a couple
+//! of wrong passwords each further attempt has to wait exponentially longer,
+//! and a server-provided wait is honoured as-is. Attempts made too early are
+//! refused locally with the remaining time.
+//!
+//! State is in memory only; restarting the app clears the local delay but
+//! not the server's.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Failures allowed before any delay (typos happen)
+const FREE_FAILURES: u32 = 2;
+
+/// Delay after the first failure past `FREE_FAILURES`, doubled each time
+const BASE_DELAY_SECS: i64 = 5;
+
+const MAX_DELAY_SECS: i64 = 300;
+
+/// Wait after a 429 that didn't say how long
+pub const DEFAULT_RETRY_AFTER_SECS: u64 = 30;
+
+#[derive(Debug, Default, Clone)]
+struct Throttle {
+    failures: u32,
+    blocked_until: Option<DateTime<Utc>>,
+}
+
+impl Throttle {
+    /// Seconds left before another attempt is allowed
+    fn remaining(&self, now: DateTime<Utc>) -> Option<u64> {
+        self.blocked_until
+            .filter(|until| *until > now)
+            // Round up so "0 seconds" is never shown
+            .map(|until| ((until - now).num_milliseconds() as u64).div_ceil(1000))
+    }
+
+    fn record_failure(&mut self, now: DateTime<Utc>, retry_after: Option<u64>) {
+        self.failures = self.failures.saturating_add(1);
+        let backoff = match self.failures.checked_sub(FREE_FAILURES + 1) {
+            Some(exponent) => (BASE_DELAY_SECS << exponent.min(16)).min(MAX_DELAY_SECS),
+            None => 0,
+        };
+        // The server's wait wins, even beyond our own cap
+        let delay = retry_after.map_or(backoff, |secs| (secs.min(i64::MAX as u64) as i64).max(backoff));
+        if delay > 0 {
+            self.blocked_until = Some(now + Duration::seconds(delay));
+        }
+    }
+}
+
+static THROTTLES: Mutex<Option<HashMap<String, Throttle>>> = Mutex::new(None);
+
+fn with_throttle<T>(email: &str, f: impl FnOnce(&mut Throttle) -> T) -> T {
+    let mut throttles = match THROTTLES.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    let throttle = throttles
+        .get_or_insert_with(HashMap::new)
+        .entry(email.trim().to_lowercase())
+        .or_default();
+    f(throttle)
+}
+
+/// Seconds to wait before trying `email` again, if attempts are on hold
+pub fn retry_wait(email: &str) -> Option<u64> {
+    let now = crate::utils::clock::now();
+    with_throttle(email, |throttle| throttle.remaining(now))
+}
+
+/// A rejected attempt; `retry_after` is the server's wait, if it sent one.
+/// Returns the seconds until the next attempt is allowed.
+pub fn record_failure(email: &str, retry_after: Option<u64>) -> Option<u64> {
+    let now = crate::utils::clock::now();
+    with_throttle(email, |throttle| {
+        throttle.record_failure(now, retry_after);
+        throttle.remaining(now)
+    })
+}
+
+/// The password was accepted
+pub fn record_success(email: &str) {
+    with_throttle(email, |throttle| *throttle = Throttle::default());
+}
+
+/// Wait in seconds from a `Retry-After` header (delta seconds or HTTP date)
+/// or the `retryAfter` / `lockedUntil` fields of the error body
+pub fn parse_retry_after(header: Option<&str>, body: &serde_json::Value, now: DateTime<Utc>) -> Option<u64> {
+    let until = |at: DateTime<Utc>| u64::try_from((at - now).num_seconds()).ok();
+
+    if let Some(value) = header.map(str::trim) {
+        if let Ok(secs) = value.parse::<u64>() {
+            return Some(secs);
+        }
+        if let Ok(at) = DateTime::parse_from_rfc2822(value) {
+            return until(at.with_timezone(&Utc));
+        }
+    }
+    if let Some(secs) = body["retryAfter"].as_u64() {
+        return Some(secs);
+    }
+    body["lockedUntil"]
+        .as_str()
+        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+        .and_then(|at| until(at.with_timezone(&Utc)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let mut throttle = Throttle::default();
+
+        throttle.record_failure(now, None);
+        throttle.record_failure(now, None);
+        assert_eq!(throttle.remaining(now), None);
+        throttle.record_failure(now, None);
+        assert_eq!(throttle.remaining(now), Some(5));
+        throttle.record_failure(now, None);
+        assert_eq!(throttle.remaining(now), Some(10));
+        for _ in 0..20 {
+            throttle.record_failure(now, None);
+        }
+        assert_eq!(throttle.remaining(now), Some(MAX_DELAY_SECS as u64));
+        assert_eq!(throttle.remaining(now + Duration::seconds(MAX_DELAY_SECS)), None);
+
+        // A longer server wait is kept
+        throttle.record_failure(now, Some(900));
+        assert_eq!(throttle.remaining(now), Some(900));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let empty = serde_json::Value::Null;
+        assert_eq!(parse_retry_after(Some("120"), &empty, now), Some(120));
+        assert_eq!(parse_retry_after(Some("Mon, 04 Mar 2024 09:01:30 GMT"), &empty, now), Some(90));
+        assert_eq!(parse_retry_after(None, &serde_json::json!({ "retryAfter": 30 }), now), Some(30));
+        assert_eq!(
+            parse_retry_after(None, &serde_json::json!({ "lockedUntil": "2024-03-04T09:15:00Z" }), now),
+            Some(900)
+        );
+        assert_eq!(parse_retry_after(Some("soon"), &empty, now), None);
+    }
+}
@@ -6,4 +6,16 @@ pub mod uploads;
 pub mod reporting;
 pub mod app_rules;
 pub mod employee_settings;
-pub mod cloudinary_upload;
\ No newline at end of file
+pub mod cloudinary_upload;
+pub mod rate_limiter;
+pub mod title_rules;
+pub mod known_sites;
+pub mod seat;
+pub mod remote_commands;
+pub mod diagnostics;
+pub mod ingest_schema;
+pub mod request_signing;
+pub mod endpoints;
+pub mod bandwidth;
+pub mod login_throttle;
+pub mod device_repair;
\ No newline at end of file
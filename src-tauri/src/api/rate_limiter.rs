@@ -0,0 +1,167 @@
+//! Client-side rate limiting for ingest traffic
+//!
+//! A single token bucket is shared by the event, heartbeat and screenshot
+//! senders so burst conditions (draining the offline queue after a long
+//! offline period, rapid focus flapping) can't hammer `/api/ingest`.
+//! Each request takes one or more tokens; when the bucket is empty the
+//! sender waits until enough tokens have been refilled.
+//!
+//! Rates come from the employee policy (`ingest_rate_per_minute`,
+//! `ingest_burst`) and fall back to the defaults below.
+
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::employee_settings::PolicySettings;
+
+/// Default sustained rate (requests per minute) across all ingest senders
+pub const DEFAULT_INGEST_RATE_PER_MINUTE: i32 = 120;
+
+/// Default burst size (bucket capacity)
+pub const DEFAULT_INGEST_BURST: i32 = 20;
+
+/// Kind of ingest request, used to weight the token cost
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IngestKind {
+    Event,
+    Heartbeat,
+    Screenshot,
+}
+
+impl IngestKind {
+    fn cost(&self) -> f64 {
+        match self {
+            IngestKind::Event => 1.0,
+            IngestKind::Heartbeat => 1.0,
+            // Screenshots are several requests (signature, upload, record) and much larger
+            IngestKind::Screenshot => 5.0,
+        }
+    }
+}
+
+/// Classic token bucket: `capacity` tokens, refilled at `refill_per_sec`
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take `cost` tokens if available. Otherwise return how long to wait.
+    pub fn try_acquire_at(&mut self, cost: f64, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+
+        // A request larger than the bucket would never fit; let it through on a full bucket
+        let cost = cost.min(self.capacity);
+
+        if self.tokens >= cost {
+            self.tokens -= cost;
+            Ok(())
+        } else {
+            let missing = cost - self.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_sec))
+        }
+    }
+
+    /// Update the rate without resetting the tokens already available
+    pub fn reconfigure(&mut self, capacity: f64, refill_per_sec: f64) {
+        self.refill(Instant::now());
+        self.capacity = capacity;
+        self.refill_per_sec = refill_per_sec;
+        self.tokens = self.tokens.min(capacity);
+    }
+}
+
+fn bucket_params(rate_per_minute: i32, burst: i32) -> (f64, f64) {
+    let rate = if rate_per_minute > 0 { rate_per_minute } else { DEFAULT_INGEST_RATE_PER_MINUTE };
+    let burst = if burst > 0 { burst } else { DEFAULT_INGEST_BURST };
+    (burst as f64, rate as f64 / 60.0)
+}
+
+lazy_static! {
+    static ref INGEST_LIMITER: Mutex<TokenBucket> = {
+        let (capacity, refill) = bucket_params(DEFAULT_INGEST_RATE_PER_MINUTE, DEFAULT_INGEST_BURST);
+        Mutex::new(TokenBucket::new(capacity, refill))
+    };
+}
+
+/// Wait until the shared ingest bucket allows a request of the given kind
+pub async fn acquire(kind: IngestKind) {
+    loop {
+        let wait = {
+            let mut bucket = INGEST_LIMITER.lock().unwrap();
+            match bucket.try_acquire_at(kind.cost(), Instant::now()) {
+                Ok(()) => return,
+                Err(wait) => wait,
+            }
+        };
+
+        log::debug!("Ingest rate limit reached, delaying {:?} request by {:?}", kind, wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Apply rates from the employee policy (called whenever settings are refreshed)
+pub fn apply_policy(policy: &PolicySettings) {
+    let (capacity, refill) = bucket_params(policy.ingest_rate_per_minute, policy.ingest_burst);
+    let mut bucket = INGEST_LIMITER.lock().unwrap();
+    if bucket.capacity != capacity || bucket.refill_per_sec != refill {
+        log::info!(
+            "Ingest rate limit updated: {:.0}/min, burst {:.0}",
+            refill * 60.0,
+            capacity
+        );
+        bucket.reconfigure(capacity, refill);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_throttles() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(3.0, 1.0);
+
+        for _ in 0..3 {
+            assert!(bucket.try_acquire_at(1.0, start).is_ok());
+        }
+        let wait = bucket.try_acquire_at(1.0, start).unwrap_err();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+
+        // One second later a single token has been refilled
+        assert!(bucket.try_acquire_at(1.0, start + Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_oversized_request_fits_full_bucket() {
+        let mut bucket = TokenBucket::new(2.0, 1.0);
+        assert!(bucket.try_acquire_at(5.0, Instant::now()).is_ok());
+    }
+
+    #[test]
+    fn test_policy_zero_means_default() {
+        let (capacity, refill) = bucket_params(0, 0);
+        assert_eq!(capacity, DEFAULT_INGEST_BURST as f64);
+        assert_eq!(refill, DEFAULT_INGEST_RATE_PER_MINUTE as f64 / 60.0);
+    }
+}
@@ -0,0 +1,166 @@
+//! Remote commands from the backend
+//!
+//! Besides screenshot jobs, the jobs endpoint can deliver commands for this
+//! device, either as `{"type": "command", "command": "force_sync"}` or with the
+//! command name as the job type. Every command is acknowledged with
+//! `command_ack` events: `received` before it runs, then `completed` or
+//! `failed` (with the error).
+
+use anyhow::Result;
+use serde_json::Value;
+use tauri::AppHandle;
+
+use super::client::ApiClient;
+use crate::error::AgentError;
+use crate::storage::audit_log::{self, AuditOutcome};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemoteCommand {
+    /// Push the offline queue to the backend now
+    ForceSync,
+    PauseTracking,
+    ResumeTracking,
+    /// Upload recent logs for support
+    UploadLogs,
+    /// Re-fetch employee policy settings
+    RefreshPolicy,
+    /// Run an update check
+    CheckUpdate,
+}
+
+impl RemoteCommand {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "force_sync" => Some(RemoteCommand::ForceSync),
+            "pause_tracking" => Some(RemoteCommand::PauseTracking),
+            "resume_tracking" => Some(RemoteCommand::ResumeTracking),
+            "upload_logs" => Some(RemoteCommand::UploadLogs),
+            "refresh_policy" => Some(RemoteCommand::RefreshPolicy),
+            "check_update" => Some(RemoteCommand::CheckUpdate),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RemoteCommand::ForceSync => "force_sync",
+            RemoteCommand::PauseTracking => "pause_tracking",
+            RemoteCommand::ResumeTracking => "resume_tracking",
+            RemoteCommand::UploadLogs => "upload_logs",
+            RemoteCommand::RefreshPolicy => "refresh_policy",
+            RemoteCommand::CheckUpdate => "check_update",
+        }
+    }
+
+    /// The command carried by a job, if it is one
+    pub fn from_job(job: &Value) -> Option<Self> {
+        match job["type"].as_str()? {
+            "command" => Self::parse(job["command"].as_str()?),
+            other => Self::parse(other),
+        }
+    }
+}
+
+async fn send_ack(job_id: &str, command: RemoteCommand, status: &str, detail: Value) -> Result<()> {
+    let client = ApiClient::new().await?;
+    let event_data = serde_json::json!({
+        "events": [{
+            "type": "command_ack",
+            "timestamp": crate::utils::time::event_timestamp(),
+            "data": {
+                "jobId": job_id,
+                "command": command.as_str(),
+                "status": status,
+                "detail": detail
+            }
+        }]
+    });
+
+    let response = client.post_with_auth("/api/ingest/events", &event_data).await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Failed to send command_ack: {}", response.status()));
+    }
+    Ok(())
+}
+
+async fn ack(job_id: &str, command: RemoteCommand, status: &str, detail: Value) {
+    if let Err(e) = send_ack(job_id, command, status, detail).await {
+        log::warn!("Failed to acknowledge {} ({}) as {}: {}", command.as_str(), job_id, status, e);
+    }
+}
+
+async fn execute(app: &AppHandle, command: RemoteCommand, job: &Value) -> Result<Value> {
+    match command {
+        RemoteCommand::ForceSync => {
+            let report = crate::commands::trigger_sync(None, None).await?;
+            Ok(Value::String(report.summary()))
+        }
+        RemoteCommand::PauseTracking => {
+            crate::sampling::pause_services(crate::sampling::service_state::PauseReason::RemoteCommand).await;
+            Ok(Value::Null)
+        }
+        RemoteCommand::ResumeTracking => {
+            crate::sampling::resume_services().await;
+            Ok(Value::Null)
+        }
+        RemoteCommand::UploadLogs => {
+            super::diagnostics::process_diagnostics_job(job).await?;
+            Ok(Value::Null)
+        }
+        RemoteCommand::RefreshPolicy => {
+            let settings = super::employee_settings::refresh_settings().await?;
+            Ok(serde_json::json!({ "fetchedAt": settings.fetched_at.to_rfc3339() }))
+        }
+        RemoteCommand::CheckUpdate => {
+            let info = crate::update_manager::check_for_updates(app.clone())
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?;
+            Ok(serde_json::json!({
+                "available": info.available,
+                "version": info.version,
+                "mandatory": info.mandatory,
+            }))
+        }
+    }
+}
+
+/// Run a remote command job and acknowledge it
+pub async fn process_command_job(app: &AppHandle, command: RemoteCommand, job: &Value) -> Result<()> {
+    let job_id = job["id"].as_str()
+        .ok_or_else(|| anyhow::anyhow!("Job missing id"))?;
+
+    log::info!("Processing remote command {} (job {})", command.as_str(), job_id);
+    ack(job_id, command, "received", Value::Null).await;
+
+    let audit_name = format!("remote_command:{}", command.as_str());
+    match execute(app, command, job).await {
+        Ok(detail) => {
+            audit_log::record_outcome(&audit_name, AuditOutcome::Success, None);
+            ack(job_id, command, "completed", detail).await;
+            Ok(())
+        }
+        Err(e) => {
+            let error = AgentError::from(e.to_string());
+            audit_log::record_outcome(&audit_name, AuditOutcome::Failure, Some(&error));
+            ack(job_id, command, "failed", serde_json::json!({ "error": e.to_string() })).await;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_from_job() {
+        let job = serde_json::json!({ "id": "j1", "type": "command", "command": "refresh_policy" });
+        assert_eq!(RemoteCommand::from_job(&job), Some(RemoteCommand::RefreshPolicy));
+
+        let job = serde_json::json!({ "id": "j2", "type": "force_sync" });
+        assert_eq!(RemoteCommand::from_job(&job), Some(RemoteCommand::ForceSync));
+
+        let job = serde_json::json!({ "id": "j3", "type": "screenshot" });
+        assert_eq!(RemoteCommand::from_job(&job), None);
+    }
+}
@@ -1,10 +1,56 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc, Duration};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-use crate::storage::app_usage;
+use crate::storage::{app_usage, database, report_cache, usage_rollup};
+use crate::utils::clock::{self, Clock};
 use crate::utils::productivity::ProductivityCategory;
 
+/// How report totals are rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Report exact seconds
+    #[default]
+    None,
+    Nearest,
+    Up,
+    Down,
+}
+
+/// Org policy for rounding reported time (e.g. nearest 15 minutes). Applied
+/// to report totals and exports only; stored usage keeps exact seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeRounding {
+    #[serde(default)]
+    pub mode: RoundingMode,
+    /// Rounding step in minutes (5, 10, 15, ...); 0 disables rounding
+    #[serde(default)]
+    pub increment_minutes: u32,
+}
+
+impl TimeRounding {
+    fn is_active(&self) -> bool {
+        self.mode != RoundingMode::None && self.increment_minutes > 0
+    }
+
+    /// Round a duration in seconds to the configured increment
+    pub fn round_seconds(&self, seconds: i64) -> i64 {
+        if !self.is_active() || seconds <= 0 {
+            return seconds;
+        }
+        let step = i64::from(self.increment_minutes) * 60;
+        let steps = match self.mode {
+            RoundingMode::Nearest => (seconds + step / 2) / step,
+            RoundingMode::Up => (seconds + step - 1) / step,
+            RoundingMode::Down | RoundingMode::None => seconds / step,
+        };
+        steps * step
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct AppUsageReport {
@@ -45,6 +91,32 @@ pub struct DailyReport {
     pub productivity_score: f64,
     pub top_apps: Vec<TopApp>,
     pub category_breakdown: CategoryBreakdown,
+    /// Rounding applied to the times above, None for exact seconds
+    #[serde(default)]
+    pub rounding: Option<TimeRounding>,
+}
+
+impl DailyReport {
+    /// Copy of the report with every duration rounded per `rounding`
+    pub fn rounded(mut self, rounding: &TimeRounding) -> Self {
+        if !rounding.is_active() {
+            return self;
+        }
+        let round = |seconds: i64| rounding.round_seconds(seconds);
+        self.productive_time = round(self.productive_time);
+        self.neutral_time = round(self.neutral_time);
+        self.unproductive_time = round(self.unproductive_time);
+        self.idle_time = round(self.idle_time);
+        self.total_work_time = round(self.total_work_time);
+        for app in &mut self.top_apps {
+            app.total_time = round(app.total_time);
+        }
+        self.category_breakdown.productive_time = round(self.category_breakdown.productive_time);
+        self.category_breakdown.neutral_time = round(self.category_breakdown.neutral_time);
+        self.category_breakdown.unproductive_time = round(self.category_breakdown.unproductive_time);
+        self.rounding = Some(*rounding);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +143,8 @@ pub struct ReportGenerator {
     employee_id: String,
     #[allow(dead_code)]
     device_id: String,
+    /// Decides what "today" is
+    clock: Arc<dyn Clock>,
 }
 
 impl ReportGenerator {
@@ -78,18 +152,23 @@ impl ReportGenerator {
         Self {
             employee_id,
             device_id,
+            clock: clock::current(),
         }
     }
 
+    #[allow(dead_code)]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub async fn generate_daily_report(&self, date: DateTime<Utc>) -> Result<DailyReport> {
         let start_of_day = date.date_naive().and_hms_opt(0, 0, 0).unwrap();
-        let end_of_day = date.date_naive().and_hms_opt(23, 59, 59).unwrap();
-        
-        let _start_time: DateTime<Utc> = DateTime::from_naive_utc_and_offset(start_of_day, Utc);
-        let _end_time: DateTime<Utc> = DateTime::from_naive_utc_and_offset(end_of_day, Utc);
+        let start_time: DateTime<Utc> = DateTime::from_naive_utc_and_offset(start_of_day, Utc);
+        let end_time = start_time + Duration::days(1);
         
-        // Get app usage summary for the day
-        let app_summary = app_usage::get_app_usage_summary().await;
+        // Get app usage summary for the day (from the daily rollups)
+        let app_summary = app_usage::get_app_usage_summary_between(start_time, end_time).await?;
         
         // Calculate totals
         let mut total_productive_time = 0i64;
@@ -175,6 +254,7 @@ impl ReportGenerator {
             productivity_score: productivity_score.max(0.0).min(100.0),
             top_apps,
             category_breakdown,
+            rounding: None,
         })
     }
 
@@ -185,7 +265,7 @@ impl ReportGenerator {
         end_time: DateTime<Utc>,
     ) -> Result<AppUsageReport> {
         // Get app usage summary
-        let app_summary = app_usage::get_app_usage_summary().await;
+        let app_summary = app_usage::get_app_usage_summary_between(start_time, end_time).await?;
         
         // Calculate totals
         let mut total_productive_time = 0i64;
@@ -276,28 +356,96 @@ impl ReportGenerator {
     }
 }
 
+impl ReportGenerator {
+    /// Daily report served from the report cache while the day's usage data
+    /// is unchanged. Today is always computed fresh.
+    pub async fn cached_daily_report(&self, date: DateTime<Utc>) -> Result<DailyReport> {
+        let day = date.date_naive();
+        if day >= self.clock.now().date_naive() {
+            return self.generate_daily_report(date).await;
+        }
+
+        let conn = database::get_connection()?;
+        let fingerprint = usage_rollup::day_fingerprint(&conn, day)?;
+        if let Some(report) = report_cache::get::<DailyReport>(&conn, day, &fingerprint)? {
+            return Ok(report);
+        }
+
+        let report = self.generate_daily_report(date).await?;
+        if let Err(e) = report_cache::put(&conn, day, &fingerprint, &report) {
+            log::warn!("Failed to cache daily report for {}: {}", day, e);
+        }
+        Ok(report)
+    }
+}
+
+/// Rounding from the org policy
+async fn policy_rounding() -> TimeRounding {
+    super::employee_settings::get_policy_settings().await.time_rounding
+}
+
 // Helper functions for generating reports
 pub async fn generate_today_report(employee_id: String, device_id: String) -> Result<DailyReport> {
     let generator = ReportGenerator::new(employee_id, device_id);
-    generator.generate_daily_report(Utc::now()).await
+    let rounding = policy_rounding().await;
+    Ok(generator.generate_daily_report(generator.clock.now()).await?.rounded(&rounding))
 }
 
 pub async fn generate_weekly_report(employee_id: String, device_id: String) -> Result<Vec<DailyReport>> {
     let mut reports = Vec::new();
     let generator = ReportGenerator::new(employee_id, device_id);
+    let rounding = policy_rounding().await;
     
-    // Generate reports for the last 7 days
+    // Generate reports for the last 7 days (cached exact, rounded on the way out)
     for i in 0..7 {
-        let date = Utc::now() - Duration::days(i);
-        let report = generator.generate_daily_report(date).await?;
-        reports.push(report);
+        let date = generator.clock.now() - Duration::days(i);
+        let report = generator.cached_daily_report(date).await?;
+        reports.push(report.rounded(&rounding));
     }
     
     Ok(reports)
 }
 
+/// Daily reports (rounded per policy) for each day in `[start, end]`, oldest first
+pub async fn generate_reports_between(start: chrono::NaiveDate, end: chrono::NaiveDate) -> Result<Vec<DailyReport>> {
+    let generator = ReportGenerator::new(String::new(), String::new());
+    let rounding = policy_rounding().await;
+    let mut reports = Vec::new();
+    for day in start.iter_days().take_while(|day| *day <= end) {
+        let date = DateTime::from_naive_utc_and_offset(day.and_hms_opt(0, 0, 0).unwrap_or_default(), Utc);
+        reports.push(generator.cached_daily_report(date).await?.rounded(&rounding));
+    }
+    Ok(reports)
+}
+
+/// CSV export of daily reports, durations in seconds
+pub fn daily_reports_csv(reports: &[DailyReport]) -> String {
+    let mut csv = String::from(
+        "date,total_work_seconds,productive_seconds,neutral_seconds,unproductive_seconds,idle_seconds,productivity_score,rounding\n",
+    );
+    for report in reports {
+        let rounding = match report.rounding {
+            Some(rounding) => format!("{:?} {}m", rounding.mode, rounding.increment_minutes).to_lowercase(),
+            None => "none".to_string(),
+        };
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{:.1},{}\n",
+            report.date,
+            report.total_work_time,
+            report.productive_time,
+            report.neutral_time,
+            report.unproductive_time,
+            report.idle_time,
+            report.productivity_score,
+            rounding
+        ));
+    }
+    csv
+}
+
 pub async fn generate_monthly_summary(employee_id: String, device_id: String) -> Result<MonthlySummary> {
     let generator = ReportGenerator::new(employee_id, device_id);
+    let rounding = policy_rounding().await;
     let mut total_productive = 0i64;
     let mut total_neutral = 0i64;
     let mut total_unproductive = 0i64;
@@ -306,8 +454,8 @@ pub async fn generate_monthly_summary(employee_id: String, device_id: String) ->
     
     // Generate reports for the last 30 days
     for i in 0..30 {
-        let date = Utc::now() - Duration::days(i);
-        let report = generator.generate_daily_report(date).await?;
+        let date = generator.clock.now() - Duration::days(i);
+        let report = generator.cached_daily_report(date).await?.rounded(&rounding);
         
         total_productive += report.productive_time;
         total_neutral += report.neutral_time;
@@ -324,7 +472,7 @@ pub async fn generate_monthly_summary(employee_id: String, device_id: String) ->
     };
     
     Ok(MonthlySummary {
-        month: Utc::now().format("%Y-%m").to_string(),
+        month: generator.clock.now().format("%Y-%m").to_string(),
         total_work_time,
         total_productive_time: total_productive,
         total_neutral_time: total_neutral,
@@ -346,3 +494,19 @@ pub struct MonthlySummary {
     pub average_productivity_score: f64,
     pub daily_scores: Vec<f64>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding_modes() {
+        let nearest = TimeRounding { mode: RoundingMode::Nearest, increment_minutes: 15 };
+        assert_eq!(nearest.round_seconds(7 * 60 + 29), 0);
+        assert_eq!(nearest.round_seconds(7 * 60 + 30), 15 * 60);
+        assert_eq!(TimeRounding { mode: RoundingMode::Up, ..nearest }.round_seconds(61), 15 * 60);
+        assert_eq!(TimeRounding { mode: RoundingMode::Down, ..nearest }.round_seconds(29 * 60), 15 * 60);
+        assert_eq!(TimeRounding { mode: RoundingMode::Up, increment_minutes: 0 }.round_seconds(61), 61);
+        assert_eq!(TimeRounding::default().round_seconds(61), 61);
+    }
+}
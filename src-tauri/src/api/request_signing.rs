@@ -0,0 +1,142 @@
+//! HMAC signing of ingest requests
+//!
+//! A stolen bearer token alone is enough to post forged heartbeats and
+//! events. Backends that support signing hand out a per-device secret at
+//! registration (`device.signingSecret`); every ingest body is then signed
+//! with HMAC-SHA256 over `timestamp.nonce.body` and the backend rejects
+//! requests with a missing, stale or replayed signature. Without a secret
+//! requests are sent unsigned, as before.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::RwLock;
+
+/// `secure_store` setting holding the device's signing secret
+const SIGNING_SECRET_KEY: &str = "ingest_signing_secret";
+
+pub const TIMESTAMP_HEADER: &str = "X-TrackEx-Timestamp";
+pub const NONCE_HEADER: &str = "X-TrackEx-Nonce";
+pub const SIGNATURE_HEADER: &str = "X-TrackEx-Signature";
+
+static SECRET: RwLock<Option<String>> = RwLock::new(None);
+
+/// Headers to attach to a signed request
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub timestamp: String,
+    pub nonce: String,
+    pub signature: String,
+}
+
+fn set_cached(secret: Option<String>) {
+    match SECRET.write() {
+        Ok(mut guard) => *guard = secret,
+        Err(e) => *e.into_inner() = secret,
+    }
+}
+
+fn cached() -> Option<String> {
+    match SECRET.read() {
+        Ok(guard) => guard.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+fn compute(secret: &str, timestamp: &str, nonce: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(nonce.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn verify_with(secret: &str, message: &[u8], signature_hex: &str) -> bool {
+    let Some(signature) = decode_hex(signature_hex.trim()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Check a value the backend signed for this device (hex HMAC-SHA256 of
+/// `message` with the signing secret). Always false without a secret.
+pub fn verify(message: &[u8], signature_hex: &str) -> bool {
+    cached().is_some_and(|secret| verify_with(&secret, message, signature_hex))
+}
+
+/// Sign an encoded request body, if this device has a signing secret
+pub fn sign(body: &[u8]) -> Option<Signature> {
+    let secret = cached()?;
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let signature = compute(&secret, &timestamp, &nonce, body);
+    Some(Signature { timestamp, nonce, signature })
+}
+
+/// Remember the secret issued at registration; `None` (older backends)
+/// turns signing off
+pub async fn store_secret(secret: Option<&str>) {
+    let secret = secret.filter(|s| !s.is_empty());
+    if let Err(e) = crate::storage::secure_store::store_setting(SIGNING_SECRET_KEY, secret.unwrap_or("")).await {
+        log::warn!("Failed to store ingest signing secret: {}", e);
+    }
+    if secret.is_some() {
+        log::info!("Ingest requests will be signed");
+    }
+    set_cached(secret.map(str::to_string));
+}
+
+/// Load the stored secret (called at startup)
+pub async fn load_secret() {
+    match crate::storage::secure_store::get_setting(SIGNING_SECRET_KEY).await {
+        Ok(secret) => set_cached(secret.filter(|s| !s.is_empty())),
+        Err(e) => log::warn!("Failed to read ingest signing secret: {}", e),
+    }
+}
+
+/// Forget the secret (logout)
+pub async fn clear() {
+    store_secret(None).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_covers_timestamp_nonce_and_body() {
+        let body = br#"{"events":[]}"#;
+        assert_eq!(
+            compute("secret", "1700000000", "abc", body),
+            "44816bfcb19f3babfe06128cce4050e332b83982ed843d26582f554140a67188"
+        );
+        assert_ne!(compute("secret", "1700000001", "abc", body), compute("secret", "1700000000", "abc", body));
+        assert_ne!(compute("secret", "1700000000", "abd", body), compute("secret", "1700000000", "abc", body));
+    }
+
+    #[test]
+    fn test_verify_backend_signature() {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(b"https://eu.example.com");
+        let signature: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        assert!(verify_with("secret", b"https://eu.example.com", &signature));
+        assert!(!verify_with("secret", b"https://evil.example.com", &signature));
+        assert!(!verify_with("other", b"https://eu.example.com", &signature));
+        assert!(!verify_with("secret", b"https://eu.example.com", "not hex"));
+    }
+}
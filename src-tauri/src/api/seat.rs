@@ -0,0 +1,38 @@
+//! License seat release
+//!
+//! Each activated device holds a license seat. Logging out releases the seat
+//! explicitly so the organization can reuse it right away instead of waiting
+//! for the backend to time the device out.
+
+use anyhow::Result;
+use std::time::Duration;
+
+use super::client::ApiClient;
+
+/// Logout must not hang on a slow backend
+const RELEASE_TIMEOUT_SECS: u64 = 5;
+
+/// Release this device's license seat. Best-effort: the backend also frees
+/// seats of devices that stop reporting.
+pub async fn release_seat(reason: &str) -> Result<()> {
+    let client = ApiClient::new().await?;
+    let device_uuid = crate::storage::database::get_or_create_device_uuid()?;
+    let body = serde_json::json!({
+        "deviceUuid": device_uuid,
+        "reason": reason,
+    });
+
+    let response = tokio::time::timeout(
+        Duration::from_secs(RELEASE_TIMEOUT_SECS),
+        client.post_with_auth("/api/desktop/seat/release", &body),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("Seat release timed out"))??;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Seat release failed: {}", response.status()));
+    }
+
+    log::info!("License seat released ({})", reason);
+    Ok(())
+}
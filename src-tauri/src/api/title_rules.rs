@@ -0,0 +1,134 @@
+//! Window title scrubbing rules
+//!
+//! Organizations can define regex rules (strip email subjects, patient IDs,
+//! ticket numbers, ...) that are applied in sampling before window titles go
+//! into app usage sessions, heartbeats or ingest events. Rules are synced from
+//! the backend right after login and then periodically. The last synced set
+//! is cached in SQLite and loaded at startup, so titles are scrubbed from the
+//! first sample after a restart and while offline.
+
+use anyhow::Result;
+use chrono::Utc;
+use rusqlite::params;
+use std::sync::RwLock;
+
+use crate::api::client::ApiClient;
+use crate::storage::database;
+use crate::utils::privacy::{TitleScrubRule, TitleScrubber};
+
+/// How often the rules are re-synced
+const SYNC_INTERVAL_SECS: u64 = 3600; // 1 hour
+
+lazy_static::lazy_static! {
+    static ref TITLE_SCRUBBER: RwLock<TitleScrubber> = RwLock::new(TitleScrubber::default());
+}
+
+/// Create the title_scrub_rules cache table (called from database::init)
+pub fn init_table(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS title_scrub_rules (
+            id TEXT PRIMARY KEY,
+            pattern TEXT NOT NULL,
+            replacement TEXT NOT NULL,
+            is_active BOOLEAN NOT NULL,
+            updated_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn load_cached_rules() -> Result<Vec<TitleScrubRule>> {
+    let conn = database::get_connection()?;
+    let mut stmt = conn.prepare("SELECT id, pattern, replacement, is_active FROM title_scrub_rules ORDER BY rowid")?;
+    let rules = stmt
+        .query_map([], |row| {
+            Ok(TitleScrubRule {
+                id: row.get(0)?,
+                pattern: row.get(1)?,
+                replacement: row.get(2)?,
+                is_active: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rules)
+}
+
+fn save_cached_rules(rules: &[TitleScrubRule]) -> Result<()> {
+    let mut conn = database::get_connection()?;
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM title_scrub_rules", [])?;
+    let now = Utc::now();
+    for rule in rules {
+        tx.execute(
+            "INSERT OR REPLACE INTO title_scrub_rules (id, pattern, replacement, is_active, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![rule.id, rule.pattern, rule.replacement, rule.is_active, now],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Fetch the title scrubbing rules from the backend and replace the active set
+pub async fn sync_title_rules() -> Result<()> {
+    let client = ApiClient::new().await?;
+    let response = client.get_with_auth("/api/agent/title-scrub-rules").await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Failed to sync title scrub rules: {}", response.status()));
+    }
+
+    let rules: Vec<TitleScrubRule> = response.json().await?;
+    if let Err(e) = save_cached_rules(&rules) {
+        log::warn!("Failed to cache title scrub rules: {}", e);
+    }
+    let scrubber = TitleScrubber::new(&rules);
+    log::info!("Synced {} title scrub rules ({} active)", rules.len(), scrubber.rule_count());
+
+    *TITLE_SCRUBBER.write().unwrap() = scrubber;
+    Ok(())
+}
+
+/// Scrub a window title with the active rules
+pub fn scrub_title(title: Option<String>) -> Option<String> {
+    let title = title?;
+    let scrubber = TITLE_SCRUBBER.read().unwrap();
+    if scrubber.rule_count() == 0 {
+        return Some(title);
+    }
+    Some(scrubber.scrub(&title))
+}
+
+pub async fn initialize_title_rules() -> Result<()> {
+    // Use the cached rules first so titles are scrubbed before the first sync
+    match load_cached_rules() {
+        Ok(rules) if !rules.is_empty() => {
+            let scrubber = TitleScrubber::new(&rules);
+            log::info!("Loaded {} title scrub rules from cache", scrubber.rule_count());
+            *TITLE_SCRUBBER.write().unwrap() = scrubber;
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to load cached title scrub rules: {}", e),
+    }
+
+    // Try to sync rules from server, but don't fail if it doesn't work
+    if let Err(e) = sync_title_rules().await {
+        log::warn!("Failed to sync title scrub rules from server: {}", e);
+    }
+
+    // Start periodic sync
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(SYNC_INTERVAL_SECS));
+        interval.tick().await; // First tick completes immediately
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = sync_title_rules().await {
+                log::error!("Failed to auto-sync title scrub rules: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,266 @@
+//! Browser-based login hand-off
+//!
+//! Instead of typing a password into the agent, the employee signs in on the
+//! server's web login page (SSO, passkeys and 2FA all handled there). The
+//! flow follows the loopback redirect pattern of RFC 8252:
+//!
+//! ```text
+//! agent: listen on 127.0.0.1:<random port>, open
+//!        <server>/desktop/login?redirect_uri=http://127.0.0.1:<port>/callback&state=<nonce>
+//!        &code_challenge=<S256 challenge>&code_challenge_method=S256
+//! browser: user signs in, server redirects to /callback?code=<one-time code>&state=<nonce>
+//! agent: POST /api/auth/desktop-exchange {code, redirectUri, codeVerifier} -> {employee}
+//!        then registers the device exactly like password login
+//! ```
+//!
+//! The one-time code is useless without the redirect URI it was issued for
+//! and the PKCE verifier (RFC 7636) that only this agent knows, so another
+//! local process that grabs the callback can't redeem it. The `state` nonce
+//! ties the callback to the login this agent started.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tauri_plugin_shell::ShellExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+
+use crate::commands::AuthStatus;
+use crate::error::AgentError;
+use crate::storage::audit_log;
+use crate::storage::state_service::StateHandle;
+
+/// How long the agent waits for the user to finish signing in
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How long a connection to the loopback listener may take to send its
+/// request before it's dropped (so one idle connection can't stall the login)
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CALLBACK_PATH: &str = "/callback";
+
+const SUCCESS_PAGE: &str = "<html><body style=\"font-family: sans-serif; text-align: center; padding-top: 60px\">\
+<h2>You're signed in to TrackEx</h2><p>You can close this tab and return to the agent.</p></body></html>";
+
+const FAILURE_PAGE: &str = "<html><body style=\"font-family: sans-serif; text-align: center; padding-top: 60px\">\
+<h2>Sign-in failed</h2><p>Return to the TrackEx agent and try again.</p></body></html>";
+
+lazy_static::lazy_static! {
+    /// Wakes a pending browser login when the user cancels it
+    static ref CANCEL: Notify = Notify::new();
+}
+
+/// Outcome of a request to the loopback listener
+#[derive(Debug, PartialEq)]
+enum Callback {
+    Code(String),
+    /// The server redirected with `error=...` (user denied, account disabled, ...)
+    Denied(String),
+    /// Not our callback (favicon, wrong state); keep waiting
+    Ignored,
+}
+
+fn random_state() -> String {
+    let bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// PKCE code verifier: 32 random bytes, base64url (43 characters)
+fn code_verifier() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// S256 code challenge for `verifier`
+fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Parse the request line (`GET /callback?code=...&state=... HTTP/1.1`)
+fn parse_callback(request_line: &str, expected_state: &str) -> Callback {
+    let Some(target) = request_line.split_whitespace().nth(1) else {
+        return Callback::Ignored;
+    };
+    let Ok(url) = reqwest::Url::parse(&format!("http://127.0.0.1{}", target)) else {
+        return Callback::Ignored;
+    };
+    if url.path() != CALLBACK_PATH {
+        return Callback::Ignored;
+    }
+
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+    if param("state").as_deref() != Some(expected_state) {
+        log::warn!("Ignoring browser login callback with mismatched state");
+        return Callback::Ignored;
+    }
+    if let Some(error) = param("error") {
+        return Callback::Denied(error);
+    }
+    match param("code") {
+        Some(code) if !code.is_empty() => Callback::Code(code),
+        _ => Callback::Denied("missing_code".to_string()),
+    }
+}
+
+#[allow(deprecated)] // shell's `open` is the opener this app already ships with
+fn open_in_browser(app: &AppHandle, url: &str) -> Result<(), tauri_plugin_shell::Error> {
+    app.shell().open(url, None)
+}
+
+/// Accept connections until the real callback arrives, answering each one
+async fn wait_for_code(listener: &TcpListener, expected_state: &str) -> Result<String, AgentError> {
+    loop {
+        let (mut stream, _) = listener.accept().await.map_err(|e| AgentError::Internal(e.to_string()))?;
+
+        let mut buffer = vec![0u8; 8192];
+        let read = match tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buffer)).await {
+            Ok(Ok(read)) => read,
+            Ok(Err(e)) => {
+                log::debug!("Browser login connection failed: {}", e);
+                continue;
+            }
+            Err(_) => {
+                log::debug!("Browser login connection sent nothing, dropping it");
+                continue;
+            }
+        };
+        let request = String::from_utf8_lossy(&buffer[..read]);
+        let callback = parse_callback(request.lines().next().unwrap_or_default(), expected_state);
+
+        let (status, body) = match &callback {
+            Callback::Code(_) => ("200 OK", SUCCESS_PAGE),
+            Callback::Denied(_) => ("200 OK", FAILURE_PAGE),
+            Callback::Ignored => ("404 Not Found", ""),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        if let Err(e) = stream.write_all(response.as_bytes()).await {
+            log::debug!("Failed to answer browser login callback: {}", e);
+        }
+
+        match callback {
+            Callback::Code(code) => return Ok(code),
+            Callback::Denied(error) => {
+                return Err(AgentError::InvalidCredentials(format!("Browser sign-in failed: {}", error)))
+            }
+            Callback::Ignored => continue,
+        }
+    }
+}
+
+async fn browser_login_impl(app: &AppHandle, server_url: String, state: &StateHandle) -> Result<AuthStatus, AgentError> {
+    let server_url = server_url.trim_end_matches('/').to_string();
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Failed to start login callback listener: {}", e)))?;
+    let port = listener.local_addr().map_err(|e| AgentError::Internal(e.to_string()))?.port();
+    let redirect_uri = format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH);
+    let nonce = random_state();
+    let verifier = code_verifier();
+
+    let login_url = reqwest::Url::parse_with_params(
+        &format!("{}/desktop/login", server_url),
+        &[
+            ("redirect_uri", redirect_uri.as_str()),
+            ("state", nonce.as_str()),
+            ("code_challenge", code_challenge(&verifier).as_str()),
+            ("code_challenge_method", "S256"),
+            ("deviceName", crate::commands::get_display_device_name().await.as_str()),
+        ],
+    )
+    .map_err(|e| AgentError::Internal(format!("Invalid server URL: {}", e)))?;
+
+    log::info!("Opening browser login on port {}", port);
+    open_in_browser(app, login_url.as_str())
+        .map_err(|e| AgentError::Internal(format!("Failed to open the browser: {}", e)))?;
+
+    let code = tokio::select! {
+        result = tokio::time::timeout(LOGIN_TIMEOUT, wait_for_code(&listener, &nonce)) => {
+            result.map_err(|_| AgentError::Timeout("Browser sign-in timed out".to_string()))??
+        }
+        _ = CANCEL.notified() => {
+            return Err(AgentError::Internal("Browser sign-in cancelled".to_string()));
+        }
+    };
+    drop(listener);
+
+    let client = crate::api::client::shared_client();
+
+    let response = client
+        .post(format!("{}/api/auth/desktop-exchange", server_url))
+        .timeout(Duration::from_secs(30))
+        .json(&serde_json::json!({ "code": code, "redirectUri": redirect_uri, "codeVerifier": verifier }))
+        .send()
+        .await
+        .map_err(AgentError::from)?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(AgentError::from_status(status, format!("Browser sign-in failed: {}", error_text)));
+    }
+
+    let exchange: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let employee_id = exchange["employee"]["id"].as_str().ok_or("Missing employee ID")?;
+    let email = exchange["employee"]["email"].as_str().ok_or("Missing employee email")?;
+
+    crate::commands::register_device_session(&client, &server_url, email, employee_id, state).await
+}
+
+/// Sign in through the server's web login in the system browser
+#[tauri::command]
+pub async fn start_browser_login(
+    app: AppHandle,
+    server_url: String,
+    state: State<'_, StateHandle>,
+) -> Result<AuthStatus, AgentError> {
+    let result = browser_login_impl(&app, server_url, &state).await;
+    audit_log::record("browser_login", &result);
+    result
+}
+
+/// Abort a pending `start_browser_login`
+#[tauri::command]
+pub async fn cancel_browser_login() -> Result<(), AgentError> {
+    CANCEL.notify_waiters();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_callback() {
+        assert_eq!(
+            parse_callback("GET /callback?code=abc%2B1&state=s1 HTTP/1.1", "s1"),
+            Callback::Code("abc+1".to_string())
+        );
+        assert_eq!(
+            parse_callback("GET /callback?error=access_denied&state=s1 HTTP/1.1", "s1"),
+            Callback::Denied("access_denied".to_string())
+        );
+        assert_eq!(parse_callback("GET /callback?code=abc&state=other HTTP/1.1", "s1"), Callback::Ignored);
+        assert_eq!(parse_callback("GET /favicon.ico HTTP/1.1", "s1"), Callback::Ignored);
+        assert_eq!(parse_callback("", "s1"), Callback::Ignored);
+    }
+
+    #[test]
+    fn test_code_challenge() {
+        // RFC 7636 appendix B
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mJ0H5nkiEp6KlbmU5TEX5kLlRB5nVE"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuJmAXmGh4Q"
+        );
+        assert_eq!(code_verifier().len(), 43);
+    }
+}
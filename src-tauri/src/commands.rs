@@ -1,15 +1,24 @@
-use std::sync::Arc;
 use tauri::State;
-use tokio::sync::Mutex;
 use serde::{Deserialize, Serialize};
 
-use crate::storage::{AppState, consent, app_usage};
+use crate::error::AgentError;
+use crate::storage::{consent, app_usage, SessionCredentials, StateHandle};
+use crate::storage::audit_log::{self, AuditOutcome};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
     pub server_url: String,
+    /// Second login step: the challenge returned by the first attempt
+    #[serde(default)]
+    pub challenge_id: Option<String>,
+    /// TOTP or emailed verification code for `challenge_id`
+    #[serde(default)]
+    pub two_factor_code: Option<String>,
+    /// Skip the second factor on this device next time
+    #[serde(default)]
+    pub remember_device: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -17,6 +26,38 @@ pub struct AuthStatus {
     pub is_authenticated: bool,
     pub email: Option<String>,
     pub device_id: Option<String>,
+    /// Set when the account needs a verification code; call `login` again with it
+    #[serde(default)]
+    pub two_factor: Option<TwoFactorChallenge>,
+}
+
+/// Second factor requested by the backend during login
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TwoFactorChallenge {
+    pub challenge_id: String,
+    /// "totp" (authenticator app) or "email"
+    pub method: String,
+    /// Masked address the email code was sent to
+    pub destination: Option<String>,
+    pub attempts_remaining: Option<u32>,
+}
+
+/// Secure store key of the remember-device token for an account
+fn trusted_device_key(email: &str) -> String {
+    format!("trusted_device:{}", email.trim().to_lowercase())
+}
+
+/// The challenge in a login response (200 or 401) asking for a second factor
+fn parse_two_factor_challenge(body: &serde_json::Value) -> Option<TwoFactorChallenge> {
+    if !body["twoFactorRequired"].as_bool().unwrap_or(false) {
+        return None;
+    }
+    Some(TwoFactorChallenge {
+        challenge_id: body["challengeId"].as_str()?.to_string(),
+        method: body["method"].as_str().unwrap_or("totp").to_string(),
+        destination: body["destination"].as_str().map(str::to_string),
+        attempts_remaining: body["attemptsRemaining"].as_u64().map(|n| n as u32),
+    })
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +74,8 @@ pub struct WorkSessionInfo {
     pub current_app: Option<String>,
     pub idle_time_seconds: u64,
     pub is_paused: bool,
+    /// Paused time of the session so far, not counted as worked
+    pub paused_seconds: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -47,7 +90,7 @@ pub struct TrackingStatus {
 use crate::sampling::app_focus::AppInfo;
 
 // Helper functions for device registration
-fn get_platform_name() -> &'static str {
+pub(crate) fn get_platform_name() -> &'static str {
     match std::env::consts::OS {
         "windows" => "Windows",
         "macos" => "macOS", 
@@ -56,7 +99,7 @@ fn get_platform_name() -> &'static str {
     }
 }
 
-fn get_os_version() -> String {
+pub(crate) fn get_os_version() -> String {
     #[cfg(target_os = "windows")]
     {
         // Method 1: Try PowerShell to get accurate Windows version (most reliable)
@@ -314,389 +357,612 @@ fn get_device_name() -> String {
     }
 }
 
-// Import PermissionsStatus from our dedicated permissions module
-use crate::permissions::PermissionsStatus;
+/// Secure store key of the user-chosen device name
+const DEVICE_NAME_KEY: &str = "device_name";
+
+/// Longest device name the backend accepts
+const MAX_DEVICE_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceNameInfo {
+    /// Name shown in the dashboard
+    pub name: String,
+    /// Auto-detected hostname
+    pub hostname: String,
+    /// Whether `name` was set by the user
+    pub custom: bool,
+    /// Whether `set_device_name` reached the backend (false when offline or
+    /// logged out; the name is then sent with the next registration)
+    pub synced: Option<bool>,
+}
 
-#[tauri::command]
-pub async fn trigger_sync() -> Result<String, String> {
-    
-    // Try to sync pending heartbeats
-    let mut synced_heartbeats = 0;
-    if let Ok(heartbeats) = crate::storage::offline_queue::get_pending_heartbeats().await {
-        for heartbeat in heartbeats {
-            if let Ok(_) = crate::sampling::send_heartbeat_to_backend(&heartbeat.heartbeat_data).await {
-                if let Ok(_) = crate::storage::offline_queue::mark_heartbeat_processed(heartbeat.id).await {
-                    synced_heartbeats += 1;
-                }
-            }
+async fn get_custom_device_name() -> Option<String> {
+    match crate::storage::secure_store::get_setting(DEVICE_NAME_KEY).await {
+        // Cleared names are stored as empty strings
+        Ok(name) => name.filter(|n| !n.trim().is_empty()),
+        Err(e) => {
+            log::warn!("Failed to read device name setting: {}", e);
+            None
         }
     }
-    
-    // Try to sync pending events
-    let mut synced_events = 0;
-    if let Ok(events) = crate::storage::offline_queue::get_pending_events().await {
-        for event in events {
-            if let Ok(_) = crate::sampling::send_event_to_backend(&event.event_type, &event.event_data).await {
-                if let Ok(_) = crate::storage::offline_queue::mark_event_processed(event.id).await {
-                    synced_events += 1;
-                }
-            }
+}
+
+/// The user's device name if set, otherwise the hostname
+pub(crate) async fn get_display_device_name() -> String {
+    get_custom_device_name().await.unwrap_or_else(get_device_name)
+}
+
+async fn push_device_name(state: &StateHandle, name: &str) -> anyhow::Result<bool> {
+    let device_id = match state.snapshot().device_id {
+        Some(device_id) if !device_id.is_empty() => device_id,
+        // Not registered yet: the name is sent with the next registration
+        _ => return Ok(false),
+    };
+    let body = serde_json::json!({
+        "deviceId": device_id,
+        "deviceUuid": crate::storage::database::get_or_create_device_uuid()?,
+        "deviceName": name,
+    });
+    let client = crate::api::client::ApiClient::new().await?;
+    let response = client.put_with_auth("/api/devices/update", &body).await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Device update failed: {}", response.status()));
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn get_device_name_info() -> Result<DeviceNameInfo, AgentError> {
+    let custom = get_custom_device_name().await;
+    let hostname = get_device_name();
+    Ok(DeviceNameInfo {
+        custom: custom.is_some(),
+        name: custom.unwrap_or_else(|| hostname.clone()),
+        hostname,
+        synced: None,
+    })
+}
+
+/// Give the device a friendly name (or go back to the hostname with `None`)
+/// and push it to the backend
+#[tauri::command]
+pub async fn set_device_name(state: State<'_, StateHandle>, name: Option<String>) -> Result<DeviceNameInfo, AgentError> {
+    let result = set_device_name_impl(&state, name).await;
+    audit_log::record("set_device_name", &result);
+    result
+}
+
+async fn set_device_name_impl(state: &StateHandle, name: Option<String>) -> Result<DeviceNameInfo, AgentError> {
+    let custom = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    if let Some(name) = &custom {
+        if name.chars().count() > MAX_DEVICE_NAME_LEN {
+            return Err(AgentError::Internal(format!(
+                "Device name is too long (max {} characters)",
+                MAX_DEVICE_NAME_LEN
+            )));
         }
     }
-    
-    let message = format!("Sync completed: {} heartbeats, {} events synced", synced_heartbeats, synced_events);
-    Ok(message)
+
+    crate::storage::secure_store::store_setting(DEVICE_NAME_KEY, custom.as_deref().unwrap_or(""))
+        .await
+        .map_err(|e| AgentError::Storage(format!("Failed to save device name: {}", e)))?;
+
+    let hostname = get_device_name();
+    let name = custom.clone().unwrap_or_else(|| hostname.clone());
+    let synced = match push_device_name(state, &name).await {
+        Ok(synced) => synced,
+        Err(e) => {
+            log::warn!("Device name saved locally, backend update failed: {}", e);
+            false
+        }
+    };
+
+    log::info!("Device name set to {:?} (synced: {})", name, synced);
+    Ok(DeviceNameInfo { name, hostname, custom: custom.is_some(), synced: Some(synced) })
+}
+
+// Import PermissionsStatus from our dedicated permissions module
+use crate::permissions::PermissionsStatus;
+
+/// Send queued data now: `scope` limits it to one data type (default all),
+/// `priority` can put clock events first and defer screenshots. Progress is
+/// emitted as `sync-progress`.
+#[tauri::command]
+pub async fn trigger_sync(
+    scope: Option<crate::sampling::offline_sync::SyncScope>,
+    priority: Option<crate::sampling::offline_sync::SyncPriority>,
+) -> Result<crate::sampling::offline_sync::SyncReport, AgentError> {
+    Ok(crate::sampling::offline_sync::run(scope.unwrap_or_default(), priority.unwrap_or_default()).await)
 }
 
 #[tauri::command]
 pub async fn login(
     request: LoginRequest,
-    state: State<'_, Arc<Mutex<AppState>>>,
+    state: State<'_, StateHandle>,
     app_handle: tauri::AppHandle,
-) -> Result<AuthStatus, String> {
-    
-    // Create HTTP client with timeout
-    let client = reqwest::Client::builder()
-        .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
+) -> Result<AuthStatus, AgentError> {
+    let result = login_impl(request, state, app_handle).await;
+    match &result {
+        Ok(status) if status.two_factor.is_some() => {
+            audit_log::record_outcome("login", audit_log::AuditOutcome::Challenged, None)
+        }
+        _ => audit_log::record("login", &result),
+    }
+    result
+}
+
+/// Send a new emailed verification code for a pending two-factor login
+#[tauri::command]
+pub async fn resend_two_factor_code(server_url: String, challenge_id: String) -> Result<(), AgentError> {
+    let client = crate::api::client::shared_client();
+    let url = format!("{}/api/auth/2fa/resend", server_url.trim_end_matches('/'));
+    let response = client
+        .post(&url)
         .timeout(std::time::Duration::from_secs(30))
-        .connect_timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        .json(&serde_json::json!({ "challengeId": challenge_id }))
+        .send()
+        .await
+        .map_err(AgentError::from)?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(AgentError::from_status(status, format!("Failed to resend code: {}", error_text)));
+    }
+    Ok(())
+}
+
+async fn login_impl(
+    request: LoginRequest,
+    state: State<'_, StateHandle>,
+    app_handle: tauri::AppHandle,
+) -> Result<AuthStatus, AgentError> {
+    
+    use crate::api::login_throttle;
+
+    // Cooperate with the backend's brute-force protection instead of
+    // submitting attempts it would refuse anyway
+    if let Some(seconds) = login_throttle::retry_wait(&request.email) {
+        return Err(too_many_attempts(seconds));
+    }
+
+    let client = crate::api::client::shared_client();
     
     // Prepare login request
     let login_url = format!("{}/api/auth/employee-login", request.server_url.trim_end_matches('/'));
+    let trusted_device_token = crate::storage::secure_store::get_setting(&trusted_device_key(&request.email))
+        .await
+        .ok()
+        .flatten()
+        .filter(|token| !token.is_empty());
     let login_data = serde_json::json!({
         "email": request.email,
-        "password": request.password
+        "password": request.password,
+        "challengeId": request.challenge_id,
+        "twoFactorCode": request.two_factor_code.as_deref().map(str::trim),
+        "rememberDevice": request.remember_device,
+        "trustedDeviceToken": trusted_device_token
     });
 
     // Make login request
     let response = client
         .post(&login_url)
+        .timeout(std::time::Duration::from_secs(30))
         .header("Content-Type", "application/json")
         .json(&login_data)
         .send()
         .await
-        .map_err(|e| {
-            if e.is_connect() {
-                "Cannot connect to server. Please check your network connection.".to_string()
-            } else if e.is_timeout() {
-                "Connection timeout. Please check your network connection.".to_string()
-            } else {
-                format!("Network error: {}", e)
-            }
-        })?;
+        .map_err(AgentError::from)?;
 
     if response.status().is_success() {
+        login_throttle::record_success(&request.email);
         let login_response: serde_json::Value = response
             .json()
             .await
             .map_err(|e| format!("Failed to parse response: {}", e))?;
 
-        if let Some(employee) = login_response.get("employee") {
-            let employee_id = employee.get("id")
-                .and_then(|v| v.as_str())
-                .ok_or("Missing employee ID")?;
-
-            // Now register device for this employee
-            let device_name = get_device_name();
-            let platform_name = get_platform_name();
-            let os_version = get_os_version();
-            
-            // Get or create a stable device UUID to prevent duplicate device records
-            let device_uuid = match crate::storage::database::get_or_create_device_uuid() {
-                Ok(uuid) => Some(uuid),
-                Err(e) => {
-                    log::warn!("Failed to get/create device UUID: {}", e);
-                    None
-                }
-            };
-            
-            let device_data = serde_json::json!({
-                "employeeId": employee_id,
-                "deviceName": device_name,
-                "platform": platform_name,
-                "osVersion": os_version,
-                "appVersion": env!("CARGO_PKG_VERSION"),
-                "deviceUuid": device_uuid // Stable UUID for device matching
+        // 2FA-enabled account: the UI asks for the code and calls login again
+        if let Some(challenge) = parse_two_factor_challenge(&login_response) {
+            log::info!("Login requires a {} verification code", challenge.method);
+            return Ok(AuthStatus {
+                is_authenticated: false,
+                email: Some(request.email),
+                device_id: None,
+                two_factor: Some(challenge),
             });
+        }
 
-            let register_url = format!("{}/api/devices/employee-register", request.server_url.trim_end_matches('/'));
-            let device_response = client
-                .post(&register_url)
-                .header("Content-Type", "application/json")
-                .json(&device_data)
-                .send()
-                .await
-                .map_err(|e| format!("Device registration error: {}", e))?;
-
-            // Handle both success (200) and "no license" (402) responses
-            // We want to complete login even without a license so the agent can receive activation events
-            let device_status = device_response.status();
-            let has_no_license = device_status.as_u16() == 402;
-            
-            if device_response.status().is_success() || has_no_license {
-                let device_result: serde_json::Value = device_response
-                    .json()
-                    .await
-                    .map_err(|e| format!("Failed to parse device response: {}", e))?;
-
-                if let Some(device) = device_result.get("device") {
-                    let device_id = device.get("id")
-                        .and_then(|v| v.as_str())
-                        .ok_or("Missing device ID")?;
-
-                    let device_token = device.get("token")
-                        .and_then(|v| v.as_str())
-                        .ok_or("Missing device token")?;
-
-                    // Store credentials securely
-                    {
-                        let mut app_state = state.lock().await;
-                        app_state.server_url = Some(request.server_url.clone());
-                        app_state.device_token = Some(device_token.to_string());
-                        app_state.device_id = Some(device_id.to_string());
-                        app_state.email = Some(request.email.clone());
-                        app_state.employee_id = Some(employee_id.to_string());
-                    }
-
-                    // Sync device token to global app state for background services
-                    if let Err(e) = crate::storage::sync_device_token_to_global(
-                        device_token.to_string(),
-                        device_id.to_string(),
-                        request.email.clone(),
-                        request.server_url.clone(),
-                        employee_id.to_string(),
-                    ).await {
-                        log::error!("Failed to sync device token to global state1: {}", e);
-                    }
-
-                    // NOTE: Do NOT start background services on login!
-                    // Background services (heartbeat, app tracking, etc.) should only start when
-                    // user explicitly clocks in. This prevents "Online Now" appearing without clock-in.
-                    // The clock_in command handles starting background services.
-                    let _ = app_handle; // Suppress unused variable warning
-
-                    // Store complete session data in secure storage for persistence
-                    let session_data = crate::storage::secure_store::SessionData {
-                        device_token: device_token.to_string(),
-                        email: request.email.clone(),
-                        device_id: device_id.to_string(),
-                        server_url: request.server_url.clone(),
-                        employee_id: Some(employee_id.to_string()),
-                    };
-                    
-                    if let Err(e) = crate::storage::secure_store::store_session_data(&session_data).await {
-                        log::warn!("Failed to store session data securely: {}", e);
-                    }
-                    
-                    // Also store device token separately for backward compatibility
-                    if let Err(e) = crate::storage::secure_store::store_device_token(&device_token).await {
-                        log::warn!("Failed to store device token securely: {}", e);
-                    }
-                    
-                    // Store session metadata in SQLite as backup (not the token, just metadata)
-                    let cache_entry = crate::storage::database::SessionCacheEntry {
-                        email: request.email.clone(),
-                        device_id: device_id.to_string(),
-                        server_url: request.server_url.clone(),
-                        employee_id: Some(employee_id.to_string()),
-                        last_validated_at: Some(chrono::Utc::now().to_rfc3339()),
-                    };
-                    if let Err(e) = crate::storage::database::store_session_cache(&cache_entry) {
-                        log::warn!("Failed to store session cache in SQLite: {}", e);
-                    }
-
-                    // Clear any existing active sessions to ensure clean state
-                    if let Err(e) = crate::storage::work_session::clear_all_active_sessions().await {
-                        log::warn!("Failed to clear existing active sessions: {}", e);
-                    }
-
-                    // Reset app usage tracker to prevent stale sessions from causing large duration calculations
-                    if let Err(e) = crate::storage::app_usage::reset_tracker().await {
-                        log::warn!("Failed to reset app usage tracker: {}", e);
-                    }
+        if let Some(token) = login_response["trustedDeviceToken"].as_str() {
+            if let Err(e) = crate::storage::secure_store::store_setting(&trusted_device_key(&request.email), token).await {
+                log::warn!("Failed to store remember-device token: {}", e);
+            }
+        }
 
-                    // Start license SSE stream to receive real-time license updates
-                    // This is started BEFORE checking license so agent can receive activation events
-                    crate::sampling::license_stream::start_license_stream(state.inner().clone()).await;
-
-                    // If device registration returned 402, set license_valid to false
-                    // but still complete login so agent can receive license activation events
-                    if has_no_license {
-                        log::warn!("Device registered but no valid license. Starting in limited mode.");
-                        let mut app_state = state.lock().await;
-                        app_state.license_valid = Some(false);
-                        app_state.license_status = Some("NO_LICENSE".to_string());
-                        // Note: Login still succeeds so license stream can receive activation
-                    }
+        if let Some(employee_id) = login_response["employee"]["id"].as_str() {
+            // NOTE: Do NOT start background services on login!
+            // Background services (heartbeat, app tracking, etc.) should only start when
+            // user explicitly clocks in. This prevents "Online Now" appearing without clock-in.
+            // The clock_in command handles starting background services.
+            let _ = app_handle; // Suppress unused variable warning
 
-                    return Ok(AuthStatus {
-                        is_authenticated: true,
-                        email: Some(request.email),
-                        device_id: Some(device_id.to_string()),
-                    });
-                }
-            } else {
-                // Device registration failed with a real error (not 402, which we handle above)
-                let status = device_response.status();
-                let error_text = device_response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-                
-                log::error!("Device registration failed: {} - {}", status, error_text);
-                return Err(format!("Device registration failed: {}", error_text));
-            }
+            return register_device_session(&client, &request.server_url, &request.email, employee_id, &state).await;
         }
     } else {
         let status = response.status();
+        let retry_after_header = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
         let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
         
         // Provide more specific error messages based on status code
+        let error_body: serde_json::Value = serde_json::from_str(&error_text).unwrap_or_default();
+        let retry_after = || {
+            login_throttle::parse_retry_after(retry_after_header.as_deref(), &error_body, crate::utils::clock::now())
+        };
         let error_message = match status.as_u16() {
-            401 => "Invalid email or password. Please check your credentials.",
+            401 => {
+                if let Some(challenge) = parse_two_factor_challenge(&error_body) {
+                    return Ok(AuthStatus {
+                        is_authenticated: false,
+                        email: Some(request.email),
+                        device_id: None,
+                        two_factor: Some(challenge),
+                    });
+                }
+                let key = match error_body["code"].as_str() {
+                    Some("INVALID_2FA_CODE") => "error.invalid_2fa_code",
+                    Some("2FA_CHALLENGE_EXPIRED") => "error.2fa_challenge_expired",
+                    _ => "error.invalid_credentials",
+                };
+                if let Some(seconds) = login_throttle::record_failure(&request.email, None) {
+                    log::info!("Login failed repeatedly, next attempt allowed in {}s", seconds);
+                }
+                return Err(AgentError::InvalidCredentials(crate::i18n::t(key)));
+            }
+            423 => {
+                let locked_for = retry_after();
+                login_throttle::record_failure(&request.email, locked_for);
+                log::warn!("Login refused: account locked (retry after {:?}s)", locked_for);
+                let message = match locked_for {
+                    Some(seconds) => crate::i18n::t_args(
+                        "error.account_locked_for",
+                        &[("minutes", seconds.div_ceil(60).max(1).to_string().as_str())],
+                    ),
+                    None => crate::i18n::t("error.account_locked"),
+                };
+                return Err(AgentError::AccountLocked(message));
+            }
+            429 => {
+                let seconds = login_throttle::record_failure(
+                    &request.email,
+                    Some(retry_after().unwrap_or(login_throttle::DEFAULT_RETRY_AFTER_SECS)),
+                )
+                .unwrap_or(login_throttle::DEFAULT_RETRY_AFTER_SECS);
+                log::warn!("Login rate limited by server, retrying allowed in {}s", seconds);
+                return Err(too_many_attempts(seconds));
+            }
             402 => {
                 // 402 Payment Required - No valid license
                 // Parse the error response to get the license status
                 if error_text.contains("NO_VALID_LICENSE") {
-                    "NO_VALID_LICENSE: Your organization needs to activate a license for your account before you can use the desktop agent. Please contact your administrator."
+                    // The frontend matches on the untranslated prefix
+                    format!("NO_VALID_LICENSE: {}", crate::i18n::t("error.no_valid_license"))
                 } else {
-                    "Payment required. Please contact your administrator to activate your license."
+                    crate::i18n::t("error.payment_required")
                 }
             },
-            404 => "Server not found. Please check your network connection.",
-            500 => "Server error. Please try again later.",
-            _ => &error_text
+            404 => crate::i18n::t("error.server_not_found"),
+            500 => crate::i18n::t("error.server_unavailable"),
+            _ => error_text
         };
         
-        return Err(format!("Login failed ({}): {}", status, error_message));
+        let message = crate::i18n::t_args(
+            "error.login_failed",
+            &[("status", status.to_string().as_str()), ("message", error_message.as_str())],
+        );
+        return Err(match status.as_u16() {
+            402 => AgentError::LicenseRequired(message),
+            404 => AgentError::Offline(message),
+            code => AgentError::Server { status: code, message },
+        });
     }
 
-    Err("Login failed".to_string())
+    Err("Login failed".into())
 }
 
-#[tauri::command]
-pub async fn logout(state: State<'_, Arc<Mutex<AppState>>>) -> Result<(), String> {
-    log::info!("Logout: Starting logout process");
+fn too_many_attempts(seconds: u64) -> AgentError {
+    AgentError::LoginThrottled {
+        retry_after_secs: seconds,
+        message: crate::i18n::t_args("error.too_many_attempts", &[("seconds", seconds.to_string().as_str())]),
+    }
+}
 
-    // ✅ FIRST: Check if user has an active work session and clock them out
-    // This must happen BEFORE clearing credentials, otherwise the clock_out API call will fail
-    if crate::storage::work_session::is_session_active().await.unwrap_or(false) {
-        log::info!("Logout: User is clocked in, performing automatic clock-out");
-        
-        // End local app usage session first
-        if let Err(e) = crate::storage::app_usage::end_current_session().await {
-            log::warn!("Logout: Failed to end current app session: {}", e);
-        }
-
-        // Send a final app focus event to close any open app usage entries
-        if let Ok(Some(current_app)) = crate::commands::get_current_app().await {
-            log::info!("Logout: Sending final app focus event to close open entries");
-            let event_data = serde_json::json!({
-                "app_name": current_app.name,
-                "app_id": current_app.app_id,
-                "window_title": current_app.window_title,
-                "timestamp": chrono::Utc::now().to_rfc3339()
+/// Device record returned by `/api/devices/employee-register`
+pub(crate) struct RegisteredDevice {
+    pub id: String,
+    pub token: String,
+    pub signing_secret: Option<String>,
+    /// Registered, but the employee has no license (402)
+    pub has_no_license: bool,
+}
+
+/// Register this device for an authenticated employee and persist the session.
+/// Shared by password and browser login.
+pub(crate) async fn register_device_session(
+    client: &reqwest::Client,
+    server_url: &str,
+    email: &str,
+    employee_id: &str,
+    state: &StateHandle,
+) -> Result<AuthStatus, AgentError> {
+    let device = request_device_registration(client, server_url, employee_id).await?;
+    establish_session(
+        server_url,
+        email,
+        employee_id,
+        &device.id,
+        &device.token,
+        device.signing_secret.as_deref(),
+        device.has_no_license,
+        state,
+    )
+    .await
+}
+
+/// Register (or re-register) this device without touching the local session.
+/// The stable device UUID lets the backend match an existing record.
+pub(crate) async fn request_device_registration(
+    client: &reqwest::Client,
+    server_url: &str,
+    employee_id: &str,
+) -> Result<RegisteredDevice, AgentError> {
+    let device_name = get_display_device_name().await;
+    let platform_name = get_platform_name();
+    let os_version = get_os_version();
+    
+    // Get or create a stable device UUID to prevent duplicate device records
+    let device_uuid = match crate::storage::database::get_or_create_device_uuid() {
+        Ok(uuid) => Some(uuid),
+        Err(e) => {
+            log::warn!("Failed to get/create device UUID: {}", e);
+            None
+        }
+    };
+    
+    let previous_device_uuid = crate::storage::database::get_legacy_device_uuid()
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to read legacy device UUID: {}", e);
+            None
+        });
+    
+    let device_data = serde_json::json!({
+        "employeeId": employee_id,
+        "deviceName": device_name,
+        "platform": platform_name,
+        "osVersion": os_version,
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "deviceUuid": device_uuid, // Stable UUID for device matching
+        // Random UUID this install registered with before, so the backend can merge records
+        "previousDeviceUuid": previous_device_uuid,
+        // Remote/VM sessions change what idle time and screenshots mean
+        "environment": crate::utils::session_environment::detect()
+    });
+
+    let register_url = format!("{}/api/devices/employee-register", server_url.trim_end_matches('/'));
+    let device_response = client
+        .post(&register_url)
+        .timeout(std::time::Duration::from_secs(30))
+        .header("Content-Type", "application/json")
+        .json(&device_data)
+        .send()
+        .await
+        .map_err(AgentError::from)?;
+
+    // Handle both success (200) and "no license" (402) responses
+    // We want to complete login even without a license so the agent can receive activation events
+    let device_status = device_response.status();
+    let has_no_license = device_status.as_u16() == 402;
+    
+    if device_response.status().is_success() || has_no_license {
+        let device_result: serde_json::Value = device_response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse device response: {}", e))?;
+
+        if let Some(device) = device_result.get("device") {
+            let device_id = device.get("id")
+                .and_then(|v| v.as_str())
+                .ok_or("Missing device ID")?;
+
+            let device_token = device.get("token")
+                .and_then(|v| v.as_str())
+                .ok_or("Missing device token")?;
+
+            let signing_secret = device.get("signingSecret").and_then(|v| v.as_str());
+
+            return Ok(RegisteredDevice {
+                id: device_id.to_string(),
+                token: device_token.to_string(),
+                signing_secret: signing_secret.map(str::to_string),
+                has_no_license,
             });
-            if let Err(e) = crate::sampling::send_event_to_backend("app_focus", &event_data).await {
-                log::warn!("Logout: Failed to send final app focus event: {}", e);
-            }
         }
+    } else {
+        // Device registration failed with a real error (not 402, which we handle above)
+        let status = device_response.status();
+        let error_text = device_response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        
+        log::error!("Device registration failed: {} - {}", status, error_text);
+        return Err(AgentError::from_status(status, format!("Device registration failed: {}", error_text)));
+    }
 
-        // Process any remaining queued events before stopping
-        log::info!("Logout: Processing remaining queued events");
-        if let Ok(events) = crate::storage::offline_queue::get_pending_events().await {
-            for event in events {
-                match crate::sampling::send_event_to_backend(&event.event_type, &event.event_data).await {
-                    Ok(_) => {
-                        let _ = crate::storage::offline_queue::mark_event_processed(event.id).await;
-                    }
-                    Err(e) => {
-                        log::warn!("Logout: Failed to send queued event {}: {}", event.id, e);
-                        let _ = crate::storage::offline_queue::mark_event_failed(event.id).await;
-                    }
-                }
-            }
+    Err("Missing device in registration response".into())
+}
+
+/// Publish the device credentials and persist them in secure storage
+pub(crate) async fn store_credentials(
+    server_url: &str,
+    email: &str,
+    employee_id: &str,
+    device_id: &str,
+    device_token: &str,
+    state: &StateHandle,
+) {
+    // Publish credentials to the state service (shared with background services)
+    if let Err(e) = state.set_session(SessionCredentials {
+        device_token: device_token.to_string(),
+        device_id: device_id.to_string(),
+        email: email.to_string(),
+        server_url: server_url.to_string(),
+        employee_id: Some(employee_id.to_string()),
+    }).await {
+        log::error!("Failed to store session in app state: {}", e);
+    }
+
+    // Store complete session data in secure storage for persistence
+    let session_data = crate::storage::secure_store::SessionData {
+        device_token: device_token.to_string(),
+        email: email.to_string(),
+        device_id: device_id.to_string(),
+        server_url: server_url.to_string(),
+        employee_id: Some(employee_id.to_string()),
+    };
+    
+    if let Err(e) = crate::storage::secure_store::store_session_data(&session_data).await {
+        log::warn!("Failed to store session data securely: {}", e);
+    }
+    
+    // Also store device token separately for backward compatibility
+    if let Err(e) = crate::storage::secure_store::store_device_token(device_token).await {
+        log::warn!("Failed to store device token securely: {}", e);
+    }
+}
+
+/// Publish and persist the credentials of a registered device.
+/// Shared by password, browser and pairing login.
+pub(crate) async fn establish_session(
+    server_url: &str,
+    email: &str,
+    employee_id: &str,
+    device_id: &str,
+    device_token: &str,
+    signing_secret: Option<&str>,
+    has_no_license: bool,
+    state: &StateHandle,
+) -> Result<AuthStatus, AgentError> {
+    store_credentials(server_url, email, employee_id, device_id, device_token, state).await;
+    let resuming = crate::api::device_repair::reset();
+    
+    // Store session metadata in SQLite as backup (not the token, just metadata)
+    let cache_entry = crate::storage::database::SessionCacheEntry {
+        email: email.to_string(),
+        device_id: device_id.to_string(),
+        server_url: server_url.to_string(),
+        employee_id: Some(employee_id.to_string()),
+        last_validated_at: Some(chrono::Utc::now().to_rfc3339()),
+    };
+    if let Err(e) = crate::storage::database::store_session_cache(&cache_entry) {
+        log::warn!("Failed to store session cache in SQLite: {}", e);
+    }
+
+    if resuming {
+        // Signing in again after the device record was lost: keep the work
+        // session and tracker going, queued data syncs with the new token
+        log::info!("Re-login after failed device repair, keeping the local session");
+    } else {
+        // Clear any existing active sessions to ensure clean state
+        if let Err(e) = crate::storage::work_session::clear_all_active_sessions().await {
+            log::warn!("Failed to clear existing active sessions: {}", e);
         }
 
-        // Process pending heartbeats
-        if let Ok(heartbeats) = crate::storage::offline_queue::get_pending_heartbeats().await {
-            for heartbeat in heartbeats {
-                match crate::sampling::send_heartbeat_to_backend(&heartbeat.heartbeat_data).await {
-                    Ok(_) => {
-                        let _ = crate::storage::offline_queue::mark_heartbeat_processed(heartbeat.id).await;
-                    }
-                    Err(e) => {
-                        log::warn!("Logout: Failed to send queued heartbeat {}: {}", heartbeat.id, e);
-                        let _ = crate::storage::offline_queue::mark_heartbeat_failed(heartbeat.id).await;
-                    }
-                }
-            }
+        // Reset app usage tracker to prevent stale sessions from causing large duration calculations
+        if let Err(e) = crate::storage::app_usage::reset_tracker().await {
+            log::warn!("Failed to reset app usage tracker: {}", e);
         }
+    }
 
-        // End local work session
-        if let Err(e) = crate::storage::work_session::end_session().await {
-            log::warn!("Logout: Failed to end local work session: {}", e);
+    // Ingest requests are signed only if the backend issued a secret
+    crate::api::request_signing::store_secret(signing_secret).await;
+
+    // Record which ingest payload versions this backend accepts
+    tokio::spawn(async {
+        if let Err(e) = crate::api::ingest_schema::negotiate_with_backend().await {
+            log::warn!("Failed to negotiate ingest schema versions: {}", e);
         }
+    });
 
-        // Send clock_out event to backend while we still have credentials
-        let (server_url, device_token) = {
-            let app_state = state.lock().await;
-            (app_state.server_url.clone(), app_state.device_token.clone())
-        };
+    // The startup syncs ran before there were credentials
+    tokio::spawn(async {
+        if let Err(e) = crate::api::title_rules::sync_title_rules().await {
+            log::warn!("Failed to sync title scrub rules after login: {}", e);
+        }
+        if let Err(e) = crate::api::known_sites::sync_known_sites().await {
+            log::warn!("Failed to sync known sites after login: {}", e);
+        }
+    });
 
-        if let (Some(_server_url), Some(_device_token)) = (server_url, device_token) {
-            if let Ok(client) = crate::api::client::ApiClient::new().await {
-                let event_data = serde_json::json!({
-                    "events": [{
-                        "type": "clock_out",
-                        "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-                        "data": {
-                            "source": "desktop_agent",
-                            "reason": "logout"
-                        }
-                    }]
-                });
-                match client.post_with_auth("/api/ingest/events", &event_data).await {
-                    Ok(response) => {
-                        if response.status().is_success() {
-                            log::info!("Logout: Successfully sent clock_out event to backend");
-                        } else {
-                            log::warn!("Logout: Clock_out event failed with status: {}", response.status());
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!("Logout: Failed to send clock_out event: {}", e);
-                    }
-                }
-            }
+    // Start license SSE stream to receive real-time license updates
+    // This is started BEFORE checking license so agent can receive activation events
+    crate::sampling::license_stream::start_license_stream(state.clone()).await;
+
+    // If device registration returned 402, set license_valid to false
+    // but still complete login so agent can receive license activation events
+    if has_no_license {
+        log::warn!("Device registered but no valid license. Starting in limited mode.");
+        if let Err(e) = state.set_license(false, Some("NO_LICENSE".to_string())).await {
+            log::warn!("Failed to update license state: {}", e);
+        }
+        // Note: Login still succeeds so license stream can receive activation
+    }
+
+    Ok(AuthStatus {
+        is_authenticated: true,
+        email: Some(email.to_string()),
+        device_id: Some(device_id.to_string()),
+        two_factor: None,
+    })
+}
+
+#[tauri::command]
+pub async fn logout(state: State<'_, StateHandle>) -> Result<(), AgentError> {
+    let result = logout_impl(state).await;
+    audit_log::record("logout", &result);
+    result
+}
+
+async fn logout_impl(state: State<'_, StateHandle>) -> Result<(), AgentError> {
+    log::info!("Logout: Starting logout process");
+
+    // Clock out BEFORE clearing credentials, otherwise the clock_out API call will fail
+    if crate::storage::work_session::is_session_active().await.unwrap_or(false) {
+        log::info!("Logout: User is clocked in, performing automatic clock-out");
+        if let Err(e) = crate::session_manager::end_session(crate::session_manager::EndReason::Logout).await {
+            log::warn!("Logout: Automatic clock-out incomplete: {}", e);
         }
-        log::info!("Logout: Automatic clock-out completed");
     }
 
+    // Free the license seat while the device token is still valid
+    if let Err(e) = crate::api::seat::release_seat("logout").await {
+        log::warn!("Logout: Failed to release license seat: {}", e);
+    }
+
+    clear_local_session(&state).await;
+
+    Ok(())
+}
+
+/// Stop services and forget the stored session (logout, device deactivation)
+pub(crate) async fn clear_local_session(state: &StateHandle) {
     // Clear in-memory state
-    {
-        let mut app_state = state.lock().await;
-        app_state.device_token = None;
-        app_state.device_id = None;
-        app_state.email = None;
-        app_state.server_url = None;
-        app_state.employee_id = None;
-        app_state.is_paused = false;
-    }
-
-    // Also clear global app state
-    if let Ok(global_state) = crate::storage::get_global_app_state() {
-        let mut state = global_state.lock().await;
-        state.device_token = None;
-        state.device_id = None;
-        state.email = None;
-        state.server_url = None;
-        state.employee_id = None;
-        state.is_paused = false;
+    if let Err(e) = state.clear_session().await {
+        log::warn!("Failed to clear app state on logout: {}", e);
     }
 
     // Stop all background services on logout
     log::info!("Logout: Stopping all background services");
     crate::sampling::stop_services().await;
+    crate::sampling::clear_pause().await;
 
     // Reset app usage tracker to clear any active sessions
     if let Err(e) = crate::storage::app_usage::reset_tracker().await {
@@ -721,26 +987,27 @@ pub async fn logout(state: State<'_, Arc<Mutex<AppState>>>) -> Result<(), String
         log::warn!("Failed to clear SQLite session cache: {}", e);
     }
 
-    Ok(())
+    crate::api::request_signing::clear().await;
+
+    // The next user of this device must not be able to search this one's
+    // history, nor inherit their activity recall consent
+    if let Err(e) = crate::storage::activity_index::clear().await {
+        log::warn!("Failed to clear activity recall data: {}", e);
+    }
 }
 
 #[tauri::command]
 pub async fn get_auth_status(
-    state: State<'_, Arc<Mutex<AppState>>>,
+    state: State<'_, StateHandle>,
     app_handle: tauri::AppHandle,
-) -> Result<AuthStatus, String> {
-    let app_state = state.lock().await;
+) -> Result<AuthStatus, AgentError> {
+    let app_state = state.snapshot();
     
     // First check in-memory state
-    if app_state.device_token.is_some() && app_state.email.is_some() && app_state.server_url.is_some() {
-        let token = app_state.device_token.as_ref().unwrap().clone();
-        let email = app_state.email.as_ref().unwrap().clone();
-        let device_id = app_state.device_id.as_ref().unwrap().clone();
-        let server_url = app_state.server_url.as_ref().unwrap().clone();
-        
+    if let (Some(token), Some(email), Some(device_id), Some(server_url)) =
+        (app_state.device_token, app_state.email, app_state.device_id, app_state.server_url)
+    {
         // Validate token with server
-        drop(app_state); // Release lock for async operation
-        
         match validate_token_with_server(&server_url, &token).await {
             Ok(true) => {
                 // Token is valid
@@ -760,22 +1027,35 @@ pub async fn get_auth_status(
                     is_authenticated: true,
                     email: Some(email),
                     device_id: Some(device_id),
+                    two_factor: None,
                 });
             }
             Ok(false) => {
                 // Token is explicitly invalid, clear session
                 log::info!("In-memory token is invalid, clearing session");
-                let mut app_state = state.lock().await;
-                app_state.device_token = None;
-                app_state.email = None;
-                app_state.device_id = None;
-                app_state.server_url = None;
-                app_state.employee_id = None;
+                if let Err(e) = state.clear_session().await {
+                    log::warn!("Failed to clear app state: {}", e);
+                }
                 
                 // Clear stored session data
                 let _ = crate::storage::secure_store::delete_session_data().await;
                 let _ = crate::storage::database::clear_session_cache();
             }
+            Err(e) if !crate::storage::offline_validation::offline_access_allowed() => {
+                // Offline for longer than the policy allows: keep the stored session
+                // so it's picked up again once the backend is reachable
+                log::warn!("Network error during in-memory token validation: {} - offline window expired", e);
+                crate::sampling::stop_services().await;
+                if let Err(e) = state.clear_session().await {
+                    log::warn!("Failed to clear app state: {}", e);
+                }
+                return Ok(AuthStatus {
+                    is_authenticated: false,
+                    email: Some(email),
+                    device_id: None,
+                    two_factor: None,
+                });
+            }
             Err(e) => {
                 // Network error - allow offline access with cached session
                 log::info!("Network error during in-memory token validation: {} - allowing offline access", e);
@@ -794,11 +1074,10 @@ pub async fn get_auth_status(
                     is_authenticated: true,
                     email: Some(email),
                     device_id: Some(device_id),
+                    two_factor: None,
                 });
             }
         }
-    } else {
-        drop(app_state); // Release lock for async operation
     }
     
     // Try to restore session from secure storage with timeout
@@ -831,6 +1110,11 @@ pub async fn get_auth_status(
                         let _ = crate::storage::database::clear_session_cache();
                     }
                 }
+                Err(e) if !crate::storage::offline_validation::offline_access_allowed() => {
+                    log::warn!("Token validation failed: {} - offline window expired, sign-in required", e);
+                    // Services may have been started at launch from the stored token
+                    crate::sampling::stop_services().await;
+                }
                 Err(e) => {
                     log::warn!("Token validation failed: {}", e);
                     // On network error, still allow cached session (offline-first)
@@ -869,14 +1153,39 @@ pub async fn get_auth_status(
         log::info!("Found session cache in SQLite for {}, attempting token retrieval...", cache_entry.email);
         
         // Try to get the device token from secure storage
-        if let Ok(Some(device_token)) = crate::storage::secure_store::get_device_token().await {
-            log::info!("Retrieved device token from secure storage, validating...");
-            
-            // Validate the token
-            match validate_token_with_server(&cache_entry.server_url, &device_token).await {
-                Ok(is_valid) => {
-                    if is_valid {
-                        log::info!("SQLite cache + secure token validated successfully");
+        match crate::storage::secure_store::get_device_token().await {
+            Ok(Some(device_token)) => {
+                log::info!("Retrieved device token from secure storage, validating...");
+                
+                // Validate the token
+                match validate_token_with_server(&cache_entry.server_url, &device_token).await {
+                    Ok(is_valid) => {
+                        if is_valid {
+                            log::info!("SQLite cache + secure token validated successfully");
+                            return restore_session_to_memory(
+                                state.clone(),
+                                app_handle,
+                                device_token,
+                                cache_entry.email,
+                                cache_entry.device_id,
+                                cache_entry.server_url,
+                                cache_entry.employee_id,
+                            ).await;
+                        } else {
+                            log::warn!("Token from SQLite fallback is invalid, clearing all session data");
+                            let _ = crate::storage::secure_store::delete_session_data().await;
+                            let _ = crate::storage::secure_store::delete_device_token().await;
+                            let _ = crate::storage::database::clear_session_cache();
+                        }
+                    }
+                    Err(e) if !crate::storage::offline_validation::offline_access_allowed() => {
+                        log::warn!("Token validation failed during SQLite fallback: {} - offline window expired", e);
+                        crate::sampling::stop_services().await;
+                    }
+                    Err(e) => {
+                        log::warn!("Token validation failed during SQLite fallback: {}", e);
+                        // On network error, allow cached session for offline use
+                        log::info!("Network error - allowing SQLite cached session for offline use");
                         return restore_session_to_memory(
                             state.clone(),
                             app_handle,
@@ -886,32 +1195,27 @@ pub async fn get_auth_status(
                             cache_entry.server_url,
                             cache_entry.employee_id,
                         ).await;
-                    } else {
-                        log::warn!("Token from SQLite fallback is invalid, clearing all session data");
-                        let _ = crate::storage::secure_store::delete_session_data().await;
-                        let _ = crate::storage::secure_store::delete_device_token().await;
-                        let _ = crate::storage::database::clear_session_cache();
                     }
                 }
-                Err(e) => {
-                    log::warn!("Token validation failed during SQLite fallback: {}", e);
-                    // On network error, allow cached session for offline use
-                    log::info!("Network error - allowing SQLite cached session for offline use");
-                    return restore_session_to_memory(
-                        state.clone(),
-                        app_handle,
-                        device_token,
-                        cache_entry.email,
-                        cache_entry.device_id,
-                        cache_entry.server_url,
-                        cache_entry.employee_id,
-                    ).await;
-                }
             }
-        } else {
-            log::warn!("SQLite cache exists but no device token in secure storage - session cannot be restored");
-            // Clear the orphaned cache entry
-            let _ = crate::storage::database::clear_session_cache();
+            Ok(None) => {
+                // The keychain entry is gone (keychain reset, profile migrated): without
+                // the token nothing can be sent, so a new sign-in is needed, online or not
+                log::warn!("Device token missing from secure storage - sign-in required");
+                let _ = crate::storage::database::clear_session_cache();
+                crate::sampling::stop_services().await;
+                return Ok(AuthStatus {
+                    is_authenticated: false,
+                    email: Some(cache_entry.email),
+                    device_id: None,
+                    two_factor: None,
+                });
+            }
+            Err(e) => {
+                // Keychain locked or access denied: keep the cache so the session is
+                // restored once secure storage is readable again
+                log::warn!("Secure storage unavailable, session not restored this time: {}", e);
+            }
         }
     }
     
@@ -921,6 +1225,7 @@ pub async fn get_auth_status(
         is_authenticated: false,
         email: None,
         device_id: None,
+        two_factor: None,
     })
 }
 
@@ -935,49 +1240,39 @@ pub struct DeviceTokenResponse {
 /// This is used by the frontend to connect to authenticated SSE endpoints
 #[tauri::command]
 pub async fn get_device_token(
-    state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<DeviceTokenResponse, String> {
-    let app_state = state.lock().await;
+    state: State<'_, StateHandle>,
+) -> Result<DeviceTokenResponse, AgentError> {
+    let app_state = state.snapshot();
     
     Ok(DeviceTokenResponse {
-        device_token: app_state.device_token.clone(),
-        server_url: app_state.server_url.clone(),
+        device_token: app_state.device_token,
+        server_url: app_state.server_url,
     })
 }
 
 /// Helper function to restore session data to memory and return authenticated status
 /// Also checks backend for active work session and syncs local state
 async fn restore_session_to_memory(
-    state: State<'_, Arc<Mutex<AppState>>>,
+    state: State<'_, StateHandle>,
     app_handle: tauri::AppHandle,
     device_token: String,
     email: String,
     device_id: String,
     server_url: String,
     employee_id: Option<String>,
-) -> Result<AuthStatus, String> {
-    let mut app_state = state.lock().await;
-    
-    // Restore ALL session data to memory
-    app_state.device_token = Some(device_token.clone());
-    app_state.email = Some(email.clone());
-    app_state.device_id = Some(device_id.clone());
-    app_state.server_url = Some(server_url.clone());
-    app_state.employee_id = employee_id.clone();
-    
-    drop(app_state); // Release lock
-
-    // Sync device token to global app state for background services
-    if let Some(ref emp_id) = employee_id {
-        if let Err(e) = crate::storage::sync_device_token_to_global(
-            device_token.clone(),
-            device_id.clone(),
-            email.clone(),
-            server_url.clone(),
-            emp_id.clone(),
-        ).await {
-            log::error!("Failed to sync device token to global state: {}", e);
-        }
+) -> Result<AuthStatus, AgentError> {
+    // Restore ALL session data to memory (shared with background services)
+    if let Err(e) = state.set_session(SessionCredentials {
+        device_token: device_token.clone(),
+        device_id: device_id.clone(),
+        email: email.clone(),
+        server_url: server_url.clone(),
+        employee_id: employee_id.clone(),
+    }).await {
+        log::error!("Failed to restore session to app state: {}", e);
+    }
+
+    if employee_id.is_some() {
 
         // Clear any existing active sessions to ensure clean state
         if let Err(e) = crate::storage::work_session::clear_all_active_sessions().await {
@@ -1040,6 +1335,7 @@ async fn restore_session_to_memory(
         is_authenticated: true,
         email: Some(email),
         device_id: Some(device_id),
+        two_factor: None,
     })
 }
 
@@ -1047,19 +1343,15 @@ async fn restore_session_to_memory(
 // Returns Ok(true) if token is valid
 // Returns Ok(false) if token is explicitly invalid (server rejected it)
 // Returns Err if there was a network/connectivity issue (caller can decide to allow offline access)
-async fn validate_token_with_server(server_url: &str, token: &str) -> Result<bool, String> {
-    // Add timeout to prevent hanging
-    let client = reqwest::Client::builder()
-        .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-        .timeout(std::time::Duration::from_secs(10))
-        .connect_timeout(std::time::Duration::from_secs(5))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+async fn validate_token_with_server(server_url: &str, token: &str) -> Result<bool, AgentError> {
+    let client = crate::api::client::shared_client();
     
     let url = format!("{}/api/auth/validate", server_url.trim_end_matches('/'));
     
     match client
         .get(&url)
+        // Add timeout to prevent hanging
+        .timeout(std::time::Duration::from_secs(10))
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await
@@ -1084,102 +1376,236 @@ async fn validate_token_with_server(server_url: &str, token: &str) -> Result<boo
             // Network error - return Err so caller can decide to allow offline access
             log::warn!("Network error during token validation: {}", e);
             if e.is_connect() || e.is_timeout() {
-                Err(format!("Network connectivity issue: {}", e))
+                Err(AgentError::Offline(format!("Network connectivity issue: {}", e)))
             } else {
                 // Other errors (DNS, SSL, etc.) - also treat as connectivity issue
-                Err(format!("Network error: {}", e))
+                Err(AgentError::Network(format!("Network error: {}", e)))
             }
         }
     }
 }
 
 #[tauri::command]
-pub async fn clear_local_database() -> Result<(), String> {
+pub async fn clear_local_database() -> Result<(), AgentError> {
+    let result = clear_local_database_impl().await;
+    audit_log::record("clear_local_database", &result);
+    result
+}
+
+async fn clear_local_database_impl() -> Result<(), AgentError> {
     log::info!("Clearing local database...");
     let conn = crate::storage::database::get_connection()
-        .map_err(|e| format!("Failed to get database connection: {}", e))?;
+        .map_err(|e| AgentError::Storage(format!("Failed to get database connection: {}", e)))?;
     
     // Clear all tables
     conn.execute("DELETE FROM app_usage_sessions", [])
-        .map_err(|e| format!("Failed to clear app_usage_sessions: {}", e))?;
+        .map_err(|e| AgentError::Storage(format!("Failed to clear app_usage_sessions: {}", e)))?;
+    
+    conn.execute("DELETE FROM app_usage_window_segments", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear app_usage_window_segments: {}", e)))?;
+    conn.execute("DELETE FROM app_usage_hourly", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear app_usage_hourly: {}", e)))?;
+    conn.execute("DELETE FROM app_usage_daily", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear app_usage_daily: {}", e)))?;
     
+    conn.execute("DELETE FROM pause_segments", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear pause_segments: {}", e)))?;
     conn.execute("DELETE FROM work_sessions", [])
-        .map_err(|e| format!("Failed to clear work_sessions: {}", e))?;
+        .map_err(|e| AgentError::Storage(format!("Failed to clear work_sessions: {}", e)))?;
+    
+    conn.execute("DELETE FROM recent_sessions_cache", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear recent_sessions_cache: {}", e)))?;
     
     conn.execute("DELETE FROM offline_queue", [])
-        .map_err(|e| format!("Failed to clear offline_queue: {}", e))?;
+        .map_err(|e| AgentError::Storage(format!("Failed to clear offline_queue: {}", e)))?;
 
     // Clear event and heartbeat queues to prevent residual sends
     conn.execute("DELETE FROM event_queue", [])
-        .map_err(|e| format!("Failed to clear event_queue: {}", e))?;
+        .map_err(|e| AgentError::Storage(format!("Failed to clear event_queue: {}", e)))?;
     conn.execute("DELETE FROM heartbeat_queue", [])
-        .map_err(|e| format!("Failed to clear heartbeat_queue: {}", e))?;
+        .map_err(|e| AgentError::Storage(format!("Failed to clear heartbeat_queue: {}", e)))?;
+    conn.execute("DELETE FROM activity_text", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear activity_text: {}", e)))?;
+    conn.execute("DELETE FROM session_search", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear session_search: {}", e)))?;
+    conn.execute("DELETE FROM daily_report_cache", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear daily_report_cache: {}", e)))?;
+    conn.execute("DELETE FROM idle_periods", [])
+        .map_err(|e| AgentError::Storage(format!("Failed to clear idle_periods: {}", e)))?;
     
     // Reset auto-increment counters
     conn.execute("DELETE FROM sqlite_sequence WHERE name IN ('app_usage_sessions', 'work_sessions', 'offline_queue', 'event_queue', 'heartbeat_queue')", [])
-        .map_err(|e| format!("Failed to reset auto-increment counters: {}", e))?;
+        .map_err(|e| AgentError::Storage(format!("Failed to reset auto-increment counters: {}", e)))?;
 
     log::info!("Local database cleared successfully - all tables and sequences reset");
     
     Ok(())
 }
 
-#[tauri::command]
-pub async fn get_recent_sessions(state: State<'_, Arc<Mutex<AppState>>>) -> Result<serde_json::Value, String> {
-    let (server_url, device_token) = {
-        let app_state = state.lock().await;
-        (app_state.server_url.clone(), app_state.device_token.clone())
-    };
+fn parse_date_param(name: &str, value: Option<String>) -> Result<Option<chrono::NaiveDate>, AgentError> {
+    value
+        .map(|v| {
+            chrono::NaiveDate::parse_from_str(&v, "%Y-%m-%d")
+                .map_err(|e| AgentError::Internal(format!("Invalid {} '{}': {}", name, v, e)))
+        })
+        .transpose()
+}
 
-    if let (Some(server_url), Some(device_token)) = (server_url, device_token) {
-        let client = reqwest::Client::builder()
-            .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-            .build()
-            .map_err(|e| format!("Failed to build client: {}", e))?;
-        
-        // Call real API to get recent sessions
-        let url = format!("{}/api/employees/sessions/recent", server_url);
-        
-        match client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", device_token))
-            .send()
-            .await
-        {
-            Ok(response) => {
-                if response.status().is_success() {
-                    match response.json::<serde_json::Value>().await {
-                        Ok(sessions_data) => {
-                            return Ok(sessions_data);
-                        }
-                        Err(e) => {
-                            log::error!("Failed to parse sessions response: {}", e);
-                        }
-                    }
-                } else {
-                    log::error!("API request failed with status: {}", response.status());
-                }
+/// Fetch recent sessions from the backend (None if unreachable)
+async fn fetch_backend_recent_sessions(
+    server_url: &str,
+    device_token: &str,
+    query: &crate::storage::recent_sessions::SessionQuery,
+) -> Option<Vec<crate::storage::recent_sessions::RecentSession>> {
+    let client = crate::api::client::shared_client();
+
+    let mut url = format!("{}/api/employees/sessions/recent", server_url);
+    let mut params = Vec::new();
+    if let Some(start) = query.start_date {
+        params.push(format!("startDate={}", start));
+    }
+    if let Some(end) = query.end_date {
+        params.push(format!("endDate={}", end));
+    }
+    if !params.is_empty() {
+        url = format!("{}?{}", url, params.join("&"));
+    }
+
+    match client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", device_token))
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => match response.json::<serde_json::Value>().await {
+            Ok(sessions_data) => {
+                let sessions = sessions_data
+                    .get("sessions")
+                    .and_then(|s| s.as_array())
+                    .map(|sessions| {
+                        sessions
+                            .iter()
+                            .filter_map(crate::storage::recent_sessions::parse_backend_session)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some(sessions)
             }
             Err(e) => {
-                log::error!("Failed to fetch sessions from API: {}", e);
+                log::error!("Failed to parse sessions response: {}", e);
+                None
             }
+        },
+        Ok(response) => {
+            log::error!("API request failed with status: {}", response.status());
+            None
+        }
+        Err(e) => {
+            log::error!("Failed to fetch sessions from API: {}", e);
+            None
         }
-        
-        // Return empty sessions if API call fails
-        return Ok(serde_json::json!({
-            "sessions": []
-        }));
     }
+}
+
+/// Recent work sessions, newest first. Backend sessions are cached locally and
+/// merged with local sessions that haven't been synced, so this also works offline.
+#[tauri::command]
+pub async fn get_recent_sessions(
+    state: State<'_, StateHandle>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<crate::storage::recent_sessions::SessionPage, AgentError> {
+    use crate::storage::recent_sessions::{self, SessionQuery, SessionSource};
+
+    let (server_url, device_token) = {
+        let app_state = state.snapshot();
+        (app_state.server_url, app_state.device_token)
+    };
+
+    let (Some(server_url), Some(device_token)) = (server_url, device_token) else {
+        return Err(AgentError::NotAuthenticated);
+    };
+
+    let query = SessionQuery::new(
+        page,
+        page_size,
+        parse_date_param("start_date", start_date)?,
+        parse_date_param("end_date", end_date)?,
+    );
+
+    let (backend_sessions, source) = match fetch_backend_recent_sessions(&server_url, &device_token, &query).await {
+        Some(sessions) => {
+            if let Err(e) = recent_sessions::cache_sessions(&sessions) {
+                log::warn!("Failed to cache recent sessions: {}", e);
+            }
+            (sessions, SessionSource::Backend)
+        }
+        None => {
+            let cached = recent_sessions::get_cached_sessions().unwrap_or_else(|e| {
+                log::warn!("Failed to read cached recent sessions: {}", e);
+                Vec::new()
+            });
+            (cached, SessionSource::Cache)
+        }
+    };
+
+    let local_sessions = recent_sessions::get_local_sessions().unwrap_or_else(|e| {
+        log::warn!("Failed to read local work sessions: {}", e);
+        Vec::new()
+    });
 
-    Err("Not authenticated".to_string())
+    let merged = recent_sessions::merge_sessions(backend_sessions, local_sessions);
+    Ok(recent_sessions::paginate(merged, &query, source))
 }
 
+/// Search local work history (app names, window titles, domains and session
+/// notes), newest first. Dates are local `YYYY-MM-DD` days, both inclusive.
 #[tauri::command]
-pub async fn accept_consent(version: String) -> Result<(), String> {
+pub async fn search_sessions(
+    query: String,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<crate::storage::session_search::SessionSearchHit>, AgentError> {
+    use crate::storage::session_search;
+
+    let start = parse_date_param("start_date", start_date)?.map(crate::utils::time::local_midnight);
+    let end = parse_date_param("end_date", end_date)?.map(|day| crate::utils::time::local_midnight(day + chrono::Duration::days(1)));
+    let limit = limit.unwrap_or(session_search::DEFAULT_SEARCH_LIMIT).clamp(1, 500);
+
+    tokio::task::spawn_blocking(move || {
+        let conn = crate::storage::database::get_connection()?;
+        session_search::search(&conn, &query, start, end, limit)
+    })
+    .await
+    .map_err(|e| AgentError::Internal(e.to_string()))?
+    .map_err(|e| AgentError::Storage(e.to_string()))
+}
+
+/// Attach a note to a local work session (an empty note clears it)
+#[tauri::command]
+pub async fn set_session_note(session_id: i64, note: String) -> Result<(), AgentError> {
+    let result = crate::storage::work_session::set_note(session_id, &note)
+        .await
+        .map_err(|e| AgentError::Storage(e.to_string()));
+    audit_log::record("set_session_note", &result);
+    result
+}
+
+#[tauri::command]
+pub async fn accept_consent(version: String) -> Result<(), AgentError> {
+    let result = accept_consent_impl(version).await;
+    audit_log::record("accept_consent", &result);
+    result
+}
+
+async fn accept_consent_impl(version: String) -> Result<(), AgentError> {
     // Initialize database first
     if let Err(e) = crate::storage::database::init().await {
         log::error!("Failed to initialize database: {}", e);
-        return Err(format!("Failed to initialize database: {}", e));
+        return Err(AgentError::Storage(format!("Failed to initialize database: {}", e)));
     }
     
     match consent::accept_consent(&version).await {
@@ -1188,13 +1614,13 @@ pub async fn accept_consent(version: String) -> Result<(), String> {
         }
         Err(e) => {
             log::error!("Failed to accept consent: {}", e);
-            Err(format!("Failed to accept consent: {}", e))
+            Err(AgentError::Storage(format!("Failed to accept consent: {}", e)))
         }
     }
 }
 
 #[tauri::command]
-pub async fn get_consent_status() -> Result<ConsentStatus, String> {
+pub async fn get_consent_status() -> Result<ConsentStatus, AgentError> {
     // Initialize database first with timeout
     let db_init_result = tokio::time::timeout(
         std::time::Duration::from_secs(5),
@@ -1207,11 +1633,11 @@ pub async fn get_consent_status() -> Result<ConsentStatus, String> {
         }
         Ok(Err(e)) => {
             log::error!("Failed to initialize database: {}", e);
-            return Err(format!("Failed to initialize database: {}", e));
+            return Err(AgentError::Storage(format!("Failed to initialize database: {}", e)));
         }
         Err(_) => {
             log::error!("Timeout initializing database");
-            return Err("Database initialization timeout".to_string());
+            return Err(AgentError::Timeout("Database initialization timeout".to_string()));
         }
     }
     
@@ -1229,87 +1655,120 @@ pub async fn get_consent_status() -> Result<ConsentStatus, String> {
         }),
         Ok(Err(e)) => {
             log::error!("Failed to get consent status: {}", e);
-            Err(format!("Failed to get consent status: {}", e))
+            Err(AgentError::Storage(format!("Failed to get consent status: {}", e)))
+        }
+        Err(_) => {
+            log::error!("Timeout getting consent status");
+            Err(AgentError::Timeout("Consent status check timeout".to_string()))
+        }
+    }
+}
+
+/// Send the clock_in event for local session `session_id` to the backend;
+/// the local session is ended again if the backend rejects it
+async fn send_clock_in_event(session_id: i64) -> Result<(), AgentError> {
+    let client = match crate::api::client::ApiClient::new().await {
+        Ok(client) => client,
+        Err(e) => return Err(AgentError::Internal(format!("Failed to create API client: {}", e))),
+    };
+    
+    // Coarse office/remote location, only if the policy asks and the employee consented
+    let location = crate::location::location_for_clock_event().await;
+    let event_data = serde_json::json!({
+        "events": [{
+            "type": "clock_in",
+            "timestamp": crate::utils::time::event_timestamp(),
+            "data": {
+                "session_id": session_id,
+                "source": "desktop_agent",
+                "location": location
+            }
+        }]
+    });
+
+    let response = client
+        .post_with_auth("/api/ingest/events", &event_data)
+        .await
+        .map_err(AgentError::from)?;
+
+    let status = response.status();
+    
+    // Handle 402 Payment Required - license expired or invalid
+    if status == reqwest::StatusCode::PAYMENT_REQUIRED {
+        // End the local session since clock in was rejected
+        if let Err(e) = crate::storage::work_session::end_session().await {
+            log::error!("Failed to end local session after license check failure: {}", e);
+        }
+        
+        let error_body = response.text().await.unwrap_or_else(|_| String::new());
+        log::warn!("Clock in rejected: License invalid (402) - {}", error_body);
+        
+        // Try to parse error message from response
+        let error_message = if let Ok(json) = serde_json::from_str::<serde_json::Value>(&error_body) {
+            if let Some(error) = json.get("error").and_then(|v| v.as_str()) {
+                error.to_string()
+            } else {
+                String::from("Your license is expired or invalid. Please contact your administrator.")
+            }
+        } else {
+            String::from("Your license is expired or invalid. Please contact your administrator.")
+        };
+        
+        return Err(AgentError::LicenseRequired(error_message));
+    }
+    
+    if !status.is_success() {
+        // End the local session since clock in failed
+        if let Err(e) = crate::storage::work_session::end_session().await {
+            log::error!("Failed to end local session after clock in failure: {}", e);
         }
-        Err(_) => {
-            log::error!("Timeout getting consent status");
-            Err("Consent status check timeout".to_string())
+        
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(AgentError::from_status(status, format!("Clock in failed: {}", error_text)));
+    }
+
+    // The backend's session id marks the local session as known to it (see recent_sessions)
+    if let Ok(body) = response.json::<serde_json::Value>().await {
+        let backend_id = match body.get("sessionId").or_else(|| body.get("session_id")) {
+            Some(serde_json::Value::String(id)) => Some(id.clone()),
+            Some(serde_json::Value::Number(id)) => Some(id.to_string()),
+            _ => None,
+        };
+        if let Some(backend_id) = backend_id {
+            if let Err(e) = crate::storage::work_session::set_backend_id(session_id, &backend_id) {
+                log::warn!("Failed to store backend session id: {}", e);
+            }
         }
     }
+
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn clock_in(state: State<'_, Arc<Mutex<AppState>>>, app_handle: tauri::AppHandle) -> Result<(), String> {
+pub async fn clock_in(state: State<'_, StateHandle>, app_handle: tauri::AppHandle) -> Result<(), AgentError> {
+    let result = clock_in_impl(state, app_handle).await;
+    audit_log::record("clock_in", &result);
+    result
+}
+
+async fn clock_in_impl(state: State<'_, StateHandle>, app_handle: tauri::AppHandle) -> Result<(), AgentError> {
+    // Clocking in is blocked while a mandatory update is overdue
+    crate::update_lockout::ensure_not_locked().await?;
     
     // ✅ 1. Save to LOCAL database first
     let session_id = crate::storage::work_session::start_session().await
-        .map_err(|e| format!("Failed to start local session: {}", e))?;
+        .map_err(|e| AgentError::Storage(format!("Failed to start local session: {}", e)))?;
     
     log::info!("Clock in: Local session started with ID {}", session_id);
     
     let (server_url, device_token) = {
-        let app_state = state.lock().await;
-        (app_state.server_url.clone(), app_state.device_token.clone())
+        let app_state = state.snapshot();
+        (app_state.server_url, app_state.device_token)
     };
 
     if let (Some(_server_url), Some(_device_token)) = (server_url, device_token) {
         // ✅ 2. Send clock_in event to REMOTE backend
-        let client = match crate::api::client::ApiClient::new().await {
-            Ok(client) => client,
-            Err(e) => return Err(format!("Failed to create API client: {}", e)),
-        };
-        
-        let event_data = serde_json::json!({
-            "events": [{
-                "type": "clock_in",
-                "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-                "data": {
-                    "session_id": session_id,
-                    "source": "desktop_agent"
-                }
-            }]
-        });
-
-        let response = client
-            .post_with_auth("/api/ingest/events", &event_data)
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        let status = response.status();
-        
-        // Handle 402 Payment Required - license expired or invalid
-        if status == reqwest::StatusCode::PAYMENT_REQUIRED {
-            // End the local session since clock in was rejected
-            if let Err(e) = crate::storage::work_session::end_session().await {
-                log::error!("Failed to end local session after license check failure: {}", e);
-            }
-            
-            let error_body = response.text().await.unwrap_or_else(|_| String::new());
-            log::warn!("Clock in rejected: License invalid (402) - {}", error_body);
-            
-            // Try to parse error message from response
-            let error_message = if let Ok(json) = serde_json::from_str::<serde_json::Value>(&error_body) {
-                if let Some(error) = json.get("error").and_then(|v| v.as_str()) {
-                    error.to_string()
-                } else {
-                    String::from("Your license is expired or invalid. Please contact your administrator.")
-                }
-            } else {
-                String::from("Your license is expired or invalid. Please contact your administrator.")
-            };
-            
-            return Err(error_message);
-        }
-        
-        if !status.is_success() {
-            // End the local session since clock in failed
-            if let Err(e) = crate::storage::work_session::end_session().await {
-                log::error!("Failed to end local session after clock in failure: {}", e);
-            }
-            
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(format!("Clock in failed: {}", error_text));
-        }
+        send_clock_in_event(session_id).await?;
 
         // ✅ 3. Start background services now that user is clocked in
         log::info!("Clock in: Starting background services");
@@ -1322,254 +1781,108 @@ pub async fn clock_in(state: State<'_, Arc<Mutex<AppState>>>, app_handle: tauri:
         crate::sampling::license_monitor::start_license_monitor().await;
 
     } else {
-        return Err("Not authenticated. Please login first.".to_string());
+        return Err(AgentError::NotAuthenticated);
     }
 
     Ok(())
 }
 
+/// Continue the session that is active on the backend but not here (the user
+/// confirmed the `session-diverged` prompt)
 #[tauri::command]
-pub async fn clock_out(state: State<'_, Arc<Mutex<AppState>>>) -> Result<(), String> {
-    
-    log::info!("Clock out: Ending local session");
-    
-    // End local app usage session
-    if let Err(e) = crate::storage::app_usage::end_current_session().await {
-        log::warn!("Failed to end current app session: {}", e);
-    }
-    
-    // Send a final app focus event to ensure any open app usage entries are closed on the backend
-    // This prevents the issue where the last app usage entry remains open with endTime: null
-    if let Ok(Some(current_app)) = crate::commands::get_current_app().await {
-        log::info!("Clock out: Sending final app focus event to close open entries");
-        
-        let event_data = serde_json::json!({
-            "app_name": current_app.name,
-            "app_id": current_app.app_id,
-            "window_title": current_app.window_title,
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        });
+pub async fn adopt_backend_session(state: State<'_, StateHandle>, app_handle: tauri::AppHandle) -> Result<(), AgentError> {
+    let result = adopt_backend_session_impl(state, app_handle).await;
+    audit_log::record("adopt_backend_session", &result);
+    result
+}
 
-        match crate::sampling::send_event_to_backend("app_focus", &event_data).await {
-            Ok(_) => {
-                log::info!("✓ Final app focus event sent to close open entries");
-            }
-            Err(e) => {
-                log::warn!("Failed to send final app focus event: {}", e);
-            }
-        }
+async fn adopt_backend_session_impl(state: State<'_, StateHandle>, app_handle: tauri::AppHandle) -> Result<(), AgentError> {
+    if state.snapshot().device_token.is_none() {
+        return Err(AgentError::NotAuthenticated);
     }
-    
-    // ✅ 1. Process any remaining queued events before stopping services
-    log::info!("Clock out: Processing remaining queued events");
-    
-    // Process pending events
-    if let Ok(events) = crate::storage::offline_queue::get_pending_events().await {
-        for event in events {
-            match crate::sampling::send_event_to_backend(&event.event_type, &event.event_data).await {
-                Ok(_) => {
-                    let _ = crate::storage::offline_queue::mark_event_processed(event.id).await;
-                    log::info!("Clock out: Processed queued event {}", event.id);
-                }
-                Err(e) => {
-                    log::warn!("Clock out: Failed to send queued event {}: {}", event.id, e);
-                    let _ = crate::storage::offline_queue::mark_event_failed(event.id).await;
-                }
-            }
-        }
+    if crate::storage::work_session::get_current_session().await?.is_some() {
+        return Ok(());
     }
-    
-    // Process pending heartbeats
-    if let Ok(heartbeats) = crate::storage::offline_queue::get_pending_heartbeats().await {
-        for heartbeat in heartbeats {
-            match crate::sampling::send_heartbeat_to_backend(&heartbeat.heartbeat_data).await {
-                Ok(_) => {
-                    let _ = crate::storage::offline_queue::mark_heartbeat_processed(heartbeat.id).await;
-                    log::info!("Clock out: Processed queued heartbeat {}", heartbeat.id);
-                }
-                Err(e) => {
-                    log::warn!("Clock out: Failed to send queued heartbeat {}: {}", heartbeat.id, e);
-                    let _ = crate::storage::offline_queue::mark_heartbeat_failed(heartbeat.id).await;
-                }
-            }
-        }
+    let backend = crate::storage::work_session::fetch_backend_session().await?;
+    if !backend.is_active {
+        return Err(AgentError::Internal("No active session on the server".to_string()));
     }
-    
-    // ✅ 2. Stop background services after processing all queued events
-    crate::sampling::stop_services().await;
-    log::info!("Clock out: Background services stopped");
-    
-    // Stop license monitoring service
-    crate::sampling::license_monitor::stop_license_monitor().await;
-    log::info!("Clock out: License monitoring service stopped");
-
-    // Reset idle state to prevent stale idle events
-    crate::sampling::reset_idle_state();
-    
-    // ✅ 3. End LOCAL session
-    crate::storage::work_session::end_session().await
-        .map_err(|e| format!("Failed to end local session: {}", e))?;
-    
-    
-    let (server_url, device_token) = {
-        let app_state = state.lock().await;
-        (app_state.server_url.clone(), app_state.device_token.clone())
-    };
-
-    if let (Some(_server_url), Some(_device_token)) = (server_url, device_token) {
-        // ✅ 2. Send clock_out event to REMOTE backend
-        let client = match crate::api::client::ApiClient::new().await {
-            Ok(client) => client,
-            Err(e) => return Err(format!("Failed to create API client: {}", e)),
-        };
-        
-        let event_data = serde_json::json!({
-            "events": [{
-                "type": "clock_out",
-                "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-                "data": {
-                    "source": "desktop_agent"
-                }
-            }]
-        });
+    let started_at = backend.started_at.unwrap_or_else(crate::utils::clock::now);
+    crate::storage::work_session::start_session_at(started_at).await
+        .map_err(|e| AgentError::Storage(format!("Failed to start local session: {}", e)))?;
+    log::info!("Adopted active backend session started at {}", started_at);
 
-        let response = client
-            .post_with_auth("/api/ingest/events", &event_data)
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
+    tokio::spawn(async move {
+        crate::sampling::start_all_background_services(app_handle).await;
+    });
+    crate::sampling::license_monitor::start_license_monitor().await;
+    Ok(())
+}
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(format!("Clock out failed: {}", error_text));
-        }
-        
+#[tauri::command]
+pub async fn clock_out(state: State<'_, StateHandle>) -> Result<(), AgentError> {
+    let result = clock_out_impl(state).await;
+    audit_log::record("clock_out", &result);
+    result
+}
 
-    } else {
-        return Err("Not authenticated. Please login first.".to_string());
+async fn clock_out_impl(state: State<'_, StateHandle>) -> Result<(), AgentError> {
+    if state.snapshot().device_token.is_none() {
+        return Err(AgentError::NotAuthenticated);
     }
-
-    Ok(())
+    crate::session_manager::end_session(crate::session_manager::EndReason::Manual).await
 }
 
 #[tauri::command]
-pub async fn get_work_session(state: State<'_, Arc<Mutex<AppState>>>) -> Result<WorkSessionInfo, String> {
-    let (server_url, device_token, employee_id) = {
-        let app_state = state.lock().await;
-        (app_state.server_url.clone(), app_state.device_token.clone(), app_state.employee_id.clone())
-    };
+pub async fn get_work_session(state: State<'_, StateHandle>) -> Result<WorkSessionInfo, AgentError> {
+    let app_state = state.snapshot();
 
-    if let (Some(_server_url), Some(_device_token), Some(_employee_id)) = (server_url, device_token, employee_id) {
-        // Fetch current work session from backend
-        let client = match crate::api::client::ApiClient::new().await {
-            Ok(client) => client,
-            Err(_) => {
-                // If API client creation fails, check local database
-                log::warn!("Failed to create API client, using local state");
-                if let Ok(Some(local_session)) = crate::storage::work_session::get_current_session().await {
-                    let current_app = match get_current_app().await {
-                        Ok(Some(app)) => Some(app.name),
-                        _ => None
-                    };
-                    return Ok(WorkSessionInfo {
-                        is_active: true,
-                        started_at: Some(local_session.started_at.to_rfc3339()),
-                        current_app,
-                        idle_time_seconds: 0,
-                        is_paused: false,
-                    });
-                }
-                return Ok(WorkSessionInfo {
-                    is_active: false,
-                    started_at: None,
-                    current_app: Some("TrackEx Agent".to_string()),
-                    idle_time_seconds: 0,
-                    is_paused: false,
-                });
+    if app_state.server_url.is_some() && app_state.device_token.is_some() && app_state.employee_id.is_some() {
+        // Answer from the local work_sessions table; the backend is reconciled in the
+        // background and a "session-diverged" event is emitted if they disagree
+        crate::storage::work_session::schedule_reconciliation();
+
+        let local_session = match crate::storage::work_session::get_current_session().await {
+            Ok(session) => session,
+            Err(e) => {
+                log::warn!("Failed to read local work session: {}", e);
+                None
             }
         };
-        
-        // Get today's date range in Z format (easier to parse)
-        let today = chrono::Utc::now().date_naive();
-        let start_date = today.and_hms_opt(0, 0, 0).unwrap().and_utc().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-        let end_date = today.and_hms_opt(23, 59, 59).unwrap().and_utc().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-        
-        let url_with_params = format!("/api/devices/sessions?startDate={}&endDate={}", start_date, end_date);
-       
-        match client.get_with_auth(&url_with_params).await {
-            Ok(response) if response.status().is_success() => {
-                if let Ok(sessions_data) = response.json::<serde_json::Value>().await {
-                    if let Some(sessions) = sessions_data.get("sessions").and_then(|s| s.as_array()) {
-                        // Find active session (no clock_out)
-                        for session in sessions {
-                            let clock_out = session.get("clockOut");
-                            let is_active = clock_out.is_none() || clock_out.and_then(|v| v.as_str()).is_none() || clock_out == Some(&serde_json::Value::Null);
-                            if is_active {
-                                // Active session found
-                                let started_at = session.get("clockIn")
-                                    .and_then(|v| v.as_str())
-                                    .map(|s| s.to_string());
-                                
-                                // Get current app for active session
-                                let current_app = match get_current_app().await {
-                                    Ok(Some(app)) => Some(app.name),
-                                    _ => None
-                                };
-                                return Ok(WorkSessionInfo {
-                                    is_active: true,
-                                    started_at,
-                                    current_app,
-                                    idle_time_seconds: 0,
-                                    is_paused: false,
-                                });
-                            }
-                        }
-                    }
-                }
-            }
-            _ => {
-                // If we can't fetch from backend, fall back to local state
-                log::warn!("Failed to fetch work session from backend, using local state");
-                
-                // Check local SQLite database for active session
-                if let Ok(Some(local_session)) = crate::storage::work_session::get_current_session().await {
-                    
-                    // Get current app for active session
-                    let current_app = match get_current_app().await {
-                        Ok(Some(app)) => Some(app.name),
-                        _ => None
-                    };
-                    
-                    return Ok(WorkSessionInfo {
-                        is_active: true,
-                        started_at: Some(local_session.started_at.to_rfc3339()),
-                        current_app,
-                        idle_time_seconds: 0,
-                        is_paused: false,
-                    });
-                }
-            }
+
+        if let Some(local_session) = local_session {
+            // Get current app for active session
+            let current_app = match get_current_app().await {
+                Ok(Some(app)) => Some(app.name),
+                _ => None
+            };
+            return Ok(WorkSessionInfo {
+                is_active: true,
+                started_at: Some(local_session.started_at.to_rfc3339()),
+                current_app,
+                idle_time_seconds: 0,
+                is_paused: app_state.is_paused,
+                paused_seconds: crate::storage::pause_segments::session_paused_seconds(local_session.id).unwrap_or(0),
+            });
         }
     }
     
-    // No active session or failed to fetch
+    // No active session
     Ok(WorkSessionInfo {
         is_active: false,
         started_at: None,
         current_app: Some("TrackEx Agent".to_string()),
         idle_time_seconds: 0,
         is_paused: false,
+        paused_seconds: 0,
     })
 }
 
-
-
-
-
 #[tauri::command]
 pub async fn get_tracking_status(
-    state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<TrackingStatus, String> {
-    let app_state = state.lock().await;
+    state: State<'_, StateHandle>,
+) -> Result<TrackingStatus, AgentError> {
+    let app_state = state.snapshot();
     let is_authenticated = app_state.device_token.is_some();
     
     Ok(TrackingStatus {
@@ -1581,7 +1894,7 @@ pub async fn get_tracking_status(
 }
 
 #[tauri::command]
-pub async fn take_screenshot() -> Result<String, String> {
+pub async fn take_screenshot() -> Result<String, AgentError> {
     // Use the cross-platform screen capture module
     match crate::screenshots::screen_capture::capture_screen().await {
         Ok(base64_data) => {
@@ -1594,7 +1907,7 @@ pub async fn take_screenshot() -> Result<String, String> {
         }
         Err(e) => {
             log::error!("Failed to capture screenshot: {}", e);
-            Err(format!("Failed to capture screenshot: {}", e))
+            Err(format!("Failed to capture screenshot: {}", e).into())
         }
     }
 }
@@ -1637,7 +1950,7 @@ fn is_trackex_agent(app_name: &str, app_id: &str, window_title: Option<&str>) ->
 }
 
 #[tauri::command]
-pub async fn get_current_app() -> Result<Option<AppInfo>, String> {
+pub async fn get_current_app() -> Result<Option<AppInfo>, AgentError> {
     // Strategy: Return the focused app, but if TrackEx is focused, return the last non-TrackEx app.
     // This ensures the UI always shows what the user is actually working on, even when viewing TrackEx.
     
@@ -1661,6 +1974,13 @@ pub async fn get_current_app() -> Result<Option<AppInfo>, String> {
             .arg("-e")
             .arg("tell application \"System Events\" to get name of first window of first application process whose frontmost is true")
             .output();
+        
+        let pid = Command::new("osascript")
+            .arg("-e")
+            .arg("tell application \"System Events\" to get unix id of first application process whose frontmost is true")
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse::<u32>().ok());
             
         match (app_name_result, bundle_id_result) {
             (Ok(name_output), Ok(bundle_output)) => {
@@ -1678,44 +1998,38 @@ pub async fn get_current_app() -> Result<Option<AppInfo>, String> {
                 
                 if !name.is_empty() {
                     // Extract browser URL/domain if this is a browser
-                    let (url, domain) = {
-                        use crate::sampling::browser_url::extract_browser_url;
-                        use crate::api::employee_settings;
-                        use crate::utils::privacy::UrlSanitizer;
-                        
-                        let url_info = extract_browser_url(
-                            &name,
-                            &bundle_id,
-                            window_title.as_deref(), // Pass window title for domain extraction
-                            None, // No hwnd on macOS
-                        );
-                        
-                        // Apply browser domain only policy
-                        let browser_domain_only = employee_settings::is_browser_domain_only().await;
-                        let sanitizer = UrlSanitizer::new(browser_domain_only);
-                        
-                        if let Some(raw_url) = url_info.url.as_ref() {
-                            sanitizer.sanitize(Some(raw_url))
-                        } else if let Some(dom) = url_info.domain.as_ref() {
-                            (Some(dom.clone()), Some(dom.clone()))
-                        } else {
-                            (None, None)
-                        }
-                    };
+                    let url_info = crate::sampling::browser_url::extract_browser_url(
+                        &name,
+                        &bundle_id,
+                        window_title.as_deref(), // Pass window title for domain extraction
+                        None, // No hwnd on macOS
+                    );
+                    // Apply browser domain only / allowlist-only policy
+                    let (url, domain) = url_info.sanitized(&crate::api::employee_settings::get_url_sanitizer().await);
                     
+                    let details = pid
+                        .map(crate::sampling::process_info::details_for_pid)
+                        .unwrap_or_default();
                     let app_info = AppInfo {
                         name: name.to_string(),
                         app_id: bundle_id.to_string(),
-                        window_title: window_title.or_else(|| Some("Active Window".to_string())),
+                        window_title: crate::utils::privacy::recorded_window_title(window_title.as_deref(), domain.as_deref(), &name)
+                            .or_else(|| Some("Active Window".to_string())),
                         url,
                         domain,
+                        raw_domain: url_info.domain,
+                        raw_window_title: window_title,
+                        exe_path: details.exe_path,
+                        file_version: details.file_version,
+                        pid,
+                        virtual_desktop: crate::sampling::virtual_desktop::current_desktop(),
                     };
                     
                     // Check if this is the TrackEx Agent itself
                     let is_trackex = is_trackex_agent(&name, &bundle_id, None);
                     
-                    log::debug!("App detection (macOS): name='{}', id='{}', window_title={:?}, url={:?}, domain={:?}, is_trackex={}", 
-                        name, bundle_id, app_info.window_title, app_info.url, app_info.domain, is_trackex);
+                    log::debug!("App detection (macOS): name='{}', id='{}', domain={:?}, is_trackex={}", 
+                        name, bundle_id, app_info.domain, is_trackex);
                     
                     if is_trackex {
                         // Return the last non-TrackEx app instead
@@ -1891,45 +2205,35 @@ pub async fn get_current_app() -> Result<Option<AppInfo>, String> {
             let final_app_id = app_id.unwrap_or_else(|| format!("pid_{}", pid));
             
             // Extract browser URL if this is a browser app
-            let (url, domain) = {
-                use crate::sampling::browser_url::extract_browser_url;
-                use crate::api::employee_settings;
-                use crate::utils::privacy::UrlSanitizer;
-                
-                let url_info = extract_browser_url(
-                    &final_app_name,
-                    &final_app_id,
-                    Some(&window_title),
-                    Some(hwnd.0 as isize),
-                );
-                
-                // Apply browser domain only policy
-                let browser_domain_only = employee_settings::is_browser_domain_only().await;
-                let sanitizer = UrlSanitizer::new(browser_domain_only);
-                
-                if let Some(raw_url) = url_info.url.as_ref() {
-                    sanitizer.sanitize(Some(raw_url))
-                } else if let Some(domain) = url_info.domain.as_ref() {
-                    // Only have domain from title, use it as both
-                    (Some(domain.clone()), Some(domain.clone()))
-                } else {
-                    (None, None)
-                }
-            };
+            let url_info = crate::sampling::browser_url::extract_browser_url(
+                &final_app_name,
+                &final_app_id,
+                Some(&window_title),
+                Some(hwnd.0 as isize),
+            );
+            // Apply browser domain only / allowlist-only policy
+            let (url, domain) = url_info.sanitized(&crate::api::employee_settings::get_url_sanitizer().await);
             
+            let details = crate::sampling::process_info::details_for_pid(pid);
             let app_info = AppInfo {
                 name: final_app_name.clone(),
                 app_id: final_app_id.clone(),
-                window_title: Some(window_title.clone()),
+                window_title: crate::utils::privacy::recorded_window_title(Some(&window_title), domain.as_deref(), &final_app_name),
                 url,
                 domain,
+                raw_domain: url_info.domain,
+                raw_window_title: Some(window_title.clone()),
+                exe_path: details.exe_path,
+                file_version: details.file_version,
+                pid: Some(pid),
+                virtual_desktop: crate::sampling::virtual_desktop::current_desktop(),
             };
             
             // Check if this is the TrackEx Agent itself
             let is_trackex = is_trackex_agent(&final_app_name, &final_app_id, Some(&window_title));
             
-            log::debug!("App detection: name='{}', id='{}', title='{}', url={:?}, domain={:?}, is_trackex={}", 
-                final_app_name, final_app_id, window_title, app_info.url, app_info.domain, is_trackex);
+            log::debug!("App detection: name='{}', id='{}', domain={:?}, is_trackex={}", 
+                final_app_name, final_app_id, app_info.domain, is_trackex);
             
             if is_trackex {
                 // Return the last non-TrackEx app instead
@@ -1953,6 +2257,12 @@ pub async fn get_current_app() -> Result<Option<AppInfo>, String> {
             window_title: Some("Unknown Window".to_string()),
             url: None,
             domain: None,
+            raw_domain: None,
+            raw_window_title: None,
+            exe_path: None,
+            file_version: None,
+            pid: None,
+            virtual_desktop: None,
         }));
     }
 }
@@ -1962,34 +2272,61 @@ fn trim_nulls(s: &str) -> String {
 }
 
 
+/// Upload the last day of logs and a health report for support
 #[tauri::command]
-pub async fn send_diagnostics() -> Result<(), String> {
-    Ok(())
+pub async fn send_diagnostics(case_id: Option<String>) -> Result<(), AgentError> {
+    crate::api::diagnostics::upload_diagnostics(case_id.as_deref(), 24)
+        .await
+        .map_err(AgentError::from)
 }
 
 #[tauri::command]
-pub async fn get_permissions_status() -> Result<PermissionsStatus, String> {
+pub async fn get_permissions_status() -> Result<PermissionsStatus, AgentError> {
     Ok(crate::permissions::get_permissions_status().await)
 }
 
 #[tauri::command]
-pub async fn request_permissions() -> Result<(), String> {
+pub async fn request_permissions() -> Result<(), AgentError> {
     crate::permissions::request_permissions()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AgentError::from)
+}
+
+/// Show the macOS Accessibility prompt; returns whether access is granted
+#[tauri::command]
+pub async fn request_accessibility_permission() -> Result<bool, AgentError> {
+    crate::permissions::request_accessibility_permission()
+        .await
+        .map_err(AgentError::from)
+}
+
+/// Ask for Automation access to a browser so its tab URL can be captured
+#[tauri::command]
+pub async fn request_automation_permission(bundle_id: String) -> Result<crate::permissions::AutomationPermission, AgentError> {
+    Ok(crate::permissions::request_automation_permission(&bundle_id).await)
+}
+
+/// Open the Automation settings pane so a denied browser can be re-enabled
+#[tauri::command]
+pub async fn open_automation_settings() -> Result<(), AgentError> {
+    crate::permissions::open_automation_settings()
+        .await
+        .map_err(AgentError::from)
 }
 
 /// Trigger screen recording permission dialog by attempting actual screen capture
 /// This is more reliable than the ScreenCaptureAccess.request() API on macOS
 #[tauri::command]
-pub async fn trigger_screen_permission_dialog() -> Result<(), String> {
+pub async fn trigger_screen_permission_dialog() -> Result<(), AgentError> {
     #[cfg(target_os = "macos")]
     {
         use std::process::Command;
         
         log::info!("Triggering screen permission dialog via test screencapture...");
         
-        let temp_dir = std::env::temp_dir();
+        // Keep the test capture out of the shared temp dir
+        let temp_dir = crate::storage::screenshot_queue::get_temp_folder()
+            .map_err(|e| format!("Failed to prepare screenshot folder: {}", e))?;
         let test_file = temp_dir.join("trackex_permission_test.jpg");
         
         // Run screencapture - this WILL trigger the permission dialog if not granted
@@ -2021,7 +2358,7 @@ pub async fn trigger_screen_permission_dialog() -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn get_app_info() -> Result<serde_json::Value, String> {
+pub async fn get_app_info() -> Result<serde_json::Value, AgentError> {
     Ok(serde_json::json!({
         "name": "TrackEx Agent",
         "version": env!("CARGO_PKG_VERSION"),
@@ -2031,400 +2368,7 @@ pub async fn get_app_info() -> Result<serde_json::Value, String> {
 }
 
 #[tauri::command]
-pub async fn send_app_focus_event(
-    state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<String, String> {
-    let (server_url, device_token) = {
-        let app_state = state.lock().await;
-        (app_state.server_url.clone(), app_state.device_token.clone())
-    };
-
-    if let (Some(server_url), Some(device_token)) = (server_url, device_token) {
-        // Get current app
-        if let Ok(Some(app_info)) = get_current_app().await {
-            // Send app_focus event to backend
-            let client = reqwest::Client::builder()
-                .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-                .build()
-                .map_err(|e| format!("Failed to build client: {}", e))?;
-            let events_url = format!("{}/api/ingest/events", server_url.trim_end_matches('/'));
-            
-            let event_data = serde_json::json!({
-                "events": [{
-                    "type": "app_focus",
-                    "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-                    "data": {
-                        "app_name": app_info.name,
-                        "app_id": app_info.app_id,
-                        "window_title": app_info.window_title.unwrap_or_default()
-                    },
-                    "from": "send_app_focus_event"
-                }]
-            });
-
-            let response = client
-                .post(&events_url)
-                .header("Content-Type", "application/json")
-                .header("Authorization", format!("Bearer {}", device_token))
-                .json(&event_data)
-                .send()
-                .await;
-
-            match response {
-                Ok(resp) if resp.status().is_success() => {
-                    Ok(format!("App focus tracked: {}", app_info.name))
-                }
-                Ok(resp) => {
-                    log::error!("Failed to send app focus event: {}", resp.status());
-                    Err("Failed to send app focus event".to_string())
-                }
-                Err(e) => {
-                    log::error!("Error sending app focus event: {}", e);
-                    Err("Network error sending app focus event".to_string())
-                }
-            }
-        } else {
-            Err("Could not detect current app".to_string())
-        }
-    } else {
-        Err("Not authenticated".to_string())
-    }
-}
-
-#[tauri::command]
-pub async fn send_heartbeat(
-    state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<String, String> {
-    let (server_url, device_token) = {
-        let app_state = state.lock().await;
-        (app_state.server_url.clone(), app_state.device_token.clone())
-    };
-
-    if let (Some(server_url), Some(device_token)) = (server_url, device_token) {
-        // Get current app for heartbeat
-        let current_app = match get_current_app().await {
-            Ok(Some(app)) => Some(serde_json::json!({
-                "name": app.name,
-                "app_id": app.app_id,
-                "window_title": app.window_title.unwrap_or_default()
-            })),
-            _ => None
-        };
-
-        // Send heartbeat to backend
-        let client = reqwest::Client::builder()
-            .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-            .build()
-            .map_err(|e| format!("Failed to build client: {}", e))?;
-        let heartbeat_url = format!("{}/api/ingest/heartbeat", server_url.trim_end_matches('/'));
-        
-        // Get idle time and work session data for time calculations
-        let idle_time = crate::sampling::idle_detector::get_idle_time().await.unwrap_or(0);
-        let idle_threshold = crate::sampling::idle_detector::get_idle_threshold();
-        let is_idle = idle_time >= idle_threshold;
-
-        let now = chrono::Utc::now();
-
-        // Check if there's an active work session for time calculations
-        let session_active = crate::storage::work_session::is_session_active().await.unwrap_or(false);
-        
-        let (session_start, total_session_time, total_active_today, total_idle_today) = if session_active {
-            // Get session start time for time calculations
-            let session_start = crate::storage::work_session::get_session_start_time().await.unwrap_or_else(|_| now);
-            let total_session_time = (now - session_start).num_seconds();
-            
-            // Calculate cumulative active and idle time for today
-            let (cumulative_active_time, cumulative_idle_time) = crate::storage::work_session::get_today_time_totals().await.unwrap_or((0, 0));
-            
-            // Add current session time to totals
-            let current_session_active = if is_idle { 0 } else { total_session_time };
-            let current_session_idle = if is_idle { total_session_time } else { 0 };
-            
-            let total_active_today = cumulative_active_time + current_session_active;
-            let total_idle_today = cumulative_idle_time + current_session_idle;
-
-
-            (session_start, total_session_time, total_active_today, total_idle_today)
-        } else {
-            // No active session - use default values
-            (now, 0, 0, 0)
-        };
-
-        let heartbeat_data = serde_json::json!({
-            "timestamp": now.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-            "status": if is_idle { "idle" } else { "active" },
-            "currentApp": current_app,
-            "idle_time_seconds": idle_time,
-            "session_start_time": session_start.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-            "total_session_time_seconds": total_session_time,
-            "active_time_today_seconds": total_active_today,
-            "idle_time_today_seconds": total_idle_today,
-            "is_paused": crate::sampling::is_services_paused().await
-        });
-
-        let response = client
-            .post(&heartbeat_url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", device_token))
-            .json(&heartbeat_data)
-            .send()
-            .await;
-
-        match response {
-            Ok(resp) if resp.status().is_success() => {
-                Ok("Heartbeat sent".to_string())
-            }
-            Ok(resp) => {
-                log::error!("Failed to send heartbeat2: {}", resp.status());
-                Err("Failed to send heartbeat".to_string())
-            }
-            Err(e) => {
-                log::error!("Error sending heartbeat: {}", e);
-                Err("Network error sending heartbeat".to_string())
-            }
-        }
-    } else {
-        Err("Not authenticated".to_string())
-    }
-}
-
-#[tauri::command]
-pub async fn check_pending_jobs(
-    state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<String, String> {
-    let (server_url, device_token) = {
-        let app_state = state.lock().await;
-        (app_state.server_url.clone(), app_state.device_token.clone())
-    };
-
-    if let (Some(server_url), Some(device_token)) = (server_url, device_token) {
-        let client = reqwest::Client::builder()
-            .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-            .build()
-            .map_err(|e| format!("Failed to build client: {}", e))?;
-        let jobs_url = format!("{}/api/ingest/jobs", server_url.trim_end_matches('/'));
-        
-        match client
-            .get(&jobs_url)
-            .header("Authorization", format!("Bearer {}", device_token))
-            .send()
-            .await
-        {
-            Ok(response) if response.status().is_success() => {
-                if let Ok(jobs_data) = response.json::<serde_json::Value>().await {
-                    if let Some(jobs) = jobs_data.get("jobs").and_then(|j| j.as_array()) {
-                        for job in jobs {
-                            if let Some(job_type) = job.get("type").and_then(|t| t.as_str()) {
-                                if job_type == "screenshot" {
-                                    if let Some(job_id) = job.get("id").and_then(|id| id.as_str()) {
-                                        log::info!("Processing screenshot job: {}", job_id);
-                                        
-                                        let events_url = format!("{}/api/ingest/events", server_url.trim_end_matches('/'));
-                                        
-                                        // Helper macro for ISO 8601 timestamp with Z suffix (Zod datetime compatible)
-                                        macro_rules! iso_timestamp {
-                                            () => {
-                                                chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
-                                            };
-                                        }
-                                        
-                                        // Get device and employee info
-                                        let device_id = match crate::storage::get_device_id().await {
-                                            Ok(id) => id,
-                                            Err(e) => {
-                                                let error_msg = format!("Failed to get device ID: {}", e);
-                                                log::error!("Failed to get device ID for job {}: {}", job_id, e);
-                                                // Send failure event
-                                                let fail_event = serde_json::json!({
-                                                    "events": [{
-                                                        "type": "screenshot_failed",
-                                                        "timestamp": iso_timestamp!(),
-                                                        "data": {
-                                                            "jobId": job_id,
-                                                            "job_id": job_id,
-                                                            "error": error_msg,
-                                                            "auto": false
-                                                        }
-                                                    }]
-                                                });
-                                                let _ = client.post(&events_url)
-                                                    .header("Content-Type", "application/json")
-                                                    .header("Authorization", format!("Bearer {}", device_token))
-                                                    .json(&fail_event)
-                                                    .send()
-                                                    .await;
-                                                continue;
-                                            }
-                                        };
-                                        let employee_id = match crate::storage::get_employee_id().await {
-                                            Ok(id) => id,
-                                            Err(e) => {
-                                                let error_msg = format!("Failed to get employee ID: {}", e);
-                                                log::error!("Failed to get employee ID for job {}: {}", job_id, e);
-                                                // Send failure event
-                                                let fail_event = serde_json::json!({
-                                                    "events": [{
-                                                        "type": "screenshot_failed",
-                                                        "timestamp": iso_timestamp!(),
-                                                        "data": {
-                                                            "jobId": job_id,
-                                                            "job_id": job_id,
-                                                            "error": error_msg,
-                                                            "auto": false
-                                                        }
-                                                    }]
-                                                });
-                                                let _ = client.post(&events_url)
-                                                    .header("Content-Type", "application/json")
-                                                    .header("Authorization", format!("Bearer {}", device_token))
-                                                    .json(&fail_event)
-                                                    .send()
-                                                    .await;
-                                                continue;
-                                            }
-                                        };
-                                        
-                                        // Capture screenshot to file
-                                        let screenshot_result = match crate::screenshots::screen_capture::capture_screen_to_file().await {
-                                            Ok(result) => result,
-                                            Err(e) => {
-                                                let error_msg = format!("Failed to capture screenshot. Please grant screen recording permission in System Settings > Privacy & Security > Screen Recording");
-                                                log::error!("Failed to capture screenshot for job {}: {}", job_id, e);
-                                                // Send failure event
-                                                let fail_event = serde_json::json!({
-                                                    "events": [{
-                                                        "type": "screenshot_failed",
-                                                        "timestamp": iso_timestamp!(),
-                                                        "data": {
-                                                            "jobId": job_id,
-                                                            "job_id": job_id,
-                                                            "error": error_msg,
-                                                            "auto": false
-                                                        }
-                                                    }]
-                                                });
-                                                let _ = client.post(&events_url)
-                                                    .header("Content-Type", "application/json")
-                                                    .header("Authorization", format!("Bearer {}", device_token))
-                                                    .json(&fail_event)
-                                                    .send()
-                                                    .await;
-                                                continue;
-                                            }
-                                        };
-                                        
-                                        log::info!(
-                                            "Screenshot captured for job {}: {}x{} ({} bytes)",
-                                            job_id,
-                                            screenshot_result.width,
-                                            screenshot_result.height,
-                                            screenshot_result.bytes
-                                        );
-                                        
-                                        // Upload to Cloudinary
-                                        let cloudinary_result = match crate::api::cloudinary_upload::upload_screenshot_file(
-                                            &screenshot_result.file_path,
-                                            &employee_id,
-                                            &device_id,
-                                        ).await {
-                                            Ok(result) => result,
-                                            Err(e) => {
-                                                let error_msg = format!("Failed to upload to Cloudinary: {}", e);
-                                                log::error!("Failed to upload screenshot to Cloudinary for job {}: {}", job_id, e);
-                                                // Clean up temp file on error
-                                                let _ = std::fs::remove_file(&screenshot_result.file_path);
-                                                // Send failure event
-                                                let fail_event = serde_json::json!({
-                                                    "events": [{
-                                                        "type": "screenshot_failed",
-                                                        "timestamp": iso_timestamp!(),
-                                                        "data": {
-                                                            "jobId": job_id,
-                                                            "job_id": job_id,
-                                                            "error": error_msg,
-                                                            "auto": false
-                                                        }
-                                                    }]
-                                                });
-                                                let _ = client.post(&events_url)
-                                                    .header("Content-Type", "application/json")
-                                                    .header("Authorization", format!("Bearer {}", device_token))
-                                                    .json(&fail_event)
-                                                    .send()
-                                                    .await;
-                                                continue;
-                                            }
-                                        };
-                                        
-                                        log::info!("Screenshot uploaded for job {}: {}", job_id, cloudinary_result.secure_url);
-                                        
-                                        // Clean up temp file
-                                        if let Err(e) = std::fs::remove_file(&screenshot_result.file_path) {
-                                            log::warn!("Failed to delete temp screenshot file: {}", e);
-                                        }
-                                        
-                                        // Send screenshot_taken event with Cloudinary data
-                                        let event_data = serde_json::json!({
-                                            "events": [{
-                                                "type": "screenshot_taken",
-                                                "timestamp": iso_timestamp!(),
-                                                "data": {
-                                                    "jobId": job_id,
-                                                    "cloudinaryPublicId": cloudinary_result.public_id,
-                                                    "cloudinaryUrl": cloudinary_result.secure_url,
-                                                    "width": cloudinary_result.width,
-                                                    "height": cloudinary_result.height,
-                                                    "format": cloudinary_result.format,
-                                                    "bytes": cloudinary_result.bytes,
-                                                    "auto": false
-                                                }
-                                            }]
-                                        });
-
-                                        match client
-                                            .post(&events_url)
-                                            .header("Content-Type", "application/json")
-                                            .header("Authorization", format!("Bearer {}", device_token))
-                                            .json(&event_data)
-                                            .send()
-                                            .await
-                                        {
-                                            Ok(resp) if resp.status().is_success() => {
-                                                log::info!("Screenshot job {} completed successfully", job_id);
-                                            }
-                                            Ok(resp) => {
-                                                let status = resp.status();
-                                                let body = resp.text().await.unwrap_or_default();
-                                                log::error!("Failed to send screenshot event for job {}: {} - {}", job_id, status, body);
-                                            }
-                                            Err(e) => {
-                                                log::error!("Network error sending screenshot event for job {}: {}", job_id, e);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                Ok("Jobs checked".to_string())
-            }
-            Ok(response) => {
-                log::error!("Failed to check jobs: {}", response.status());
-                Err("Failed to check jobs".to_string())
-            }
-            Err(e) => {
-                log::error!("Error checking jobs: {}", e);
-                Err("Network error checking jobs".to_string())
-            }
-        }
-    } else {
-        Err("Not authenticated".to_string())
-    }
-}
-
-#[tauri::command]
-pub async fn get_idle_time() -> Result<u64, String> {
+pub async fn get_idle_time() -> Result<u64, AgentError> {
     #[cfg(target_os = "macos")]
     {
         use std::process::Command;
@@ -2498,82 +2442,176 @@ pub async fn get_idle_time() -> Result<u64, String> {
 // This prevents duplicate idle events and ensures proper work session checks
 
 #[tauri::command]
-pub async fn start_background_services(app_handle: tauri::AppHandle) -> Result<(), String> {
+pub async fn start_background_services(app_handle: tauri::AppHandle) -> Result<(), AgentError> {
     crate::sampling::start_all_background_services(app_handle).await;
+    audit_log::record_outcome("start_background_services", AuditOutcome::Success, None);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn stop_background_services() -> Result<(), String> {
+pub async fn stop_background_services() -> Result<(), AgentError> {
     crate::sampling::stop_services().await;
+    audit_log::record_outcome("stop_background_services", AuditOutcome::Success, None);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn pause_background_services() -> Result<(), String> {
-    crate::sampling::pause_services().await;
+pub async fn pause_background_services() -> Result<(), AgentError> {
+    crate::sampling::pause_services(crate::sampling::service_state::PauseReason::User).await;
+    audit_log::record_outcome("pause_background_services", AuditOutcome::Success, None);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn resume_background_services() -> Result<(), String> {
+pub async fn resume_background_services() -> Result<(), AgentError> {
     crate::sampling::resume_services().await;
+    audit_log::record_outcome("resume_background_services", AuditOutcome::Success, None);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_background_service_state() -> Result<crate::sampling::BackgroundServiceState, String> {
+pub async fn get_background_service_state() -> Result<crate::sampling::BackgroundServiceState, AgentError> {
     Ok(crate::sampling::get_service_state().await)
 }
 
+/// Build a historical usage query from command parameters. Dates are local
+/// `YYYY-MM-DD` days, both inclusive; returns None when no range was given.
+fn usage_query(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    app: Option<String>,
+    category: Option<String>,
+) -> Result<Option<app_usage::UsageQuery>, AgentError> {
+    let start = parse_date_param("start_date", start_date)?;
+    let end = parse_date_param("end_date", end_date)?;
+    let category = category
+        .filter(|c| !c.is_empty())
+        .map(|c| match c.to_uppercase().as_str() {
+            "PRODUCTIVE" => Ok(crate::utils::productivity::ProductivityCategory::PRODUCTIVE),
+            "NEUTRAL" => Ok(crate::utils::productivity::ProductivityCategory::NEUTRAL),
+            "UNPRODUCTIVE" => Ok(crate::utils::productivity::ProductivityCategory::UNPRODUCTIVE),
+            _ => Err(AgentError::Internal(format!("Invalid category '{}'", c))),
+        })
+        .transpose()?;
+    let app = app.filter(|a| !a.is_empty());
+
+    let (start, end) = match (start, end) {
+        (None, None) if app.is_none() && category.is_none() => return Ok(None),
+        (None, None) => {
+            let today = chrono::Local::now().date_naive();
+            (today, today)
+        }
+        (Some(start), None) => (start, chrono::Local::now().date_naive().max(start)),
+        (None, Some(end)) => (end, end),
+        (Some(start), Some(end)) => (start, end),
+    };
+    if end < start {
+        return Err(AgentError::Internal(format!("end_date {} is before start_date {}", end, start)));
+    }
+
+    Ok(Some(app_usage::UsageQuery {
+        start: crate::utils::time::local_midnight(start),
+        end: crate::utils::time::local_midnight(end + chrono::Duration::days(1)),
+        app,
+        category,
+    }))
+}
+
+#[tauri::command]
+pub async fn get_app_usage_summary(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    app: Option<String>,
+    category: Option<String>,
+) -> Result<std::collections::HashMap<String, app_usage::AppUsageSummary>, AgentError> {
+    match usage_query(start_date, end_date, app, category)? {
+        Some(query) => app_usage::query_usage_summary(&query).await.map_err(AgentError::from),
+        None => Ok(app_usage::get_app_usage_summary().await),
+    }
+}
+
 #[tauri::command]
-pub async fn get_app_usage_summary() -> Result<std::collections::HashMap<String, app_usage::AppUsageSummary>, String> {
-    Ok(app_usage::get_app_usage_summary().await)
+pub async fn get_usage_totals(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    app: Option<String>,
+    category: Option<String>,
+) -> Result<app_usage::UsageTotals, AgentError> {
+    match usage_query(start_date, end_date, app, category)? {
+        Some(query) => app_usage::query_usage_totals(&query).await.map_err(AgentError::from),
+        None => Ok(app_usage::get_usage_totals().await),
+    }
 }
 
+/// First clock-in, last clock-out and time totals of a local calendar day
+/// (`YYYY-MM-DD`, default today), from local data
 #[tauri::command]
-pub async fn get_usage_totals() -> Result<(i64, i64, i64, i64), String> {
-    Ok(app_usage::get_usage_totals().await)
+pub async fn get_day_summary(date: Option<String>) -> Result<crate::storage::day_summary::DaySummary, AgentError> {
+    let day = parse_date_param("date", date)?.unwrap_or_else(crate::utils::clock::today);
+    crate::storage::day_summary::summarize(day).await.map_err(AgentError::from)
 }
 
 #[tauri::command]
-pub async fn get_current_app_session() -> Result<Option<app_usage::AppUsageSession>, String> {
+pub async fn get_current_app_session() -> Result<Option<app_usage::AppUsageSession>, AgentError> {
     Ok(app_usage::get_current_session().await)
 }
 
 #[tauri::command]
-pub async fn get_detailed_idle_info() -> Result<crate::sampling::idle_detector::IdleInfo, String> {
-    crate::sampling::idle_detector::get_detailed_idle_info().await.map_err(|e| e.to_string())
+pub async fn get_detailed_idle_info() -> Result<crate::sampling::idle_detector::IdleInfo, AgentError> {
+    crate::sampling::idle_detector::get_detailed_idle_info().await.map_err(AgentError::from)
 }
 
 #[tauri::command]
-pub async fn generate_today_report(employee_id: String, device_id: String) -> Result<crate::api::reporting::DailyReport, String> {
-    crate::api::reporting::generate_today_report(employee_id, device_id).await.map_err(|e| e.to_string())
+pub async fn generate_today_report(employee_id: String, device_id: String) -> Result<crate::api::reporting::DailyReport, AgentError> {
+    crate::api::reporting::generate_today_report(employee_id, device_id).await.map_err(AgentError::from)
 }
 
 #[tauri::command]
-pub async fn generate_weekly_report(employee_id: String, device_id: String) -> Result<Vec<crate::api::reporting::DailyReport>, String> {
-    crate::api::reporting::generate_weekly_report(employee_id, device_id).await.map_err(|e| e.to_string())
+pub async fn generate_weekly_report(employee_id: String, device_id: String) -> Result<Vec<crate::api::reporting::DailyReport>, AgentError> {
+    crate::api::reporting::generate_weekly_report(employee_id, device_id).await.map_err(AgentError::from)
 }
 
 #[tauri::command]
-pub async fn generate_monthly_summary(employee_id: String, device_id: String) -> Result<crate::api::reporting::MonthlySummary, String> {
-    crate::api::reporting::generate_monthly_summary(employee_id, device_id).await.map_err(|e| e.to_string())
+pub async fn generate_monthly_summary(employee_id: String, device_id: String) -> Result<crate::api::reporting::MonthlySummary, AgentError> {
+    crate::api::reporting::generate_monthly_summary(employee_id, device_id).await.map_err(AgentError::from)
 }
 
+/// Write daily report totals (rounded per the org policy) for the inclusive
+/// date range to a CSV file; defaults to the last 7 days. Returns the number
+/// of days written.
 #[tauri::command]
-pub async fn sync_app_rules() -> Result<(), String> {
-    crate::api::app_rules::sync_app_rules().await.map_err(|e| e.to_string())
+pub async fn export_daily_reports_csv(
+    path: String,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<usize, AgentError> {
+    let today = chrono::Utc::now().date_naive();
+    let end = parse_date_param("end_date", end_date)?.unwrap_or(today);
+    let start = parse_date_param("start_date", start_date)?.unwrap_or(end - chrono::Duration::days(6));
+    if start > end || (end - start).num_days() >= 366 {
+        return Err(AgentError::Internal("start_date must be before end_date and within a year of it".to_string()));
+    }
+
+    let reports = crate::api::reporting::generate_reports_between(start, end).await?;
+    std::fs::write(&path, crate::api::reporting::daily_reports_csv(&reports))
+        .map_err(|e| AgentError::Internal(format!("Failed to write {}: {}", path, e)))?;
+    log::info!("Exported {} daily reports to {}", reports.len(), path);
+    Ok(reports.len())
+}
+
+#[tauri::command]
+pub async fn sync_app_rules() -> Result<(), AgentError> {
+    crate::api::app_rules::sync_app_rules().await.map_err(AgentError::from)
 }
 
 #[tauri::command]
-pub async fn get_app_rules() -> Result<Vec<crate::utils::productivity::AppRule>, String> {
+pub async fn get_app_rules() -> Result<Vec<crate::utils::productivity::AppRule>, AgentError> {
     Ok(crate::api::app_rules::get_app_rules().await)
 }
 
 #[tauri::command]
-pub async fn get_rule_statistics() -> Result<crate::api::app_rules::RuleStatistics, String> {
-    crate::api::app_rules::get_rule_statistics().await.map_err(|e| e.to_string())
+pub async fn get_rule_statistics() -> Result<crate::api::app_rules::RuleStatistics, AgentError> {
+    crate::api::app_rules::get_rule_statistics().await.map_err(AgentError::from)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -2587,15 +2625,15 @@ pub struct LicenseCheckResult {
 /// This should be called periodically and after authentication
 #[tauri::command]
 pub async fn check_license_status(
-    state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<LicenseCheckResult, String> {
+    state: State<'_, StateHandle>,
+) -> Result<LicenseCheckResult, AgentError> {
     let (server_url, device_token) = {
-        let app_state = state.lock().await;
-        (app_state.server_url.clone(), app_state.device_token.clone())
+        let app_state = state.snapshot();
+        (app_state.server_url, app_state.device_token)
     };
 
     if server_url.is_none() || device_token.is_none() {
-        return Err("Not authenticated. Please login first.".to_string());
+        return Err(AgentError::NotAuthenticated);
     }
 
     // Create API client
@@ -2608,16 +2646,7 @@ pub async fn check_license_status(
     let response = client
         .get_with_auth(license_url)
         .await
-        .map_err(|e| {
-            let err_str = e.to_string();
-            if err_str.contains("connect") {
-                "Cannot connect to server. Please check your network connection.".to_string()
-            } else if err_str.contains("timeout") {
-                "Connection timeout. Please check your network connection.".to_string()
-            } else {
-                format!("Network error: {}", e)
-            }
-        })?;
+        .map_err(AgentError::from)?;
 
     let status_code = response.status();
     
@@ -2641,11 +2670,8 @@ pub async fn check_license_status(
             .to_string();
 
         // Update state with license info and timestamp
-        {
-            let mut app_state = state.lock().await;
-            app_state.license_valid = Some(valid);
-            app_state.license_status = status.clone();
-            app_state.last_license_check = Some(chrono::Utc::now().timestamp());
+        if let Err(e) = state.set_license(valid, status.clone()).await {
+            log::warn!("Failed to update license state: {}", e);
         }
 
         Ok(LicenseCheckResult {
@@ -2670,11 +2696,8 @@ pub async fn check_license_status(
             .map(|s| s.to_string());
 
         // Update state with invalid license info
-        {
-            let mut app_state = state.lock().await;
-            app_state.license_valid = Some(false);
-            app_state.license_status = status.clone();
-            app_state.last_license_check = Some(chrono::Utc::now().timestamp());
+        if let Err(e) = state.set_license(false, status.clone()).await {
+            log::warn!("Failed to update license state: {}", e);
         }
 
         Ok(LicenseCheckResult {
@@ -2685,7 +2708,10 @@ pub async fn check_license_status(
     } else {
         // For 404, 500, or other server errors, return error (not a license issue)
         // This allows the UI to show a generic error instead of "License Required"
-        Err(format!("License check failed with status: {} - Server may be temporarily unavailable", status_code))
+        Err(AgentError::Server {
+            status: status_code.as_u16(),
+            message: "License check failed - Server may be temporarily unavailable".to_string(),
+        })
     }
 }
 
@@ -2693,12 +2719,74 @@ pub async fn check_license_status(
 /// This is a wrapper around check_license_status for clarity
 #[tauri::command]
 pub async fn retry_license_check(
-    state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<LicenseCheckResult, String> {
+    state: State<'_, StateHandle>,
+) -> Result<LicenseCheckResult, AgentError> {
     check_license_status(state).await
 }
 
 #[tauri::command]
 pub fn get_app_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_two_factor_challenge() {
+        let body = serde_json::json!({
+            "twoFactorRequired": true,
+            "challengeId": "ch_123",
+            "method": "email",
+            "destination": "j***@example.com",
+            "attemptsRemaining": 3
+        });
+        let challenge = parse_two_factor_challenge(&body).unwrap();
+        assert_eq!(challenge.challenge_id, "ch_123");
+        assert_eq!(challenge.method, "email");
+        assert_eq!(challenge.attempts_remaining, Some(3));
+
+        assert_eq!(parse_two_factor_challenge(&serde_json::json!({ "employee": { "id": "e1" } })), None);
+        assert_eq!(parse_two_factor_challenge(&serde_json::json!({ "twoFactorRequired": true })), None);
+    }
+
+    #[tokio::test]
+    async fn test_token_validation_against_mock_backend() {
+        use crate::test_support::{MockBackend, TEST_TOKEN};
+
+        let backend = MockBackend::start().await;
+        assert!(validate_token_with_server(&backend.url(), TEST_TOKEN).await.unwrap());
+        assert!(!validate_token_with_server(&backend.url(), "revoked-token").await.unwrap());
+
+        // Nothing listening: offline, so the caller can keep the cached session
+        let unreachable = validate_token_with_server("http://127.0.0.1:9", TEST_TOKEN).await;
+        assert!(unreachable.unwrap_err().is_offline());
+    }
+
+    #[tokio::test]
+    async fn test_clock_in_event_against_mock_backend() {
+        use crate::test_support::{agent_env, MockBackend};
+
+        let backend = MockBackend::start().await;
+        let _env = agent_env(&backend).await;
+        let session_id = crate::storage::work_session::start_session().await.unwrap();
+        send_clock_in_event(session_id).await.unwrap();
+        assert_eq!(backend.received_event_types().await, vec!["clock_in"]);
+        let body = &backend.received("/api/ingest/events").await[0];
+        assert_eq!(body["events"][0]["data"]["session_id"], session_id);
+        assert_eq!(body["schema_version"], 1);
+        assert!(crate::storage::work_session::is_session_active().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_clock_in_ends_local_session() {
+        use crate::test_support::{agent_env, MockBackend};
+
+        let backend = MockBackend::failing(402).await;
+        let _env = agent_env(&backend).await;
+        let session_id = crate::storage::work_session::start_session().await.unwrap();
+        let result = send_clock_in_event(session_id).await;
+        assert!(matches!(result, Err(AgentError::LicenseRequired(message)) if message == "Mock failure"));
+        assert!(!crate::storage::work_session::is_session_active().await.unwrap());
+    }
+}
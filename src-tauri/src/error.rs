@@ -0,0 +1,213 @@
+//! Typed errors for Tauri commands
+//!
+//! Commands return `Result<T, AgentError>` instead of `Result<T, String>`.
+//! Errors serialize to `{ "code": "...", "message": "..." }` so the frontend
+//! can branch on the kind of failure (not logged in, offline, license, ...)
+//! while still having a human-readable message to display.
+//!
+//! `api` and `storage` keep using `anyhow` internally; they raise an
+//! `AgentError` wrapped in `anyhow::Error` where the kind matters, and the
+//! `From<anyhow::Error>` conversion recovers it at the command boundary.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentError {
+    /// No session credentials are available - user must log in
+    NotAuthenticated,
+    /// The server rejected the session token (401)
+    AuthExpired(String),
+    /// Login rejected because of wrong email or password
+    InvalidCredentials(String),
+    /// Too many login attempts; retry after the given number of seconds (429)
+    LoginThrottled { retry_after_secs: u64, message: String },
+    /// The account is locked after repeated failed logins (423)
+    AccountLocked(String),
+    /// The employee/organization has no valid license (402)
+    LicenseRequired(String),
+    /// The server could not be reached (connection refused, timeout, DNS)
+    Offline(String),
+    /// Any other transport-level failure
+    Network(String),
+    /// The server answered with an unexpected error status
+    Server { status: u16, message: String },
+    /// An OS permission (screen recording, accessibility) is missing or was refused
+    PermissionDenied(String),
+    /// Local database or secure storage failure
+    Storage(String),
+    /// A local operation did not complete in time
+    Timeout(String),
+    /// A mandatory update is overdue and must be installed first
+    UpdateRequired(String),
+    /// Everything else
+    Internal(String),
+}
+
+impl AgentError {
+    /// Stable, machine-readable error code sent to the frontend
+    pub fn code(&self) -> &'static str {
+        match self {
+            AgentError::NotAuthenticated => "NOT_AUTHENTICATED",
+            AgentError::AuthExpired(_) => "AUTH_EXPIRED",
+            AgentError::InvalidCredentials(_) => "INVALID_CREDENTIALS",
+            AgentError::LoginThrottled { .. } => "LOGIN_THROTTLED",
+            AgentError::AccountLocked(_) => "ACCOUNT_LOCKED",
+            AgentError::LicenseRequired(_) => "LICENSE_REQUIRED",
+            AgentError::Offline(_) => "OFFLINE",
+            AgentError::Network(_) => "NETWORK",
+            AgentError::Server { .. } => "SERVER",
+            AgentError::PermissionDenied(_) => "PERMISSION_DENIED",
+            AgentError::Storage(_) => "STORAGE",
+            AgentError::Timeout(_) => "TIMEOUT",
+            AgentError::UpdateRequired(_) => "UPDATE_REQUIRED",
+            AgentError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    /// Map a non-success HTTP status (and the body/message that came with it) to an error kind
+    pub fn from_status(status: reqwest::StatusCode, message: impl Into<String>) -> Self {
+        let message = message.into();
+        match status.as_u16() {
+            401 => AgentError::AuthExpired(message),
+            402 => AgentError::LicenseRequired(message),
+            403 => AgentError::PermissionDenied(message),
+            code => AgentError::Server { status: code, message },
+        }
+    }
+
+    /// Whether the failure is a connectivity problem the caller may treat as "offline"
+    #[allow(dead_code)]
+    pub fn is_offline(&self) -> bool {
+        matches!(self, AgentError::Offline(_))
+    }
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentError::NotAuthenticated => write!(f, "{}", crate::i18n::t("error.not_authenticated")),
+            AgentError::AuthExpired(message)
+            | AgentError::InvalidCredentials(message)
+            | AgentError::LoginThrottled { message, .. }
+            | AgentError::AccountLocked(message)
+            | AgentError::LicenseRequired(message)
+            | AgentError::Offline(message)
+            | AgentError::Network(message)
+            | AgentError::PermissionDenied(message)
+            | AgentError::Storage(message)
+            | AgentError::Timeout(message)
+            | AgentError::UpdateRequired(message)
+            | AgentError::Internal(message) => write!(f, "{}", message),
+            AgentError::Server { status, message } => write!(
+                f,
+                "{}",
+                crate::i18n::t_args("error.server", &[("status", status.to_string().as_str()), ("message", message.as_str())])
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AgentError {}
+
+impl Serialize for AgentError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AgentError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        // Lets the login screen count down instead of parsing the message
+        match self {
+            AgentError::LoginThrottled { retry_after_secs, .. } => {
+                state.serialize_field("retryAfterSeconds", retry_after_secs)?
+            }
+            _ => state.skip_field("retryAfterSeconds")?,
+        }
+        state.end()
+    }
+}
+
+impl From<String> for AgentError {
+    fn from(message: String) -> Self {
+        AgentError::Internal(message)
+    }
+}
+
+impl From<&str> for AgentError {
+    fn from(message: &str) -> Self {
+        AgentError::Internal(message.to_string())
+    }
+}
+
+impl From<reqwest::Error> for AgentError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_connect() {
+            AgentError::Offline("Cannot connect to server. Please check your network connection.".to_string())
+        } else if e.is_timeout() {
+            AgentError::Offline("Connection timeout. Please check your network connection.".to_string())
+        } else if let Some(status) = e.status() {
+            AgentError::from_status(status, e.to_string())
+        } else {
+            AgentError::Network(format!("Network error: {}", e))
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AgentError {
+    fn from(e: rusqlite::Error) -> Self {
+        AgentError::Storage(format!("Database error: {}", e))
+    }
+}
+
+impl From<anyhow::Error> for AgentError {
+    fn from(e: anyhow::Error) -> Self {
+        if let Some(agent_error) = e.downcast_ref::<AgentError>() {
+            return agent_error.clone();
+        }
+        let e = match e.downcast::<reqwest::Error>() {
+            Ok(reqwest_error) => return reqwest_error.into(),
+            Err(e) => e,
+        };
+        if let Some(db_error) = e.downcast_ref::<rusqlite::Error>() {
+            return AgentError::Storage(format!("Database error: {}", db_error));
+        }
+        AgentError::Internal(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_and_message() {
+        let json = serde_json::to_value(AgentError::NotAuthenticated).unwrap();
+        assert_eq!(json["code"], "NOT_AUTHENTICATED");
+        assert_eq!(json["message"], "Not authenticated. Please login first.");
+
+        let json = serde_json::to_value(AgentError::LicenseRequired("NO_VALID_LICENSE: x".to_string())).unwrap();
+        assert_eq!(json["code"], "LICENSE_REQUIRED");
+        assert_eq!(json["message"], "NO_VALID_LICENSE: x");
+        assert!(json.get("retryAfterSeconds").is_none());
+
+        let json = serde_json::to_value(AgentError::LoginThrottled { retry_after_secs: 30, message: "wait".to_string() }).unwrap();
+        assert_eq!(json["code"], "LOGIN_THROTTLED");
+        assert_eq!(json["retryAfterSeconds"], 30);
+    }
+
+    #[test]
+    fn test_status_mapping() {
+        assert_eq!(AgentError::from_status(reqwest::StatusCode::UNAUTHORIZED, "x").code(), "AUTH_EXPIRED");
+        assert_eq!(AgentError::from_status(reqwest::StatusCode::PAYMENT_REQUIRED, "x").code(), "LICENSE_REQUIRED");
+        assert_eq!(AgentError::from_status(reqwest::StatusCode::INTERNAL_SERVER_ERROR, "x").code(), "SERVER");
+    }
+
+    #[test]
+    fn test_anyhow_round_trip_keeps_kind() {
+        let wrapped: anyhow::Error = AgentError::NotAuthenticated.into();
+        assert_eq!(AgentError::from(wrapped), AgentError::NotAuthenticated);
+
+        let plain = anyhow::anyhow!("something broke");
+        assert_eq!(AgentError::from(plain), AgentError::Internal("something broke".to_string()));
+    }
+}
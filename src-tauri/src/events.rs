@@ -0,0 +1,289 @@
+//! Tracking state events pushed to the frontend
+//!
+//! Sampling and storage code emit these through the global app handle so the
+//! dashboard updates in real time instead of polling `get_tracking_status` /
+//! `get_work_session`. Emitting is best-effort: before the handle is set (or
+//! when no window is listening) events are simply dropped.
+
+use serde::Serialize;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+pub const SESSION_STARTED: &str = "session-started";
+pub const SESSION_ENDED: &str = "session-ended";
+pub const IDLE_CHANGED: &str = "idle-changed";
+pub const APP_CHANGED: &str = "app-changed";
+pub const SYNC_PROGRESS: &str = "sync-progress";
+pub const SESSION_DIVERGED: &str = "session-diverged";
+pub const PERMISSION_LOST: &str = "permission-lost";
+pub const PERMISSION_RESTORED: &str = "permission-restored";
+pub const DEVICE_DEACTIVATED: &str = "device-deactivated";
+pub const STORAGE_PRESSURE: &str = "storage-pressure";
+pub const SETTINGS_UPDATED: &str = "settings-updated";
+pub const SERVICE_STATE_CHANGED: &str = "service-state-changed";
+pub const TRACKING_RESUMED: &str = "tracking-resumed";
+pub const SCREENSHOT_REVIEW_REQUESTED: &str = "screenshot-review-requested";
+pub const REAUTH_REQUIRED: &str = "reauth-required";
+
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Store the app handle (called once from main setup)
+pub fn init(app_handle: AppHandle) {
+    if APP_HANDLE.set(app_handle).is_err() {
+        log::warn!("Event emitter already initialized");
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStartedPayload {
+    pub session_id: i64,
+    pub started_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEndedPayload {
+    pub ended_at: String,
+}
+
+/// The local work session disagrees with the backend (see `work_session::reconcile_with_backend`)
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionDivergedPayload {
+    pub local_active: bool,
+    pub local_started_at: Option<String>,
+    pub backend_active: bool,
+    pub backend_started_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IdleChangedPayload {
+    pub is_idle: bool,
+    pub idle_time_seconds: u64,
+    pub threshold_seconds: u64,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppChangedPayload {
+    pub name: String,
+    pub app_id: String,
+    pub window_title: Option<String>,
+    pub url: Option<String>,
+    pub domain: Option<String>,
+    pub category: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncProgressPayload {
+    /// Items found in the offline queue at the start of the pass
+    pub total: usize,
+    pub synced: usize,
+    pub failed: usize,
+    pub remaining: usize,
+    pub phase: crate::sampling::offline_sync::SyncPhase,
+    pub completed: bool,
+}
+
+/// An OS permission changed while the app was running (see permissions::watcher)
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionChangedPayload {
+    pub permission: String,
+    pub timestamp: String,
+}
+
+/// The backend deactivated this device; credentials were cleared
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceDeactivatedPayload {
+    pub message: Option<String>,
+    pub timestamp: String,
+}
+
+/// The device couldn't be re-registered; the user has to sign in again.
+/// Local data is kept and synced after login.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReauthRequiredPayload {
+    pub reason: String,
+    pub timestamp: String,
+}
+
+/// Local storage pressure changed (see storage::storage_guard)
+#[derive(Debug, Clone, Serialize)]
+pub struct StoragePressurePayload {
+    pub pressure: crate::storage::storage_guard::StoragePressure,
+    pub database_bytes: u64,
+    pub free_disk_bytes: Option<u64>,
+    pub timestamp: String,
+}
+
+/// Tracking resumed after a pause or snooze
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackingResumedPayload {
+    pub reason: crate::sampling::service_state::PauseReason,
+    pub paused_at: String,
+    /// Resumed because the pause deadline was reached
+    pub automatic: bool,
+    pub timestamp: String,
+}
+
+/// A captured screenshot waits for the employee's review before upload
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenshotReviewPayload {
+    pub id: i64,
+    pub taken_at: String,
+    /// Uploaded as is if not reviewed by then
+    pub review_deadline: String,
+}
+
+/// Employee settings were re-fetched after a push from the backend
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsUpdatedPayload {
+    pub auto_screenshots: bool,
+    pub screenshot_interval: i32,
+    pub browser_domain_only: bool,
+    pub fetched_at: String,
+}
+
+fn emit<T: Serialize + Clone>(event: &str, payload: T) {
+    let Some(app_handle) = APP_HANDLE.get() else {
+        return;
+    };
+    if let Err(e) = app_handle.emit(event, payload) {
+        log::warn!("Failed to emit {} event: {}", event, e);
+    }
+}
+
+/// Show a localized system notification (see `i18n::notify`)
+pub fn notify(title_key: &str, body_key: &str) {
+    // Shown once Do Not Disturb / presenting ends
+    if crate::sampling::presentation::hold_notification(title_key, body_key) {
+        return;
+    }
+    if let Some(app_handle) = APP_HANDLE.get() {
+        crate::i18n::notify(app_handle, title_key, body_key);
+    }
+}
+
+pub fn session_started(session_id: i64, started_at: chrono::DateTime<chrono::Utc>) {
+    emit(SESSION_STARTED, SessionStartedPayload {
+        session_id,
+        started_at: started_at.to_rfc3339(),
+    });
+}
+
+pub fn session_ended() {
+    emit(SESSION_ENDED, SessionEndedPayload {
+        ended_at: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub fn session_diverged(
+    local_started_at: Option<chrono::DateTime<chrono::Utc>>,
+    backend_active: bool,
+    backend_started_at: Option<chrono::DateTime<chrono::Utc>>,
+) {
+    emit(SESSION_DIVERGED, SessionDivergedPayload {
+        local_active: local_started_at.is_some(),
+        local_started_at: local_started_at.map(|dt| dt.to_rfc3339()),
+        backend_active,
+        backend_started_at: backend_started_at.map(|dt| dt.to_rfc3339()),
+    });
+}
+
+pub fn idle_changed(is_idle: bool, idle_time_seconds: u64, threshold_seconds: u64) {
+    emit(IDLE_CHANGED, IdleChangedPayload {
+        is_idle,
+        idle_time_seconds,
+        threshold_seconds,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub fn app_changed(app_info: &crate::sampling::app_focus::AppInfo, category: &str) {
+    emit(APP_CHANGED, AppChangedPayload {
+        name: app_info.name.clone(),
+        app_id: app_info.app_id.clone(),
+        window_title: app_info.window_title.clone(),
+        url: app_info.url.clone(),
+        domain: app_info.domain.clone(),
+        category: category.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub fn sync_progress(total: usize, synced: usize, failed: usize, phase: crate::sampling::offline_sync::SyncPhase) {
+    emit(SYNC_PROGRESS, SyncProgressPayload {
+        total,
+        synced,
+        failed,
+        remaining: total.saturating_sub(synced + failed),
+        phase,
+        completed: phase == crate::sampling::offline_sync::SyncPhase::Done,
+    });
+}
+
+pub fn permission_lost(permission: &str) {
+    emit(PERMISSION_LOST, PermissionChangedPayload {
+        permission: permission.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub fn permission_restored(permission: &str) {
+    emit(PERMISSION_RESTORED, PermissionChangedPayload {
+        permission: permission.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub fn device_deactivated(message: Option<&str>) {
+    emit(DEVICE_DEACTIVATED, DeviceDeactivatedPayload {
+        message: message.map(|m| m.to_string()),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub fn reauth_required(reason: &str) {
+    emit(REAUTH_REQUIRED, ReauthRequiredPayload {
+        reason: reason.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub fn settings_updated(settings: &crate::api::employee_settings::EmployeeSettings) {
+    emit(SETTINGS_UPDATED, SettingsUpdatedPayload {
+        auto_screenshots: settings.auto_screenshots,
+        screenshot_interval: settings.screenshot_interval,
+        browser_domain_only: settings.policy.as_ref().map_or(true, |p| p.browser_domain_only),
+        fetched_at: settings.fetched_at.to_rfc3339(),
+    });
+}
+
+pub fn service_state_changed(transition: &crate::sampling::service_state::ServiceTransition) {
+    emit(SERVICE_STATE_CHANGED, transition.clone());
+}
+
+pub fn tracking_resumed(pause: &crate::storage::pause_state::PersistedPause, automatic: bool) {
+    emit(TRACKING_RESUMED, TrackingResumedPayload {
+        reason: pause.reason,
+        paused_at: pause.paused_at.to_rfc3339(),
+        automatic,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub fn screenshot_review_requested(id: i64, taken_at: chrono::DateTime<chrono::Utc>, review_deadline: chrono::DateTime<chrono::Utc>) {
+    emit(SCREENSHOT_REVIEW_REQUESTED, ScreenshotReviewPayload {
+        id,
+        taken_at: taken_at.to_rfc3339(),
+        review_deadline: review_deadline.to_rfc3339(),
+    });
+}
+
+pub fn storage_pressure(status: &crate::storage::storage_guard::StorageStatus) {
+    emit(STORAGE_PRESSURE, StoragePressurePayload {
+        pressure: status.pressure,
+        database_bytes: status.database_bytes,
+        free_disk_bytes: status.free_disk_bytes,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
@@ -0,0 +1,226 @@
+//! Localization of agent-generated strings
+//!
+//! Tray menu labels, notifications and command error messages come from the
+//! JSON catalogs embedded next to this file (one flat `key -> text` map per
+//! language, `{name}` placeholders). The language follows the system locale
+//! unless the user picked one with `set_locale`; the override is stored in
+//! `<data_dir>/TrackEx/locale`. Missing keys fall back to English.
+//!
+//! Until `init` runs (e.g. in unit tests) everything is English.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use tauri::menu::MenuItem;
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::error::AgentError;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Emitted after the language changes, with the new locale
+pub const LOCALE_CHANGED_EVENT: &str = "locale-changed";
+
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("en.json")),
+    ("es", include_str!("es.json")),
+    ("de", include_str!("de.json")),
+    ("fr", include_str!("fr.json")),
+];
+
+lazy_static::lazy_static! {
+    static ref MESSAGES: HashMap<&'static str, HashMap<String, String>> = CATALOGS
+        .iter()
+        .map(|(locale, json)| {
+            let messages = serde_json::from_str(json).unwrap_or_else(|e| {
+                log::error!("Invalid {} catalog: {}", locale, e);
+                HashMap::new()
+            });
+            (*locale, messages)
+        })
+        .collect();
+}
+
+static LOCALE: RwLock<&'static str> = RwLock::new(DEFAULT_LOCALE);
+
+/// Tray items and the catalog key of their label, relabeled on locale change
+static MENU_ITEMS: Mutex<Vec<(MenuItem<Wry>, &'static str)>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocaleInfo {
+    /// Language in use
+    pub locale: String,
+    /// Language derived from the system locale
+    pub system_locale: String,
+    /// Language chosen by the user, if any
+    pub override_locale: Option<String>,
+    pub available: Vec<String>,
+}
+
+/// Supported language for a locale tag like "es-MX" or "de_DE.UTF-8"
+fn resolve(tag: &str) -> Option<&'static str> {
+    let language = tag
+        .split(['-', '_', '.'])
+        .next()?
+        .to_lowercase();
+    CATALOGS.iter().map(|(locale, _)| *locale).find(|locale| *locale == language)
+}
+
+fn system_locale() -> &'static str {
+    sys_locale::get_locale()
+        .as_deref()
+        .and_then(resolve)
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+fn override_path() -> Option<PathBuf> {
+    let mut path = crate::profile::data_dir()?;
+    path.push("locale");
+    Some(path)
+}
+
+fn load_override() -> Option<&'static str> {
+    let value = std::fs::read_to_string(override_path()?).ok()?;
+    resolve(value.trim())
+}
+
+fn save_override(locale: Option<&str>) -> anyhow::Result<()> {
+    let path = override_path().ok_or_else(|| anyhow::anyhow!("No data directory"))?;
+    match locale {
+        Some(locale) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, locale)?;
+        }
+        None if path.exists() => std::fs::remove_file(path)?,
+        None => {}
+    }
+    Ok(())
+}
+
+fn set_current(locale: &'static str) {
+    match LOCALE.write() {
+        Ok(mut current) => *current = locale,
+        Err(e) => *e.into_inner() = locale,
+    }
+}
+
+/// Pick the language from the saved override or the system locale
+pub fn init() {
+    let locale = load_override().unwrap_or_else(system_locale);
+    set_current(locale);
+    log::info!("Using locale {}", locale);
+}
+
+pub fn current_locale() -> &'static str {
+    LOCALE.read().map(|l| *l).unwrap_or(DEFAULT_LOCALE)
+}
+
+fn lookup(locale: &str, key: &str) -> Option<String> {
+    MESSAGES.get(locale)?.get(key).cloned()
+}
+
+/// Localized text for `key`
+pub fn t(key: &str) -> String {
+    lookup(current_locale(), key)
+        .or_else(|| lookup(DEFAULT_LOCALE, key))
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Localized text for `key` with `{name}` placeholders filled in
+pub fn t_args(key: &str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(t(key), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+}
+
+/// Create a tray menu item whose label follows the locale
+pub fn menu_item(app: &AppHandle, id: &str, key: &'static str) -> tauri::Result<MenuItem<Wry>> {
+    let item = MenuItem::with_id(app, id, t(key), true, None::<&str>)?;
+    if let Ok(mut items) = MENU_ITEMS.lock() {
+        items.push((item.clone(), key));
+    }
+    Ok(item)
+}
+
+fn relabel_menu_items() {
+    if let Ok(items) = MENU_ITEMS.lock() {
+        for (item, key) in items.iter() {
+            if let Err(e) = item.set_text(t(key)) {
+                log::warn!("Failed to relabel tray item {}: {}", key, e);
+            }
+        }
+    }
+}
+
+/// Show a system notification with localized title and body
+pub fn notify(app: &AppHandle, title_key: &str, body_key: &str) {
+    if let Err(e) = app.notification().builder().title(t(title_key)).body(t(body_key)).show() {
+        log::warn!("Failed to show notification {}: {}", title_key, e);
+    }
+}
+
+fn locale_info() -> LocaleInfo {
+    LocaleInfo {
+        locale: current_locale().to_string(),
+        system_locale: system_locale().to_string(),
+        override_locale: load_override().map(str::to_string),
+        available: CATALOGS.iter().map(|(locale, _)| locale.to_string()).collect(),
+    }
+}
+
+#[tauri::command]
+pub async fn get_locale() -> Result<LocaleInfo, AgentError> {
+    Ok(locale_info())
+}
+
+/// Override the language, or follow the system locale again with `None`
+#[tauri::command]
+pub async fn set_locale(app: AppHandle, locale: Option<String>) -> Result<LocaleInfo, AgentError> {
+    let chosen = match locale.as_deref() {
+        Some(tag) => Some(resolve(tag).ok_or_else(|| AgentError::Internal(format!("Unsupported locale: {}", tag)))?),
+        None => None,
+    };
+    save_override(chosen).map_err(|e| AgentError::Storage(e.to_string()))?;
+
+    set_current(chosen.unwrap_or_else(system_locale));
+    relabel_menu_items();
+    crate::update_check::refresh_tray_text();
+    crate::tray_status::refresh();
+
+    let info = locale_info();
+    if let Err(e) = app.emit(LOCALE_CHANGED_EVENT, &info.locale) {
+        log::warn!("Failed to emit locale change: {}", e);
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_locale_tags() {
+        assert_eq!(resolve("es-MX"), Some("es"));
+        assert_eq!(resolve("de_DE.UTF-8"), Some("de"));
+        assert_eq!(resolve("FR"), Some("fr"));
+        assert_eq!(resolve("ja-JP"), None);
+    }
+
+    #[test]
+    fn test_catalogs_have_every_english_key() {
+        let english = &MESSAGES[DEFAULT_LOCALE];
+        for (locale, _) in CATALOGS {
+            for key in english.keys() {
+                assert!(MESSAGES[locale].contains_key(key), "{} is missing {}", locale, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_placeholders_and_fallback() {
+        assert_eq!(t_args("tray.update_available", &[("version", "1.4.0")]), "Update Available (v1.4.0)");
+        assert_eq!(t("no.such.key"), "no.such.key");
+    }
+}
@@ -1,4 +1,6 @@
 pub mod commands;
+pub mod error;
+pub mod events;
 pub mod consent;
 pub mod sampling;
 pub mod screenshots;
@@ -7,4 +9,20 @@ pub mod api;
 pub mod policy;
 pub mod utils;
 pub mod permissions;
-pub mod update_manager;
\ No newline at end of file
+pub mod update_manager;
+pub mod update_check;
+pub mod update_lockout;
+pub mod update_rollback;
+pub mod update_schedule;
+pub mod i18n;
+pub mod location;
+pub mod time_import;
+pub mod session_export;
+pub mod browser_login;
+pub mod pairing;
+pub mod snooze;
+pub mod session_manager;
+pub mod tray_status;
+pub mod profile;
+#[cfg(test)]
+pub mod test_support;
\ No newline at end of file
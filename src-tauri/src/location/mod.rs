@@ -0,0 +1,231 @@
+//! Coarse, consent-gated location for clock events
+//!
+//! Organizations can ask for clock_in/clock_out events to say whether the
+//! employee worked from the office or remotely. Nothing is looked up unless
+//! the policy enables it (`location_enabled`) *and* the employee opted into
+//! the `location` consent scope.
+//!
+//! The location is coarse by design: the backend geolocates the device's
+//! public IP to country/region/city (no GPS, no Wi-Fi positioning), and the
+//! public IP is compared against the organization's office ranges. The IP
+//! itself is not attached to events.
+
+pub mod office_network;
+
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::api::client::ApiClient;
+use crate::api::employee_settings::PolicySettings;
+use crate::error::AgentError;
+
+/// Consent scope the employee has to grant
+pub const LOCATION_CONSENT_SCOPE: &str = "location";
+
+/// Clock events must not wait long for the lookup
+const LOOKUP_TIMEOUT_SECS: u64 = 3;
+
+/// Lookups are reused for this long (clock in/out in quick succession)
+const CACHE_TTL_SECS: u64 = 10 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkLocation {
+    Office,
+    Remote,
+}
+
+/// Location attached to clock events
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoarseLocation {
+    pub classification: WorkLocation,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GeoIpResponse {
+    ip: String,
+    #[serde(default)]
+    country: Option<String>,
+    #[serde(default)]
+    region: Option<String>,
+    #[serde(default)]
+    city: Option<String>,
+}
+
+static CACHE: Mutex<Option<(Instant, GeoIpResponse)>> = Mutex::new(None);
+
+/// An IPv4 or IPv6 network in CIDR notation ("203.0.113.0/24"); a bare
+/// address is a single-host network
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (address, prefix) = match value.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+            None => (value.trim(), None),
+        };
+        let network: IpAddr = address.parse().ok()?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(IpRange { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether `ip` is inside any of the (policy-provided) ranges; invalid entries are ignored
+pub fn in_ranges(ip: IpAddr, ranges: &[String]) -> bool {
+    ranges
+        .iter()
+        .filter_map(|range| IpRange::parse(range))
+        .any(|range| range.contains(ip))
+}
+
+fn classify(geo: &GeoIpResponse, office_ranges: &[String]) -> CoarseLocation {
+    let in_office = geo
+        .ip
+        .parse::<IpAddr>()
+        .map(|ip| in_ranges(ip, office_ranges))
+        .unwrap_or(false);
+    CoarseLocation {
+        classification: if in_office { WorkLocation::Office } else { WorkLocation::Remote },
+        country: geo.country.clone(),
+        region: geo.region.clone(),
+        city: geo.city.clone(),
+    }
+}
+
+async fn fetch_geoip() -> anyhow::Result<GeoIpResponse> {
+    if let Ok(cache) = CACHE.lock() {
+        if let Some((fetched_at, geo)) = cache.as_ref() {
+            if fetched_at.elapsed() < Duration::from_secs(CACHE_TTL_SECS) {
+                return Ok(geo.clone());
+            }
+        }
+    }
+
+    let client = ApiClient::new().await?;
+    let response = client.get_with_auth("/api/desktop/geoip").await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("GeoIP lookup failed: {}", response.status()));
+    }
+    let geo: GeoIpResponse = response.json().await?;
+
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = Some((Instant::now(), geo.clone()));
+    }
+    Ok(geo)
+}
+
+async fn is_allowed(policy: &PolicySettings) -> bool {
+    if !policy.location_enabled {
+        return false;
+    }
+    crate::storage::consent::has_scope_consent(LOCATION_CONSENT_SCOPE)
+        .await
+        .unwrap_or(false)
+}
+
+/// Coarse location for a clock event, or `None` when disabled, not consented
+/// or the lookup fails. Never delays the event by more than a few seconds.
+pub async fn location_for_clock_event() -> Option<CoarseLocation> {
+    let policy = crate::api::employee_settings::get_policy_settings().await;
+    if !is_allowed(&policy).await {
+        return None;
+    }
+
+    match tokio::time::timeout(Duration::from_secs(LOOKUP_TIMEOUT_SECS), fetch_geoip()).await {
+        Ok(Ok(geo)) => Some(classify(&geo, &policy.office_ip_ranges)),
+        Ok(Err(e)) => {
+            log::warn!("Location lookup failed: {}", e);
+            None
+        }
+        Err(_) => {
+            log::warn!("Location lookup timed out");
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocationConsent {
+    /// The organization enabled location on clock events
+    pub requested: bool,
+    pub granted: bool,
+}
+
+#[tauri::command]
+pub async fn get_location_consent() -> Result<LocationConsent, AgentError> {
+    let policy = crate::api::employee_settings::get_policy_settings().await;
+    let granted = crate::storage::consent::has_scope_consent(LOCATION_CONSENT_SCOPE)
+        .await
+        .map_err(|e| AgentError::Storage(e.to_string()))?;
+    Ok(LocationConsent { requested: policy.location_enabled, granted })
+}
+
+/// Opt in or out of attaching coarse location to clock events
+#[tauri::command]
+pub async fn set_location_consent(granted: bool) -> Result<(), AgentError> {
+    let result = crate::storage::consent::set_scope_consent(LOCATION_CONSENT_SCOPE, granted)
+        .await
+        .map_err(|e| AgentError::Storage(e.to_string()));
+    crate::storage::audit_log::record("set_location_consent", &result);
+    if result.is_ok() && !granted {
+        if let Ok(mut cache) = CACHE.lock() {
+            *cache = None;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_ranges() {
+        let ranges = vec!["203.0.113.0/24".to_string(), "2001:db8::/32".to_string(), "not a range".to_string()];
+        assert!(in_ranges("203.0.113.57".parse().unwrap(), &ranges));
+        assert!(!in_ranges("203.0.114.1".parse().unwrap(), &ranges));
+        assert!(in_ranges("2001:db8:1::1".parse().unwrap(), &ranges));
+        assert!(IpRange::parse("198.51.100.7").unwrap().contains("198.51.100.7".parse().unwrap()));
+        assert!(IpRange::parse("0.0.0.0/0").unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert_eq!(IpRange::parse("10.0.0.0/33"), None);
+    }
+
+    #[test]
+    fn test_classify_office_vs_remote() {
+        let geo = GeoIpResponse {
+            ip: "203.0.113.10".to_string(),
+            country: Some("US".to_string()),
+            region: None,
+            city: Some("Austin".to_string()),
+        };
+        let office = classify(&geo, &["203.0.113.0/24".to_string()]);
+        assert_eq!(office.classification, WorkLocation::Office);
+        assert_eq!(office.city.as_deref(), Some("Austin"));
+        assert_eq!(classify(&geo, &[]).classification, WorkLocation::Remote);
+    }
+}
@@ -0,0 +1,167 @@
+//! Office network detection
+//!
+//! Classifies the current network as office or remote from the policy's
+//! office LAN ranges (matched against the local address of the outbound
+//! interface) and office Wi-Fi SSIDs. The public egress ranges
+//! (`office_ip_ranges`) are a different thing, matched by `location` against
+//! the public address. Only the classification leaves the device: heartbeats
+//! carry `"network": "office" | "remote"`, never the address or SSID. Without
+//! LAN ranges or SSIDs in the policy nothing is checked.
+
+use serde::Serialize;
+use std::net::{IpAddr, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::api::employee_settings::PolicySettings;
+
+/// Heartbeats are frequent; the network is re-checked at most this often
+const CACHE_TTL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkClassification {
+    Office,
+    Remote,
+}
+
+static CACHE: Mutex<Option<(Instant, NetworkClassification)>> = Mutex::new(None);
+
+/// Address of the interface used for outbound traffic. Connecting a UDP
+/// socket only selects a route; no packet is sent.
+fn local_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// Extract the SSID from the platform tool's output
+fn parse_ssid(output: &str) -> Option<String> {
+    let ssid = if cfg!(any(target_os = "macos", target_os = "windows")) {
+        // "    SSID                   : Office-5G" (but not "BSSID")
+        output.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "SSID").then_some(value)
+        })
+    } else {
+        // `iwgetid -r` prints just the SSID
+        output.lines().next()
+    };
+    ssid.map(str::trim)
+        // macOS hides the SSID from apps without Location Services access
+        .filter(|s| !s.is_empty() && *s != "<redacted>")
+        .map(str::to_string)
+}
+
+/// BSD name of the Wi-Fi interface ("Hardware Port: Wi-Fi" / "Device: en1"),
+/// which isn't en0 on every Mac
+#[cfg(target_os = "macos")]
+fn wifi_device() -> String {
+    std::process::Command::new("networksetup")
+        .arg("-listallhardwareports")
+        .output()
+        .ok()
+        .and_then(|output| {
+            let output = String::from_utf8_lossy(&output.stdout).into_owned();
+            let mut lines = output.lines();
+            lines.find(|line| line.trim() == "Hardware Port: Wi-Fi")?;
+            lines.next()?.strip_prefix("Device: ").map(|device| device.trim().to_string())
+        })
+        .unwrap_or_else(|| "en0".to_string())
+}
+
+fn current_ssid() -> Option<String> {
+    // `networksetup -getairportnetwork` reports no network at all since
+    // macOS 14.4; `ipconfig getsummary` still lists the SSID
+    #[cfg(target_os = "macos")]
+    let output = std::process::Command::new("ipconfig").args(["getsummary", &wifi_device()]).output();
+    #[cfg(not(target_os = "macos"))]
+    let output = if cfg!(target_os = "windows") {
+        std::process::Command::new("netsh").args(["wlan", "show", "interfaces"]).output()
+    } else {
+        std::process::Command::new("iwgetid").arg("-r").output()
+    };
+    let output = output.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_ssid(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn classify(local_ip: Option<IpAddr>, ssid: Option<&str>, policy: &PolicySettings) -> NetworkClassification {
+    let ip_match = local_ip.is_some_and(|ip| super::in_ranges(ip, &policy.office_lan_ranges));
+    let ssid_match = ssid.is_some_and(|ssid| policy.office_ssids.iter().any(|office| office == ssid));
+    if ip_match || ssid_match {
+        NetworkClassification::Office
+    } else {
+        NetworkClassification::Remote
+    }
+}
+
+/// Office/remote classification for heartbeats, `None` when the policy
+/// defines no office networks
+pub async fn classify_current_network(policy: &PolicySettings) -> Option<NetworkClassification> {
+    if policy.office_lan_ranges.is_empty() && policy.office_ssids.is_empty() {
+        return None;
+    }
+
+    if let Ok(cache) = CACHE.lock() {
+        if let Some((checked_at, classification)) = *cache {
+            if checked_at.elapsed() < Duration::from_secs(CACHE_TTL_SECS) {
+                return Some(classification);
+            }
+        }
+    }
+
+    let check_ssid = !policy.office_ssids.is_empty();
+    let (ip, ssid) = tokio::task::spawn_blocking(move || {
+        (local_ip(), if check_ssid { current_ssid() } else { None })
+    })
+    .await
+    .ok()?;
+
+    let classification = classify(ip, ssid.as_deref(), policy);
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = Some((Instant::now(), classification));
+    }
+    Some(classification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let policy = PolicySettings {
+            office_lan_ranges: vec!["10.20.0.0/16".to_string()],
+            // Public egress ranges don't apply to the local address
+            office_ip_ranges: vec!["192.168.0.0/16".to_string()],
+            office_ssids: vec!["Office-5G".to_string()],
+            ..PolicySettings::default()
+        };
+        let office_ip = "10.20.3.4".parse().ok();
+        let home_ip = "192.168.1.20".parse().ok();
+
+        assert_eq!(classify(office_ip, None, &policy), NetworkClassification::Office);
+        assert_eq!(classify(home_ip, Some("Office-5G"), &policy), NetworkClassification::Office);
+        assert_eq!(classify(home_ip, Some("Home"), &policy), NetworkClassification::Remote);
+        assert_eq!(classify(None, None, &policy), NetworkClassification::Remote);
+    }
+
+    #[test]
+    fn test_parse_ssid() {
+        #[cfg(target_os = "macos")]
+        {
+            assert_eq!(parse_ssid("  BSSID : aa:bb\n  SSID : Office-5G\n").as_deref(), Some("Office-5G"));
+            assert_eq!(parse_ssid("  SSID : <redacted>\n"), None);
+        }
+        #[cfg(target_os = "windows")]
+        assert_eq!(
+            parse_ssid("    BSSID                  : aa:bb\n    SSID                   : Office-5G\n").as_deref(),
+            Some("Office-5G")
+        );
+        #[cfg(target_os = "linux")]
+        assert_eq!(parse_ssid("Office-5G\n").as_deref(), Some("Office-5G"));
+    }
+}
@@ -2,6 +2,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod commands;
+mod error;
+mod events;
 mod consent;
 mod sampling;
 mod screenshots;
@@ -11,17 +13,31 @@ mod policy;
 mod utils;
 mod permissions;
 mod update_manager;
+mod update_check;
+mod update_lockout;
+mod update_rollback;
+mod update_schedule;
+mod i18n;
+mod location;
+mod time_import;
+mod session_export;
+mod browser_login;
+mod pairing;
+mod snooze;
+mod session_manager;
+mod tray_status;
+mod profile;
+#[cfg(test)]
+mod test_support;
 
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tauri::{Manager, WindowEvent, RunEvent};
-use tauri::menu::{MenuBuilder, MenuItem};
+use tauri::{Emitter, Manager, WindowEvent, RunEvent};
+use tauri::menu::MenuBuilder;
 use tauri::tray::{TrayIconBuilder, TrayIconEvent, MouseButton};
-use tokio::sync::Mutex;
 use utils::logging;
 
 use crate::commands::*;
-use crate::storage::AppState;
+use crate::storage::StateHandle;
 
 /// Global flag to track if we're in the middle of a graceful shutdown
 /// Prevents infinite loop when exit() triggers ExitRequested again
@@ -30,6 +46,9 @@ static SHUTDOWN_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
 /// Force clock-out function that sends clock_out event to backend
 /// Called on app shutdown to ensure employee is properly clocked out
 async fn force_clock_out() {
+    // Graceful shutdown: not a crash of a freshly installed update
+    crate::update_rollback::record_clean_exit();
+    
     log::info!("Force clock-out: Checking if user is clocked in...");
     
     // Check if user is authenticated and clocked in
@@ -44,59 +63,22 @@ async fn force_clock_out() {
     }
     
     log::info!("Force clock-out: User is clocked in, sending clock_out event to backend...");
-    
-    // End local app usage session
-    if let Err(e) = crate::storage::app_usage::end_current_session().await {
-        log::warn!("Force clock-out: Failed to end current app session: {}", e);
-    }
-    
-    // Stop background services
-    crate::sampling::stop_services().await;
-    crate::sampling::reset_idle_state();
-    
-    // End local work session
-    if let Err(e) = crate::storage::work_session::end_session().await {
-        log::warn!("Force clock-out: Failed to end local session: {}", e);
-    }
-    
-    // Send clock_out event to backend
-    match crate::api::client::ApiClient::new().await {
-        Ok(client) => {
-            let event_data = serde_json::json!({
-                "events": [{
-                    "type": "clock_out",
-                    "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-                    "data": {
-                        "source": "desktop_agent_shutdown",
-                        "reason": "app_quit"
-                    }
-                }]
-            });
-            
-            match client.post_with_auth("/api/ingest/events", &event_data).await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        log::info!("Force clock-out: Successfully sent clock_out event to backend");
-                    } else {
-                        log::warn!("Force clock-out: Backend returned error status: {}", response.status());
-                    }
-                }
-                Err(e) => {
-                    log::warn!("Force clock-out: Failed to send clock_out event: {}", e);
-                    // Queue the event for later sync if network is unavailable
-                    let _ = crate::storage::offline_queue::queue_event("clock_out", &event_data).await;
-                }
-            }
-        }
-        Err(e) => {
-            log::warn!("Force clock-out: Failed to create API client: {}", e);
-        }
+    if let Err(e) = crate::session_manager::end_session(crate::session_manager::EndReason::AppQuit).await {
+        log::warn!("Force clock-out: {}", e);
     }
 }
 
 fn main() {
+    // Launched by a browser as native-messaging host: relay extension messages and exit
+    if crate::sampling::browser_bridge::is_native_host_invocation() {
+        crate::sampling::browser_bridge::run_native_host();
+        return;
+    }
+    
     // Initialize logging
     logging::init();
+    i18n::init();
+    crate::profile::log_startup();
     
     // Setup Unix signal handlers for graceful shutdown on macOS/Linux
     // This catches Cmd+Q, Dock quit, and system shutdown signals
@@ -156,9 +138,16 @@ fn main() {
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .manage(Arc::new(Mutex::new(AppState::new())))
+        .manage(StateHandle::spawn())
         .invoke_handler(tauri::generate_handler![
             login,
+            resend_two_factor_code,
+            browser_login::start_browser_login,
+            browser_login::cancel_browser_login,
+            pairing::start_pairing,
+            pairing::complete_pairing,
+            pairing::cancel_pairing,
+            storage::storage_guard::get_storage_usage,
             logout,
             get_auth_status,
             get_device_token,
@@ -166,6 +155,7 @@ fn main() {
             get_consent_status,
             clock_in,
             clock_out,
+            adopt_backend_session,
             get_work_session,
             get_recent_sessions,
             clear_local_database,
@@ -175,13 +165,15 @@ fn main() {
             take_screenshot,
             get_current_app,
             send_diagnostics,
+            get_device_name_info,
+            set_device_name,
             get_permissions_status,
             request_permissions,
+            request_accessibility_permission,
+            request_automation_permission,
+            open_automation_settings,
             trigger_screen_permission_dialog,
             get_app_info,
-            send_app_focus_event,
-            send_heartbeat,
-            check_pending_jobs,
             get_idle_time,
             start_background_services,
             stop_background_services,
@@ -205,16 +197,87 @@ fn main() {
             update_manager::check_for_updates,
             update_manager::install_update,
             update_manager::get_current_version,
+            update_manager::get_update_channel,
+            update_manager::set_update_channel,
+            permissions::onboarding::get_onboarding_state,
+            permissions::onboarding::skip_onboarding_step,
+            update_check::get_update_check_status,
+            update_lockout::get_update_lockout_status,
+            update_rollback::get_rollback_status,
+            update_rollback::rollback_update,
+            update_schedule::get_install_schedule,
+            update_schedule::queue_update_install,
             update_manager::test_update_endpoint,
+            time_import::import_time_entries,
+            session_export::export_sessions_ics,
+            i18n::get_locale,
+            i18n::set_locale,
+            location::get_location_consent,
+            location::set_location_consent,
+            sampling::sleep_guard::get_prevent_sleep,
+            sampling::sleep_guard::set_prevent_sleep,
+            storage::activity_index::search_activity,
+            storage::activity_index::get_activity_recall_enabled,
+            storage::activity_index::set_activity_recall_enabled,
+            api::endpoints::get_server_endpoints,
+            api::bandwidth::get_upload_bandwidth_limit,
+            api::bandwidth::set_upload_bandwidth_limit,
+            search_sessions,
+            set_session_note,
+            snooze::snooze_tracking,
+            export_daily_reports_csv,
+            #[cfg(feature = "mock-data")]
+            storage::mock_data::generate_mock_data,
+            screenshots::review::get_screenshots_for_review,
+            screenshots::review::review_screenshot,
+            screenshots::review::get_screenshot_preview,
+            commands::get_day_summary,
         ])
         .setup(|app| {
-            // Set the global app state
-            let app_state = app.state::<Arc<Mutex<AppState>>>();
-            crate::storage::set_global_app_state(app_state.inner().clone());
+            // Expose the app state service to background services
+            let app_state = app.state::<StateHandle>();
+            crate::storage::set_state_service(app_state.inner().clone());
+            
+            // Let sampling/storage push tracking state to the frontend
+            crate::events::init(app.handle().clone());
+            
+            // Detect crashes of a freshly installed update and offer a rollback
+            if crate::update_rollback::on_startup() > 0 {
+                log::warn!("Previous launches of this version crashed, rollback will be offered");
+            }
+            let app_handle_for_rollback = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                crate::update_rollback::start_health_watch(app_handle_for_rollback).await;
+            });
+            
+            // Install queued updates once the policy's install window opens
+            let app_handle_for_schedule = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                crate::update_schedule::start_install_scheduler(app_handle_for_schedule).await;
+            });
+            
+            // Re-validate OS permission grants; the UI reopens the wizard if one was revoked
+            let app_handle_for_onboarding = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                crate::permissions::onboarding::check_on_launch(&app_handle_for_onboarding).await;
+            });
+            
+            // Pause screenshots and notify the UI if Screen Recording gets revoked
+            tauri::async_runtime::spawn(async move {
+                crate::permissions::watcher::start_screen_recording_watcher().await;
+            });
+            
+            // Check for updates in the background and surface them in the tray
+            let app_handle_for_update_check = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                crate::update_check::start_periodic_check(app_handle_for_update_check).await;
+            });
             
             // Initialize the database directly
             let app_handle_for_bg = app.handle().clone();
             tauri::async_runtime::spawn(async move {
+                let launched_at = chrono::Utc::now();
+                
                 // Check for version migration BEFORE initializing database
                 // This clears stale data if the app was updated
                 match crate::storage::check_version_and_migrate().await {
@@ -244,9 +307,53 @@ fn main() {
                 } else {
                 }
                 
+                crate::api::ingest_schema::load_accepted_versions().await;
+                crate::api::request_signing::load_secret().await;
+                crate::api::endpoints::load_fallback_urls().await;
+                crate::api::bandwidth::load_user_limit().await;
+                crate::update_lockout::init().await;
+                
+                // Key for encrypting screenshots at rest, needed before any capture
+                // (scheduled, manual or remote), then drop anything a previous run
+                // left behind unqueued
+                crate::screenshots::at_rest::load_key().await;
+                if let Err(e) = crate::storage::screenshot_queue::init_queue_table().await {
+                    log::error!("Failed to initialize screenshot queue table: {}", e);
+                }
+                match crate::storage::screenshot_queue::sweep_orphaned_files(launched_at).await {
+                    Ok(count) if count > 0 => log::info!("Removed {} orphaned screenshot file(s) at startup", count),
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Failed to sweep screenshot temp folder: {}", e),
+                }
+                
+                if let Err(e) = crate::api::title_rules::initialize_title_rules().await {
+                    log::error!("Failed to initialize title scrub rules: {}", e);
+                }
+                
+                crate::utils::public_suffix::initialize().await;
+                
+                if let Err(e) = crate::api::known_sites::initialize_known_sites().await {
+                    log::error!("Failed to initialize known sites: {}", e);
+                }
+                
                 // Initialize power state monitoring
                 crate::sampling::power_state::init();
                 
+                // Logoff and OS shutdown clock out the same way quitting does
+                crate::sampling::power_state::set_session_end_handler(|| async {
+                    if SHUTDOWN_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+                        return;
+                    }
+                    force_clock_out().await;
+                    log::info!("Force clock-out complete on session end");
+                });
+                #[cfg(target_os = "windows")]
+                crate::sampling::power_state::session_notifications::start();
+                
+                // A pause from before the restart stays in effect
+                crate::sampling::restore_pause_state().await;
+                crate::snooze::start_countdown();
+
                 // Start background services
                 crate::sampling::start_services().await;
                 tokio::spawn(crate::sampling::start_queue_processing_service());
@@ -254,8 +361,19 @@ fn main() {
                 // Start sync service for offline/online data synchronization
                 tokio::spawn(crate::sampling::start_sync_service());
                 
+                // Browser extension bridge (native messaging relay)
+                if let Err(e) = crate::sampling::browser_bridge::install_native_host_manifests() {
+                    log::warn!("Failed to register native messaging host: {}", e);
+                }
+                tokio::spawn(crate::sampling::browser_bridge::start_bridge_listener());
+                
+                // Weekly prune, ANALYZE and VACUUM during quiet periods
+                tokio::spawn(crate::storage::maintenance::start_maintenance_scheduler());
+                
                 // Start all sampling services - but only if user is authenticated AND clocked in
                 // This prevents race conditions where services try to access empty global state
+                crate::sampling::start_job_polling_service(app_handle_for_bg.clone()).await;
+                
                 tokio::spawn(async move {
                     // Wait for initial authentication check before starting services
                     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -272,11 +390,12 @@ fn main() {
             });
             
             // Create system tray
-            let quit_i = MenuItem::with_id(app, "quit", "Quit TrackEx", true, None::<&str>)?;
-            let pause_i = MenuItem::with_id(app, "pause", "Pause Tracking", true, None::<&str>)?;
-            let resume_i = MenuItem::with_id(app, "resume", "Resume Tracking", true, None::<&str>)?;
-            let show_i = MenuItem::with_id(app, "show", "Show TrackEx", true, None::<&str>)?;
-            let diagnostics_i = MenuItem::with_id(app, "diagnostics", "Send Diagnostics", true, None::<&str>)?;
+            let quit_i = i18n::menu_item(app.handle(), "quit", "tray.quit")?;
+            let pause_i = i18n::menu_item(app.handle(), "pause", "tray.pause")?;
+            let resume_i = i18n::menu_item(app.handle(), "resume", "tray.resume")?;
+            let show_i = i18n::menu_item(app.handle(), "show", "tray.show")?;
+            let diagnostics_i = i18n::menu_item(app.handle(), "diagnostics", "tray.diagnostics")?;
+            let update_i = crate::update_check::tray_item(app.handle())?;
             
             let menu = MenuBuilder::new(app)
                 .item(&show_i)
@@ -285,6 +404,7 @@ fn main() {
                 .item(&resume_i)
                 .separator()
                 .item(&diagnostics_i)
+                .item(&update_i)
                 .separator()
                 .item(&quit_i)
                 .build()?;
@@ -307,7 +427,7 @@ fn main() {
 
             let mut tray_builder = TrayIconBuilder::new()
                 .menu(&menu)
-                .tooltip("TrackEx Agent");
+                .tooltip(i18n::t("tray.tooltip"));
 
             if let Some(icon) = tray_icon {
                 tray_builder = tray_builder.icon(icon);
@@ -315,7 +435,7 @@ fn main() {
                 log::error!("Failed to load tray icon");
             }
 
-            let _tray = tray_builder
+            let tray = tray_builder
                 .on_menu_event(move |app, event| match event.id.as_ref() {
                     "quit" => {
                         log::info!("Quit requested from tray menu");
@@ -348,6 +468,19 @@ fn main() {
                     }
                     "diagnostics" => {
                         println!("Diagnostics requested from tray");
+                        tauri::async_runtime::spawn(async {
+                            if let Err(e) = crate::api::diagnostics::upload_diagnostics(None, 24).await {
+                                log::warn!("Failed to upload diagnostics: {}", e);
+                            }
+                        });
+                    }
+                    crate::update_check::TRAY_ITEM_ID => {
+                        if let Some(window) = app.get_webview_window("main") {
+                            let _ = window.show();
+                            let _ = window.set_focus();
+                        }
+                        // The update dialog runs the check and shows the result
+                        let _ = app.emit(crate::update_check::UPDATE_CHECK_REQUESTED_EVENT, ());
                     }
                     _ => {}
                 })
@@ -360,6 +493,8 @@ fn main() {
                     }
                 })
                 .build(app)?;
+            crate::tray_status::set_tray(tray);
+            crate::tray_status::follow_app_state();
 
             // Show main window on startup
             if let Some(window) = app.get_webview_window("main") {
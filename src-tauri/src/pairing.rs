@@ -0,0 +1,317 @@
+//! Device pairing without user credentials
+//!
+//! For shared machines (kiosks, shift workstations) nobody should type a
+//! password into the agent. In pairing mode the agent asks the backend for a
+//! short pairing code, shows it (and a QR code of the approval URL), and waits
+//! while an admin approves the device from the web dashboard:
+//!
+//! ```text
+//! agent: POST /api/devices/pairing {device info} -> {pairingId, secret, code, approvalUrl, expiresAt}
+//! admin: approves code in the dashboard, choosing the employee
+//! agent: GET /api/devices/pairing/<pairingId> (X-Pairing-Secret) until
+//!        {status: "approved", device: {id, token}, employee: {id, email}}
+//! ```
+//!
+//! The secret never leaves the agent, so knowing the displayed code is not
+//! enough to pick up the device token.
+
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::State;
+use tokio::sync::Notify;
+
+use crate::commands::AuthStatus;
+use crate::error::AgentError;
+use crate::storage::audit_log;
+use crate::storage::state_service::StateHandle;
+
+/// Used when the backend doesn't say how often to poll
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 3;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Consecutive failed status polls tolerated before pairing gives up
+const MAX_POLL_FAILURES: u32 = 10;
+
+/// Information shown on the pairing screen
+#[derive(Debug, Clone, Serialize)]
+pub struct PairingInfo {
+    /// Short code the admin enters or confirms in the dashboard
+    pub code: String,
+    pub approval_url: String,
+    /// QR code of `approval_url` as an SVG document
+    pub qr_svg: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PairingResponse {
+    pairing_id: String,
+    secret: String,
+    code: String,
+    approval_url: String,
+    expires_at: String,
+    #[serde(default)]
+    poll_interval_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingPairing {
+    server_url: String,
+    pairing_id: String,
+    secret: String,
+    poll_interval: Duration,
+}
+
+/// Credentials the backend hands out once the admin approves
+#[derive(Debug, PartialEq)]
+struct ApprovedDevice {
+    device_id: String,
+    device_token: String,
+    /// Per-device secret for signing ingest requests, if the backend issues one
+    signing_secret: Option<String>,
+    employee_id: String,
+    email: String,
+    has_no_license: bool,
+}
+
+/// Result of one status poll
+#[derive(Debug, PartialEq)]
+enum PairingStatus {
+    Pending,
+    Approved(ApprovedDevice),
+    /// Expired or rejected by the admin
+    Closed(String),
+}
+
+static PENDING: Mutex<Option<PendingPairing>> = Mutex::new(None);
+
+lazy_static::lazy_static! {
+    /// Wakes `complete_pairing` when the user leaves the pairing screen
+    static ref CANCEL: Notify = Notify::new();
+}
+
+fn render_qr(data: &str) -> Result<String, AgentError> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| AgentError::Internal(format!("Failed to render QR code: {}", e)))?;
+    Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+}
+
+fn parse_status(body: &serde_json::Value) -> Result<PairingStatus, AgentError> {
+    match body["status"].as_str().unwrap_or("pending") {
+        "pending" => Ok(PairingStatus::Pending),
+        "approved" => {
+            let field = |value: &serde_json::Value, name: &str| {
+                value
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| AgentError::Internal(format!("Missing {} in pairing response", name)))
+            };
+            Ok(PairingStatus::Approved(ApprovedDevice {
+                device_id: field(&body["device"]["id"], "device ID")?,
+                device_token: field(&body["device"]["token"], "device token")?,
+                signing_secret: body["device"]["signingSecret"].as_str().map(str::to_string),
+                employee_id: field(&body["employee"]["id"], "employee ID")?,
+                // Shared devices may be bound to an employee without a login email
+                email: body["employee"]["email"].as_str().unwrap_or_default().to_string(),
+                has_no_license: body["license"]["valid"].as_bool() == Some(false),
+            }))
+        }
+        other => Ok(PairingStatus::Closed(other.to_string())),
+    }
+}
+
+
+/// Request a pairing code for this device
+#[tauri::command]
+pub async fn start_pairing(server_url: String) -> Result<PairingInfo, AgentError> {
+    let server_url = server_url.trim_end_matches('/').to_string();
+    let device_uuid = crate::storage::database::get_or_create_device_uuid()
+        .map_err(|e| log::warn!("Failed to get/create device UUID: {}", e))
+        .ok();
+
+    let response = crate::api::client::shared_client()
+        .post(format!("{}/api/devices/pairing", server_url))
+        .timeout(REQUEST_TIMEOUT)
+        .json(&serde_json::json!({
+            "deviceName": crate::commands::get_display_device_name().await,
+            "platform": crate::commands::get_platform_name(),
+            "osVersion": crate::commands::get_os_version(),
+            "appVersion": env!("CARGO_PKG_VERSION"),
+            "deviceUuid": device_uuid,
+            "environment": crate::utils::session_environment::detect()
+        }))
+        .send()
+        .await
+        .map_err(AgentError::from)?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(AgentError::from_status(status, format!("Failed to start pairing: {}", error_text)));
+    }
+    let pairing: PairingResponse = response
+        .json()
+        .await
+        .map_err(|e| AgentError::Internal(format!("Failed to parse pairing response: {}", e)))?;
+
+    let info = PairingInfo {
+        code: pairing.code.clone(),
+        qr_svg: render_qr(&pairing.approval_url)?,
+        approval_url: pairing.approval_url.clone(),
+        expires_at: pairing.expires_at.clone(),
+    };
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = Some(PendingPairing {
+            server_url,
+            pairing_id: pairing.pairing_id,
+            secret: pairing.secret,
+            poll_interval: Duration::from_secs(pairing.poll_interval_seconds.unwrap_or(DEFAULT_POLL_INTERVAL_SECS).max(1)),
+        });
+    }
+    log::info!("Device pairing started, waiting for approval");
+    Ok(info)
+}
+
+/// Whether a failed poll is worth retrying (connectivity, server trouble)
+fn is_transient(error: &AgentError) -> bool {
+    match error {
+        AgentError::Offline(_) | AgentError::Network(_) => true,
+        AgentError::Server { status, .. } => *status >= 500 || *status == 429,
+        _ => false,
+    }
+}
+
+async fn poll_status(client: &reqwest::Client, url: &str, pending: &PendingPairing) -> Result<PairingStatus, AgentError> {
+    let response = client
+        .get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .header("X-Pairing-Secret", &pending.secret)
+        .send()
+        .await
+        .map_err(AgentError::from)?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(AgentError::from_status(status, format!("Pairing failed: {}", error_text)));
+    }
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| AgentError::Internal(format!("Failed to parse pairing status: {}", e)))?;
+    parse_status(&body)
+}
+
+async fn poll_until_approved(client: &reqwest::Client, pending: &PendingPairing) -> Result<ApprovedDevice, AgentError> {
+    let url = format!("{}/api/devices/pairing/{}", pending.server_url, pending.pairing_id);
+    let mut failures = 0;
+    loop {
+        match poll_status(client, &url, pending).await {
+            Ok(PairingStatus::Pending) => failures = 0,
+            Ok(PairingStatus::Approved(device)) => return Ok(device),
+            Ok(PairingStatus::Closed(reason)) => return Err(AgentError::Internal(format!("Pairing {}", reason))),
+            Err(e) if is_transient(&e) && failures < MAX_POLL_FAILURES => {
+                failures += 1;
+                log::warn!("Pairing status poll failed ({}/{}), retrying: {}", failures, MAX_POLL_FAILURES, e);
+            }
+            Err(e) => return Err(e),
+        }
+        tokio::time::sleep(pending.poll_interval).await;
+    }
+}
+
+async fn complete_pairing_impl(state: &StateHandle) -> Result<AuthStatus, AgentError> {
+    let pending = PENDING
+        .lock()
+        .ok()
+        .and_then(|p| p.clone())
+        .ok_or_else(|| AgentError::Internal("No pairing in progress".to_string()))?;
+    let client = crate::api::client::shared_client();
+
+    let result = tokio::select! {
+        result = poll_until_approved(&client, &pending) => result,
+        _ = CANCEL.notified() => return Err(AgentError::Internal("Pairing cancelled".to_string())),
+    };
+    if let Ok(mut current) = PENDING.lock() {
+        *current = None;
+    }
+
+    let device = result?;
+    log::info!("Device pairing approved");
+    crate::commands::establish_session(
+        &pending.server_url,
+        &device.email,
+        &device.employee_id,
+        &device.device_id,
+        &device.device_token,
+        device.signing_secret.as_deref(),
+        device.has_no_license,
+        state,
+    )
+    .await
+}
+
+/// Wait for the admin to approve the pairing started with `start_pairing`
+#[tauri::command]
+pub async fn complete_pairing(state: State<'_, StateHandle>) -> Result<AuthStatus, AgentError> {
+    let result = complete_pairing_impl(&state).await;
+    audit_log::record("complete_pairing", &result);
+    result
+}
+
+/// Abandon the pending pairing
+#[tauri::command]
+pub async fn cancel_pairing() -> Result<(), AgentError> {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = None;
+    }
+    CANCEL.notify_waiters();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pairing_status() {
+        assert_eq!(parse_status(&serde_json::json!({ "status": "pending" })).unwrap(), PairingStatus::Pending);
+        assert_eq!(
+            parse_status(&serde_json::json!({ "status": "expired" })).unwrap(),
+            PairingStatus::Closed("expired".to_string())
+        );
+
+        let approved = serde_json::json!({
+            "status": "approved",
+            "device": { "id": "d1", "token": "tok" },
+            "employee": { "id": "e1" },
+            "license": { "valid": false }
+        });
+        match parse_status(&approved).unwrap() {
+            PairingStatus::Approved(device) => {
+                assert_eq!(device.device_id, "d1");
+                assert_eq!(device.email, "");
+                assert!(device.has_no_license);
+            }
+            other => panic!("unexpected status {:?}", other),
+        }
+
+        assert!(parse_status(&serde_json::json!({ "status": "approved", "device": { "id": "d1" } })).is_err());
+    }
+
+    #[test]
+    fn test_transient_poll_errors() {
+        assert!(is_transient(&AgentError::Offline("timeout".to_string())));
+        assert!(is_transient(&AgentError::Server { status: 503, message: String::new() }));
+        assert!(!is_transient(&AgentError::Server { status: 404, message: String::new() }));
+        assert!(!is_transient(&AgentError::Internal("Failed to parse pairing status".to_string())));
+    }
+
+    #[test]
+    fn test_render_qr_svg() {
+        let svg = render_qr("https://trackex.example/pair/ABC-123").unwrap();
+        assert!(svg.contains("<svg"));
+    }
+}
@@ -0,0 +1,122 @@
+//! macOS Accessibility and Automation (Apple Events) permission probes
+//!
+//! Accessibility is read with `AXIsProcessTrusted`. Automation is per target
+//! app; `AEDeterminePermissionToAutomateTarget` reports the TCC decision
+//! without prompting, so the browsers used for URL capture can be probed up
+//! front instead of discovering a denial from a failed AppleScript call.
+
+use std::ffi::c_void;
+
+use core_foundation::base::TCFType;
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::string::{CFString, CFStringRef};
+
+use super::AutomationPermission;
+
+/// Browsers queried over AppleScript for the active tab URL (see sampling/browser_url.rs)
+pub const AUTOMATION_TARGETS: [&str; 6] = [
+    "com.google.Chrome",
+    "com.microsoft.edgemac",
+    "com.brave.Browser",
+    "com.vivaldi.Vivaldi",
+    "com.operasoftware.Opera",
+    "company.thebrowser.Browser",
+];
+
+/// typeApplicationBundleID ('bund')
+const TYPE_APPLICATION_BUNDLE_ID: u32 = 0x62756e64;
+/// typeWildCard ('****'): any event class / id
+const TYPE_WILD_CARD: u32 = 0x2a2a2a2a;
+
+const NO_ERR: i32 = 0;
+/// errAEEventNotPermitted: the user refused
+const ERR_AE_EVENT_NOT_PERMITTED: i32 = -1743;
+/// errAEEventWouldRequireUserConsent: not asked yet
+const ERR_AE_EVENT_WOULD_REQUIRE_USER_CONSENT: i32 = -1744;
+
+#[repr(C)]
+struct AEDesc {
+    descriptor_type: u32,
+    data_handle: *mut c_void,
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+    fn AXIsProcessTrustedWithOptions(options: *const c_void) -> bool;
+    static kAXTrustedCheckOptionPrompt: CFStringRef;
+}
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn AECreateDesc(type_code: u32, data: *const c_void, size: isize, result: *mut AEDesc) -> i16;
+    fn AEDisposeDesc(desc: *mut AEDesc) -> i16;
+    fn AEDeterminePermissionToAutomateTarget(
+        target: *const AEDesc,
+        event_class: u32,
+        event_id: u32,
+        ask_user_if_needed: u8,
+    ) -> i32;
+}
+
+/// Whether the agent is trusted for Accessibility
+pub fn is_accessibility_trusted() -> bool {
+    unsafe { AXIsProcessTrusted() }
+}
+
+/// Show the system Accessibility prompt (only shown once per app by macOS)
+pub fn prompt_accessibility() -> bool {
+    unsafe {
+        let key = CFString::wrap_under_get_rule(kAXTrustedCheckOptionPrompt);
+        let options = CFDictionary::from_CFType_pairs(&[(key, CFBoolean::true_value())]);
+        AXIsProcessTrustedWithOptions(options.as_concrete_TypeRef() as *const c_void)
+    }
+}
+
+/// Automation permission for a target app. With `ask` the system prompt is
+/// shown if the user hasn't decided yet; this blocks until they answer, so
+/// callers must run it off the async runtime.
+pub fn automation_permission(bundle_id: &str, ask: bool) -> AutomationPermission {
+    let status = unsafe {
+        let mut desc = AEDesc { descriptor_type: 0, data_handle: std::ptr::null_mut() };
+        let created = AECreateDesc(
+            TYPE_APPLICATION_BUNDLE_ID,
+            bundle_id.as_ptr() as *const c_void,
+            bundle_id.len() as isize,
+            &mut desc,
+        );
+        if created != 0 {
+            return AutomationPermission::Unknown;
+        }
+        let status = AEDeterminePermissionToAutomateTarget(&desc, TYPE_WILD_CARD, TYPE_WILD_CARD, ask as u8);
+        AEDisposeDesc(&mut desc);
+        status
+    };
+
+    automation_from_status(status)
+}
+
+/// Map the AEDeterminePermissionToAutomateTarget status. Other errors (e.g.
+/// procNotFound when the app isn't running) leave the state unknown.
+fn automation_from_status(status: i32) -> AutomationPermission {
+    match status {
+        NO_ERR => AutomationPermission::Granted,
+        ERR_AE_EVENT_NOT_PERMITTED => AutomationPermission::Denied,
+        ERR_AE_EVENT_WOULD_REQUIRE_USER_CONSENT => AutomationPermission::Unknown,
+        _ => AutomationPermission::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_automation_status_mapping() {
+        assert_eq!(automation_from_status(0), AutomationPermission::Granted);
+        assert_eq!(automation_from_status(-1743), AutomationPermission::Denied);
+        assert_eq!(automation_from_status(-1744), AutomationPermission::Unknown);
+        assert_eq!(automation_from_status(-600), AutomationPermission::Unknown);
+    }
+}
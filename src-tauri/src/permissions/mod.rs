@@ -0,0 +1,308 @@
+#[cfg(target_os = "macos")]
+pub mod macos;
+pub mod onboarding;
+pub mod watcher;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+#[cfg(target_os = "macos")]
+use core_graphics::access::ScreenCaptureAccess;
+
+#[cfg(target_os = "windows")]
+use windows::{
+    Win32::{
+        UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN},
+    },
+};
+
+// Global flag to prevent duplicate permission requests
+static PERMISSION_REQUEST_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// macOS Automation (Apple Events) permission state per target app bundle id
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationPermission {
+    /// Not asked yet (or the outcome is not known)
+    Unknown,
+    Granted,
+    Denied,
+}
+
+lazy_static::lazy_static! {
+    static ref AUTOMATION_PERMISSIONS: Mutex<HashMap<String, AutomationPermission>> =
+        Mutex::new(HashMap::new());
+}
+
+/// AppleScript error returned when the user refused Automation access ("Not authorized to send Apple events")
+pub const APPLE_EVENTS_NOT_AUTHORIZED: &str = "-1743";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PermissionsStatus {
+    pub screen_recording: bool,
+    pub accessibility: bool,
+    /// Automation permission per browser bundle id (macOS)
+    #[serde(default)]
+    pub automation: HashMap<String, AutomationPermission>,
+    /// Some browsers refused Automation, so their URLs are not captured
+    #[serde(default)]
+    pub url_capture_limited: bool,
+    /// Window titles can be read (needs Accessibility or Screen Recording on macOS)
+    #[serde(default)]
+    pub window_titles_available: bool,
+}
+
+impl Default for PermissionsStatus {
+    fn default() -> Self {
+        Self {
+            screen_recording: false,
+            accessibility: true, // We'll assume this is available for now
+            automation: HashMap::new(),
+            url_capture_limited: false,
+            window_titles_available: true,
+        }
+    }
+}
+
+/// Check if screen recording permission is granted
+pub async fn has_screen_recording_permission() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        ScreenCaptureAccess::default().preflight()
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        // On Windows, we can test screen capture by trying to get screen dimensions
+        // If this fails, it might indicate permission issues
+        unsafe {
+            let width = GetSystemMetrics(SM_CXSCREEN);
+            let height = GetSystemMetrics(SM_CYSCREEN);
+            width > 0 && height > 0
+        }
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        true // Assume permission on other platforms
+    }
+}
+
+/// Check if accessibility permission is granted
+pub async fn has_accessibility_permission() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        macos::is_accessibility_trusted()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        true // Not a separate grant on other platforms
+    }
+}
+
+/// Show the macOS Accessibility prompt and open the matching settings pane
+pub async fn request_accessibility_permission() -> Result<bool> {
+    #[cfg(target_os = "macos")]
+    {
+        if macos::prompt_accessibility() {
+            return Ok(true);
+        }
+        std::process::Command::new("open")
+            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility")
+            .spawn()?;
+        Ok(false)
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        Ok(true)
+    }
+}
+
+/// Read the Automation decision for the browsers used for URL capture,
+/// without prompting, and remember it so denied browsers aren't queried
+pub async fn probe_automation_targets() {
+    #[cfg(target_os = "macos")]
+    {
+        let results = tokio::task::spawn_blocking(|| {
+            macos::AUTOMATION_TARGETS
+                .iter()
+                .map(|bundle_id| (*bundle_id, macos::automation_permission(bundle_id, false)))
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+
+        for (bundle_id, permission) in results {
+            if permission != AutomationPermission::Unknown {
+                record_automation_result(bundle_id, permission);
+            }
+        }
+    }
+}
+
+/// Ask the user for Automation access to a browser (shows the system prompt
+/// if they haven't decided yet)
+pub async fn request_automation_permission(bundle_id: &str) -> AutomationPermission {
+    #[cfg(target_os = "macos")]
+    {
+        let target = bundle_id.to_string();
+        let permission = tokio::task::spawn_blocking(move || macos::automation_permission(&target, true))
+            .await
+            .unwrap_or(AutomationPermission::Unknown);
+        if permission != AutomationPermission::Unknown {
+            record_automation_result(bundle_id, permission);
+        }
+        permission
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = bundle_id;
+        AutomationPermission::Granted
+    }
+}
+
+/// Automation permission for controlling the given app (e.g. reading the URL from Chrome)
+pub fn automation_permission(bundle_id: &str) -> AutomationPermission {
+    AUTOMATION_PERMISSIONS
+        .lock()
+        .unwrap()
+        .get(bundle_id)
+        .copied()
+        .unwrap_or(AutomationPermission::Unknown)
+}
+
+/// Automation outcomes recorded so far, across all target apps
+pub fn automation_results() -> Vec<AutomationPermission> {
+    AUTOMATION_PERMISSIONS.lock().unwrap().values().copied().collect()
+}
+
+/// Whether Apple Events may be sent to the given app. Once denied we stop
+/// asking so the user isn't prompted (or the query isn't attempted) on every sample.
+pub fn can_automate(bundle_id: &str) -> bool {
+    automation_permission(bundle_id) != AutomationPermission::Denied
+}
+
+/// Record the outcome of an Apple Events call to the given app
+pub fn record_automation_result(bundle_id: &str, permission: AutomationPermission) {
+    let mut permissions = AUTOMATION_PERMISSIONS.lock().unwrap();
+    let previous = permissions.insert(bundle_id.to_string(), permission);
+    if previous != Some(permission) {
+        log::info!("Automation permission for {}: {:?}", bundle_id, permission);
+    }
+}
+
+/// Get comprehensive permissions status
+pub async fn get_permissions_status() -> PermissionsStatus {
+    probe_automation_targets().await;
+
+    let screen_recording = has_screen_recording_permission().await;
+    let accessibility = has_accessibility_permission().await;
+    let automation = AUTOMATION_PERMISSIONS.lock().unwrap().clone();
+    PermissionsStatus {
+        url_capture_limited: automation.values().any(|p| *p == AutomationPermission::Denied),
+        window_titles_available: accessibility || screen_recording,
+        screen_recording,
+        accessibility,
+        automation,
+    }
+}
+
+/// Request permissions in a controlled, single-request manner
+pub async fn request_permissions() -> Result<()> {
+    // Prevent duplicate requests
+    if PERMISSION_REQUEST_IN_PROGRESS.load(Ordering::Acquire) {
+        return Ok(());
+    }
+
+    PERMISSION_REQUEST_IN_PROGRESS.store(true, Ordering::Release);
+    
+    let result = request_permissions_internal().await;
+    
+    PERMISSION_REQUEST_IN_PROGRESS.store(false, Ordering::Release);
+    
+    result
+}
+
+async fn request_permissions_internal() -> Result<()> {
+    
+    #[cfg(target_os = "macos")]
+    {
+        log::info!("Requesting screen recording permission...");
+        
+        // Request screen recording permission
+        if !has_screen_recording_permission().await {
+            log::info!("Screen recording permission not granted, requesting...");
+            
+            // The request() method triggers the permission dialog
+            let result = ScreenCaptureAccess::default().request();
+            log::info!("Screen recording permission request result: {:?}", result);
+            
+            // Small delay to allow dialog to appear
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        } else {
+            log::info!("Screen recording permission already granted");
+        }
+        
+        // Give macOS time to show permission dialogs and user to respond
+        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        // On Windows, most permissions are granted by default
+        // We can test screen capture capability
+        
+        // Try to get screen dimensions as a test
+        let has_screen_access = has_screen_recording_permission().await;
+        if has_screen_access {
+        } else {
+            log::warn!("Windows screen capture access may be limited");
+        }
+        
+        // Windows doesn't require explicit permission requests for most functionality
+        // The app will work with the permissions it has
+    }
+    
+    Ok(())
+}
+
+/// Open macOS Privacy Settings to the Screen Recording section
+#[allow(dead_code)]
+pub async fn open_privacy_settings() -> Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_ScreenCapture")
+            .spawn()?;
+    }
+    
+    Ok(())
+}
+
+/// Open macOS Privacy Settings to the Automation section (to re-enable browser URL access)
+pub async fn open_automation_settings() -> Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Automation")
+            .spawn()?;
+    }
+    
+    // Denied entries are re-checked after the user changed the setting
+    AUTOMATION_PERMISSIONS.lock().unwrap().clear();
+    
+    Ok(())
+}
+
+/// Check if all required permissions are granted
+#[allow(dead_code)]
+pub async fn are_required_permissions_granted() -> bool {
+    let status = get_permissions_status().await;
+    status.screen_recording
+}
@@ -0,0 +1,311 @@
+//! Permission onboarding state machine
+//!
+//! Sequences the OS grants the agent needs on the current platform (screen
+//! recording, accessibility, automation, notifications) into ordered steps.
+//! Progress (skipped optional steps, completion time) is persisted in
+//! `<data_dir>/TrackEx/onboarding.json`, but grants are re-checked on every
+//! launch: a permission revoked in System Settings puts the wizard back on
+//! that step. Automation can only be checked while a browser runs; when
+//! none answers, the step keeps the last grant seen. The UI only needs
+//! `get_onboarding_state`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::Emitter;
+use tauri_plugin_notification::{NotificationExt, PermissionState};
+
+use crate::error::AgentError;
+
+/// Emitted on launch when onboarding is not complete
+pub const ONBOARDING_REQUIRED_EVENT: &str = "onboarding-required";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    ScreenRecording,
+    Accessibility,
+    Automation,
+    Notifications,
+}
+
+impl OnboardingStep {
+    /// Steps for this platform, in the order the wizard walks through them
+    pub fn for_platform() -> Vec<OnboardingStep> {
+        if cfg!(target_os = "macos") {
+            vec![
+                OnboardingStep::ScreenRecording,
+                OnboardingStep::Accessibility,
+                OnboardingStep::Automation,
+                OnboardingStep::Notifications,
+            ]
+        } else {
+            vec![OnboardingStep::Notifications]
+        }
+    }
+
+    /// Required steps can't be skipped; tracking doesn't work without them
+    pub fn is_required(&self) -> bool {
+        matches!(self, OnboardingStep::ScreenRecording | OnboardingStep::Accessibility)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Not granted yet (or not asked yet)
+    Pending,
+    Granted,
+    /// The user refused; it has to be enabled in the OS settings
+    Denied,
+    /// Optional step the user chose to skip
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StepState {
+    pub step: OnboardingStep,
+    pub status: StepStatus,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct OnboardingState {
+    pub steps: Vec<StepState>,
+    /// The step the wizard should show, `None` when done
+    pub current_step: Option<OnboardingStep>,
+    pub completed: bool,
+    /// When onboarding was first completed (RFC 3339)
+    pub completed_at: Option<String>,
+}
+
+/// Persisted wizard progress
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct OnboardingProgress {
+    #[serde(default)]
+    skipped: Vec<OnboardingStep>,
+    /// Steps last seen granted, for checks that can't always answer
+    #[serde(default)]
+    granted: Vec<OnboardingStep>,
+    #[serde(default)]
+    completed_at: Option<String>,
+}
+
+fn progress_path() -> Result<PathBuf> {
+    let mut path = crate::profile::data_dir().ok_or_else(|| anyhow::anyhow!("No data directory"))?;
+    path.push("onboarding.json");
+    Ok(path)
+}
+
+fn load_progress() -> OnboardingProgress {
+    progress_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_progress(progress: &OnboardingProgress) -> Result<()> {
+    let path = progress_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(progress)?)?;
+    Ok(())
+}
+
+/// Current grant for a step, without prompting the user; `None` when it
+/// can't be checked right now
+async fn check_step(app: &tauri::AppHandle, step: OnboardingStep) -> Option<StepStatus> {
+    let granted = |granted: bool| Some(if granted { StepStatus::Granted } else { StepStatus::Pending });
+    match step {
+        OnboardingStep::ScreenRecording => granted(super::has_screen_recording_permission().await),
+        OnboardingStep::Accessibility => granted(super::has_accessibility_permission().await),
+        OnboardingStep::Automation => {
+            super::probe_automation_targets().await;
+            let results = super::automation_results();
+            if results.iter().any(|p| *p == super::AutomationPermission::Denied) {
+                Some(StepStatus::Denied)
+            } else if results.iter().any(|p| *p == super::AutomationPermission::Granted) {
+                Some(StepStatus::Granted)
+            } else {
+                // No supported browser running to ask
+                None
+            }
+        }
+        OnboardingStep::Notifications => match app.notification().permission_state() {
+            Ok(PermissionState::Granted) => Some(StepStatus::Granted),
+            Ok(PermissionState::Denied) => Some(StepStatus::Denied),
+            Ok(_) => Some(StepStatus::Pending),
+            Err(e) => {
+                log::warn!("Failed to read notification permission: {}", e);
+                Some(StepStatus::Pending)
+            }
+        },
+    }
+}
+
+/// Remember which steps were granted; returns whether that changed
+fn record_grants(progress: &mut OnboardingProgress, checked: &[(OnboardingStep, Option<StepStatus>)]) -> bool {
+    let before = progress.granted.clone();
+    for (step, status) in checked {
+        match status {
+            Some(StepStatus::Granted) if !progress.granted.contains(step) => progress.granted.push(*step),
+            Some(StepStatus::Granted) | None => {}
+            Some(_) => progress.granted.retain(|granted| granted != step),
+        }
+    }
+    progress.granted != before
+}
+
+/// Combine the checked grants with the persisted progress; steps that
+/// couldn't be checked keep their last seen grant
+fn build_state(checked: Vec<(OnboardingStep, Option<StepStatus>)>, progress: &OnboardingProgress) -> OnboardingState {
+    let steps: Vec<StepState> = checked
+        .into_iter()
+        .map(|(step, status)| {
+            let required = step.is_required();
+            let status = status.unwrap_or(if progress.granted.contains(&step) {
+                StepStatus::Granted
+            } else {
+                StepStatus::Pending
+            });
+            let status = if status != StepStatus::Granted && !required && progress.skipped.contains(&step) {
+                StepStatus::Skipped
+            } else {
+                status
+            };
+            StepState { step, status, required }
+        })
+        .collect();
+
+    let current_step = steps
+        .iter()
+        .find(|s| matches!(s.status, StepStatus::Pending | StepStatus::Denied))
+        .map(|s| s.step);
+
+    OnboardingState {
+        completed: current_step.is_none(),
+        current_step,
+        steps,
+        completed_at: progress.completed_at.clone(),
+    }
+}
+
+/// Re-check all grants and update the persisted progress
+pub async fn current_state(app: &tauri::AppHandle) -> OnboardingState {
+    let mut progress = load_progress();
+    let mut checked = Vec::new();
+    for step in OnboardingStep::for_platform() {
+        checked.push((step, check_step(app, step).await));
+    }
+
+    let mut changed = record_grants(&mut progress, &checked);
+    let mut state = build_state(checked, &progress);
+    if state.completed && progress.completed_at.is_none() {
+        progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
+        state.completed_at = progress.completed_at.clone();
+        changed = true;
+    }
+    if changed {
+        if let Err(e) = save_progress(&progress) {
+            log::warn!("Failed to save onboarding progress: {}", e);
+        }
+    }
+    state
+}
+
+/// Re-validate grants on launch and tell the UI if the wizard has to run
+pub async fn check_on_launch(app: &tauri::AppHandle) {
+    let state = current_state(app).await;
+    if state.completed {
+        return;
+    }
+
+    if state.completed_at.is_some() {
+        log::warn!("Onboarding was completed before, but {:?} is no longer granted", state.current_step);
+    }
+    if let Err(e) = app.emit(ONBOARDING_REQUIRED_EVENT, &state) {
+        log::warn!("Failed to emit onboarding event: {}", e);
+    }
+}
+
+/// Onboarding wizard state: which grants are done and which step comes next
+#[tauri::command]
+pub async fn get_onboarding_state(app: tauri::AppHandle) -> Result<OnboardingState, AgentError> {
+    Ok(current_state(&app).await)
+}
+
+/// Skip an optional onboarding step
+#[tauri::command]
+pub async fn skip_onboarding_step(app: tauri::AppHandle, step: OnboardingStep) -> Result<OnboardingState, AgentError> {
+    if step.is_required() {
+        return Err(AgentError::PermissionDenied(format!("{:?} permission is required and can't be skipped", step)));
+    }
+
+    let mut progress = load_progress();
+    if !progress.skipped.contains(&step) {
+        progress.skipped.push(step);
+        save_progress(&progress).map_err(|e| AgentError::Storage(e.to_string()))?;
+    }
+    Ok(current_state(&app).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_step_follows_order() {
+        let progress = OnboardingProgress::default();
+        let state = build_state(
+            vec![
+                (OnboardingStep::ScreenRecording, Some(StepStatus::Granted)),
+                (OnboardingStep::Accessibility, Some(StepStatus::Pending)),
+                (OnboardingStep::Notifications, Some(StepStatus::Pending)),
+            ],
+            &progress,
+        );
+        assert_eq!(state.current_step, Some(OnboardingStep::Accessibility));
+        assert!(!state.completed);
+    }
+
+    #[test]
+    fn test_skipped_optional_steps_complete_onboarding() {
+        let progress = OnboardingProgress {
+            skipped: vec![OnboardingStep::Automation, OnboardingStep::ScreenRecording],
+            ..OnboardingProgress::default()
+        };
+        let state = build_state(
+            vec![
+                (OnboardingStep::ScreenRecording, Some(StepStatus::Granted)),
+                (OnboardingStep::Automation, Some(StepStatus::Denied)),
+            ],
+            &progress,
+        );
+        assert_eq!(state.steps[1].status, StepStatus::Skipped);
+        assert!(state.completed);
+
+        // Revoked required permission: skipping it has no effect
+        let state = build_state(vec![(OnboardingStep::ScreenRecording, Some(StepStatus::Pending))], &progress);
+        assert_eq!(state.current_step, Some(OnboardingStep::ScreenRecording));
+    }
+
+    #[test]
+    fn test_unchecked_automation_keeps_last_grant() {
+        let mut progress = OnboardingProgress::default();
+        let granted = [(OnboardingStep::Automation, Some(StepStatus::Granted))];
+        assert!(record_grants(&mut progress, &granted));
+        assert!(!record_grants(&mut progress, &granted));
+
+        // No browser running on a later launch
+        let state = build_state(vec![(OnboardingStep::Automation, None)], &progress);
+        assert_eq!(state.steps[0].status, StepStatus::Granted);
+        assert!(state.completed);
+
+        // Denied since then
+        assert!(record_grants(&mut progress, &[(OnboardingStep::Automation, Some(StepStatus::Denied))]));
+        let state = build_state(vec![(OnboardingStep::Automation, None)], &progress);
+        assert_eq!(state.current_step, Some(OnboardingStep::Automation));
+    }
+}
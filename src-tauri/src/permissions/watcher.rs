@@ -0,0 +1,131 @@
+//! Screen Recording permission watcher
+//!
+//! Screen Recording can be revoked in System Settings while the employee is
+//! clocked in, after which every screenshot attempt fails. The watcher
+//! re-validates the permission periodically; when it goes away it emits a
+//! `permission-lost` event to the UI, reports it to the backend and pauses
+//! screenshot capture (auto screenshots and screenshot jobs fail fast with a
+//! clear error) until the permission is granted again.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::api::client::ApiClient;
+
+/// How often the permission is re-validated
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Name used in events and backend reports
+pub const SCREEN_RECORDING: &str = "screen_recording";
+
+static SCREEN_RECORDING_LOST: AtomicBool = AtomicBool::new(false);
+
+/// Last observed grant, `None` before the first check
+static LAST_GRANTED: Mutex<Option<bool>> = Mutex::new(None);
+
+/// Whether screenshot capture is paused because the permission was revoked
+pub fn screenshots_paused() -> bool {
+    SCREEN_RECORDING_LOST.load(Ordering::SeqCst)
+}
+
+/// Fail fast instead of attempting a capture that can't succeed
+pub fn ensure_screen_recording() -> anyhow::Result<()> {
+    if screenshots_paused() {
+        return Err(anyhow::anyhow!(crate::i18n::t("error.screen_recording_revoked")));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transition {
+    Lost,
+    Restored,
+}
+
+/// Only a change from granted to missing counts as a revocation; a permission
+/// that was never granted is handled by onboarding
+fn transition(previous: Option<bool>, granted: bool) -> Option<Transition> {
+    match (previous, granted) {
+        (Some(true), false) => Some(Transition::Lost),
+        (Some(false), true) => Some(Transition::Restored),
+        _ => None,
+    }
+}
+
+async fn report_to_backend(event_type: &str) {
+    if !crate::sampling::is_authenticated().await {
+        return;
+    }
+    let client = match ApiClient::new().await {
+        Ok(client) => client,
+        Err(e) => {
+            log::warn!("Failed to create API client for {} report: {}", event_type, e);
+            return;
+        }
+    };
+    let event_data = serde_json::json!({
+        "events": [{
+            "type": event_type,
+            "timestamp": crate::utils::time::event_timestamp(),
+            "data": {
+                "permission": SCREEN_RECORDING,
+                "platform": std::env::consts::OS
+            }
+        }]
+    });
+    match client.post_with_auth("/api/ingest/events", &event_data).await {
+        Ok(response) if !response.status().is_success() => {
+            log::warn!("Backend rejected {} report: {}", event_type, response.status());
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to report {}: {}", event_type, e),
+    }
+}
+
+async fn check_once() {
+    let granted = super::has_screen_recording_permission().await;
+    let previous = {
+        let mut last = LAST_GRANTED.lock().unwrap();
+        last.replace(granted)
+    };
+
+    match transition(previous, granted) {
+        Some(Transition::Lost) => {
+            log::warn!("Screen Recording permission revoked, pausing screenshot capture");
+            SCREEN_RECORDING_LOST.store(true, Ordering::SeqCst);
+            crate::events::permission_lost(SCREEN_RECORDING);
+            crate::events::notify("notification.permission_lost.title", "notification.permission_lost.body");
+            report_to_backend("permission_lost").await;
+        }
+        Some(Transition::Restored) => {
+            log::info!("Screen Recording permission restored, resuming screenshot capture");
+            SCREEN_RECORDING_LOST.store(false, Ordering::SeqCst);
+            crate::events::permission_restored(SCREEN_RECORDING);
+            report_to_backend("permission_restored").await;
+        }
+        None => {}
+    }
+}
+
+/// Re-validate Screen Recording for the lifetime of the app
+pub async fn start_screen_recording_watcher() {
+    let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        check_once().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition() {
+        assert_eq!(transition(Some(true), false), Some(Transition::Lost));
+        assert_eq!(transition(Some(false), true), Some(Transition::Restored));
+        assert_eq!(transition(None, false), None);
+        assert_eq!(transition(Some(true), true), None);
+    }
+}
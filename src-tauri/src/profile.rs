@@ -0,0 +1,147 @@
+//! Server profile: production or staging
+//!
+//! QA runs the production and staging agents side by side. The staging
+//! profile talks to the staging server by default and keeps its own
+//! keychain/credential entries and data directory (database, logs, caches),
+//! so neither agent clobbers the other's session.
+//!
+//! The profile is chosen at build time with the `staging` cargo feature and
+//! can be overridden at runtime with `TRACKEX_PROFILE=staging|production`.
+//! Browsers don't pass that variable to the native-messaging host, so host
+//! mode picks the profile itself with `set`.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Runtime override of the build profile
+const PROFILE_ENV: &str = "TRACKEX_PROFILE";
+
+const STAGING_SERVER_URL: &str = "https://staging.trackex.app";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    Production,
+    Staging,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 2] = [Profile::Production, Profile::Staging];
+}
+
+static PROFILE: OnceLock<Profile> = OnceLock::new();
+
+/// Unrecognized `TRACKEX_PROFILE` value; the profile is resolved while the
+/// logger is being set up, so it's reported later by `log_startup`
+static IGNORED_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+fn parse(value: &str) -> Option<Profile> {
+    match value.trim().to_lowercase().as_str() {
+        "production" | "prod" => Some(Profile::Production),
+        "staging" | "sandbox" => Some(Profile::Staging),
+        _ => None,
+    }
+}
+
+/// Profile selected by the `staging` cargo feature
+pub fn built() -> Profile {
+    if cfg!(feature = "staging") { Profile::Staging } else { Profile::Production }
+}
+
+/// Run this process with `profile`, whatever the build and environment say;
+/// must be called before anything reads the profile
+pub fn set(profile: Profile) {
+    if PROFILE.set(profile).is_err() {
+        log::warn!("Profile already resolved, ignoring {:?}", profile);
+    }
+}
+
+/// Profile this process runs with
+pub fn current() -> Profile {
+    *PROFILE.get_or_init(|| {
+        let built = built();
+        match std::env::var(PROFILE_ENV) {
+            Ok(value) => parse(&value).unwrap_or_else(|| {
+                let _ = IGNORED_OVERRIDE.set(value);
+                built
+            }),
+            Err(_) => built,
+        }
+    })
+}
+
+pub fn is_staging() -> bool {
+    current() == Profile::Staging
+}
+
+/// Report the profile in use (called once logging is initialized)
+pub fn log_startup() {
+    if let Some(value) = IGNORED_OVERRIDE.get() {
+        log::warn!("Ignoring unknown {}={}", PROFILE_ENV, value);
+    }
+    if is_staging() {
+        log::warn!("Running with the staging server profile (data in {})", data_dir_name());
+    }
+}
+
+/// Name of the per-profile directory under the platform data dirs
+pub fn data_dir_name() -> &'static str {
+    data_dir_name_of(current())
+}
+
+fn data_dir_name_of(profile: Profile) -> &'static str {
+    match profile {
+        Profile::Production => "TrackEx",
+        Profile::Staging => "TrackEx Staging",
+    }
+}
+
+/// `<data_dir>/<profile dir>`
+pub fn data_dir() -> Option<PathBuf> {
+    data_dir_of(current())
+}
+
+/// Data directory of `profile`, which need not be the one this process runs with
+pub fn data_dir_of(profile: Profile) -> Option<PathBuf> {
+    #[cfg(test)]
+    let root = Some(test_data_root().to_path_buf());
+    #[cfg(not(test))]
+    let root = dirs::data_dir();
+    root.map(|dir| dir.join(data_dir_name_of(profile)))
+}
+
+/// Data root of test builds: one temporary directory per test process, so
+/// tests never touch the user's data (on Windows `dirs::data_dir()` ignores
+/// HOME and XDG_DATA_HOME)
+#[cfg(test)]
+pub fn test_data_root() -> &'static std::path::Path {
+    static ROOT: OnceLock<tempfile::TempDir> = OnceLock::new();
+    ROOT.get_or_init(|| tempfile::tempdir().expect("create temp data dir")).path()
+}
+
+/// Keychain service / credential target prefix
+pub fn keychain_service() -> &'static str {
+    match current() {
+        Profile::Production => "com.trackex.agent",
+        Profile::Staging => "com.trackex.agent.staging",
+    }
+}
+
+/// Server used when none was configured at login; None keeps the build default
+pub fn default_server_url() -> Option<&'static str> {
+    is_staging().then_some(STAGING_SERVER_URL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profile() {
+        assert_eq!(parse("Staging"), Some(Profile::Staging));
+        assert_eq!(parse("sandbox"), Some(Profile::Staging));
+        assert_eq!(parse(" production "), Some(Profile::Production));
+        assert_eq!(parse("qa"), None);
+    }
+}
@@ -76,6 +76,24 @@ pub struct AppInfo {
     /// The domain extracted from the URL (always just the domain, e.g., "github.com")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub domain: Option<String>,
+    /// Domain before the URL policy was applied, for local rule checks
+    /// (screenshot suppression) only; never serialized
+    #[serde(skip)]
+    pub raw_domain: Option<String>,
+    /// Window title before allowlist-only mode redacted it; same use as `raw_domain`
+    #[serde(skip)]
+    pub raw_window_title: Option<String>,
+    /// Executable path with the home directory replaced by `~`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe_path: Option<String>,
+    /// File (Windows) or bundle (macOS) version of the executable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// Virtual desktop / Space the window is on (see `virtual_desktop`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub virtual_desktop: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -104,10 +122,19 @@ pub async fn start_sampling(_app_handle: AppHandle) {
         }
 
         if let Ok(app_info_opt) = get_current_app().await {
-                if let Some(app_info) = app_info_opt {
-                    // Check if app has changed
+                if let Some(mut app_info) = app_info_opt {
+                    // Scrub sensitive title parts before they reach app_usage or ingest events
+                    app_info.window_title = crate::api::title_rules::scrub_title(app_info.window_title);
+                    // "Slack Helper (Renderer)" / java -> the product that owns the window
+                    attribute_to_top_level(&mut app_info);
+                    super::presentation::update(&app_info);
+                    super::screen_sharing::refresh().await;
+                    
+                    // Check if app has changed (moving to another desktop starts a new segment too)
                     let app_changed = last_app_info.as_ref().map_or(true, |last| {
-                        last.name != app_info.name || last.app_id != app_info.app_id
+                        last.name != app_info.name
+                            || last.app_id != app_info.app_id
+                            || last.virtual_desktop != app_info.virtual_desktop
                     });
                     
                     // Get idle status
@@ -127,20 +154,24 @@ pub async fn start_sampling(_app_handle: AppHandle) {
                         }
                         
                         // Classify the new app (now includes URL/domain for better browser classification)
-                        let category = classifier.classify_app(
+                        let category = classifier.classify_app_with_path(
                             &app_info.name, 
                             &app_info.app_id, 
                             app_info.window_title.as_deref(),
                             app_info.domain.as_deref(),
+                            app_info.exe_path.as_deref(),
                         );
                         
                         log::debug!("App classified as: {} (domain: {:?})", category, app_info.domain);
                         
+                        crate::events::app_changed(&app_info, &category.to_string());
+                        
                         // Start new session
                         if let Err(e) = app_usage::start_app_session(
                             app_info.name.clone(),
                             app_info.app_id.clone(),
                             app_info.window_title.clone(),
+                            app_info.virtual_desktop.clone(),
                             category.clone(),
                             is_idle,
                         ).await {
@@ -156,12 +187,22 @@ pub async fn start_sampling(_app_handle: AppHandle) {
                             "window_title": app_info.window_title,
                             "url": app_info.url,
                             "domain": app_info.domain,
-                            "timestamp": chrono::Utc::now().to_rfc3339()
+                            "exe_path": app_info.exe_path,
+                            "file_version": app_info.file_version,
+                            "pid": app_info.pid,
+                            "virtual_desktop": app_info.virtual_desktop,
+                            "timestamp": crate::utils::time::event_timestamp()
                         });
 
                         // Queue event for batched sending (sent every 10 seconds)
                         crate::sampling::event_batcher::queue_event("app_focus", &event_data).await;
                         log::debug!("App focus event queued for batch: {} (domain: {:?})", app_info.name, app_info.domain);
+
+                        crate::storage::session_search::record_focus(
+                            app_info.name.clone(),
+                            app_info.window_title.clone(),
+                            app_info.domain.clone(),
+                        ).await;
                         
                         last_app_info = Some(app_info.clone());
                     } else {
@@ -169,6 +210,9 @@ pub async fn start_sampling(_app_handle: AppHandle) {
                         if let Err(e) = app_usage::update_current_session(is_idle).await {
                             log::warn!("Failed to update session idle status: {}", e);
                         }
+                        
+                        // Track window-level segments (document/file) within the app
+                        app_usage::update_window_title(app_info.window_title.clone()).await;
                     }
                 } else {
                     log::trace!("No app detected in current check");
@@ -187,6 +231,109 @@ pub async fn start_sampling(_app_handle: AppHandle) {
 
 }
 
+/// Parent levels searched for the product owning a helper process
+const MAX_PARENT_DEPTH: usize = 5;
+
+/// JVM/Electron runtimes that run on behalf of a product (JetBrains IDEs, ...)
+const RUNTIME_PROCESS_NAMES: &[&str] = &["java", "javaw", "openjdk platform binary", "electron"];
+
+/// Launchers and shells: a helper started by one of these has no product parent
+const LAUNCHER_PROCESS_NAMES: &[&str] = &[
+    "launchd", "init", "systemd", "explorer", "svchost", "services", "cmd", "powershell", "pwsh", "bash", "zsh", "sh",
+];
+
+lazy_static::lazy_static! {
+    /// Electron/Chromium helpers: "Slack Helper", "Code Helper (Renderer)"
+    static ref HELPER_NAME: regex::Regex = regex::Regex::new(r"(?i)^(.+?)\s+helper(?:\s*\(.*\))?$").unwrap();
+}
+
+/// The last helper PID resolved and its result (resolving scans the process table)
+static TOP_LEVEL_CACHE: std::sync::Mutex<Option<(u32, Option<(u32, String)>)>> = std::sync::Mutex::new(None);
+
+fn normalized_process_name(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+}
+
+/// Whether `name` is a helper or runtime process rather than a product
+fn is_helper_process(name: &str) -> bool {
+    let name = normalized_process_name(name);
+    HELPER_NAME.is_match(&name) || RUNTIME_PROCESS_NAMES.contains(&name.as_str())
+}
+
+/// Walk up from a helper to the first ancestor that is neither a helper nor a
+/// launcher. `lookup` returns a process's name and parent PID.
+fn find_top_level(pid: u32, lookup: impl Fn(u32) -> Option<(String, Option<u32>)>) -> Option<(u32, String)> {
+    let mut current = pid;
+    for _ in 0..MAX_PARENT_DEPTH {
+        let parent = lookup(current)?.1?;
+        let (parent_name, _) = lookup(parent)?;
+        if LAUNCHER_PROCESS_NAMES.contains(&normalized_process_name(&parent_name).as_str()) {
+            return None;
+        }
+        if !is_helper_process(&parent_name) {
+            return Some((parent, parent_name));
+        }
+        current = parent;
+    }
+    None
+}
+
+fn cached_top_level(pid: u32) -> Option<(u32, String)> {
+    let mut cache = match TOP_LEVEL_CACHE.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    if let Some((cached_pid, result)) = cache.as_ref() {
+        if *cached_pid == pid {
+            return result.clone();
+        }
+    }
+
+    let mut sys = sysinfo::System::new();
+    sys.refresh_processes();
+    let result = find_top_level(pid, |pid| {
+        let process = sys.process(sysinfo::Pid::from_u32(pid))?;
+        Some((process.name().to_string(), process.parent().map(|parent| parent.as_u32())))
+    });
+    *cache = Some((pid, result.clone()));
+    result
+}
+
+/// Attribute helper/runtime processes to the product that spawned them, e.g.
+/// "Slack Helper (Renderer)" to Slack or a JVM to the IDE that launched it.
+/// Without a product parent, Electron helpers fall back to their name prefix.
+pub fn attribute_to_top_level(app: &mut AppInfo) {
+    if !is_helper_process(&app.name) {
+        return;
+    }
+
+    match app.pid.and_then(cached_top_level) {
+        Some((pid, process_name)) => {
+            #[cfg(target_os = "windows")]
+            let name = get_windows_process_name(pid).unwrap_or_else(|| process_name.trim_end_matches(".exe").to_string());
+            #[cfg(not(target_os = "windows"))]
+            let name = process_name;
+            log::debug!("Attributing helper '{}' to parent process '{}' ({})", app.name, name, pid);
+
+            #[cfg(target_os = "windows")]
+            if let Some(app_id) = get_windows_app_id(pid) {
+                app.app_id = app_id;
+            }
+            let details = super::process_info::details_for_pid(pid);
+            app.name = name;
+            app.pid = Some(pid);
+            app.exe_path = details.exe_path;
+            app.file_version = details.file_version;
+        }
+        None => {
+            if let Some(product) = HELPER_NAME.captures(&app.name).map(|captures| captures[1].to_string()) {
+                app.name = product;
+            }
+        }
+    }
+}
+
 // #[cfg(target_os = "macos")]
 // pub async fn get_current_app() -> Result<AppInfo> {
 //     use std::process::Command;
@@ -577,5 +724,39 @@ pub async fn get_current_app() -> Result<AppInfo> {
         name: "Unknown".to_string(),
         app_id: "unknown.bundle.id".to_string(),
         window_title: None,
+        url: None,
+        domain: None,
+        raw_domain: None,
+        raw_window_title: None,
+        exe_path: None,
+        file_version: None,
+        pid: None,
+        virtual_desktop: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_top_level_skips_helpers_and_launchers() {
+        let table = |pid: u32| -> Option<(String, Option<u32>)> {
+            match pid {
+                1 => Some(("launchd".to_string(), None)),
+                100 => Some(("Slack".to_string(), Some(1))),
+                101 => Some(("Slack Helper".to_string(), Some(100))),
+                102 => Some(("Slack Helper (Renderer)".to_string(), Some(101))),
+                200 => Some(("java".to_string(), Some(1))),
+                _ => None,
+            }
+        };
+        assert_eq!(find_top_level(102, table), Some((100, "Slack".to_string())));
+        assert_eq!(find_top_level(200, table), None);
+
+        assert!(is_helper_process("Code Helper (GPU)"));
+        assert!(is_helper_process("javaw.exe"));
+        assert!(!is_helper_process("Slack"));
+        assert_eq!(&HELPER_NAME.captures("Slack Helper (Renderer)").unwrap()[1], "Slack");
+    }
+}
@@ -0,0 +1,78 @@
+//! Keep sampling on schedule while the window is hidden (macOS App Nap)
+//!
+//! macOS naps processes without visible windows: timers are coalesced and
+//! delayed, so focus sampling and heartbeats drift far from their intervals.
+//! While a work session is active the agent holds an `NSProcessInfo`
+//! activity assertion, which opts out of App Nap without keeping the Mac
+//! awake (idle system sleep stays allowed). Elsewhere this is a no-op.
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::ffi::CString;
+
+    /// NSActivityUserInitiatedAllowingIdleSystemSleep
+    const ACTIVITY_OPTIONS: u64 = 0x00EF_FFFF;
+
+    /// Retained activity token, stored as `usize` so the static is `Send`
+    pub fn begin(reason: &str) -> Option<usize> {
+        let reason = CString::new(reason).ok()?;
+        unsafe {
+            let info: *mut Object = msg_send![class!(NSProcessInfo), processInfo];
+            let reason: *mut Object = msg_send![class!(NSString), stringWithUTF8String: reason.as_ptr()];
+            let token: *mut Object = msg_send![info, beginActivityWithOptions: ACTIVITY_OPTIONS reason: reason];
+            if token.is_null() {
+                return None;
+            }
+            let _: *mut Object = msg_send![token, retain];
+            Some(token as usize)
+        }
+    }
+
+    pub fn end(token: usize) {
+        unsafe {
+            let token = token as *mut Object;
+            let info: *mut Object = msg_send![class!(NSProcessInfo), processInfo];
+            let _: () = msg_send![info, endActivity: token];
+            let _: () = msg_send![token, release];
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    pub fn begin(_reason: &str) -> Option<usize> {
+        None
+    }
+
+    pub fn end(_token: usize) {}
+}
+
+static ACTIVITY: std::sync::Mutex<Option<usize>> = std::sync::Mutex::new(None);
+
+/// Opt out of App Nap (called when background services start for a session)
+pub fn begin_session_activity() {
+    let mut activity = match ACTIVITY.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    if activity.is_none() {
+        *activity = platform::begin("Tracking an active work session");
+        if activity.is_some() {
+            log::info!("App Nap disabled for the active work session");
+        }
+    }
+}
+
+/// Allow App Nap again (called when background services stop)
+pub fn end_session_activity() {
+    let mut activity = match ACTIVITY.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    if let Some(token) = activity.take() {
+        platform::end(token);
+        log::info!("App Nap re-enabled");
+    }
+}
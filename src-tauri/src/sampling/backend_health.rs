@@ -0,0 +1,118 @@
+//! Alerting when the backend stays unreachable
+//!
+//! Failed heartbeats are queued and synced later, which is invisible to the
+//! employee, so a long outage looked like missing live status on the
+//! dashboard. Once heartbeats have failed continuously for
+//! `OUTAGE_ALERT_MINUTES` while clocked in, the user gets a notification and
+//! an offline warning in the tray tooltip, and the service lifecycle turns
+//! Degraded (failing "backend"), which emits `service-state-changed`. The
+//! first successful heartbeat clears all of it.
+
+use chrono::{DateTime, Duration, Utc};
+use crate::utils::clock;
+use std::sync::Mutex;
+
+/// Continuous heartbeat failure before the user is alerted
+const OUTAGE_ALERT_MINUTES: i64 = 5;
+
+/// Lifecycle service name used while the backend is unreachable
+pub const BACKEND_SERVICE: &str = "backend";
+
+static OUTAGE: Mutex<Outage> = Mutex::new(Outage {
+    failing_since: None,
+    alerted: false,
+});
+
+#[derive(Debug, Clone, PartialEq)]
+struct Outage {
+    failing_since: Option<DateTime<Utc>>,
+    alerted: bool,
+}
+
+/// What changed after a heartbeat result
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutageChange {
+    None,
+    Alert,
+    Recovered,
+}
+
+impl Outage {
+    fn record(&mut self, success: bool, now: DateTime<Utc>) -> OutageChange {
+        if success {
+            let was_alerted = self.alerted;
+            *self = Outage { failing_since: None, alerted: false };
+            return if was_alerted { OutageChange::Recovered } else { OutageChange::None };
+        }
+
+        let since = *self.failing_since.get_or_insert(now);
+        if !self.alerted && now - since >= Duration::minutes(OUTAGE_ALERT_MINUTES) {
+            self.alerted = true;
+            return OutageChange::Alert;
+        }
+        OutageChange::None
+    }
+}
+
+async fn record(success: bool) {
+    let change = {
+        let mut outage = match OUTAGE.lock() {
+            Ok(guard) => guard,
+            Err(e) => e.into_inner(),
+        };
+        outage.record(success, clock::now())
+    };
+
+    match change {
+        OutageChange::Alert => {
+            log::warn!("Backend unreachable for {} minutes, alerting user", OUTAGE_ALERT_MINUTES);
+            crate::events::notify("notification.backend_offline.title", "notification.backend_offline.body");
+            crate::tray_status::set_offline(true);
+            super::set_backend_reachable(false).await;
+        }
+        OutageChange::Recovered => {
+            log::info!("Backend reachable again");
+            crate::tray_status::set_offline(false);
+            super::set_backend_reachable(true).await;
+        }
+        OutageChange::None => {}
+    }
+}
+
+/// A heartbeat reached the backend
+pub async fn heartbeat_succeeded() {
+    record(true).await;
+}
+
+/// A live heartbeat failed (it was queued for later)
+pub async fn heartbeat_failed() {
+    record(false).await;
+}
+
+/// Forget the outage when heartbeats stop (clock-out, logout)
+pub async fn reset() {
+    record(true).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_after_continuous_failures() {
+        let mut outage = Outage { failing_since: None, alerted: false };
+        let start = Utc::now();
+
+        assert_eq!(outage.record(false, start), OutageChange::None);
+        assert_eq!(outage.record(false, start + Duration::minutes(4)), OutageChange::None);
+        assert_eq!(outage.record(false, start + Duration::minutes(5)), OutageChange::Alert);
+        // Alerted once per outage
+        assert_eq!(outage.record(false, start + Duration::minutes(9)), OutageChange::None);
+        assert_eq!(outage.record(true, start + Duration::minutes(10)), OutageChange::Recovered);
+
+        // A success in between restarts the clock
+        assert_eq!(outage.record(false, start + Duration::minutes(11)), OutageChange::None);
+        assert_eq!(outage.record(true, start + Duration::minutes(12)), OutageChange::None);
+        assert_eq!(outage.record(false, start + Duration::minutes(16)), OutageChange::None);
+    }
+}
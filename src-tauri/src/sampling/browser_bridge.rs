@@ -0,0 +1,625 @@
+//! Browser extension bridge via native messaging
+//!
+//! Title/UI Automation based URL extraction misses single-page apps and
+//! breaks whenever a browser changes its UI. The official TrackEx browser
+//! extensions instead push the active tab to the agent:
+//!
+//! ```text
+//! extension --(native messaging, stdio)--> trackex-agent (host mode)
+//!           --(JSON lines, 127.0.0.1:bridge_port())--> running agent
+//! ```
+//!
+//! The browser launches the agent binary as a native-messaging host. In that
+//! mode it only relays messages to the running agent and exits when the
+//! browser closes stdin. The running agent keeps the latest tab per browser
+//! and `browser_url::extract_browser_url` prefers it over title parsing.
+//!
+//! Native messaging framing: every message is a UTF-8 JSON document prefixed
+//! by its length as a 32-bit unsigned integer in native byte order.
+//!
+//! Any local process (or web page) can connect to a loopback port, so the
+//! listener only trusts connections that first present the per-install
+//! secret from `BRIDGE_SECRET_FILE`. The agent creates it in its data
+//! directory, readable by the user only; the native host runs as the same
+//! user and reads it before relaying. Unauthenticated connections get a
+//! bounded auth line and a timeout, nothing more.
+//!
+//! Each profile has its own host name, port and data directory. The browser
+//! launches the host without the agent's environment, so the host works out
+//! the profile from its arguments (see `host_profile`).
+
+use crate::profile::Profile;
+use lazy_static::lazy_static;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Name of the native messaging host (must match the manifest and the
+/// extensions). Side-by-side profiles register separate hosts.
+pub fn native_host_name() -> &'static str {
+    native_host_name_of(crate::profile::current())
+}
+
+fn native_host_name_of(profile: Profile) -> &'static str {
+    match profile {
+        Profile::Production => "com.trackex.agent",
+        Profile::Staging => "com.trackex.agent.staging",
+    }
+}
+
+/// Loopback port the running agent listens on for relayed extension
+/// messages; one per profile so both agents can run at once
+pub fn bridge_port() -> u16 {
+    bridge_port_of(crate::profile::current())
+}
+
+fn bridge_port_of(profile: Profile) -> u16 {
+    match profile {
+        Profile::Production => 47615,
+        Profile::Staging => 47616,
+    }
+}
+
+/// Version of the extension <-> agent protocol
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Tab reports older than this are ignored (the extension reports on every
+/// tab switch/navigation and re-sends periodically while the window is focused)
+const TAB_REPORT_MAX_AGE: Duration = Duration::from_secs(15);
+
+/// Per-install secret relays present on the loopback connection
+const BRIDGE_SECRET_FILE: &str = "browser_bridge.key";
+
+/// Time a relay has to authenticate after connecting
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest auth line read from a connection that hasn't authenticated yet
+const MAX_AUTH_LINE_BYTES: u64 = 1024;
+
+/// Maximum accepted native message size (Chrome limits host-bound messages to 4 GB,
+/// tab updates are tiny)
+const MAX_MESSAGE_BYTES: u32 = 1024 * 1024;
+
+/// Messages sent by the browser extensions
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExtensionMessage {
+    /// Handshake sent when the extension connects
+    Hello {
+        browser: String,
+        #[serde(default)]
+        extension_version: Option<String>,
+        #[serde(default)]
+        protocol_version: Option<u32>,
+    },
+    /// The active tab of the focused browser window changed (or was re-sent)
+    TabUpdate {
+        browser: String,
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        incognito: bool,
+    },
+    /// The browser lost focus or has no active tab
+    TabCleared { browser: String },
+}
+
+/// Latest tab reported by an extension
+#[derive(Debug, Clone)]
+pub struct ExtensionTab {
+    pub url: String,
+    pub title: Option<String>,
+    pub received_at: Instant,
+}
+
+lazy_static! {
+    /// Latest tab per browser family ("chrome", "edge", "firefox", ...)
+    static ref LATEST_TABS: Mutex<HashMap<String, ExtensionTab>> = Mutex::new(HashMap::new());
+}
+
+/// Apply a message received from an extension
+pub fn handle_message(message: ExtensionMessage) {
+    match message {
+        ExtensionMessage::Hello { browser, extension_version, protocol_version } => {
+            log::info!(
+                "Browser extension connected: {} (extension {:?}, protocol {:?})",
+                browser,
+                extension_version,
+                protocol_version
+            );
+        }
+        ExtensionMessage::TabUpdate { browser, url, title, incognito } => {
+            let mut tabs = LATEST_TABS.lock().unwrap();
+            if incognito {
+                // Never record private browsing URLs
+                tabs.remove(&browser.to_lowercase());
+                return;
+            }
+            tabs.insert(
+                browser.to_lowercase(),
+                ExtensionTab { url, title, received_at: Instant::now() },
+            );
+        }
+        ExtensionMessage::TabCleared { browser } => {
+            LATEST_TABS.lock().unwrap().remove(&browser.to_lowercase());
+        }
+    }
+}
+
+/// Whether a browser family reported by an extension matches the focused app
+fn browser_matches(browser: &str, app_name: &str, app_id: &str) -> bool {
+    let app = format!("{} {}", app_name, app_id).to_lowercase();
+    match browser {
+        "chrome" => app.contains("chrome") && !app.contains("edge"),
+        "edge" => app.contains("edge"),
+        "firefox" => app.contains("firefox"),
+        "brave" => app.contains("brave"),
+        other => app.contains(other),
+    }
+}
+
+/// Get the active tab pushed by the extension of the focused browser, if recent
+pub fn get_active_tab(app_name: &str, app_id: &str) -> Option<ExtensionTab> {
+    let tabs = LATEST_TABS.lock().unwrap();
+    tabs.iter()
+        .filter(|(browser, _)| browser_matches(browser, app_name, app_id))
+        .map(|(_, tab)| tab)
+        .filter(|tab| tab.received_at.elapsed() <= TAB_REPORT_MAX_AGE)
+        .max_by_key(|tab| tab.received_at)
+        .cloned()
+}
+
+// ---------------------------------------------------------------------------
+// Bridge secret
+// ---------------------------------------------------------------------------
+
+fn secret_path() -> Option<PathBuf> {
+    crate::profile::data_dir().map(|dir| dir.join(BRIDGE_SECRET_FILE))
+}
+
+fn read_secret() -> Option<String> {
+    let secret = std::fs::read_to_string(secret_path()?).ok()?;
+    let secret = secret.trim();
+    (!secret.is_empty()).then(|| secret.to_string())
+}
+
+/// Load the bridge secret, creating it (user-only permissions) on first use
+fn load_or_create_secret() -> anyhow::Result<String> {
+    if let Some(secret) = read_secret() {
+        return Ok(secret);
+    }
+
+    let path = secret_path().ok_or_else(|| anyhow::anyhow!("No data directory"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    let secret: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(&path)?.write_all(secret.as_bytes())?;
+    Ok(secret)
+}
+
+/// First line a relay sends on a new connection
+fn auth_line(secret: &str) -> String {
+    serde_json::json!({ "type": "auth", "secret": secret }).to_string()
+}
+
+/// Whether `line` is an auth message carrying `secret` (compared in constant time)
+fn is_authorized(line: &str, secret: &str) -> bool {
+    let Ok(message) = serde_json::from_str::<serde_json::Value>(line) else {
+        return false;
+    };
+    let presented = match (message["type"].as_str(), message["secret"].as_str()) {
+        (Some("auth"), Some(presented)) => presented.as_bytes(),
+        _ => return false,
+    };
+    presented.len() == secret.len()
+        && presented.iter().zip(secret.as_bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// ---------------------------------------------------------------------------
+// Agent side: loopback listener
+// ---------------------------------------------------------------------------
+
+/// Start the loopback listener that receives relayed extension messages
+pub async fn start_bridge_listener() {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+    let secret = match load_or_create_secret() {
+        Ok(secret) => std::sync::Arc::new(secret),
+        Err(e) => {
+            log::warn!("Browser bridge disabled, no bridge secret: {}", e);
+            return;
+        }
+    };
+
+    let listener = match tokio::net::TcpListener::bind(("127.0.0.1", bridge_port())).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::warn!("Browser bridge unavailable (port {}): {}", bridge_port(), e);
+            return;
+        }
+    };
+
+    log::info!("Browser bridge listening on 127.0.0.1:{}", bridge_port());
+
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("Browser bridge accept failed: {}", e);
+                continue;
+            }
+        };
+
+        let secret = secret.clone();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stream);
+            let mut auth = Vec::new();
+            let read = tokio::time::timeout(
+                AUTH_TIMEOUT,
+                (&mut reader).take(MAX_AUTH_LINE_BYTES).read_until(b'\n', &mut auth),
+            )
+            .await;
+            let authorized = matches!(read, Ok(Ok(_)))
+                && std::str::from_utf8(&auth).is_ok_and(|line| is_authorized(line, &secret));
+            if !authorized {
+                log::warn!("Rejected unauthenticated browser bridge connection");
+                return;
+            }
+            let mut lines = reader.lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str::<ExtensionMessage>(&line) {
+                    Ok(message) => handle_message(message),
+                    Err(e) => log::debug!("Ignoring invalid browser bridge message: {}", e),
+                }
+            }
+        });
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Host side: native messaging over stdio
+// ---------------------------------------------------------------------------
+
+/// Whether the process was launched by a browser as a native-messaging host.
+/// Chrome/Edge pass the caller origin (`chrome-extension://<id>/`), Firefox
+/// passes the manifest path followed by the extension id.
+pub fn is_native_host_invocation() -> bool {
+    std::env::args().skip(1).any(|arg| {
+        arg.starts_with("chrome-extension://")
+            || (arg.ends_with(".json")
+                && (arg.contains("NativeMessagingHosts") || arg.contains("native-messaging-hosts")))
+    })
+}
+
+/// Read one native message. Returns `Ok(None)` when the browser closed stdin.
+pub fn read_message<R: Read>(reader: &mut R) -> std::io::Result<Option<serde_json::Value>> {
+    let mut len_bytes = [0u8; 4];
+    match reader.read_exact(&mut len_bytes) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_ne_bytes(len_bytes);
+    if len > MAX_MESSAGE_BYTES {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("native message too large: {} bytes", len),
+        ));
+    }
+
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    serde_json::from_slice(&buf)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Write one native message
+pub fn write_message<W: Write>(writer: &mut W, message: &serde_json::Value) -> std::io::Result<()> {
+    let bytes = serde_json::to_vec(message)?;
+    writer.write_all(&(bytes.len() as u32).to_ne_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()
+}
+
+/// Profile named by a manifest path argument. Firefox passes the manifest
+/// path, whose file name is the host name.
+fn profile_from_manifest_arg(args: &[String]) -> Option<Profile> {
+    args.iter()
+        // Split by hand: the path uses the host platform's separators
+        .filter_map(|arg| arg.rsplit(['/', '\\']).next()?.strip_suffix(".json"))
+        .find_map(|stem| Profile::ALL.into_iter().find(|profile| native_host_name_of(*profile) == stem))
+}
+
+/// Whether the agent of `profile` is running and has a bridge secret
+fn agent_reachable(profile: Profile) -> bool {
+    let has_secret = crate::profile::data_dir_of(profile)
+        .is_some_and(|dir| dir.join(BRIDGE_SECRET_FILE).exists());
+    has_secret
+        && std::net::TcpStream::connect_timeout(
+            &std::net::SocketAddr::from(([127, 0, 0, 1], bridge_port_of(profile))),
+            Duration::from_millis(500),
+        )
+        .is_ok()
+}
+
+/// Profile of the agent to relay to. The browser doesn't pass the agent's
+/// environment (`TRACKEX_PROFILE`), so it comes from the manifest name where
+/// the browser passes one (Firefox). Chromium only passes the extension
+/// origin; there the running agent is used, preferring the build's own
+/// profile when both run.
+fn host_profile() -> Profile {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(profile) = profile_from_manifest_arg(&args) {
+        return profile;
+    }
+    let built = crate::profile::built();
+    std::iter::once(built)
+        .chain(Profile::ALL.into_iter().filter(|profile| *profile != built))
+        .find(|profile| agent_reachable(*profile))
+        .unwrap_or(built)
+}
+
+/// Run as a native-messaging host: relay extension messages to the running agent.
+/// Blocks until the browser closes the connection. Nothing else may be written
+/// to stdout in this mode.
+pub fn run_native_host() {
+    crate::profile::set(host_profile());
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let mut input = stdin.lock();
+    let mut output = stdout.lock();
+    let mut agent: Option<std::net::TcpStream> = None;
+
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("trackex native host: failed to read message: {}", e);
+                break;
+            }
+        };
+
+        if agent.is_none() {
+            agent = connect_to_agent();
+        }
+
+        let delivered = match agent.as_mut() {
+            Some(stream) => writeln!(stream, "{}", message).is_ok(),
+            None => false,
+        };
+        if !delivered {
+            // Agent not running (or restarted) - reconnect on the next message
+            agent = None;
+        }
+
+        let ack = serde_json::json!({
+            "type": "ack",
+            "protocol_version": PROTOCOL_VERSION,
+            "agent_running": delivered,
+        });
+        if write_message(&mut output, &ack).is_err() {
+            break;
+        }
+    }
+}
+
+/// Connect to the running agent and authenticate with the bridge secret
+fn connect_to_agent() -> Option<std::net::TcpStream> {
+    // Read on every connect: the agent creates the secret on its first start
+    let secret = read_secret()?;
+    let mut stream = std::net::TcpStream::connect_timeout(
+        &std::net::SocketAddr::from(([127, 0, 0, 1], bridge_port())),
+        Duration::from_secs(2),
+    )
+    .ok()?;
+    writeln!(stream, "{}", auth_line(&secret)).ok()?;
+    Some(stream)
+}
+
+// ---------------------------------------------------------------------------
+// Host manifest registration
+// ---------------------------------------------------------------------------
+
+/// Register the agent as native-messaging host for the official extensions.
+/// Extension ids are provided at build time; browsers without one are skipped.
+pub fn install_native_host_manifests() -> anyhow::Result<()> {
+    let exe = std::env::current_exe()?;
+    let exe_path = exe.to_string_lossy().to_string();
+
+    let chromium_origins: Vec<String> = option_env!("TRACKEX_CHROME_EXTENSION_IDS")
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| format!("chrome-extension://{}/", id))
+        .collect();
+    let firefox_ids: Vec<String> = option_env!("TRACKEX_FIREFOX_EXTENSION_IDS")
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .collect();
+
+    if !chromium_origins.is_empty() {
+        let manifest = serde_json::json!({
+            "name": native_host_name(),
+            "description": "TrackEx Agent browser bridge",
+            "path": exe_path,
+            "type": "stdio",
+            "allowed_origins": chromium_origins,
+        });
+        for browser in ["chrome", "edge", "brave"] {
+            write_manifest(browser, &manifest)?;
+        }
+    }
+
+    if !firefox_ids.is_empty() {
+        let manifest = serde_json::json!({
+            "name": native_host_name(),
+            "description": "TrackEx Agent browser bridge",
+            "path": exe_path,
+            "type": "stdio",
+            "allowed_extensions": firefox_ids,
+        });
+        write_manifest("firefox", &manifest)?;
+    }
+
+    Ok(())
+}
+
+/// Per-user manifest directory for a browser
+#[cfg(not(target_os = "windows"))]
+fn manifest_dir(browser: &str) -> Option<std::path::PathBuf> {
+    #[cfg(target_os = "macos")]
+    let base = dirs::home_dir()?.join("Library/Application Support");
+    #[cfg(not(target_os = "macos"))]
+    let base = dirs::config_dir()?;
+
+    let relative = match browser {
+        #[cfg(target_os = "macos")]
+        "chrome" => "Google/Chrome/NativeMessagingHosts",
+        #[cfg(target_os = "macos")]
+        "edge" => "Microsoft Edge/NativeMessagingHosts",
+        #[cfg(target_os = "macos")]
+        "brave" => "BraveSoftware/Brave-Browser/NativeMessagingHosts",
+        #[cfg(target_os = "macos")]
+        "firefox" => "Mozilla/NativeMessagingHosts",
+        #[cfg(not(target_os = "macos"))]
+        "chrome" => "google-chrome/NativeMessagingHosts",
+        #[cfg(not(target_os = "macos"))]
+        "edge" => "microsoft-edge/NativeMessagingHosts",
+        #[cfg(not(target_os = "macos"))]
+        "brave" => "BraveSoftware/Brave-Browser/NativeMessagingHosts",
+        #[cfg(not(target_os = "macos"))]
+        "firefox" => return Some(dirs::home_dir()?.join(".mozilla/native-messaging-hosts")),
+        _ => return None,
+    };
+
+    Some(base.join(relative))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn write_manifest(browser: &str, manifest: &serde_json::Value) -> anyhow::Result<()> {
+    let Some(dir) = manifest_dir(browser) else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", native_host_name()));
+    std::fs::write(&path, serde_json::to_string_pretty(manifest)?)?;
+    log::info!("Registered native messaging host for {}: {}", browser, path.display());
+    Ok(())
+}
+
+/// On Windows the manifest lives next to the agent data and is referenced from HKCU
+#[cfg(target_os = "windows")]
+fn write_manifest(browser: &str, manifest: &serde_json::Value) -> anyhow::Result<()> {
+    let registry_root = match browser {
+        "chrome" => r"HKCU\Software\Google\Chrome\NativeMessagingHosts",
+        "edge" => r"HKCU\Software\Microsoft\Edge\NativeMessagingHosts",
+        "brave" => r"HKCU\Software\BraveSoftware\Brave-Browser\NativeMessagingHosts",
+        "firefox" => r"HKCU\Software\Mozilla\NativeMessagingHosts",
+        _ => return Ok(()),
+    };
+
+    let dir = crate::profile::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Data directory not available"))?
+        .join("NativeMessagingHosts")
+        .join(browser);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", native_host_name()));
+    std::fs::write(&path, serde_json::to_string_pretty(manifest)?)?;
+
+    let key = format!(r"{}\{}", registry_root, native_host_name());
+    let status = std::process::Command::new("reg")
+        .args(["add", &key, "/ve", "/t", "REG_SZ", "/d", &path.to_string_lossy(), "/f"])
+        .status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Failed to register native messaging host for {}", browser));
+    }
+
+    log::info!("Registered native messaging host for {}: {}", browser, path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_message_round_trip() {
+        let message = serde_json::json!({"type": "tab_update", "browser": "chrome", "url": "https://example.com/app#/inbox"});
+        let mut buf = Vec::new();
+        write_message(&mut buf, &message).unwrap();
+
+        let mut reader = std::io::Cursor::new(buf);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        // Stream closed
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_tab_update() {
+        let message: ExtensionMessage = serde_json::from_str(
+            r#"{"type":"tab_update","browser":"firefox","url":"https://github.com/pulls","title":"Pull requests"}"#,
+        ).unwrap();
+        assert_eq!(message, ExtensionMessage::TabUpdate {
+            browser: "firefox".to_string(),
+            url: "https://github.com/pulls".to_string(),
+            title: Some("Pull requests".to_string()),
+            incognito: false,
+        });
+    }
+
+    #[test]
+    fn test_bridge_auth() {
+        let secret = "0123456789abcdef";
+        assert!(is_authorized(&auth_line(secret), secret));
+        assert!(!is_authorized(&auth_line("0123456789abcdee"), secret));
+        assert!(!is_authorized(&auth_line(""), secret));
+        // A web page posting to the port, or a message without auth
+        assert!(!is_authorized("POST / HTTP/1.1", secret));
+        assert!(!is_authorized(r#"{"type":"tab_update","browser":"chrome","url":"https://x.test"}"#, secret));
+    }
+
+    #[test]
+    fn test_profile_from_firefox_manifest_path() {
+        let args = |manifest: &str| vec![manifest.to_string(), "trackex@trackex.app".to_string()];
+        assert_eq!(
+            profile_from_manifest_arg(&args("/home/u/.mozilla/native-messaging-hosts/com.trackex.agent.staging.json")),
+            Some(Profile::Staging)
+        );
+        assert_eq!(
+            profile_from_manifest_arg(&args(r"C:\Users\u\NativeMessagingHosts\firefox\com.trackex.agent.json")),
+            Some(Profile::Production)
+        );
+        assert_eq!(profile_from_manifest_arg(&["chrome-extension://abcdef/".to_string()]), None);
+    }
+
+    #[test]
+    fn test_browser_matching() {
+        assert!(browser_matches("chrome", "Google Chrome", "chrome.exe"));
+        assert!(!browser_matches("chrome", "Microsoft Edge", "msedge.exe"));
+        assert!(browser_matches("edge", "Microsoft Edge", "msedge.exe"));
+        assert!(browser_matches("firefox", "Firefox", "org.mozilla.firefox"));
+    }
+}
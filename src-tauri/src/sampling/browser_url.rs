@@ -2,9 +2,12 @@
 //!
 //! Extracts the current URL/domain from browser windows.
 //! Uses Windows UI Automation API for reliable URL extraction directly from the address bar.
-//! Falls back to window title parsing on macOS or when UI Automation fails.
+//! On macOS, asks Chromium-based browsers for the active tab URL via AppleScript
+//! (requires Automation permission), skipping incognito windows.
+//! Falls back to window title parsing when neither is available.
 
-use crate::utils::privacy::{is_browser_app, is_browser_by_name, extract_domain_from_window_title};
+use crate::utils::privacy::{is_browser_app, is_browser_by_name, extract_domain_from_window_title, UrlSanitizer};
+use crate::utils::public_suffix;
 
 /// Windows UI Automation module for reading browser address bar
 #[cfg(target_os = "windows")]
@@ -22,93 +25,188 @@ mod uia {
     use windows::core::{Interface, BSTR};
     use windows::Win32::System::Variant::VARIANT;
 
+    use std::collections::HashMap;
+    use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+    use std::sync::{Mutex, OnceLock};
+    use std::time::Duration;
+
+    /// Maximum time a caller waits for the UIA worker
+    const UIA_TIMEOUT: Duration = Duration::from_millis(1500);
+
+    /// Pending requests allowed while the worker is busy; beyond that the
+    /// worker is considered stuck and callers fall back to title parsing
+    const UIA_QUEUE_DEPTH: usize = 2;
+
+    /// Cached address-bar elements are dropped once this many windows were seen
+    const MAX_CACHED_WINDOWS: usize = 32;
+
+    struct UrlRequest {
+        hwnd: isize,
+        reply: SyncSender<Option<String>>,
+    }
+
+    static UIA_WORKER: OnceLock<Mutex<SyncSender<UrlRequest>>> = OnceLock::new();
+
+    fn worker() -> &'static Mutex<SyncSender<UrlRequest>> {
+        UIA_WORKER.get_or_init(|| {
+            let (sender, receiver) = mpsc::sync_channel(UIA_QUEUE_DEPTH);
+            std::thread::Builder::new()
+                .name("uia-worker".to_string())
+                .spawn(move || run_worker(receiver))
+                .expect("failed to spawn UIA worker thread");
+            Mutex::new(sender)
+        })
+    }
+
     /// Extract URL from browser window using Windows UI Automation API.
-    /// Uses multiple strategies to find the address bar URL reliably.
+    ///
+    /// All UIA/COM work runs on a dedicated MTA worker thread. The caller waits
+    /// at most `UIA_TIMEOUT`, so a hung browser can't block focus sampling.
     pub fn get_browser_url(hwnd: isize) -> Option<String> {
-        unsafe {
-            // Initialize COM (may already be initialized, that's OK)
+        let (reply, response) = mpsc::sync_channel(1);
+
+        let sent = worker().lock().unwrap().try_send(UrlRequest { hwnd, reply });
+        match sent {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                log::debug!("[UIA] Worker busy, skipping URL extraction");
+                return None;
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                log::warn!("[UIA] Worker thread is gone");
+                return None;
+            }
+        }
+
+        match response.recv_timeout(UIA_TIMEOUT) {
+            Ok(url) => url,
+            Err(_) => {
+                log::warn!("[UIA] URL extraction timed out after {:?}", UIA_TIMEOUT);
+                None
+            }
+        }
+    }
+
+    /// Worker loop: owns the COM apartment, the IUIAutomation instance and the
+    /// per-HWND cache of located address-bar elements.
+    fn run_worker(requests: Receiver<UrlRequest>) {
+        let automation: IUIAutomation = unsafe {
             let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
-            
-            // Create UI Automation instance
-            let automation: IUIAutomation = match CoCreateInstance(
-                &CUIAutomation,
-                None,
-                CLSCTX_INPROC_SERVER
-            ) {
+
+            match CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER) {
                 Ok(a) => a,
                 Err(e) => {
                     log::warn!("[UIA] Failed to create UIAutomation: {:?}", e);
-                    return None;
-                }
-            };
-            
-            // Get element from HWND
-            let hwnd = HWND(hwnd as *mut _);
-            let element = match automation.ElementFromHandle(hwnd) {
-                Ok(e) => e,
-                Err(e) => {
-                    log::warn!("[UIA] Failed to get element from HWND: {:?}", e);
-                    return None;
+                    return;
                 }
-            };
-            
-            // Log window info for debugging
-            if let Ok(name) = element.CurrentName() {
-                log::info!("[UIA] Searching for URL in window: {}", name.to_string());
             }
-            
-            // Strategy 1: Search by known AutomationIds (fastest, most reliable)
-            log::debug!("[UIA] Strategy 1: Searching by AutomationId...");
-            if let Some(url) = find_by_automation_id(&automation, &element) {
-                log::info!("[UIA] Found URL via AutomationId strategy: {}", url);
-                return Some(url);
-            }
-            
-            // Strategy 2: Search by Name containing "address"
-            log::debug!("[UIA] Strategy 2: Searching by Name property...");
-            if let Some(url) = find_by_name(&automation, &element) {
-                log::info!("[UIA] Found URL via Name strategy: {}", url);
-                return Some(url);
-            }
-            
-            // Strategy 3: Find ToolBar then search Edit controls inside
-            log::debug!("[UIA] Strategy 3: Searching inside ToolBar...");
-            if let Some(url) = find_via_toolbar(&automation, &element) {
-                log::info!("[UIA] Found URL via ToolBar strategy: {}", url);
-                return Some(url);
-            }
-            
-            // Strategy 4: Find ALL Edit controls and check for URL values
-            log::debug!("[UIA] Strategy 4: Searching all Edit controls...");
-            if let Some(url) = find_in_all_edit_controls(&automation, &element) {
-                log::info!("[UIA] Found URL via Edit control scan: {}", url);
-                return Some(url);
-            }
-            
-            // Strategy 5: Search ComboBox controls (some browsers use ComboBox for address bar)
-            log::debug!("[UIA] Strategy 5: Searching ComboBox controls...");
-            if let Some(url) = find_in_combobox_controls(&automation, &element) {
-                log::info!("[UIA] Found URL via ComboBox strategy: {}", url);
-                return Some(url);
+        };
+
+        let mut cache: HashMap<isize, IUIAutomationElement> = HashMap::new();
+
+        for request in requests {
+            let url = unsafe { url_for_window(&automation, &mut cache, request.hwnd) };
+            // The caller may have timed out already
+            let _ = request.reply.send(url);
+        }
+    }
+
+    /// Read the URL for a window, reusing the cached address bar while it is valid
+    unsafe fn url_for_window(
+        automation: &IUIAutomation,
+        cache: &mut HashMap<isize, IUIAutomationElement>,
+        hwnd: isize,
+    ) -> Option<String> {
+        if let Some(address_bar) = cache.get(&hwnd) {
+            match get_element_value(address_bar) {
+                Some(url) if looks_like_url(&url) => {
+                    log::debug!("[UIA] Got URL from cached address bar");
+                    return Some(url);
+                }
+                _ => {
+                    log::debug!("[UIA] Cached address bar no longer valid, searching again");
+                    cache.remove(&hwnd);
+                }
             }
-            
-            // Strategy 6: Search Document controls (for browser address displayed as document)
-            log::debug!("[UIA] Strategy 6: Searching Document controls...");
-            if let Some(url) = find_in_document_controls(&automation, &element) {
-                log::info!("[UIA] Found URL via Document strategy: {}", url);
-                return Some(url);
+        }
+
+        let (address_bar, url) = locate_address_bar(automation, hwnd)?;
+
+        if cache.len() >= MAX_CACHED_WINDOWS {
+            cache.clear();
+        }
+        cache.insert(hwnd, address_bar);
+
+        Some(url)
+    }
+
+    /// Find the address bar of a browser window.
+    /// Uses multiple strategies to find the address bar URL reliably.
+    unsafe fn locate_address_bar(
+        automation: &IUIAutomation,
+        hwnd: isize,
+    ) -> Option<(IUIAutomationElement, String)> {
+        // Get element from HWND
+        let hwnd = HWND(hwnd as *mut _);
+        let element = match automation.ElementFromHandle(hwnd) {
+            Ok(e) => e,
+            Err(e) => {
+                log::warn!("[UIA] Failed to get element from HWND: {:?}", e);
+                return None;
             }
-            
-            log::warn!("[UIA] All strategies failed to find URL");
-            None
+        };
+        
+        // Strategy 1: Search by known AutomationIds (fastest, most reliable)
+        log::debug!("[UIA] Strategy 1: Searching by AutomationId...");
+        if let Some(found) = find_by_automation_id(automation, &element) {
+            log::debug!("[UIA] Found URL via AutomationId strategy");
+            return Some(found);
+        }
+        
+        // Strategy 2: Search by Name containing "address"
+        log::debug!("[UIA] Strategy 2: Searching by Name property...");
+        if let Some(found) = find_by_name(automation, &element) {
+            log::debug!("[UIA] Found URL via Name strategy");
+            return Some(found);
+        }
+        
+        // Strategy 3: Find ToolBar then search Edit controls inside
+        log::debug!("[UIA] Strategy 3: Searching inside ToolBar...");
+        if let Some(found) = find_via_toolbar(automation, &element) {
+            log::debug!("[UIA] Found URL via ToolBar strategy");
+            return Some(found);
+        }
+        
+        // Strategy 4: Find ALL Edit controls and check for URL values
+        log::debug!("[UIA] Strategy 4: Searching all Edit controls...");
+        if let Some(found) = find_in_all_edit_controls(automation, &element) {
+            log::debug!("[UIA] Found URL via Edit control scan");
+            return Some(found);
+        }
+        
+        // Strategy 5: Search ComboBox controls (some browsers use ComboBox for address bar)
+        log::debug!("[UIA] Strategy 5: Searching ComboBox controls...");
+        if let Some(found) = find_in_combobox_controls(automation, &element) {
+            log::debug!("[UIA] Found URL via ComboBox strategy");
+            return Some(found);
+        }
+        
+        // Strategy 6: Search Document controls (for browser address displayed as document)
+        log::debug!("[UIA] Strategy 6: Searching Document controls...");
+        if let Some(found) = find_in_document_controls(automation, &element) {
+            log::debug!("[UIA] Found URL via Document strategy");
+            return Some(found);
         }
+        
+        log::warn!("[UIA] All strategies failed to find URL");
+        None
     }
 
     /// Strategy 1: Search for elements with known AutomationIds
     unsafe fn find_by_automation_id(
         automation: &IUIAutomation,
         element: &IUIAutomationElement
-    ) -> Option<String> {
+    ) -> Option<(IUIAutomationElement, String)> {
         // Known AutomationIds for browser address bars
         let known_ids = [
             "addressEditBox",      // Microsoft Edge
@@ -142,7 +240,7 @@ mod uia {
                             log::debug!("[UIA] Found element with AutomationId: {}", id_str);
                             if let Some(url) = get_element_value(&elem) {
                                 if looks_like_url(&url) {
-                                    return Some(url);
+                                    return Some((elem, url));
                                 }
                             }
                         }
@@ -158,7 +256,7 @@ mod uia {
     unsafe fn find_by_name(
         automation: &IUIAutomation,
         element: &IUIAutomationElement
-    ) -> Option<String> {
+    ) -> Option<(IUIAutomationElement, String)> {
         let name_keywords = [
             "address and search bar",
             "address bar",
@@ -189,7 +287,7 @@ mod uia {
                             log::debug!("[UIA] Found element with Name: {}", name_str);
                             if let Some(url) = get_element_value(&elem) {
                                 if looks_like_url(&url) {
-                                    return Some(url);
+                                    return Some((elem, url));
                                 }
                             }
                         }
@@ -205,7 +303,7 @@ mod uia {
     unsafe fn find_via_toolbar(
         automation: &IUIAutomation,
         element: &IUIAutomationElement
-    ) -> Option<String> {
+    ) -> Option<(IUIAutomationElement, String)> {
         // First find ToolBar controls
         let toolbar_type = VARIANT::from(UIA_ToolBarControlTypeId.0 as i32);
         let toolbar_condition = match automation.CreatePropertyCondition(
@@ -243,7 +341,7 @@ mod uia {
                             if let Some(value) = get_element_value(&edit) {
                                 if looks_like_url(&value) {
                                     log::debug!("[UIA] Found URL in ToolBar Edit control");
-                                    return Some(value);
+                                    return Some((edit, value));
                                 }
                             }
                         }
@@ -259,7 +357,7 @@ mod uia {
     unsafe fn find_in_all_edit_controls(
         automation: &IUIAutomation,
         element: &IUIAutomationElement
-    ) -> Option<String> {
+    ) -> Option<(IUIAutomationElement, String)> {
         let edit_type = VARIANT::from(UIA_EditControlTypeId.0 as i32);
         let condition = match automation.CreatePropertyCondition(
             UIA_ControlTypePropertyId,
@@ -278,7 +376,7 @@ mod uia {
         log::debug!("[UIA] Found {} total Edit controls", count);
         
         // Log all Edit controls for debugging
-        let mut found_urls: Vec<String> = Vec::new();
+        let mut found_urls: Vec<(IUIAutomationElement, String)> = Vec::new();
         for i in 0..count {
             if let Ok(edit) = edit_elements.GetElement(i) {
                 let auto_id = edit.CurrentAutomationId().map(|s| s.to_string()).unwrap_or_default();
@@ -287,7 +385,7 @@ mod uia {
                 if let Some(value) = get_element_value(&edit) {
                     log::debug!("[UIA] Edit[{}] AutomationId='{}' Name='{}' Value='{}'", i, auto_id, name, &value[..value.len().min(80)]);
                     if looks_like_url(&value) {
-                        found_urls.push(value);
+                        found_urls.push((edit, value));
                     }
                 } else {
                     log::debug!("[UIA] Edit[{}] AutomationId='{}' Name='{}' (no value)", i, auto_id, name);
@@ -308,7 +406,7 @@ mod uia {
     unsafe fn find_in_combobox_controls(
         automation: &IUIAutomation,
         element: &IUIAutomationElement
-    ) -> Option<String> {
+    ) -> Option<(IUIAutomationElement, String)> {
         let combobox_type = VARIANT::from(UIA_ComboBoxControlTypeId.0 as i32);
         let condition = match automation.CreatePropertyCondition(
             UIA_ControlTypePropertyId,
@@ -331,7 +429,7 @@ mod uia {
                 // Check the ComboBox value directly
                 if let Some(value) = get_element_value(&elem) {
                     if looks_like_url(&value) {
-                        return Some(value);
+                        return Some((elem, value));
                     }
                 }
                 
@@ -347,7 +445,7 @@ mod uia {
                             if let Ok(edit) = edits.GetElement(j) {
                                 if let Some(value) = get_element_value(&edit) {
                                     if looks_like_url(&value) {
-                                        return Some(value);
+                                        return Some((edit, value));
                                     }
                                 }
                             }
@@ -364,7 +462,7 @@ mod uia {
     unsafe fn find_in_document_controls(
         automation: &IUIAutomation,
         element: &IUIAutomationElement
-    ) -> Option<String> {
+    ) -> Option<(IUIAutomationElement, String)> {
         let doc_type = VARIANT::from(UIA_DocumentControlTypeId.0 as i32);
         let condition = match automation.CreatePropertyCondition(
             UIA_ControlTypePropertyId,
@@ -386,7 +484,7 @@ mod uia {
             if let Ok(elem) = elements.GetElement(i) {
                 if let Some(value) = get_element_value(&elem) {
                     if looks_like_url(&value) {
-                        return Some(value);
+                        return Some((elem, value));
                     }
                 }
             }
@@ -470,6 +568,172 @@ mod uia {
     }
 }
 
+/// macOS AppleScript queries for the active tab URL
+#[cfg(target_os = "macos")]
+mod applescript {
+    use crate::permissions::{self, AutomationPermission, APPLE_EVENTS_NOT_AUTHORIZED};
+    use std::collections::{HashMap, HashSet};
+    use std::process::{Command, Stdio};
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+
+    /// A query that takes longer is abandoned; it runs off the sampling path,
+    /// but a stuck Automation prompt must not pile up osascript processes
+    const SCRIPT_TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// Answers older than this are not used for the current sample
+    const RESULT_TTL: Duration = Duration::from_secs(10);
+
+    /// Printed by the script for incognito windows
+    const PRIVATE_MARKER: &str = "<private>";
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum TabUrl {
+        Url(String),
+        /// The front window is a private/incognito window
+        Private,
+        /// No answer for this window yet, or the query failed; the window
+        /// may be private, so its title must not be parsed either
+        Unknown,
+    }
+
+    struct Answer {
+        window_title: Option<String>,
+        tab: Option<TabUrl>,
+        at: Instant,
+    }
+
+    static ANSWERS: OnceLock<Mutex<HashMap<String, Answer>>> = OnceLock::new();
+    static IN_FLIGHT: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+    fn answers() -> std::sync::MutexGuard<'static, HashMap<String, Answer>> {
+        match ANSWERS.get_or_init(|| Mutex::new(HashMap::new())).lock() {
+            Ok(guard) => guard,
+            Err(e) => e.into_inner(),
+        }
+    }
+
+    fn in_flight() -> std::sync::MutexGuard<'static, HashSet<String>> {
+        match IN_FLIGHT.get_or_init(|| Mutex::new(HashSet::new())).lock() {
+            Ok(guard) => guard,
+            Err(e) => e.into_inner(),
+        }
+    }
+
+    /// AppleScript returning the active tab URL for a browser bundle id.
+    /// Only Chromium-based browsers tell private windows apart (`mode`).
+    /// Safari's dictionary has no such property, so it is not queried and
+    /// stays on window title parsing.
+    fn url_script(bundle_id: &str) -> Option<String> {
+        match bundle_id {
+            "com.google.Chrome"
+            | "com.google.Chrome.beta"
+            | "com.google.Chrome.canary"
+            | "com.microsoft.edgemac"
+            | "com.brave.Browser"
+            | "com.vivaldi.Vivaldi"
+            | "com.operasoftware.Opera"
+            | "company.thebrowser.Browser" => Some(format!(
+                "tell application id \"{}\"\n\
+                 if mode of front window is \"incognito\" then return \"{}\"\n\
+                 return URL of active tab of front window\n\
+                 end tell",
+                bundle_id, PRIVATE_MARKER
+            )),
+            // Firefox has no AppleScript dictionary
+            _ => None,
+        }
+    }
+
+    /// Active tab of the browser's front window.
+    ///
+    /// The osascript query runs on a background thread so a busy browser or
+    /// an Automation prompt never blocks focus sampling. The caller gets the
+    /// latest answer for the same window title, and `Unknown` until one
+    /// arrives or when Automation is denied. `None` only for browsers
+    /// without a script.
+    pub fn get_browser_url(bundle_id: &str, window_title: Option<&str>) -> Option<TabUrl> {
+        let script = url_script(bundle_id)?;
+
+        if !permissions::can_automate(bundle_id) {
+            log::trace!("Automation permission denied for {}, skipping AppleScript", bundle_id);
+            return Some(TabUrl::Unknown);
+        }
+
+        if in_flight().insert(bundle_id.to_string()) {
+            let bundle_id = bundle_id.to_string();
+            let window_title = window_title.map(str::to_string);
+            let spawned = std::thread::Builder::new()
+                .name("applescript-url".to_string())
+                .spawn({
+                    let bundle_id = bundle_id.clone();
+                    move || {
+                        let tab = run_script(&bundle_id, &script);
+                        answers().insert(bundle_id.clone(), Answer { window_title, tab, at: Instant::now() });
+                        in_flight().remove(&bundle_id);
+                    }
+                });
+            if spawned.is_err() {
+                in_flight().remove(&bundle_id);
+            }
+        }
+
+        let tab = answers()
+            .get(bundle_id)
+            .filter(|answer| answer.window_title.as_deref() == window_title && answer.at.elapsed() < RESULT_TTL)
+            .and_then(|answer| answer.tab.clone());
+        Some(tab.unwrap_or(TabUrl::Unknown))
+    }
+
+    fn run_script(bundle_id: &str, script: &str) -> Option<TabUrl> {
+        let mut child = Command::new("osascript")
+            .arg("-e")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .ok()?;
+
+        let started = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(_)) => break,
+                Ok(None) if started.elapsed() < SCRIPT_TIMEOUT => {
+                    std::thread::sleep(Duration::from_millis(25));
+                }
+                _ => {
+                    log::debug!("AppleScript URL query for {} timed out", bundle_id);
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return None;
+                }
+            }
+        }
+
+        let output = child.wait_with_output().ok()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains(APPLE_EVENTS_NOT_AUTHORIZED) {
+                permissions::record_automation_result(bundle_id, AutomationPermission::Denied);
+            } else {
+                log::debug!("AppleScript URL query for {} failed: {}", bundle_id, stderr.trim());
+            }
+            return None;
+        }
+
+        permissions::record_automation_result(bundle_id, AutomationPermission::Granted);
+
+        let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if url == PRIVATE_MARKER {
+            Some(TabUrl::Private)
+        } else if url.is_empty() || url == "missing value" {
+            None
+        } else {
+            Some(TabUrl::Url(url))
+        }
+    }
+}
+
 /// Result of URL extraction from a browser
 #[derive(Debug, Clone)]
 pub struct BrowserUrlInfo {
@@ -477,6 +741,8 @@ pub struct BrowserUrlInfo {
     pub url: Option<String>,
     /// The domain extracted from the browser
     pub domain: Option<String>,
+    /// The registrable domain (eTLD+1), e.g. "bbc.co.uk" for "news.bbc.co.uk"
+    pub registrable_domain: Option<String>,
 }
 
 impl BrowserUrlInfo {
@@ -484,21 +750,38 @@ impl BrowserUrlInfo {
         Self {
             url: None,
             domain: None,
+            registrable_domain: None,
         }
     }
     
     pub fn from_domain(domain: String) -> Self {
+        let registrable_domain = public_suffix::registrable_domain(&domain);
         Self {
             url: None,
             domain: Some(domain),
+            registrable_domain,
         }
     }
     
     pub fn from_url(url: String) -> Self {
         let domain = extract_domain_from_url(&url);
+        let registrable_domain = domain.as_deref().and_then(public_suffix::registrable_domain);
         Self {
             url: Some(url),
             domain,
+            registrable_domain,
+        }
+    }
+
+    /// (url, domain) to record under the URL policy
+    pub fn sanitized(&self, sanitizer: &UrlSanitizer) -> (Option<String>, Option<String>) {
+        if let Some(url) = self.url.as_deref() {
+            sanitizer.sanitize(Some(url))
+        } else if let Some(domain) = self.domain.as_deref() {
+            // Only have domain from title, use it as both
+            sanitizer.sanitize_domain(domain)
+        } else {
+            (None, None)
         }
     }
 }
@@ -535,13 +818,21 @@ fn extract_domain_from_url(url: &str) -> Option<String> {
         return None;
     }
     
+    // Without a scheme this may be a search term or file name; require a real public suffix
+    if !url.contains("://") && domain != "localhost" && !public_suffix::looks_like_domain(domain) {
+        return None;
+    }
+    
     Some(domain.to_lowercase())
 }
 
 /// Extract URL/domain from a browser window
 /// 
+/// If the TrackEx browser extension reported the active tab recently, that URL is used.
 /// On Windows: Uses UI Automation API to read the actual URL from the address bar
-/// On macOS: Uses window title parsing as fallback
+/// On macOS: Uses AppleScript for Chromium browsers; titles are parsed only for
+/// browsers without a script (Safari, Firefox), since a Chromium window that
+/// hasn't been checked yet may be incognito
 /// 
 /// This is the MOST ROBUST solution because it reads the actual URL directly
 /// from the browser's address bar, eliminating issues with:
@@ -560,17 +851,38 @@ pub fn extract_browser_url(
         return BrowserUrlInfo::empty();
     }
     
+    // Prefer the active tab pushed by the TrackEx browser extension (works for SPAs)
+    if let Some(tab) = super::browser_bridge::get_active_tab(app_name, app_id) {
+        log::debug!("Got URL from browser extension");
+        return BrowserUrlInfo::from_url(tab.url);
+    }
+    
     // On Windows, try UI Automation first for accurate URL extraction
     #[cfg(target_os = "windows")]
     if let Some(handle) = hwnd {
         if let Some(url) = uia::get_browser_url(handle) {
-            log::info!("Got URL from UI Automation: {}", url);
+            log::debug!("Got URL from UI Automation");
             return BrowserUrlInfo::from_url(url);
         } else {
             log::debug!("UI Automation failed to get URL, falling back to window title");
         }
     }
     
+    // On macOS, ask Chromium-based browsers for the active tab URL
+    #[cfg(target_os = "macos")]
+    match applescript::get_browser_url(app_id, window_title) {
+        Some(applescript::TabUrl::Url(url)) => {
+            log::debug!("Got URL from AppleScript");
+            return BrowserUrlInfo::from_url(url);
+        }
+        // Nothing about private windows is recorded, not even the title's
+        // domain, and a window not checked yet may be private
+        Some(applescript::TabUrl::Private) | Some(applescript::TabUrl::Unknown) => {
+            return BrowserUrlInfo::empty()
+        }
+        None => {}
+    }
+    
     // Suppress unused variable warning on non-Windows
     #[cfg(not(target_os = "windows"))]
     let _ = hwnd;
@@ -579,7 +891,7 @@ pub fn extract_browser_url(
     // This works on macOS and as fallback on Windows
     if let Some(title) = window_title {
         if let Some(domain) = extract_domain_from_window_title(title) {
-            log::debug!("Extracted domain '{}' from browser title: {}", domain, title);
+            log::debug!("Extracted domain '{}' from browser title", domain);
             return BrowserUrlInfo::from_domain(domain);
         }
     }
@@ -640,5 +952,9 @@ mod tests {
         let info = BrowserUrlInfo::from_url("https://github.com/user/repo".to_string());
         assert_eq!(info.url, Some("https://github.com/user/repo".to_string()));
         assert_eq!(info.domain, Some("github.com".to_string()));
+        
+        let info = BrowserUrlInfo::from_url("https://news.bbc.co.uk/world".to_string());
+        assert_eq!(info.domain, Some("news.bbc.co.uk".to_string()));
+        assert_eq!(info.registrable_domain, Some("bbc.co.uk".to_string()));
     }
 }
@@ -11,6 +11,7 @@
 //! The batch interval is 10 seconds to balance real-time updates with server efficiency.
 
 use chrono::{DateTime, Utc};
+use crate::utils::clock;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
@@ -41,7 +42,7 @@ impl EventBatcherState {
     fn new() -> Self {
         Self {
             events: Vec::new(),
-            last_flush: Utc::now(),
+            last_flush: clock::now(),
         }
     }
 }
@@ -60,7 +61,7 @@ lazy_static::lazy_static! {
 pub async fn queue_event(event_type: &str, data: &Value) {
     let event = BatchedEvent {
         event_type: event_type.to_string(),
-        timestamp: Utc::now(),
+        timestamp: clock::now(),
         data: data.clone(),
     };
 
@@ -104,7 +105,7 @@ pub async fn flush_events() {
         
         // Take all events and reset state
         let events = std::mem::take(&mut state.events);
-        state.last_flush = Utc::now();
+        state.last_flush = clock::now();
         events
     };
 
@@ -116,7 +117,7 @@ pub async fn flush_events() {
         "events": events_to_send.iter().map(|e| {
             serde_json::json!({
                 "type": e.event_type,
-                "timestamp": e.timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                "timestamp": crate::utils::time::format_timestamp(e.timestamp),
                 "data": e.data,
                 "from": "event_batcher"
             })
@@ -152,19 +153,10 @@ async fn send_batch_to_server(payload: &Value) -> anyhow::Result<()> {
         return Err(anyhow::anyhow!("Server URL or device token is empty"));
     }
     
-    let client = reqwest::Client::builder()
-        .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-        .build()?;
+    crate::api::rate_limiter::acquire(crate::api::rate_limiter::IngestKind::Event).await;
     
-    let events_url = format!("{}/api/ingest/events", server_url.trim_end_matches('/'));
-    
-    let response = client
-        .post(&events_url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", device_token))
-        .json(payload)
-        .send()
-        .await?;
+    let client = crate::api::client::ApiClient::new().await?;
+    let response = client.post_with_auth("/api/ingest/events", payload).await?;
     
     if response.status().is_success() {
         Ok(())
@@ -1,9 +1,13 @@
 use tauri::AppHandle;
 use tokio::time::Duration;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use std::sync::OnceLock;
+use chrono::{DateTime, Utc};
+use crate::utils::clock;
+use crate::utils::time::format_timestamp;
 
 use crate::sampling::{idle_detector};
 use crate::storage::{work_session, offline_queue};
@@ -26,19 +30,156 @@ pub async fn trigger_immediate_heartbeat() {
     log::debug!("Immediate heartbeat triggered");
 }
 
+/// Time range covered by a coalesced heartbeat
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoveredRange {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Number of interval heartbeats represented (including the one being sent)
+    pub heartbeat_count: u32,
+}
+
+#[derive(Debug, PartialEq)]
+enum HeartbeatDecision {
+    Send(Option<CoveredRange>),
+    Skip,
+}
+
+/// Coalesces identical idle heartbeats.
+///
+/// While the user stays idle only one heartbeat per idle interval is sent; it
+/// carries the time range of the heartbeats it replaces. When the user becomes
+/// active again the pending range is flushed with the first active heartbeat.
+#[derive(Debug, Default)]
+struct IdleCoalescer {
+    /// Time of the last heartbeat actually sent, and whether it was idle
+    last_sent: Option<(DateTime<Utc>, bool)>,
+    /// Start of the range of skipped idle heartbeats
+    window_start: Option<DateTime<Utc>>,
+    skipped: u32,
+}
+
+impl IdleCoalescer {
+    fn decide(&mut self, now: DateTime<Utc>, is_idle: bool, idle_interval_secs: i64) -> HeartbeatDecision {
+        let last_idle_send = match self.last_sent {
+            Some((at, true)) if is_idle => Some(at),
+            _ => None,
+        };
+
+        if let Some(last_at) = last_idle_send {
+            if (now - last_at).num_seconds() < idle_interval_secs {
+                self.window_start.get_or_insert(now);
+                self.skipped += 1;
+                return HeartbeatDecision::Skip;
+            }
+        }
+
+        let range = self.window_start.take().map(|from| CoveredRange {
+            from,
+            to: now,
+            heartbeat_count: self.skipped + 1,
+        });
+        self.skipped = 0;
+        self.last_sent = Some((now, is_idle));
+        HeartbeatDecision::Send(range)
+    }
+}
+
+/// Presence state that drives the heartbeat interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Activity {
+    Active,
+    Idle,
+}
+
+/// Heartbeat interval that follows activity: the minimum while the user is
+/// active and doubling towards the maximum while they stay idle. Large fleets
+/// of idle machines then cost the backend a fraction of the requests while
+/// activity is still reported at full rate (app changes and the end of an
+/// idle period also trigger an immediate heartbeat).
+#[derive(Debug, Default)]
+struct AdaptiveInterval {
+    idle_secs: Option<u64>,
+}
+
+impl AdaptiveInterval {
+    fn next(&mut self, activity: Activity, min_secs: u64, max_secs: u64) -> u64 {
+        let max_secs = max_secs.max(min_secs);
+        match activity {
+            Activity::Active => {
+                self.idle_secs = None;
+                min_secs
+            }
+            Activity::Idle => {
+                let next = self.idle_secs.map_or(min_secs, |secs| secs * 2).clamp(min_secs, max_secs);
+                self.idle_secs = Some(next);
+                next
+            }
+        }
+    }
+}
+
+/// How much about the current activity heartbeats may carry. Some orgs only
+/// want presence in heartbeats; app usage is still reported through events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HeartbeatDetail {
+    #[default]
+    Full,
+    /// App name and id only, no window title, URL or domain
+    AppOnly,
+    /// Online and idle state only, no current app
+    PresenceOnly,
+}
+
+impl HeartbeatDetail {
+    /// Strip what this level doesn't allow from a heartbeat payload
+    pub fn apply(self, heartbeat: &mut Value) {
+        match self {
+            HeartbeatDetail::Full => {}
+            HeartbeatDetail::AppOnly => {
+                if let Some(app) = heartbeat.get_mut("currentApp").and_then(Value::as_object_mut) {
+                    app.retain(|key, _| key == "name" || key == "app_id");
+                }
+            }
+            HeartbeatDetail::PresenceOnly => {
+                heartbeat["currentApp"] = Value::Null;
+            }
+        }
+        if self != HeartbeatDetail::Full {
+            // Tells the backend the missing fields are withheld, not unknown
+            heartbeat["heartbeat_detail"] = json!(self);
+        }
+    }
+}
+
+/// Interval bounds from the policy, falling back to the built-in defaults
+fn interval_bounds(policy: &crate::api::employee_settings::PolicySettings) -> (u64, u64) {
+    let min_secs = match policy.heartbeat_min_interval_s {
+        secs if secs > 0 => secs as u64,
+        _ => super::get_heartbeat_interval(),
+    };
+    let max_secs = match policy.heartbeat_max_interval_s {
+        secs if secs > 0 => secs as u64,
+        _ => super::get_max_heartbeat_interval(),
+    };
+    (min_secs, max_secs)
+}
+
 #[allow(dead_code)]
 pub async fn start_heartbeat_service(_app_handle: AppHandle) {
-    let interval_seconds = super::get_heartbeat_interval();
-    let mut interval = tokio::time::interval(Duration::from_secs(interval_seconds));
     let trigger = get_heartbeat_trigger();
+    let mut coalescer = IdleCoalescer::default();
+    let mut adaptive = AdaptiveInterval::default();
+    let mut next_at = tokio::time::Instant::now();
     
-    log::info!("Heartbeat service starting (interval: {}s)", interval_seconds);
+    log::info!("Heartbeat service starting (adaptive interval)");
     
     loop {
-        // Wait for either the interval to tick or check for trigger periodically
+        // Wait for either the next heartbeat time or check for trigger periodically
         tokio::select! {
-            _ = interval.tick() => {
-                // Regular interval tick
+            _ = tokio::time::sleep_until(next_at) => {
+                // Regular heartbeat
             }
             _ = tokio::time::sleep(Duration::from_millis(100)) => {
                 // Check if immediate heartbeat was triggered
@@ -64,33 +205,40 @@ pub async fn start_heartbeat_service(_app_handle: AppHandle) {
             // Stop if user is not authenticated or not clocked in
             if !super::is_services_running().await {
                 log::info!("Heartbeat service stopping - user clocked out or logged out");
+                super::backend_health::reset().await;
                 break; // Service stopped completely
             }
             // Otherwise, just wait before checking again
+            next_at = tokio::time::Instant::now() + Duration::from_secs(super::get_heartbeat_interval());
             continue;
         }
 
         // Send heartbeat - ALWAYS send, even when idle
         // The heartbeat includes the idle status, so the backend knows if user is active or idle
-        match send_heartbeat().await {
-            Ok(_) => {
-                // Heartbeat sent successfully
-            }
+        let next_secs = match send_heartbeat(&mut coalescer, &mut adaptive).await {
+            Ok(next_secs) => next_secs,
             Err(e) => {
                 log::error!("Failed to send heartbeat (will retry on next interval): {}", e);
                 // Don't break - continue sending heartbeats on next interval
+                super::get_heartbeat_interval()
             }
-        }
+        };
+        super::overtime::check().await;
+        next_at = tokio::time::Instant::now() + Duration::from_secs(next_secs);
     }
 
     log::info!("Heartbeat service stopped");
 }
 
+/// Send (or coalesce) one heartbeat and return the seconds until the next one
 #[allow(dead_code)]
-async fn send_heartbeat() -> anyhow::Result<()> {
+async fn send_heartbeat(coalescer: &mut IdleCoalescer, adaptive: &mut AdaptiveInterval) -> anyhow::Result<u64> {
     // Get current app info
     let current_app = match get_current_app().await {
-        Ok(app_opt) => app_opt,
+        Ok(app_opt) => app_opt.map(|mut app| {
+            app.window_title = crate::api::title_rules::scrub_title(app.window_title);
+            app
+        }),
         Err(e) => {
             log::debug!("Could not get current app for heartbeat: {}", e);
             None
@@ -102,7 +250,24 @@ async fn send_heartbeat() -> anyhow::Result<()> {
     let idle_threshold = idle_detector::get_idle_threshold();
     let is_idle = idle_time >= idle_threshold;
 
-    let now = chrono::Utc::now();
+    let now = clock::now();
+    let is_paused = super::is_services_paused().await;
+    let pause = super::current_pause();
+
+    let policy = crate::api::employee_settings::get_policy_settings().await;
+    let (min_secs, max_secs) = interval_bounds(&policy);
+    let activity = if is_idle { Activity::Idle } else { Activity::Active };
+    let next_secs = adaptive.next(activity, min_secs, max_secs);
+    
+    // Coalesce repeated idle heartbeats into one per idle interval
+    let idle_interval = super::get_idle_heartbeat_interval() as i64;
+    let covered_range = match coalescer.decide(now, is_idle, idle_interval) {
+        HeartbeatDecision::Send(range) => range,
+        HeartbeatDecision::Skip => {
+            log::trace!("Idle heartbeat coalesced (idle_time={}s)", idle_time);
+            return Ok(next_secs);
+        }
+    };
     
     // Check if there's an active work session
     let session_active = work_session::is_session_active().await.unwrap_or(false);
@@ -110,7 +275,8 @@ async fn send_heartbeat() -> anyhow::Result<()> {
     let (session_start, total_session_time, total_active_today, total_idle_today) = if session_active {
         // Get session start time for time calculations
         let session_start = work_session::get_session_start_time().await.unwrap_or_else(|_| now);
-        let total_session_time = (now - session_start).num_seconds();
+        let paused = work_session::get_session_paused_seconds().await.unwrap_or(0);
+        let total_session_time = ((now - session_start).num_seconds() - paused).max(0);
         
         // Calculate cumulative active and idle time for today
         let (cumulative_active_time, cumulative_idle_time) = work_session::get_today_time_totals().await.unwrap_or((0, 0));
@@ -132,12 +298,15 @@ async fn send_heartbeat() -> anyhow::Result<()> {
         (now, 0, 0, 0)
     };
 
+    // Office/remote classification only, never the address or SSID
+    let network = crate::location::office_network::classify_current_network(&policy).await;
+
     // Create heartbeat data with complete time information
     // WORKAROUND: Always send status="active" to keep user in "Online Now" count
     // Backend should ideally treat both 'active' and 'idle' as online, but until then,
     // we send status="active" and let the backend/frontend use idle_time_seconds to show idle state
-    let heartbeat_data = json!({
-        "timestamp": now.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+    let mut heartbeat_data = json!({
+        "timestamp": format_timestamp(now),
         "status": "active",  // Always "active" to stay in Online count (workaround)
         "idle_time_seconds": idle_time,  // Backend can use this to determine if user is idle
         "is_idle": is_idle,  // Explicit idle flag for future use
@@ -148,36 +317,140 @@ async fn send_heartbeat() -> anyhow::Result<()> {
             "url": app.url,
             "domain": app.domain
         })),
-        "session_start_time": session_start.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        "session_start_time": format_timestamp(session_start),
         "total_session_time_seconds": total_session_time,
         "active_time_today_seconds": total_active_today,
         "idle_time_today_seconds": total_idle_today,
-        "is_paused": super::is_services_paused().await
+        "is_paused": is_paused,
+        "pause_reason": pause.as_ref().map(|pause| pause.reason),
+        "paused_until": pause.as_ref().and_then(|pause| pause.resume_at),
+        "network": network,
+        "storage_pressure": crate::storage::storage_guard::status().pressure,
+        "environment": crate::utils::session_environment::detect(),
+        "power": super::power_state::power_status(),
+        "is_screen_sharing": super::screen_sharing::is_screen_sharing(),
+        // Lets the backend scale its presence timeout with the adaptive interval
+        "heartbeat_interval_seconds": next_secs
     });
+    
+    if let Some(range) = &covered_range {
+        heartbeat_data["covered_range"] = json!({
+            "from": format_timestamp(range.from),
+            "to": format_timestamp(range.to),
+            "heartbeat_count": range.heartbeat_count
+        });
+        log::debug!("Heartbeat covers {} coalesced idle heartbeats", range.heartbeat_count);
+    }
+    policy.heartbeat_detail.apply(&mut heartbeat_data);
 
     // Try to send heartbeat live first, fallback to queue if failed
     match super::send_heartbeat_to_backend(&heartbeat_data).await {
         Ok(_) => {
-            log::info!("✓ Heartbeat sent (status=active, idle_time={}s, user_is_idle={})", 
-                idle_time, is_idle);
-            Ok(())
+            log::info!("✓ Heartbeat sent (status=active, idle_time={}s, user_is_idle={}, next in {}s)", 
+                idle_time, is_idle, next_secs);
+            super::backend_health::heartbeat_succeeded().await;
+            Ok(next_secs)
         }
         Err(e) => {
             log::warn!("Failed to send heartbeat live, queuing for later: {}", e);
+            super::backend_health::heartbeat_failed().await;
             // Queue heartbeat for offline processing
             match offline_queue::queue_heartbeat(&heartbeat_data).await {
                 Ok(_) => {
                     log::debug!("Heartbeat queued for later delivery");
-                    Ok(())
+                    Ok(next_secs)
                 }
                 Err(queue_err) => {
                     log::error!("Failed to queue heartbeat: {}", queue_err);
                     // Don't return error - we want heartbeat service to continue
                     // Just log the error and move on to next heartbeat
-                    Ok(())
+                    Ok(next_secs)
                 }
             }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_heartbeats_are_coalesced() {
+        let mut coalescer = IdleCoalescer::default();
+        let start = Utc::now();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+
+        // First idle heartbeat is always sent
+        assert_eq!(coalescer.decide(at(0), true, 60), HeartbeatDecision::Send(None));
+        // Following idle heartbeats within the idle interval are skipped
+        assert_eq!(coalescer.decide(at(10), true, 60), HeartbeatDecision::Skip);
+        assert_eq!(coalescer.decide(at(20), true, 60), HeartbeatDecision::Skip);
+
+        // Next idle heartbeat carries the covered range
+        let range = CoveredRange { from: at(10), to: at(60), heartbeat_count: 3 };
+        assert_eq!(coalescer.decide(at(60), true, 60), HeartbeatDecision::Send(Some(range)));
+    }
+
+    #[test]
+    fn test_activity_flushes_pending_range() {
+        let mut coalescer = IdleCoalescer::default();
+        let start = Utc::now();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+
+        assert_eq!(coalescer.decide(at(0), false, 60), HeartbeatDecision::Send(None));
+        assert_eq!(coalescer.decide(at(10), true, 60), HeartbeatDecision::Send(None));
+        assert_eq!(coalescer.decide(at(20), true, 60), HeartbeatDecision::Skip);
+
+        let range = CoveredRange { from: at(20), to: at(30), heartbeat_count: 2 };
+        assert_eq!(coalescer.decide(at(30), false, 60), HeartbeatDecision::Send(Some(range)));
+        assert_eq!(coalescer.decide(at(40), false, 60), HeartbeatDecision::Send(None));
+    }
+
+    #[test]
+    fn test_adaptive_interval() {
+        let mut adaptive = AdaptiveInterval::default();
+        assert_eq!(adaptive.next(Activity::Active, 10, 60), 10);
+        // Idle backs off exponentially up to the maximum
+        assert_eq!(adaptive.next(Activity::Idle, 10, 60), 10);
+        assert_eq!(adaptive.next(Activity::Idle, 10, 60), 20);
+        assert_eq!(adaptive.next(Activity::Idle, 10, 60), 40);
+        assert_eq!(adaptive.next(Activity::Idle, 10, 60), 60);
+        assert_eq!(adaptive.next(Activity::Idle, 10, 60), 60);
+        // Activity resets immediately
+        assert_eq!(adaptive.next(Activity::Active, 10, 60), 10);
+        assert_eq!(adaptive.next(Activity::Idle, 10, 60), 10);
+        // A maximum below the minimum is ignored
+        assert_eq!(adaptive.next(Activity::Idle, 10, 5), 10);
+    }
+
+    #[test]
+    fn test_heartbeat_detail_levels() {
+        let heartbeat = json!({
+            "status": "active",
+            "is_idle": false,
+            "currentApp": {
+                "name": "Safari",
+                "app_id": "com.apple.Safari",
+                "window_title": "Quarterly plan - Docs",
+                "url": "https://docs.example.com/plan",
+                "domain": "docs.example.com"
+            }
+        });
+
+        let mut full = heartbeat.clone();
+        HeartbeatDetail::Full.apply(&mut full);
+        assert_eq!(full, heartbeat);
+
+        let mut app_only = heartbeat.clone();
+        HeartbeatDetail::AppOnly.apply(&mut app_only);
+        assert_eq!(app_only["currentApp"], json!({ "name": "Safari", "app_id": "com.apple.Safari" }));
+        assert_eq!(app_only["heartbeat_detail"], "app_only");
+
+        let mut presence = heartbeat.clone();
+        HeartbeatDetail::PresenceOnly.apply(&mut presence);
+        assert!(presence["currentApp"].is_null());
+        assert_eq!(presence["is_idle"], false);
+        assert_eq!(presence["heartbeat_detail"], "presence_only");
+    }
+}
@@ -41,7 +41,7 @@ pub async fn get_idle_time() -> Result<u64> {
                                     // Convert nanoseconds to seconds
                                     let idle_seconds = idle_ns / 1_000_000_000;
                                     log::trace!("macOS idle time: {}s ({}ns)", idle_seconds, idle_ns);
-                                    return Ok(idle_seconds);
+                                    return Ok(super::input_activity::adjust_idle_time(idle_seconds));
                                 }
                             }
                         }
@@ -77,7 +77,8 @@ pub async fn get_idle_time() -> Result<u64> {
             let idle_time_ms = current_time - last_input_info.dwTime;
             let idle_seconds = idle_time_ms as u64 / 1000;
             log::trace!("Windows idle time: {}s ({}ms)", idle_seconds, idle_time_ms);
-            return Ok(idle_seconds) // Convert to seconds
+            // Game controllers don't reset GetLastInputInfo
+            return Ok(super::input_activity::adjust_idle_time(idle_seconds))
         } else {
             log::warn!("GetLastInputInfo failed");
             return Ok(0)
@@ -119,12 +120,19 @@ pub async fn get_detailed_idle_info() -> Result<IdleInfo> {
     let idle_time = get_idle_time().await?;
     let threshold = get_idle_threshold();
     let is_idle = idle_time >= threshold;
+    let now = crate::utils::clock::now();
+
+    // Today's stored idle periods (since local midnight)
+    let today_start = crate::utils::time::local_midnight(crate::utils::clock::today());
+    let conn = crate::storage::database::get_connection()?;
+    let breakdown = crate::storage::idle_periods::breakdown_between(&conn, today_start, now)?;
     
     Ok(IdleInfo {
         idle_time_seconds: idle_time,
         threshold_seconds: threshold,
         is_idle,
-        last_activity_time: chrono::Utc::now() - chrono::Duration::seconds(idle_time as i64),
+        last_activity_time: now - chrono::Duration::seconds(idle_time as i64),
+        breakdown,
     })
 }
 
@@ -134,6 +142,9 @@ pub struct IdleInfo {
     pub threshold_seconds: u64,
     pub is_idle: bool,
     pub last_activity_time: chrono::DateTime<chrono::Utc>,
+    /// Today's idle periods by length and meeting time
+    #[serde(default)]
+    pub breakdown: crate::storage::idle_periods::IdleBreakdown,
 }
 
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
@@ -0,0 +1,184 @@
+//! Input the OS idle counters miss
+//!
+//! Idle time comes from `GetLastInputInfo` (Windows) and `HIDIdleTime`
+//! (macOS). Touch and pen input already reset both, but game controllers
+//! (XInput) never do, and the ioreg counter can lag behind tablet pointer
+//! events. This module tracks those extra sources and `idle_detector` takes
+//! the shortest idle time of all of them.
+//!
+//! Windows: XInput controllers are polled on every idle check; a changed
+//! button or a stick/trigger that moved past its deadzone means the
+//! controller was used since the previous check. The packet number alone
+//! also changes on analog noise, so a resting controller would keep the
+//! machine active.
+//! macOS: tablet pointer/proximity events are read from the HID event source.
+//! Controllers on macOS are not covered.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Unix time (ms) of the last input seen from an extra source, 0 = none
+static LAST_EXTRA_INPUT_MS: AtomicI64 = AtomicI64::new(0);
+
+/// Record input from a source the OS idle counter doesn't see
+pub fn record_activity() {
+    LAST_EXTRA_INPUT_MS.store(crate::utils::clock::now().timestamp_millis(), Ordering::Relaxed);
+}
+
+fn seconds_since_extra_input(now_ms: i64) -> Option<u64> {
+    let last = LAST_EXTRA_INPUT_MS.load(Ordering::Relaxed);
+    (last > 0).then(|| ((now_ms - last).max(0) / 1000) as u64)
+}
+
+/// Idle time once extra input sources are taken into account
+fn combine(os_idle_seconds: u64, extra_idle_seconds: &[Option<u64>]) -> u64 {
+    extra_idle_seconds
+        .iter()
+        .flatten()
+        .fold(os_idle_seconds, |idle, extra| idle.min(*extra))
+}
+
+/// XINPUT_GAMEPAD_LEFT_THUMB_DEADZONE
+#[cfg(any(target_os = "windows", test))]
+const LEFT_THUMB_DEADZONE: i32 = 7849;
+/// XINPUT_GAMEPAD_RIGHT_THUMB_DEADZONE
+#[cfg(any(target_os = "windows", test))]
+const RIGHT_THUMB_DEADZONE: i32 = 8689;
+/// XINPUT_GAMEPAD_TRIGGER_THRESHOLD
+#[cfg(any(target_os = "windows", test))]
+const TRIGGER_THRESHOLD: i32 = 30;
+
+/// The parts of an XInput gamepad state that show deliberate input
+#[cfg(any(target_os = "windows", test))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct PadState {
+    buttons: u16,
+    /// Left X/Y, right X/Y
+    thumbs: [i16; 4],
+    /// Left, right
+    triggers: [u8; 2],
+}
+
+#[cfg(any(target_os = "windows", test))]
+impl PadState {
+    /// Whether going from `self` to `next` is input rather than analog noise
+    fn used_since(&self, next: &PadState) -> bool {
+        let moved = |from: i32, to: i32, deadzone: i32| (to - from).abs() > deadzone;
+        let thumb_deadzones = [LEFT_THUMB_DEADZONE, LEFT_THUMB_DEADZONE, RIGHT_THUMB_DEADZONE, RIGHT_THUMB_DEADZONE];
+        self.buttons != next.buttons
+            || self
+                .thumbs
+                .iter()
+                .zip(next.thumbs.iter())
+                .zip(thumb_deadzones)
+                .any(|((from, to), deadzone)| moved(*from as i32, *to as i32, deadzone))
+            || self
+                .triggers
+                .iter()
+                .zip(next.triggers.iter())
+                .any(|(from, to)| moved(*from as i32, *to as i32, TRIGGER_THRESHOLD))
+    }
+}
+
+/// Poll XInput controllers; a changed button or a stick/trigger moved past
+/// its deadzone since the last counted input counts as input
+#[cfg(target_os = "windows")]
+fn poll_gamepads() {
+    use std::sync::Mutex;
+    use windows::Win32::UI::Input::XboxController::{XInputGetState, XINPUT_STATE, XUSER_MAX_COUNT};
+
+    static PADS: Mutex<[Option<PadState>; XUSER_MAX_COUNT as usize]> = Mutex::new([None; XUSER_MAX_COUNT as usize]);
+
+    let mut pads = match PADS.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    let mut used = false;
+    for (index, last) in pads.iter_mut().enumerate() {
+        let mut state = XINPUT_STATE::default();
+        // ERROR_SUCCESS; anything else means no controller in this slot
+        if unsafe { XInputGetState(index as u32, &mut state) } != 0 {
+            *last = None;
+            continue;
+        }
+        let pad = &state.Gamepad;
+        let current = PadState {
+            buttons: pad.wButtons.0,
+            thumbs: [pad.sThumbLX, pad.sThumbLY, pad.sThumbRX, pad.sThumbRY],
+            triggers: [pad.bLeftTrigger, pad.bRightTrigger],
+        };
+        match last {
+            // Keep the reference point while the sticks drift so slow noise
+            // never adds up to a deadzone
+            Some(previous) if !previous.used_since(&current) => {}
+            Some(_) => {
+                used = true;
+                *last = Some(current);
+            }
+            None => *last = Some(current),
+        }
+    }
+    if used {
+        log::trace!("Game controller input detected");
+        record_activity();
+    }
+}
+
+/// Seconds since the last tablet pointer/proximity event
+#[cfg(target_os = "macos")]
+fn tablet_idle_seconds() -> Option<u64> {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(source_state: i32, event_type: u32) -> f64;
+    }
+    /// kCGEventSourceStateHIDSystemState
+    const HID_SYSTEM_STATE: i32 = 1;
+    /// kCGEventTabletPointer, kCGEventTabletProximity
+    const TABLET_EVENTS: [u32; 2] = [23, 24];
+
+    TABLET_EVENTS
+        .iter()
+        .map(|event_type| unsafe { CGEventSourceSecondsSinceLastEventType(HID_SYSTEM_STATE, *event_type) })
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(|seconds| seconds as u64)
+        .min()
+}
+
+/// Adjust the OS idle counter for input it doesn't see
+pub fn adjust_idle_time(os_idle_seconds: u64) -> u64 {
+    #[cfg(target_os = "windows")]
+    poll_gamepads();
+
+    let now_ms = crate::utils::clock::now().timestamp_millis();
+    #[cfg(target_os = "macos")]
+    let extra = [seconds_since_extra_input(now_ms), tablet_idle_seconds()];
+    #[cfg(not(target_os = "macos"))]
+    let extra = [seconds_since_extra_input(now_ms)];
+
+    combine(os_idle_seconds, &extra)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_input_shortens_idle_time() {
+        assert_eq!(combine(300, &[None]), 300);
+        assert_eq!(combine(300, &[Some(12), None]), 12);
+        assert_eq!(combine(5, &[Some(12)]), 5);
+
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        record_activity();
+        assert!(seconds_since_extra_input(now_ms + 3_000).is_some_and(|seconds| seconds >= 2));
+    }
+
+    #[test]
+    fn test_gamepad_noise_is_not_input() {
+        let rest = PadState { thumbs: [1200, -900, 300, 0], ..Default::default() };
+        // Stick drift and a resting finger on a trigger stay inside the deadzones
+        assert!(!rest.used_since(&PadState { thumbs: [1500, -600, 900, 200], triggers: [10, 0], ..rest }));
+        assert!(rest.used_since(&PadState { thumbs: [12000, -900, 300, 0], ..rest }));
+        assert!(rest.used_since(&PadState { triggers: [0, 200], ..rest }));
+        assert!(rest.used_since(&PadState { buttons: 0x1000, ..rest }));
+    }
+}
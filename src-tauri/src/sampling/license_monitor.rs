@@ -1,10 +1,8 @@
 // License monitoring service
 // Periodically checks license status and handles expiration during active sessions
 
-use crate::storage::AppState;
+use crate::storage::StateHandle;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tokio::sync::Mutex;
 use tokio::time::{interval, Duration};
 use log::{info, warn, error};
 
@@ -80,12 +78,12 @@ pub async fn stop_license_monitor() {
 /// Returns Ok(true) if license is valid, Ok(false) if invalid
 async fn check_license_and_handle_expiration() -> Result<bool, String> {
     // Get app state to access server URL and device token
-    let app_state = crate::storage::get_global_app_state()
+    let app_state = crate::storage::get_state_service()
         .map_err(|e| format!("Failed to get app state: {}", e))?;
     
     let (server_url, device_token) = {
-        let state = app_state.lock().await;
-        (state.server_url.clone(), state.device_token.clone())
+        let snapshot = app_state.snapshot();
+        (snapshot.server_url, snapshot.device_token)
     };
 
     if server_url.is_none() || device_token.is_none() {
@@ -133,11 +131,8 @@ async fn check_license_and_handle_expiration() -> Result<bool, String> {
             .map(|s| s.to_string());
 
         // Update state with license info and timestamp
-        {
-            let mut state = app_state.lock().await;
-            state.license_valid = Some(valid);
-            state.license_status = status.clone();
-            state.last_license_check = Some(chrono::Utc::now().timestamp());
+        if let Err(e) = app_state.set_license(valid, status.clone()).await {
+            warn!("Failed to update license state: {}", e);
         }
 
         if !valid {
@@ -159,11 +154,8 @@ async fn check_license_and_handle_expiration() -> Result<bool, String> {
             .map(|s| s.to_string());
 
         // Update state with invalid license info
-        {
-            let mut state = app_state.lock().await;
-            state.license_valid = Some(false);
-            state.license_status = status.clone();
-            state.last_license_check = Some(chrono::Utc::now().timestamp());
+        if let Err(e) = app_state.set_license(false, status.clone()).await {
+            warn!("Failed to update license state: {}", e);
         }
 
         error!("License expired or invalid: {:?}", status);
@@ -183,7 +175,7 @@ async fn check_license_and_handle_expiration() -> Result<bool, String> {
 /// Handle license expiration by auto-clocking out if user is clocked in
 /// Handle license expiration - called when license becomes invalid
 /// Can be called from SSE stream or periodic checks
-pub async fn handle_license_expiration(_state: Arc<Mutex<AppState>>) {
+pub async fn handle_license_expiration(_state: StateHandle) {
     info!("Handling license expiration...");
 
     // Check if user is currently clocked in using the sampling module function
@@ -194,52 +186,8 @@ pub async fn handle_license_expiration(_state: Arc<Mutex<AppState>>) {
 
     warn!("License expired while user is clocked in - performing auto-clockout");
 
-    // End local app usage session
-    if let Err(e) = crate::storage::app_usage::end_current_session().await {
-        error!("Failed to end current app session during license expiration: {}", e);
-    }
-
-    // Stop background services
-    crate::sampling::stop_services().await;
-    crate::sampling::reset_idle_state();
-
-    // Send clock_out event to backend
-    match crate::api::client::ApiClient::new().await {
-        Ok(client) => {
-            let event_data = serde_json::json!({
-                "events": [{
-                    "type": "clock_out",
-                    "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-                    "data": {
-                        "source": "license_expiration",
-                        "reason": "license_expired"
-                    }
-                }]
-            });
-            
-            match client.post_with_auth("/api/ingest/events", &event_data).await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        info!("Successfully sent clock_out event due to license expiration");
-                    } else {
-                        error!("Backend returned error status during license expiration clockout: {}", response.status());
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to send clock_out event during license expiration: {}", e);
-                    // Queue the event for later sync if network is unavailable
-                    let _ = crate::storage::offline_queue::queue_event("clock_out", &event_data).await;
-                }
-            }
-        }
-        Err(e) => {
-            error!("Failed to create API client during license expiration: {}", e);
-        }
-    }
-
-    // End local work session
-    if let Err(e) = crate::storage::work_session::end_session().await {
-        error!("Failed to end local session during license expiration: {}", e);
+    if let Err(e) = crate::session_manager::end_session(crate::session_manager::EndReason::LicenseExpired).await {
+        error!("Auto-clockout during license expiration incomplete: {}", e);
     }
 
     info!("Auto-clockout due to license expiration completed");
@@ -3,16 +3,22 @@
 //! Connects to /api/agent/license-stream to receive real-time license updates
 //! without polling. When the admin activates a license, the agent instantly receives
 //! the update and refreshes the license state.
+//!
+//! The stream also subscribes to the settings channel: `settings_updated`
+//! events make the agent re-fetch its employee settings, so policy changes
+//! reach running agents within seconds.
 
 use crate::sampling::license_monitor;
-use crate::storage::AppState;
+use crate::storage::StateHandle;
 use anyhow::{Context, Result};
 use serde::Deserialize;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use tokio::sync::Mutex;
 use tokio::time::sleep;
 
+/// Set when the backend deactivated this device; the stream stops reconnecting
+static DEVICE_DEACTIVATED: AtomicBool = AtomicBool::new(false);
+
 /// License update event from SSE stream
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
@@ -38,7 +44,8 @@ struct LicenseEvent {
 ///
 /// Auto-reconnects with exponential backoff (1s → 2s → 4s → ... → 60s max)
 /// Stops retrying if authentication fails (401) - requires re-login
-pub async fn start_license_stream(state: Arc<Mutex<AppState>>) {
+pub async fn start_license_stream(state: StateHandle) {
+    DEVICE_DEACTIVATED.store(false, Ordering::SeqCst);
     tokio::spawn(async move {
         let mut backoff_seconds = 1u64;
         const MAX_BACKOFF: u64 = 60;
@@ -57,6 +64,11 @@ pub async fn start_license_stream(state: Arc<Mutex<AppState>>) {
                 Err(e) => {
                     let error_msg = e.to_string();
                     
+                    // The backend refuses the token because the device was deactivated
+                    if error_msg.contains("403") && error_msg.to_lowercase().contains("deactivated") {
+                        handle_device_deactivation(Some(error_msg), state.clone()).await;
+                    }
+                    
                     // Check if this is an authentication error (401)
                     if error_msg.contains("401") || error_msg.contains("authentication") {
                         consecutive_auth_failures += 1;
@@ -80,6 +92,11 @@ pub async fn start_license_stream(state: Arc<Mutex<AppState>>) {
                 }
             }
 
+            if DEVICE_DEACTIVATED.load(Ordering::SeqCst) {
+                log::info!("Device deactivated, not reconnecting license stream");
+                break;
+            }
+
             // Exponential backoff before reconnecting
             log::info!("Reconnecting license stream in {} seconds...", backoff_seconds);
             sleep(Duration::from_secs(backoff_seconds)).await;
@@ -91,33 +108,29 @@ pub async fn start_license_stream(state: Arc<Mutex<AppState>>) {
 }
 
 /// Connect to the SSE stream and listen for events
-async fn connect_and_listen(state: Arc<Mutex<AppState>>) -> Result<()> {
+async fn connect_and_listen(state: StateHandle) -> Result<()> {
     // Get server URL and device token from state
     let (server_url, device_token) = {
-        let state_lock = state.lock().await;
-        let server = state_lock
+        let snapshot = state.snapshot();
+        let server = snapshot
             .server_url
-            .clone()
             .context("Server URL not configured")?;
-        let token = state_lock
+        let token = snapshot
             .device_token
-            .clone()
             .context("Device token not available")?;
         (server, token)
     };
 
-    let url = format!("{}/api/desktop/license-stream", server_url);
+    // Servers without channel support ignore the parameter and only send license events
+    let url = format!("{}/api/desktop/license-stream?channels=license,settings", server_url);
     log::info!("Connecting to license stream: {}", url);
 
-    // Create HTTP client with auth header
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(300)) // 5-minute timeout for long connections
-        .build()
-        .context("Failed to build HTTP client")?;
+    let client = crate::api::client::shared_client();
 
     // Start the SSE connection
     let mut response = client
         .get(&url)
+        .timeout(Duration::from_secs(300)) // 5-minute timeout for long connections
         .header("Authorization", format!("Bearer {}", device_token))
         .header("Accept", "text/event-stream")
         .header("Cache-Control", "no-cache")
@@ -153,6 +166,11 @@ async fn connect_and_listen(state: Arc<Mutex<AppState>>) -> Result<()> {
 
                 // Clear buffer
                 data_buffer.clear();
+
+                // The device token is dead after a deactivation
+                if DEVICE_DEACTIVATED.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
             }
         }
     }
@@ -161,7 +179,7 @@ async fn connect_and_listen(state: Arc<Mutex<AppState>>) -> Result<()> {
 }
 
 /// Handle any license event from the stream
-async fn handle_license_event(data: &str, state: Arc<Mutex<AppState>>) -> Result<()> {
+async fn handle_license_event(data: &str, state: StateHandle) -> Result<()> {
     let event: LicenseEvent = serde_json::from_str(data)
         .context("Failed to parse license event data")?;
 
@@ -172,10 +190,7 @@ async fn handle_license_event(data: &str, state: Arc<Mutex<AppState>>) -> Result
             log::info!("License stream connected successfully");
             // Update state with initial license status from connection event
             if let Some(valid) = event.valid {
-                let mut state_lock = state.lock().await;
-                state_lock.license_valid = Some(valid);
-                state_lock.license_status = event.status.clone();
-                state_lock.last_license_check = Some(chrono::Utc::now().timestamp());
+                state.set_license(valid, event.status.clone()).await?;
                 log::info!(
                     "Initial license state: valid={}, status={:?}",
                     valid,
@@ -192,6 +207,13 @@ async fn handle_license_event(data: &str, state: Arc<Mutex<AppState>>) -> Result
         "license_expired" | "license_revoked" => {
             handle_license_revocation(event, state.clone()).await?;
         }
+        "device_deactivated" | "seat_released" => {
+            handle_device_deactivation(event.message, state.clone()).await;
+        }
+        "settings_updated" | "policy_updated" => {
+            log::info!("Employee settings changed on the backend");
+            crate::api::employee_settings::handle_settings_push();
+        }
         _ => {
             log::warn!("Unknown license event type: {}", event.event_type);
         }
@@ -201,24 +223,19 @@ async fn handle_license_event(data: &str, state: Arc<Mutex<AppState>>) -> Result
 }
 
 /// Handle positive license update events (activated, renewed, updated)
-async fn handle_license_update(event: LicenseEvent, state: Arc<Mutex<AppState>>) -> Result<()> {
+async fn handle_license_update(event: LicenseEvent, state: StateHandle) -> Result<()> {
     log::info!("License update received: {:?}", event);
 
     let valid = event.valid.unwrap_or(false);
 
     // Update AppState with new license info
-    {
-        let mut state_lock = state.lock().await;
-        state_lock.license_valid = Some(valid);
-        state_lock.license_status = event.status.clone();
-        state_lock.last_license_check = Some(chrono::Utc::now().timestamp());
-        log::info!(
-            "Updated license state: valid={}, status={:?}, message={:?}",
-            valid,
-            event.status,
-            event.message
-        );
-    }
+    state.set_license(valid, event.status.clone()).await?;
+    log::info!(
+        "Updated license state: valid={}, status={:?}, message={:?}",
+        valid,
+        event.status,
+        event.message
+    );
 
     // If license became valid, no action needed - UI will automatically update
     // If license became invalid, handle it
@@ -230,16 +247,11 @@ async fn handle_license_update(event: LicenseEvent, state: Arc<Mutex<AppState>>)
 }
 
 /// Handle license revocation/expiration events
-async fn handle_license_revocation(event: LicenseEvent, state: Arc<Mutex<AppState>>) -> Result<()> {
+async fn handle_license_revocation(event: LicenseEvent, state: StateHandle) -> Result<()> {
     log::warn!("License revocation received: {:?}", event);
 
     // Update AppState
-    {
-        let mut state_lock = state.lock().await;
-        state_lock.license_valid = Some(false);
-        state_lock.license_status = event.status.clone();
-        state_lock.last_license_check = Some(chrono::Utc::now().timestamp());
-    }
+    state.set_license(false, event.status.clone()).await?;
 
     // Handle the license becoming invalid
     handle_license_invalidation(state).await;
@@ -248,7 +260,7 @@ async fn handle_license_revocation(event: LicenseEvent, state: Arc<Mutex<AppStat
 }
 
 /// Handle cases where license becomes invalid (expired, revoked, etc)
-async fn handle_license_invalidation(state: Arc<Mutex<AppState>>) {
+async fn handle_license_invalidation(state: StateHandle) {
     // If user is clocked in, handle expiration (auto-clockout)
     if crate::sampling::is_clocked_in().await {
         log::warn!("License became invalid while clocked in - triggering auto-clockout");
@@ -257,3 +269,19 @@ async fn handle_license_invalidation(state: Arc<Mutex<AppState>>) {
         log::info!("License became invalid, but user is not clocked in - no action needed");
     }
 }
+
+/// The admin deactivated this device (or released its seat): clock out, stop
+/// services, clear credentials and show the deactivation screen instead of
+/// retrying with a token the backend no longer accepts
+async fn handle_device_deactivation(message: Option<String>, state: StateHandle) {
+    if DEVICE_DEACTIVATED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    log::warn!("Device deactivated by the backend: {:?}", message);
+
+    // Ends the local session and queues the clock_out event
+    license_monitor::handle_license_expiration(state.clone()).await;
+
+    crate::commands::clear_local_session(&state).await;
+    crate::events::device_deactivated(message.as_deref());
+}
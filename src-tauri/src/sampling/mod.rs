@@ -1,15 +1,27 @@
 // Sampling module - simplified for production testing
 
 pub mod app_focus;
+pub mod app_nap;
+pub mod backend_health;
+pub mod browser_bridge;
 pub mod browser_url;
 pub mod event_batcher;
 pub mod idle_detector;
+pub mod input_activity;
 pub mod heartbeat;
 pub mod power_state;
+pub mod presentation;
+pub mod process_info;
 pub mod queue_processor;
+pub mod screen_sharing;
 pub mod screenshot_service;
+pub mod sleep_guard;
+pub mod virtual_desktop;
 pub mod license_monitor;
 pub mod license_stream;
+pub mod offline_sync;
+pub mod overtime;
+pub mod service_state;
 
 #[allow(dead_code)]
 pub fn is_dev_mode() -> bool {
@@ -34,13 +46,37 @@ pub fn get_heartbeat_interval() -> u64 {
     }
 }
 
-use std::sync::atomic::{AtomicBool, Ordering};
+/// Upper bound of the adaptive heartbeat interval (idle or paused) when the
+/// policy doesn't set one
+pub fn get_max_heartbeat_interval() -> u64 {
+    if is_dev_mode() {
+        30 // 30 seconds for development
+    } else {
+        300 // 5 minutes for production
+    }
+}
+
+/// Interval between heartbeats while the user stays idle.
+/// Identical idle heartbeats in between are coalesced into one covered time range.
+pub fn get_idle_heartbeat_interval() -> u64 {
+    if is_dev_mode() {
+        15 // 15 seconds for development
+    } else {
+        60 // 1 minute for production
+    }
+}
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use tokio::sync::RwLock;
-use crate::storage::offline_queue;
+use crate::storage::{offline_queue, pause_state::{self, PersistedPause}};
 
 // Global state for background services
 static SERVICES_RUNNING: AtomicBool = AtomicBool::new(false);
 static SERVICES_PAUSED: AtomicBool = AtomicBool::new(false);
+// Active pause (mirrored in the pause_state table)
+static ACTIVE_PAUSE: std::sync::Mutex<Option<PersistedPause>> = std::sync::Mutex::new(None);
+// Bumped on every pause/resume so stale auto-resume timers do nothing
+static PAUSE_GENERATION: AtomicU64 = AtomicU64::new(0);
 
 // Helper function to check if user is authenticated
 #[allow(dead_code)]
@@ -89,6 +125,9 @@ pub struct BackgroundServiceState {
     pub last_app_check: Option<chrono::DateTime<chrono::Utc>>,
     pub last_heartbeat: Option<chrono::DateTime<chrono::Utc>>,
     pub last_idle_check: Option<chrono::DateTime<chrono::Utc>>,
+    /// Lifecycle state, when it was entered and recent transitions
+    #[serde(flatten)]
+    pub lifecycle: service_state::LifecycleTracker,
 }
 
 impl BackgroundServiceState {
@@ -104,8 +143,47 @@ impl BackgroundServiceState {
             last_app_check: None,
             last_heartbeat: None,
             last_idle_check: None,
+            lifecycle: service_state::LifecycleTracker::new(),
         }
     }
+
+    /// Session services; job polling runs outside the session and isn't tracked
+    fn running_flags(&self) -> [(&'static str, bool); 6] {
+        [
+            ("app_focus", self.app_focus_running),
+            ("heartbeat", self.heartbeat_running),
+            ("idle_detection", self.idle_detection_running),
+            ("queue_processor", self.queue_processor_running),
+            ("screenshot_service", self.screenshot_service_running),
+            ("event_batcher", self.event_batcher_running),
+        ]
+    }
+}
+
+/// Move the service lifecycle to `to`, notifying the frontend if it changed
+async fn set_lifecycle(to: service_state::ServiceLifecycle) {
+    let transition = {
+        let mut state = BACKGROUND_SERVICES.write().await;
+        state.lifecycle.transition(to, crate::utils::clock::now())
+    };
+    if let Some(transition) = transition {
+        log::info!("Background services: {:?} -> {:?}", transition.from, transition.to);
+        crate::events::service_state_changed(&transition);
+    }
+}
+
+/// Degrade the lifecycle while the backend is unreachable (see backend_health)
+pub async fn set_backend_reachable(reachable: bool) {
+    let transition = {
+        let mut state = BACKGROUND_SERVICES.write().await;
+        state
+            .lifecycle
+            .service_changed(backend_health::BACKEND_SERVICE, reachable, crate::utils::clock::now())
+    };
+    if let Some(transition) = transition {
+        log::info!("Background services: {:?} -> {:?}", transition.from, transition.to);
+        crate::events::service_state_changed(&transition);
+    }
 }
 
 #[allow(dead_code)]
@@ -121,23 +199,176 @@ pub async fn is_services_paused() -> bool {
 #[allow(dead_code)]
 pub async fn start_services() {
     SERVICES_RUNNING.store(true, Ordering::Relaxed);
-    SERVICES_PAUSED.store(false, Ordering::Relaxed);
+    // A persisted pause outlives service restarts
+    SERVICES_PAUSED.store(current_pause().is_some(), Ordering::Relaxed);
+}
+
+/// The active pause, if tracking is paused
+pub fn current_pause() -> Option<PersistedPause> {
+    match ACTIVE_PAUSE.lock() {
+        Ok(guard) => guard.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+fn set_active_pause(pause: Option<PersistedPause>) {
+    let mut guard = match ACTIVE_PAUSE.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    *guard = pause;
+}
+
+/// Resume automatically at `resume_at`, unless tracking was paused or
+/// resumed again in the meantime
+fn schedule_auto_resume(resume_at: chrono::DateTime<chrono::Utc>) {
+    let generation = PAUSE_GENERATION.load(Ordering::SeqCst);
+    tokio::spawn(async move {
+        let wait = (resume_at - crate::utils::clock::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+        if PAUSE_GENERATION.load(Ordering::SeqCst) == generation {
+            log::info!("Pause deadline reached, resuming tracking");
+            resume(true).await;
+        }
+    });
+}
+
+/// Mark tracking as paused in memory and in AppState
+async fn apply_pause(pause: PersistedPause) {
+    PAUSE_GENERATION.fetch_add(1, Ordering::SeqCst);
+    SERVICES_PAUSED.store(true, Ordering::Relaxed);
+    let reason = pause.reason;
+    let resume_at = pause.resume_at;
+    set_active_pause(Some(pause));
+    // Focus isn't sampled while paused, so the last state would stick
+    presentation::reset();
+    // A paused session isn't tracked, so the machine may sleep again
+    sleep_guard::release();
+
+    if let Ok(state_service) = crate::storage::get_state_service() {
+        if let Err(e) = state_service.set_paused(true).await {
+            log::warn!("Failed to update pause flag: {}", e);
+        }
+    }
+    if let Some(resume_at) = resume_at {
+        schedule_auto_resume(resume_at);
+    }
+    if SERVICES_RUNNING.load(Ordering::Relaxed) {
+        set_lifecycle(service_state::ServiceLifecycle::Paused { reason }).await;
+    }
+}
+
+/// Restore a pause persisted before the last shutdown (called at startup)
+pub async fn restore_pause_state() {
+    match pause_state::restore() {
+        Ok(Some(pause_state::Restored::Paused(pause))) => {
+            log::info!(
+                "Restoring tracking pause ({}) from {}{}",
+                pause.reason.as_str(),
+                pause.paused_at,
+                pause.resume_at.map(|at| format!(" until {}", at)).unwrap_or_default()
+            );
+            apply_pause(pause).await;
+        }
+        Ok(Some(pause_state::Restored::Expired { resume_at })) => {
+            // The pause ended while the app was closed
+            if let Err(e) = crate::storage::pause_segments::end_at(resume_at) {
+                log::warn!("Failed to close pause segment: {}", e);
+            }
+        }
+        Ok(None) => {
+            if let Err(e) = crate::storage::pause_segments::end() {
+                log::warn!("Failed to close pause segment: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to load persisted pause state: {}", e),
+    }
 }
 
 
 #[allow(dead_code)]
 pub async fn stop_services() {
     SERVICES_RUNNING.store(false, Ordering::Relaxed);
+    app_nap::end_session_activity();
+    sleep_guard::release();
+    presentation::reset();
+    set_lifecycle(service_state::ServiceLifecycle::Stopped).await;
 }
 
 #[allow(dead_code)]
-pub async fn pause_services() {
-    SERVICES_PAUSED.store(true, Ordering::Relaxed);
+pub async fn pause_services(reason: service_state::PauseReason) {
+    pause_services_until(reason, None).await;
+}
+
+/// Pause tracking, optionally resuming automatically at `resume_at`. The
+/// pause is persisted so it survives restarts.
+pub async fn pause_services_until(reason: service_state::PauseReason, resume_at: Option<chrono::DateTime<chrono::Utc>>) {
+    let pause = PersistedPause {
+        reason,
+        paused_at: crate::utils::clock::now(),
+        resume_at,
+    };
+    if let Err(e) = pause_state::save(&pause) {
+        log::warn!("Failed to persist pause state: {}", e);
+    }
+    if let Err(e) = crate::storage::pause_segments::start(reason.as_str()) {
+        log::warn!("Failed to record pause segment: {}", e);
+    }
+    apply_pause(pause).await;
 }
 
 #[allow(dead_code)]
 pub async fn resume_services() {
+    resume(false).await;
+}
+
+/// Drop the active pause in memory, in the pause_state table and its open
+/// segment. Returns the pause that was active.
+async fn drop_pause() -> Option<PersistedPause> {
+    PAUSE_GENERATION.fetch_add(1, Ordering::SeqCst);
     SERVICES_PAUSED.store(false, Ordering::Relaxed);
+    let previous = current_pause();
+    set_active_pause(None);
+    if let Err(e) = pause_state::clear() {
+        log::warn!("Failed to clear persisted pause state: {}", e);
+    }
+    if let Err(e) = crate::storage::pause_segments::end() {
+        log::warn!("Failed to close pause segment: {}", e);
+    }
+    if let Ok(state_service) = crate::storage::get_state_service() {
+        if let Err(e) = state_service.set_paused(false).await {
+            log::warn!("Failed to update pause flag: {}", e);
+        }
+    }
+    previous
+}
+
+/// Forget the pause when the session ends (clock-out, logout, license
+/// expiry), so the next clock-in doesn't start paused
+pub async fn clear_pause() {
+    if let Some(pause) = drop_pause().await {
+        log::info!("Tracking pause ({}) ended with the session", pause.reason.as_str());
+    }
+}
+
+/// Resume tracking; `automatic` when a pause deadline was reached
+async fn resume(automatic: bool) {
+    let previous = drop_pause().await;
+    let paused = matches!(
+        BACKGROUND_SERVICES.read().await.lifecycle.lifecycle,
+        service_state::ServiceLifecycle::Paused { .. }
+    );
+    if paused {
+        set_lifecycle(service_state::ServiceLifecycle::Running).await;
+    }
+    sleep_guard::refresh().await;
+
+    if let Some(pause) = previous {
+        crate::events::tracking_resumed(&pause, automatic);
+        if automatic && pause.reason == service_state::PauseReason::Snooze {
+            crate::events::notify("notification.snooze_ended.title", "notification.snooze_ended.body");
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -151,15 +382,45 @@ pub async fn update_service_state<F>(updater: F)
 where 
     F: FnOnce(&mut BackgroundServiceState),
 {
-    let mut state = BACKGROUND_SERVICES.write().await;
-    updater(&mut state);
+    let transitions = {
+        let mut state = BACKGROUND_SERVICES.write().await;
+        let before = state.running_flags();
+        updater(&mut state);
+        let after = state.running_flags();
+
+        // Services exiting (or coming back) while running degrade (or restore) the lifecycle
+        let now = crate::utils::clock::now();
+        let mut transitions = Vec::new();
+        for ((service, was_running), (_, running)) in before.into_iter().zip(after) {
+            if was_running != running {
+                transitions.extend(state.lifecycle.service_changed(service, running, now));
+            }
+        }
+        transitions
+    };
+    for transition in transitions {
+        log::warn!("Background services: {:?} -> {:?}", transition.from, transition.to);
+        crate::events::service_state_changed(&transition);
+    }
 }
 
 #[allow(dead_code)]
 pub async fn start_all_background_services(app_handle: tauri::AppHandle) {
-    
+    // Services started while paused stay paused (for the same reason)
+    let was_paused = matches!(
+        BACKGROUND_SERVICES.read().await.lifecycle.lifecycle,
+        service_state::ServiceLifecycle::Paused { .. }
+    );
+    if !was_paused {
+        set_lifecycle(service_state::ServiceLifecycle::Starting).await;
+    }
+    let restored_pause = current_pause();
+
     // Start services
     start_services().await;
+    // Hidden-window timers must keep their cadence during the session
+    app_nap::begin_session_activity();
+    sleep_guard::refresh().await;
     
     // Guard: Check which services are already running
     let (
@@ -167,7 +428,6 @@ pub async fn start_all_background_services(app_handle: tauri::AppHandle) {
         heartbeat_running,
         idle_detection_running,
         queue_processor_running,
-        job_polling_running,
     ) = {
         let state = BACKGROUND_SERVICES.read().await;
         (
@@ -175,7 +435,6 @@ pub async fn start_all_background_services(app_handle: tauri::AppHandle) {
             state.heartbeat_running,
             state.idle_detection_running,
             state.queue_processor_running,
-            state.job_polling_running,
         )
     };
     
@@ -185,7 +444,7 @@ pub async fn start_all_background_services(app_handle: tauri::AppHandle) {
         tokio::spawn(async move {
             update_service_state(|state| {
                 state.app_focus_running = true;
-                state.last_app_check = Some(chrono::Utc::now());
+                state.last_app_check = Some(crate::utils::clock::now());
             }).await;
             
             app_focus::start_sampling(app_handle1).await;
@@ -204,7 +463,7 @@ pub async fn start_all_background_services(app_handle: tauri::AppHandle) {
         tokio::spawn(async move {
             update_service_state(|state| {
                 state.heartbeat_running = true;
-                state.last_heartbeat = Some(chrono::Utc::now());
+                state.last_heartbeat = Some(crate::utils::clock::now());
             }).await;
             
             heartbeat::start_heartbeat_service(app_handle2).await;
@@ -223,7 +482,7 @@ pub async fn start_all_background_services(app_handle: tauri::AppHandle) {
         tokio::spawn(async move {
             update_service_state(|state| {
                 state.idle_detection_running = true;
-                state.last_idle_check = Some(chrono::Utc::now());
+                state.last_idle_check = Some(crate::utils::clock::now());
             }).await;
             
             start_idle_detection_service(app_handle3).await;
@@ -236,23 +495,7 @@ pub async fn start_all_background_services(app_handle: tauri::AppHandle) {
         log::debug!("Idle detection service already running, skipping spawn");
     }
     
-    // Start job polling (only if not already running)
-    if !job_polling_running {
-        let app_handle4 = app_handle.clone();
-        tokio::spawn(async move {
-            update_service_state(|state| {
-                state.job_polling_running = true;
-            }).await;
-            
-            crate::api::job_polling::start_job_polling(app_handle4).await;
-            
-            update_service_state(|state| {
-                state.job_polling_running = false;
-            }).await;
-        });
-    } else {
-        log::debug!("Job polling already running, skipping spawn");
-    }
+    start_job_polling_service(app_handle.clone()).await;
     
     // Start offline queue processor (only if not already running)
     if !queue_processor_running {
@@ -323,6 +566,14 @@ pub async fn start_all_background_services(app_handle: tauri::AppHandle) {
     } else {
         log::debug!("Event batcher already running, skipping spawn");
     }
+
+    if !was_paused {
+        let to = match restored_pause {
+            Some(pause) => service_state::ServiceLifecycle::Paused { reason: pause.reason },
+            None => service_state::ServiceLifecycle::Running,
+        };
+        set_lifecycle(to).await;
+    }
 }
 
 // Global idle state tracking
@@ -343,7 +594,7 @@ async fn start_idle_detection_service(_app_handle: tauri::AppHandle) {
     let interval_seconds = 3; // Check idle status every 3 seconds for better responsiveness
 
     let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(interval_seconds));
-    let mut last_check_time = chrono::Utc::now();
+    let mut last_check_time = crate::utils::clock::now();
     
     loop {
         // Check if services should continue running (authenticated AND clocked in)
@@ -362,7 +613,7 @@ async fn start_idle_detection_service(_app_handle: tauri::AppHandle) {
         }
 
         // Detect potential sleep/wake events by checking for large time gaps
-        let now = chrono::Utc::now();
+        let now = crate::utils::clock::now();
         let time_since_last_check = (now - last_check_time).num_seconds() as u64;
         
         // If more than 2x the interval has passed, we likely woke from sleep
@@ -382,7 +633,7 @@ async fn start_idle_detection_service(_app_handle: tauri::AppHandle) {
         // Run idle detection (only when authenticated and clocked in)
         // Update service state
         update_service_state(|state| {
-            state.last_idle_check = Some(chrono::Utc::now());
+            state.last_idle_check = Some(crate::utils::clock::now());
         }).await;
         
         // Check idle status and send events if needed
@@ -409,6 +660,15 @@ async fn start_idle_detection_service(_app_handle: tauri::AppHandle) {
                 log::error!("Failed to update app session idle status: {}", e);
             }
             
+            if state_changed {
+                crate::events::idle_changed(is_idle, idle_time, threshold);
+                crate::storage::idle_periods::record_idle_change(is_idle, idle_time).await;
+                if !is_idle {
+                    // The heartbeat may be backed off for minutes while idle
+                    heartbeat::trigger_immediate_heartbeat().await;
+                }
+            }
+            
             // Send idle events only when status changes AND user is clocked in
             if state_changed && should_services_run().await {
                 let event_type = if is_idle { "idle_start" } else { "idle_end" };
@@ -416,7 +676,7 @@ async fn start_idle_detection_service(_app_handle: tauri::AppHandle) {
                     "idle_time_seconds": idle_time,
                     "threshold_seconds": threshold,
                     "is_idle": is_idle,
-                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "timestamp": crate::utils::time::event_timestamp(),
                     "reason": "user_activity"
                 });
                 log::debug!("Sending idle event: {} (idle_time: {}s)", event_type, idle_time);
@@ -433,6 +693,25 @@ async fn start_idle_detection_service(_app_handle: tauri::AppHandle) {
 
 }
 
+/// Start job polling (only if not already running). Started at launch and
+/// kept running across clock-in/out, see `api::job_polling`.
+pub async fn start_job_polling_service(app_handle: tauri::AppHandle) {
+    if BACKGROUND_SERVICES.read().await.job_polling_running {
+        log::debug!("Job polling already running, skipping spawn");
+        return;
+    }
+    update_service_state(|state| {
+        state.job_polling_running = true;
+    }).await;
+    tokio::spawn(async move {
+        crate::api::job_polling::start_job_polling(app_handle).await;
+        
+        update_service_state(|state| {
+            state.job_polling_running = false;
+        }).await;
+    });
+}
+
 #[allow(dead_code)]
 pub fn get_job_polling_interval() -> u64 {
     if is_dev_mode() {
@@ -482,7 +761,7 @@ pub async fn start_queue_processing_service() {
         if let Ok(events) = offline_queue::get_pending_events().await {
             for event in events {
                 log::debug!("Sending event: 1");
-                if let Err(e) = send_event_to_backend(&event.event_type, &event.event_data).await {
+                if let Err(e) = send_queued_event(&event).await {
                     log::error!("Failed to send event: {}", e);
                     if let Err(e) = offline_queue::mark_event_failed(event.id).await {
                         log::error!("Failed to mark event as failed: {}", e);
@@ -519,49 +798,68 @@ pub async fn start_sync_service() {
 
         // Check if we're online and have pending data to sync
         if is_online().await {
+            if let Err(e) = crate::storage::offline_periods::record_online().await {
+                log::warn!("Failed to record/report offline period: {}", e);
+            }
+
+            let heartbeats = offline_queue::get_pending_heartbeats().await.unwrap_or_default();
+            let events = offline_queue::get_pending_events().await.unwrap_or_default();
+            let total = heartbeats.len() + events.len();
+            let (mut synced, mut failed) = (0, 0);
+            if total > 0 {
+                crate::events::sync_progress(total, 0, 0, offline_sync::SyncPhase::Heartbeats);
+            }
             
             // Sync pending heartbeats
-            if let Ok(heartbeats) = offline_queue::get_pending_heartbeats().await {
-                if !heartbeats.is_empty() {
-                    for heartbeat in heartbeats {
-                        if let Err(e) = send_heartbeat_to_backend(&heartbeat.heartbeat_data).await {
-                            log::error!("Failed to sync heartbeat {}: {}", heartbeat.id, e);
-                            if let Err(e) = offline_queue::mark_heartbeat_failed(heartbeat.id).await {
-                                log::error!("Failed to mark heartbeat as failed: {}", e);
-                            }
-                        } else {
-                            if let Err(e) = offline_queue::mark_heartbeat_processed(heartbeat.id).await {
-                                log::error!("Failed to mark heartbeat as processed: {}", e);
-                            }
-                        }
+            for heartbeat in heartbeats {
+                if let Err(e) = send_heartbeat_to_backend(&heartbeat.heartbeat_data).await {
+                    log::error!("Failed to sync heartbeat {}: {}", heartbeat.id, e);
+                    failed += 1;
+                    if let Err(e) = offline_queue::mark_heartbeat_failed(heartbeat.id).await {
+                        log::error!("Failed to mark heartbeat as failed: {}", e);
+                    }
+                } else {
+                    synced += 1;
+                    if let Err(e) = offline_queue::mark_heartbeat_processed(heartbeat.id).await {
+                        log::error!("Failed to mark heartbeat as processed: {}", e);
                     }
                 }
+                crate::events::sync_progress(total, synced, failed, offline_sync::SyncPhase::Heartbeats);
             }
 
             // Sync pending events
-            if let Ok(events) = offline_queue::get_pending_events().await {
-                if !events.is_empty() {
-                    for event in events {
-                        log::debug!("Sending event: {:?}", event);
-                        if let Err(e) = send_event_to_backend(&event.event_type, &event.event_data).await {
-                            log::error!("Failed to sync event {}: {}", event.id, e);
-                            if let Err(e) = offline_queue::mark_event_failed(event.id).await {
-                                log::error!("Failed to mark event as failed: {}", e);
-                            }
-                        } else {
-                            if let Err(e) = offline_queue::mark_event_processed(event.id).await {
-                                log::error!("Failed to mark event as processed: {}", e);
-                            }
-                        }
+            for event in events {
+                log::debug!("Sending event: {:?}", event);
+                if let Err(e) = send_queued_event(&event).await {
+                    log::error!("Failed to sync event {}: {}", event.id, e);
+                    failed += 1;
+                    if let Err(e) = offline_queue::mark_event_failed(event.id).await {
+                        log::error!("Failed to mark event as failed: {}", e);
+                    }
+                } else {
+                    synced += 1;
+                    if let Err(e) = offline_queue::mark_event_processed(event.id).await {
+                        log::error!("Failed to mark event as processed: {}", e);
                     }
                 }
+                crate::events::sync_progress(total, synced, failed, offline_sync::SyncPhase::Events);
+            }
+            
+            if total > 0 {
+                crate::events::sync_progress(total, synced, failed, offline_sync::SyncPhase::Done);
+            }
+
+            // Sync command audit log
+            if let Err(e) = crate::storage::audit_log::sync_pending_entries().await {
+                log::error!("Failed to sync audit log: {}", e);
             }
 
             // Skip syncing app_usage sessions - app_focus events already handle this
             // if let Err(e) = sync_local_app_usage_sessions().await {
             //     log::error!("Failed to sync local app usage sessions: {}", e);
             // }
-        } else {
+        } else if let Err(e) = crate::storage::offline_periods::record_offline().await {
+            log::warn!("Failed to record offline period: {}", e);
         }
 
         interval.tick().await;
@@ -574,13 +872,7 @@ async fn is_online() -> bool {
     if let Ok(server_url) = crate::storage::get_server_url().await {
         if let Ok(device_token) = crate::storage::get_device_token().await {
             if !server_url.is_empty() && !device_token.is_empty() {
-                let client = match reqwest::Client::builder()
-                    .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-                    .build()
-                {
-                    Ok(c) => c,
-                    Err(_) => return false,
-                };
+                let client = crate::api::client::shared_client();
                 let test_url = format!("{}/api/auth/simple-session", server_url.trim_end_matches('/'));
                 
                 match client
@@ -612,20 +904,12 @@ pub async fn send_heartbeat_to_backend(heartbeat_data: &serde_json::Value) -> an
         return Err(anyhow::anyhow!("Server URL or device token is empty"));
     }
     
-    let client = reqwest::Client::builder()
-        .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-        .build()?;
-    let heartbeat_url = format!("{}/api/ingest/heartbeat", server_url.trim_end_matches('/'));
+    crate::api::rate_limiter::acquire(crate::api::rate_limiter::IngestKind::Heartbeat).await;
     
-    log::trace!("Sending heartbeat to {}: {}", heartbeat_url, serde_json::to_string_pretty(heartbeat_data).unwrap_or_default());
+    log::trace!("Sending heartbeat: {}", serde_json::to_string_pretty(heartbeat_data).unwrap_or_default());
     
-    let response = client
-        .post(&heartbeat_url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", device_token))
-        .json(heartbeat_data)
-        .send()
-        .await?;
+    let client = crate::api::client::ApiClient::new().await?;
+    let response = client.post_with_auth("/api/ingest/heartbeat", heartbeat_data).await?;
     
     let status = response.status();
     
@@ -634,13 +918,10 @@ pub async fn send_heartbeat_to_backend(heartbeat_data: &serde_json::Value) -> an
         log::warn!("Heartbeat failed: License expired or invalid (402)");
         
         // Get app state and trigger license expiration handler
-        if let Ok(app_state) = crate::storage::get_global_app_state() {
+        if let Ok(app_state) = crate::storage::get_state_service() {
             // Update license state
-            {
-                let mut state = app_state.lock().await;
-                state.license_valid = Some(false);
-                state.license_status = Some("EXPIRED".to_string());
-                state.last_license_check = Some(chrono::Utc::now().timestamp());
+            if let Err(e) = app_state.set_license(false, Some("EXPIRED".to_string())).await {
+                log::warn!("Failed to update license state: {}", e);
             }
             
             // Check if user is clocked in before triggering auto-clockout
@@ -667,6 +948,25 @@ pub async fn send_heartbeat_to_backend(heartbeat_data: &serde_json::Value) -> an
 }
 
 pub async fn send_event_to_backend(event_type: &str, event_data: &serde_json::Value) -> anyhow::Result<()> {
+    send_event_at(event_type, event_data, crate::utils::clock::now()).await
+}
+
+/// Replay a queued event with the time it was queued at, not the time it's sent
+pub async fn send_queued_event(event: &crate::storage::offline_queue::QueuedEvent) -> anyhow::Result<()> {
+    // Rows queued by older versions hold a whole `{"events": [...]}` envelope
+    if let Some([inner]) = event.event_data["events"].as_array().map(Vec::as_slice) {
+        if inner["type"] == event.event_type.as_str() {
+            let occurred_at = inner["timestamp"]
+                .as_str()
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                .map_or(event.timestamp, |at| at.with_timezone(&chrono::Utc));
+            return send_event_at(&event.event_type, &inner["data"], occurred_at).await;
+        }
+    }
+    send_event_at(&event.event_type, &event.event_data, event.timestamp).await
+}
+
+async fn send_event_at(event_type: &str, event_data: &serde_json::Value, occurred_at: chrono::DateTime<chrono::Utc>) -> anyhow::Result<()> {
     // Get server URL and device token from storage
     let server_url = crate::storage::get_server_url().await?;
     let device_token = crate::storage::get_device_token().await?;
@@ -675,27 +975,19 @@ pub async fn send_event_to_backend(event_type: &str, event_data: &serde_json::Va
         return Ok(());
     }
     
-    let client = reqwest::Client::builder()
-        .user_agent(format!("TrackEx-Agent/{}", env!("CARGO_PKG_VERSION")))
-        .build()?;
-    let events_url = format!("{}/api/ingest/events", server_url.trim_end_matches('/'));
+    crate::api::rate_limiter::acquire(crate::api::rate_limiter::IngestKind::Event).await;
     
     let event_payload = serde_json::json!({
         "events": [{
             "type": event_type,
-            "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            "timestamp": crate::utils::time::format_timestamp(occurred_at),
             "data": event_data,
             "from": "send_event_to_backend"
         }]
     });
     
-    let response = client
-        .post(&events_url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", device_token))
-        .json(&event_payload)
-        .send()
-        .await?;
+    let client = crate::api::client::ApiClient::new().await?;
+    let response = client.post_with_auth("/api/ingest/events", &event_payload).await?;
     
     if response.status().is_success() {
         Ok(())
@@ -0,0 +1,294 @@
+//! Manual sync of the offline queue (`trigger_sync`)
+//!
+//! Sends what was queued while offline and reports how it went. Progress
+//! is emitted as `sync-progress` events after every item so the UI can show
+//! a real progress bar, and the returned report has per-type counts, the
+//! failures with their reasons and how long the pass took.
+//!
+//! Users on a poor connection can limit a sync to one data type and pass
+//! hints to send clock events before anything else and leave the bulky
+//! screenshot uploads to the background retry queue.
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::storage::offline_queue::QueuedEvent;
+
+/// Failures beyond this are counted but not listed in the report
+const MAX_REPORTED_FAILURES: usize = 50;
+
+/// Most queued screenshots uploaded by one manual sync
+const MAX_SCREENSHOTS_PER_SYNC: i32 = 200;
+
+/// Event types that record clocking in and out
+const CLOCK_EVENT_TYPES: &[&str] = &["clock_in", "clock_out"];
+
+/// What the sync is currently sending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    Heartbeats,
+    Events,
+    Screenshots,
+    Done,
+}
+
+/// Which queued data a sync sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncScope {
+    #[default]
+    All,
+    Events,
+    Heartbeats,
+    Screenshots,
+}
+
+impl SyncScope {
+    fn includes(self, phase: SyncPhase) -> bool {
+        match self {
+            SyncScope::All => true,
+            SyncScope::Events => phase == SyncPhase::Events,
+            SyncScope::Heartbeats => phase == SyncPhase::Heartbeats,
+            SyncScope::Screenshots => phase == SyncPhase::Screenshots,
+        }
+    }
+}
+
+/// Ordering hints for a sync
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPriority {
+    /// Send clock in/out events before heartbeats and other events
+    #[serde(default)]
+    pub clock_events_first: bool,
+    /// Skip screenshot uploads unless the scope is `screenshots`
+    #[serde(default)]
+    pub defer_screenshots: bool,
+}
+
+impl SyncPriority {
+    /// Phases to run for `scope`, in order
+    fn phases(&self, scope: SyncScope) -> Vec<SyncPhase> {
+        let mut phases = if self.clock_events_first {
+            vec![SyncPhase::Events, SyncPhase::Heartbeats]
+        } else {
+            vec![SyncPhase::Heartbeats, SyncPhase::Events]
+        };
+        if !(self.defer_screenshots && scope == SyncScope::All) {
+            phases.push(SyncPhase::Screenshots);
+        }
+        phases.retain(|phase| scope.includes(*phase));
+        phases
+    }
+}
+
+/// Clock events first, otherwise keep queue order
+fn order_events(events: &mut [QueuedEvent]) {
+    events.sort_by_key(|event| !CLOCK_EVENT_TYPES.contains(&event.event_type.as_str()));
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCounts {
+    pub queued: usize,
+    pub synced: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncFailure {
+    /// "heartbeat" or the event type
+    pub kind: String,
+    pub queue_id: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub scope: SyncScope,
+    pub heartbeats: SyncCounts,
+    pub events: SyncCounts,
+    pub screenshots: SyncCounts,
+    /// Phases left out by `defer_screenshots`
+    pub deferred: Vec<SyncPhase>,
+    pub failures: Vec<SyncFailure>,
+    pub duration_ms: u64,
+}
+
+impl SyncReport {
+    fn total(&self) -> usize {
+        self.heartbeats.queued + self.events.queued + self.screenshots.queued
+    }
+
+    fn synced(&self) -> usize {
+        self.heartbeats.synced + self.events.synced + self.screenshots.synced
+    }
+
+    fn failed(&self) -> usize {
+        self.heartbeats.failed + self.events.failed + self.screenshots.failed
+    }
+
+    fn record_failure(&mut self, kind: &str, queue_id: i64, error: &anyhow::Error) {
+        if self.failures.len() < MAX_REPORTED_FAILURES {
+            self.failures.push(SyncFailure {
+                kind: kind.to_string(),
+                queue_id,
+                reason: error.to_string(),
+            });
+        }
+    }
+
+    fn emit_progress(&self, phase: SyncPhase) {
+        crate::events::sync_progress(self.total(), self.synced(), self.failed(), phase);
+    }
+
+    /// One-line summary for logs and remote command acks
+    pub fn summary(&self) -> String {
+        format!(
+            "Sync completed: {} heartbeats, {} events, {} screenshots synced ({} failed)",
+            self.heartbeats.synced,
+            self.events.synced,
+            self.screenshots.synced,
+            self.failed()
+        )
+    }
+}
+
+/// Send the queued data in `scope`, in the order the hints ask for
+pub async fn run(scope: SyncScope, priority: SyncPriority) -> SyncReport {
+    let started = Instant::now();
+    let phases = priority.phases(scope);
+    let mut report = SyncReport { scope, ..Default::default() };
+    if scope == SyncScope::All && !phases.contains(&SyncPhase::Screenshots) {
+        report.deferred.push(SyncPhase::Screenshots);
+    }
+
+    // Read every queue up front so progress has a stable total
+    let heartbeats = if phases.contains(&SyncPhase::Heartbeats) {
+        crate::storage::offline_queue::get_pending_heartbeats().await.unwrap_or_else(|e| {
+            log::warn!("Failed to read queued heartbeats: {}", e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let mut events = if phases.contains(&SyncPhase::Events) {
+        crate::storage::offline_queue::get_pending_events().await.unwrap_or_else(|e| {
+            log::warn!("Failed to read queued events: {}", e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    if priority.clock_events_first {
+        order_events(&mut events);
+    }
+    let screenshots = if phases.contains(&SyncPhase::Screenshots) {
+        crate::storage::screenshot_queue::get_pending_uploads(MAX_SCREENSHOTS_PER_SYNC).await.unwrap_or_else(|e| {
+            log::warn!("Failed to read queued screenshots: {}", e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    report.heartbeats.queued = heartbeats.len();
+    report.events.queued = events.len();
+    report.screenshots.queued = screenshots.len();
+
+    for phase in phases {
+        report.emit_progress(phase);
+        match phase {
+            SyncPhase::Heartbeats => {
+                for heartbeat in &heartbeats {
+                    crate::api::bandwidth::consume(heartbeat.heartbeat_data.to_string().len()).await;
+                    let result = match super::send_heartbeat_to_backend(&heartbeat.heartbeat_data).await {
+                        Ok(()) => crate::storage::offline_queue::mark_heartbeat_processed(heartbeat.id).await,
+                        Err(e) => Err(e),
+                    };
+                    match result {
+                        Ok(()) => report.heartbeats.synced += 1,
+                        Err(e) => {
+                            report.heartbeats.failed += 1;
+                            report.record_failure("heartbeat", heartbeat.id, &e);
+                        }
+                    }
+                    report.emit_progress(phase);
+                }
+            }
+            SyncPhase::Events => {
+                for event in &events {
+                    crate::api::bandwidth::consume(event.event_data.to_string().len()).await;
+                    let result = match super::send_queued_event(event).await {
+                        Ok(()) => crate::storage::offline_queue::mark_event_processed(event.id).await,
+                        Err(e) => Err(e),
+                    };
+                    match result {
+                        Ok(()) => report.events.synced += 1,
+                        Err(e) => {
+                            report.events.failed += 1;
+                            report.record_failure(&event.event_type, event.id, &e);
+                        }
+                    }
+                    report.emit_progress(phase);
+                }
+            }
+            SyncPhase::Screenshots => {
+                for screenshot in &screenshots {
+                    // Uploads are throttled by the bandwidth cap inside the upload itself
+                    match super::screenshot_service::upload_queued(screenshot).await {
+                        Ok(()) => report.screenshots.synced += 1,
+                        Err(e) => {
+                            report.screenshots.failed += 1;
+                            report.record_failure("screenshot", screenshot.id, &e);
+                        }
+                    }
+                    report.emit_progress(phase);
+                }
+            }
+            SyncPhase::Done => {}
+        }
+    }
+
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report.emit_progress(SyncPhase::Done);
+    log::info!("{} in {}ms", report.summary(), report.duration_ms);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_totals_and_failure_cap() {
+        let mut report = SyncReport::default();
+        report.heartbeats = SyncCounts { queued: 3, synced: 2, failed: 1 };
+        report.events = SyncCounts { queued: 100, synced: 40, failed: 60 };
+        for id in 0..61 {
+            report.record_failure("app_focus", id, &anyhow::anyhow!("status 500"));
+        }
+
+        assert_eq!(report.total(), 103);
+        assert_eq!(report.synced(), 42);
+        assert_eq!(report.failures.len(), MAX_REPORTED_FAILURES);
+        assert_eq!(report.summary(), "Sync completed: 2 heartbeats, 40 events, 0 screenshots synced (61 failed)");
+    }
+
+    #[test]
+    fn test_scope_and_priority_order() {
+        let default = SyncPriority::default();
+        assert_eq!(
+            default.phases(SyncScope::All),
+            vec![SyncPhase::Heartbeats, SyncPhase::Events, SyncPhase::Screenshots]
+        );
+
+        let poor_connection = SyncPriority { clock_events_first: true, defer_screenshots: true };
+        assert_eq!(poor_connection.phases(SyncScope::All), vec![SyncPhase::Events, SyncPhase::Heartbeats]);
+        // An explicit screenshots scope isn't deferred
+        assert_eq!(poor_connection.phases(SyncScope::Screenshots), vec![SyncPhase::Screenshots]);
+        assert_eq!(default.phases(SyncScope::Heartbeats), vec![SyncPhase::Heartbeats]);
+    }
+}
@@ -0,0 +1,221 @@
+//! Daily and weekly working hours limits
+//!
+//! The policy can cap hours per day (`daily_hours_limit`) and per week,
+//! Monday to Sunday (`weekly_hours_limit`). Worked time comes from the local
+//! work sessions without paused time, so the check works offline. The
+//! employee is warned `overtime_warning_minutes` before a limit, once per
+//! day or week. Once a limit is exceeded an `overtime_limit` event is sent
+//! and, with `overtime_auto_clock_out`, the session is ended.
+//!
+//! Checked from the heartbeat loop, at most every `CHECK_INTERVAL_SECS`.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::api::employee_settings::PolicySettings;
+use crate::utils::clock;
+
+/// Warning lead time when the policy doesn't set one
+pub const DEFAULT_WARNING_MINUTES: u32 = 30;
+
+const CHECK_INTERVAL_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Limit {
+    Daily,
+    Weekly,
+}
+
+impl Limit {
+    fn as_str(self) -> &'static str {
+        match self {
+            Limit::Daily => "daily",
+            Limit::Weekly => "weekly",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Check {
+    Under,
+    Approaching { limit: Limit, remaining_secs: i64 },
+    Exceeded { limit: Limit, limit_secs: i64, worked_secs: i64 },
+}
+
+/// Compare worked seconds against the policy limits; an exceeded limit wins
+/// over an approaching one, and the daily limit over the weekly one
+fn evaluate(worked_today: i64, worked_week: i64, policy: &PolicySettings) -> Check {
+    let warning_secs = match policy.overtime_warning_minutes {
+        0 => DEFAULT_WARNING_MINUTES,
+        minutes => minutes,
+    } as i64
+        * 60;
+    let limits = [
+        (Limit::Daily, policy.daily_hours_limit, worked_today),
+        (Limit::Weekly, policy.weekly_hours_limit, worked_week),
+    ];
+    let limits = limits
+        .iter()
+        .filter(|(_, hours, _)| *hours > 0.0)
+        .map(|&(limit, hours, worked)| (limit, (hours * 3600.0).round() as i64, worked));
+
+    let mut check = Check::Under;
+    for (limit, limit_secs, worked_secs) in limits {
+        let remaining_secs = limit_secs - worked_secs;
+        if remaining_secs <= 0 {
+            return Check::Exceeded { limit, limit_secs, worked_secs };
+        }
+        if remaining_secs <= warning_secs && check == Check::Under {
+            check = Check::Approaching { limit, remaining_secs };
+        }
+    }
+    check
+}
+
+struct State {
+    last_check: Option<chrono::DateTime<chrono::Utc>>,
+    /// Warnings and limit events already handled, keyed by limit and period
+    handled: Vec<String>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State { last_check: None, handled: Vec::new() });
+
+fn lock() -> std::sync::MutexGuard<'static, State> {
+    match STATE.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    }
+}
+
+/// Key of the day or week a limit applies to
+fn period_key(kind: &str, limit: Limit, today: NaiveDate) -> String {
+    match limit {
+        Limit::Daily => format!("{}:{}:{}", kind, limit.as_str(), today),
+        Limit::Weekly => {
+            let week = today.iso_week();
+            format!("{}:{}:{}-W{}", kind, limit.as_str(), week.year(), week.week())
+        }
+    }
+}
+
+/// Returns true the first time `key` is seen
+fn first_time(key: String) -> bool {
+    let mut state = lock();
+    if state.handled.contains(&key) {
+        return false;
+    }
+    state.handled.push(key);
+    true
+}
+
+/// Check today's and this week's worked time against the policy limits
+pub async fn check() {
+    let now = clock::now();
+    {
+        let mut state = lock();
+        if state.last_check.is_some_and(|at| (now - at).num_seconds() < CHECK_INTERVAL_SECS) {
+            return;
+        }
+        state.last_check = Some(now);
+    }
+
+    let policy = crate::api::employee_settings::get_policy_settings().await;
+    if policy.daily_hours_limit <= 0.0 && policy.weekly_hours_limit <= 0.0 {
+        return;
+    }
+
+    let today = clock::today();
+    let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let (day_start, day_end) = crate::utils::time::day_bounds(today);
+    let week_start = crate::utils::time::local_midnight(week_start);
+    let worked = async {
+        let today = crate::storage::work_session::worked_seconds_between(day_start, day_end).await?;
+        let week = crate::storage::work_session::worked_seconds_between(week_start, day_end).await?;
+        Ok::<_, anyhow::Error>((today, week))
+    };
+    let (worked_today, worked_week) = match worked.await {
+        Ok(worked) => worked,
+        Err(e) => {
+            log::warn!("Overtime check failed to read worked time: {}", e);
+            return;
+        }
+    };
+
+    match evaluate(worked_today, worked_week, &policy) {
+        Check::Under => {}
+        Check::Approaching { limit, remaining_secs } => {
+            if first_time(period_key("warned", limit, today)) {
+                log::info!("Approaching {} hours limit ({} min left)", limit.as_str(), remaining_secs / 60);
+                crate::events::notify("notification.overtime_warning.title", "notification.overtime_warning.body");
+            }
+        }
+        Check::Exceeded { limit, limit_secs, worked_secs } => {
+            if !first_time(period_key("exceeded", limit, today)) && !policy.overtime_auto_clock_out {
+                return;
+            }
+            limit_exceeded(limit, limit_secs, worked_secs, policy.overtime_auto_clock_out).await;
+        }
+    }
+}
+
+async fn limit_exceeded(limit: Limit, limit_secs: i64, worked_secs: i64, auto_clock_out: bool) {
+    log::warn!(
+        "{} hours limit exceeded (worked {}s of {}s){}",
+        limit.as_str(),
+        worked_secs,
+        limit_secs,
+        if auto_clock_out { ", clocking out" } else { "" }
+    );
+    let event_data = serde_json::json!({
+        "limit": limit,
+        "limit_seconds": limit_secs,
+        "worked_seconds": worked_secs,
+        "auto_clock_out": auto_clock_out,
+    });
+    if let Err(e) = super::send_event_to_backend("overtime_limit", &event_data).await {
+        log::warn!("Failed to send overtime_limit event, queuing it: {}", e);
+        if let Err(e) = crate::storage::offline_queue::queue_event("overtime_limit", &event_data).await {
+            log::warn!("Failed to queue overtime_limit event: {}", e);
+        }
+    }
+
+    if auto_clock_out {
+        crate::events::notify("notification.overtime_exceeded.title", "notification.overtime_clocked_out.body");
+        if let Err(e) = crate::session_manager::end_session(crate::session_manager::EndReason::OvertimeLimit).await {
+            log::warn!("Overtime clock-out incomplete: {}", e);
+        }
+    } else {
+        crate::events::notify("notification.overtime_exceeded.title", "notification.overtime_exceeded.body");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_limits() {
+        let policy = PolicySettings {
+            daily_hours_limit: 8.0,
+            weekly_hours_limit: 40.0,
+            overtime_warning_minutes: 30,
+            ..PolicySettings::default()
+        };
+        let hour = 3600;
+
+        assert_eq!(evaluate(7 * hour, 20 * hour, &policy), Check::Under);
+        assert_eq!(
+            evaluate(7 * hour + 40 * 60, 20 * hour, &policy),
+            Check::Approaching { limit: Limit::Daily, remaining_secs: 20 * 60 }
+        );
+        // The week runs out before the day does
+        assert_eq!(
+            evaluate(2 * hour, 40 * hour + 1, &policy),
+            Check::Exceeded { limit: Limit::Weekly, limit_secs: 40 * hour, worked_secs: 40 * hour + 1 }
+        );
+        // No limits configured
+        assert_eq!(evaluate(20 * hour, 80 * hour, &PolicySettings::default()), Check::Under);
+    }
+}
@@ -1,6 +1,9 @@
 // Power state monitoring module for detecting sleep/wake events
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicU64, Ordering};
 use chrono::Utc;
+use crate::utils::clock;
 
 // Track the last activity timestamp
 static LAST_ACTIVITY_TIMESTAMP: AtomicU64 = AtomicU64::new(0);
@@ -13,14 +16,14 @@ static IS_SLEEPING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBoo
 
 /// Initialize power state monitoring
 pub fn init() {
-    let now = Utc::now().timestamp() as u64;
+    let now = clock::now().timestamp() as u64;
     LAST_ACTIVITY_TIMESTAMP.store(now, Ordering::Relaxed);
     log::info!("Power state monitoring initialized");
 }
 
 /// Update the last activity timestamp
 pub fn update_last_activity() {
-    let now = Utc::now().timestamp() as u64;
+    let now = clock::now().timestamp() as u64;
     LAST_ACTIVITY_TIMESTAMP.store(now, Ordering::Relaxed);
 }
 
@@ -33,7 +36,7 @@ pub fn get_last_activity_timestamp() -> u64 {
 /// Mark system as entering sleep
 #[allow(dead_code)]
 pub fn mark_sleep_start() {
-    let now = Utc::now().timestamp() as u64;
+    let now = clock::now().timestamp() as u64;
     SLEEP_START_TIME.store(now, Ordering::Relaxed);
     IS_SLEEPING.store(true, Ordering::Relaxed);
     log::info!("System entering sleep mode at {}", now);
@@ -42,7 +45,7 @@ pub fn mark_sleep_start() {
 /// Mark system as waking up and return sleep duration
 pub fn mark_wake_up() -> u64 {
     let sleep_start = SLEEP_START_TIME.load(Ordering::Relaxed);
-    let now = Utc::now().timestamp() as u64;
+    let now = clock::now().timestamp() as u64;
     let sleep_duration = if sleep_start > 0 {
         now.saturating_sub(sleep_start)
     } else {
@@ -66,7 +69,7 @@ pub fn is_system_sleeping() -> bool {
 #[allow(dead_code)]
 pub async fn detect_time_gap() -> Option<u64> {
     let last_activity = get_last_activity_timestamp();
-    let now = Utc::now().timestamp() as u64;
+    let now = clock::now().timestamp() as u64;
     
     // If more than 10 minutes have passed since last activity, consider it a sleep event
     const SLEEP_THRESHOLD: u64 = 600; // 10 minutes
@@ -176,17 +179,22 @@ pub async fn start_power_monitoring() {
 /// Handle system sleep event
 #[allow(dead_code)]
 pub async fn handle_system_sleep() {
+    begin_away("system_sleep").await;
+}
+
+/// User left the machine (sleep, lock, session disconnect): start an idle period
+async fn begin_away(reason: &str) {
     if is_system_sleeping() {
         return; // Already in sleep state
     }
     
     mark_sleep_start();
-    log::info!("🌙 System is going to sleep");
+    log::info!("🌙 User away ({})", reason);
     
     // Send idle_start event
     let event_data = serde_json::json!({
-        "reason": "system_sleep",
-        "timestamp": Utc::now().to_rfc3339(),
+        "reason": reason,
+        "timestamp": crate::utils::time::event_timestamp(),
         "idle_time_seconds": 0,
     });
     
@@ -201,6 +209,11 @@ pub async fn handle_system_sleep() {
 
 /// Handle system wake event
 pub async fn handle_system_wake(sleep_duration: u64) {
+    end_away("system_wake", sleep_duration).await;
+}
+
+/// User is back (wake, unlock, session reconnect): end the idle period
+async fn end_away(reason: &str, sleep_duration: u64) {
     if !is_system_sleeping() && sleep_duration == 0 {
         return; // Not coming from sleep
     }
@@ -211,12 +224,12 @@ pub async fn handle_system_wake(sleep_duration: u64) {
         mark_wake_up()
     };
     
-    log::info!("☀️ System woke up after {} seconds", actual_duration);
+    log::info!("☀️ User back ({}) after {} seconds", reason, actual_duration);
     
     // Send idle_end event with the sleep duration
     let event_data = serde_json::json!({
-        "reason": "system_wake",
-        "timestamp": Utc::now().to_rfc3339(),
+        "reason": reason,
+        "timestamp": crate::utils::time::event_timestamp(),
         "idle_time_seconds": actual_duration,
         "sleep_duration_seconds": actual_duration,
     });
@@ -235,3 +248,410 @@ pub async fn handle_system_wake(sleep_duration: u64) {
     }
 }
 
+
+/// Battery at or below this level (on battery) is critical: non-essential
+/// work (screenshots, rule sync) pauses until the machine is charged
+const CRITICAL_BATTERY_PERCENT: u8 = 10;
+
+/// Power readings are reused for this long (reading them may spawn `pmset`)
+const POWER_STATUS_TTL_SECS: i64 = 30;
+
+/// Power source as reported in heartbeats
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct PowerStatus {
+    /// Running on AC power (desktops without a battery report true)
+    pub on_ac_power: bool,
+    /// Charge in percent, None without a battery
+    pub battery_percent: Option<u8>,
+    /// Low Power Mode (macOS) / Battery Saver (Windows)
+    pub low_power_mode: bool,
+}
+
+impl PowerStatus {
+    pub fn is_battery_critical(&self) -> bool {
+        !self.on_ac_power && self.battery_percent.is_some_and(|percent| percent <= CRITICAL_BATTERY_PERCENT)
+    }
+}
+
+static POWER_STATUS: std::sync::Mutex<Option<(chrono::DateTime<Utc>, PowerStatus)>> = std::sync::Mutex::new(None);
+
+/// `pmset -g batt` and the `lowpowermode` line of `pmset -g`
+#[cfg(any(target_os = "macos", test))]
+fn parse_pmset(batt: &str, settings: &str) -> PowerStatus {
+    let battery_percent = batt
+        .lines()
+        .find(|line| line.contains("InternalBattery"))
+        .and_then(|line| line.split('\t').nth(1))
+        .and_then(|status| status.split('%').next())
+        .and_then(|percent| percent.trim().parse().ok());
+    let low_power_mode = settings.lines().any(|line| {
+        let mut parts = line.split_whitespace();
+        parts.next() == Some("lowpowermode") && parts.next() == Some("1")
+    });
+    PowerStatus {
+        on_ac_power: !batt.contains("'Battery Power'"),
+        battery_percent,
+        low_power_mode,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn read_power_status() -> PowerStatus {
+    let pmset = |args: &[&str]| {
+        std::process::Command::new("pmset")
+            .args(args)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    };
+    parse_pmset(&pmset(&["-g", "batt"]), &pmset(&["-g"]))
+}
+
+#[cfg(target_os = "windows")]
+fn read_power_status() -> PowerStatus {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if unsafe { GetSystemPowerStatus(&mut status) }.is_err() {
+        return PowerStatus { on_ac_power: true, ..PowerStatus::default() };
+    }
+    PowerStatus {
+        // 0 = offline, 1 = online, 255 = unknown
+        on_ac_power: status.ACLineStatus != 0,
+        // 255 = unknown; BatteryFlag 128 = no system battery
+        battery_percent: (status.BatteryLifePercent <= 100 && status.BatteryFlag & 128 == 0)
+            .then_some(status.BatteryLifePercent),
+        low_power_mode: status.SystemStatusFlag == 1,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn read_power_status() -> PowerStatus {
+    let supplies: Vec<_> = std::fs::read_dir("/sys/class/power_supply")
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    let read = |path: &std::path::Path, file: &str| {
+        std::fs::read_to_string(path.join(file)).map(|value| value.trim().to_string()).ok()
+    };
+    let battery = supplies.iter().find(|path| read(path, "type").as_deref() == Some("Battery"));
+    let mains_online = supplies
+        .iter()
+        .any(|path| read(path, "type").as_deref() == Some("Mains") && read(path, "online").as_deref() == Some("1"));
+    PowerStatus {
+        on_ac_power: mains_online || battery.is_none(),
+        battery_percent: battery.and_then(|path| read(path, "capacity")?.parse().ok()),
+        low_power_mode: false,
+    }
+}
+
+/// Current power source, battery level and low-power mode (cached briefly)
+pub fn power_status() -> PowerStatus {
+    let mut cached = match POWER_STATUS.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    let now = clock::now();
+    if let Some((read_at, status)) = cached.as_ref() {
+        if (now - *read_at).num_seconds() < POWER_STATUS_TTL_SECS {
+            return status.clone();
+        }
+    }
+    let status = read_power_status();
+    if status.is_battery_critical() && !cached.as_ref().is_some_and(|(_, previous)| previous.is_battery_critical()) {
+        log::warn!("Battery critical ({:?}%), pausing screenshots and rule sync", status.battery_percent);
+    }
+    *cached = Some((now, status.clone()));
+    status
+}
+
+/// Whether non-essential background work should be skipped to save battery
+pub fn is_battery_critical() -> bool {
+    power_status().is_battery_critical()
+}
+
+/// Session changes reported by the OS (WTS notifications on Windows)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionChange {
+    Lock,
+    Unlock,
+    /// Console or remote session disconnected (e.g. RDP window closed)
+    Disconnect,
+    /// Console or remote session connected again
+    Reconnect,
+    Logoff,
+}
+
+impl SessionChange {
+    fn reason(self) -> &'static str {
+        match self {
+            SessionChange::Lock => "session_lock",
+            SessionChange::Unlock => "session_unlock",
+            SessionChange::Disconnect => "session_disconnect",
+            SessionChange::Reconnect => "session_reconnect",
+            SessionChange::Logoff => "session_logoff",
+        }
+    }
+}
+
+type SessionEndHandler = Box<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Run on logoff and OS shutdown; set by main so both take the graceful
+/// clock-out path
+static SESSION_END_HANDLER: std::sync::OnceLock<SessionEndHandler> = std::sync::OnceLock::new();
+
+/// Register what happens when the user's session ends (logoff, shutdown)
+pub fn set_session_end_handler<F, Fut>(handler: F)
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let handler: SessionEndHandler = Box::new(move || Box::pin(handler()));
+    if SESSION_END_HANDLER.set(handler).is_err() {
+        log::warn!("Session end handler already registered");
+    }
+}
+
+/// Run the session end handler to completion
+pub async fn end_user_session() {
+    match SESSION_END_HANDLER.get() {
+        Some(handler) => handler().await,
+        None => log::warn!("User session ending but no session end handler is registered"),
+    }
+}
+
+/// Lock and disconnect start an idle period, unlock and reconnect end it and
+/// logoff clocks the user out. WTS_SESSION_LOGOFF is only a backup for
+/// WM_ENDSESSION, which Windows delivers far more reliably; the session end
+/// handler ignores whichever of the two comes second.
+pub async fn handle_session_change(change: SessionChange) {
+    log::info!("Session change: {:?}", change);
+    match change {
+        SessionChange::Lock | SessionChange::Disconnect => begin_away(change.reason()).await,
+        SessionChange::Unlock | SessionChange::Reconnect => end_away(change.reason(), 0).await,
+        SessionChange::Logoff => end_user_session().await,
+    }
+}
+
+/// WTS session-change notifications (lock, unlock, logoff, disconnect) and
+/// OS shutdown handling.
+///
+/// WM_QUERYENDSESSION only asks whether the session may end; another app or
+/// the user can still cancel, so it just shows a shutdown block reason
+/// ("TrackEx is saving your session") and flushes pending events. The
+/// clock-out runs on WM_ENDSESSION once the end is confirmed, and the handler
+/// waits for it, up to `SHUTDOWN_GRACE`, since Windows kills processes shortly
+/// after it returns. A cancelled shutdown drops the block reason again.
+/// Logoff goes through the same WM_ENDSESSION path (with ENDSESSION_LOGOFF
+/// set), since WTS_SESSION_LOGOFF rarely reaches this window.
+#[cfg(target_os = "windows")]
+pub mod session_notifications {
+    use super::SessionChange;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use windows::core::{w, HSTRING};
+    use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows::Win32::System::Shutdown::{ShutdownBlockReasonCreate, ShutdownBlockReasonDestroy};
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::System::RemoteDesktop::{
+        WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION, WTS_CONSOLE_CONNECT, WTS_CONSOLE_DISCONNECT,
+        WTS_REMOTE_CONNECT, WTS_REMOTE_DISCONNECT, WTS_SESSION_LOCK, WTS_SESSION_LOGOFF, WTS_SESSION_UNLOCK,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, TranslateMessage,
+        ENDSESSION_LOGOFF, MSG, WINDOW_EX_STYLE, WINDOW_STYLE, WM_ENDSESSION, WM_QUERYENDSESSION,
+        WM_WTSSESSION_CHANGE, WNDCLASSW,
+    };
+
+    /// Longest the agent holds up shutdown; Windows force-closes apps that
+    /// block much longer anyway
+    const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+    /// Event flush started on WM_QUERYENDSESSION
+    static PENDING_FLUSH: Mutex<Option<tauri::async_runtime::JoinHandle<()>>> = Mutex::new(None);
+
+    fn take_pending_flush() -> Option<tauri::async_runtime::JoinHandle<()>> {
+        match PENDING_FLUSH.lock() {
+            Ok(mut guard) => guard.take(),
+            Err(e) => e.into_inner().take(),
+        }
+    }
+
+    /// Show the block reason and get pending events out while the session
+    /// may still continue
+    unsafe fn query_session_end(hwnd: HWND) {
+        let mut pending = match PENDING_FLUSH.lock() {
+            Ok(guard) => guard,
+            Err(e) => e.into_inner(),
+        };
+        if pending.is_some() {
+            return;
+        }
+        let reason = HSTRING::from(crate::i18n::t("shutdown.saving_session"));
+        if let Err(e) = ShutdownBlockReasonCreate(hwnd, &reason) {
+            log::warn!("Failed to set shutdown block reason: {}", e);
+        }
+        log::info!("Session end requested, flushing events");
+        *pending = Some(tauri::async_runtime::spawn(crate::sampling::event_batcher::flush_events()));
+    }
+
+    /// The session is ending: clock out, wait (bounded) for it, then let
+    /// shutdown proceed
+    unsafe fn end_session(hwnd: HWND, lparam: LPARAM) {
+        if lparam.0 as u32 & ENDSESSION_LOGOFF != 0 {
+            log::info!("User logging off, saving session");
+        } else {
+            log::info!("Session ending, saving session before shutdown");
+        }
+        let flush = take_pending_flush();
+        let finished = tauri::async_runtime::block_on(async {
+            tokio::time::timeout(SHUTDOWN_GRACE, async {
+                if let Some(flush) = flush {
+                    let _ = flush.await;
+                }
+                super::end_user_session().await;
+            })
+            .await
+            .is_ok()
+        });
+        if !finished {
+            log::warn!("Session not fully saved within {:?} of shutdown", SHUTDOWN_GRACE);
+        }
+        let _ = ShutdownBlockReasonDestroy(hwnd);
+    }
+
+    /// Shutdown was cancelled; the session goes on as before
+    unsafe fn cancel_session_end(hwnd: HWND) {
+        log::info!("Session end cancelled");
+        take_pending_flush();
+        let _ = ShutdownBlockReasonDestroy(hwnd);
+    }
+
+    fn session_change(code: u32) -> Option<SessionChange> {
+        match code {
+            WTS_SESSION_LOCK => Some(SessionChange::Lock),
+            WTS_SESSION_UNLOCK => Some(SessionChange::Unlock),
+            WTS_SESSION_LOGOFF => Some(SessionChange::Logoff),
+            WTS_CONSOLE_DISCONNECT | WTS_REMOTE_DISCONNECT => Some(SessionChange::Disconnect),
+            WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT => Some(SessionChange::Reconnect),
+            _ => None,
+        }
+    }
+
+    unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if msg == WM_WTSSESSION_CHANGE {
+            if let Some(change) = session_change(wparam.0 as u32) {
+                tauri::async_runtime::spawn(super::handle_session_change(change));
+            }
+            return LRESULT(0);
+        }
+        match msg {
+            WM_QUERYENDSESSION => {
+                query_session_end(hwnd);
+                // Allow the shutdown; the block reason holds it while we save
+                return LRESULT(1);
+            }
+            // wParam is FALSE when the shutdown was cancelled
+            WM_ENDSESSION if wparam.0 != 0 => {
+                end_session(hwnd, lparam);
+                return LRESULT(0);
+            }
+            WM_ENDSESSION => {
+                cancel_session_end(hwnd);
+                return LRESULT(0);
+            }
+            _ => {}
+        }
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+
+    /// Create a hidden window registered for session notifications and pump
+    /// its messages on a dedicated thread
+    pub fn start() {
+        let spawned = std::thread::Builder::new()
+            .name("wts-session-notifications".into())
+            .spawn(|| unsafe {
+                let instance = match GetModuleHandleW(None) {
+                    Ok(module) => module.into(),
+                    Err(e) => {
+                        log::warn!("Session notifications unavailable: {}", e);
+                        return;
+                    }
+                };
+                let class_name = w!("TrackExSessionNotifications");
+                let class = WNDCLASSW {
+                    lpfnWndProc: Some(window_proc),
+                    hInstance: instance,
+                    lpszClassName: class_name,
+                    ..Default::default()
+                };
+                RegisterClassW(&class);
+
+                // Never shown; message-only windows don't receive WTS notifications
+                let hwnd = match CreateWindowExW(
+                    WINDOW_EX_STYLE::default(),
+                    class_name,
+                    w!(""),
+                    WINDOW_STYLE::default(),
+                    0,
+                    0,
+                    0,
+                    0,
+                    None,
+                    None,
+                    Some(instance),
+                    None,
+                ) {
+                    Ok(hwnd) => hwnd,
+                    Err(e) => {
+                        log::warn!("Failed to create session notification window: {}", e);
+                        return;
+                    }
+                };
+                if let Err(e) = WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) {
+                    log::warn!("Failed to register for session notifications: {}", e);
+                    return;
+                }
+                log::info!("Session change notifications registered");
+
+                let mut msg = MSG::default();
+                while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+            });
+        if let Err(e) = spawned {
+            log::warn!("Failed to start session notification thread: {}", e);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_session_change_codes() {
+            assert_eq!(session_change(WTS_SESSION_LOCK), Some(SessionChange::Lock));
+            assert_eq!(session_change(WTS_REMOTE_DISCONNECT), Some(SessionChange::Disconnect));
+            assert_eq!(session_change(WTS_SESSION_LOGOFF), Some(SessionChange::Logoff));
+            assert_eq!(session_change(0x9), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pmset() {
+        let batt = "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=1234)\t8%; discharging; 0:25 remaining present: true\n";
+        let settings = "System-wide power settings:\nCurrently in use:\n lowpowermode         1\n sleep                1\n";
+        let status = parse_pmset(batt, settings);
+        assert_eq!(status, PowerStatus { on_ac_power: false, battery_percent: Some(8), low_power_mode: true });
+        assert!(status.is_battery_critical());
+
+        let desktop = parse_pmset("Now drawing from 'AC Power'\n", "Currently in use:\n sleep 0\n");
+        assert_eq!(desktop, PowerStatus { on_ac_power: true, battery_percent: None, low_power_mode: false });
+        assert!(!desktop.is_battery_critical());
+    }
+}
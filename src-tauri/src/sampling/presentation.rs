@@ -0,0 +1,259 @@
+//! Do-not-disturb and presentation detection
+//!
+//! While the OS is in Do Not Disturb / Focus Assist, or the employee is
+//! presenting (a full-screen slide show), screenshots would capture the
+//! audience's view and notifications would pop up in front of it. Auto
+//! screenshots and screenshot jobs wait until it ends, and notifications are
+//! held back and shown afterwards.
+//!
+//! Re-evaluated on every app focus sample, and reset when sampling stops or
+//! pauses so a stale state can't hold back jobs and notifications.
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+use super::app_focus::AppInfo;
+
+/// Most held notifications shown once presenting ends
+const MAX_HELD_NOTIFICATIONS: usize = 5;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresentationState {
+    /// OS Do Not Disturb / Focus / Focus Assist is on
+    pub do_not_disturb: bool,
+    /// App showing a presentation, if any
+    pub presenting_app: Option<String>,
+}
+
+impl PresentationState {
+    pub fn is_active(&self) -> bool {
+        self.do_not_disturb || self.presenting_app.is_some()
+    }
+}
+
+static STATE: Mutex<Option<PresentationState>> = Mutex::new(None);
+
+/// Notification (title key, body key) pairs held while presenting
+static HELD: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// What the OS itself reports
+#[derive(Debug, Default)]
+struct OsState {
+    do_not_disturb: bool,
+    /// The OS says the foreground app is presenting (Windows presentation settings)
+    presentation_mode: bool,
+    /// The foreground app is full screen
+    full_screen: bool,
+}
+
+#[cfg(target_os = "windows")]
+fn os_state() -> OsState {
+    use windows::Win32::UI::Shell::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME,
+        QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+
+    match unsafe { SHQueryUserNotificationState() } {
+        Ok(state) => OsState {
+            do_not_disturb: state == QUNS_QUIET_TIME,
+            presentation_mode: state == QUNS_PRESENTATION_MODE,
+            full_screen: state == QUNS_BUSY || state == QUNS_RUNNING_D3D_FULL_SCREEN,
+        },
+        Err(e) => {
+            log::debug!("Failed to query notification state: {:?}", e);
+            OsState::default()
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn os_state() -> OsState {
+    // Focus (Monterey and later) records active assertions here; reading it
+    // may need Full Disk Access, in which case Focus is treated as off
+    let do_not_disturb = dirs::home_dir()
+        .map(|home| home.join("Library/DoNotDisturb/DB/Assertions.json"))
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map_or(false, |json| focus_assertions_active(&json));
+    OsState { do_not_disturb, ..OsState::default() }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn os_state() -> OsState {
+    OsState::default()
+}
+
+/// Whether macOS Focus has any active assertion
+#[cfg(any(target_os = "macos", test))]
+fn focus_assertions_active(json: &str) -> bool {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(json) else {
+        return false;
+    };
+    value["data"].as_array().map_or(false, |data| {
+        data.iter().any(|entry| {
+            entry["storeAssertionRecords"]
+                .as_array()
+                .map_or(false, |records| !records.is_empty())
+        })
+    })
+}
+
+/// Whether the focused app is showing a presentation
+fn is_presenting_app(app: &AppInfo, full_screen: bool) -> bool {
+    let app_id = app.app_id.to_lowercase();
+    let title = app.window_title.as_deref().unwrap_or("").to_lowercase();
+
+    // Google Slides / PowerPoint Online present at .../present
+    if let Some(url) = app.url.as_deref() {
+        let url = url.to_lowercase();
+        if (url.contains("docs.google.com/presentation") && url.contains("/present"))
+            || (url.contains("powerpoint.officeapps.live.com") && title.contains("slide show"))
+        {
+            return true;
+        }
+    }
+
+    let is_slides_app = ["powerpnt", "com.microsoft.powerpoint", "com.apple.iwork.keynote", "soffice", "libreoffice"]
+        .iter()
+        .any(|id| app_id.contains(id));
+    if !is_slides_app {
+        return false;
+    }
+    full_screen
+        || ["slide show", "slideshow", "presenter view", "presenting"].iter().any(|hint| title.contains(hint))
+        // Keynote's playing window has no title
+        || (app_id.contains("keynote") && title.is_empty())
+}
+
+fn set_state(next: PresentationState) -> Option<PresentationState> {
+    let mut state = match STATE.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    state.replace(next)
+}
+
+/// Re-evaluate with the currently focused app
+pub fn update(app: &AppInfo) {
+    let os = os_state();
+    let presenting = os.presentation_mode || is_presenting_app(app, os.full_screen);
+    let next = PresentationState {
+        do_not_disturb: os.do_not_disturb,
+        presenting_app: presenting.then(|| app.name.clone()),
+    };
+
+    let was_active = set_state(next.clone()).map_or(false, |previous| {
+        if previous != next {
+            log::info!("Presentation state changed: {:?}", next);
+        }
+        previous.is_active()
+    });
+    if was_active && !next.is_active() {
+        show_held_notifications();
+    }
+}
+
+/// Forget the state (sampling stopped or paused), showing held notifications
+pub fn reset() {
+    let was_active = {
+        let mut state = match STATE.lock() {
+            Ok(guard) => guard,
+            Err(e) => e.into_inner(),
+        };
+        state.take().map_or(false, |previous| previous.is_active())
+    };
+    if was_active {
+        log::info!("Presentation state reset");
+        show_held_notifications();
+    }
+}
+
+/// Current state; inactive until the first sample
+pub fn current() -> PresentationState {
+    match STATE.lock() {
+        Ok(guard) => guard.clone().unwrap_or_default(),
+        Err(e) => e.into_inner().clone().unwrap_or_default(),
+    }
+}
+
+/// Do Not Disturb is on or the employee is presenting
+pub fn is_presenting() -> bool {
+    current().is_active()
+}
+
+/// Hold a notification while presenting; returns false when it should be
+/// shown now
+pub fn hold_notification(title_key: &str, body_key: &str) -> bool {
+    if !is_presenting() {
+        return false;
+    }
+    let mut held = match HELD.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    if !held.iter().any(|(title, _)| title == title_key) {
+        if held.len() >= MAX_HELD_NOTIFICATIONS {
+            held.remove(0);
+        }
+        held.push((title_key.to_string(), body_key.to_string()));
+    }
+    log::debug!("Notification {} held while presenting", title_key);
+    true
+}
+
+fn show_held_notifications() {
+    let held = {
+        let mut held = match HELD.lock() {
+            Ok(guard) => guard,
+            Err(e) => e.into_inner(),
+        };
+        std::mem::take(&mut *held)
+    };
+    for (title_key, body_key) in held {
+        crate::events::notify(&title_key, &body_key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(app_id: &str, title: &str, url: Option<&str>) -> AppInfo {
+        AppInfo {
+            name: app_id.to_string(),
+            app_id: app_id.to_string(),
+            window_title: Some(title.to_string()),
+            url: url.map(str::to_string),
+            domain: None,
+            raw_domain: None,
+            raw_window_title: None,
+            exe_path: None,
+            file_version: None,
+            pid: None,
+            virtual_desktop: None,
+        }
+    }
+
+    #[test]
+    fn test_presenting_app_heuristics() {
+        assert!(is_presenting_app(&app("POWERPNT.EXE", "PowerPoint Slide Show - Roadmap.pptx", None), false));
+        assert!(is_presenting_app(&app("com.apple.iWork.Keynote", "", None), false));
+        assert!(is_presenting_app(&app("com.microsoft.Powerpoint", "Roadmap.pptx", None), true));
+        assert!(!is_presenting_app(&app("com.microsoft.Powerpoint", "Roadmap.pptx", None), false));
+        assert!(is_presenting_app(
+            &app("com.google.Chrome", "Roadmap", Some("https://docs.google.com/presentation/d/abc/present")),
+            false
+        ));
+        // Full screen alone isn't presenting (videos, games)
+        assert!(!is_presenting_app(&app("com.google.Chrome", "YouTube", Some("https://youtube.com/watch")), true));
+    }
+
+    #[test]
+    fn test_focus_assertions() {
+        let active = r#"{"data":[{"storeAssertionRecords":[{"assertionDetails":{"assertionDetailsModeIdentifier":"com.apple.donotdisturb.mode.default"}}]}]}"#;
+        assert!(focus_assertions_active(active));
+        assert!(!focus_assertions_active(r#"{"data":[{"storeAssertionRecords":[]}]}"#));
+        assert!(!focus_assertions_active(r#"{"data":[{}]}"#));
+        assert!(!focus_assertions_active("not json"));
+    }
+}
@@ -0,0 +1,175 @@
+//! Executable details for the focused app
+//!
+//! `AppInfo` carries the executable path, file version and PID of the
+//! foreground process so app rules can match by path (`PATH` rules) and
+//! support can tell apart apps that share a display name. Paths are
+//! privacy-filtered here: the user's home directory becomes `~`, so the
+//! account name is never reported. Versions are cached per executable since
+//! reading them means opening the binary or its bundle.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Cached versions; the focused app changes rarely, so this stays tiny
+const MAX_CACHED_VERSIONS: usize = 256;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessDetails {
+    /// Privacy-filtered executable path
+    pub exe_path: Option<String>,
+    pub file_version: Option<String>,
+}
+
+static VERSION_CACHE: Mutex<Option<HashMap<PathBuf, Option<String>>>> = Mutex::new(None);
+
+/// `path` relative to `prefix`, comparing components case-insensitively if
+/// asked to (Windows paths are; `Path::strip_prefix` never ignores case)
+fn strip_prefix(path: &Path, prefix: &Path, ignore_case: bool) -> Option<PathBuf> {
+    let same = |a: &std::ffi::OsStr, b: &std::ffi::OsStr| {
+        if ignore_case {
+            a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+        } else {
+            a == b
+        }
+    };
+    let mut components = path.components();
+    for expected in prefix.components() {
+        if !same(components.next()?.as_os_str(), expected.as_os_str()) {
+            return None;
+        }
+    }
+    Some(components.as_path().to_path_buf())
+}
+
+/// Replace the home directory prefix with `~`
+fn privacy_filter_path(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| strip_prefix(path, home, cfg!(target_os = "windows"))) {
+        Some(relative) => Path::new("~").join(relative).to_string_lossy().into_owned(),
+        None => path.to_string_lossy().into_owned(),
+    }
+}
+
+fn exe_path_for_pid(pid: u32) -> Option<PathBuf> {
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut sys = sysinfo::System::new();
+    sys.refresh_process(pid);
+    sys.process(pid)?.exe().map(Path::to_path_buf)
+}
+
+/// Version of the app bundle containing `exe` (CFBundleShortVersionString)
+#[cfg(target_os = "macos")]
+fn read_file_version(exe: &Path) -> Option<String> {
+    use core_foundation::bundle::CFBundle;
+    use core_foundation::string::CFString;
+    use core_foundation::url::CFURL;
+
+    let bundle_path = exe.ancestors().find(|p| p.extension().is_some_and(|ext| ext == "app"))?;
+    let bundle = CFBundle::new(CFURL::from_path(bundle_path, true)?)?;
+    let info = bundle.info_dictionary();
+    ["CFBundleShortVersionString", "CFBundleVersion"].iter().find_map(|key| {
+        info.find(CFString::from_static_string(key))
+            .and_then(|value| value.downcast::<CFString>())
+            .map(|value| value.to_string())
+    })
+}
+
+/// Fixed file version from the executable's version resource
+#[cfg(target_os = "windows")]
+fn read_file_version(exe: &Path) -> Option<String> {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Storage::FileSystem::{
+        GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW, VS_FIXEDFILEINFO,
+    };
+
+    let wide: Vec<u16> = exe.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    unsafe {
+        let size = GetFileVersionInfoSizeW(PCWSTR(wide.as_ptr()), None);
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        GetFileVersionInfoW(PCWSTR(wide.as_ptr()), None, size, data.as_mut_ptr() as *mut c_void).ok()?;
+
+        let mut info: *mut c_void = std::ptr::null_mut();
+        let mut len = 0u32;
+        if !VerQueryValueW(data.as_ptr() as *const c_void, w!("\\"), &mut info, &mut len).as_bool() || info.is_null() {
+            return None;
+        }
+        let info = &*(info as *const VS_FIXEDFILEINFO);
+        Some(format!(
+            "{}.{}.{}.{}",
+            info.dwFileVersionMS >> 16,
+            info.dwFileVersionMS & 0xFFFF,
+            info.dwFileVersionLS >> 16,
+            info.dwFileVersionLS & 0xFFFF
+        ))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn read_file_version(_exe: &Path) -> Option<String> {
+    None
+}
+
+fn cached_file_version(exe: &Path) -> Option<String> {
+    let mut cache = match VERSION_CACHE.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if let Some(version) = cache.get(exe) {
+        return version.clone();
+    }
+    if cache.len() >= MAX_CACHED_VERSIONS {
+        cache.clear();
+    }
+    let version = read_file_version(exe);
+    cache.insert(exe.to_path_buf(), version.clone());
+    version
+}
+
+/// Executable path and version of a running process
+pub fn details_for_pid(pid: u32) -> ProcessDetails {
+    let Some(exe) = exe_path_for_pid(pid) else {
+        return ProcessDetails::default();
+    };
+    ProcessDetails {
+        exe_path: Some(privacy_filter_path(&exe, dirs::home_dir().as_deref())),
+        file_version: cached_file_version(&exe),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_privacy_filter_path_hides_home() {
+        let home = Path::new("/Users/jane");
+        assert_eq!(
+            privacy_filter_path(Path::new("/Users/jane/Applications/Tool.app/Contents/MacOS/Tool"), Some(home)),
+            "~/Applications/Tool.app/Contents/MacOS/Tool"
+        );
+        assert_eq!(
+            privacy_filter_path(Path::new("/Applications/Safari.app/Contents/MacOS/Safari"), Some(home)),
+            "/Applications/Safari.app/Contents/MacOS/Safari"
+        );
+        assert_eq!(privacy_filter_path(Path::new("/usr/bin/vim"), None), "/usr/bin/vim");
+    }
+
+    #[test]
+    fn test_strip_prefix_ignoring_case() {
+        let home = Path::new("/Users/Jane");
+        assert_eq!(
+            strip_prefix(Path::new("/users/jane/AppData/Tool.exe"), home, true),
+            Some(PathBuf::from("AppData/Tool.exe"))
+        );
+        assert_eq!(strip_prefix(Path::new("/users/jane/AppData/Tool.exe"), home, false), None);
+        assert_eq!(strip_prefix(Path::new("/Users/Janet/Tool.exe"), home, true), None);
+        assert_eq!(strip_prefix(Path::new("/Users"), home, true), None);
+    }
+}
@@ -24,6 +24,11 @@ pub async fn start_queue_processor(_app_handle: AppHandle) {
             log::info!("Queue processor stopping - user clocked out or logged out");
             break;
         }
+
+        // Keep queued data (and its retries) until the device is repaired or the user signs in again
+        if crate::api::device_repair::credentials_rejected() {
+            continue;
+        }
         
         // Process pending events
         match process_pending_events().await {
@@ -58,8 +63,9 @@ async fn process_pending_events() -> anyhow::Result<usize> {
     let count = pending_events.len();
     
     for event in pending_events {
+        crate::api::bandwidth::consume(event.event_data.to_string().len()).await;
         // Try to send the event
-        match super::send_event_to_backend(&event.event_type, &event.event_data).await {
+        match super::send_queued_event(&event).await {
             Ok(_) => {
                 // Mark as processed
                 offline_queue::mark_event_processed(event.id).await?;
@@ -82,6 +88,7 @@ async fn process_pending_heartbeats() -> anyhow::Result<usize> {
     let count = pending_heartbeats.len();
     
     for heartbeat in pending_heartbeats {
+        crate::api::bandwidth::consume(heartbeat.heartbeat_data.to_string().len()).await;
         // Try to send the heartbeat
         match super::send_heartbeat_to_backend(&heartbeat.heartbeat_data).await {
             Ok(_) => {
@@ -101,3 +108,66 @@ async fn process_pending_heartbeats() -> anyhow::Result<usize> {
     Ok(count)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{agent_env, MockBackend};
+
+    #[tokio::test]
+    async fn test_offline_queue_drains_to_backend() {
+        let backend = MockBackend::start().await;
+        let _env = agent_env(&backend).await;
+        offline_queue::queue_event("app_focus", &serde_json::json!({ "app_name": "Code" })).await.unwrap();
+        offline_queue::queue_event("clock_out", &serde_json::json!({ "session_id": 1 })).await.unwrap();
+        offline_queue::queue_heartbeat(&serde_json::json!({ "status": "active" })).await.unwrap();
+
+        assert_eq!(process_pending_events().await.unwrap(), 2);
+        assert_eq!(process_pending_heartbeats().await.unwrap(), 1);
+        // Clock events go first
+        assert_eq!(backend.received_event_types().await, vec!["clock_out", "app_focus"]);
+        assert_eq!(backend.received("/api/ingest/heartbeat").await[0]["status"], "active");
+        assert!(offline_queue::get_pending_events().await.unwrap().is_empty());
+        assert!(offline_queue::get_pending_heartbeats().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replay_keeps_original_time() {
+        use chrono::TimeZone;
+        let backend = MockBackend::start().await;
+        let _env = agent_env(&backend).await;
+        let ended_at = chrono::Utc.with_ymd_and_hms(2024, 3, 4, 17, 30, 0).unwrap();
+        offline_queue::queue_event_at("clock_out", &serde_json::json!({ "reason": "manual" }), ended_at).await.unwrap();
+        // Queued by an older version as a whole envelope
+        let envelope = serde_json::json!({ "events": [{
+            "type": "clock_out",
+            "timestamp": "2024-03-05T17:00:00.000Z",
+            "data": { "reason": "logout" }
+        }] });
+        offline_queue::queue_event("clock_out", &envelope).await.unwrap();
+
+        assert_eq!(process_pending_events().await.unwrap(), 2);
+        let events: Vec<_> = backend
+            .received("/api/ingest/events")
+            .await
+            .into_iter()
+            .flat_map(|body| body["events"].as_array().cloned().unwrap_or_default())
+            .collect();
+        assert_eq!(events[0]["timestamp"], "2024-03-04T17:30:00.000Z");
+        assert_eq!(events[0]["data"]["reason"], "manual");
+        assert_eq!(events[1]["timestamp"], "2024-03-05T17:00:00.000Z");
+        assert_eq!(events[1]["data"]["reason"], "logout");
+    }
+
+    #[tokio::test]
+    async fn test_failed_sends_stay_queued() {
+        let backend = MockBackend::failing(503).await;
+        let _env = agent_env(&backend).await;
+        offline_queue::queue_event("idle_start", &serde_json::json!({})).await.unwrap();
+
+        assert_eq!(process_pending_events().await.unwrap(), 1);
+        let pending = offline_queue::get_pending_events().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].retry_count, 1);
+    }
+}
@@ -0,0 +1,183 @@
+//! Screen sharing detection
+//!
+//! While the employee shares their screen in a meeting, a screenshot can
+//! capture other participants' video and shared content. Sharing is reported
+//! in heartbeats (`is_screen_sharing`), and with the
+//! `pause_screenshots_while_sharing` policy no screenshots are taken.
+//!
+//! Detection is heuristic: meeting apps show a sharing toolbar window, and
+//! browsers an "is sharing your screen" bar, while sharing; Zoom also runs a
+//! dedicated helper process. Re-checked at most every `CHECK_INTERVAL_SECS`
+//! from app focus sampling.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const CHECK_INTERVAL_SECS: u64 = 10;
+
+/// Lowercase (owner or title, title) substrings of a sharing window, and the app
+const WINDOW_SIGNATURES: &[(&str, &str, &str)] = &[
+    ("zoom", "share toolbar", "Zoom"),
+    ("zoom", "you are screen sharing", "Zoom"),
+    ("teams", "sharing control bar", "Microsoft Teams"),
+    ("teams", "sharing toolbar", "Microsoft Teams"),
+    ("webex", "sharing control", "Webex"),
+    ("", "is sharing your screen", "Browser"),
+    ("", "is sharing a window", "Browser"),
+];
+
+/// Lowercase process names that only run while sharing
+const PROCESS_SIGNATURES: &[(&str, &str)] = &[("cpthost", "Zoom")];
+
+static SHARING: AtomicBool = AtomicBool::new(false);
+static LAST_CHECK: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// App that is sharing the screen, from on-screen windows (owner, title) and
+/// running process names
+fn sharing_app(windows: &[(String, String)], processes: &[String]) -> Option<&'static str> {
+    let by_window = windows.iter().find_map(|(owner, title)| {
+        let owner = owner.to_lowercase();
+        let title = title.to_lowercase();
+        WINDOW_SIGNATURES
+            .iter()
+            .find(|(app, hint, _)| (owner.contains(app) || title.contains(app)) && title.contains(hint))
+            .map(|(_, _, name)| *name)
+    });
+    by_window.or_else(|| {
+        processes.iter().find_map(|process| {
+            let process = process.to_lowercase();
+            PROCESS_SIGNATURES
+                .iter()
+                .find(|(signature, _)| process.starts_with(signature))
+                .map(|(_, name)| *name)
+        })
+    })
+}
+
+/// Visible top-level windows as (owner, title)
+#[cfg(target_os = "windows")]
+fn visible_windows() -> Vec<(String, String)> {
+    use windows::core::BOOL;
+    use windows::Win32::Foundation::{HWND, LPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowTextW, IsWindowVisible};
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let titles = &mut *(lparam.0 as *mut Vec<(String, String)>);
+        if IsWindowVisible(hwnd).as_bool() {
+            let mut buffer = [0u16; 256];
+            let len = GetWindowTextW(hwnd, &mut buffer);
+            if len > 0 {
+                // Window titles carry the app name on Windows; the owner isn't needed
+                titles.push((String::new(), String::from_utf16_lossy(&buffer[..len as usize])));
+            }
+        }
+        BOOL(1)
+    }
+
+    let mut titles: Vec<(String, String)> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(Some(collect), LPARAM(&mut titles as *mut _ as isize));
+    }
+    titles
+}
+
+/// On-screen windows as (owning app, title); titles need Screen Recording
+#[cfg(target_os = "macos")]
+fn visible_windows() -> Vec<(String, String)> {
+    use core_foundation::base::{CFType, TCFType};
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::string::CFString;
+    use core_graphics::window::{
+        copy_window_info, kCGNullWindowID, kCGWindowListOptionOnScreenOnly, kCGWindowName, kCGWindowOwnerName,
+    };
+
+    let Some(windows) = copy_window_info(kCGWindowListOptionOnScreenOnly, kCGNullWindowID) else {
+        return Vec::new();
+    };
+    let text = |dict: &CFDictionary<CFString, CFType>, key| {
+        dict.find(unsafe { CFString::wrap_under_get_rule(key) })
+            .and_then(|value| value.downcast::<CFString>())
+            .map(|value| value.to_string())
+            .unwrap_or_default()
+    };
+    windows
+        .iter()
+        .map(|window| {
+            let dict: CFDictionary<CFString, CFType> =
+                unsafe { CFDictionary::wrap_under_get_rule(*window as CFDictionaryRef) };
+            (text(&dict, unsafe { kCGWindowOwnerName }), text(&dict, unsafe { kCGWindowName }))
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn visible_windows() -> Vec<(String, String)> {
+    Vec::new()
+}
+
+fn process_names() -> Vec<String> {
+    let mut sys = sysinfo::System::new();
+    sys.refresh_processes();
+    sys.processes().values().map(|process| process.name().to_string()).collect()
+}
+
+/// Re-check sharing if the last check is older than `CHECK_INTERVAL_SECS`
+pub async fn refresh() {
+    {
+        let mut last = match LAST_CHECK.lock() {
+            Ok(guard) => guard,
+            Err(e) => e.into_inner(),
+        };
+        if last.map_or(false, |at| at.elapsed() < Duration::from_secs(CHECK_INTERVAL_SECS)) {
+            return;
+        }
+        *last = Some(Instant::now());
+    }
+
+    let app = match tokio::task::spawn_blocking(|| sharing_app(&visible_windows(), &process_names())).await {
+        Ok(app) => app,
+        Err(e) => {
+            log::debug!("Screen sharing check failed: {}", e);
+            return;
+        }
+    };
+    let sharing = app.is_some();
+    if SHARING.swap(sharing, Ordering::SeqCst) != sharing {
+        match app {
+            Some(app) => log::info!("Screen sharing started ({})", app),
+            None => log::info!("Screen sharing ended"),
+        }
+    }
+}
+
+/// Whether the screen was being shared at the last check
+pub fn is_screen_sharing() -> bool {
+    SHARING.load(Ordering::SeqCst)
+}
+
+/// The policy pauses screenshots and the screen is being shared
+pub async fn screenshots_paused() -> bool {
+    is_screen_sharing()
+        && crate::api::employee_settings::get_policy_settings()
+            .await
+            .pause_screenshots_while_sharing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(owner: &str, title: &str) -> (String, String) {
+        (owner.to_string(), title.to_string())
+    }
+
+    #[test]
+    fn test_sharing_signatures() {
+        assert_eq!(sharing_app(&[window("Microsoft Teams", "Sharing control bar | Microsoft Teams")], &[]), Some("Microsoft Teams"));
+        assert_eq!(sharing_app(&[window("Google Chrome", "meet.google.com is sharing your screen.")], &[]), Some("Browser"));
+        assert_eq!(sharing_app(&[], &["CptHost.exe".to_string()]), Some("Zoom"));
+        // Being in a meeting isn't sharing
+        assert_eq!(sharing_app(&[window("zoom.us", "Zoom Meeting")], &["zoom.us".to_string()]), None);
+    }
+}
@@ -6,6 +6,7 @@
 use tauri::AppHandle;
 use tokio::time::Duration;
 use chrono::Utc;
+use crate::utils::clock;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::RwLock;
 use std::sync::OnceLock;
@@ -76,14 +77,14 @@ pub async fn start_screenshot_service(_app_handle: AppHandle) {
     // Initialize last capture time
     {
         let mut last_capture_guard = get_last_capture_lock().write().await;
-        let now = Utc::now();
+        let now = clock::now();
         *last_capture_guard = Some(now);
         log::info!(
             "Screenshot service: Initialized - first screenshot will be taken immediately when auto_screenshots enabled"
         );
     }
     
-    let mut last_cleanup = Utc::now();
+    let mut last_cleanup = clock::now();
     
     loop {
         // Check if services should continue running
@@ -97,6 +98,22 @@ pub async fn start_screenshot_service(_app_handle: AppHandle) {
             continue;
         }
         
+        // Screen Recording was revoked: don't keep attempting captures that fail
+        if crate::permissions::watcher::screenshots_paused() {
+            log::debug!("Auto screenshots paused - Screen Recording permission revoked");
+            process_retry_queue().await;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            continue;
+        }
+        
+        // Do Not Disturb or presenting: the overdue capture is taken once it ends
+        if super::presentation::is_presenting() {
+            log::debug!("Auto screenshots deferred - presenting or Do Not Disturb");
+            process_retry_queue().await;
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            continue;
+        }
+        
         // Check if auto screenshots are enabled
         let settings = match employee_settings::get_employee_settings().await {
             Ok(s) => s,
@@ -149,7 +166,7 @@ pub async fn start_screenshot_service(_app_handle: AppHandle) {
                     
                     // Update last capture time
                     let mut last_capture_guard = get_last_capture_lock().write().await;
-                    let now = Utc::now();
+                    let now = clock::now();
                     *last_capture_guard = Some(now);
                     log::info!(
                         "Updated last_capture_time to {} - next screenshot in {}min",
@@ -189,7 +206,7 @@ pub async fn start_screenshot_service(_app_handle: AppHandle) {
             let last_capture_guard = get_last_capture_lock().read().await;
             match *last_capture_guard {
                 Some(last) => {
-                    let now: chrono::DateTime<Utc> = Utc::now();
+                    let now: chrono::DateTime<Utc> = clock::now();
                     let elapsed = now.signed_duration_since(last).num_seconds() as u64;
                     // Apply tolerance: capture if elapsed >= (interval - tolerance)
                     // This ensures we don't miss captures due to minor timing variations
@@ -257,7 +274,7 @@ pub async fn start_screenshot_service(_app_handle: AppHandle) {
             // Update GLOBAL last capture time
             {
                 let mut last_capture_guard = get_last_capture_lock().write().await;
-                let now = Utc::now();
+                let now = clock::now();
                 *last_capture_guard = Some(now);
                 log::info!(
                     "Updated last_capture_time to {} - next screenshot in {}s",
@@ -271,12 +288,12 @@ pub async fn start_screenshot_service(_app_handle: AppHandle) {
         process_retry_queue().await;
         
         // Periodic cleanup
-        let now: chrono::DateTime<Utc> = Utc::now();
+        let now: chrono::DateTime<Utc> = clock::now();
         if now.signed_duration_since(last_cleanup).num_seconds() as u64 >= CLEANUP_INTERVAL_SECS {
             if let Err(e) = screenshot_queue::cleanup_stale_files().await {
                 log::warn!("Failed to cleanup stale files: {}", e);
             }
-            last_cleanup = Utc::now();
+            last_cleanup = clock::now();
         }
         
         // Calculate sleep time based on when next screenshot should be taken
@@ -285,7 +302,7 @@ pub async fn start_screenshot_service(_app_handle: AppHandle) {
             let last_capture_guard = get_last_capture_lock().read().await;
             if let Some(last_capture) = *last_capture_guard {
                 let next_capture_time = last_capture + chrono::Duration::seconds(interval_secs as i64);
-                let now = Utc::now();
+                let now = clock::now();
                 let time_until_next = next_capture_time.signed_duration_since(now).num_seconds();
                 
                 if time_until_next > 0 {
@@ -325,7 +342,19 @@ async fn capture_and_upload_screenshot() -> anyhow::Result<()> {
     let employee_id = crate::storage::get_employee_id().await
         .map_err(|_| anyhow::anyhow!("No employee ID available"))?;
     
-    let taken_at = Utc::now();
+    if crate::sampling::power_state::is_battery_critical() {
+        return Err(anyhow::anyhow!("Battery critical - screenshot not captured"));
+    }
+    
+    if let Some(app) = crate::api::app_rules::screenshot_suppressed_by_focused_app().await {
+        return Err(anyhow::anyhow!("Screenshots are disabled while {} is focused - screenshot not captured", app));
+    }
+    
+    if super::screen_sharing::screenshots_paused().await {
+        return Err(anyhow::anyhow!("Screen is being shared - screenshot not captured"));
+    }
+    
+    let taken_at = clock::now();
     
     // Capture screenshot to temp file
     let screenshot_result = screen_capture::capture_screen_to_file().await?;
@@ -339,8 +368,23 @@ async fn capture_and_upload_screenshot() -> anyhow::Result<()> {
         file_path
     );
     
+    // In review mode the employee decides first; the retry queue uploads it afterwards
+    if let Some(deadline) = crate::screenshots::review::review_deadline(taken_at).await {
+        let queue_id = screenshot_queue::queue_screenshot_with_review(
+            &file_path,
+            &employee_id,
+            &device_id,
+            taken_at,
+            screenshot_result.scale_factor,
+            Some(deadline),
+        ).await?;
+        crate::screenshots::review::request_review(queue_id, taken_at, deadline);
+        log::info!("Screenshot {} held for review until {}", queue_id, deadline);
+        return Ok(());
+    }
+    
     // Queue for upload
-    let queue_id = queue_screenshot(&file_path, &employee_id, &device_id, taken_at).await?;
+    let queue_id = queue_screenshot(&file_path, &employee_id, &device_id, taken_at, screenshot_result.scale_factor).await?;
     
     // Try immediate upload
     match cloudinary_upload::upload_and_record_screenshot(
@@ -348,6 +392,7 @@ async fn capture_and_upload_screenshot() -> anyhow::Result<()> {
         &employee_id,
         &device_id,
         taken_at,
+        Some(screenshot_result.scale_factor),
         true, // is_auto
     ).await {
         Ok(screenshot_id) => {
@@ -382,38 +427,8 @@ async fn process_retry_queue() {
     log::info!("Processing {} pending screenshot uploads", pending.len());
     
     for queued in pending {
-        let file_path = std::path::Path::new(&queued.file_path);
-        
-        if !file_path.exists() {
-            log::warn!("Queued file no longer exists: {}", queued.file_path);
-            let _ = screenshot_queue::remove_from_queue(queued.id).await;
-            continue;
-        }
-        
-        match cloudinary_upload::upload_and_record_screenshot(
-            file_path,
-            &queued.employee_id,
-            &queued.device_id,
-            queued.taken_at,
-            true, // is_auto
-        ).await {
-            Ok(screenshot_id) => {
-                log::info!(
-                    "Retry upload successful: {} -> {}",
-                    queued.file_path,
-                    screenshot_id
-                );
-                let _ = mark_uploaded(queued.id).await;
-            }
-            Err(e) => {
-                log::warn!(
-                    "Retry upload failed (attempt {}): {} - {}",
-                    queued.retry_count + 1,
-                    queued.file_path,
-                    e
-                );
-                let _ = mark_upload_failed(queued.id).await;
-            }
+        if let Err(e) = upload_queued(&queued).await {
+            log::warn!("{}", e);
         }
         
         // Small delay between retries
@@ -421,6 +436,44 @@ async fn process_retry_queue() {
     }
 }
 
+/// Upload one queued screenshot and update the queue (retry queue and manual sync)
+pub(crate) async fn upload_queued(queued: &screenshot_queue::QueuedScreenshot) -> anyhow::Result<()> {
+    let file_path = std::path::Path::new(&queued.file_path);
+    
+    if !file_path.exists() {
+        let _ = screenshot_queue::remove_from_queue(queued.id).await;
+        return Err(anyhow::anyhow!("Queued file no longer exists: {}", queued.file_path));
+    }
+    
+    match cloudinary_upload::upload_and_record_screenshot(
+        file_path,
+        &queued.employee_id,
+        &queued.device_id,
+        queued.taken_at,
+        queued.scale_factor,
+        true, // is_auto
+    ).await {
+        Ok(screenshot_id) => {
+            log::info!(
+                "Retry upload successful: {} -> {}",
+                queued.file_path,
+                screenshot_id
+            );
+            let _ = mark_uploaded(queued.id).await;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = mark_upload_failed(queued.id).await;
+            Err(anyhow::anyhow!(
+                "Retry upload failed (attempt {}): {} - {}",
+                queued.retry_count + 1,
+                queued.file_path,
+                e
+            ))
+        }
+    }
+}
+
 /// Manually trigger a screenshot capture (for on-demand screenshots)
 #[allow(dead_code)]
 pub async fn take_manual_screenshot() -> anyhow::Result<String> {
@@ -429,7 +482,7 @@ pub async fn take_manual_screenshot() -> anyhow::Result<String> {
     let employee_id = crate::storage::get_employee_id().await
         .map_err(|_| anyhow::anyhow!("No employee ID available"))?;
     
-    let taken_at = Utc::now();
+    let taken_at = clock::now();
     
     // Capture screenshot
     let screenshot_result = screen_capture::capture_screen_to_file().await?;
@@ -440,6 +493,7 @@ pub async fn take_manual_screenshot() -> anyhow::Result<String> {
         &employee_id,
         &device_id,
         taken_at,
+        Some(screenshot_result.scale_factor),
         false, // not auto
     ).await?;
     
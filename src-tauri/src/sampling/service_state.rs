@@ -0,0 +1,150 @@
+//! Background service lifecycle
+//!
+//! `BackgroundServiceState` used to be a bag of running flags, so the UI
+//! couldn't tell whether tracking was stopped, paused by a remote command or
+//! missing a crashed service. The lifecycle is modelled explicitly here;
+//! every change is timestamped, kept in a short history and emitted to the
+//! frontend as a `service-state-changed` event.
+
+use chrono::{DateTime, Utc};
+use crate::utils::clock;
+use serde::Serialize;
+
+/// Transitions kept for `get_background_service_state`
+const MAX_TRANSITIONS: usize = 20;
+
+/// Why tracking is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    /// The employee paused from the UI
+    User,
+    /// An admin paused tracking with a remote command
+    RemoteCommand,
+    /// The employee snoozed tracking until a set time
+    Snooze,
+}
+
+impl PauseReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::RemoteCommand => "remote_command",
+            Self::Snooze => "snooze",
+        }
+    }
+
+    /// Parse a stored reason; unknown values count as a user pause
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "remote_command" => Self::RemoteCommand,
+            "snooze" => Self::Snooze,
+            _ => Self::User,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ServiceLifecycle {
+    Stopped,
+    Starting,
+    Running,
+    Paused { reason: PauseReason },
+    /// Running, but these services exited unexpectedly
+    Degraded { failing: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceTransition {
+    pub from: ServiceLifecycle,
+    pub to: ServiceLifecycle,
+    pub at: DateTime<Utc>,
+}
+
+/// Current lifecycle state plus recent transitions
+#[derive(Debug, Clone, Serialize)]
+pub struct LifecycleTracker {
+    pub lifecycle: ServiceLifecycle,
+    pub since: DateTime<Utc>,
+    pub transitions: Vec<ServiceTransition>,
+}
+
+impl LifecycleTracker {
+    pub fn new() -> Self {
+        Self {
+            lifecycle: ServiceLifecycle::Stopped,
+            since: clock::now(),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Move to `to`; returns the transition, or None if nothing changed
+    pub fn transition(&mut self, to: ServiceLifecycle, now: DateTime<Utc>) -> Option<ServiceTransition> {
+        if self.lifecycle == to {
+            return None;
+        }
+        let transition = ServiceTransition {
+            from: std::mem::replace(&mut self.lifecycle, to.clone()),
+            to,
+            at: now,
+        };
+        self.since = now;
+        self.transitions.push(transition.clone());
+        if self.transitions.len() > MAX_TRANSITIONS {
+            self.transitions.remove(0);
+        }
+        Some(transition)
+    }
+
+    /// A service exited (`running == false`) or came back; moves between
+    /// Running and Degraded. Ignored while stopped, starting or paused.
+    pub fn service_changed(&mut self, service: &str, running: bool, now: DateTime<Utc>) -> Option<ServiceTransition> {
+        let mut failing = match &self.lifecycle {
+            ServiceLifecycle::Running => Vec::new(),
+            ServiceLifecycle::Degraded { failing } => failing.clone(),
+            _ => return None,
+        };
+        if running {
+            failing.retain(|name| name != service);
+        } else if !failing.iter().any(|name| name == service) {
+            failing.push(service.to_string());
+        }
+
+        let to = if failing.is_empty() {
+            ServiceLifecycle::Running
+        } else {
+            ServiceLifecycle::Degraded { failing }
+        };
+        self.transition(to, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle_transitions_and_degradation() {
+        let mut tracker = LifecycleTracker::new();
+        let now = Utc::now();
+
+        // Exits before the services are up don't count
+        assert!(tracker.service_changed("heartbeat", false, now).is_none());
+
+        tracker.transition(ServiceLifecycle::Starting, now).unwrap();
+        tracker.transition(ServiceLifecycle::Running, now).unwrap();
+        assert!(tracker.transition(ServiceLifecycle::Running, now).is_none());
+
+        let degraded = tracker.service_changed("heartbeat", false, now).unwrap();
+        assert_eq!(degraded.to, ServiceLifecycle::Degraded { failing: vec!["heartbeat".to_string()] });
+        tracker.service_changed("app_focus", false, now).unwrap();
+        tracker.service_changed("heartbeat", true, now).unwrap();
+        let recovered = tracker.service_changed("app_focus", true, now).unwrap();
+        assert_eq!(recovered.to, ServiceLifecycle::Running);
+
+        tracker.transition(ServiceLifecycle::Paused { reason: PauseReason::RemoteCommand }, now).unwrap();
+        assert!(tracker.service_changed("heartbeat", false, now).is_none());
+        assert_eq!(tracker.transitions.len(), 7);
+    }
+}
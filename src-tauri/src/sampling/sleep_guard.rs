@@ -0,0 +1,263 @@
+//! Optional prevent-sleep while clocked in
+//!
+//! Long stretches without input (reading, watching a training video) let the
+//! machine go to sleep, and the wake-up gap is then recorded as idle sleep.
+//! The org policy can set a default and employees can choose their own mode:
+//!
+//! - `system`: keep the machine awake, the display may still turn off
+//! - `display`: keep the display on as well
+//!
+//! The assertion is held only while background services run for a work
+//! session (`SetThreadExecutionState` on Windows, `caffeinate` on macOS,
+//! `systemd-inhibit` on Linux). It is released while tracking is paused,
+//! taken again on resume and released at clock-out.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::api::employee_settings::PolicySettings;
+use crate::error::AgentError;
+use crate::storage::audit_log;
+
+/// `secure_store` setting holding the employee's choice
+const PREVENT_SLEEP_KEY: &str = "prevent_sleep";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PreventSleepMode {
+    #[default]
+    Off,
+    /// Prevent idle system sleep
+    System,
+    /// Prevent idle system sleep and display sleep
+    Display,
+}
+
+impl PreventSleepMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "off" => Some(PreventSleepMode::Off),
+            "system" => Some(PreventSleepMode::System),
+            "display" => Some(PreventSleepMode::Display),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            PreventSleepMode::Off => "off",
+            PreventSleepMode::System => "system",
+            PreventSleepMode::Display => "display",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreventSleepSetting {
+    /// Mode in effect
+    pub mode: PreventSleepMode,
+    /// The org default from the policy
+    pub policy_mode: PreventSleepMode,
+    /// The employee's own choice, if any
+    pub user_mode: Option<PreventSleepMode>,
+    /// Whether an assertion is held right now
+    pub active: bool,
+}
+
+/// The employee's choice wins over the org default
+fn effective_mode(policy_mode: PreventSleepMode, user_mode: Option<PreventSleepMode>) -> PreventSleepMode {
+    user_mode.unwrap_or(policy_mode)
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::PreventSleepMode;
+    use std::sync::mpsc;
+    use windows::Win32::System::Power::{
+        SetThreadExecutionState, ES_CONTINUOUS, ES_DISPLAY_REQUIRED, ES_SYSTEM_REQUIRED,
+    };
+
+    /// Execution state is per thread, so a dedicated thread holds it until
+    /// the sender is dropped
+    pub struct Assertion(#[allow(dead_code)] mpsc::Sender<()>);
+
+    pub fn acquire(mode: PreventSleepMode) -> Option<Assertion> {
+        let flags = match mode {
+            PreventSleepMode::Off => return None,
+            PreventSleepMode::System => ES_CONTINUOUS | ES_SYSTEM_REQUIRED,
+            PreventSleepMode::Display => ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED,
+        };
+        let (tx, rx) = mpsc::channel::<()>();
+        std::thread::Builder::new()
+            .name("prevent-sleep".into())
+            .spawn(move || unsafe {
+                SetThreadExecutionState(flags);
+                // Returns once the Assertion (sender) is dropped
+                let _ = rx.recv();
+                SetThreadExecutionState(ES_CONTINUOUS);
+            })
+            .map_err(|e| log::warn!("Failed to start prevent-sleep thread: {}", e))
+            .ok()?;
+        Some(Assertion(tx))
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use super::PreventSleepMode;
+    use std::process::{Child, Command, Stdio};
+
+    /// The helper process holding the assertion; killed on drop
+    pub struct Assertion(Child);
+
+    impl Drop for Assertion {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn command(mode: PreventSleepMode) -> Command {
+        let mut command = Command::new("caffeinate");
+        command.arg(if mode == PreventSleepMode::Display { "-di" } else { "-i" });
+        // Ends by itself should the agent die without releasing it
+        command.args(["-w", &std::process::id().to_string()]);
+        command
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn command(mode: PreventSleepMode) -> Command {
+        let what = if mode == PreventSleepMode::Display { "idle:sleep" } else { "sleep" };
+        let mut command = Command::new("systemd-inhibit");
+        command.args([&format!("--what={}", what), "--who=TrackEx", "--why=Active work session", "sleep", "infinity"]);
+        command
+    }
+
+    pub fn acquire(mode: PreventSleepMode) -> Option<Assertion> {
+        if mode == PreventSleepMode::Off {
+            return None;
+        }
+        command(mode)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map(Assertion)
+            .map_err(|e| log::warn!("Failed to prevent sleep: {}", e))
+            .ok()
+    }
+}
+
+/// The held assertion and its mode
+static ASSERTION: Mutex<Option<(PreventSleepMode, platform::Assertion)>> = Mutex::new(None);
+
+fn apply(mode: PreventSleepMode) {
+    let mut assertion = match ASSERTION.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    if assertion.as_ref().map(|(held, _)| *held) == Some(mode) {
+        return;
+    }
+    // Drop the old assertion before taking one with a different mode
+    if assertion.take().is_some() {
+        log::info!("Sleep allowed again");
+    }
+    if let Some(held) = platform::acquire(mode) {
+        log::info!("Preventing sleep during the work session (mode: {})", mode.as_str());
+        *assertion = Some((mode, held));
+    }
+}
+
+fn is_active() -> bool {
+    match ASSERTION.lock() {
+        Ok(guard) => guard.is_some(),
+        Err(e) => e.into_inner().is_some(),
+    }
+}
+
+async fn user_mode() -> Option<PreventSleepMode> {
+    match crate::storage::secure_store::get_setting(PREVENT_SLEEP_KEY).await {
+        // Cleared choices are stored as empty strings
+        Ok(value) => value.as_deref().and_then(PreventSleepMode::parse),
+        Err(e) => {
+            log::warn!("Failed to read prevent-sleep setting: {}", e);
+            None
+        }
+    }
+}
+
+/// Take or release the assertion for the current session state and `policy`
+pub async fn refresh_with(policy: &PolicySettings) {
+    let mode = if super::should_services_run().await {
+        effective_mode(policy.prevent_sleep, user_mode().await)
+    } else {
+        PreventSleepMode::Off
+    };
+    apply(mode);
+}
+
+/// Take or release the assertion for the current session state
+pub async fn refresh() {
+    let policy = crate::api::employee_settings::get_policy_settings().await;
+    refresh_with(&policy).await;
+}
+
+/// Release the assertion (clock-out, shutdown)
+pub fn release() {
+    apply(PreventSleepMode::Off);
+}
+
+#[tauri::command]
+pub async fn get_prevent_sleep() -> Result<PreventSleepSetting, AgentError> {
+    let policy_mode = crate::api::employee_settings::get_policy_settings().await.prevent_sleep;
+    let user_mode = user_mode().await;
+    Ok(PreventSleepSetting {
+        mode: effective_mode(policy_mode, user_mode),
+        policy_mode,
+        user_mode,
+        active: is_active(),
+    })
+}
+
+/// Choose a prevent-sleep mode ("off", "system", "display"), or go back to
+/// the org default with `None`
+#[tauri::command]
+pub async fn set_prevent_sleep(mode: Option<String>) -> Result<PreventSleepSetting, AgentError> {
+    let result = set_prevent_sleep_impl(mode).await;
+    audit_log::record("set_prevent_sleep", &result);
+    result
+}
+
+async fn set_prevent_sleep_impl(mode: Option<String>) -> Result<PreventSleepSetting, AgentError> {
+    let mode = match mode.as_deref() {
+        Some(value) => Some(
+            PreventSleepMode::parse(value)
+                .ok_or_else(|| AgentError::Internal(format!("Unknown prevent-sleep mode: {}", value)))?,
+        ),
+        None => None,
+    };
+    crate::storage::secure_store::store_setting(PREVENT_SLEEP_KEY, mode.map(PreventSleepMode::as_str).unwrap_or(""))
+        .await
+        .map_err(|e| AgentError::Storage(format!("Failed to save prevent-sleep setting: {}", e)))?;
+
+    refresh().await;
+    get_prevent_sleep().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_choice_overrides_policy() {
+        assert_eq!(effective_mode(PreventSleepMode::Off, None), PreventSleepMode::Off);
+        assert_eq!(effective_mode(PreventSleepMode::System, None), PreventSleepMode::System);
+        assert_eq!(effective_mode(PreventSleepMode::System, Some(PreventSleepMode::Off)), PreventSleepMode::Off);
+        assert_eq!(effective_mode(PreventSleepMode::Off, Some(PreventSleepMode::Display)), PreventSleepMode::Display);
+        assert_eq!(PreventSleepMode::parse("display"), Some(PreventSleepMode::Display));
+        assert_eq!(PreventSleepMode::parse(""), None);
+    }
+}
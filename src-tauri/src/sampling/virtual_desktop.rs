@@ -0,0 +1,85 @@
+//! Virtual desktop (Windows) / Space (macOS) of the focused window
+//!
+//! Some employees keep work and personal apps on separate desktops. The
+//! identifier of the desktop the focused window is on is stored with every
+//! app_usage segment so usage can be analysed per context. Identifiers are
+//! opaque: a GUID on Windows, the Space ID on macOS and the desktop index on
+//! Linux (EWMH). Desktop names are never read.
+
+/// Desktop the foreground window is on, if it can be determined
+#[cfg(target_os = "windows")]
+pub fn current_desktop() -> Option<String> {
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+    use windows::Win32::UI::Shell::{IVirtualDesktopManager, VirtualDesktopManager};
+    use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+    thread_local! {
+        // Sampling runs on tokio workers; one manager per thread keeps COM happy
+        static MANAGER: Option<IVirtualDesktopManager> = unsafe {
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            CoCreateInstance(&VirtualDesktopManager, None, CLSCTX_ALL)
+                .map_err(|e| log::debug!("Virtual desktop manager unavailable: {:?}", e))
+                .ok()
+        };
+    }
+
+    MANAGER.with(|manager| {
+        let manager = manager.as_ref()?;
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0.is_null() {
+                return None;
+            }
+            let id = manager.GetWindowDesktopId(hwnd).ok()?;
+            // Windows that aren't on any desktop (shell surfaces) report GUID_NULL
+            (id != windows::core::GUID::zeroed()).then(|| format!("{:?}", id))
+        }
+    })
+}
+
+/// ID of the active Space; the focused window is always on it
+#[cfg(target_os = "macos")]
+pub fn current_desktop() -> Option<String> {
+    // Private but stable SkyLight calls, re-exported by CoreGraphics
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGSMainConnectionID() -> i32;
+        fn CGSGetActiveSpace(connection: i32) -> u64;
+    }
+
+    let space = unsafe { CGSGetActiveSpace(CGSMainConnectionID()) };
+    (space != 0).then(|| space.to_string())
+}
+
+/// `_NET_CURRENT_DESKTOP` of the X11 root window
+#[cfg(target_os = "linux")]
+pub fn current_desktop() -> Option<String> {
+    let output = std::process::Command::new("xprop")
+        .args(["-root", "_NET_CURRENT_DESKTOP"])
+        .output()
+        .ok()?;
+    parse_xprop_desktop(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn current_desktop() -> Option<String> {
+    None
+}
+
+/// `_NET_CURRENT_DESKTOP(CARDINAL) = 1`
+#[cfg(any(target_os = "linux", test))]
+fn parse_xprop_desktop(output: &str) -> Option<String> {
+    let index: u32 = output.split('=').nth(1)?.trim().parse().ok()?;
+    Some(index.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xprop_desktop() {
+        assert_eq!(parse_xprop_desktop("_NET_CURRENT_DESKTOP(CARDINAL) = 2\n").as_deref(), Some("2"));
+        assert_eq!(parse_xprop_desktop("_NET_CURRENT_DESKTOP:  not found.\n"), None);
+    }
+}
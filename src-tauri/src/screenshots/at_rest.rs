@@ -0,0 +1,133 @@
+//! Encryption of screenshots waiting in the temp folder
+//!
+//! Captured screenshots sit in `screenshots_temp` until they are uploaded,
+//! which can be hours when offline or in review mode. They are written
+//! encrypted (ChaCha20-Poly1305) so another process or a disk image can't
+//! read them, and only decrypted in memory for redaction, OCR and upload.
+//!
+//! The key lives in the OS keychain so queued files survive a restart. Where
+//! secure storage isn't available a per-process key is used instead; files
+//! from a previous run then fail to decrypt and are dropped after their
+//! upload retries.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Keychain setting holding the base64 key
+const KEY_SETTING: &str = "screenshot_temp_key";
+
+/// Prefix marking an encrypted file; files without it predate encryption
+const MAGIC: &[u8; 4] = b"TXS1";
+
+const NONCE_LEN: usize = 12;
+
+static KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+fn random_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    key
+}
+
+/// Load the key from the keychain, creating it on first run. Called at
+/// startup, before the first capture; until then a per-process key is used.
+pub async fn load_key() {
+    let stored = match crate::storage::secure_store::get_setting(KEY_SETTING).await {
+        Ok(value) => value.and_then(|encoded| STANDARD.decode(encoded).ok()),
+        Err(e) => {
+            log::warn!("Failed to read screenshot key from secure storage: {}", e);
+            None
+        }
+    };
+
+    let key = match stored.and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) {
+        Some(key) => key,
+        None => {
+            let key = random_key();
+            if let Err(e) = crate::storage::secure_store::store_setting(KEY_SETTING, &STANDARD.encode(key)).await {
+                log::warn!("Failed to store screenshot key, queued screenshots won't survive a restart: {}", e);
+            }
+            key
+        }
+    };
+
+    if KEY.set(key).is_err() {
+        log::warn!("Screenshot key already in use, keeping the per-process key");
+    }
+}
+
+fn cipher() -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(Key::from_slice(KEY.get_or_init(random_key)))
+}
+
+fn seal(plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher()
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow!("Failed to encrypt screenshot"))?;
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open(data: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(body) = data.strip_prefix(MAGIC) else {
+        // Queued before encryption was introduced
+        return Ok(data);
+    };
+    if body.len() < NONCE_LEN {
+        return Err(anyhow!("Encrypted screenshot is truncated"));
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    cipher()
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt screenshot (written with a different key?)"))
+}
+
+/// Encrypt `jpeg` and write it to `path`
+pub fn write(path: &Path, jpeg: &[u8]) -> Result<()> {
+    std::fs::write(path, seal(jpeg)?)?;
+    Ok(())
+}
+
+/// Read and decrypt the screenshot at `path`
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    open(std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_hides_plaintext() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shot.enc");
+        let jpeg = b"\xFF\xD8\xFF\xE0 not really a jpeg".to_vec();
+
+        write(&path, &jpeg).unwrap();
+        let on_disk = std::fs::read(&path).unwrap();
+        assert!(on_disk.starts_with(MAGIC));
+        assert!(!on_disk.windows(jpeg.len()).any(|window| window == jpeg.as_slice()));
+        assert_eq!(read(&path).unwrap(), jpeg);
+
+        // Tampering is detected rather than returning garbage
+        let mut tampered = on_disk.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, tampered).unwrap();
+        assert!(read(&path).is_err());
+
+        // Files queued before encryption still read back
+        std::fs::write(&path, &jpeg).unwrap();
+        assert_eq!(read(&path).unwrap(), jpeg);
+    }
+}
@@ -0,0 +1,86 @@
+//! Pixel conversion and in-memory JPEG encoding for native captures
+//!
+//! The OS capture APIs hand back 32-bit pixels in various channel orders and
+//! with rows padded for alignment. These helpers pack them into RGB and encode
+//! straight into a buffer, so captures never touch disk before the queue does.
+
+use anyhow::{anyhow, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::RgbImage;
+
+/// JPEG quality used for all screenshots (good balance of quality and size)
+pub const JPEG_QUALITY: u8 = 75;
+
+/// A screenshot encoded in memory by a native capture path
+#[derive(Debug)]
+pub struct CapturedFrame {
+    pub jpeg: Vec<u8>,
+    /// Physical pixels
+    pub width: u32,
+    pub height: u32,
+    /// Physical pixels per logical pixel of the captured display
+    pub scale_factor: f64,
+}
+
+/// Byte offsets of the red, green and blue channels within a 32-bit pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelLayout {
+    pub red: usize,
+    pub green: usize,
+    pub blue: usize,
+}
+
+impl PixelLayout {
+    pub const BGRA: Self = Self { red: 2, green: 1, blue: 0 };
+    pub const RGBA: Self = Self { red: 0, green: 1, blue: 2 };
+    pub const ARGB: Self = Self { red: 1, green: 2, blue: 3 };
+    pub const ABGR: Self = Self { red: 3, green: 2, blue: 1 };
+}
+
+/// Pack 32-bit pixels (rows may be padded to `bytes_per_row`) into an RGB image
+pub fn to_rgb(data: &[u8], width: u32, height: u32, bytes_per_row: usize, layout: PixelLayout) -> Result<RgbImage> {
+    let (width_px, height_px) = (width as usize, height as usize);
+    if bytes_per_row < width_px * 4 || data.len() < bytes_per_row * height_px.saturating_sub(1) + width_px * 4 {
+        return Err(anyhow!(
+            "Pixel buffer too small for {}x{} ({} bytes, {} per row)",
+            width,
+            height,
+            data.len(),
+            bytes_per_row
+        ));
+    }
+
+    let mut rgb = Vec::with_capacity(width_px * height_px * 3);
+    for row in data.chunks(bytes_per_row).take(height_px) {
+        for pixel in row[..width_px * 4].chunks_exact(4) {
+            rgb.extend_from_slice(&[pixel[layout.red], pixel[layout.green], pixel[layout.blue]]);
+        }
+    }
+    RgbImage::from_raw(width, height, rgb).ok_or_else(|| anyhow!("Failed to create image from pixel data"))
+}
+
+/// Encode an image as JPEG into memory
+pub fn encode_jpeg(image: &RgbImage, quality: u8) -> Result<Vec<u8>> {
+    let mut jpeg_data = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg_data, quality).encode_image(image)?;
+    Ok(jpeg_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_rgb_skips_row_padding() {
+        // 2x2 BGRA image with 4 bytes of padding per row
+        let data = [
+            0, 0, 255, 255, 0, 255, 0, 255, 9, 9, 9, 9, //
+            255, 0, 0, 255, 1, 2, 3, 255, 9, 9, 9, 9,
+        ];
+        let image = to_rgb(&data, 2, 2, 12, PixelLayout::BGRA).unwrap();
+        assert_eq!(image.as_raw(), &vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 3, 2, 1]);
+
+        assert!(to_rgb(&data, 3, 2, 12, PixelLayout::BGRA).is_err());
+        assert!(encode_jpeg(&image, JPEG_QUALITY).unwrap().starts_with(&[0xFF, 0xD8]));
+    }
+}
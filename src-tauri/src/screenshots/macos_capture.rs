@@ -0,0 +1,247 @@
+//! In-process screen capture on macOS
+//!
+//! On macOS 14+ the main display is captured with ScreenCaptureKit's
+//! `SCScreenshotManager`; on 12.6 and 13 with `CGDisplayCreateImage`. Either
+//! way the resulting CGImage is converted and JPEG-encoded in memory, inside
+//! an autorelease pool since the capture runs on a blocking worker thread
+//! that has none. Completion handlers that fire after a timeout release
+//! what they were given instead of leaking it. This
+//! replaces the `screencapture` CLI, which cost a process spawn, a temp file
+//! and a decode round trip per screenshot.
+
+use anyhow::{anyhow, Result};
+use block::ConcreteBlock;
+use objc::runtime::{Class, Object, NO, YES};
+use objc::{class, msg_send, sel, sel_impl};
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_char;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::encode::{self, CapturedFrame, PixelLayout};
+
+/// How long to wait for a ScreenCaptureKit completion handler
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type CGImageRef = *const c_void;
+type CFDataRef = *const c_void;
+
+/// kCGBitmapAlphaInfoMask
+const ALPHA_INFO_MASK: u32 = 0x1F;
+/// kCGBitmapByteOrderMask
+const BYTE_ORDER_MASK: u32 = 0x7000;
+/// kCGBitmapByteOrder32Little
+const BYTE_ORDER_32_LITTLE: u32 = 2 << 12;
+/// kCGImageAlphaPremultipliedFirst, kCGImageAlphaFirst, kCGImageAlphaNoneSkipFirst
+const ALPHA_FIRST: [u32; 3] = [2, 4, 6];
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGMainDisplayID() -> u32;
+    fn CGDisplayPixelsWide(display: u32) -> usize;
+    fn CGDisplayCreateImage(display: u32) -> CGImageRef;
+    fn CGImageGetWidth(image: CGImageRef) -> usize;
+    fn CGImageGetHeight(image: CGImageRef) -> usize;
+    fn CGImageGetBitsPerPixel(image: CGImageRef) -> usize;
+    fn CGImageGetBytesPerRow(image: CGImageRef) -> usize;
+    fn CGImageGetBitmapInfo(image: CGImageRef) -> u32;
+    fn CGImageGetDataProvider(image: CGImageRef) -> *const c_void;
+    fn CGDataProviderCopyData(provider: *const c_void) -> CFDataRef;
+    fn CGImageRetain(image: CGImageRef) -> CGImageRef;
+    fn CGImageRelease(image: CGImageRef);
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFDataGetLength(data: CFDataRef) -> isize;
+    fn CFDataGetBytePtr(data: CFDataRef) -> *const u8;
+    fn CFRelease(cf: *const c_void);
+}
+
+// Linked so the ScreenCaptureKit classes can be looked up at runtime
+#[link(name = "ScreenCaptureKit", kind = "framework")]
+extern "C" {}
+
+/// Capture the main display as JPEG (blocking; call from a blocking task)
+pub fn capture_main_display() -> Result<CapturedFrame> {
+    objc::rc::autoreleasepool(|| {
+        let image = match unsafe { capture_with_screencapturekit() } {
+            Ok(Some(image)) => image,
+            Ok(None) => unsafe { capture_with_coregraphics()? },
+            Err(e) => {
+                log::warn!("ScreenCaptureKit capture failed, falling back to CoreGraphics: {}", e);
+                unsafe { capture_with_coregraphics()? }
+            }
+        };
+        let result = unsafe { encode_cgimage(image) };
+        unsafe { CGImageRelease(image) };
+        result
+    })
+}
+
+unsafe fn capture_with_coregraphics() -> Result<CGImageRef> {
+    let image = CGDisplayCreateImage(CGMainDisplayID());
+    if image.is_null() {
+        Err(anyhow!("CGDisplayCreateImage returned no image"))
+    } else {
+        Ok(image)
+    }
+}
+
+/// `Ok(None)` when SCScreenshotManager isn't available (before macOS 14)
+unsafe fn capture_with_screencapturekit() -> Result<Option<CGImageRef>> {
+    let Some(manager) = Class::get("SCScreenshotManager") else {
+        return Ok(None);
+    };
+    let content = shareable_content()?;
+    let result = capture_display(manager, content);
+    let _: () = msg_send![content, release];
+    result.map(Some)
+}
+
+unsafe fn error_description(error: *mut Object) -> String {
+    if error.is_null() {
+        return "unknown error".to_string();
+    }
+    let description: *mut Object = msg_send![error, localizedDescription];
+    let utf8: *const c_char = msg_send![description, UTF8String];
+    if utf8.is_null() {
+        return "unknown error".to_string();
+    }
+    CStr::from_ptr(utf8).to_string_lossy().into_owned()
+}
+
+/// Wait for a completion handler's result (raw pointers travel as `usize`)
+fn wait_for(rx: mpsc::Receiver<Result<usize, String>>, what: &str) -> Result<usize> {
+    rx.recv_timeout(CAPTURE_TIMEOUT)
+        .map_err(|_| anyhow!("Timed out waiting for {}", what))?
+        .map_err(|e| anyhow!("Failed to get {}: {}", what, e))
+}
+
+/// Retained `SCShareableContent` for on-screen content
+unsafe fn shareable_content() -> Result<*mut Object> {
+    let (tx, rx) = mpsc::channel();
+    let handler = ConcreteBlock::new(move |content: *mut Object, error: *mut Object| {
+        let result = if content.is_null() {
+            Err(unsafe { error_description(error) })
+        } else {
+            let _: *mut Object = unsafe { msg_send![content, retain] };
+            Ok(content as usize)
+        };
+        // Nobody is waiting any more (timed out): give the content back
+        if let Err(mpsc::SendError(Ok(content))) = tx.send(result) {
+            let _: () = unsafe { msg_send![content as *mut Object, release] };
+        }
+    })
+    .copy();
+    let _: () = msg_send![
+        class!(SCShareableContent),
+        getShareableContentExcludingDesktopWindows: NO
+        onScreenWindowsOnly: YES
+        completionHandler: &*handler
+    ];
+    Ok(wait_for(rx, "shareable content")? as *mut Object)
+}
+
+unsafe fn capture_display(manager: &Class, content: *mut Object) -> Result<CGImageRef> {
+    let displays: *mut Object = msg_send![content, displays];
+    let count: usize = msg_send![displays, count];
+    let main_display_id = CGMainDisplayID();
+    let display = (0..count)
+        .map(|i| -> *mut Object { msg_send![displays, objectAtIndex: i] })
+        .find(|&display| {
+            let id: u32 = msg_send![display, displayID];
+            id == main_display_id
+        })
+        .ok_or_else(|| anyhow!("Main display not found in shareable content"))?;
+
+    let no_windows: *mut Object = msg_send![class!(NSArray), array];
+    let filter: *mut Object = msg_send![class!(SCContentFilter), alloc];
+    let filter: *mut Object = msg_send![filter, initWithDisplay: display excludingWindows: no_windows];
+
+    // SCDisplay reports points; capture at the native pixel resolution
+    let scale: f32 = msg_send![filter, pointPixelScale];
+    let width: isize = msg_send![display, width];
+    let height: isize = msg_send![display, height];
+    let config: *mut Object = msg_send![class!(SCStreamConfiguration), new];
+    let _: () = msg_send![config, setWidth: (width as f32 * scale) as usize];
+    let _: () = msg_send![config, setHeight: (height as f32 * scale) as usize];
+    let _: () = msg_send![config, setShowsCursor: YES];
+
+    let (tx, rx) = mpsc::channel();
+    let handler = ConcreteBlock::new(move |image: CGImageRef, error: *mut Object| {
+        let result = if image.is_null() {
+            Err(unsafe { error_description(error) })
+        } else {
+            Ok(unsafe { CGImageRetain(image) } as usize)
+        };
+        if let Err(mpsc::SendError(Ok(image))) = tx.send(result) {
+            unsafe { CGImageRelease(image as CGImageRef) };
+        }
+    })
+    .copy();
+    let _: () = msg_send![manager, captureImageWithFilter: filter configuration: config completionHandler: &*handler];
+    let result = wait_for(rx, "screenshot");
+
+    let _: () = msg_send![filter, release];
+    let _: () = msg_send![config, release];
+    Ok(result? as CGImageRef)
+}
+
+/// Channel order of a 32-bit CGImage from its bitmap info
+fn layout_for(bitmap_info: u32) -> PixelLayout {
+    let alpha_first = ALPHA_FIRST.contains(&(bitmap_info & ALPHA_INFO_MASK));
+    let little_endian = bitmap_info & BYTE_ORDER_MASK == BYTE_ORDER_32_LITTLE;
+    match (little_endian, alpha_first) {
+        (true, true) => PixelLayout::BGRA,
+        (true, false) => PixelLayout::ABGR,
+        (false, true) => PixelLayout::ARGB,
+        (false, false) => PixelLayout::RGBA,
+    }
+}
+
+unsafe fn encode_cgimage(image: CGImageRef) -> Result<CapturedFrame> {
+    let width = CGImageGetWidth(image) as u32;
+    let height = CGImageGetHeight(image) as u32;
+    let bits_per_pixel = CGImageGetBitsPerPixel(image);
+    if bits_per_pixel != 32 {
+        return Err(anyhow!("Unsupported screenshot pixel format ({} bits per pixel)", bits_per_pixel));
+    }
+
+    let data = CGDataProviderCopyData(CGImageGetDataProvider(image));
+    if data.is_null() {
+        return Err(anyhow!("Failed to read screenshot pixels"));
+    }
+    // CGDisplayPixelsWide reports points, so this is the Retina scale
+    let points_wide = CGDisplayPixelsWide(CGMainDisplayID()).max(1);
+    let pixels = std::slice::from_raw_parts(CFDataGetBytePtr(data), CFDataGetLength(data) as usize);
+    let rgb = encode::to_rgb(
+        pixels,
+        width,
+        height,
+        CGImageGetBytesPerRow(image),
+        layout_for(CGImageGetBitmapInfo(image)),
+    );
+    CFRelease(data);
+
+    Ok(CapturedFrame {
+        jpeg: encode::encode_jpeg(&rgb?, encode::JPEG_QUALITY)?,
+        width,
+        height,
+        scale_factor: width as f64 / points_wide as f64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_for_bitmap_info() {
+        // What CGDisplayCreateImage and ScreenCaptureKit return (BGRA in memory)
+        assert_eq!(layout_for(BYTE_ORDER_32_LITTLE | 2), PixelLayout::BGRA);
+        assert_eq!(layout_for(BYTE_ORDER_32_LITTLE | 1), PixelLayout::ABGR);
+        assert_eq!(layout_for(6), PixelLayout::ARGB);
+        assert_eq!(layout_for(5), PixelLayout::RGBA);
+    }
+}
@@ -1,4 +1,15 @@
 // Screenshots module - simplified for production testing
 
 pub mod screen_capture;
-pub mod permissions;
\ No newline at end of file
+pub mod at_rest;
+pub mod permissions;
+pub mod encode;
+pub mod ocr;
+pub mod redaction;
+pub mod review;
+
+#[cfg(target_os = "macos")]
+pub mod macos_capture;
+
+#[cfg(target_os = "windows")]
+pub mod windows_capture;
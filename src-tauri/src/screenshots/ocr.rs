@@ -0,0 +1,105 @@
+//! Local OCR through the `tesseract` CLI
+//!
+//! Screenshots are piped to tesseract over stdin and its TSV output parsed
+//! into words with bounding boxes; nothing is sent anywhere. Used for
+//! redaction before upload and for the opt-in local activity index.
+
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Where tesseract is usually installed when it isn't on PATH (GUI apps on
+/// macOS don't inherit the shell's PATH)
+const TESSERACT_LOCATIONS: &[&str] = &[
+    "/opt/homebrew/bin/tesseract",
+    "/usr/local/bin/tesseract",
+    "/usr/bin/tesseract",
+    "C:\\Program Files\\Tesseract-OCR\\tesseract.exe",
+];
+
+/// A recognised word and its bounding box in image pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    /// (block, paragraph, line) the word belongs to
+    pub line: (u32, u32, u32),
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+    pub text: String,
+}
+
+/// Parse tesseract's TSV output, keeping word-level rows with text
+pub fn parse_tsv(tsv: &str) -> Vec<Word> {
+    tsv.lines()
+        .skip(1)
+        .filter_map(|row| {
+            let columns: Vec<&str> = row.split('\t').collect();
+            if columns.len() < 12 || columns[0] != "5" {
+                return None;
+            }
+            let number = |i: usize| columns[i].parse::<u32>().ok();
+            let text = columns[11].trim();
+            if text.is_empty() {
+                return None;
+            }
+            Some(Word {
+                line: (number(2)?, number(3)?, number(4)?),
+                left: number(6)?,
+                top: number(7)?,
+                width: number(8)?,
+                height: number(9)?,
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn tesseract_binary() -> PathBuf {
+    TESSERACT_LOCATIONS
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from("tesseract"))
+}
+
+/// Run OCR on an encoded image, returning tesseract's TSV output
+fn run_tesseract(image_data: &[u8]) -> Result<String> {
+    let mut command = Command::new(tesseract_binary());
+    command
+        .args(["stdin", "stdout", "tsv"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW: don't flash a console on every screenshot
+        command.creation_flags(0x0800_0000);
+    }
+
+    let mut child = command.spawn().map_err(|e| anyhow!("OCR unavailable (tesseract not found): {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(image_data)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!("tesseract exited with {}", output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Recognise the words in an encoded image
+pub fn recognize_words(image_data: &[u8]) -> Result<Vec<Word>> {
+    Ok(parse_tsv(&run_tesseract(image_data)?))
+}
+
+/// Recognised text, one line per OCR line
+pub fn plain_text(words: &[Word]) -> String {
+    words
+        .chunk_by(|a, b| a.line == b.line)
+        .map(|line| line.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,235 @@
+//! Sensitive-data redaction for screenshots
+//!
+//! When the org policy enables it, every screenshot captured for upload goes
+//! through a local OCR pass (see `ocr`) before it is queued. Text matching
+//! the enabled patterns is blurred in place:
+//!
+//! - card numbers (13-19 digits, optionally grouped, passing the Luhn check)
+//! - email addresses
+//! - US social security numbers (`123-45-6789`)
+//!
+//! Nothing leaves the machine for OCR. If redaction is required but OCR fails
+//! (e.g. tesseract isn't installed) the screenshot is withheld rather than
+//! uploaded unredacted.
+
+use anyhow::{anyhow, Result};
+use image::{imageops, RgbImage};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::encode;
+use super::ocr::{self, Word};
+use super::screen_capture::ScreenshotResult;
+
+/// Extra pixels blurred around each match so glyph edges don't survive
+const REGION_PADDING: u32 = 4;
+
+lazy_static::lazy_static! {
+    static ref CARD_NUMBER: Regex = Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap();
+    static ref EMAIL: Regex = Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap();
+    static ref SSN: Regex = Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap();
+}
+
+/// Which patterns to redact, from the org policy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RedactionPolicy {
+    /// Run the OCR pass at all
+    pub enabled: bool,
+    pub credit_cards: bool,
+    pub emails: bool,
+    pub ssns: bool,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        // Off until the org opts in; once on, every pattern unless turned off
+        Self {
+            enabled: false,
+            credit_cards: true,
+            emails: true,
+            ssns: true,
+        }
+    }
+}
+
+impl RedactionPolicy {
+    pub fn is_active(&self) -> bool {
+        self.enabled && (self.credit_cards || self.emails || self.ssns)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Region {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+}
+
+fn passes_luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match i % 2 {
+            1 if digit * 2 > 9 => digit * 2 - 9,
+            1 => digit * 2,
+            _ => digit,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+/// Byte spans of `text` that match an enabled pattern
+fn sensitive_spans(text: &str, policy: &RedactionPolicy) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    if policy.credit_cards {
+        spans.extend(CARD_NUMBER.find_iter(text).filter_map(|m| {
+            let digits: Vec<u32> = m.as_str().chars().filter_map(|c| c.to_digit(10)).collect();
+            passes_luhn(&digits).then_some((m.start(), m.end()))
+        }));
+    }
+    if policy.emails {
+        spans.extend(EMAIL.find_iter(text).map(|m| (m.start(), m.end())));
+    }
+    if policy.ssns {
+        spans.extend(SSN.find_iter(text).map(|m| (m.start(), m.end())));
+    }
+    spans
+}
+
+/// Regions to blur. Words are matched per line, so numbers OCR splits into
+/// groups ("4111 1111 ...") are still recognised.
+fn sensitive_regions(words: &[Word], policy: &RedactionPolicy) -> Vec<Region> {
+    let mut regions = Vec::new();
+    for line in words.chunk_by(|a, b| a.line == b.line) {
+        let mut text = String::new();
+        let mut offsets = Vec::with_capacity(line.len());
+        for word in line {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            offsets.push((text.len(), text.len() + word.text.len()));
+            text.push_str(&word.text);
+        }
+
+        for (start, end) in sensitive_spans(&text, policy) {
+            let region = line
+                .iter()
+                .zip(&offsets)
+                .filter(|(_, offset)| offset.0 < end && start < offset.1)
+                .map(|(word, _)| Region {
+                    left: word.left,
+                    top: word.top,
+                    right: word.left + word.width,
+                    bottom: word.top + word.height,
+                })
+                .reduce(|a, b| Region {
+                    left: a.left.min(b.left),
+                    top: a.top.min(b.top),
+                    right: a.right.max(b.right),
+                    bottom: a.bottom.max(b.bottom),
+                });
+            regions.extend(region);
+        }
+    }
+    regions
+}
+
+fn blur_region(image: &mut RgbImage, region: &Region) {
+    let left = region.left.saturating_sub(REGION_PADDING);
+    let top = region.top.saturating_sub(REGION_PADDING);
+    let right = (region.right + REGION_PADDING).min(image.width());
+    let bottom = (region.bottom + REGION_PADDING).min(image.height());
+    if right <= left || bottom <= top {
+        return;
+    }
+    let (width, height) = (right - left, bottom - top);
+
+    let patch = imageops::crop_imm(image, left, top, width, height).to_image();
+    // Strong enough that the text can't be read back, relative to its size
+    let blurred = imageops::blur(&patch, (height as f32 / 2.0).max(8.0));
+    imageops::replace(image, &blurred, left as i64, top as i64);
+}
+
+/// Blur sensitive text in the JPEG at `path`, returning the number of regions
+fn redact_file(path: &Path, policy: &RedactionPolicy) -> Result<usize> {
+    let jpeg = super::at_rest::read(path)?;
+    let regions = sensitive_regions(&ocr::recognize_words(&jpeg)?, policy);
+    if regions.is_empty() {
+        return Ok(0);
+    }
+
+    let mut image = image::load_from_memory(&jpeg)?.to_rgb8();
+    for region in &regions {
+        blur_region(&mut image, region);
+    }
+    super::at_rest::write(path, &encode::encode_jpeg(&image, encode::JPEG_QUALITY)?)?;
+    Ok(regions.len())
+}
+
+/// Apply the policy's redaction to a captured screenshot. The file is
+/// deleted and an error returned if redaction is required but fails.
+pub async fn redact_screenshot(mut screenshot: ScreenshotResult) -> Result<ScreenshotResult> {
+    let policy = crate::api::employee_settings::get_policy_settings().await.screenshot_redaction;
+    if !policy.is_active() {
+        return Ok(screenshot);
+    }
+
+    let path = screenshot.file_path.clone();
+    match tokio::task::spawn_blocking(move || redact_file(&path, &policy)).await? {
+        Ok(regions) => {
+            if regions > 0 {
+                log::info!("Redacted {} sensitive region(s) from screenshot", regions);
+                screenshot.bytes = std::fs::metadata(&screenshot.file_path)?.len() as usize;
+            }
+            Ok(screenshot)
+        }
+        Err(e) => {
+            if let Err(remove_error) = std::fs::remove_file(&screenshot.file_path) {
+                log::warn!("Failed to delete unredacted screenshot: {}", remove_error);
+            }
+            Err(anyhow!("Screenshot withheld because redaction failed: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TSV: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+4\t1\t1\t1\t1\t0\t10\t10\t400\t20\t-1\t
+5\t1\t1\t1\t1\t1\t10\t10\t40\t20\t96\tCard:
+5\t1\t1\t1\t1\t2\t60\t10\t40\t20\t95\t4111
+5\t1\t1\t1\t1\t3\t110\t10\t40\t20\t95\t1111
+5\t1\t1\t1\t1\t4\t160\t10\t40\t20\t95\t1111
+5\t1\t1\t1\t1\t5\t210\t10\t40\t20\t95\t1111
+5\t1\t1\t1\t2\t1\t10\t40\t200\t20\t93\tjane.doe@example.com
+5\t1\t1\t1\t3\t1\t10\t70\t100\t20\t91\t123-45-6789
+5\t1\t1\t1\t4\t1\t10\t100\t160\t20\t92\t1234567812345678";
+
+    #[test]
+    fn test_sensitive_regions_follow_policy() {
+        let words = ocr::parse_tsv(TSV);
+        assert_eq!(words.len(), 8);
+
+        let all = RedactionPolicy { enabled: true, ..RedactionPolicy::default() };
+        let regions = sensitive_regions(&words, &all);
+        // The grouped card number is one region; the non-Luhn number is kept
+        assert_eq!(
+            regions,
+            vec![
+                Region { left: 60, top: 10, right: 250, bottom: 30 },
+                Region { left: 10, top: 40, right: 210, bottom: 60 },
+                Region { left: 10, top: 70, right: 110, bottom: 90 },
+            ]
+        );
+
+        let emails_only = RedactionPolicy { credit_cards: false, ssns: false, ..all.clone() };
+        assert_eq!(sensitive_regions(&words, &emails_only).len(), 1);
+        assert!(!RedactionPolicy::default().is_active());
+    }
+}
@@ -0,0 +1,181 @@
+//! Employee review of screenshots before upload
+//!
+//! When the org policy enables `screenshot_review`, automatic screenshots are
+//! queued locally with a review deadline instead of being uploaded right
+//! away. The employee is notified and can accept, blur or reject each one
+//! until the deadline; unreviewed screenshots are uploaded as captured once
+//! it passes. Rejections are reported to the backend as `screenshot_rejected`
+//! events so admins can see that a screenshot was withheld.
+//!
+//! Screenshots requested by an admin job are not held for review.
+//!
+//! Queued files are encrypted, so the review dialog gets its preview from
+//! `get_screenshot_preview` rather than the file path.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use image::imageops;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::{at_rest, encode};
+use crate::error::AgentError;
+use crate::storage::audit_log;
+use crate::storage::offline_queue;
+use crate::storage::screenshot_queue::{self, ScreenshotAwaitingReview};
+use crate::utils::clock;
+
+/// Review window when the policy doesn't set one
+pub const DEFAULT_REVIEW_MINUTES: u32 = 10;
+
+/// Previews are scaled down to this width to keep the IPC payload small
+const PREVIEW_MAX_WIDTH: u32 = 960;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    /// Upload as captured
+    Accept,
+    /// Upload a blurred copy
+    Blur,
+    /// Delete without uploading
+    Reject,
+}
+
+/// Deadline for a screenshot captured at `taken_at`, or None when the policy
+/// doesn't ask for review
+pub async fn review_deadline(taken_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let policy = crate::api::employee_settings::get_policy_settings().await;
+    if !policy.screenshot_review {
+        return None;
+    }
+    let minutes = match policy.screenshot_review_minutes {
+        0 => DEFAULT_REVIEW_MINUTES,
+        minutes => minutes,
+    };
+    Some(taken_at + Duration::minutes(minutes as i64))
+}
+
+/// Tell the employee a screenshot is waiting for review
+pub fn request_review(id: i64, taken_at: DateTime<Utc>, deadline: DateTime<Utc>) {
+    crate::events::screenshot_review_requested(id, taken_at, deadline);
+    crate::events::notify("notification.screenshot_review.title", "notification.screenshot_review.body");
+}
+
+/// Blur the whole JPEG at `path` so nothing on screen stays readable
+fn blur_file(path: &Path) -> anyhow::Result<()> {
+    let image = image::load_from_memory(&at_rest::read(path)?)?.to_rgb8();
+    let sigma = (image.width() as f32 / 80.0).max(12.0);
+    let blurred = imageops::blur(&image, sigma);
+    at_rest::write(path, &encode::encode_jpeg(&blurred, encode::JPEG_QUALITY)?)?;
+    Ok(())
+}
+
+/// Decrypt the JPEG at `path` and scale it down for the review dialog
+fn preview_jpeg(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let image = image::load_from_memory(&at_rest::read(path)?)?.to_rgb8();
+    let image = if image.width() > PREVIEW_MAX_WIDTH {
+        let height = (image.height() as u64 * PREVIEW_MAX_WIDTH as u64 / image.width() as u64).max(1) as u32;
+        imageops::resize(&image, PREVIEW_MAX_WIDTH, height, imageops::FilterType::Triangle)
+    } else {
+        image
+    };
+    encode::encode_jpeg(&image, encode::JPEG_QUALITY)
+}
+
+async fn find_awaiting_review(id: i64) -> Result<ScreenshotAwaitingReview, AgentError> {
+    screenshot_queue::awaiting_review_by_id(id, clock::now())
+        .await
+        .map_err(|e| AgentError::Storage(e.to_string()))?
+        .ok_or_else(|| AgentError::Internal("Screenshot is no longer awaiting review".to_string()))
+}
+
+/// Apply the employee's decision to a screenshot awaiting review
+async fn apply_decision(screenshot: &ScreenshotAwaitingReview, decision: ReviewDecision) -> anyhow::Result<()> {
+    match decision {
+        ReviewDecision::Accept => screenshot_queue::finish_review(screenshot.id).await,
+        ReviewDecision::Blur => {
+            let path = screenshot.file_path.clone();
+            tokio::task::spawn_blocking(move || blur_file(Path::new(&path))).await??;
+            screenshot_queue::finish_review(screenshot.id).await
+        }
+        ReviewDecision::Reject => {
+            screenshot_queue::remove_from_queue(screenshot.id).await?;
+            if let Err(e) = std::fs::remove_file(&screenshot.file_path) {
+                log::warn!("Failed to delete rejected screenshot {}: {}", screenshot.file_path, e);
+            }
+            offline_queue::queue_event(
+                "screenshot_rejected",
+                &serde_json::json!({
+                    "taken_at": screenshot.taken_at.to_rfc3339(),
+                    "rejected_at": clock::now().to_rfc3339(),
+                }),
+            )
+            .await
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_screenshots_for_review() -> Result<Vec<ScreenshotAwaitingReview>, AgentError> {
+    screenshot_queue::awaiting_review(clock::now())
+        .await
+        .map_err(|e| AgentError::Storage(e.to_string()))
+}
+
+/// Preview of a screenshot awaiting review as a `data:image/jpeg;base64,...` URL
+#[tauri::command]
+pub async fn get_screenshot_preview(id: i64) -> Result<String, AgentError> {
+    let screenshot = find_awaiting_review(id).await?;
+    let jpeg = tokio::task::spawn_blocking(move || preview_jpeg(Path::new(&screenshot.file_path)))
+        .await
+        .map_err(|e| AgentError::Internal(e.to_string()))?
+        .map_err(|e| AgentError::Internal(format!("Failed to load screenshot preview: {}", e)))?;
+    Ok(format!("data:image/jpeg;base64,{}", STANDARD.encode(jpeg)))
+}
+
+#[tauri::command]
+pub async fn review_screenshot(id: i64, decision: ReviewDecision) -> Result<(), AgentError> {
+    let result: Result<(), AgentError> = async {
+        let screenshot = find_awaiting_review(id).await?;
+        apply_decision(&screenshot, decision)
+            .await
+            .map_err(|e| AgentError::Internal(format!("Failed to apply screenshot review: {}", e)))?;
+        log::info!("Screenshot {} reviewed: {:?}", id, decision);
+        Ok(())
+    }
+    .await;
+    audit_log::record("review_screenshot", &result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blur_hides_detail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shot.jpg");
+        // Sharp black/white stripes
+        let stripes = image::RgbImage::from_fn(64, 64, |x, _| if x % 2 == 0 { image::Rgb([0, 0, 0]) } else { image::Rgb([255, 255, 255]) });
+        std::fs::write(&path, encode::encode_jpeg(&stripes, encode::JPEG_QUALITY).unwrap()).unwrap();
+
+        blur_file(&path).unwrap();
+        let blurred = image::load_from_memory(&at_rest::read(&path).unwrap()).unwrap().to_rgb8();
+        assert_eq!(blurred.dimensions(), (64, 64));
+        let pixel = blurred.get_pixel(32, 32)[0] as i32;
+        assert!((pixel - 128).abs() < 40, "stripes should average out, got {}", pixel);
+    }
+
+    #[test]
+    fn test_preview_is_decrypted_and_scaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shot.enc");
+        let wide = image::RgbImage::from_pixel(1920, 1080, image::Rgb([30, 60, 90]));
+        at_rest::write(&path, &encode::encode_jpeg(&wide, encode::JPEG_QUALITY).unwrap()).unwrap();
+
+        let preview = image::load_from_memory(&preview_jpeg(&path).unwrap()).unwrap();
+        assert_eq!((preview.width(), preview.height()), (PREVIEW_MAX_WIDTH, 540));
+    }
+}
@@ -3,14 +3,6 @@ use base64::{self, Engine};
 use std::path::PathBuf;
 use chrono::Utc;
 
-#[cfg(target_os = "macos")]
-use image::GenericImageView;
-
-#[cfg(target_os = "macos")]
-use core_graphics::{
-    image::CGImageRef,
-};
-
 #[cfg(target_os = "windows")]
 use windows::{
     Win32::{
@@ -27,6 +19,8 @@ pub struct ScreenshotResult {
     pub height: u32,
     pub bytes: usize,
     pub format: String,
+    /// Display scaling (physical / logical pixels); width and height are physical
+    pub scale_factor: f64,
 }
 
 /// Capture screen and return base64 encoded JPEG (legacy method)
@@ -54,15 +48,22 @@ pub async fn capture_screen() -> Result<String> {
     }
 }
 
-/// Capture screen and save to temp folder, returning file info
+/// Capture screen and save to temp folder, returning file info.
+/// Sensitive text is redacted first when the org policy requires it.
+/// Refused under critical storage pressure, whoever asked for the screenshot
+/// (schedule, manual capture or a backend job).
 pub async fn capture_screen_to_file() -> Result<ScreenshotResult> {
+    if !crate::storage::storage_guard::can_store_screenshots() {
+        return Err(anyhow::anyhow!("Storage nearly full - screenshot not captured"));
+    }
+
     let temp_folder = crate::storage::screenshot_queue::get_temp_folder()?;
     let timestamp = Utc::now().format("%Y%m%d_%H%M%S_%3f").to_string();
-    let filename = format!("screenshot_{}.jpg", timestamp);
+    let filename = format!("screenshot_{}.enc", timestamp);
     let file_path = temp_folder.join(&filename);
     
     #[cfg(target_os = "macos")]
-    {
+    let result = {
         // Check screen recording permission first
         if !super::permissions::has_screen_recording_permission().await {
             log::warn!("Screen recording permission not granted on macOS");
@@ -70,117 +71,49 @@ pub async fn capture_screen_to_file() -> Result<ScreenshotResult> {
                 "Screen recording permission not granted. Please enable it in System Preferences > Privacy & Security > Screen Recording"
             ));
         }
-        capture_screen_to_file_macos(&file_path).await
-    }
+        capture_screen_to_file_macos(&file_path).await?
+    };
     
     #[cfg(target_os = "windows")]
-    {
-        capture_screen_to_file_windows(&file_path).await
-    }
+    let result = capture_screen_to_file_windows(&file_path).await?;
     
     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-    {
-        Err(anyhow::anyhow!("Screen capture not implemented for this platform"))
-    }
+    let result = {
+        let _ = file_path;
+        Err::<ScreenshotResult, _>(anyhow::anyhow!("Screen capture not implemented for this platform"))?
+    };
+    
+    let result = super::redaction::redact_screenshot(result).await?;
+    crate::storage::activity_index::index_screenshot(&result.file_path, Utc::now()).await;
+    Ok(result)
 }
 
 #[cfg(target_os = "macos")]
 async fn capture_screen_macos() -> Result<String> {
-    use std::process::Command;
-    
-    // Create temp file for screenshot
-    let temp_dir = std::env::temp_dir();
-    let temp_filename = format!("trackex_screenshot_{}.jpg", Utc::now().timestamp_millis());
-    let temp_file = temp_dir.join(&temp_filename);
-    
-    log::info!("Capturing macOS screenshot to temp file: {:?}", temp_file);
-    
-    // Use screencapture CLI tool which handles permissions properly
-    let output = Command::new("screencapture")
-        .arg("-x")  // No sound
-        .arg("-t")
-        .arg("jpg")
-        .arg(temp_file.to_string_lossy().to_string())
-        .output()?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        log::error!("screencapture command failed: {}", stderr);
-        return Err(anyhow::anyhow!("screencapture failed: {}", stderr));
-    }
-    
-    // Verify file was created
-    if !temp_file.exists() {
-        log::error!("Screenshot file was not created at {:?}", temp_file);
-        return Err(anyhow::anyhow!("Screenshot file was not created - permission may be denied"));
-    }
-    
-    // Read file and encode to base64
-    let file_data = std::fs::read(&temp_file).map_err(|e| {
-        log::error!("Failed to read screenshot file: {}", e);
-        anyhow::anyhow!("Failed to read screenshot file: {}", e)
-    })?;
-    
-    let base64_data = base64::engine::general_purpose::STANDARD.encode(&file_data);
-    
-    log::info!("Successfully captured macOS screenshot, size: {} bytes", file_data.len());
-    
-    // Cleanup temp file
-    if let Err(e) = std::fs::remove_file(&temp_file) {
-        log::warn!("Failed to cleanup temp screenshot file: {}", e);
-    }
-    
-    Ok(base64_data)
+    let frame = tokio::task::spawn_blocking(super::macos_capture::capture_main_display).await??;
+    log::info!("Successfully captured macOS screenshot, size: {} bytes", frame.jpeg.len());
+    Ok(base64::engine::general_purpose::STANDARD.encode(&frame.jpeg))
 }
 
+/// macOS: Capture screen in-process and write the encoded JPEG to file
 #[cfg(target_os = "macos")]
-unsafe fn convert_cgimage_to_jpeg(image: CGImageRef) -> Result<Vec<u8>> {
-    // For simplicity, we'll create a minimal JPEG representation
-    // In a real implementation, you'd use ImageIO framework
-    
-    // Get image dimensions using the public API
-    let width = image.width();
-    let height = image.height();
-    
-    // For now, return a minimal placeholder JPEG
-    // This would need to be replaced with actual ImageIO conversion
-    let placeholder_jpeg = create_placeholder_jpeg(width as u32, height as u32)?;
-    
-    Ok(placeholder_jpeg)
+async fn capture_screen_to_file_macos(file_path: &std::path::Path) -> Result<ScreenshotResult> {
+    let frame = tokio::task::spawn_blocking(super::macos_capture::capture_main_display).await??;
+    write_frame(file_path, frame)
 }
 
-/// macOS: Capture screen to file using Core Graphics
-#[cfg(target_os = "macos")]
-async fn capture_screen_to_file_macos(file_path: &std::path::Path) -> Result<ScreenshotResult> {
-    use std::process::Command;
-    
-    // Use screencapture command-line tool which handles permissions properly
-    let output = Command::new("screencapture")
-        .arg("-x") // No sound
-        .arg("-t")
-        .arg("jpg")
-        .arg(file_path.to_string_lossy().to_string())
-        .output()?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow::anyhow!("screencapture failed: {}", stderr));
-    }
-    
-    // Get file metadata
-    let metadata = std::fs::metadata(file_path)?;
-    let bytes = metadata.len() as usize;
-    
-    // Read image to get dimensions
-    let img = image::open(file_path)?;
-    let (width, height) = image::GenericImageView::dimensions(&img);
-    
+/// Write a natively captured frame, encrypted, to the temp folder
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn write_frame(file_path: &std::path::Path, frame: super::encode::CapturedFrame) -> Result<ScreenshotResult> {
+    super::at_rest::write(file_path, &frame.jpeg)?;
+
     Ok(ScreenshotResult {
         file_path: file_path.to_path_buf(),
-        width,
-        height,
-        bytes,
+        width: frame.width,
+        height: frame.height,
+        bytes: frame.jpeg.len(),
         format: "jpeg".to_string(),
+        scale_factor: frame.scale_factor,
     })
 }
 
@@ -218,21 +151,19 @@ fn create_placeholder_jpeg(width: u32, height: u32) -> Result<Vec<u8>> {
 #[cfg(target_os = "windows")]
 async fn capture_screen_windows() -> Result<String> {
     // Try modern Windows Graphics Capture API first (Windows 10+)
-    if let Ok(result) = capture_screen_modern_windows().await {
-        return Ok(result);
+    match capture_screen_modern_windows().await {
+        Ok(result) => return Ok(result),
+        Err(e) => log::warn!("Windows.Graphics.Capture failed, falling back to GDI: {}", e),
     }
     
     // Fallback to GDI for older Windows or if modern API fails
-    log::warn!("Modern screenshot API failed, falling back to GDI");
     capture_screen_gdi_windows().await
 }
 
 #[cfg(target_os = "windows")]
 async fn capture_screen_modern_windows() -> Result<String> {
-    // For now, we'll implement the GDI version as the primary method
-    // Modern Windows Graphics Capture API implementation would go here
-    // This requires more complex COM integration
-    Err(anyhow::anyhow!("Modern Windows Graphics Capture not yet implemented"))
+    let frame = tokio::task::spawn_blocking(super::windows_capture::capture_primary_monitor).await??;
+    Ok(base64::engine::general_purpose::STANDARD.encode(&frame.jpeg))
 }
 
 #[cfg(target_os = "windows")]
@@ -345,9 +276,19 @@ pub async fn capture_active_window() -> Result<String> {
     capture_screen().await
 }
 
-/// Windows: Capture screen to file using GDI
+/// Windows: Capture screen to file, preferring Windows.Graphics.Capture over GDI
 #[cfg(target_os = "windows")]
 async fn capture_screen_to_file_windows(file_path: &std::path::Path) -> Result<ScreenshotResult> {
+    match tokio::task::spawn_blocking(super::windows_capture::capture_primary_monitor).await? {
+        Ok(frame) => return write_frame(file_path, frame),
+        Err(e) => log::warn!("Windows.Graphics.Capture failed, falling back to GDI: {}", e),
+    }
+    capture_screen_to_file_gdi_windows(file_path).await
+}
+
+/// Windows: Capture screen to file using GDI
+#[cfg(target_os = "windows")]
+async fn capture_screen_to_file_gdi_windows(file_path: &std::path::Path) -> Result<ScreenshotResult> {
     unsafe {
         // Get screen dimensions
         let screen_width = GetSystemMetrics(SM_CXSCREEN) as u32;
@@ -422,12 +363,13 @@ async fn capture_screen_to_file_windows(file_path: &std::path::Path) -> Result<S
                 let img = image::RgbImage::from_raw(screen_width, screen_height, buffer)
                     .ok_or_else(|| anyhow::anyhow!("Failed to create image from bitmap data"))?;
                 
-                // Save to file with JPEG quality 75% for good balance of quality and file size
-                let output_file = std::fs::File::create(file_path)?;
-                let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(output_file, 75);
+                // Encode with JPEG quality 75% for good balance of quality and file size
+                let mut jpeg = Vec::new();
+                let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 75);
                 encoder.encode_image(&img)?;
+                super::at_rest::write(file_path, &jpeg)?;
                 
-                let bytes = std::fs::metadata(file_path)?.len() as usize;
+                let bytes = jpeg.len();
                 
                 // Cleanup
                 let _ = DeleteObject(bitmap.into());
@@ -440,6 +382,7 @@ async fn capture_screen_to_file_windows(file_path: &std::path::Path) -> Result<S
                     height: screen_height,
                     bytes,
                     format: "jpeg".to_string(),
+                    scale_factor: super::windows_capture::primary_monitor_scale(),
                 });
             }
         }
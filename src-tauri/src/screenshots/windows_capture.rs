@@ -0,0 +1,148 @@
+//! Screen capture on Windows via Windows.Graphics.Capture
+//!
+//! GDI's BitBlt misreports sizes on scaled monitors when the caller isn't
+//! DPI-aware, produces washed-out or black frames on HDR displays and can't see
+//! DirectX-protected content. Windows.Graphics.Capture (Windows 10 1903+)
+//! captures the primary monitor through the compositor in physical pixels,
+//! converted to 8-bit BGRA (SDR) by the system. The frame is copied to a
+//! CPU-readable staging texture and encoded to JPEG in memory.
+
+use anyhow::{anyhow, Result};
+use std::time::{Duration, Instant};
+use windows::core::Interface;
+use windows::Graphics::Capture::{Direct3D11CaptureFrame, Direct3D11CaptureFramePool, GraphicsCaptureItem, GraphicsCaptureSession};
+use windows::Graphics::DirectX::Direct3D11::IDirect3DDevice;
+use windows::Graphics::DirectX::DirectXPixelFormat;
+use windows::Win32::Foundation::{HMODULE, POINT};
+use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D, D3D11_CPU_ACCESS_READ,
+    D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_SDK_VERSION,
+    D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING,
+};
+use windows::Win32::Graphics::Dxgi::IDXGIDevice;
+use windows::Win32::Graphics::Gdi::{MonitorFromPoint, HMONITOR, MONITOR_DEFAULTTOPRIMARY};
+use windows::Win32::System::WinRT::Direct3D11::{CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess};
+use windows::Win32::System::WinRT::Graphics::Capture::IGraphicsCaptureItemInterop;
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+
+use super::encode::{self, CapturedFrame, PixelLayout};
+
+/// How long to wait for the compositor to deliver the first frame
+const FRAME_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// DPI of a monitor at 100% scaling
+const BASE_DPI: f64 = 96.0;
+
+fn primary_monitor() -> HMONITOR {
+    unsafe { MonitorFromPoint(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY) }
+}
+
+/// Display scaling of the primary monitor (1.0 at 100%, 1.5 at 150%, ...)
+pub fn primary_monitor_scale() -> f64 {
+    let (mut dpi_x, mut dpi_y) = (0u32, 0u32);
+    match unsafe { GetDpiForMonitor(primary_monitor(), MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) } {
+        Ok(()) if dpi_x > 0 => dpi_x as f64 / BASE_DPI,
+        _ => 1.0,
+    }
+}
+
+fn create_device() -> Result<(ID3D11Device, ID3D11DeviceContext)> {
+    let mut device = None;
+    let mut context = None;
+    unsafe {
+        D3D11CreateDevice(
+            None,
+            D3D_DRIVER_TYPE_HARDWARE,
+            HMODULE::default(),
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+            None,
+            D3D11_SDK_VERSION,
+            Some(&mut device),
+            None,
+            Some(&mut context),
+        )?;
+    }
+    match (device, context) {
+        (Some(device), Some(context)) => Ok((device, context)),
+        _ => Err(anyhow!("D3D11CreateDevice returned no device")),
+    }
+}
+
+/// Wait for the first frame (polled so no event handler has to cross threads)
+fn next_frame(pool: &Direct3D11CaptureFramePool) -> Result<Direct3D11CaptureFrame> {
+    let started = Instant::now();
+    loop {
+        if let Ok(frame) = pool.TryGetNextFrame() {
+            return Ok(frame);
+        }
+        if started.elapsed() > FRAME_TIMEOUT {
+            return Err(anyhow!("Timed out waiting for a capture frame"));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Copy a GPU frame into CPU memory and encode it
+fn read_frame(device: &ID3D11Device, context: &ID3D11DeviceContext, frame: &Direct3D11CaptureFrame) -> Result<(Vec<u8>, u32, u32)> {
+    let access: IDirect3DDxgiInterfaceAccess = frame.Surface()?.cast()?;
+    let texture: ID3D11Texture2D = unsafe { access.GetInterface()? };
+
+    let mut desc = D3D11_TEXTURE2D_DESC::default();
+    unsafe { texture.GetDesc(&mut desc) };
+    desc.Usage = D3D11_USAGE_STAGING;
+    desc.BindFlags = 0;
+    desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
+    desc.MiscFlags = 0;
+
+    let mut staging = None;
+    unsafe { device.CreateTexture2D(&desc, None, Some(&mut staging))? };
+    let staging = staging.ok_or_else(|| anyhow!("Failed to create staging texture"))?;
+
+    unsafe {
+        context.CopyResource(&staging, &texture);
+        let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+        context.Map(&staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))?;
+        let pitch = mapped.RowPitch as usize;
+        let pixels = std::slice::from_raw_parts(mapped.pData as *const u8, pitch * desc.Height as usize);
+        let rgb = encode::to_rgb(pixels, desc.Width, desc.Height, pitch, PixelLayout::BGRA);
+        context.Unmap(&staging, 0);
+        Ok((encode::encode_jpeg(&rgb?, encode::JPEG_QUALITY)?, desc.Width, desc.Height))
+    }
+}
+
+/// Capture the primary monitor as JPEG (blocking; call from a blocking task)
+pub fn capture_primary_monitor() -> Result<CapturedFrame> {
+    if !GraphicsCaptureSession::IsSupported()? {
+        return Err(anyhow!("Windows.Graphics.Capture is not supported on this system"));
+    }
+
+    let interop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()?;
+    let item: GraphicsCaptureItem = unsafe { interop.CreateForMonitor(primary_monitor())? };
+
+    let (device, context) = create_device()?;
+    let dxgi_device: IDXGIDevice = device.cast()?;
+    let winrt_device: IDirect3DDevice = unsafe { CreateDirect3D11DeviceFromDXGIDevice(&dxgi_device)? }.cast()?;
+
+    let pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
+        &winrt_device,
+        DirectXPixelFormat::B8G8R8A8UIntNormalized,
+        1,
+        item.Size()?,
+    )?;
+    let session = pool.CreateCaptureSession(&item)?;
+    // Only honoured on Windows 11; older systems always draw the capture border
+    let _ = session.SetIsBorderRequired(false);
+    session.StartCapture()?;
+
+    let result = next_frame(&pool).and_then(|frame| {
+        let encoded = read_frame(&device, &context, &frame);
+        let _ = frame.Close();
+        encoded
+    });
+    let _ = session.Close();
+    let _ = pool.Close();
+
+    let (jpeg, width, height) = result?;
+    Ok(CapturedFrame { jpeg, width, height, scale_factor: primary_monitor_scale() })
+}
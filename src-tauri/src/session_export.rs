@@ -0,0 +1,161 @@
+//! Calendar export of work sessions
+//!
+//! Writes finished work sessions, and the breaks between sessions on the same
+//! day, as iCalendar (RFC 5545) events so employees can overlay their tracked
+//! time on a personal calendar. Times are written in UTC; calendar apps show
+//! them in the viewer's time zone.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+use crate::error::AgentError;
+use crate::storage::recent_sessions::{self, RecentSession, SessionQuery};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub sessions: usize,
+    pub breaks: usize,
+}
+
+/// A gap between two sessions on the same day
+#[derive(Debug, Clone, PartialEq)]
+struct Break {
+    uid: String,
+    started_at: DateTime<Utc>,
+    ended_at: DateTime<Utc>,
+}
+
+fn ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Breaks between consecutive sessions; `sessions` must be finished and oldest first
+fn breaks_between(sessions: &[RecentSession]) -> Vec<Break> {
+    sessions
+        .windows(2)
+        .filter_map(|pair| {
+            let (previous, next) = (&pair[0], &pair[1]);
+            let ended_at = previous.ended_at?;
+            (previous.date == next.date && next.started_at > ended_at).then(|| Break {
+                uid: format!("break-{}-{}@trackex", previous.id, next.id),
+                started_at: ended_at,
+                ended_at: next.started_at,
+            })
+        })
+        .collect()
+}
+
+fn push_event(lines: &mut Vec<String>, uid: &str, summary: &str, start: DateTime<Utc>, end: DateTime<Utc>, stamp: &str) {
+    lines.push("BEGIN:VEVENT".to_string());
+    lines.push(format!("UID:{}", uid));
+    lines.push(format!("DTSTAMP:{}", stamp));
+    lines.push(format!("DTSTART:{}", ics_time(start)));
+    lines.push(format!("DTEND:{}", ics_time(end)));
+    lines.push(format!("SUMMARY:{}", summary));
+    // Tracked time shouldn't show the employee as busy
+    lines.push("TRANSP:TRANSPARENT".to_string());
+    lines.push("END:VEVENT".to_string());
+}
+
+fn build_calendar(sessions: &[RecentSession], breaks: &[Break], now: DateTime<Utc>) -> String {
+    let stamp = ics_time(now);
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//TrackEx//TrackEx Agent//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:TrackEx work sessions".to_string(),
+    ];
+    for session in sessions {
+        if let Some(ended_at) = session.ended_at {
+            let uid = format!("session-{}@trackex", session.id);
+            push_event(&mut lines, &uid, "Work session", session.started_at, ended_at, &stamp);
+        }
+    }
+    for item in breaks {
+        push_event(&mut lines, &item.uid, "Break", item.started_at, item.ended_at, &stamp);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    // RFC 5545 requires CRLF line endings
+    let mut calendar = lines.join("\r\n");
+    calendar.push_str("\r\n");
+    calendar
+}
+
+fn parse_date(value: Option<&str>) -> Result<Option<NaiveDate>, AgentError> {
+    value
+        .map(|v| {
+            NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                .map_err(|_| AgentError::Internal(format!("Invalid date '{}', expected YYYY-MM-DD", v)))
+        })
+        .transpose()
+}
+
+/// Write finished work sessions (and breaks) in the optional date range to an .ics file
+#[tauri::command]
+pub async fn export_sessions_ics(
+    path: String,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<ExportSummary, AgentError> {
+    let range = SessionQuery::new(None, None, parse_date(start_date.as_deref())?, parse_date(end_date.as_deref())?);
+
+    let mut sessions: Vec<RecentSession> = recent_sessions::get_local_sessions()
+        .map_err(|e| AgentError::Storage(e.to_string()))?
+        .into_iter()
+        .filter(|s| s.ended_at.is_some() && range.matches(s))
+        .collect();
+    sessions.sort_by_key(|s| s.started_at);
+
+    let breaks = breaks_between(&sessions);
+    let calendar = build_calendar(&sessions, &breaks, Utc::now());
+    std::fs::write(&path, calendar)
+        .map_err(|e| AgentError::Internal(format!("Failed to write {}: {}", path, e)))?;
+
+    log::info!("Exported {} sessions and {} breaks to {}", sessions.len(), breaks.len(), path);
+    Ok(ExportSummary { path, sessions: sessions.len(), breaks: breaks.len() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn session(id: &str, start: (u32, u32), end: (u32, u32), date: &str) -> RecentSession {
+        let started_at = Utc.with_ymd_and_hms(2024, 3, 1, start.0, start.1, 0).unwrap();
+        let ended_at = Utc.with_ymd_and_hms(2024, 3, 1, end.0, end.1, 0).unwrap();
+        RecentSession {
+            id: id.to_string(),
+            started_at,
+            ended_at: Some(ended_at),
+            duration: (ended_at - started_at).num_seconds(),
+            date: date.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_breaks_only_within_a_day() {
+        let sessions = vec![
+            session("local-1", (8, 0), (12, 0), "2024-03-01"),
+            session("local-2", (12, 45), (17, 0), "2024-03-01"),
+            session("local-3", (18, 0), (19, 0), "2024-03-02"),
+        ];
+        let breaks = breaks_between(&sessions);
+        assert_eq!(breaks.len(), 1);
+        assert_eq!(breaks[0].uid, "break-local-1-local-2@trackex");
+        assert_eq!((breaks[0].ended_at - breaks[0].started_at).num_minutes(), 45);
+    }
+
+    #[test]
+    fn test_calendar_format() {
+        let sessions = vec![session("local-1", (8, 0), (12, 0), "2024-03-01")];
+        let now = Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap();
+        let calendar = build_calendar(&sessions, &[], now);
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(calendar.contains("UID:session-local-1@trackex\r\n"));
+        assert!(calendar.contains("DTSTART:20240301T080000Z\r\nDTEND:20240301T120000Z\r\n"));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+    }
+}
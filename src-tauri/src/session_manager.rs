@@ -0,0 +1,221 @@
+//! Ending a work session
+//!
+//! Clocking out manually, logging out while clocked in, quitting the app,
+//! license expiry and the overtime limit all end the session the same way:
+//! close the open app usage segment, deliver what is still queued, stop the
+//! background services, end the local session and send a `clock_out` event
+//! carrying the reason.
+//! Reasons that have to be quick (app quit) or that the backend would refuse
+//! anyway (license expired) skip the network-heavy steps.
+
+use serde::Serialize;
+
+use crate::error::AgentError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    /// The employee clocked out
+    Manual,
+    Logout,
+    /// The app is quitting or the machine shutting down
+    AppQuit,
+    LicenseExpired,
+    /// The policy's daily or weekly hours limit was exceeded
+    OvertimeLimit,
+}
+
+impl EndReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EndReason::Manual => "manual",
+            EndReason::Logout => "logout",
+            EndReason::AppQuit => "app_quit",
+            EndReason::LicenseExpired => "license_expired",
+            EndReason::OvertimeLimit => "overtime_limit",
+        }
+    }
+
+    /// `source` of the clock_out event, as sent before the reasons were unified
+    fn source(self) -> &'static str {
+        match self {
+            EndReason::Manual | EndReason::Logout | EndReason::OvertimeLimit => "desktop_agent",
+            EndReason::AppQuit => "desktop_agent_shutdown",
+            EndReason::LicenseExpired => "license_expiration",
+        }
+    }
+
+    /// Whether there is time (and a backend willing) to deliver queued data
+    fn flushes_queue(self) -> bool {
+        matches!(self, EndReason::Manual | EndReason::Logout | EndReason::OvertimeLimit)
+    }
+}
+
+/// Close open app usage on the backend with a last app_focus event
+async fn send_final_app_focus() {
+    if let Ok(Some(current_app)) = crate::commands::get_current_app().await {
+        let event_data = serde_json::json!({
+            "app_name": current_app.name,
+            "app_id": current_app.app_id,
+            "window_title": current_app.window_title,
+            "timestamp": crate::utils::time::format_timestamp(chrono::Utc::now())
+        });
+        match crate::sampling::send_event_to_backend("app_focus", &event_data).await {
+            Ok(_) => log::info!("End session: final app focus event sent"),
+            Err(e) => log::warn!("End session: failed to send final app focus event: {}", e),
+        }
+    }
+}
+
+/// Deliver queued events and heartbeats before the services stop
+async fn flush_offline_queue() {
+    use crate::storage::offline_queue;
+
+    if let Ok(events) = offline_queue::get_pending_events().await {
+        for event in events {
+            match crate::sampling::send_queued_event(&event).await {
+                Ok(_) => {
+                    let _ = offline_queue::mark_event_processed(event.id).await;
+                }
+                Err(e) => {
+                    log::warn!("End session: failed to send queued event {}: {}", event.id, e);
+                    let _ = offline_queue::mark_event_failed(event.id).await;
+                }
+            }
+        }
+    }
+
+    if let Ok(heartbeats) = offline_queue::get_pending_heartbeats().await {
+        for heartbeat in heartbeats {
+            match crate::sampling::send_heartbeat_to_backend(&heartbeat.heartbeat_data).await {
+                Ok(_) => {
+                    let _ = offline_queue::mark_heartbeat_processed(heartbeat.id).await;
+                }
+                Err(e) => {
+                    log::warn!("End session: failed to send queued heartbeat {}: {}", heartbeat.id, e);
+                    let _ = offline_queue::mark_heartbeat_failed(heartbeat.id).await;
+                }
+            }
+        }
+    }
+}
+
+/// Send the clock_out event; queued for later if the backend can't be reached
+async fn send_clock_out(reason: EndReason) -> Result<(), AgentError> {
+    if !crate::sampling::is_authenticated().await {
+        return Err(AgentError::NotAuthenticated);
+    }
+
+    // Quitting has a few seconds at most; skip the location lookup
+    let ended_at = crate::utils::clock::now();
+    let location = match reason {
+        EndReason::AppQuit => None,
+        _ => crate::location::location_for_clock_event().await,
+    };
+    let data = serde_json::json!({
+        "source": reason.source(),
+        "reason": reason.as_str(),
+        "location": location
+    });
+    let event_data = serde_json::json!({
+        "events": [{
+            "type": "clock_out",
+            "timestamp": crate::utils::time::format_timestamp(ended_at),
+            "data": data
+        }]
+    });
+
+    let client = crate::api::client::ApiClient::new()
+        .await
+        .map_err(|e| AgentError::Internal(format!("Failed to create API client: {}", e)))?;
+    let response = match client.post_with_auth("/api/ingest/events", &event_data).await {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("End session: failed to send clock_out event, queuing it: {}", e);
+            crate::storage::offline_queue::queue_event_at("clock_out", &data, ended_at)
+                .await
+                .map_err(|e| AgentError::Storage(format!("Failed to queue clock_out event: {}", e)))?;
+            return Ok(());
+        }
+    };
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(AgentError::from_status(status, format!("Clock out failed: {}", error_text)));
+    }
+    log::info!("End session: clock_out sent (reason={})", reason.as_str());
+    Ok(())
+}
+
+/// End the current work session for `reason`. The session is always ended
+/// locally; the error reports what failed along the way.
+pub async fn end_session(reason: EndReason) -> Result<(), AgentError> {
+    log::info!("Ending work session (reason={})", reason.as_str());
+
+    if let Err(e) = crate::storage::app_usage::end_current_session().await {
+        log::warn!("End session: failed to end current app session: {}", e);
+    }
+
+    if reason.flushes_queue() {
+        send_final_app_focus().await;
+        flush_offline_queue().await;
+    }
+
+    crate::sampling::stop_services().await;
+    crate::sampling::clear_pause().await;
+    // License expiry is detected by the monitor, which keeps watching for renewal
+    if reason != EndReason::LicenseExpired {
+        crate::sampling::license_monitor::stop_license_monitor().await;
+    }
+    crate::sampling::reset_idle_state();
+
+    let local = crate::storage::work_session::end_session()
+        .await
+        .map_err(|e| AgentError::Storage(format!("Failed to end local session: {}", e)));
+    if let Err(e) = &local {
+        log::warn!("End session: {}", e);
+    }
+
+    send_clock_out(reason).await?;
+    local
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{agent_env, MockBackend};
+
+    #[tokio::test]
+    async fn test_end_session_sends_reason() {
+        let backend = MockBackend::start().await;
+        let _env = agent_env(&backend).await;
+        crate::storage::work_session::start_session().await.unwrap();
+
+        end_session(EndReason::Logout).await.unwrap();
+        assert!(!crate::storage::work_session::is_session_active().await.unwrap());
+        let clock_out = backend
+            .received("/api/ingest/events")
+            .await
+            .into_iter()
+            .flat_map(|body| body["events"].as_array().cloned().unwrap_or_default())
+            .find(|event| event["type"] == "clock_out")
+            .expect("clock_out sent");
+        assert_eq!(clock_out["data"]["reason"], "logout");
+        assert_eq!(clock_out["data"]["source"], "desktop_agent");
+    }
+
+    #[tokio::test]
+    async fn test_end_session_clears_pause() {
+        let backend = MockBackend::start().await;
+        let _env = agent_env(&backend).await;
+        crate::storage::work_session::start_session().await.unwrap();
+        crate::sampling::pause_services(crate::sampling::service_state::PauseReason::User).await;
+
+        end_session(EndReason::Manual).await.unwrap();
+        // The next clock-in must not start paused
+        assert!(crate::sampling::current_pause().is_none());
+        assert!(!crate::sampling::is_services_paused().await);
+        assert_eq!(crate::storage::pause_state::load().unwrap(), None);
+    }
+}
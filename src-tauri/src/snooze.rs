@@ -0,0 +1,127 @@
+//! Snoozing tracking until a set time
+//!
+//! `snooze_tracking("13:00")` pauses all capture until the next 13:00 local
+//! time (or an explicit RFC 3339 timestamp), using the same persisted pause
+//! as `pause_background_services`, so a snooze survives restarts. While
+//! snoozed the tray tooltip counts down the remaining time; when the deadline
+//! passes tracking resumes on its own and a `tracking-resumed` event is sent.
+
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Utc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::error::AgentError;
+use crate::sampling::service_state::PauseReason;
+use crate::storage::audit_log;
+
+/// Longest allowed snooze
+const MAX_SNOOZE_HOURS: i64 = 24;
+
+/// How often the tray countdown is refreshed
+const COUNTDOWN_INTERVAL_SECS: u64 = 30;
+
+static COUNTDOWN_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Resolve `until` to the end of the snooze: "HH:MM" is the next occurrence
+/// of that local time, anything else must be an RFC 3339 timestamp. The result
+/// must lie within the next `MAX_SNOOZE_HOURS`.
+fn parse_until(until: &str, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+    let until = until.trim();
+    let resume_at = match NaiveTime::parse_from_str(until, "%H:%M") {
+        Ok(time) => {
+            let today = now.date_naive().and_time(time);
+            let candidate = Local.from_local_datetime(&today).earliest()?;
+            if candidate > now {
+                candidate
+            } else {
+                Local.from_local_datetime(&(today + chrono::Duration::days(1))).earliest()?
+            }
+        }
+        Err(_) => DateTime::parse_from_rfc3339(until).ok()?.with_timezone(&Local),
+    };
+    (resume_at > now && resume_at - now <= chrono::Duration::hours(MAX_SNOOZE_HOURS))
+        .then(|| resume_at.with_timezone(&Utc))
+}
+
+/// Tray tooltip while snoozed
+fn snoozed_tooltip(resume_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    // Round up so the last minute shows "1 min left" rather than 0
+    let minutes = ((resume_at - now).num_seconds().max(0) + 59) / 60;
+    crate::i18n::t_args(
+        "tray.tooltip_snoozed",
+        &[
+            ("time", &resume_at.with_timezone(&Local).format("%H:%M").to_string()),
+            ("minutes", &minutes.to_string()),
+        ],
+    )
+}
+
+/// Count down in the tray until the snooze ends (no-op when not snoozed or
+/// already counting)
+pub fn start_countdown() {
+    if COUNTDOWN_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tauri::async_runtime::spawn(async {
+        loop {
+            let snooze = crate::sampling::current_pause()
+                .filter(|pause| pause.reason == PauseReason::Snooze)
+                .and_then(|pause| pause.resume_at);
+            let Some(resume_at) = snooze else {
+                break;
+            };
+            crate::tray_status::set_snooze(Some(snoozed_tooltip(resume_at, Utc::now())));
+            tokio::time::sleep(Duration::from_secs(COUNTDOWN_INTERVAL_SECS)).await;
+        }
+        crate::tray_status::set_snooze(None);
+        COUNTDOWN_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+/// Pause all capture until `until` ("HH:MM" local time or RFC 3339);
+/// returns when tracking will resume
+#[tauri::command]
+pub async fn snooze_tracking(until: String) -> Result<DateTime<Utc>, AgentError> {
+    let result = match parse_until(&until, Local::now()) {
+        Some(resume_at) => {
+            crate::sampling::pause_services_until(PauseReason::Snooze, Some(resume_at)).await;
+            start_countdown();
+            log::info!("Tracking snoozed until {}", resume_at);
+            Ok(resume_at)
+        }
+        None => Err(AgentError::Internal(crate::i18n::t_args(
+            "error.invalid_snooze_time",
+            &[("value", until.as_str())],
+        ))),
+    };
+    audit_log::record("snooze_tracking", &result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_until_next_occurrence() {
+        let now = Local.with_ymd_and_hms(2024, 5, 6, 11, 30, 0).unwrap();
+
+        let lunch = parse_until("13:00", now).unwrap();
+        assert_eq!(lunch, Local.with_ymd_and_hms(2024, 5, 6, 13, 0, 0).unwrap().with_timezone(&Utc));
+
+        // Times already passed today mean tomorrow
+        let morning = parse_until("09:00", now).unwrap();
+        assert_eq!(morning, Local.with_ymd_and_hms(2024, 5, 7, 9, 0, 0).unwrap().with_timezone(&Utc));
+
+        let explicit = (now + chrono::Duration::hours(2)).to_rfc3339();
+        assert!(parse_until(&explicit, now).is_some());
+        assert!(parse_until(&(now + chrono::Duration::days(2)).to_rfc3339(), now).is_none());
+        assert!(parse_until("lunch", now).is_none());
+    }
+
+    #[test]
+    fn test_snoozed_tooltip_rounds_up() {
+        let now = Utc::now();
+        assert!(snoozed_tooltip(now + chrono::Duration::seconds(61), now).contains("(2 min left)"));
+    }
+}
@@ -0,0 +1,221 @@
+//! Local, searchable text index of screenshots (opt-in)
+//!
+//! Employees who want personal recall ("where did I see that invoice number?")
+//! can opt into the `activity_recall` consent scope. Each screenshot the agent
+//! captures is then OCR'd locally and its text stored in an SQLite FTS5
+//! table together with the app in focus. Only the text is kept, never the
+//! image, and none of it is uploaded. Entries expire after
+//! `RETENTION_DAYS`; opting out or logging out deletes the index and
+//! withdraws the consent.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use super::database;
+use crate::error::AgentError;
+
+/// Consent scope the employee has to grant
+pub const ACTIVITY_RECALL_CONSENT_SCOPE: &str = "activity_recall";
+
+/// How long indexed text is kept
+const RETENTION_DAYS: i64 = 30;
+
+const DEFAULT_SEARCH_LIMIT: u32 = 50;
+
+/// A search hit
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ActivityMatch {
+    pub captured_at: String,
+    pub app_name: Option<String>,
+    pub window_title: Option<String>,
+    /// Matching text with hits wrapped in `[` `]`
+    pub snippet: String,
+}
+
+/// Create the FTS5 index (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS activity_text USING fts5(
+            text,
+            window_title,
+            app_name UNINDEXED,
+            captured_at UNINDEXED,
+            tokenize = 'unicode61'
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn insert(
+    conn: &Connection,
+    captured_at: DateTime<Utc>,
+    app_name: Option<&str>,
+    window_title: Option<&str>,
+    text: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO activity_text (text, window_title, app_name, captured_at) VALUES (?1, ?2, ?3, ?4)",
+        params![text, window_title, app_name, captured_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Turn free text into an FTS5 query: every term must match, as a literal
+/// prefix, so user input can't produce FTS syntax errors
+fn to_fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn search(conn: &Connection, query: &str, limit: u32) -> Result<Vec<ActivityMatch>> {
+    let Some(fts_query) = to_fts_query(query) else {
+        return Ok(Vec::new());
+    };
+    let mut stmt = conn.prepare(
+        "SELECT captured_at, app_name, window_title, snippet(activity_text, 0, '[', ']', '…', 12)
+         FROM activity_text WHERE activity_text MATCH ?1
+         ORDER BY rank LIMIT ?2",
+    )?;
+    let matches = stmt
+        .query_map(params![fts_query, limit], |row| {
+            Ok(ActivityMatch {
+                captured_at: row.get(0)?,
+                app_name: row.get(1)?,
+                window_title: row.get(2)?,
+                snippet: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(matches)
+}
+
+/// Delete entries past the retention period (called from maintenance)
+pub fn prune(conn: &Connection, now: DateTime<Utc>) -> Result<usize> {
+    Ok(conn.execute(
+        "DELETE FROM activity_text WHERE captured_at < ?1",
+        params![(now - Duration::days(RETENTION_DAYS)).to_rfc3339()],
+    )?)
+}
+
+pub async fn is_enabled() -> bool {
+    super::consent::has_scope_consent(ACTIVITY_RECALL_CONSENT_SCOPE)
+        .await
+        .unwrap_or(false)
+}
+
+/// Withdraw the consent and delete the index (opt-out, logout), along with
+/// the app focus entries of the session search
+pub async fn clear() -> Result<()> {
+    super::consent::set_scope_consent(ACTIVITY_RECALL_CONSENT_SCOPE, false).await?;
+    let conn = database::get_connection()?;
+    conn.execute("DELETE FROM activity_text", [])?;
+    super::session_search::clear_activity(&conn)?;
+    Ok(())
+}
+
+/// OCR a captured screenshot and index its text in the background, if the
+/// employee opted in. The image is read now since the file is deleted once
+/// uploaded.
+pub async fn index_screenshot(file_path: &std::path::Path, captured_at: DateTime<Utc>) {
+    if !is_enabled().await {
+        return;
+    }
+    let image_data = match crate::screenshots::at_rest::read(file_path) {
+        Ok(data) => data,
+        Err(e) => {
+            log::warn!("Failed to read screenshot for activity index: {}", e);
+            return;
+        }
+    };
+    let app = crate::sampling::app_focus::get_last_non_trackex_app().await;
+    let redact_titles = crate::api::employee_settings::get_policy_settings().await.redact_titles;
+
+    tokio::task::spawn_blocking(move || {
+        let result = crate::screenshots::ocr::recognize_words(&image_data).and_then(|words| {
+            let text = crate::screenshots::ocr::plain_text(&words);
+            if text.trim().is_empty() {
+                return Ok(());
+            }
+            let conn = database::get_connection()?;
+            insert(
+                &conn,
+                captured_at,
+                app.as_ref().map(|a| a.name.as_str()),
+                app.as_ref().and_then(|a| a.window_title.as_deref()).filter(|_| !redact_titles),
+                &text,
+            )
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to index screenshot text: {}", e);
+        }
+    });
+}
+
+/// Search the local activity index
+#[tauri::command]
+pub async fn search_activity(query: String, limit: Option<u32>) -> Result<Vec<ActivityMatch>, AgentError> {
+    if !is_enabled().await {
+        return Err(AgentError::PermissionDenied("Activity recall is not enabled".to_string()));
+    }
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, 500);
+    tokio::task::spawn_blocking(move || {
+        let conn = database::get_connection()?;
+        search(&conn, &query, limit)
+    })
+    .await
+    .map_err(|e| AgentError::Internal(e.to_string()))?
+    .map_err(|e| AgentError::Storage(e.to_string()))
+}
+
+#[tauri::command]
+pub async fn get_activity_recall_enabled() -> Result<bool, AgentError> {
+    super::consent::has_scope_consent(ACTIVITY_RECALL_CONSENT_SCOPE)
+        .await
+        .map_err(|e| AgentError::Storage(e.to_string()))
+}
+
+/// Opt in or out of the local activity index; opting out deletes it, along
+/// with the app focus entries of the session search
+#[tauri::command]
+pub async fn set_activity_recall_enabled(enabled: bool) -> Result<(), AgentError> {
+    let result = if enabled {
+        super::consent::set_scope_consent(ACTIVITY_RECALL_CONSENT_SCOPE, true).await
+    } else {
+        clear().await
+    }
+    .map_err(|e| AgentError::Storage(e.to_string()));
+    super::audit_log::record("set_activity_recall_enabled", &result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_matches_prefixes_and_escapes_input() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+        let now = Utc::now();
+        insert(&conn, now, Some("Mail"), Some("Inbox"), "Invoice INV-2024-0042 from Acme").unwrap();
+        insert(&conn, now, Some("Editor"), None, "quarterly report draft").unwrap();
+        insert(&conn, now - Duration::days(40), None, None, "old invoice").unwrap();
+
+        let hits = search(&conn, "invo acme", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].app_name.as_deref(), Some("Mail"));
+        assert!(hits[0].snippet.contains("[Invoice]"));
+
+        // FTS operators and stray quotes are treated as text
+        assert!(search(&conn, "\"report OR NEAR(", 10).unwrap().is_empty());
+        assert!(search(&conn, "   ", 10).unwrap().is_empty());
+
+        assert_eq!(prune(&conn, now).unwrap(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+//! Local copy of the organisation's app rules
+//!
+//! Lets app-rule syncs be conditional: the ETag, Last-Modified and rules
+//! version of the last successful sync are kept next to the rules, so an
+//! unchanged rule set is a cheap 304 and changed ones can be sent as deltas.
+//! Rules are stored as the backend's JSON, keyed by rule id.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Validators and version of the last successful sync
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RulesSyncState {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Backend rules version, used to request deltas
+    pub version: Option<String>,
+}
+
+/// Create the cache tables (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_rules_cache (
+            id TEXT PRIMARY KEY,
+            rule_json TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_rules_sync_state (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            etag TEXT,
+            last_modified TEXT,
+            version TEXT
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Sync state, or the default (unconditional sync) when nothing is cached
+pub fn load_state(conn: &Connection) -> Result<RulesSyncState> {
+    let has_rules: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM app_rules_cache)", [], |row| row.get(0))?;
+    if !has_rules {
+        return Ok(RulesSyncState::default());
+    }
+    let state = conn
+        .query_row(
+            "SELECT etag, last_modified, version FROM app_rules_sync_state WHERE id = 1",
+            [],
+            |row| {
+                Ok(RulesSyncState {
+                    etag: row.get(0)?,
+                    last_modified: row.get(1)?,
+                    version: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(state.unwrap_or_default())
+}
+
+/// Cached rules as JSON
+pub fn load_rules(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT rule_json FROM app_rules_cache ORDER BY id")?;
+    let rules = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(rules)
+}
+
+/// Apply a sync result: `full` replaces the cache, otherwise `upserts`
+/// (id, json) and `deleted` ids are applied on top of it
+pub fn apply(
+    conn: &mut Connection,
+    full: bool,
+    upserts: &[(String, String)],
+    deleted: &[String],
+    state: &RulesSyncState,
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    if full {
+        tx.execute("DELETE FROM app_rules_cache", [])?;
+    }
+    for id in deleted {
+        tx.execute("DELETE FROM app_rules_cache WHERE id = ?1", params![id])?;
+    }
+    for (id, json) in upserts {
+        tx.execute(
+            "INSERT OR REPLACE INTO app_rules_cache (id, rule_json) VALUES (?1, ?2)",
+            params![id, json],
+        )?;
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO app_rules_sync_state (id, etag, last_modified, version) VALUES (1, ?1, ?2, ?3)",
+        params![state.etag, state.last_modified, state.version],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_sync_then_delta() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+        assert_eq!(load_state(&conn).unwrap(), RulesSyncState::default());
+
+        let state = RulesSyncState {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            version: Some("1".to_string()),
+        };
+        let rules = vec![("a".to_string(), "{\"id\":\"a\"}".to_string()), ("b".to_string(), "{\"id\":\"b\"}".to_string())];
+        apply(&mut conn, true, &rules, &[], &state).unwrap();
+        assert_eq!(load_state(&conn).unwrap(), state);
+
+        let next = RulesSyncState { version: Some("2".to_string()), ..state };
+        let changed = vec![("c".to_string(), "{\"id\":\"c\"}".to_string())];
+        apply(&mut conn, false, &changed, &["a".to_string()], &next).unwrap();
+        assert_eq!(load_rules(&conn).unwrap(), vec!["{\"id\":\"b\"}", "{\"id\":\"c\"}"]);
+        assert_eq!(load_state(&conn).unwrap().version.as_deref(), Some("2"));
+    }
+}
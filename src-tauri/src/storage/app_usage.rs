@@ -1,10 +1,10 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc, Duration};
-use rusqlite::params;
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use super::database;
+use super::{database, usage_rollup};
 use crate::utils::productivity::ProductivityCategory;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,16 +13,132 @@ pub struct AppUsageSession {
     pub app_name: String,
     pub app_id: String,
     pub window_title: Option<String>,
+    /// Virtual desktop / Space the app was used on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub virtual_desktop: Option<String>,
     pub category: ProductivityCategory,
     pub start_time: DateTime<Utc>,
     pub end_time: Option<DateTime<Utc>>,
     pub duration_seconds: i64,
     pub is_idle: bool,
     pub is_active: bool,
+    /// Per-window-title segments within this app session.
+    /// Only recorded when window-level tracking is enabled by policy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub window_segments: Vec<WindowSegment>,
+}
+
+/// Window segments are kept this long; app-level time lives on in the rollups
+const WINDOW_SEGMENT_RETENTION_DAYS: i64 = 30;
+
+/// Time spent in one window (document, spreadsheet, ...) of an app
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WindowSegment {
+    pub window_title: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub duration_seconds: i64,
+}
+
+impl AppUsageSession {
+    /// Track a window title change at `now`, closing the open segment if the title differs
+    fn switch_window(&mut self, window_title: Option<String>, now: DateTime<Utc>) {
+        if let Some(open) = self.window_segments.last() {
+            if open.end_time.is_none() && Some(&open.window_title) == window_title.as_ref() {
+                return;
+            }
+        }
+
+        self.close_window_segment(now);
+
+        if let Some(window_title) = window_title {
+            self.window_segments.push(WindowSegment {
+                window_title,
+                start_time: now,
+                end_time: None,
+                duration_seconds: 0,
+            });
+        }
+    }
+
+    fn close_window_segment(&mut self, now: DateTime<Utc>) {
+        if let Some(open) = self.window_segments.last_mut().filter(|segment| segment.end_time.is_none()) {
+            open.end_time = Some(now);
+            open.duration_seconds = (now - open.start_time).num_seconds();
+        }
+    }
+}
+
+/// Default minimum duration of a stand-alone app segment, in seconds
+pub const DEFAULT_MIN_SEGMENT_SECONDS: i64 = 5;
+
+/// Default gap tolerated between segments that get merged, in seconds
+pub const DEFAULT_MERGE_GAP_SECONDS: i64 = 30;
+
+/// Policy for collapsing rapid app switches (alt-tabbing) into fewer sessions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AppUsageMergePolicy {
+    /// Segments shorter than this are folded into the previous segment
+    pub min_segment_seconds: i64,
+    /// Same-app segments separated by at most this gap are merged
+    pub merge_gap_seconds: i64,
+}
+
+impl Default for AppUsageMergePolicy {
+    fn default() -> Self {
+        Self {
+            min_segment_seconds: DEFAULT_MIN_SEGMENT_SECONDS,
+            merge_gap_seconds: DEFAULT_MERGE_GAP_SECONDS,
+        }
+    }
+}
+
+impl AppUsageMergePolicy {
+    /// Build the policy from employee policy settings (values < 0 fall back to defaults, 0 disables)
+    pub fn from_settings(policy: &crate::api::employee_settings::PolicySettings) -> Self {
+        let defaults = Self::default();
+        Self {
+            min_segment_seconds: if policy.min_app_segment_s >= 0 {
+                policy.min_app_segment_s as i64
+            } else {
+                defaults.min_segment_seconds
+            },
+            merge_gap_seconds: if policy.app_merge_gap_s >= 0 {
+                policy.app_merge_gap_s as i64
+            } else {
+                defaults.merge_gap_seconds
+            },
+        }
+    }
+
+    /// Try to merge a finished segment into the previous one.
+    /// Returns true if `next` was absorbed into `prev`.
+    fn merge(&self, prev: &mut AppUsageSession, next: &AppUsageSession) -> bool {
+        let (Some(prev_end), Some(next_end)) = (prev.end_time, next.end_time) else {
+            return false;
+        };
+
+        let gap = (next.start_time - prev_end).num_seconds();
+        if gap < 0 || gap > self.merge_gap_seconds || prev.is_idle != next.is_idle {
+            return false;
+        }
+
+        let same_app = prev.app_id == next.app_id && prev.virtual_desktop == next.virtual_desktop;
+        let too_short = next.duration_seconds < self.min_segment_seconds;
+        if !same_app && !too_short {
+            return false;
+        }
+
+        prev.end_time = Some(next_end);
+        prev.duration_seconds += next.duration_seconds;
+        true
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct AppUsageTracker {
+    merge_policy: AppUsageMergePolicy,
+    track_window_segments: bool,
     current_session: Option<AppUsageSession>,
     session_history: Vec<AppUsageSession>,
     total_productive_time: i64,
@@ -34,6 +150,8 @@ pub struct AppUsageTracker {
 impl AppUsageTracker {
     pub fn new() -> Self {
         Self {
+            merge_policy: AppUsageMergePolicy::default(),
+            track_window_segments: false,
             current_session: None,
             session_history: Vec::new(),
             total_productive_time: 0,
@@ -48,40 +166,36 @@ impl AppUsageTracker {
         app_name: String,
         app_id: String,
         window_title: Option<String>,
+        virtual_desktop: Option<String>,
         category: ProductivityCategory,
         is_idle: bool,
     ) -> Result<()> {
         let now = Utc::now();
 
         // End current session if it exists
-        if let Some(mut current) = self.current_session.take() {
-            current.end_time = Some(now);
-            current.duration_seconds = (now - current.start_time).num_seconds();
-            current.is_active = false;
-            
-            // Update totals
-            self.update_totals(&current);
-            
-            // Save to database
-            self.save_session_to_db(&current).await?;
-            
-            self.session_history.push(current);
-        }
+        self.finish_current_session(now).await?;
 
         // Start new session
-        let new_session = AppUsageSession {
+        let mut new_session = AppUsageSession {
             id: None,
             app_name,
             app_id,
             window_title,
+            virtual_desktop,
             category,
             start_time: now,
             end_time: None,
             duration_seconds: 0,
             is_idle,
             is_active: true,
+            window_segments: Vec::new(),
         };
 
+        if self.track_window_segments {
+            let window_title = new_session.window_title.clone();
+            new_session.switch_window(window_title, now);
+        }
+
         self.current_session = Some(new_session);
         
         Ok(())
@@ -95,24 +209,82 @@ impl AppUsageTracker {
     }
 
     pub async fn end_current_session(&mut self) -> Result<()> {
-        if let Some(mut current) = self.current_session.take() {
-            let now = Utc::now();
-            current.end_time = Some(now);
-            current.duration_seconds = (now - current.start_time).num_seconds();
-            current.is_active = false;
-            
-            // Update totals
-            self.update_totals(&current);
-            
-            // Save to database
-            self.save_session_to_db(&current).await?;
-            
-            // Don't send to backend - app_focus events already handle this
-            // self.send_session_to_backend(&current).await?;
-            
-            self.session_history.push(current);
-            
+        // Don't send to backend - app_focus events already handle this
+        self.finish_current_session(Utc::now()).await
+    }
+
+    pub fn set_merge_policy(&mut self, policy: AppUsageMergePolicy) {
+        self.merge_policy = policy;
+    }
+
+    /// Enable or disable per-window-title segments (privacy setting)
+    pub fn set_window_tracking(&mut self, enabled: bool) {
+        self.track_window_segments = enabled;
+        if !enabled {
+            if let Some(ref mut session) = self.current_session {
+                session.window_segments.clear();
+            }
         }
+    }
+
+    /// Record the current window title of the focused app
+    pub fn update_window_title(&mut self, window_title: Option<String>) {
+        if !self.track_window_segments {
+            return;
+        }
+        if let Some(ref mut session) = self.current_session {
+            session.switch_window(window_title, Utc::now());
+        }
+    }
+
+    /// Close the current session at `now` and persist it, merging it into the
+    /// previous segment when the merge policy allows
+    async fn finish_current_session(&mut self, now: DateTime<Utc>) -> Result<()> {
+        let Some(mut current) = self.current_session.take() else {
+            return Ok(());
+        };
+
+        current.end_time = Some(now);
+        current.duration_seconds = (now - current.start_time).num_seconds();
+        current.is_active = false;
+        current.close_window_segment(now);
+
+        let policy = self.merge_policy;
+        if let Some(prev) = self.session_history.last_mut().filter(|prev| prev.id.is_some()) {
+            if policy.merge(prev, &current) {
+                prev.window_segments.extend(current.window_segments.iter().cloned());
+
+                // Time is accounted under the segment it was merged into
+                let mut absorbed = prev.clone();
+                absorbed.duration_seconds = current.duration_seconds;
+                let merged = prev.clone();
+
+                log::debug!(
+                    "Merged {}s {} segment into {} ({}s total)",
+                    current.duration_seconds,
+                    current.app_name,
+                    merged.app_name,
+                    merged.duration_seconds
+                );
+
+                self.update_totals(&absorbed);
+                self.update_session_in_db(&merged).await?;
+                self.add_to_rollups(&absorbed, current.start_time, false)?;
+                self.save_window_segments_to_db(&merged, &current.window_segments).await?;
+                return Ok(());
+            }
+        }
+
+        // Update totals
+        self.update_totals(&current);
+
+        // Save to database
+        let id = self.save_session_to_db(&current).await?;
+        self.save_window_segments_to_db(&current, &current.window_segments).await?;
+        self.add_to_rollups(&current, current.start_time, true)?;
+        current.id = Some(id);
+
+        self.session_history.push(current);
         Ok(())
     }
 
@@ -125,13 +297,22 @@ impl AppUsageTracker {
         &self.session_history
     }
 
-    pub fn get_totals(&self) -> (i64, i64, i64, i64) {
-        (
-            self.total_productive_time,
-            self.total_neutral_time,
-            self.total_unproductive_time,
-            self.total_idle_time,
-        )
+    pub fn get_totals(&self) -> UsageTotals {
+        let mut totals = UsageTotals {
+            productive_seconds: self.total_productive_time,
+            neutral_seconds: self.total_neutral_time,
+            unproductive_seconds: self.total_unproductive_time,
+            idle_seconds: self.total_idle_time,
+            ..UsageTotals::default()
+        };
+        totals.active_seconds = totals.productive_seconds + totals.neutral_seconds + totals.unproductive_seconds;
+        totals.meeting_seconds = self
+            .session_history
+            .iter()
+            .filter(|session| !session.is_idle && is_meeting_app(&session.app_name, &session.app_id))
+            .map(|session| session.duration_seconds)
+            .sum();
+        totals
     }
 
     pub fn get_app_usage_summary(&self) -> HashMap<String, AppUsageSummary> {
@@ -144,6 +325,7 @@ impl AppUsageTracker {
                 AppUsageSummary::new(session.app_name.clone(), session.app_id.clone())
             });
             entry.add_time(session.category.clone(), current_duration, session.is_idle);
+            entry.add_window_time(&session.window_segments);
         }
 
         // Process history
@@ -152,6 +334,7 @@ impl AppUsageTracker {
                 AppUsageSummary::new(session.app_name.clone(), session.app_id.clone())
             });
             entry.add_time(session.category.clone(), session.duration_seconds, session.is_idle);
+            entry.add_window_time(&session.window_segments);
         }
 
         summary
@@ -171,14 +354,14 @@ impl AppUsageTracker {
         }
     }
 
-    async fn save_session_to_db(&self, session: &AppUsageSession) -> Result<()> {
+    async fn save_session_to_db(&self, session: &AppUsageSession) -> Result<i64> {
         let conn = database::get_connection()?;
         
         conn.execute(
             "INSERT INTO app_usage_sessions (
                 app_name, app_id, window_title, category, 
-                start_time, end_time, duration_seconds, is_idle, is_active, synced
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                start_time, end_time, duration_seconds, is_idle, is_active, synced, virtual_desktop
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             params![
                 session.app_name,
                 session.app_id,
@@ -190,9 +373,68 @@ impl AppUsageTracker {
                 session.is_idle,
                 session.is_active,
                 true, // Set synced = true since app_focus handles backend sync
+                session.virtual_desktop,
             ],
         )?;
         
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Count `duration_seconds` of `session` from `start` in the hourly/daily rollups
+    fn add_to_rollups(&self, session: &AppUsageSession, start: DateTime<Utc>, new_session: bool) -> Result<()> {
+        let conn = database::get_connection()?;
+        usage_rollup::add_segment(
+            &conn,
+            &session.app_name,
+            &session.app_id,
+            &session.category,
+            session.is_idle,
+            start,
+            start + Duration::seconds(session.duration_seconds),
+            new_session,
+        )
+    }
+
+    /// Store `segments` under `session`; segments are keyed by the session's
+    /// start time and app, since session ids are reused once
+    /// app_usage_sessions is recreated
+    async fn save_window_segments_to_db(&self, session: &AppUsageSession, segments: &[WindowSegment]) -> Result<()> {
+        if segments.is_empty() {
+            return Ok(());
+        }
+
+        let conn = database::get_connection()?;
+        for segment in segments {
+            conn.execute(
+                "INSERT INTO app_usage_window_segments (
+                    session_start, app_name, app_id, window_title, start_time, end_time, duration_seconds
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    session.start_time,
+                    session.app_name,
+                    session.app_id,
+                    segment.window_title,
+                    segment.start_time,
+                    segment.end_time,
+                    segment.duration_seconds,
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    async fn update_session_in_db(&self, session: &AppUsageSession) -> Result<()> {
+        let Some(id) = session.id else {
+            return Ok(());
+        };
+
+        let conn = database::get_connection()?;
+        conn.execute(
+            "UPDATE app_usage_sessions SET end_time = ?1, duration_seconds = ?2 WHERE id = ?3",
+            params![session.end_time, session.duration_seconds, id],
+        )?;
+
         Ok(())
     }
 
@@ -204,7 +446,7 @@ impl AppUsageTracker {
         
         let mut stmt = conn.prepare(
             "SELECT id, app_name, app_id, window_title, category, 
-                    start_time, end_time, duration_seconds, is_idle, is_active
+                    start_time, end_time, duration_seconds, is_idle, is_active, virtual_desktop
              FROM app_usage_sessions 
              WHERE start_time >= ?1 
              ORDER BY start_time DESC"
@@ -212,11 +454,7 @@ impl AppUsageTracker {
         
         let rows = stmt.query_map(params![cutoff_time], |row| {
             let category_str: String = row.get(4)?;
-            let category = match category_str.as_str() {
-                "PRODUCTIVE" => ProductivityCategory::PRODUCTIVE,
-                "UNPRODUCTIVE" => ProductivityCategory::UNPRODUCTIVE,
-                _ => ProductivityCategory::NEUTRAL,
-            };
+            let category = parse_category(&category_str);
             
             Ok(AppUsageSession {
                 id: Some(row.get(0)?),
@@ -229,6 +467,8 @@ impl AppUsageTracker {
                 duration_seconds: row.get(7)?,
                 is_idle: row.get(8)?,
                 is_active: row.get(9)?,
+                virtual_desktop: row.get(10)?,
+                window_segments: Vec::new(),
             })
         })?;
         
@@ -245,6 +485,58 @@ impl AppUsageTracker {
     }
 }
 
+/// Category as stored in the `category` column
+pub(super) fn parse_category(value: &str) -> ProductivityCategory {
+    match value {
+        "PRODUCTIVE" => ProductivityCategory::PRODUCTIVE,
+        "UNPRODUCTIVE" => ProductivityCategory::UNPRODUCTIVE,
+        _ => ProductivityCategory::NEUTRAL,
+    }
+}
+
+/// Apps whose (non-idle) time counts as meetings, matched against app name or id
+const MEETING_APPS: &[&str] = &["zoom", "teams", "webex", "skype", "gotomeeting", "facetime"];
+
+pub(super) fn is_meeting_app(app_name: &str, app_id: &str) -> bool {
+    let app_name = app_name.to_lowercase();
+    let app_id = app_id.to_lowercase();
+    MEETING_APPS
+        .iter()
+        .any(|meeting| app_name.contains(meeting) || app_id.contains(meeting))
+}
+
+/// Usage totals over a period, in seconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    /// Non-idle time (productive + neutral + unproductive)
+    pub active_seconds: i64,
+    pub idle_seconds: i64,
+    pub productive_seconds: i64,
+    pub neutral_seconds: i64,
+    pub unproductive_seconds: i64,
+    /// Gaps between work sessions on the same day
+    pub break_seconds: i64,
+    /// Active time spent in meeting apps (Zoom, Teams, ...)
+    pub meeting_seconds: i64,
+}
+
+impl UsageTotals {
+    fn from_summary(summary: &HashMap<String, AppUsageSummary>) -> Self {
+        let mut totals = Self::default();
+        for entry in summary.values() {
+            totals.productive_seconds += entry.productive_time;
+            totals.neutral_seconds += entry.neutral_time;
+            totals.unproductive_seconds += entry.unproductive_time;
+            totals.idle_seconds += entry.idle_time;
+            if is_meeting_app(&entry.app_name, &entry.app_id) {
+                totals.meeting_seconds += entry.total_time - entry.idle_time;
+            }
+        }
+        totals.active_seconds = totals.productive_seconds + totals.neutral_seconds + totals.unproductive_seconds;
+        totals
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppUsageSummary {
     pub app_name: String,
@@ -255,10 +547,14 @@ pub struct AppUsageSummary {
     pub unproductive_time: i64,
     pub idle_time: i64,
     pub session_count: i32,
+    /// Time per window title (only populated when window-level tracking is enabled).
+    /// App-level totals above are unaffected.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub window_time: HashMap<String, i64>,
 }
 
 impl AppUsageSummary {
-    fn new(app_name: String, app_id: String) -> Self {
+    pub(super) fn new(app_name: String, app_id: String) -> Self {
         Self {
             app_name,
             app_id,
@@ -268,6 +564,18 @@ impl AppUsageSummary {
             unproductive_time: 0,
             idle_time: 0,
             session_count: 0,
+            window_time: HashMap::new(),
+        }
+    }
+
+    fn add_window_time(&mut self, segments: &[WindowSegment]) {
+        let now = Utc::now();
+        for segment in segments {
+            let duration = match segment.end_time {
+                Some(_) => segment.duration_seconds,
+                None => (now - segment.start_time).num_seconds(),
+            };
+            *self.window_time.entry(segment.window_title.clone()).or_insert(0) += duration;
         }
     }
 
@@ -302,11 +610,27 @@ pub async fn start_app_session(
     app_name: String,
     app_id: String,
     window_title: Option<String>,
+    virtual_desktop: Option<String>,
     category: ProductivityCategory,
     is_idle: bool,
 ) -> Result<()> {
     let mut tracker = APP_USAGE_TRACKER.lock().await;
-    tracker.start_app_session(app_name, app_id, window_title, category, is_idle).await
+    tracker.start_app_session(app_name, app_id, window_title, virtual_desktop, category, is_idle).await
+}
+
+/// Record the current window title of the focused app (no-op unless window tracking is enabled)
+pub async fn update_window_title(window_title: Option<String>) {
+    let mut tracker = APP_USAGE_TRACKER.lock().await;
+    tracker.update_window_title(window_title);
+}
+
+/// Enable or disable per-window-title segments (called when employee settings are refreshed)
+pub async fn set_window_tracking(enabled: bool) {
+    let mut tracker = APP_USAGE_TRACKER.lock().await;
+    if tracker.track_window_segments != enabled {
+        log::info!("Window-level usage tracking {}", if enabled { "enabled" } else { "disabled" });
+        tracker.set_window_tracking(enabled);
+    }
 }
 
 pub async fn update_current_session(is_idle: bool) -> Result<()> {
@@ -330,9 +654,164 @@ pub async fn get_app_usage_summary() -> HashMap<String, AppUsageSummary> {
     tracker.get_app_usage_summary()
 }
 
-pub async fn get_usage_totals() -> (i64, i64, i64, i64) {
+/// Per-app usage between `start` and `end` from the rollups, including the
+/// part of the current session that falls in the range
+pub async fn get_app_usage_summary_between(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<HashMap<String, AppUsageSummary>> {
     let tracker = APP_USAGE_TRACKER.lock().await;
-    tracker.get_totals()
+    let conn = database::get_connection()?;
+    let mut summary = usage_rollup::summary_between(&conn, start, end)?;
+
+    if let Some(session) = &tracker.current_session {
+        let overlap = (end.min(Utc::now()) - start.max(session.start_time)).num_seconds();
+        if overlap > 0 {
+            let entry = summary.entry(session.app_name.clone()).or_insert_with(|| {
+                AppUsageSummary::new(session.app_name.clone(), session.app_id.clone())
+            });
+            entry.add_time(session.category.clone(), overlap, session.is_idle);
+            entry.add_window_time(&session.window_segments);
+        }
+    }
+
+    // Window titles aren't rolled up; take them from the stored segments
+    for (app_name, window_time) in window_time_between(&conn, start, end)? {
+        if let Some(entry) = summary.get_mut(&app_name) {
+            for (window_title, seconds) in window_time {
+                *entry.window_time.entry(window_title).or_insert(0) += seconds;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Stored window time per app name and window title between `start` and
+/// `end`, with segments clipped to the range
+fn window_time_between(
+    conn: &Connection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<HashMap<String, HashMap<String, i64>>> {
+    let mut stmt = conn.prepare(
+        "SELECT app_name, window_title, start_time, end_time
+         FROM app_usage_window_segments
+         WHERE start_time < ?2 AND end_time > ?1",
+    )?;
+    let rows = stmt
+        .query_map(params![start, end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, DateTime<Utc>>(2)?,
+                row.get::<_, DateTime<Utc>>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut window_time: HashMap<String, HashMap<String, i64>> = HashMap::new();
+    for (app_name, window_title, segment_start, segment_end) in rows {
+        let seconds = super::work_session::overlap_seconds(start, end, segment_start, segment_end);
+        if seconds > 0 {
+            *window_time.entry(app_name).or_default().entry(window_title).or_insert(0) += seconds;
+        }
+    }
+    Ok(window_time)
+}
+
+/// Delete window segments past the retention period (called from maintenance)
+pub fn prune_window_segments(conn: &Connection, now: DateTime<Utc>) -> Result<usize> {
+    Ok(conn.execute(
+        "DELETE FROM app_usage_window_segments WHERE end_time < ?1",
+        params![now - Duration::days(WINDOW_SEGMENT_RETENTION_DAYS)],
+    )?)
+}
+
+pub async fn get_usage_totals() -> UsageTotals {
+    let mut totals = {
+        let tracker = APP_USAGE_TRACKER.lock().await;
+        tracker.get_totals()
+    };
+
+    let today = Utc::now().with_timezone(&chrono::Local).date_naive();
+    let (start, end) = crate::utils::time::day_bounds(today);
+    match super::work_session::break_seconds_between(start, end).await {
+        Ok(breaks) => totals.break_seconds = breaks,
+        Err(e) => log::warn!("Failed to compute break time: {}", e),
+    }
+    totals
+}
+
+/// Historical usage query: a time range plus optional app/category filters
+#[derive(Debug, Clone)]
+pub struct UsageQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Matched case-insensitively against app name or app id
+    pub app: Option<String>,
+    pub category: Option<ProductivityCategory>,
+}
+
+impl UsageQuery {
+    /// Keep only the matching apps, and only the requested category's time
+    fn apply(&self, summary: &mut HashMap<String, AppUsageSummary>) {
+        if let Some(app) = self.app.as_ref().map(|a| a.to_lowercase()) {
+            summary.retain(|_, entry| {
+                entry.app_name.to_lowercase().contains(&app) || entry.app_id.to_lowercase() == app
+            });
+        }
+
+        if let Some(category) = &self.category {
+            for entry in summary.values_mut() {
+                let kept = match category {
+                    ProductivityCategory::PRODUCTIVE => entry.productive_time,
+                    ProductivityCategory::NEUTRAL => entry.neutral_time,
+                    ProductivityCategory::UNPRODUCTIVE => entry.unproductive_time,
+                };
+                *entry = AppUsageSummary {
+                    total_time: kept,
+                    productive_time: if *category == ProductivityCategory::PRODUCTIVE { kept } else { 0 },
+                    neutral_time: if *category == ProductivityCategory::NEUTRAL { kept } else { 0 },
+                    unproductive_time: if *category == ProductivityCategory::UNPRODUCTIVE { kept } else { 0 },
+                    idle_time: 0,
+                    ..entry.clone()
+                };
+            }
+            summary.retain(|_, entry| entry.total_time > 0);
+        }
+    }
+}
+
+/// Per-app usage for a historical range from the local database
+pub async fn query_usage_summary(query: &UsageQuery) -> Result<HashMap<String, AppUsageSummary>> {
+    let mut summary = get_app_usage_summary_between(query.start, query.end).await?;
+    query.apply(&mut summary);
+    Ok(summary)
+}
+
+/// Usage totals for a historical range. Breaks are only counted when the
+/// query isn't filtered to an app or category.
+pub async fn query_usage_totals(query: &UsageQuery) -> Result<UsageTotals> {
+    let summary = query_usage_summary(query).await?;
+    let mut totals = UsageTotals::from_summary(&summary);
+    if query.app.is_none() && query.category.is_none() {
+        totals.break_seconds = super::work_session::break_seconds_between(query.start, query.end).await?;
+    }
+    Ok(totals)
+}
+
+/// Replace the merge policy (called when employee settings are refreshed)
+pub async fn set_merge_policy(policy: AppUsageMergePolicy) {
+    let mut tracker = APP_USAGE_TRACKER.lock().await;
+    if tracker.merge_policy != policy {
+        log::info!(
+            "App usage merge policy updated: min_segment={}s, merge_gap={}s",
+            policy.min_segment_seconds,
+            policy.merge_gap_seconds
+        );
+        tracker.set_merge_policy(policy);
+    }
 }
 
 pub async fn load_recent_sessions(hours: i64) -> Result<()> {
@@ -344,23 +823,14 @@ pub async fn load_recent_sessions(hours: i64) -> Result<()> {
 pub async fn reset_tracker() -> Result<()> {
     let mut tracker = APP_USAGE_TRACKER.lock().await;
     // End any current session to prevent large duration calculations
-    if let Some(mut current) = tracker.current_session.take() {
-        let now = Utc::now();
-        current.end_time = Some(now);
-        current.duration_seconds = (now - current.start_time).num_seconds();
-        current.is_active = false;
-        
-        // Update totals
-        tracker.update_totals(&current);
-        
-        // Save to database
-        tracker.save_session_to_db(&current).await?;
-        
-        tracker.session_history.push(current);
-    }
+    tracker.finish_current_session(Utc::now()).await?;
     
-    // Reset tracker to clean state
+    // Reset tracker to clean state (keeping the configured policies)
+    let merge_policy = tracker.merge_policy;
+    let track_window_segments = tracker.track_window_segments;
     *tracker = AppUsageTracker::new();
+    tracker.set_merge_policy(merge_policy);
+    tracker.set_window_tracking(track_window_segments);
     
     log::info!("App usage tracker reset successfully");
     Ok(())
@@ -378,6 +848,7 @@ pub async fn handle_system_wake(_sleep_duration_seconds: u64) -> Result<()> {
         
         // Save the session
         tracker.save_session_to_db(&session).await?;
+        tracker.add_to_rollups(&session, session.start_time, true)?;
         log::info!("Marked previous session as idle due to system sleep: {} ({}s)", 
                   session.app_name, session.duration_seconds);
     }
@@ -386,6 +857,91 @@ pub async fn handle_system_wake(_sleep_duration_seconds: u64) -> Result<()> {
     Ok(())
 }
 
+/// Recorded time for one app/window combination (for rule statistics)
+#[derive(Debug, Clone)]
+pub struct UsageGroup {
+    pub app_name: String,
+    pub app_id: String,
+    pub window_title: Option<String>,
+    pub category: ProductivityCategory,
+    pub is_idle: bool,
+    pub seconds: i64,
+    pub sessions: i64,
+}
+
+/// Time per app/window/category for sessions started since `since`
+pub async fn usage_groups_since(since: DateTime<Utc>) -> Result<Vec<UsageGroup>> {
+    let conn = database::get_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT app_name, app_id, window_title, category, is_idle, SUM(duration_seconds), COUNT(*)
+         FROM app_usage_sessions
+         WHERE start_time >= ?1 AND end_time IS NOT NULL
+         GROUP BY app_name, app_id, window_title, category, is_idle",
+    )?;
+    let groups = stmt
+        .query_map(params![since], |row| {
+            let category: String = row.get(3)?;
+            Ok(UsageGroup {
+                app_name: row.get(0)?,
+                app_id: row.get(1)?,
+                window_title: row.get(2)?,
+                category: parse_category(&category),
+                is_idle: row.get(4)?,
+                seconds: row.get(5)?,
+                sessions: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(groups)
+}
+
+/// app_usage_sessions tables created before desktop tracking lack this column
+fn ensure_virtual_desktop_column(conn: &rusqlite::Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('app_usage_sessions') WHERE name = 'virtual_desktop'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_column {
+        conn.execute("ALTER TABLE app_usage_sessions ADD COLUMN virtual_desktop TEXT", [])?;
+    }
+    Ok(())
+}
+
+/// Per-window-title segments within an app session (window-level tracking)
+fn init_window_segments_table(conn: &Connection) -> Result<()> {
+    // Segments used to be keyed by session id, which gets reused; they can't
+    // be attributed reliably, so the old table is dropped
+    let keyed_by_id: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('app_usage_window_segments') WHERE name = 'session_id'",
+        [],
+        |row| row.get(0),
+    )?;
+    if keyed_by_id {
+        conn.execute("DROP TABLE app_usage_window_segments", [])?;
+    }
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_usage_window_segments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_start DATETIME NOT NULL,
+            app_name TEXT NOT NULL,
+            app_id TEXT NOT NULL,
+            window_title TEXT NOT NULL,
+            start_time DATETIME NOT NULL,
+            end_time DATETIME,
+            duration_seconds INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_app_usage_window_start ON app_usage_window_segments(start_time)",
+        [],
+    )?;
+    Ok(())
+}
+
 // Initialize database table for app usage sessions
 pub async fn init_database() -> Result<()> {
     let conn = database::get_connection()?;
@@ -407,6 +963,8 @@ pub async fn init_database() -> Result<()> {
         [],
     )?;
     
+    ensure_virtual_desktop_column(&conn)?;
+
     // Create indexes for better performance
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_app_usage_app_name ON app_usage_sessions(app_name)",
@@ -423,5 +981,168 @@ pub async fn init_database() -> Result<()> {
         [],
     )?;
     
+    init_window_segments_table(&conn)?;
+    usage_rollup::init_tables(&conn)?;
+    
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(app_id: &str, start: DateTime<Utc>, seconds: i64) -> AppUsageSession {
+        AppUsageSession {
+            id: Some(1),
+            app_name: app_id.to_string(),
+            app_id: app_id.to_string(),
+            window_title: None,
+            virtual_desktop: None,
+            category: ProductivityCategory::NEUTRAL,
+            start_time: start,
+            end_time: Some(start + Duration::seconds(seconds)),
+            duration_seconds: seconds,
+            is_idle: false,
+            is_active: false,
+            window_segments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_short_segment_folded_into_previous() {
+        let policy = AppUsageMergePolicy::default();
+        let start = Utc::now();
+        let mut prev = segment("code", start, 60);
+        let short = segment("slack", start + Duration::seconds(60), 2);
+
+        assert!(policy.merge(&mut prev, &short));
+        assert_eq!(prev.duration_seconds, 62);
+        assert_eq!(prev.end_time, short.end_time);
+    }
+
+    #[test]
+    fn test_same_app_merged_across_small_gap() {
+        let policy = AppUsageMergePolicy::default();
+        let start = Utc::now();
+        let mut prev = segment("code", start, 60);
+        let next = segment("code", start + Duration::seconds(70), 40);
+
+        assert!(policy.merge(&mut prev, &next));
+        assert_eq!(prev.duration_seconds, 100);
+    }
+
+    #[test]
+    fn test_distinct_segments_kept() {
+        let policy = AppUsageMergePolicy::default();
+        let start = Utc::now();
+        let mut prev = segment("code", start, 60);
+
+        // Different app, long enough to stand on its own
+        assert!(!policy.merge(&mut prev, &segment("slack", start + Duration::seconds(60), 30)));
+        // Same app but the gap is too large
+        assert!(!policy.merge(&mut prev, &segment("code", start + Duration::seconds(600), 30)));
+        // Same app on another virtual desktop
+        let mut other_desktop = segment("code", start + Duration::seconds(60), 30);
+        other_desktop.virtual_desktop = Some("2".to_string());
+        assert!(!policy.merge(&mut prev, &other_desktop));
+        assert_eq!(prev.duration_seconds, 60);
+    }
+
+    #[test]
+    fn test_usage_query_filters() {
+        let mut code = AppUsageSummary::new("Visual Studio Code".to_string(), "code".to_string());
+        code.add_time(ProductivityCategory::PRODUCTIVE, 100, false);
+        code.add_time(ProductivityCategory::NEUTRAL, 20, false);
+        code.add_time(ProductivityCategory::PRODUCTIVE, 30, true);
+        let mut slack = AppUsageSummary::new("Slack".to_string(), "slack".to_string());
+        slack.add_time(ProductivityCategory::NEUTRAL, 50, false);
+
+        let summary: HashMap<String, AppUsageSummary> =
+            [("Visual Studio Code".to_string(), code), ("Slack".to_string(), slack)].into_iter().collect();
+        let start = Utc::now();
+        let query = |app: Option<&str>, category| UsageQuery {
+            start,
+            end: start,
+            app: app.map(str::to_string),
+            category,
+        };
+
+        let mut by_app = summary.clone();
+        query(Some("studio"), None).apply(&mut by_app);
+        assert_eq!(by_app.len(), 1);
+        assert_eq!(by_app["Visual Studio Code"].total_time, 150);
+
+        let mut productive = summary.clone();
+        query(None, Some(ProductivityCategory::PRODUCTIVE)).apply(&mut productive);
+        assert_eq!(productive.len(), 1);
+        assert_eq!(productive["Visual Studio Code"].total_time, 100);
+        assert_eq!(productive["Visual Studio Code"].idle_time, 0);
+    }
+
+    #[test]
+    fn test_usage_totals_from_summary() {
+        let mut zoom = AppUsageSummary::new("Zoom".to_string(), "zoom.us".to_string());
+        zoom.add_time(ProductivityCategory::PRODUCTIVE, 600, false);
+        zoom.add_time(ProductivityCategory::PRODUCTIVE, 60, true);
+        let mut news = AppUsageSummary::new("News".to_string(), "news".to_string());
+        news.add_time(ProductivityCategory::UNPRODUCTIVE, 120, false);
+
+        let summary: HashMap<String, AppUsageSummary> =
+            [("Zoom".to_string(), zoom), ("News".to_string(), news)].into_iter().collect();
+        let totals = UsageTotals::from_summary(&summary);
+
+        assert_eq!(totals.active_seconds, 720);
+        assert_eq!(totals.idle_seconds, 60);
+        assert_eq!(totals.productive_seconds, 600);
+        assert_eq!(totals.unproductive_seconds, 120);
+        assert_eq!(totals.meeting_seconds, 600);
+    }
+
+    #[test]
+    fn test_window_segments_roll_up_to_app() {
+        let start = Utc::now();
+        let mut session = segment("excel", start, 0);
+        session.end_time = None;
+
+        session.switch_window(Some("budget.xlsx".to_string()), start);
+        session.switch_window(Some("budget.xlsx".to_string()), start + Duration::seconds(10));
+        session.switch_window(Some("forecast.xlsx".to_string()), start + Duration::seconds(30));
+        session.close_window_segment(start + Duration::seconds(45));
+
+        assert_eq!(session.window_segments.len(), 2);
+        assert_eq!(session.window_segments[0].duration_seconds, 30);
+        assert_eq!(session.window_segments[1].duration_seconds, 15);
+
+        let mut summary = AppUsageSummary::new("excel".to_string(), "excel".to_string());
+        summary.add_time(ProductivityCategory::NEUTRAL, 45, false);
+        summary.add_window_time(&session.window_segments);
+        assert_eq!(summary.total_time, 45);
+        assert_eq!(summary.window_time.values().sum::<i64>(), summary.total_time);
+    }
+
+    #[test]
+    fn test_stored_window_time_is_clipped_and_pruned() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE app_usage_window_segments (session_id INTEGER NOT NULL)", []).unwrap();
+        init_window_segments_table(&conn).unwrap();
+
+        let start = Utc::now() - Duration::days(40);
+        for (title, offset, seconds) in [("budget.xlsx", 0, 600), ("forecast.xlsx", 600, 300)] {
+            let segment_start = start + Duration::seconds(offset);
+            conn.execute(
+                "INSERT INTO app_usage_window_segments (
+                    session_start, app_name, app_id, window_title, start_time, end_time, duration_seconds
+                ) VALUES (?1, 'Excel', 'excel', ?2, ?3, ?4, ?5)",
+                params![start, title, segment_start, segment_start + Duration::seconds(seconds), seconds],
+            )
+            .unwrap();
+        }
+
+        let window_time = window_time_between(&conn, start + Duration::seconds(300), start + Duration::hours(1)).unwrap();
+        assert_eq!(window_time["Excel"]["budget.xlsx"], 300);
+        assert_eq!(window_time["Excel"]["forecast.xlsx"], 300);
+
+        assert_eq!(prune_window_segments(&conn, Utc::now()).unwrap(), 2);
+        assert!(window_time_between(&conn, start, start + Duration::hours(1)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+//! Command audit log
+//!
+//! Records every mutating Tauri command (clock in/out, login/logout, clearing
+//! the local database, installing updates, ...) with its timestamp and
+//! outcome. Entries are kept locally in the `audit_log` table and uploaded to
+//! the backend by the sync service, so disputes such as "the agent clocked me
+//! out" can be investigated afterwards.
+//!
+//! The table is intentionally NOT cleared by `clear_local_database`.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+use serde::Serialize;
+
+use super::database;
+use crate::error::AgentError;
+
+/// Maximum number of entries uploaded per sync pass
+const SYNC_BATCH_SIZE: usize = 50;
+
+/// Outcome of an audited command
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// The command started (used for commands that may never return, e.g. install_update)
+    Started,
+    /// A second factor was requested; the login succeeds only once it is verified
+    Challenged,
+    Success,
+    Failure,
+}
+
+impl AuditOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Started => "started",
+            AuditOutcome::Challenged => "challenged",
+            AuditOutcome::Success => "success",
+            AuditOutcome::Failure => "failure",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub command: String,
+    pub invoked_at: DateTime<Utc>,
+    pub outcome: String,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Create the audit_log table (called from database::init)
+pub fn init_table(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            command TEXT NOT NULL,
+            invoked_at DATETIME NOT NULL,
+            outcome TEXT NOT NULL,
+            error_code TEXT,
+            error_message TEXT,
+            synced BOOLEAN NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Record an audit entry. Failures are logged and never propagated so auditing
+/// can't break the command being audited.
+pub fn record_outcome(command: &str, outcome: AuditOutcome, error: Option<&AgentError>) {
+    let result = database::get_connection().and_then(|conn| {
+        conn.execute(
+            "INSERT INTO audit_log (command, invoked_at, outcome, error_code, error_message)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                command,
+                Utc::now(),
+                outcome.as_str(),
+                error.map(|e| e.code()),
+                error.map(|e| e.to_string()),
+            ],
+        )?;
+        Ok(())
+    });
+
+    if let Err(e) = result {
+        log::warn!("Failed to write audit log entry for {}: {}", command, e);
+    }
+}
+
+/// Record the result of a command invocation
+pub fn record<T>(command: &str, result: &Result<T, AgentError>) {
+    match result {
+        Ok(_) => record_outcome(command, AuditOutcome::Success, None),
+        Err(e) => record_outcome(command, AuditOutcome::Failure, Some(e)),
+    }
+}
+
+/// Get audit entries that have not been uploaded yet (oldest first)
+pub async fn get_unsynced_entries(limit: usize) -> Result<Vec<AuditEntry>> {
+    let conn = database::get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, command, invoked_at, outcome, error_code, error_message
+         FROM audit_log
+         WHERE synced = 0
+         ORDER BY id ASC
+         LIMIT ?1",
+    )?;
+
+    let entries = stmt
+        .query_map(params![limit as i64], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                command: row.get(1)?,
+                invoked_at: row.get(2)?,
+                outcome: row.get(3)?,
+                error_code: row.get(4)?,
+                error_message: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+pub async fn mark_synced(ids: &[i64]) -> Result<()> {
+    let conn = database::get_connection()?;
+    for id in ids {
+        conn.execute("UPDATE audit_log SET synced = 1 WHERE id = ?1", params![id])?;
+    }
+    Ok(())
+}
+
+/// Upload pending audit entries to the backend.
+/// Returns the number of entries synced.
+pub async fn sync_pending_entries() -> Result<usize> {
+    let entries = get_unsynced_entries(SYNC_BATCH_SIZE).await?;
+    if entries.is_empty() {
+        return Ok(0);
+    }
+
+    let client = crate::api::client::ApiClient::new().await?;
+    let payload = serde_json::json!({ "entries": entries });
+
+    let response = client.post_with_auth("/api/ingest/audit-log", &payload).await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Audit log upload failed with status: {}", response.status()));
+    }
+
+    let ids: Vec<i64> = entries.iter().map(|entry| entry.id).collect();
+    mark_synced(&ids).await?;
+
+    log::info!("Synced {} audit log entries", ids.len());
+    Ok(ids.len())
+}
@@ -60,4 +60,41 @@ pub async fn get_consent_status() -> Result<ConsentRecord> {
         }
         Err(e) => Err(e.into()),
     }
-}
\ No newline at end of file
+}
+/// Create the consent_scopes table (called from database::init). Scopes are
+/// optional data the employee opts into separately from the monitoring
+/// consent, e.g. `location`.
+pub fn init_scopes_table(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS consent_scopes (
+            scope TEXT PRIMARY KEY,
+            granted BOOLEAN NOT NULL,
+            updated_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub async fn set_scope_consent(scope: &str, granted: bool) -> Result<()> {
+    let conn = database::get_connection()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO consent_scopes (scope, granted, updated_at) VALUES (?1, ?2, ?3)",
+        params![scope, granted, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Whether the employee opted into `scope`; never asked counts as no
+pub async fn has_scope_consent(scope: &str) -> Result<bool> {
+    let conn = database::get_connection()?;
+    match conn.query_row(
+        "SELECT granted FROM consent_scopes WHERE scope = ?1",
+        params![scope],
+        |row| row.get(0),
+    ) {
+        Ok(granted) => Ok(granted),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
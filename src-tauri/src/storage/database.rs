@@ -2,9 +2,8 @@ use anyhow::Result;
 use rusqlite::Connection;
 use std::path::PathBuf;
 
-fn get_db_path() -> Result<PathBuf> {
-    let mut path = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("Failed to get data directory"))?;
-    path.push("TrackEx");
+pub(crate) fn get_db_path() -> Result<PathBuf> {
+    let mut path = crate::profile::data_dir().ok_or_else(|| anyhow::anyhow!("Failed to get data directory"))?;
     
     // Create directory with better error handling
     if let Err(e) = std::fs::create_dir_all(&path) {
@@ -143,6 +142,54 @@ pub async fn init() -> Result<()> {
                 [],
             )?;
 
+    // Command audit log (kept across clear_local_database)
+    super::audit_log::init_table(&conn)?;
+    
+    // Server-synced known site mapping cache
+    crate::api::known_sites::init_table(&conn)?;
+
+    // Server-synced window title scrub rules cache
+    crate::api::title_rules::init_table(&conn)?;
+    
+    // Recent sessions fetched from the backend (for offline display)
+    super::recent_sessions::init_table(&conn)?;
+
+    // Opt-in consent for optional data (location)
+    super::consent::init_scopes_table(&conn)?;
+
+    // Policy window for using the stored session offline
+    super::offline_validation::init_column(&conn)?;
+
+    // Weekly prune/VACUUM bookkeeping
+    super::maintenance::init_table(&conn)?;
+
+    // Opt-in local OCR text index
+    super::activity_index::init_table(&conn)?;
+
+    // Backend connectivity gaps (reported as offline_period events)
+    super::offline_periods::init_table(&conn)?;
+    super::offline_periods::close_interrupted(&conn)?;
+
+    // Local work history search (app focus and session notes)
+    super::work_session::ensure_note_column(&conn)?;
+    super::session_search::init_table(&conn)?;
+
+    // Backend id of synced sessions, for merging recent sessions
+    super::work_session::ensure_backend_id_column(&conn)?;
+
+    // Per-day report cache for weekly/monthly reports
+    super::report_cache::init_table(&conn)?;
+
+    // Cached app rules for conditional rule syncs
+    super::app_rules_cache::init_table(&conn)?;
+
+    // Tracking pause, restored at startup
+    super::pause_state::init_table(&conn)?;
+    super::pause_segments::init_table(&conn)?;
+
+    // Idle periods for the idle breakdown
+    super::idle_periods::init_table(&conn)?;
+
     log::info!("Database initialized successfully");
     Ok(())
 }
@@ -239,8 +286,11 @@ pub fn update_session_cache_validation() -> Result<()> {
 }
 
 /// Get or create a stable device UUID
-/// This UUID is generated once and persisted in SQLite to uniquely identify this device installation
-/// Used to prevent duplicate device records on the backend when registering
+/// The UUID is derived from the OS machine identifier (see `utils::machine_id`) so it
+/// survives reinstalls, and persisted in SQLite. Used to prevent duplicate device
+/// records on the backend when registering. Installs that stored a random UUID
+/// before are migrated: the hardware UUID becomes canonical and the old value is
+/// kept as `legacy_device_uuid` so the backend can merge the two records.
 pub fn get_or_create_device_uuid() -> Result<String> {
     let conn = get_connection()?;
     
@@ -249,10 +299,14 @@ pub fn get_or_create_device_uuid() -> Result<String> {
         "CREATE TABLE IF NOT EXISTS device_info (
             id INTEGER PRIMARY KEY CHECK (id = 1),
             device_uuid TEXT NOT NULL,
+            legacy_device_uuid TEXT,
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
         )",
         [],
     )?;
+    ensure_legacy_uuid_column(&conn)?;
+    
+    let hardware_uuid = crate::utils::machine_id::hardware_device_uuid();
     
     // Try to get existing UUID
     let existing_uuid: Result<String, rusqlite::Error> = conn.query_row(
@@ -262,13 +316,28 @@ pub fn get_or_create_device_uuid() -> Result<String> {
     );
     
     match existing_uuid {
-        Ok(uuid) => {
-            log::info!("Using existing device UUID: {}", uuid);
-            Ok(uuid)
-        }
+        Ok(uuid) => match hardware_uuid {
+            Some(hardware_uuid) if hardware_uuid != uuid => {
+                conn.execute(
+                    "UPDATE device_info
+                     SET device_uuid = ?1, legacy_device_uuid = COALESCE(legacy_device_uuid, ?2)
+                     WHERE id = 1",
+                    rusqlite::params![hardware_uuid, uuid],
+                )?;
+                log::info!("Migrated device UUID {} to hardware-derived {}", uuid, hardware_uuid);
+                Ok(hardware_uuid)
+            }
+            _ => {
+                log::info!("Using existing device UUID: {}", uuid);
+                Ok(uuid)
+            }
+        },
         Err(rusqlite::Error::QueryReturnedNoRows) => {
-            // Generate a new UUID
-            let new_uuid = uuid::Uuid::new_v4().to_string();
+            // Fall back to a random UUID if the machine identifier can't be read
+            let new_uuid = hardware_uuid.unwrap_or_else(|| {
+                log::warn!("Machine identifier unavailable, generating a random device UUID");
+                uuid::Uuid::new_v4().to_string()
+            });
             
             conn.execute(
                 "INSERT INTO device_info (id, device_uuid) VALUES (1, ?1)",
@@ -285,6 +354,33 @@ pub fn get_or_create_device_uuid() -> Result<String> {
     }
 }
 
+/// device_info tables created before the hardware-derived UUID lack this column
+fn ensure_legacy_uuid_column(conn: &Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('device_info') WHERE name = 'legacy_device_uuid'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_column {
+        conn.execute("ALTER TABLE device_info ADD COLUMN legacy_device_uuid TEXT", [])?;
+    }
+    Ok(())
+}
+
+/// The random device UUID used before migrating to the hardware-derived one, if any
+pub fn get_legacy_device_uuid() -> Result<Option<String>> {
+    let conn = get_connection()?;
+    match conn.query_row(
+        "SELECT legacy_device_uuid FROM device_info WHERE id = 1",
+        [],
+        |row| row.get(0),
+    ) {
+        Ok(uuid) => Ok(uuid),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 /// Get the stored device UUID (returns None if not yet created)
 #[allow(dead_code)]
 pub fn get_device_uuid() -> Result<Option<String>> {
@@ -0,0 +1,113 @@
+//! Attendance summary of a single day
+//!
+//! First clock-in, last clock-out and the day's time totals, computed from
+//! the local work sessions, pause segments and idle periods so the
+//! attendance view works offline. A day is a local calendar day; sessions
+//! belong to the day they started on, like everywhere else, while worked,
+//! idle and paused time are clipped to the day.
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use super::database;
+use crate::utils::clock;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DaySummary {
+    /// Local calendar date, e.g. "2024-05-02"
+    pub date: String,
+    pub first_clock_in: Option<DateTime<Utc>>,
+    /// None while a session of the day is still running
+    pub last_clock_out: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub session_count: usize,
+    /// Clocked-in time without pauses
+    pub worked_seconds: i64,
+    /// Worked time minus idle time
+    pub active_seconds: i64,
+    pub idle_seconds: i64,
+    /// Time between sessions
+    pub break_seconds: i64,
+    pub paused_seconds: i64,
+}
+
+pub async fn summarize(day: NaiveDate) -> Result<DaySummary> {
+    summarize_with(&database::get_connection()?, day, clock::now())
+}
+
+fn summarize_with(conn: &Connection, day: NaiveDate, now: DateTime<Utc>) -> Result<DaySummary> {
+    let (start, end) = crate::utils::time::day_bounds(day);
+    let mut stmt = conn.prepare(
+        "SELECT started_at, ended_at FROM work_sessions
+         WHERE started_at >= ?1 AND started_at < ?2
+         ORDER BY started_at ASC",
+    )?;
+    let sessions = stmt
+        .query_map(params![start, end], |row| {
+            Ok((row.get::<_, DateTime<Utc>>(0)?, row.get::<_, Option<DateTime<Utc>>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let is_active = sessions.iter().any(|(_, ended_at)| ended_at.is_none());
+    let worked_seconds = super::work_session::worked_seconds_with(conn, start, end, now)?;
+    let idle_seconds = super::idle_periods::breakdown_between(conn, start, end.min(now))?.total_idle_seconds;
+
+    Ok(DaySummary {
+        date: day.format("%Y-%m-%d").to_string(),
+        first_clock_in: sessions.first().map(|(started_at, _)| *started_at),
+        last_clock_out: if is_active {
+            None
+        } else {
+            sessions.iter().filter_map(|(_, ended_at)| *ended_at).max()
+        },
+        is_active,
+        session_count: sessions.len(),
+        worked_seconds,
+        active_seconds: (worked_seconds - idle_seconds).max(0),
+        idle_seconds,
+        break_seconds: super::work_session::break_seconds_with(conn, start, end)?,
+        paused_seconds: super::pause_segments::paused_seconds_between_with(conn, start, end, now)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Local, TimeZone};
+
+    #[test]
+    fn test_summary_of_two_sessions() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE work_sessions (id INTEGER PRIMARY KEY, started_at DATETIME NOT NULL, ended_at DATETIME, is_active BOOLEAN)",
+            [],
+        )
+        .unwrap();
+        super::super::pause_segments::init_table(&conn).unwrap();
+        super::super::idle_periods::init_table(&conn).unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let at = |hour: u32| Local.from_local_datetime(&day.and_hms_opt(hour, 0, 0).unwrap()).unwrap().with_timezone(&Utc);
+
+        for (started_at, ended_at) in [(at(9), at(12)), (at(13), at(17))] {
+            conn.execute(
+                "INSERT INTO work_sessions (started_at, ended_at, is_active) VALUES (?1, ?2, 0)",
+                params![started_at, ended_at],
+            )
+            .unwrap();
+        }
+
+        let now = at(20);
+        let summary = summarize_with(&conn, day, now).unwrap();
+        assert_eq!(summary.date, "2024-03-04");
+        assert_eq!(summary.first_clock_in, Some(at(9)));
+        assert_eq!(summary.last_clock_out, Some(at(17)));
+        assert_eq!(summary.session_count, 2);
+        assert_eq!(summary.worked_seconds, Duration::hours(7).num_seconds());
+        assert_eq!(summary.break_seconds, Duration::hours(1).num_seconds());
+        assert!(!summary.is_active);
+
+        assert_eq!(summarize_with(&conn, day + Duration::days(1), now).unwrap().session_count, 0);
+    }
+}
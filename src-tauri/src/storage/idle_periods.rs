@@ -0,0 +1,186 @@
+//! Local record of idle periods
+//!
+//! idle_start/idle_end events are only queued for the backend, so the agent
+//! could report nothing but the current idle counter. Each user idle period
+//! (from the last input to the first input after it) is also written to the
+//! `idle_periods` table, tagged with whether a meeting app was in focus, and
+//! `get_detailed_idle_info` summarizes today's periods from it. Periods are
+//! kept for `RETENTION_DAYS`.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use super::database;
+
+/// Idle periods shorter than this count as short idles
+pub const SHORT_IDLE_SECONDS: i64 = 5 * 60;
+
+/// How long idle periods are kept
+const RETENTION_DAYS: i64 = 30;
+
+/// Today's idle periods, summarized
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IdleBreakdown {
+    /// Idle periods shorter than `SHORT_IDLE_SECONDS`
+    pub short_idle_count: u32,
+    pub long_idle_count: u32,
+    pub total_idle_seconds: i64,
+    /// Idle time while a meeting app was in focus
+    pub meeting_idle_seconds: i64,
+    pub longest_idle_seconds: i64,
+    pub longest_idle_started_at: Option<DateTime<Utc>>,
+}
+
+/// Create the idle_periods table (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS idle_periods (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at DATETIME NOT NULL,
+            ended_at DATETIME,
+            app_name TEXT,
+            in_meeting BOOLEAN NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_idle_periods_started_at ON idle_periods(started_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn open_period(conn: &Connection, started_at: DateTime<Utc>, app_name: Option<&str>, in_meeting: bool) -> Result<()> {
+    // A period left open by a crash ends where the new one starts
+    close_period(conn, started_at)?;
+    conn.execute(
+        "INSERT INTO idle_periods (started_at, app_name, in_meeting) VALUES (?1, ?2, ?3)",
+        params![started_at, app_name, in_meeting],
+    )?;
+    Ok(())
+}
+
+fn close_period(conn: &Connection, ended_at: DateTime<Utc>) -> Result<()> {
+    conn.execute(
+        "UPDATE idle_periods SET ended_at = MAX(started_at, ?1) WHERE ended_at IS NULL",
+        params![ended_at],
+    )?;
+    Ok(())
+}
+
+/// Insert a finished idle period (used by the mock data generator)
+#[allow(dead_code)]
+pub(super) fn insert_period(
+    conn: &Connection,
+    started_at: DateTime<Utc>,
+    ended_at: DateTime<Utc>,
+    app_name: Option<&str>,
+    in_meeting: bool,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO idle_periods (started_at, ended_at, app_name, in_meeting) VALUES (?1, ?2, ?3, ?4)",
+        params![started_at, ended_at, app_name, in_meeting],
+    )?;
+    Ok(())
+}
+
+/// Breakdown of idle periods overlapping `[start, now)`; open periods count
+/// up to `now` and periods are clipped to the range
+pub fn breakdown_between(conn: &Connection, start: DateTime<Utc>, now: DateTime<Utc>) -> Result<IdleBreakdown> {
+    let mut stmt = conn.prepare(
+        "SELECT started_at, ended_at, in_meeting FROM idle_periods
+         WHERE started_at < ?2 AND (ended_at IS NULL OR ended_at > ?1)",
+    )?;
+    let periods = stmt
+        .query_map(params![start, now], |row| {
+            Ok((
+                row.get::<_, DateTime<Utc>>(0)?,
+                row.get::<_, Option<DateTime<Utc>>>(1)?,
+                row.get::<_, bool>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut breakdown = IdleBreakdown::default();
+    for (started_at, ended_at, in_meeting) in periods {
+        let from = started_at.max(start);
+        let seconds = (ended_at.unwrap_or(now).min(now) - from).num_seconds().max(0);
+        if seconds < SHORT_IDLE_SECONDS {
+            breakdown.short_idle_count += 1;
+        } else {
+            breakdown.long_idle_count += 1;
+        }
+        breakdown.total_idle_seconds += seconds;
+        if in_meeting {
+            breakdown.meeting_idle_seconds += seconds;
+        }
+        if seconds > breakdown.longest_idle_seconds {
+            breakdown.longest_idle_seconds = seconds;
+            breakdown.longest_idle_started_at = Some(from);
+        }
+    }
+    Ok(breakdown)
+}
+
+/// Record an idle state change detected by the idle service. `idle_time` is
+/// the OS idle counter; an idle period starts at the last input.
+pub async fn record_idle_change(is_idle: bool, idle_time_seconds: u64) {
+    let now = Utc::now();
+    let current = super::app_usage::get_current_session().await;
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = database::get_connection()?;
+        if is_idle {
+            let started_at = now - Duration::seconds(idle_time_seconds as i64);
+            let in_meeting = current
+                .as_ref()
+                .is_some_and(|session| super::app_usage::is_meeting_app(&session.app_name, &session.app_id));
+            open_period(&conn, started_at, current.as_ref().map(|s| s.app_name.as_str()), in_meeting)
+        } else {
+            close_period(&conn, now)
+        }
+    })
+    .await;
+    match result {
+        Ok(Err(e)) => log::warn!("Failed to record idle period: {}", e),
+        Err(e) => log::warn!("Idle period task failed: {}", e),
+        Ok(Ok(())) => {}
+    }
+}
+
+/// Delete idle periods past the retention period (called from maintenance)
+pub fn prune(conn: &Connection, now: DateTime<Utc>) -> Result<usize> {
+    Ok(conn.execute(
+        "DELETE FROM idle_periods WHERE ended_at IS NOT NULL AND ended_at < ?1",
+        params![now - Duration::days(RETENTION_DAYS)],
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown_of_stored_periods() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+        let day_start = Utc::now() - Duration::hours(8);
+
+        // Started before the day: only the part inside counts
+        open_period(&conn, day_start - Duration::minutes(30), Some("Code"), false).unwrap();
+        close_period(&conn, day_start + Duration::minutes(2)).unwrap();
+        open_period(&conn, day_start + Duration::hours(1), Some("zoom.us"), true).unwrap();
+        close_period(&conn, day_start + Duration::hours(1) + Duration::minutes(20)).unwrap();
+        // Still idle
+        open_period(&conn, day_start + Duration::hours(8) - Duration::minutes(6), None, false).unwrap();
+
+        let breakdown = breakdown_between(&conn, day_start, day_start + Duration::hours(8)).unwrap();
+        assert_eq!(breakdown.short_idle_count, 1);
+        assert_eq!(breakdown.long_idle_count, 2);
+        assert_eq!(breakdown.total_idle_seconds, (2 + 20 + 6) * 60);
+        assert_eq!(breakdown.meeting_idle_seconds, 20 * 60);
+        assert_eq!(breakdown.longest_idle_seconds, 20 * 60);
+        assert_eq!(breakdown.longest_idle_started_at, Some(day_start + Duration::hours(1)));
+    }
+}
@@ -0,0 +1,203 @@
+//! Periodic database maintenance
+//!
+//! Delivered queue rows are only marked `processed`, so on long-lived installs
+//! the SQLite file keeps growing. About once a week, while the user is away
+//! (or not clocked in), this task prunes old processed and abandoned queue
+//! rows and synced audit entries, then runs `ANALYZE` and `VACUUM` to give the
+//! space back to the OS. The time of the last run is kept in the database so
+//! restarts don't reset the schedule.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+
+use super::database;
+
+/// Minimum time between maintenance runs
+const MAINTENANCE_INTERVAL_DAYS: i64 = 7;
+
+/// How often the scheduler checks whether maintenance is due
+const CHECK_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Idle time after which maintenance may run during a work session
+const MIN_IDLE_SECS: u64 = 10 * 60;
+
+/// Delivered queue rows are kept this long (for diagnostics)
+const PROCESSED_RETENTION_DAYS: i64 = 7;
+
+/// Rows that exhausted their retries are kept this long
+const FAILED_RETENTION_DAYS: i64 = 30;
+
+/// Audit entries already uploaded are kept this long
+const AUDIT_RETENTION_DAYS: i64 = 90;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PruneSummary {
+    pub events: usize,
+    pub heartbeats: usize,
+    pub audit_entries: usize,
+}
+
+/// Create the maintenance bookkeeping table (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS maintenance_runs (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            last_run_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn last_run(conn: &Connection) -> Option<DateTime<Utc>> {
+    conn.query_row("SELECT last_run_at FROM maintenance_runs WHERE id = 1", [], |row| row.get(0))
+        .ok()
+}
+
+fn is_due(last_run: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    last_run.is_none_or(|at| now - at >= Duration::days(MAINTENANCE_INTERVAL_DAYS))
+}
+
+/// Delete delivered and abandoned queue rows and old synced audit entries
+fn prune(conn: &Connection, now: DateTime<Utc>) -> Result<PruneSummary> {
+    let processed_cutoff = now - Duration::days(PROCESSED_RETENTION_DAYS);
+    let failed_cutoff = now - Duration::days(FAILED_RETENTION_DAYS);
+    let queue_sql = |table: &str| {
+        format!(
+            "DELETE FROM {} WHERE (processed = 1 AND timestamp < ?1)
+                OR (processed = 0 AND retry_count >= max_retries AND timestamp < ?2)",
+            table
+        )
+    };
+
+    Ok(PruneSummary {
+        events: conn.execute(&queue_sql("event_queue"), params![processed_cutoff, failed_cutoff])?,
+        heartbeats: conn.execute(&queue_sql("heartbeat_queue"), params![processed_cutoff, failed_cutoff])?,
+        audit_entries: conn.execute(
+            "DELETE FROM audit_log WHERE synced = 1 AND invoked_at < ?1",
+            params![now - Duration::days(AUDIT_RETENTION_DAYS)],
+        )?,
+    })
+}
+
+/// Prune, analyze and vacuum the database now
+pub fn run_maintenance() -> Result<PruneSummary> {
+    let conn = database::get_connection()?;
+    let now = Utc::now();
+    let size_before = database::get_db_path().ok().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len());
+
+    let summary = prune(&conn, now)?;
+    match super::activity_index::prune(&conn, now) {
+        Ok(removed) if removed > 0 => log::info!("Pruned {} expired activity index entries", removed),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to prune activity index: {}", e),
+    }
+    match super::session_search::prune(&conn, now) {
+        Ok(removed) if removed > 0 => log::info!("Pruned {} expired session search entries", removed),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to prune session search index: {}", e),
+    }
+    match super::idle_periods::prune(&conn, now) {
+        Ok(removed) if removed > 0 => log::info!("Pruned {} expired idle periods", removed),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to prune idle periods: {}", e),
+    }
+    match super::app_usage::prune_window_segments(&conn, now) {
+        Ok(removed) if removed > 0 => log::info!("Pruned {} expired window segments", removed),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to prune window segments: {}", e),
+    }
+    conn.execute_batch("ANALYZE; VACUUM;")?;
+    conn.execute(
+        "INSERT OR REPLACE INTO maintenance_runs (id, last_run_at) VALUES (1, ?1)",
+        params![now],
+    )?;
+
+    let size_after = database::get_db_path().ok().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len());
+    log::info!(
+        "Database maintenance: pruned {} events, {} heartbeats, {} audit entries; size {:?} -> {:?} bytes",
+        summary.events,
+        summary.heartbeats,
+        summary.audit_entries,
+        size_before,
+        size_after
+    );
+    Ok(summary)
+}
+
+/// Whether now is a good time: nobody is working, or the user has been idle a while
+async fn is_quiet_period() -> bool {
+    if !crate::storage::work_session::is_session_active().await.unwrap_or(false) {
+        return true;
+    }
+    crate::sampling::idle_detector::get_idle_time().await.unwrap_or(0) >= MIN_IDLE_SECS
+}
+
+/// Run maintenance roughly weekly during quiet periods (runs for the app's lifetime)
+pub async fn start_maintenance_scheduler() {
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+
+        let due = database::get_connection()
+            .map(|conn| is_due(last_run(&conn), Utc::now()))
+            .unwrap_or(false);
+        if !due || !is_quiet_period().await {
+            continue;
+        }
+
+        match tokio::task::spawn_blocking(run_maintenance).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => log::warn!("Database maintenance failed: {}", e),
+            Err(e) => log::warn!("Database maintenance task panicked: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_due_weekly() {
+        let now = Utc::now();
+        assert!(is_due(None, now));
+        assert!(!is_due(Some(now - Duration::days(6)), now));
+        assert!(is_due(Some(now - Duration::days(7)), now));
+    }
+
+    #[test]
+    fn test_prune_keeps_pending_and_recent_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        for table in ["event_queue", "heartbeat_queue"] {
+            conn.execute(
+                &format!(
+                    "CREATE TABLE {} (id INTEGER PRIMARY KEY, timestamp DATETIME NOT NULL,
+                        processed BOOLEAN NOT NULL DEFAULT 0, retry_count INTEGER NOT NULL DEFAULT 0,
+                        max_retries INTEGER NOT NULL DEFAULT 3)",
+                    table
+                ),
+                [],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "CREATE TABLE audit_log (id INTEGER PRIMARY KEY, invoked_at DATETIME NOT NULL, synced BOOLEAN NOT NULL)",
+            [],
+        )
+        .unwrap();
+
+        let now = Utc::now();
+        let old = now - Duration::days(40);
+        let insert = "INSERT INTO event_queue (timestamp, processed, retry_count) VALUES (?1, ?2, ?3)";
+        conn.execute(insert, params![old, 1, 0]).unwrap(); // delivered long ago
+        conn.execute(insert, params![now, 1, 0]).unwrap(); // delivered recently
+        conn.execute(insert, params![old, 0, 3]).unwrap(); // abandoned
+        conn.execute(insert, params![old, 0, 1]).unwrap(); // still pending
+        conn.execute("INSERT INTO audit_log (invoked_at, synced) VALUES (?1, 1), (?1, 0)", params![now - Duration::days(100)])
+            .unwrap();
+
+        let summary = prune(&conn, now).unwrap();
+        assert_eq!(summary, PruneSummary { events: 2, heartbeats: 0, audit_entries: 1 });
+    }
+}
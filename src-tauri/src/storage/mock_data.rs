@@ -0,0 +1,235 @@
+//! Fake tracking history for UI development
+//!
+//! `generate_mock_data` fills the local database with plausible work
+//! sessions (split by a lunch break), app usage with rollups, idle periods,
+//! search entries and screenshot metadata for every weekday in a date range,
+//! so dashboards can be built without tracking for weeks. Output is
+//! deterministic for a given seed.
+//!
+//! Only compiled (and registered) in builds with the `mock-data` cargo
+//! feature. Generated
+//! rows are local only: screenshot entries are marked as exhausted uploads
+//! and never sent. `clear_local_database` removes everything again.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::error::AgentError;
+
+/// Longest range that can be generated at once
+const MAX_DAYS: i64 = 180;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MockDataSummary {
+    pub days: u32,
+    pub work_sessions: u32,
+    pub app_segments: u32,
+    pub idle_periods: u32,
+    pub screenshots: u32,
+}
+
+mod generator {
+    use super::MockDataSummary;
+    use crate::storage::{idle_periods, screenshot_queue, session_search, usage_rollup};
+    use crate::utils::productivity::ProductivityCategory;
+    use anyhow::Result;
+    use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc, Weekday};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+    use rusqlite::{params, Connection};
+
+    /// (app name, app id, category, window titles, domain)
+    type MockApp = (&'static str, &'static str, ProductivityCategory, &'static [&'static str], Option<&'static str>);
+
+    const APPS: &[MockApp] = &[
+        ("Visual Studio Code", "code", ProductivityCategory::PRODUCTIVE, &["main.rs — agent", "README.md — dashboard", "api.ts — web"], None),
+        ("Slack", "slack", ProductivityCategory::NEUTRAL, &["#engineering", "#general", "Direct message"], None),
+        ("Google Chrome", "chrome", ProductivityCategory::PRODUCTIVE, &["Pull request #42 - GitHub", "Sprint board - Jira"], Some("github.com")),
+        ("Google Chrome", "chrome", ProductivityCategory::UNPRODUCTIVE, &["YouTube", "Reddit - Home"], Some("youtube.com")),
+        ("zoom.us", "zoom", ProductivityCategory::NEUTRAL, &["Zoom Meeting", "Daily standup"], None),
+        ("Microsoft Excel", "excel", ProductivityCategory::PRODUCTIVE, &["Q3 budget.xlsx", "Timesheet.xlsx"], None),
+        ("Terminal", "terminal", ProductivityCategory::PRODUCTIVE, &["cargo test", "ssh build-server"], None),
+        ("Spotify", "spotify", ProductivityCategory::UNPRODUCTIVE, &["Focus playlist"], None),
+    ];
+
+    fn local_time(day: NaiveDate, minutes: i64) -> DateTime<Utc> {
+        let naive = day.and_hms_opt(0, 0, 0).unwrap_or_default() + Duration::minutes(minutes);
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|| DateTime::from_naive_utc_and_offset(naive, Utc))
+    }
+
+    /// Fill `[start, end)` of one work session with app segments, idle
+    /// periods and screenshots
+    fn fill_session(
+        conn: &Connection,
+        rng: &mut StdRng,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        summary: &mut MockDataSummary,
+    ) -> Result<()> {
+        let mut cursor = start;
+        let mut next_screenshot = start + Duration::minutes(rng.gen_range(5..15));
+        while cursor < end {
+            let (app_name, app_id, category, titles, domain) = APPS.choose(rng).unwrap_or(&APPS[0]).clone();
+            let title = titles.choose(rng).copied();
+            let segment_end = (cursor + Duration::minutes(rng.gen_range(2..40))).min(end);
+            // Now and then the employee walks away mid-segment
+            let is_idle = rng.gen_bool(0.08);
+
+            conn.execute(
+                "INSERT INTO app_usage_sessions
+                    (app_name, app_id, window_title, category, start_time, end_time, duration_seconds, is_idle, is_active)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 0)",
+                params![
+                    app_name,
+                    app_id,
+                    title,
+                    category.to_string(),
+                    cursor,
+                    segment_end,
+                    (segment_end - cursor).num_seconds(),
+                    is_idle
+                ],
+            )?;
+            usage_rollup::add_segment(conn, app_name, app_id, &category, is_idle, cursor, segment_end, true)?;
+            session_search::insert_activity(conn, cursor, app_name, title, domain)?;
+            summary.app_segments += 1;
+
+            if is_idle {
+                idle_periods::insert_period(conn, cursor, segment_end, Some(app_name), app_id == "zoom")?;
+                summary.idle_periods += 1;
+            }
+            while next_screenshot < segment_end {
+                if !is_idle {
+                    conn.execute(
+                        "INSERT INTO screenshot_queue (file_path, employee_id, device_id, taken_at, retry_count)
+                         VALUES (?1, 'mock', 'mock', ?2, ?3)",
+                        params![
+                            format!("mock://screenshot-{}.jpg", next_screenshot.timestamp()),
+                            next_screenshot,
+                            screenshot_queue::MAX_RETRIES
+                        ],
+                    )?;
+                    summary.screenshots += 1;
+                }
+                next_screenshot += Duration::minutes(rng.gen_range(8..15));
+            }
+            cursor = segment_end;
+        }
+        Ok(())
+    }
+
+    pub fn generate(conn: &mut Connection, start: NaiveDate, end: NaiveDate, seed: u64) -> Result<MockDataSummary> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut summary = MockDataSummary::default();
+        let tx = conn.transaction()?;
+
+        for day in start.iter_days().take_while(|day| *day <= end) {
+            if matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+                continue;
+            }
+            summary.days += 1;
+
+            // Morning and afternoon sessions around a lunch break (minutes since midnight)
+            let clock_in = rng.gen_range(8 * 60..9 * 60 + 30);
+            let lunch = rng.gen_range(12 * 60..13 * 60);
+            let back = lunch + rng.gen_range(30..60);
+            let clock_out = back + rng.gen_range(3 * 60..5 * 60);
+            for (from, to) in [(clock_in, lunch), (back, clock_out)] {
+                let (session_start, session_end) = (local_time(day, from), local_time(day, to));
+                tx.execute(
+                    "INSERT INTO work_sessions (started_at, ended_at, is_active) VALUES (?1, ?2, 0)",
+                    params![session_start, session_end],
+                )?;
+                summary.work_sessions += 1;
+                fill_session(&tx, &mut rng, session_start, session_end, &mut summary)?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(summary)
+    }
+}
+
+/// Populate the local database with fake history for `[start_date, end_date]`
+/// (YYYY-MM-DD, default: the last 14 days)
+#[tauri::command]
+pub async fn generate_mock_data(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    seed: Option<u64>,
+) -> Result<MockDataSummary, AgentError> {
+    let parse = |name: &str, value: Option<String>| {
+        value
+            .map(|v| {
+                NaiveDate::parse_from_str(&v, "%Y-%m-%d")
+                    .map_err(|e| AgentError::Internal(format!("Invalid {} '{}': {}", name, v, e)))
+            })
+            .transpose()
+    };
+    let yesterday = chrono::Local::now().date_naive() - chrono::Duration::days(1);
+    let end = parse("end_date", end_date)?.unwrap_or(yesterday);
+    let start = parse("start_date", start_date)?.unwrap_or(end - chrono::Duration::days(13));
+    if start > end || (end - start).num_days() >= MAX_DAYS {
+        return Err(AgentError::Internal(format!(
+            "start_date must be before end_date and at most {} days earlier",
+            MAX_DAYS
+        )));
+    }
+
+    let seed = seed.unwrap_or(42);
+    let summary = tokio::task::spawn_blocking(move || {
+        let mut conn = super::database::get_connection()?;
+        generator::generate(&mut conn, start, end, seed)
+    })
+    .await
+    .map_err(|e| AgentError::Internal(e.to_string()))?
+    .map_err(|e| AgentError::Storage(e.to_string()))?;
+    log::info!("Generated mock data for {} to {}: {:?}", start, end, summary);
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE work_sessions (id INTEGER PRIMARY KEY, started_at DATETIME, ended_at DATETIME, is_active BOOLEAN);
+             CREATE TABLE app_usage_sessions (
+                id INTEGER PRIMARY KEY, app_name TEXT, app_id TEXT, window_title TEXT, category TEXT,
+                start_time DATETIME, end_time DATETIME, duration_seconds INTEGER, is_idle BOOLEAN, is_active BOOLEAN
+             );
+             CREATE TABLE screenshot_queue (
+                id INTEGER PRIMARY KEY, file_path TEXT, employee_id TEXT, device_id TEXT,
+                taken_at DATETIME, retry_count INTEGER, last_attempt DATETIME
+             );",
+        )
+        .unwrap();
+        crate::storage::usage_rollup::init_tables(&conn).unwrap();
+        crate::storage::session_search::init_table(&conn).unwrap();
+        crate::storage::idle_periods::init_table(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_generates_weekday_history() {
+        // Monday to Sunday
+        let start = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 6, 9).unwrap();
+        let summary = generator::generate(&mut test_db(), start, end, 7).unwrap();
+        assert_eq!(summary.days, 5);
+        assert_eq!(summary.work_sessions, 10);
+        assert!(summary.app_segments > 50);
+        assert!(summary.screenshots > 0);
+
+        // Same seed, same data
+        assert_eq!(generator::generate(&mut test_db(), start, end, 7).unwrap(), summary);
+    }
+}
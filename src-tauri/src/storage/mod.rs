@@ -5,12 +5,30 @@ pub mod work_session;
 pub mod offline_queue;
 pub mod app_usage;
 pub mod screenshot_queue;
+pub mod state_service;
+pub mod audit_log;
+pub mod recent_sessions;
+pub mod offline_validation;
+pub mod storage_guard;
+pub mod maintenance;
+pub mod activity_index;
+pub mod offline_periods;
+pub mod usage_rollup;
+pub mod session_search;
+pub mod report_cache;
+pub mod app_rules_cache;
+pub mod pause_state;
+pub mod pause_segments;
+pub mod day_summary;
+pub mod idle_periods;
+#[cfg(feature = "mock-data")]
+pub mod mock_data;
 
 use anyhow::Result;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 use std::sync::OnceLock;
 
+pub use state_service::{SessionCredentials, StateHandle};
+
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub device_token: Option<String>,
@@ -57,131 +75,83 @@ impl AppState {
     }
 }
 
-// Global app state manager
-static GLOBAL_APP_STATE: OnceLock<Arc<Mutex<AppState>>> = OnceLock::new();
-
-pub fn set_global_app_state(state: Arc<Mutex<AppState>>) {
-    GLOBAL_APP_STATE.set(state).expect("Failed to set global app state");
-}
+// Global app state service
+static STATE_SERVICE: OnceLock<StateHandle> = OnceLock::new();
 
-// Function to sync device token from Tauri-managed AppState to Global AppState
-pub async fn sync_device_token_to_global(device_token: String, device_id: String, email: String, server_url: String, employee_id: String) -> Result<()> {
-    match get_global_app_state() {
-        Ok(global_state) => {
-            let mut state = global_state.lock().await;
-            state.device_token = Some(device_token);
-            state.device_id = Some(device_id);
-            state.email = Some(email);
-            state.server_url = Some(server_url);
-            state.employee_id = Some(employee_id);
-            Ok(())
-        }
-        Err(e) => {
-            // If global state is not initialized, log warning but don't fail
-            log::warn!("Global app state not initialized yet, skipping device token sync: {}", e);
-            Ok(())
-        }
+pub fn set_state_service(handle: StateHandle) {
+    if STATE_SERVICE.set(handle).is_err() {
+        log::warn!("App state service already initialized");
     }
 }
 
-pub fn get_global_app_state() -> Result<Arc<Mutex<AppState>>> {
-    GLOBAL_APP_STATE.get()
+pub fn get_state_service() -> Result<StateHandle> {
+    STATE_SERVICE.get()
         .cloned()
-        .ok_or_else(|| anyhow::anyhow!("Global app state not initialized"))
+        .ok_or_else(|| anyhow::anyhow!("App state service not initialized"))
+}
+
+/// Lock-free copy of the current app state, if the state service is running
+pub fn get_state_snapshot() -> Result<AppState> {
+    get_state_service().map(|service| service.snapshot())
+}
+
+fn default_server_url() -> String {
+    if let Some(url) = crate::profile::default_server_url() {
+        return url.to_string();
+    }
+    #[cfg(debug_assertions)]
+    {
+        "http://localhost:3000".to_string()
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        "https://www.trackex.app".to_string()
+    }
 }
 
 // Global storage functions
 pub async fn get_server_url() -> Result<String> {
-    // Try to get the server URL from the global app state, fallback to default if not available
-    match get_global_app_state() {
-        Ok(app_state) => {
-            let state = app_state.lock().await;
-            if let Some(url) = &state.server_url {
-                Ok(url.clone())
+    // Try to get the server URL from the app state, fallback to default if not available
+    match get_state_snapshot() {
+        Ok(state) => {
+            if let Some(url) = state.server_url {
+                Ok(url)
             } else {
                 log::warn!("No server URL found in app state, using default");
-                #[cfg(debug_assertions)]
-                {
-                    Ok("http://localhost:3000".to_string())
-                }
-                #[cfg(not(debug_assertions))]
-                {
-                    Ok("https://www.trackex.app".to_string())
-                }
+                Ok(default_server_url())
             }
         }
         Err(_) => {
-            log::warn!("Global app state not available, using default server URL");
-            #[cfg(debug_assertions)]
-            {
-                Ok("http://localhost:3000".to_string())
-            }
-            #[cfg(not(debug_assertions))]
-            {
-                Ok("https://www.trackex.app".to_string())
-            }
+            log::warn!("App state service not available, using default server URL");
+            Ok(default_server_url())
         }
     }
 }
 
 pub async fn get_device_token() -> Result<String> {
-    // Try to get the device token from the global app state, fallback to empty if not available
-    match get_global_app_state() {
-        Ok(app_state) => {
-            let state = app_state.lock().await;
-            if let Some(token) = &state.device_token {
-                if !token.is_empty() {
-                    Ok(token.clone())
-                } else {
-                    Err(anyhow::anyhow!("Device token is empty - user not authenticated"))
-                }
-            } else {
-                Err(anyhow::anyhow!("No device token found - user not authenticated"))
-            }
-        }
-        Err(_) => {
-            Err(anyhow::anyhow!("Global app state not available"))
-        }
+    let state = get_state_snapshot()?;
+    match state.device_token {
+        Some(token) if !token.is_empty() => Ok(token),
+        // Missing/empty token surfaces to commands as AgentError::NotAuthenticated
+        _ => Err(crate::error::AgentError::NotAuthenticated.into()),
     }
 }
 
 pub async fn get_device_id() -> Result<String> {
-    match get_global_app_state() {
-        Ok(app_state) => {
-            let state = app_state.lock().await;
-            if let Some(device_id) = &state.device_id {
-                if !device_id.is_empty() {
-                    Ok(device_id.clone())
-                } else {
-                    Err(anyhow::anyhow!("Device ID is empty - user not authenticated"))
-                }
-            } else {
-                Err(anyhow::anyhow!("No device ID found - user not authenticated"))
-            }
-        }
-        Err(_) => {
-            Err(anyhow::anyhow!("Global app state not available"))
-        }
+    let state = get_state_snapshot()?;
+    match state.device_id {
+        Some(device_id) if !device_id.is_empty() => Ok(device_id),
+        Some(_) => Err(anyhow::anyhow!("Device ID is empty - user not authenticated")),
+        None => Err(anyhow::anyhow!("No device ID found - user not authenticated")),
     }
 }
 
 pub async fn get_employee_id() -> Result<String> {
-    match get_global_app_state() {
-        Ok(app_state) => {
-            let state = app_state.lock().await;
-            if let Some(employee_id) = &state.employee_id {
-                if !employee_id.is_empty() {
-                    Ok(employee_id.clone())
-                } else {
-                    Err(anyhow::anyhow!("Employee ID is empty - user not authenticated"))
-                }
-            } else {
-                Err(anyhow::anyhow!("No employee ID found - user not authenticated"))
-            }
-        }
-        Err(_) => {
-            Err(anyhow::anyhow!("Global app state not available"))
-        }
+    let state = get_state_snapshot()?;
+    match state.employee_id {
+        Some(employee_id) if !employee_id.is_empty() => Ok(employee_id),
+        Some(_) => Err(anyhow::anyhow!("Employee ID is empty - user not authenticated")),
+        None => Err(anyhow::anyhow!("No employee ID found - user not authenticated")),
     }
 }
 
@@ -271,6 +241,18 @@ pub async fn check_version_and_migrate() -> Result<bool> {
                             log::info!("Cleared app_usage_sessions table");
                         }
                         
+                        if let Err(e) = conn.execute("DELETE FROM app_usage_window_segments", []) {
+                            log::warn!("Failed to clear app_usage_window_segments table: {}", e);
+                        }
+                        
+                        if let Err(e) = conn.execute("DELETE FROM pause_segments", []) {
+                            log::warn!("Failed to clear pause_segments table: {}", e);
+                        }
+                        
+                        if let Err(e) = conn.execute("DELETE FROM recent_sessions_cache", []) {
+                            log::warn!("Failed to clear recent_sessions_cache table: {}", e);
+                        }
+                        
                         // Clear event queue
                         if let Err(e) = conn.execute("DELETE FROM event_queue", []) {
                             log::warn!("Failed to clear event_queue table: {}", e);
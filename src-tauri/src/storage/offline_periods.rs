@@ -0,0 +1,166 @@
+//! Periods without backend connectivity
+//!
+//! The sync service checks connectivity every 30 seconds. When the backend
+//! stops answering an offline period is opened in the `offline_periods`
+//! table, and when it answers again the period is closed and reported with
+//! an `offline_period` event, so dashboards can explain gaps in realtime
+//! presence. Periods still unreported after a restart are sent on the next
+//! reconnect. Each failed check also moves `last_seen_at` forward, so a
+//! period left open when the app exited ends at its last check rather than
+//! at the next launch.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use super::database;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflinePeriod {
+    pub id: i64,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub duration_seconds: i64,
+}
+
+/// Create the offline_periods table (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS offline_periods (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at DATETIME NOT NULL,
+            ended_at DATETIME,
+            reported BOOLEAN NOT NULL DEFAULT 0,
+            last_seen_at DATETIME
+        )",
+        [],
+    )?;
+    let has_last_seen: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('offline_periods') WHERE name = 'last_seen_at'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_last_seen {
+        conn.execute("ALTER TABLE offline_periods ADD COLUMN last_seen_at DATETIME", [])?;
+    }
+    Ok(())
+}
+
+/// Close a period the previous run left open at its last offline check
+/// (called from database::init, before the sync service runs)
+pub fn close_interrupted(conn: &Connection) -> Result<()> {
+    let closed = conn.execute(
+        "UPDATE offline_periods SET ended_at = COALESCE(last_seen_at, started_at) WHERE ended_at IS NULL",
+        [],
+    )?;
+    if closed > 0 {
+        log::info!("Closed offline period left open by the previous run");
+    }
+    Ok(())
+}
+
+/// Open a period unless one is open already; returns whether one was opened
+fn open_period(conn: &Connection, now: DateTime<Utc>) -> Result<bool> {
+    let open: Option<i64> = conn
+        .query_row("SELECT id FROM offline_periods WHERE ended_at IS NULL LIMIT 1", [], |row| row.get(0))
+        .optional()?;
+    if let Some(id) = open {
+        conn.execute("UPDATE offline_periods SET last_seen_at = ?1 WHERE id = ?2", params![now, id])?;
+        return Ok(false);
+    }
+    conn.execute("INSERT INTO offline_periods (started_at, last_seen_at) VALUES (?1, ?1)", params![now])?;
+    Ok(true)
+}
+
+fn close_period(conn: &Connection, now: DateTime<Utc>) -> Result<usize> {
+    Ok(conn.execute("UPDATE offline_periods SET ended_at = ?1 WHERE ended_at IS NULL", params![now])?)
+}
+
+fn unreported(conn: &Connection) -> Result<Vec<OfflinePeriod>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, started_at, ended_at FROM offline_periods
+         WHERE ended_at IS NOT NULL AND reported = 0
+         ORDER BY id ASC",
+    )?;
+    let periods = stmt
+        .query_map([], |row| {
+            let started_at: DateTime<Utc> = row.get(1)?;
+            let ended_at: DateTime<Utc> = row.get(2)?;
+            Ok(OfflinePeriod {
+                id: row.get(0)?,
+                started_at,
+                ended_at,
+                duration_seconds: (ended_at - started_at).num_seconds().max(0),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(periods)
+}
+
+/// The backend did not answer
+pub async fn record_offline() -> Result<()> {
+    let conn = database::get_connection()?;
+    if open_period(&conn, Utc::now())? {
+        log::warn!("Backend unreachable, offline period started");
+    }
+    Ok(())
+}
+
+/// The backend answered: close the open period and report unreported ones
+pub async fn record_online() -> Result<()> {
+    let conn = database::get_connection()?;
+    if close_period(&conn, Utc::now())? > 0 {
+        log::info!("Backend reachable again, offline period ended");
+    }
+
+    for period in unreported(&conn)? {
+        let event_data = serde_json::json!({
+            "started_at": period.started_at.to_rfc3339(),
+            "ended_at": period.ended_at.to_rfc3339(),
+            "duration_seconds": period.duration_seconds,
+        });
+        crate::sampling::send_event_to_backend("offline_period", &event_data).await?;
+        conn.execute("UPDATE offline_periods SET reported = 1 WHERE id = ?1", params![period.id])?;
+        log::info!("Reported offline period of {}s", period.duration_seconds);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_period_lifecycle() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+        let start = Utc::now();
+
+        assert!(open_period(&conn, start).unwrap());
+        // Still offline on the next check: same period
+        assert!(!open_period(&conn, start + chrono::Duration::seconds(30)).unwrap());
+        assert!(unreported(&conn).unwrap().is_empty());
+
+        assert_eq!(close_period(&conn, start + chrono::Duration::seconds(95)).unwrap(), 1);
+        let periods = unreported(&conn).unwrap();
+        assert_eq!(periods.len(), 1);
+        assert_eq!(periods[0].duration_seconds, 95);
+    }
+
+    #[test]
+    fn test_period_open_at_exit_ends_at_last_check() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+        let start = Utc::now();
+
+        open_period(&conn, start).unwrap();
+        open_period(&conn, start + chrono::Duration::seconds(60)).unwrap();
+        // The app exits and starts again much later
+        close_interrupted(&conn).unwrap();
+        let periods = unreported(&conn).unwrap();
+        assert_eq!(periods.len(), 1);
+        assert_eq!(periods[0].duration_seconds, 60);
+    }
+}
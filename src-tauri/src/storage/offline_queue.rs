@@ -41,6 +41,19 @@ pub async fn queue_heartbeat(heartbeat_data: &Value) -> Result<()> {
         params![data_str, now],
     )?;
     
+    // Under storage pressure only the newest heartbeats are kept
+    if super::storage_guard::status().pressure == super::storage_guard::StoragePressure::Critical {
+        let dropped = conn.execute(
+            "DELETE FROM heartbeat_queue WHERE processed = 0 AND id NOT IN (
+                SELECT id FROM heartbeat_queue WHERE processed = 0 ORDER BY id DESC LIMIT ?1
+            )",
+            params![super::storage_guard::MAX_QUEUED_HEARTBEATS],
+        )?;
+        if dropped > 0 {
+            log::warn!("Storage pressure: dropped {} oldest queued heartbeats", dropped);
+        }
+    }
+    
     Ok(())
 }
 
@@ -103,15 +116,20 @@ pub async fn mark_heartbeat_failed(id: i64) -> Result<()> {
 
 // Event queue operations
 pub async fn queue_event(event_type: &str, event_data: &Value) -> Result<()> {
+    queue_event_at(event_type, event_data, Utc::now()).await
+}
+
+/// Queue an event that happened at `occurred_at`; it's replayed with that
+/// timestamp (see `sampling::send_queued_event`)
+pub async fn queue_event_at(event_type: &str, event_data: &Value, occurred_at: DateTime<Utc>) -> Result<()> {
     let conn = database::get_connection()?;
     
-    let now = Utc::now();
     let data_str = serde_json::to_string(event_data)?;
     
     conn.execute(
         "INSERT INTO event_queue (event_type, event_data, timestamp) 
          VALUES (?1, ?2, ?3)",
-        params![event_type, data_str, now],
+        params![event_type, data_str, occurred_at],
     )?;
     
     Ok(())
@@ -0,0 +1,119 @@
+//! Bounded offline access
+//!
+//! When the backend can't be reached, a stored session is still accepted so
+//! employees can keep working offline, but only for a limited time since the
+//! token was last validated online (`session_cache.last_validated_at`). The
+//! window comes from the policy (`offline_validation_hours`) and is remembered
+//! in the session cache so it also applies when the agent starts offline.
+//! Once it has passed, the session has to be validated online again and
+//! background services are stopped. A token that is gone from secure storage
+//! can't be used offline either (nothing could be sent with it), while an
+//! unreadable keychain keeps the session cache for the next attempt.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use rusqlite::Connection;
+
+use super::database::get_connection;
+
+/// Window used when the policy doesn't set one (or hasn't been fetched yet)
+pub const DEFAULT_OFFLINE_VALIDATION_HOURS: u32 = 72;
+
+/// session_cache tables created before the offline window lack this column
+pub fn init_column(conn: &Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('session_cache') WHERE name = 'offline_window_hours'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_column {
+        conn.execute("ALTER TABLE session_cache ADD COLUMN offline_window_hours INTEGER", [])?;
+    }
+    Ok(())
+}
+
+/// Remember the policy's window for offline starts (0 = default)
+pub fn remember_window(hours: u32) -> Result<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE session_cache SET offline_window_hours = ?1 WHERE id = 1",
+        [effective_hours(hours)],
+    )?;
+    Ok(())
+}
+
+fn effective_hours(hours: u32) -> u32 {
+    if hours == 0 {
+        DEFAULT_OFFLINE_VALIDATION_HOURS
+    } else {
+        hours
+    }
+}
+
+/// `last_validated_at` is RFC 3339 after login and SQLite's
+/// `CURRENT_TIMESTAMP` ("YYYY-MM-DD HH:MM:SS", UTC) after revalidation
+fn parse_validated_at(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|time| time.and_utc())
+        })
+}
+
+/// End of the offline window, `None` when the session was never validated
+fn window_end(last_validated_at: Option<&str>, hours: u32) -> Option<DateTime<Utc>> {
+    let validated_at = parse_validated_at(last_validated_at?)?;
+    Some(validated_at + Duration::hours(effective_hours(hours) as i64))
+}
+
+/// Whether a stored session may still be used without reaching the backend
+pub fn offline_access_allowed() -> bool {
+    let row = get_connection().and_then(|conn| {
+        Ok(conn.query_row(
+            "SELECT last_validated_at, offline_window_hours FROM session_cache WHERE id = 1",
+            [],
+            |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<u32>>(1)?)),
+        )?)
+    });
+    let (last_validated_at, hours) = match row {
+        Ok(row) => row,
+        Err(e) => {
+            log::warn!("No session validation record, offline access denied: {}", e);
+            return false;
+        }
+    };
+
+    match window_end(last_validated_at.as_deref(), hours.unwrap_or(0)) {
+        Some(end) if Utc::now() < end => {
+            log::info!("Offline access allowed until {}", end.to_rfc3339());
+            true
+        }
+        Some(end) => {
+            log::warn!("Offline validation window ended at {}, online sign-in required", end.to_rfc3339());
+            false
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_window_end_for_both_timestamp_formats() {
+        let expected = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        assert_eq!(window_end(Some("2024-03-01T09:00:00+00:00"), 72), Some(expected));
+        assert_eq!(window_end(Some("2024-03-01 09:00:00"), 0), Some(expected));
+        assert_eq!(
+            window_end(Some("2024-03-01 09:00:00"), 24),
+            Some(Utc.with_ymd_and_hms(2024, 3, 2, 9, 0, 0).unwrap())
+        );
+        assert_eq!(window_end(None, 72), None);
+        assert_eq!(window_end(Some("yesterday"), 72), None);
+    }
+}
@@ -0,0 +1,175 @@
+//! Paused intervals of work sessions
+//!
+//! Pausing stops the services but the session keeps running, so its
+//! duration used to include the paused time. Each pause during an active
+//! session is recorded as a segment in `pause_segments`, linked to the work
+//! session, and subtracted from session durations, today's totals and the
+//! local session list. A segment still open counts up to now.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use super::database;
+use crate::utils::clock;
+
+/// Seconds of a segment, open ones counting up to `?1`
+const SEGMENT_SECONDS_SQL: &str =
+    "COALESCE(SUM(strftime('%s', COALESCE(p.ended_at, ?1)) - strftime('%s', p.started_at)), 0)";
+
+/// Create the pause_segments table (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pause_segments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id INTEGER NOT NULL REFERENCES work_sessions(id) ON DELETE CASCADE,
+            reason TEXT NOT NULL,
+            started_at DATETIME NOT NULL,
+            ended_at DATETIME
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_pause_segments_session ON pause_segments(session_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn start_with(conn: &Connection, reason: &str, at: DateTime<Utc>) -> Result<Option<i64>> {
+    // A pause replacing another one (e.g. snooze while paused) continues the open segment
+    let open: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pause_segments WHERE ended_at IS NULL)",
+        [],
+        |row| row.get(0),
+    )?;
+    if open {
+        return Ok(None);
+    }
+    let inserted = conn.execute(
+        "INSERT INTO pause_segments (session_id, reason, started_at)
+         SELECT id, ?1, ?2 FROM work_sessions WHERE is_active = 1 ORDER BY started_at DESC LIMIT 1",
+        params![reason, at],
+    )?;
+    Ok((inserted > 0).then(|| conn.last_insert_rowid()))
+}
+
+fn end_with(conn: &Connection, at: DateTime<Utc>) -> Result<usize> {
+    Ok(conn.execute(
+        "UPDATE pause_segments SET ended_at = MAX(started_at, ?1) WHERE ended_at IS NULL",
+        params![at],
+    )?)
+}
+
+fn session_paused_with(conn: &Connection, session_id: i64, now: DateTime<Utc>) -> Result<i64> {
+    Ok(conn.query_row(
+        &format!("SELECT {} FROM pause_segments p WHERE p.session_id = ?2", SEGMENT_SECONDS_SQL),
+        params![now, session_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Paused time of the sessions `get_today_time_totals` counts as today
+fn today_paused_with(conn: &Connection, now: DateTime<Utc>) -> Result<i64> {
+    Ok(conn.query_row(
+        &format!(
+            "SELECT {} FROM pause_segments p JOIN work_sessions w ON w.id = p.session_id
+             WHERE DATE(w.started_at) = DATE(?1)",
+            SEGMENT_SECONDS_SQL
+        ),
+        params![now],
+        |row| row.get(0),
+    )?)
+}
+
+/// Paused seconds between `start` and `end`, segments clipped to the window
+pub(crate) fn paused_seconds_between_with(
+    conn: &Connection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<i64> {
+    let mut stmt = conn.prepare(
+        "SELECT started_at, ended_at FROM pause_segments
+         WHERE started_at < ?2 AND (ended_at IS NULL OR ended_at > ?1)",
+    )?;
+    let segments = stmt
+        .query_map(params![start, end], |row| {
+            Ok((row.get::<_, DateTime<Utc>>(0)?, row.get::<_, Option<DateTime<Utc>>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(segments
+        .into_iter()
+        .map(|(started_at, ended_at)| {
+            super::work_session::overlap_seconds(started_at, ended_at.unwrap_or(now), start, end)
+        })
+        .sum())
+}
+
+/// Open a segment for the active work session; nothing without one
+pub fn start(reason: &str) -> Result<()> {
+    if let Some(id) = start_with(&database::get_connection()?, reason, clock::now())? {
+        log::info!("Pause segment {} started ({})", id, reason);
+    }
+    Ok(())
+}
+
+/// Close the open segment, if any (resume, clock-out)
+pub fn end() -> Result<()> {
+    end_at(clock::now())
+}
+
+/// Close the open segment at `at` (a pause that expired while the app was
+/// closed ended at its deadline, not at the restart)
+pub fn end_at(at: DateTime<Utc>) -> Result<()> {
+    end_with(&database::get_connection()?, at.min(clock::now()))?;
+    Ok(())
+}
+
+/// Paused seconds of a work session
+pub fn session_paused_seconds(session_id: i64) -> Result<i64> {
+    session_paused_with(&database::get_connection()?, session_id, clock::now())
+}
+
+/// Paused seconds of today's work sessions
+pub fn today_paused_seconds() -> Result<i64> {
+    today_paused_with(&database::get_connection()?, clock::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_segments_follow_the_active_session() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE work_sessions (id INTEGER PRIMARY KEY, started_at DATETIME NOT NULL, ended_at DATETIME, is_active BOOLEAN)",
+            [],
+        )
+        .unwrap();
+        init_table(&conn).unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+
+        // Not clocked in: nothing to pause
+        assert_eq!(start_with(&conn, "manual", start).unwrap(), None);
+
+        conn.execute("INSERT INTO work_sessions (id, started_at, is_active) VALUES (1, ?1, 1)", params![start])
+            .unwrap();
+        start_with(&conn, "manual", start + Duration::minutes(30)).unwrap().unwrap();
+        // Snoozing while paused keeps the same segment
+        assert_eq!(start_with(&conn, "snooze", start + Duration::minutes(40)).unwrap(), None);
+        // Open segments count up to now
+        assert_eq!(session_paused_with(&conn, 1, start + Duration::minutes(45)).unwrap(), 15 * 60);
+
+        end_with(&conn, start + Duration::hours(1)).unwrap();
+        start_with(&conn, "manual", start + Duration::hours(2)).unwrap().unwrap();
+        end_with(&conn, start + Duration::hours(2) + Duration::minutes(10)).unwrap();
+
+        let later = start + Duration::hours(3);
+        assert_eq!(session_paused_with(&conn, 1, later).unwrap(), 40 * 60);
+        assert_eq!(today_paused_with(&conn, later).unwrap(), 40 * 60);
+        assert_eq!(today_paused_with(&conn, later + Duration::days(1)).unwrap(), 0);
+    }
+}
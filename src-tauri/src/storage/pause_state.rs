@@ -0,0 +1,127 @@
+//! Persisted tracking pause
+//!
+//! Pausing used to live only in memory, so a restart silently resumed
+//! tracking. The active pause (reason, when it started and an optional
+//! auto-resume deadline) is kept in the `pause_state` table and restored at
+//! startup; pauses whose deadline passed while the app was closed are
+//! dropped.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use super::database;
+use crate::sampling::service_state::PauseReason;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PersistedPause {
+    pub reason: PauseReason,
+    pub paused_at: DateTime<Utc>,
+    /// Tracking resumes automatically at this time
+    pub resume_at: Option<DateTime<Utc>>,
+}
+
+/// Create the pause_state table (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pause_state (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            reason TEXT NOT NULL,
+            paused_at DATETIME NOT NULL,
+            resume_at DATETIME
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn save_with(conn: &Connection, pause: &PersistedPause) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO pause_state (id, reason, paused_at, resume_at) VALUES (1, ?1, ?2, ?3)",
+        params![pause.reason.as_str(), pause.paused_at, pause.resume_at],
+    )?;
+    Ok(())
+}
+
+/// A stored pause as found at startup
+#[derive(Debug, Clone, PartialEq)]
+pub enum Restored {
+    Paused(PersistedPause),
+    /// The deadline passed while the app was closed; the row is removed
+    Expired { resume_at: DateTime<Utc> },
+}
+
+fn restore_with(conn: &Connection, now: DateTime<Utc>) -> Result<Option<Restored>> {
+    let row: Option<(String, DateTime<Utc>, Option<DateTime<Utc>>)> = conn
+        .query_row("SELECT reason, paused_at, resume_at FROM pause_state WHERE id = 1", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .optional()?;
+    let Some((reason, paused_at, resume_at)) = row else {
+        return Ok(None);
+    };
+    if let Some(resume_at) = resume_at.filter(|resume_at| *resume_at <= now) {
+        conn.execute("DELETE FROM pause_state", [])?;
+        return Ok(Some(Restored::Expired { resume_at }));
+    }
+    Ok(Some(Restored::Paused(PersistedPause {
+        reason: PauseReason::parse(&reason),
+        paused_at,
+        resume_at,
+    })))
+}
+
+/// The stored pause, unless its deadline has passed (then it is removed)
+fn load_with(conn: &Connection, now: DateTime<Utc>) -> Result<Option<PersistedPause>> {
+    Ok(match restore_with(conn, now)? {
+        Some(Restored::Paused(pause)) => Some(pause),
+        _ => None,
+    })
+}
+
+pub fn save(pause: &PersistedPause) -> Result<()> {
+    save_with(&database::get_connection()?, pause)
+}
+
+pub fn load() -> Result<Option<PersistedPause>> {
+    load_with(&database::get_connection()?, Utc::now())
+}
+
+/// Like `load`, but also reports a pause that expired while the app was closed
+pub fn restore() -> Result<Option<Restored>> {
+    restore_with(&database::get_connection()?, Utc::now())
+}
+
+pub fn clear() -> Result<()> {
+    database::get_connection()?.execute("DELETE FROM pause_state", [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_pause_restored_until_deadline() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+        let now = Utc::now();
+
+        let pause = PersistedPause {
+            reason: PauseReason::RemoteCommand,
+            paused_at: now,
+            resume_at: Some(now + Duration::minutes(30)),
+        };
+        save_with(&conn, &pause).unwrap();
+        assert_eq!(load_with(&conn, now + Duration::minutes(10)).unwrap(), Some(pause));
+
+        // Deadline passed while the app was closed
+        assert_eq!(
+            restore_with(&conn, now + Duration::hours(1)).unwrap(),
+            Some(Restored::Expired { resume_at: now + Duration::minutes(30) })
+        );
+        assert_eq!(load_with(&conn, now).unwrap(), None);
+    }
+}
@@ -0,0 +1,304 @@
+//! Local cache of recent work sessions
+//!
+//! `get_recent_sessions` used to fetch everything from the backend on each
+//! call and returned nothing offline. Sessions fetched from the backend are
+//! now cached in the `recent_sessions_cache` table and merged with the local
+//! `work_sessions` table, so sessions recorded while offline (not yet known
+//! to the backend) still show up. Local sessions the backend acknowledged
+//! (they have a `backend_id`) and those older than what the backend returned
+//! are left out, since the backend list is authoritative for its window.
+//! Pagination and date filtering happen locally over the merged list.
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use super::database;
+
+/// Default page size when the caller doesn't pass one
+pub const DEFAULT_PAGE_SIZE: u32 = 10;
+
+/// Upper bound for the page size
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// Cached entries older than this are dropped
+const CACHE_RETENTION_DAYS: i64 = 90;
+
+/// Local and backend sessions starting within this window are the same session
+const SAME_SESSION_TOLERANCE_SECS: i64 = 120;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecentSession {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Duration in seconds
+    pub duration: i64,
+    /// Local calendar date, e.g. "2024-05-02"
+    pub date: String,
+}
+
+/// Where the returned sessions came from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSource {
+    /// Fresh backend data merged with local unsynced sessions
+    Backend,
+    /// Backend unreachable: cached backend data merged with local sessions
+    Cache,
+}
+
+/// Pagination and date range filter
+#[derive(Debug, Clone, Default)]
+pub struct SessionQuery {
+    /// 1-based page number
+    pub page: u32,
+    pub page_size: u32,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl SessionQuery {
+    pub fn new(page: Option<u32>, page_size: Option<u32>, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> Self {
+        Self {
+            page: page.unwrap_or(1).max(1),
+            page_size: page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+            start_date,
+            end_date,
+        }
+    }
+
+    pub(crate) fn matches(&self, session: &RecentSession) -> bool {
+        let date = session.started_at.with_timezone(&Local).date_naive();
+        self.start_date.map_or(true, |start| date >= start) && self.end_date.map_or(true, |end| date <= end)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionPage {
+    pub sessions: Vec<RecentSession>,
+    pub page: u32,
+    pub page_size: u32,
+    pub total: usize,
+    pub has_more: bool,
+    pub source: SessionSource,
+}
+
+fn local_date(started_at: DateTime<Utc>) -> String {
+    started_at.with_timezone(&Local).date_naive().to_string()
+}
+
+/// Parse a session from the backend response. Unknown shapes are skipped.
+pub fn parse_backend_session(value: &serde_json::Value) -> Option<RecentSession> {
+    let id = match value.get("id")? {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let parse_time = |key: &str, alt: &str| {
+        value
+            .get(key)
+            .or_else(|| value.get(alt))
+            .and_then(|v| v.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|dt| dt.with_timezone(&Utc))
+    };
+    let started_at = parse_time("started_at", "clockIn")?;
+    let ended_at = parse_time("ended_at", "clockOut");
+    let duration = value
+        .get("duration")
+        .and_then(|v| v.as_i64())
+        .unwrap_or_else(|| (ended_at.unwrap_or_else(Utc::now) - started_at).num_seconds().max(0));
+    let date = value
+        .get("date")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| local_date(started_at));
+
+    Some(RecentSession { id, started_at, ended_at, duration, date })
+}
+
+/// Create the recent_sessions_cache table (called from database::init)
+pub fn init_table(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recent_sessions_cache (
+            id TEXT PRIMARY KEY,
+            started_at DATETIME NOT NULL,
+            ended_at DATETIME,
+            duration INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Store sessions fetched from the backend and prune old entries
+pub fn cache_sessions(sessions: &[RecentSession]) -> Result<()> {
+    let conn = database::get_connection()?;
+    for session in sessions {
+        conn.execute(
+            "INSERT OR REPLACE INTO recent_sessions_cache (id, started_at, ended_at, duration, date, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP)",
+            params![session.id, session.started_at, session.ended_at, session.duration, session.date],
+        )?;
+    }
+
+    let cutoff = Utc::now() - chrono::Duration::days(CACHE_RETENTION_DAYS);
+    conn.execute("DELETE FROM recent_sessions_cache WHERE started_at < ?1", params![cutoff])?;
+    Ok(())
+}
+
+pub fn get_cached_sessions() -> Result<Vec<RecentSession>> {
+    let conn = database::get_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, started_at, ended_at, duration, date FROM recent_sessions_cache ORDER BY started_at DESC",
+    )?;
+    let sessions = stmt
+        .query_map([], |row| {
+            Ok(RecentSession {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                ended_at: row.get(2)?,
+                duration: row.get(3)?,
+                date: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(sessions)
+}
+
+/// Sessions recorded in the local work_sessions table that the backend
+/// hasn't acknowledged
+pub fn get_local_sessions() -> Result<Vec<RecentSession>> {
+    let conn = database::get_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT w.id, w.started_at, w.ended_at,
+                (SELECT COALESCE(SUM(strftime('%s', COALESCE(p.ended_at, ?1)) - strftime('%s', p.started_at)), 0)
+                 FROM pause_segments p WHERE p.session_id = w.id)
+         FROM work_sessions w WHERE w.backend_id IS NULL ORDER BY w.started_at DESC",
+    )?;
+    let now = Utc::now();
+    let sessions = stmt
+        .query_map(params![now], |row| {
+            let id: i64 = row.get(0)?;
+            let started_at: DateTime<Utc> = row.get(1)?;
+            let ended_at: Option<DateTime<Utc>> = row.get(2)?;
+            let paused: i64 = row.get(3)?;
+            Ok(RecentSession {
+                id: format!("local-{}", id),
+                started_at,
+                ended_at,
+                // Paused intervals aren't worked time
+                duration: ((ended_at.unwrap_or(now) - started_at).num_seconds() - paused).max(0),
+                date: local_date(started_at),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(sessions)
+}
+
+/// Merge backend sessions with local ones the backend doesn't know about yet,
+/// newest first. Local sessions from before the oldest backend session are
+/// outside the window the backend answered for and are skipped.
+pub fn merge_sessions(backend: Vec<RecentSession>, local: Vec<RecentSession>) -> Vec<RecentSession> {
+    let window_start = backend.iter().map(|b| b.started_at).min();
+    let mut merged = backend;
+    for session in local {
+        if window_start.is_some_and(|start| session.started_at < start - chrono::Duration::seconds(SAME_SESSION_TOLERANCE_SECS)) {
+            continue;
+        }
+        let known = merged.iter().any(|b| {
+            (b.started_at - session.started_at).num_seconds().abs() <= SAME_SESSION_TOLERANCE_SECS
+        });
+        if !known {
+            merged.push(session);
+        }
+    }
+    merged.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    merged
+}
+
+/// Apply the date filter and cut out the requested page
+pub fn paginate(sessions: Vec<RecentSession>, query: &SessionQuery, source: SessionSource) -> SessionPage {
+    let filtered: Vec<RecentSession> = sessions.into_iter().filter(|s| query.matches(s)).collect();
+    let total = filtered.len();
+    let offset = ((query.page - 1) * query.page_size) as usize;
+    let sessions: Vec<RecentSession> = filtered.into_iter().skip(offset).take(query.page_size as usize).collect();
+
+    SessionPage {
+        has_more: offset + sessions.len() < total,
+        sessions,
+        page: query.page,
+        page_size: query.page_size,
+        total,
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn session(id: &str, started_at: DateTime<Utc>) -> RecentSession {
+        RecentSession {
+            id: id.to_string(),
+            started_at,
+            ended_at: Some(started_at + Duration::hours(1)),
+            duration: 3600,
+            date: local_date(started_at),
+        }
+    }
+
+    #[test]
+    fn test_merge_keeps_unsynced_local_sessions() {
+        let now = Utc::now();
+        let backend = vec![session("b1", now - Duration::days(1))];
+        let local = vec![
+            session("local-1", now - Duration::days(1) + Duration::seconds(30)),
+            session("local-2", now - Duration::hours(2)),
+            // Older than anything the backend returned
+            session("local-3", now - Duration::days(30)),
+        ];
+
+        let merged = merge_sessions(backend, local.clone());
+        let ids: Vec<&str> = merged.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["local-2", "b1"]);
+
+        // Nothing from the backend (offline, first sync): keep everything local
+        assert_eq!(merge_sessions(Vec::new(), local).len(), 3);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let now = Utc::now();
+        let sessions: Vec<RecentSession> = (0..5)
+            .map(|i| session(&format!("s{}", i), now - Duration::days(i)))
+            .collect();
+
+        let page = paginate(sessions.clone(), &SessionQuery::new(Some(2), Some(2), None, None), SessionSource::Cache);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.sessions.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), vec!["s2", "s3"]);
+        assert!(page.has_more);
+
+        let today = now.with_timezone(&Local).date_naive();
+        let page = paginate(sessions, &SessionQuery::new(None, None, Some(today), Some(today)), SessionSource::Cache);
+        assert_eq!(page.total, 1);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn test_parse_backend_session() {
+        let value = serde_json::json!({
+            "id": 42,
+            "clockIn": "2024-05-02T08:00:00Z",
+            "clockOut": "2024-05-02T12:30:00Z"
+        });
+        let parsed = parse_backend_session(&value).unwrap();
+        assert_eq!(parsed.id, "42");
+        assert_eq!(parsed.duration, 4 * 3600 + 1800);
+        assert!(parse_backend_session(&serde_json::json!({ "id": "x" })).is_none());
+    }
+}
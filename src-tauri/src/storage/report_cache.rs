@@ -0,0 +1,82 @@
+//! Cached per-day reports
+//!
+//! Weekly and monthly reports are built from one daily report per day, and
+//! recomputing 30 of them on every call made month views slow. Finished days
+//! are cached here together with a fingerprint of the day's usage rollups;
+//! a cached report is only reused while the fingerprint still matches, so
+//! days whose data changed (late syncs, imports) are recomputed.
+
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Create the daily_report_cache table (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_report_cache (
+            day DATE PRIMARY KEY,
+            fingerprint TEXT NOT NULL,
+            report_json TEXT NOT NULL,
+            computed_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Cached report for `day`, if it was computed from the same data
+pub fn get<T: DeserializeOwned>(conn: &Connection, day: NaiveDate, fingerprint: &str) -> Result<Option<T>> {
+    let cached: Option<(String, String)> = conn
+        .query_row(
+            "SELECT fingerprint, report_json FROM daily_report_cache WHERE day = ?1",
+            params![day],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    match cached {
+        Some((cached_fingerprint, json)) if cached_fingerprint == fingerprint => {
+            match serde_json::from_str(&json) {
+                Ok(report) => Ok(Some(report)),
+                Err(e) => {
+                    // Written by an older report format; recompute
+                    log::debug!("Discarding cached report for {}: {}", day, e);
+                    Ok(None)
+                }
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+pub fn put<T: Serialize>(conn: &Connection, day: NaiveDate, fingerprint: &str, report: &T) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO daily_report_cache (day, fingerprint, report_json, computed_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![day, fingerprint, serde_json::to_string(report)?, Utc::now()],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_report_invalidated_by_fingerprint() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+
+        put(&conn, day, "3:7200:4", &vec![1, 2, 3]).unwrap();
+        assert_eq!(get::<Vec<i32>>(&conn, day, "3:7200:4").unwrap(), Some(vec![1, 2, 3]));
+        // New data for the day
+        assert_eq!(get::<Vec<i32>>(&conn, day, "3:7500:5").unwrap(), None);
+        // Unreadable (older format) entries are ignored
+        assert_eq!(get::<String>(&conn, day, "3:7200:4").unwrap(), None);
+
+        put(&conn, day, "3:7500:5", &vec![4]).unwrap();
+        assert_eq!(get::<Vec<i32>>(&conn, day, "3:7500:5").unwrap(), Some(vec![4]));
+    }
+}
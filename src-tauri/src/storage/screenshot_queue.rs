@@ -1,15 +1,20 @@
 //! Screenshot upload queue for handling failed uploads with retry logic
 //! 
 //! This module manages a local SQLite queue of pending screenshot uploads.
-//! Screenshots are saved to a temp folder and queued for upload to Cloudinary.
+//! Screenshots are saved, encrypted, to an agent-owned temp folder and queued
+//! for upload to Cloudinary (see `screenshots::at_rest`).
 //! Failed uploads are retried with exponential backoff.
 //!
+//! In screenshot review mode rows carry a `review_deadline`; they are held
+//! back from upload until the employee accepts them or the deadline passes
+//! (see `screenshots::review`).
+//!
 //! Note: All SQLite operations use spawn_blocking to avoid Send/Sync issues.
 
 use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
-use rusqlite::params;
-use std::path::PathBuf;
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
 
 use super::database;
 
@@ -39,6 +44,9 @@ pub struct QueuedScreenshot {
     pub employee_id: String,
     pub device_id: String,
     pub taken_at: DateTime<Utc>,
+    /// Physical pixels per logical pixel of the captured display; None for
+    /// rows queued before it was recorded
+    pub scale_factor: Option<f64>,
     pub retry_count: i32,
     pub last_attempt: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
@@ -70,6 +78,26 @@ pub async fn init_queue_table() -> Result<()> {
             [],
         )?;
         
+        // Queues created before screenshot review lack the deadline column
+        let has_review_deadline: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('screenshot_queue') WHERE name = 'review_deadline'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_review_deadline {
+            conn.execute("ALTER TABLE screenshot_queue ADD COLUMN review_deadline DATETIME", [])?;
+        }
+        
+        // ... and queues created before DPI-aware captures lack the scale factor
+        let has_scale_factor: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('screenshot_queue') WHERE name = 'scale_factor'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_scale_factor {
+            conn.execute("ALTER TABLE screenshot_queue ADD COLUMN scale_factor REAL", [])?;
+        }
+        
         log::info!("Screenshot queue table initialized");
         Ok(())
     }).await?
@@ -81,6 +109,20 @@ pub async fn queue_screenshot(
     employee_id: &str,
     device_id: &str,
     taken_at: DateTime<Utc>,
+    scale_factor: f64,
+) -> Result<i64> {
+    queue_screenshot_with_review(file_path, employee_id, device_id, taken_at, scale_factor, None).await
+}
+
+/// Add a screenshot to the upload queue, held back for review until
+/// `review_deadline` if set
+pub async fn queue_screenshot_with_review(
+    file_path: &str,
+    employee_id: &str,
+    device_id: &str,
+    taken_at: DateTime<Utc>,
+    scale_factor: f64,
+    review_deadline: Option<DateTime<Utc>>,
 ) -> Result<i64> {
     let file_path = file_path.to_string();
     let employee_id = employee_id.to_string();
@@ -90,9 +132,9 @@ pub async fn queue_screenshot(
         let conn = database::get_connection()?;
         
         conn.execute(
-            "INSERT INTO screenshot_queue (file_path, employee_id, device_id, taken_at)
-             VALUES (?1, ?2, ?3, ?4)",
-            params![file_path, employee_id, device_id, taken_at],
+            "INSERT INTO screenshot_queue (file_path, employee_id, device_id, taken_at, review_deadline, scale_factor)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![file_path, employee_id, device_id, taken_at, review_deadline, scale_factor],
         )?;
         
         let id = conn.last_insert_rowid();
@@ -112,14 +154,14 @@ pub async fn get_pending_uploads(limit: i32) -> Result<Vec<QueuedScreenshot>> {
         let conn = database::get_connection()?;
         
         let mut stmt = conn.prepare(
-            "SELECT id, file_path, employee_id, device_id, taken_at, retry_count, last_attempt, created_at
+            "SELECT id, file_path, employee_id, device_id, taken_at, retry_count, last_attempt, created_at, scale_factor
              FROM screenshot_queue 
-             WHERE retry_count < ?1
+             WHERE retry_count < ?1 AND (review_deadline IS NULL OR review_deadline <= ?3)
              ORDER BY created_at ASC
              LIMIT ?2"
         )?;
         
-        let screenshot_iter = stmt.query_map(params![MAX_RETRIES, limit], |row| {
+        let screenshot_iter = stmt.query_map(params![MAX_RETRIES, limit, now], |row| {
             Ok(QueuedScreenshot {
                 id: row.get(0)?,
                 file_path: row.get(1)?,
@@ -129,6 +171,7 @@ pub async fn get_pending_uploads(limit: i32) -> Result<Vec<QueuedScreenshot>> {
                 retry_count: row.get(5)?,
                 last_attempt: row.get(6)?,
                 created_at: row.get(7)?,
+                scale_factor: row.get(8)?,
             })
         })?;
         
@@ -240,6 +283,62 @@ pub async fn mark_upload_failed(id: i64) -> Result<()> {
     }).await?
 }
 
+/// A screenshot waiting for the employee's review
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ScreenshotAwaitingReview {
+    pub id: i64,
+    pub file_path: String,
+    pub taken_at: DateTime<Utc>,
+    pub review_deadline: DateTime<Utc>,
+}
+
+fn awaiting_review_sync(conn: &rusqlite::Connection, id: Option<i64>, now: DateTime<Utc>) -> Result<Vec<ScreenshotAwaitingReview>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, file_path, taken_at, review_deadline FROM screenshot_queue
+         WHERE review_deadline > ?1 AND (?2 IS NULL OR id = ?2)
+         ORDER BY taken_at ASC",
+    )?;
+    let rows = stmt
+        .query_map(params![now, id], |row| {
+            Ok(ScreenshotAwaitingReview {
+                id: row.get(0)?,
+                file_path: row.get(1)?,
+                taken_at: row.get(2)?,
+                review_deadline: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Screenshots still inside their review window, oldest first
+pub async fn awaiting_review(now: DateTime<Utc>) -> Result<Vec<ScreenshotAwaitingReview>> {
+    tokio::task::spawn_blocking(move || {
+        let conn = database::get_connection()?;
+        awaiting_review_sync(&conn, None, now)
+    }).await?
+}
+
+/// One screenshot, if it is still inside its review window
+pub async fn awaiting_review_by_id(id: i64, now: DateTime<Utc>) -> Result<Option<ScreenshotAwaitingReview>> {
+    tokio::task::spawn_blocking(move || {
+        let conn = database::get_connection()?;
+        Ok(awaiting_review_sync(&conn, Some(id), now)?.into_iter().next())
+    }).await?
+}
+
+/// Release a reviewed screenshot for upload
+pub async fn finish_review(id: i64) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let conn = database::get_connection()?;
+        conn.execute(
+            "UPDATE screenshot_queue SET review_deadline = NULL WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }).await?
+}
+
 /// Remove a screenshot from the queue (without deleting file) - sync version
 fn remove_from_queue_sync(id: i64) -> Result<()> {
     let conn = database::get_connection()?;
@@ -261,14 +360,20 @@ pub async fn remove_from_queue(id: i64) -> Result<()> {
 
 /// Get the screenshot temp folder path
 pub fn get_temp_folder() -> Result<PathBuf> {
-    let mut path = dirs::data_dir()
+    let mut path = crate::profile::data_dir()
         .ok_or_else(|| anyhow::anyhow!("Failed to get data directory"))?;
-    path.push("TrackEx");
     path.push("screenshots_temp");
     
     // Create directory if it doesn't exist
     std::fs::create_dir_all(&path)?;
     
+    // Only the agent's user may list or open queued screenshots
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))?;
+    }
+    
     Ok(path)
 }
 
@@ -332,38 +437,55 @@ pub enum TempFolderStatus {
 
 /// Clean up stale screenshot files older than the threshold
 pub async fn cleanup_stale_files() -> Result<u32> {
+    let threshold = Utc::now() - Duration::hours(STALE_FILE_THRESHOLD_HOURS);
+    remove_unqueued_files(threshold).await
+}
+
+/// Delete every temp file from before `launched_at` that isn't queued, e.g.
+/// left by a crash between capture and queueing. Run once at startup; files
+/// written since launch may belong to a capture in progress.
+pub async fn sweep_orphaned_files(launched_at: DateTime<Utc>) -> Result<u32> {
+    remove_unqueued_files(launched_at).await
+}
+
+/// Delete temp files modified before `threshold` that aren't queued, and
+/// queue entries whose file is gone
+async fn remove_unqueued_files(threshold: DateTime<Utc>) -> Result<u32> {
     // Run entire cleanup in spawn_blocking since it involves both file I/O and database
-    tokio::task::spawn_blocking(|| {
-        let temp_folder = get_temp_folder()?;
-        let threshold = Utc::now() - Duration::hours(STALE_FILE_THRESHOLD_HOURS);
-        let mut deleted_count = 0;
-        
-        if !temp_folder.exists() {
-            return Ok(0);
-        }
-        
-        for entry in std::fs::read_dir(&temp_folder)? {
-            if let Ok(entry) = entry {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_file() {
-                        // Check file modification time
-                        if let Ok(modified) = metadata.modified() {
-                            let modified_time: DateTime<Utc> = modified.into();
+    tokio::task::spawn_blocking(move || {
+        let conn = database::get_connection()?;
+        remove_unqueued_files_in(&conn, &get_temp_folder()?, threshold)
+    }).await?
+}
+
+fn remove_unqueued_files_in(conn: &Connection, temp_folder: &Path, threshold: DateTime<Utc>) -> Result<u32> {
+    let mut deleted_count = 0;
+    
+    if !temp_folder.exists() {
+        return Ok(0);
+    }
+    
+    for entry in std::fs::read_dir(temp_folder)? {
+        if let Ok(entry) = entry {
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    // Check file modification time
+                    if let Ok(modified) = metadata.modified() {
+                        let modified_time: DateTime<Utc> = modified.into();
+                        
+                        if modified_time < threshold {
+                            let path = entry.path();
                             
-                            if modified_time < threshold {
-                                let path = entry.path();
-                                
-                                // Check if file is still in queue
-                                let file_path_str = path.to_string_lossy().to_string();
-                                let in_queue = is_file_in_queue_sync(&file_path_str)?;
-                                
-                                if !in_queue {
-                                    if let Err(e) = std::fs::remove_file(&path) {
-                                        log::warn!("Failed to delete stale file {:?}: {}", path, e);
-                                    } else {
-                                        log::info!("Deleted stale screenshot file: {:?}", path);
-                                        deleted_count += 1;
-                                    }
+                            // Check if file is still in queue
+                            let file_path_str = path.to_string_lossy().to_string();
+                            let in_queue = is_file_in_queue_sync(conn, &file_path_str)?;
+                            
+                            if !in_queue {
+                                if let Err(e) = std::fs::remove_file(&path) {
+                                    log::warn!("Failed to delete stale file {:?}: {}", path, e);
+                                } else {
+                                    log::info!("Deleted stale screenshot file: {:?}", path);
+                                    deleted_count += 1;
                                 }
                             }
                         }
@@ -371,25 +493,23 @@ pub async fn cleanup_stale_files() -> Result<u32> {
                 }
             }
         }
-        
-        // Also clean up queue entries for files that no longer exist
-        let orphaned_count = cleanup_orphaned_queue_entries_sync()?;
-        
-        if deleted_count > 0 || orphaned_count > 0 {
-            log::info!(
-                "Cleanup complete: {} stale files deleted, {} orphaned queue entries removed",
-                deleted_count, orphaned_count
-            );
-        }
-        
-        Ok(deleted_count)
-    }).await?
+    }
+    
+    // Also clean up queue entries for files that no longer exist
+    let orphaned_count = cleanup_orphaned_queue_entries_sync(conn)?;
+    
+    if deleted_count > 0 || orphaned_count > 0 {
+        log::info!(
+            "Cleanup complete: {} stale files deleted, {} orphaned queue entries removed",
+            deleted_count, orphaned_count
+        );
+    }
+    
+    Ok(deleted_count)
 }
 
 /// Check if a file path is in the queue - sync version
-fn is_file_in_queue_sync(file_path: &str) -> Result<bool> {
-    let conn = database::get_connection()?;
-    
+fn is_file_in_queue_sync(conn: &Connection, file_path: &str) -> Result<bool> {
     let count: i32 = conn.query_row(
         "SELECT COUNT(*) FROM screenshot_queue WHERE file_path = ?1",
         params![file_path],
@@ -400,9 +520,7 @@ fn is_file_in_queue_sync(file_path: &str) -> Result<bool> {
 }
 
 /// Remove queue entries for files that no longer exist - sync version
-fn cleanup_orphaned_queue_entries_sync() -> Result<u32> {
-    let conn = database::get_connection()?;
-    
+fn cleanup_orphaned_queue_entries_sync(conn: &Connection) -> Result<u32> {
     let mut stmt = conn.prepare(
         "SELECT id, file_path FROM screenshot_queue"
     )?;
@@ -450,4 +568,30 @@ mod tests {
         let path = get_temp_folder();
         assert!(path.is_ok());
     }
+
+    #[test]
+    fn test_sweep_removes_unqueued_files() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE screenshot_queue (id INTEGER PRIMARY KEY, file_path TEXT NOT NULL)", []).unwrap();
+        let folder = tempfile::tempdir().unwrap();
+
+        let queued = folder.path().join("screenshot_queued.enc");
+        let orphan = folder.path().join("screenshot_orphan.enc");
+        std::fs::write(&queued, b"queued").unwrap();
+        std::fs::write(&orphan, b"orphan").unwrap();
+        conn.execute("INSERT INTO screenshot_queue (file_path) VALUES (?1)", params![queued.to_string_lossy()]).unwrap();
+        conn.execute("INSERT INTO screenshot_queue (file_path) VALUES ('/gone/screenshot.enc')", []).unwrap();
+
+        // Files written after the threshold (a capture in progress) stay
+        let before = Utc::now() - Duration::minutes(1);
+        assert_eq!(remove_unqueued_files_in(&conn, folder.path(), before).unwrap(), 0);
+        assert!(orphan.exists());
+
+        let after = Utc::now() + Duration::minutes(1);
+        assert_eq!(remove_unqueued_files_in(&conn, folder.path(), after).unwrap(), 1);
+        assert!(queued.exists());
+        assert!(!orphan.exists());
+        let remaining: i64 = conn.query_row("SELECT COUNT(*) FROM screenshot_queue", [], |row| row.get(0)).unwrap();
+        assert_eq!(remaining, 1);
+    }
 }
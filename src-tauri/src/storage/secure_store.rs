@@ -1,8 +1,10 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-#[allow(dead_code)]
-const SERVICE_NAME: &str = "com.trackex.agent";
+/// Keychain service / credential prefix, separate per server profile
+fn service_name() -> &'static str {
+    crate::profile::keychain_service()
+}
 #[allow(dead_code)]
 const DEVICE_TOKEN_KEY: &str = "device_token";
 #[allow(dead_code)]
@@ -11,6 +13,7 @@ const SESSION_DATA_KEY: &str = "session_data";
 const APP_VERSION_KEY: &str = "app_version";
 #[allow(dead_code)]
 const SERVER_URL_KEY: &str = "server_url";
+const UPDATE_CHANNEL_KEY: &str = "update_channel";
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SessionData {
@@ -27,7 +30,7 @@ pub async fn store_device_token(token: &str) -> Result<()> {
         use keyring::Entry;
         
         // Use a consistent service and account name 
-        let entry = Entry::new(SERVICE_NAME, DEVICE_TOKEN_KEY)?;
+        let entry = Entry::new(service_name(), DEVICE_TOKEN_KEY)?;
         
         // Store directly without checking existing - this reduces keychain prompts
         entry.set_password(token)?;
@@ -41,7 +44,7 @@ pub async fn store_device_token(token: &str) -> Result<()> {
         
         unsafe {
             // Create wide string for target name (Windows W functions expect UTF-16)
-            let target_name_str = format!("{}:{}", SERVICE_NAME, DEVICE_TOKEN_KEY);
+            let target_name_str = format!("{}:{}", service_name(), DEVICE_TOKEN_KEY);
             let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
             let credential_blob = token.as_bytes();
             
@@ -83,7 +86,7 @@ pub async fn get_device_token() -> Result<Option<String>> {
     #[cfg(target_os = "macos")]
     {
         use keyring::Entry;
-        let entry = Entry::new(SERVICE_NAME, DEVICE_TOKEN_KEY)?;
+        let entry = Entry::new(service_name(), DEVICE_TOKEN_KEY)?;
         match entry.get_password() {
             Ok(token) => {
                 log::info!("Retrieved device token from macOS Keychain");
@@ -108,7 +111,7 @@ pub async fn get_device_token() -> Result<Option<String>> {
             use winapi::um::wincred::*;
             use std::slice;
             
-            let target_name_str = format!("{}:{}", SERVICE_NAME, DEVICE_TOKEN_KEY);
+            let target_name_str = format!("{}:{}", service_name(), DEVICE_TOKEN_KEY);
             let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
             
             let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
@@ -166,7 +169,7 @@ pub async fn delete_device_token() -> Result<()> {
     #[cfg(target_os = "macos")]
     {
         use keyring::Entry;
-        let entry = Entry::new(SERVICE_NAME, DEVICE_TOKEN_KEY)?;
+        let entry = Entry::new(service_name(), DEVICE_TOKEN_KEY)?;
         match entry.delete_password() {
             Ok(_) => {
             }
@@ -192,7 +195,7 @@ pub async fn store_session_data(_session: &SessionData) -> Result<()> {
     {
         use keyring::Entry;
         
-        let entry = Entry::new(SERVICE_NAME, SESSION_DATA_KEY)?;
+        let entry = Entry::new(service_name(), SESSION_DATA_KEY)?;
         let session_json = serde_json::to_string(_session)?;
         entry.set_password(&session_json)?;
         log::info!("Stored session data in macOS Keychain");
@@ -216,7 +219,7 @@ pub async fn store_session_data(_session: &SessionData) -> Result<()> {
             use winapi::um::wincred::*;
             
             // Create wide string for target name
-            let target_name_str = format!("{}:{}", SERVICE_NAME, SESSION_DATA_KEY);
+            let target_name_str = format!("{}:{}", service_name(), SESSION_DATA_KEY);
             let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
             
             let mut credential = CREDENTIALW {
@@ -258,7 +261,7 @@ pub async fn get_session_data() -> Result<Option<SessionData>> {
         use keyring::Entry;
         log::info!("Attempting to retrieve session data from keychain...");
         
-        match Entry::new(SERVICE_NAME, SESSION_DATA_KEY) {
+        match Entry::new(service_name(), SESSION_DATA_KEY) {
             Ok(entry) => {
                 match entry.get_password() {
                     Ok(session_json) => {
@@ -298,7 +301,7 @@ pub async fn get_session_data() -> Result<Option<SessionData>> {
             use winapi::um::wincred::*;
             use std::slice;
             
-            let target_name_str = format!("{}:{}", SERVICE_NAME, SESSION_DATA_KEY);
+            let target_name_str = format!("{}:{}", service_name(), SESSION_DATA_KEY);
             let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
             
             let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
@@ -365,7 +368,7 @@ pub async fn delete_session_data() -> Result<()> {
     #[cfg(target_os = "macos")]
     {
         use keyring::Entry;
-        let entry = Entry::new(SERVICE_NAME, SESSION_DATA_KEY)?;
+        let entry = Entry::new(service_name(), SESSION_DATA_KEY)?;
         match entry.delete_password() {
             Ok(_) => {
                 log::info!("Deleted session data from macOS Keychain");
@@ -385,7 +388,7 @@ pub async fn delete_session_data() -> Result<()> {
         unsafe {
             use winapi::um::wincred::*;
             
-            let target_name_str = format!("{}:{}", SERVICE_NAME, SESSION_DATA_KEY);
+            let target_name_str = format!("{}:{}", service_name(), SESSION_DATA_KEY);
             let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
             
             if CredDeleteW(wide_target.as_ptr(), CRED_TYPE_GENERIC, 0) != 0 {
@@ -416,7 +419,7 @@ pub async fn get_server_url() -> Result<Option<String>> {
     #[cfg(target_os = "macos")]
     {
         use keyring::Entry;
-        let entry = Entry::new(SERVICE_NAME, "server_url")?;
+        let entry = Entry::new(service_name(), "server_url")?;
         match entry.get_password() {
             Ok(url) => {
                 return Ok(Some(url));
@@ -439,7 +442,7 @@ pub async fn store_app_version(version: &str) -> Result<()> {
     #[cfg(target_os = "macos")]
     {
         use keyring::Entry;
-        let entry = Entry::new(SERVICE_NAME, APP_VERSION_KEY)?;
+        let entry = Entry::new(service_name(), APP_VERSION_KEY)?;
         entry.set_password(version)?;
         log::info!("Stored app version in macOS Keychain: {}", version);
     }
@@ -451,7 +454,7 @@ pub async fn store_app_version(version: &str) -> Result<()> {
         
         unsafe {
             // Create wide string for target name (Windows W functions expect UTF-16)
-            let target_name_str = format!("{}:{}", SERVICE_NAME, APP_VERSION_KEY);
+            let target_name_str = format!("{}:{}", service_name(), APP_VERSION_KEY);
             let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
             let credential_blob = version.as_bytes();
             
@@ -493,7 +496,7 @@ pub async fn get_stored_app_version() -> Result<Option<String>> {
     #[cfg(target_os = "macos")]
     {
         use keyring::Entry;
-        match Entry::new(SERVICE_NAME, APP_VERSION_KEY) {
+        match Entry::new(service_name(), APP_VERSION_KEY) {
             Ok(entry) => {
                 match entry.get_password() {
                     Ok(version) => {
@@ -525,7 +528,7 @@ pub async fn get_stored_app_version() -> Result<Option<String>> {
             use winapi::um::wincred::*;
             use std::slice;
             
-            let target_name_str = format!("{}:{}", SERVICE_NAME, APP_VERSION_KEY);
+            let target_name_str = format!("{}:{}", service_name(), APP_VERSION_KEY);
             let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
             
             let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
@@ -579,6 +582,120 @@ pub async fn get_stored_app_version() -> Result<Option<String>> {
     }
 }
 
+/// Store a small non-credential setting (e.g. the update channel) in secure storage.
+/// Settings survive `clear_all_credentials`.
+pub async fn store_setting(key: &str, value: &str) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        use keyring::Entry;
+        let entry = Entry::new(service_name(), key)?;
+        entry.set_password(value)?;
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        use winapi::um::wincred::*;
+        use std::ptr;
+        
+        unsafe {
+            let target_name_str = format!("{}:{}", service_name(), key);
+            let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
+            let credential_blob = value.as_bytes();
+            
+            let mut credential = CREDENTIALW {
+                Flags: 0,
+                Type: CRED_TYPE_GENERIC,
+                TargetName: wide_target.as_ptr() as *mut u16,
+                Comment: ptr::null_mut(),
+                LastWritten: winapi::shared::minwindef::FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 },
+                CredentialBlobSize: credential_blob.len() as u32,
+                CredentialBlob: credential_blob.as_ptr() as *mut u8,
+                Persist: CRED_PERSIST_LOCAL_MACHINE,
+                AttributeCount: 0,
+                Attributes: ptr::null_mut(),
+                TargetAlias: ptr::null_mut(),
+                UserName: ptr::null_mut(),
+            };
+            
+            if CredWriteW(&mut credential, 0) == 0 {
+                let error = winapi::um::errhandlingapi::GetLastError();
+                return Err(anyhow::anyhow!("Failed to store {}, error: {}", key, error));
+            }
+        }
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let _ = (key, value);
+        log::warn!("Secure storage not implemented for this platform");
+    }
+    
+    Ok(())
+}
+
+/// Read a setting stored with `store_setting`
+pub async fn get_setting(key: &str) -> Result<Option<String>> {
+    #[cfg(target_os = "macos")]
+    {
+        use keyring::Entry;
+        let entry = Entry::new(service_name(), key)?;
+        match entry.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        unsafe {
+            use winapi::um::wincred::*;
+            use std::slice;
+            
+            let target_name_str = format!("{}:{}", service_name(), key);
+            let wide_target: Vec<u16> = target_name_str.encode_utf16().chain(std::iter::once(0)).collect();
+            let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+            
+            if CredReadW(wide_target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) == 0 {
+                let error = winapi::um::errhandlingapi::GetLastError();
+                // ERROR_NOT_FOUND = 1168
+                if error == 1168 {
+                    return Ok(None);
+                }
+                return Err(anyhow::anyhow!("Failed to read {}, error: {}", key, error));
+            }
+            if credential.is_null() {
+                return Ok(None);
+            }
+            
+            let cred = &*credential;
+            let value = if cred.CredentialBlobSize > 0 && !cred.CredentialBlob.is_null() {
+                let blob = slice::from_raw_parts(cred.CredentialBlob, cred.CredentialBlobSize as usize);
+                String::from_utf8(blob.to_vec()).ok()
+            } else {
+                None
+            };
+            CredFree(credential as *mut _);
+            Ok(value)
+        }
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let _ = key;
+        log::warn!("Secure storage not implemented for this platform");
+        Ok(None)
+    }
+}
+
+pub async fn store_update_channel(channel: &str) -> Result<()> {
+    store_setting(UPDATE_CHANNEL_KEY, channel).await
+}
+
+pub async fn get_update_channel() -> Result<Option<String>> {
+    get_setting(UPDATE_CHANNEL_KEY).await
+}
+
 /// Clear all stored credentials (device token, session data, server URL, app version)
 /// Used when version migration requires a clean slate
 pub async fn clear_all_credentials() -> Result<()> {
@@ -589,7 +706,7 @@ pub async fn clear_all_credentials() -> Result<()> {
         use keyring::Entry;
         
         // Delete device token
-        if let Ok(entry) = Entry::new(SERVICE_NAME, DEVICE_TOKEN_KEY) {
+        if let Ok(entry) = Entry::new(service_name(), DEVICE_TOKEN_KEY) {
             match entry.delete_password() {
                 Ok(_) => log::info!("Deleted device_token from keychain"),
                 Err(keyring::Error::NoEntry) => log::info!("No device_token to delete"),
@@ -598,7 +715,7 @@ pub async fn clear_all_credentials() -> Result<()> {
         }
         
         // Delete session data
-        if let Ok(entry) = Entry::new(SERVICE_NAME, SESSION_DATA_KEY) {
+        if let Ok(entry) = Entry::new(service_name(), SESSION_DATA_KEY) {
             match entry.delete_password() {
                 Ok(_) => log::info!("Deleted session_data from keychain"),
                 Err(keyring::Error::NoEntry) => log::info!("No session_data to delete"),
@@ -607,7 +724,7 @@ pub async fn clear_all_credentials() -> Result<()> {
         }
         
         // Delete server URL
-        if let Ok(entry) = Entry::new(SERVICE_NAME, SERVER_URL_KEY) {
+        if let Ok(entry) = Entry::new(service_name(), SERVER_URL_KEY) {
             match entry.delete_password() {
                 Ok(_) => log::info!("Deleted server_url from keychain"),
                 Err(keyring::Error::NoEntry) => log::info!("No server_url to delete"),
@@ -616,7 +733,7 @@ pub async fn clear_all_credentials() -> Result<()> {
         }
         
         // Delete app version (will be re-stored with new version)
-        if let Ok(entry) = Entry::new(SERVICE_NAME, APP_VERSION_KEY) {
+        if let Ok(entry) = Entry::new(service_name(), APP_VERSION_KEY) {
             match entry.delete_password() {
                 Ok(_) => log::info!("Deleted app_version from keychain"),
                 Err(keyring::Error::NoEntry) => log::info!("No app_version to delete"),
@@ -634,7 +751,7 @@ pub async fn clear_all_credentials() -> Result<()> {
         
         for key in keys.iter() {
             unsafe {
-                let target_name = match CString::new(format!("{}:{}", SERVICE_NAME, key)) {
+                let target_name = match CString::new(format!("{}:{}", service_name(), key)) {
                     Ok(name) => name,
                     Err(_) => continue,
                 };
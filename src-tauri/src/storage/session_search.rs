@@ -0,0 +1,258 @@
+//! Local search over work history
+//!
+//! Answers "when did I last work on X" without the web dashboard. Every work
+//! session note is written to an SQLite FTS5 table; `search_sessions` matches
+//! against it and attributes each hit to the local work session it happened
+//! in. App focus changes (app name, window title, domain) are indexed too,
+//! but only with the same `activity_recall` consent as the screenshot text
+//! index (see `activity_index`); opting out or logging out deletes them.
+//! Entries expire after `RETENTION_DAYS`.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use super::database;
+
+/// How long focus entries are kept
+const RETENTION_DAYS: i64 = 90;
+
+pub const DEFAULT_SEARCH_LIMIT: u32 = 50;
+
+/// What a hit matched
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchHitKind {
+    /// An app/window/domain the employee focused
+    Activity,
+    /// A note attached to a work session
+    Note,
+}
+
+impl SearchHitKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Activity => "activity",
+            Self::Note => "note",
+        }
+    }
+}
+
+/// A search hit
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SessionSearchHit {
+    pub kind: SearchHitKind,
+    pub occurred_at: DateTime<Utc>,
+    pub app_name: Option<String>,
+    pub domain: Option<String>,
+    /// Matching text with hits wrapped in `[` `]`
+    pub snippet: String,
+    /// Local work session the hit falls in, if any
+    pub session_id: Option<i64>,
+    pub session_started_at: Option<DateTime<Utc>>,
+}
+
+/// Create the FTS5 index (called from database::init)
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS session_search USING fts5(
+            content,
+            app_name,
+            domain,
+            kind UNINDEXED,
+            session_id UNINDEXED,
+            occurred_at UNINDEXED,
+            tokenize = 'unicode61'
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub(super) fn insert_activity(
+    conn: &Connection,
+    occurred_at: DateTime<Utc>,
+    app_name: &str,
+    window_title: Option<&str>,
+    domain: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO session_search (content, app_name, domain, kind, occurred_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            window_title.unwrap_or_default(),
+            app_name,
+            domain,
+            SearchHitKind::Activity.as_str(),
+            occurred_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// Replace the indexed note of a work session (an empty note removes it)
+pub fn index_note(conn: &Connection, session_id: i64, started_at: DateTime<Utc>, note: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM session_search WHERE kind = ?1 AND session_id = ?2",
+        params![SearchHitKind::Note.as_str(), session_id],
+    )?;
+    if !note.trim().is_empty() {
+        conn.execute(
+            "INSERT INTO session_search (content, kind, session_id, occurred_at) VALUES (?1, ?2, ?3, ?4)",
+            params![note, SearchHitKind::Note.as_str(), session_id, started_at],
+        )?;
+    }
+    Ok(())
+}
+
+/// Turn free text into an FTS5 query: every term must match, as a literal
+/// prefix, so user input can't produce FTS syntax errors
+fn to_fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Newest matches first, optionally limited to `[start, end)`
+pub fn search(
+    conn: &Connection,
+    query: &str,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    limit: u32,
+) -> Result<Vec<SessionSearchHit>> {
+    let Some(fts_query) = to_fts_query(query) else {
+        return Ok(Vec::new());
+    };
+    let mut stmt = conn.prepare(
+        "SELECT s.kind, s.occurred_at, s.app_name, s.domain,
+                snippet(session_search, -1, '[', ']', '…', 12),
+                COALESCE(s.session_id, (
+                    SELECT w.id FROM work_sessions w
+                    WHERE w.started_at <= s.occurred_at AND (w.ended_at IS NULL OR w.ended_at >= s.occurred_at)
+                    ORDER BY w.started_at DESC LIMIT 1
+                )) AS sid
+         FROM session_search s
+         WHERE session_search MATCH ?1
+           AND (?2 IS NULL OR s.occurred_at >= ?2)
+           AND (?3 IS NULL OR s.occurred_at < ?3)
+         ORDER BY s.occurred_at DESC LIMIT ?4",
+    )?;
+    let rows = stmt
+        .query_map(params![fts_query, start, end, limit], |row| {
+            let kind: String = row.get(0)?;
+            Ok((
+                if kind == SearchHitKind::Note.as_str() { SearchHitKind::Note } else { SearchHitKind::Activity },
+                row.get::<_, DateTime<Utc>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut hits = Vec::with_capacity(rows.len());
+    for (kind, occurred_at, app_name, domain, snippet, session_id) in rows {
+        let session_started_at = match session_id {
+            Some(id) => conn
+                .query_row("SELECT started_at FROM work_sessions WHERE id = ?1", params![id], |row| row.get(0))
+                .ok(),
+            None => None,
+        };
+        hits.push(SessionSearchHit {
+            kind,
+            occurred_at,
+            app_name,
+            domain,
+            snippet,
+            session_id,
+            session_started_at,
+        });
+    }
+    Ok(hits)
+}
+
+/// Delete activity entries past the retention period (called from maintenance).
+/// Notes live as long as their session.
+pub fn prune(conn: &Connection, now: DateTime<Utc>) -> Result<usize> {
+    Ok(conn.execute(
+        "DELETE FROM session_search WHERE kind = ?1 AND occurred_at < ?2",
+        params![SearchHitKind::Activity.as_str(), now - Duration::days(RETENTION_DAYS)],
+    )?)
+}
+
+/// Delete all indexed app focus entries (activity recall turned off, logout)
+pub fn clear_activity(conn: &Connection) -> Result<usize> {
+    Ok(conn.execute(
+        "DELETE FROM session_search WHERE kind = ?1",
+        params![SearchHitKind::Activity.as_str()],
+    )?)
+}
+
+/// Record an app focus change for later search, if activity recall is on
+pub async fn record_focus(app_name: String, window_title: Option<String>, domain: Option<String>) {
+    if !super::activity_index::is_enabled().await {
+        return;
+    }
+    let now = Utc::now();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = database::get_connection()?;
+        insert_activity(&conn, now, &app_name, window_title.as_deref(), domain.as_deref())
+    })
+    .await;
+    match result {
+        Ok(Err(e)) => log::warn!("Failed to index app focus for search: {}", e),
+        Err(e) => log::warn!("Session search indexing task failed: {}", e),
+        Ok(Ok(())) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_attributes_hits_to_sessions() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE work_sessions (id INTEGER PRIMARY KEY, started_at DATETIME NOT NULL, ended_at DATETIME)",
+            [],
+        )
+        .unwrap();
+        init_table(&conn).unwrap();
+
+        let start = Utc::now() - Duration::hours(3);
+        conn.execute(
+            "INSERT INTO work_sessions (id, started_at, ended_at) VALUES (7, ?1, ?2)",
+            params![start, start + Duration::hours(1)],
+        )
+        .unwrap();
+
+        insert_activity(&conn, start + Duration::minutes(10), "Chrome", Some("Q3 budget - Sheets"), Some("docs.google.com")).unwrap();
+        insert_activity(&conn, start + Duration::hours(2), "Code", Some("budget.rs"), None).unwrap();
+        index_note(&conn, 7, start, "Prepared the budget review").unwrap();
+
+        let hits = search(&conn, "budget", None, None, 10).unwrap();
+        assert_eq!(hits.len(), 3);
+        // Newest first; the last one is outside any session
+        assert_eq!(hits[0].app_name.as_deref(), Some("Code"));
+        assert_eq!(hits[0].session_id, None);
+        assert_eq!(hits[1].session_id, Some(7));
+        assert!(hits.iter().any(|hit| hit.kind == SearchHitKind::Note && hit.snippet.contains("[budget]")));
+
+        let by_domain = search(&conn, "docs.google", None, Some(start + Duration::hours(1)), 10).unwrap();
+        assert_eq!(by_domain.len(), 1);
+
+        // Re-indexing a note replaces it
+        index_note(&conn, 7, start, "").unwrap();
+        assert_eq!(search(&conn, "review", None, None, 10).unwrap().len(), 0);
+
+        // Opting out of activity recall keeps notes
+        index_note(&conn, 7, start, "budget notes").unwrap();
+        assert_eq!(clear_activity(&conn).unwrap(), 2);
+        assert_eq!(search(&conn, "budget", None, None, 10).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,255 @@
+//! App state service
+//!
+//! Owns the agent's `AppState` inside a single actor task instead of a shared
+//! `Mutex`. Writers send `StateCommand`s over an mpsc channel; readers take
+//! lock-free snapshots from a `watch` channel. Every change that is applied is
+//! also published as a `StateEvent` on a broadcast channel so background
+//! services can react to login, logout, pause and license transitions.
+
+use super::AppState;
+use anyhow::Result;
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+
+/// Capacity of the state-change broadcast channel.
+/// Slow subscribers that fall further behind than this will see `Lagged`.
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Credentials written to the state when a session is established or restored
+#[derive(Debug, Clone)]
+pub struct SessionCredentials {
+    pub device_token: String,
+    pub device_id: String,
+    pub email: String,
+    pub server_url: String,
+    pub employee_id: Option<String>,
+}
+
+/// Explicit state-change notifications published by the state actor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StateEvent {
+    /// A session was established (login) or restored from storage
+    Authenticated { email: String, device_id: String },
+    /// Session credentials were cleared (logout or invalid token)
+    LoggedOut,
+    /// Tracking pause flag changed
+    PauseChanged { is_paused: bool },
+    /// License validity or status changed
+    LicenseChanged { valid: bool, status: Option<String> },
+}
+
+/// Commands understood by the state actor
+#[derive(Debug)]
+enum StateCommand {
+    SetSession(SessionCredentials),
+    ClearSession,
+    SetPaused(bool),
+    SetLicense {
+        valid: bool,
+        status: Option<String>,
+        checked_at: i64,
+    },
+}
+
+struct Envelope {
+    command: StateCommand,
+    ack: oneshot::Sender<()>,
+}
+
+/// Cloneable handle to the state actor.
+///
+/// Reads never block: `snapshot()` clones the latest published `AppState`.
+/// Writes are awaited until the actor has applied them, so a caller always
+/// observes its own update in the next snapshot.
+#[derive(Clone)]
+pub struct StateHandle {
+    commands: mpsc::UnboundedSender<Envelope>,
+    snapshot: watch::Receiver<AppState>,
+    events: broadcast::Sender<StateEvent>,
+}
+
+impl StateHandle {
+    /// Spawn the state actor with an empty `AppState` and return its handle
+    pub fn spawn() -> Self {
+        let (command_tx, mut command_rx) = mpsc::unbounded_channel::<Envelope>();
+        let (snapshot_tx, snapshot_rx) = watch::channel(AppState::new());
+        let (event_tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        let actor_events = event_tx.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut state = AppState::new();
+
+            while let Some(Envelope { command, ack }) = command_rx.recv().await {
+                let event = apply_command(&mut state, command);
+
+                // Publish the snapshot before acknowledging so the writer reads its own update
+                snapshot_tx.send_replace(state.clone());
+
+                if let Some(event) = event {
+                    // No subscribers is not an error
+                    let _ = actor_events.send(event);
+                }
+
+                let _ = ack.send(());
+            }
+
+            log::info!("App state service stopped: all handles dropped");
+        });
+
+        Self {
+            commands: command_tx,
+            snapshot: snapshot_rx,
+            events: event_tx,
+        }
+    }
+
+    /// Get a copy of the current state without blocking
+    pub fn snapshot(&self) -> AppState {
+        self.snapshot.borrow().clone()
+    }
+
+    /// Subscribe to explicit state-change events
+    pub fn subscribe(&self) -> broadcast::Receiver<StateEvent> {
+        self.events.subscribe()
+    }
+
+    /// Store session credentials after login or restore
+    pub async fn set_session(&self, credentials: SessionCredentials) -> Result<()> {
+        self.send(StateCommand::SetSession(credentials)).await
+    }
+
+    /// Clear all session credentials and reset the pause flag
+    pub async fn clear_session(&self) -> Result<()> {
+        self.send(StateCommand::ClearSession).await
+    }
+
+    /// Update the tracking pause flag
+    pub async fn set_paused(&self, is_paused: bool) -> Result<()> {
+        self.send(StateCommand::SetPaused(is_paused)).await
+    }
+
+    /// Record the result of a license check
+    pub async fn set_license(&self, valid: bool, status: Option<String>) -> Result<()> {
+        self.send(StateCommand::SetLicense {
+            valid,
+            status,
+            checked_at: chrono::Utc::now().timestamp(),
+        })
+        .await
+    }
+
+    async fn send(&self, command: StateCommand) -> Result<()> {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.commands
+            .send(Envelope { command, ack: ack_tx })
+            .map_err(|_| anyhow::anyhow!("App state service is not running"))?;
+        ack_rx
+            .await
+            .map_err(|_| anyhow::anyhow!("App state service dropped the command"))
+    }
+}
+
+/// Apply a command to the state, returning the event to publish (if anything changed)
+fn apply_command(state: &mut AppState, command: StateCommand) -> Option<StateEvent> {
+    match command {
+        StateCommand::SetSession(credentials) => {
+            state.device_token = Some(credentials.device_token);
+            state.device_id = Some(credentials.device_id.clone());
+            state.email = Some(credentials.email.clone());
+            state.server_url = Some(credentials.server_url);
+            state.employee_id = credentials.employee_id;
+            Some(StateEvent::Authenticated {
+                email: credentials.email,
+                device_id: credentials.device_id,
+            })
+        }
+        StateCommand::ClearSession => {
+            let was_authenticated = state.device_token.is_some();
+            state.device_token = None;
+            state.device_id = None;
+            state.email = None;
+            state.server_url = None;
+            state.employee_id = None;
+            state.is_paused = false;
+            was_authenticated.then_some(StateEvent::LoggedOut)
+        }
+        StateCommand::SetPaused(is_paused) => {
+            if state.is_paused == is_paused {
+                return None;
+            }
+            state.is_paused = is_paused;
+            Some(StateEvent::PauseChanged { is_paused })
+        }
+        StateCommand::SetLicense { valid, status, checked_at } => {
+            let changed = state.license_valid != Some(valid) || state.license_status != status;
+            state.license_valid = Some(valid);
+            state.license_status = status.clone();
+            state.last_license_check = Some(checked_at);
+            changed.then_some(StateEvent::LicenseChanged { valid, status })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials() -> SessionCredentials {
+        SessionCredentials {
+            device_token: "token".to_string(),
+            device_id: "device".to_string(),
+            email: "user@example.com".to_string(),
+            server_url: "https://example.com".to_string(),
+            employee_id: Some("employee".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_set_and_clear_session() {
+        let mut state = AppState::new();
+
+        let event = apply_command(&mut state, StateCommand::SetSession(credentials()));
+        assert_eq!(
+            event,
+            Some(StateEvent::Authenticated {
+                email: "user@example.com".to_string(),
+                device_id: "device".to_string(),
+            })
+        );
+        assert_eq!(state.device_token.as_deref(), Some("token"));
+
+        state.is_paused = true;
+        assert_eq!(apply_command(&mut state, StateCommand::ClearSession), Some(StateEvent::LoggedOut));
+        assert!(state.device_token.is_none());
+        assert!(!state.is_paused);
+
+        // Clearing an already-empty session is not a change
+        assert_eq!(apply_command(&mut state, StateCommand::ClearSession), None);
+    }
+
+    #[test]
+    fn test_license_event_only_on_change() {
+        let mut state = AppState::new();
+        let set_license = |valid: bool| StateCommand::SetLicense {
+            valid,
+            status: Some("ACTIVE".to_string()),
+            checked_at: 1,
+        };
+
+        assert!(apply_command(&mut state, set_license(true)).is_some());
+        assert!(apply_command(&mut state, set_license(true)).is_none());
+        assert_eq!(state.last_license_check, Some(1));
+        assert!(apply_command(&mut state, set_license(false)).is_some());
+    }
+
+    #[test]
+    fn test_pause_event_only_on_change() {
+        let mut state = AppState::new();
+        assert!(apply_command(&mut state, StateCommand::SetPaused(false)).is_none());
+        assert_eq!(
+            apply_command(&mut state, StateCommand::SetPaused(true)),
+            Some(StateEvent::PauseChanged { is_paused: true })
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Disk usage guard for local storage
+//!
+//! The agent keeps queues in SQLite and screenshots in a temp folder while
+//! the backend is unreachable. On a long offline stretch (or a nearly full
+//! laptop disk) that must not grow until the machine runs out of space. The
+//! guard watches the database file size and the free space of the volume
+//! holding the data directory:
+//!
+//! - `Warning`: shown in the UI and reported in heartbeats
+//! - `Critical`: new screenshots are refused and the heartbeat queue is
+//!   trimmed to its cap
+//!
+//! Checks are cached for a minute; every pressure change is emitted to the UI.
+//! `get_storage_usage` breaks the local footprint down for the settings screen.
+
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::AgentError;
+
+/// Database size considered critical (queues should never get near this)
+pub const MAX_DATABASE_BYTES: u64 = 500 * 1024 * 1024;
+
+/// Free disk space below which nothing new is written to the temp folder
+pub const MIN_FREE_DISK_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Pending heartbeats kept under storage pressure (~28h at 10s intervals)
+pub const MAX_QUEUED_HEARTBEATS: i64 = 10_000;
+
+const CHECK_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoragePressure {
+    Ok,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageStatus {
+    pub pressure: StoragePressure,
+    pub database_bytes: u64,
+    /// `None` when the volume couldn't be determined
+    pub free_disk_bytes: Option<u64>,
+}
+
+static LAST_CHECK: Mutex<Option<(Instant, StorageStatus)>> = Mutex::new(None);
+
+fn assess(database_bytes: u64, free_disk_bytes: Option<u64>) -> StoragePressure {
+    let free = free_disk_bytes.unwrap_or(u64::MAX);
+    if database_bytes >= MAX_DATABASE_BYTES || free < MIN_FREE_DISK_BYTES {
+        StoragePressure::Critical
+    } else if database_bytes >= MAX_DATABASE_BYTES / 5 * 4 || free < MIN_FREE_DISK_BYTES * 2 {
+        StoragePressure::Warning
+    } else {
+        StoragePressure::Ok
+    }
+}
+
+/// Database file plus its WAL/journal
+fn database_bytes(db_path: &Path) -> u64 {
+    ["", "-wal", "-journal"]
+        .iter()
+        .filter_map(|suffix| {
+            let mut path = db_path.as_os_str().to_owned();
+            path.push(suffix);
+            std::fs::metadata(path).ok()
+        })
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Free space of the volume with the longest mount point containing `path`
+fn free_disk_bytes(path: &Path) -> Option<u64> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+fn measure() -> anyhow::Result<StorageStatus> {
+    let db_path = super::database::get_db_path()?;
+    let database_bytes = database_bytes(&db_path);
+    let free_disk_bytes = free_disk_bytes(&db_path);
+    Ok(StorageStatus {
+        pressure: assess(database_bytes, free_disk_bytes),
+        database_bytes,
+        free_disk_bytes,
+    })
+}
+
+/// Current storage status (cached for a minute)
+pub fn status() -> StorageStatus {
+    let mut last_check = match LAST_CHECK.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    if let Some((checked_at, status)) = last_check.as_ref() {
+        if checked_at.elapsed() < CHECK_TTL {
+            return status.clone();
+        }
+    }
+
+    let status = match measure() {
+        Ok(status) => status,
+        Err(e) => {
+            log::warn!("Failed to measure local storage: {}", e);
+            StorageStatus { pressure: StoragePressure::Ok, database_bytes: 0, free_disk_bytes: None }
+        }
+    };
+    let previous = last_check.as_ref().map(|(_, s)| s.pressure).unwrap_or(StoragePressure::Ok);
+    *last_check = Some((Instant::now(), status.clone()));
+    drop(last_check);
+
+    if status.pressure != previous {
+        log::warn!(
+            "Storage pressure {:?} -> {:?} (database {} MB, free disk {:?} MB)",
+            previous,
+            status.pressure,
+            status.database_bytes / (1024 * 1024),
+            status.free_disk_bytes.map(|b| b / (1024 * 1024))
+        );
+        crate::events::storage_pressure(&status);
+    }
+    status
+}
+
+/// Whether new screenshots may be written to disk
+pub fn can_store_screenshots() -> bool {
+    status().pressure != StoragePressure::Critical
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableUsage {
+    pub table: String,
+    pub rows: i64,
+}
+
+/// What the agent stores locally
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageUsage {
+    pub database_bytes: u64,
+    pub tables: Vec<TableUsage>,
+    pub log_bytes: u64,
+    pub screenshot_cache_bytes: u64,
+    pub free_disk_bytes: Option<u64>,
+    pub pressure: StoragePressure,
+}
+
+fn table_row_counts(conn: &Connection) -> rusqlite::Result<Vec<TableUsage>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<rusqlite::Result<Vec<_>>>()?;
+    names
+        .into_iter()
+        .map(|table| {
+            // Names come from sqlite_master, quoting only guards odd identifiers
+            let sql = format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\""));
+            let rows = conn.query_row(&sql, [], |row| row.get(0))?;
+            Ok(TableUsage { table, rows })
+        })
+        .collect()
+}
+
+fn directory_bytes(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Sizes and row counts of everything the agent keeps on disk
+#[tauri::command]
+pub async fn get_storage_usage() -> Result<StorageUsage, AgentError> {
+    tokio::task::spawn_blocking(|| {
+        let db_path = super::database::get_db_path()?;
+        let conn = super::database::get_connection()?;
+        let tables = table_row_counts(&conn)?;
+        let database_bytes = database_bytes(&db_path);
+        let free_disk_bytes = free_disk_bytes(&db_path);
+        Ok::<_, anyhow::Error>(StorageUsage {
+            database_bytes,
+            tables,
+            log_bytes: crate::utils::logging::log_dir().map(|dir| directory_bytes(&dir)).unwrap_or(0),
+            screenshot_cache_bytes: super::screenshot_queue::get_temp_folder_size().unwrap_or(0),
+            free_disk_bytes,
+            pressure: assess(database_bytes, free_disk_bytes),
+        })
+    })
+    .await
+    .map_err(|e| AgentError::Internal(e.to_string()))?
+    .map_err(|e| AgentError::Storage(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assess_pressure() {
+        let gb = 1024 * 1024 * 1024;
+        assert_eq!(assess(10 * 1024 * 1024, Some(50 * gb)), StoragePressure::Ok);
+        assert_eq!(assess(420 * 1024 * 1024, Some(50 * gb)), StoragePressure::Warning);
+        assert_eq!(assess(10 * 1024 * 1024, Some(gb + gb / 2)), StoragePressure::Warning);
+        assert_eq!(assess(MAX_DATABASE_BYTES, Some(50 * gb)), StoragePressure::Critical);
+        assert_eq!(assess(10 * 1024 * 1024, Some(gb / 2)), StoragePressure::Critical);
+        assert_eq!(assess(10 * 1024 * 1024, None), StoragePressure::Ok);
+    }
+
+    #[test]
+    fn test_table_row_counts() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE a (id INTEGER); CREATE TABLE b (id INTEGER); INSERT INTO b VALUES (1), (2);")
+            .unwrap();
+        let counts: Vec<(String, i64)> = table_row_counts(&conn).unwrap().into_iter().map(|t| (t.table, t.rows)).collect();
+        assert_eq!(counts, vec![("a".to_string(), 0), ("b".to_string(), 2)]);
+    }
+}
@@ -0,0 +1,348 @@
+//! Hourly and daily app usage rollups
+//!
+//! Summaries used to be computed by scanning every `app_usage_sessions` row,
+//! which gets slow after months of tracking. `app_usage` now adds each
+//! finished segment to `app_usage_hourly` and `app_usage_daily` as it is
+//! written (split at hour boundaries), and summaries and reports read those
+//! tables instead. Existing databases are backfilled once from the raw rows.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, DurationRound, NaiveDate, Timelike, Utc};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+use super::app_usage::AppUsageSummary;
+use crate::utils::productivity::ProductivityCategory;
+
+/// Create the rollup tables and backfill them from existing sessions
+/// (called from app_usage::init_database)
+pub fn init_tables(conn: &Connection) -> Result<()> {
+    for (table, key) in [("app_usage_hourly", "hour_start DATETIME"), ("app_usage_daily", "day DATE")] {
+        let key_column = key.split(' ').next().unwrap_or_default();
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    {key} NOT NULL,
+                    app_name TEXT NOT NULL,
+                    app_id TEXT NOT NULL,
+                    productive_seconds INTEGER NOT NULL DEFAULT 0,
+                    neutral_seconds INTEGER NOT NULL DEFAULT 0,
+                    unproductive_seconds INTEGER NOT NULL DEFAULT 0,
+                    idle_seconds INTEGER NOT NULL DEFAULT 0,
+                    session_count INTEGER NOT NULL DEFAULT 0,
+                    PRIMARY KEY ({key_column}, app_name)
+                )"
+            ),
+            [],
+        )?;
+    }
+
+    backfill_if_empty(conn)
+}
+
+/// Column a segment's time is counted in
+fn time_column(category: &ProductivityCategory, is_idle: bool) -> &'static str {
+    if is_idle {
+        return "idle_seconds";
+    }
+    match category {
+        ProductivityCategory::PRODUCTIVE => "productive_seconds",
+        ProductivityCategory::NEUTRAL => "neutral_seconds",
+        ProductivityCategory::UNPRODUCTIVE => "unproductive_seconds",
+    }
+}
+
+/// Split `[start, end)` at hour boundaries into (hour_start, seconds) pieces
+fn hour_slices(start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<(DateTime<Utc>, i64)> {
+    let mut slices = Vec::new();
+    let mut cursor = start;
+    while cursor < end {
+        let hour_start = cursor.duration_trunc(Duration::hours(1)).unwrap_or(cursor);
+        let next_hour = hour_start + Duration::hours(1);
+        let slice_end = next_hour.min(end);
+        slices.push((hour_start, (slice_end - cursor).num_seconds()));
+        cursor = slice_end;
+    }
+    slices
+}
+
+/// Add a finished segment to the rollups. `new_session` is false when the
+/// segment was merged into an already counted session.
+#[allow(clippy::too_many_arguments)]
+pub fn add_segment(
+    conn: &Connection,
+    app_name: &str,
+    app_id: &str,
+    category: &ProductivityCategory,
+    is_idle: bool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    new_session: bool,
+) -> Result<()> {
+    let column = time_column(category, is_idle);
+    let hourly_sql = format!(
+        "INSERT INTO app_usage_hourly (hour_start, app_name, app_id, {column}, session_count)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(hour_start, app_name) DO UPDATE SET
+            {column} = {column} + excluded.{column},
+            session_count = session_count + excluded.session_count"
+    );
+    let daily_sql = format!(
+        "INSERT INTO app_usage_daily (day, app_name, app_id, {column}, session_count)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(day, app_name) DO UPDATE SET
+            {column} = {column} + excluded.{column},
+            session_count = session_count + excluded.session_count"
+    );
+
+    let mut counted = !new_session;
+    for (hour_start, seconds) in hour_slices(start, end) {
+        if seconds <= 0 {
+            continue;
+        }
+        let sessions = if counted { 0 } else { 1 };
+        counted = true;
+        conn.execute(&hourly_sql, params![hour_start, app_name, app_id, seconds, sessions])?;
+        conn.execute(&daily_sql, params![hour_start.date_naive(), app_name, app_id, seconds, sessions])?;
+    }
+    Ok(())
+}
+
+/// Rebuild the rollups from raw sessions when they are empty (first start
+/// after upgrading)
+fn backfill_if_empty(conn: &Connection) -> Result<()> {
+    let has_rollups: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM app_usage_daily)", [], |row| row.get(0))?;
+    if has_rollups {
+        return Ok(());
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT app_name, app_id, category, start_time, duration_seconds, is_idle
+         FROM app_usage_sessions
+         WHERE end_time IS NOT NULL AND duration_seconds > 0",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, DateTime<Utc>>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, bool>(5)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    conn.execute_batch("BEGIN")?;
+    let result = rows.iter().try_for_each(|(app_name, app_id, category, start, seconds, is_idle)| {
+        let category = super::app_usage::parse_category(category);
+        // Merged sessions exclude the gaps between segments, so the stored
+        // duration is laid out from the start rather than up to end_time
+        add_segment(conn, app_name, app_id, &category, *is_idle, *start, *start + Duration::seconds(*seconds), true)
+    });
+    match result {
+        Ok(()) => {
+            conn.execute_batch("COMMIT")?;
+            log::info!("Backfilled app usage rollups from {} sessions", rows.len());
+            Ok(())
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+/// Whether `time` falls on a UTC day boundary
+fn is_midnight(time: DateTime<Utc>) -> bool {
+    time.num_seconds_from_midnight() == 0 && time.nanosecond() == 0
+}
+
+/// Per-app usage between `start` and `end`. Whole-day ranges read the daily
+/// table; anything else reads whole hours from the hourly table and the
+/// partial hours at either end from the raw sessions, so ranges that don't
+/// start on the hour (half-hour timezones, "last 24h") aren't overcounted.
+pub fn summary_between(conn: &Connection, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<HashMap<String, AppUsageSummary>> {
+    let mut summary = HashMap::new();
+    if is_midnight(start) && is_midnight(end) {
+        add_rollup_rows(conn, &mut summary, "app_usage_daily", "day", start.date_naive(), end.date_naive())?;
+        return Ok(summary);
+    }
+
+    let hour = Duration::hours(1);
+    let floor_start = start.duration_trunc(hour).unwrap_or(start);
+    let full_start = if floor_start < start { floor_start + hour } else { floor_start };
+    let full_end = end.duration_trunc(hour).unwrap_or(end);
+    if full_start < full_end {
+        add_rollup_rows(conn, &mut summary, "app_usage_hourly", "hour_start", full_start, full_end)?;
+        add_raw_sessions(conn, &mut summary, start, full_start)?;
+        add_raw_sessions(conn, &mut summary, full_end, end)?;
+    } else {
+        add_raw_sessions(conn, &mut summary, start, end)?;
+    }
+    Ok(summary)
+}
+
+fn add_rollup_rows<K: rusqlite::ToSql>(
+    conn: &Connection,
+    summary: &mut HashMap<String, AppUsageSummary>,
+    table: &str,
+    key: &str,
+    from: K,
+    to: K,
+) -> Result<()> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT app_name, MAX(app_id), SUM(productive_seconds), SUM(neutral_seconds),
+                SUM(unproductive_seconds), SUM(idle_seconds), SUM(session_count)
+         FROM {table} WHERE {key} >= ?1 AND {key} < ?2 GROUP BY app_name"
+    ))?;
+    let mut rows = stmt.query(params![from, to])?;
+    while let Some(row) = rows.next()? {
+        let app_name: String = row.get(0)?;
+        let entry = summary
+            .entry(app_name.clone())
+            .or_insert_with(|| AppUsageSummary::new(app_name, String::new()));
+        entry.app_id = row.get(1)?;
+        entry.productive_time += row.get::<_, i64>(2)?;
+        entry.neutral_time += row.get::<_, i64>(3)?;
+        entry.unproductive_time += row.get::<_, i64>(4)?;
+        entry.idle_time += row.get::<_, i64>(5)?;
+        entry.session_count += row.get::<_, i32>(6)?;
+        entry.total_time = entry.productive_time + entry.neutral_time + entry.unproductive_time + entry.idle_time;
+    }
+    Ok(())
+}
+
+/// Add the part of the raw sessions inside `[from, to)`, laid out from their
+/// start like the rollups are
+fn add_raw_sessions(
+    conn: &Connection,
+    summary: &mut HashMap<String, AppUsageSummary>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<()> {
+    if from >= to {
+        return Ok(());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT app_name, app_id, category, start_time, duration_seconds, is_idle
+         FROM app_usage_sessions
+         WHERE end_time IS NOT NULL AND duration_seconds > 0 AND start_time < ?2 AND end_time > ?1",
+    )?;
+    let mut rows = stmt.query(params![from, to])?;
+    while let Some(row) = rows.next()? {
+        let session_start: DateTime<Utc> = row.get(3)?;
+        let session_end = session_start + Duration::seconds(row.get(4)?);
+        let seconds = (session_end.min(to) - session_start.max(from)).num_seconds();
+        if seconds <= 0 {
+            continue;
+        }
+        let app_name: String = row.get(0)?;
+        let category = super::app_usage::parse_category(&row.get::<_, String>(2)?);
+        let entry = summary
+            .entry(app_name.clone())
+            .or_insert_with(|| AppUsageSummary::new(app_name, String::new()));
+        entry.app_id = row.get(1)?;
+        match time_column(&category, row.get(5)?) {
+            "idle_seconds" => entry.idle_time += seconds,
+            "productive_seconds" => entry.productive_time += seconds,
+            "unproductive_seconds" => entry.unproductive_time += seconds,
+            _ => entry.neutral_time += seconds,
+        }
+        // Counted in the range where the session starts, like in the rollups
+        if session_start >= from {
+            entry.session_count += 1;
+        }
+        entry.total_time = entry.productive_time + entry.neutral_time + entry.unproductive_time + entry.idle_time;
+    }
+    Ok(())
+}
+
+/// Cheap fingerprint of one day's rollups; changes whenever usage is added
+pub fn day_fingerprint(conn: &Connection, day: NaiveDate) -> Result<String> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(productive_seconds + neutral_seconds + unproductive_seconds + idle_seconds), 0),
+                COALESCE(SUM(session_count), 0)
+         FROM app_usage_daily WHERE day = ?1",
+        params![day],
+        |row| Ok(format!("{}:{}:{}", row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_segments_split_across_hours_and_days() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_usage_sessions (
+                app_name TEXT, app_id TEXT, category TEXT, start_time DATETIME,
+                end_time DATETIME, duration_seconds INTEGER, is_idle BOOLEAN
+            )",
+            [],
+        )
+        .unwrap();
+        init_tables(&conn).unwrap();
+
+        // 23:30 -> 01:15 the next day, then a merged 10 minute tail
+        let start = Utc.with_ymd_and_hms(2024, 3, 4, 23, 30, 0).unwrap();
+        let end = start + Duration::minutes(105);
+        add_segment(&conn, "code", "code", &ProductivityCategory::PRODUCTIVE, false, start, end, true).unwrap();
+        add_segment(&conn, "code", "code", &ProductivityCategory::PRODUCTIVE, false, end, end + Duration::minutes(10), false).unwrap();
+
+        let hours: i64 = conn.query_row("SELECT COUNT(*) FROM app_usage_hourly", [], |row| row.get(0)).unwrap();
+        assert_eq!(hours, 3);
+
+        let day = Utc.with_ymd_and_hms(2024, 3, 4, 0, 0, 0).unwrap();
+        let first = summary_between(&conn, day, day + Duration::days(1)).unwrap();
+        assert_eq!(first["code"].productive_time, 30 * 60);
+        assert_eq!(first["code"].session_count, 1);
+
+        let both = summary_between(&conn, day, day + Duration::days(2)).unwrap();
+        assert_eq!(both["code"].total_time, 115 * 60);
+        assert_eq!(both["code"].session_count, 1);
+
+        // Sub-day ranges are answered from the hourly table
+        let morning = summary_between(&conn, day + Duration::hours(24), day + Duration::hours(25)).unwrap();
+        assert_eq!(morning["code"].productive_time, 60 * 60);
+    }
+
+    #[test]
+    fn test_partial_hours_come_from_raw_sessions() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_usage_sessions (
+                app_name TEXT, app_id TEXT, category TEXT, start_time DATETIME,
+                end_time DATETIME, duration_seconds INTEGER, is_idle BOOLEAN
+            )",
+            [],
+        )
+        .unwrap();
+        init_tables(&conn).unwrap();
+
+        // 09:00 - 11:00, read from 09:30 (e.g. a half-hour timezone)
+        let start = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let end = start + Duration::hours(2);
+        conn.execute(
+            "INSERT INTO app_usage_sessions VALUES ('code', 'code', 'PRODUCTIVE', ?1, ?2, 7200, 0)",
+            params![start, end],
+        )
+        .unwrap();
+        add_segment(&conn, "code", "code", &ProductivityCategory::PRODUCTIVE, false, start, end, true).unwrap();
+
+        let summary = summary_between(&conn, start + Duration::minutes(30), end).unwrap();
+        assert_eq!(summary["code"].productive_time, 90 * 60);
+        // Started before the range
+        assert_eq!(summary["code"].session_count, 0);
+
+        let inside = summary_between(&conn, start + Duration::minutes(10), start + Duration::minutes(20)).unwrap();
+        assert_eq!(inside["code"].total_time, 10 * 60);
+    }
+}
@@ -1,10 +1,21 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use rusqlite::params;
+use crate::utils::clock;
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 
 use super::database;
 
+/// Minimum time between two background reconciliations with the backend
+const RECONCILE_INTERVAL_SECS: i64 = 60;
+
+/// Start times closer than this are considered the same session
+const START_TIME_TOLERANCE_SECS: i64 = 120;
+
+static LAST_RECONCILE: AtomicI64 = AtomicI64::new(0);
+static RECONCILING: AtomicBool = AtomicBool::new(false);
+
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct WorkSession {
@@ -16,17 +27,21 @@ pub struct WorkSession {
 
 #[allow(dead_code)]
 pub async fn start_session() -> Result<i64> {
+    start_session_at(clock::now()).await
+}
+
+/// Start a session with an explicit start time (e.g. adopted from the backend)
+pub async fn start_session_at(now: DateTime<Utc>) -> Result<i64> {
     let conn = database::get_connection()?;
     
-    // End any existing active sessions first
+    // End any existing active sessions (and their pause) first
+    super::pause_segments::end()?;
     conn.execute(
-        "UPDATE work_sessions SET is_active = 0, ended_at = CURRENT_TIMESTAMP 
+        "UPDATE work_sessions SET is_active = 0, ended_at = ?1 
          WHERE is_active = 1",
-        [],
+        params![clock::now()],
     )?;
     
-    let now = Utc::now();
-    
     // Start new session
     conn.execute(
         "INSERT INTO work_sessions (started_at, is_active) VALUES (?1, 1)",
@@ -35,20 +50,46 @@ pub async fn start_session() -> Result<i64> {
     
     let session_id = conn.last_insert_rowid();
     
+    crate::events::session_started(session_id, now);
+    
     Ok(session_id)
 }
 
+/// Insert an already finished session (e.g. imported from another time tracker).
+/// Returns `None` if a session with the same start already exists.
+pub async fn insert_completed_session(started_at: DateTime<Utc>, ended_at: DateTime<Utc>) -> Result<Option<i64>> {
+    let conn = database::get_connection()?;
+
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM work_sessions WHERE started_at = ?1)",
+        params![started_at],
+        |row| row.get(0),
+    )?;
+    if exists {
+        return Ok(None);
+    }
+
+    conn.execute(
+        "INSERT INTO work_sessions (started_at, ended_at, is_active) VALUES (?1, ?2, 0)",
+        params![started_at, ended_at],
+    )?;
+    Ok(Some(conn.last_insert_rowid()))
+}
+
 #[allow(dead_code)]
 pub async fn end_session() -> Result<()> {
     let conn = database::get_connection()?;
+    // Ending while paused closes the pause
+    super::pause_segments::end()?;
     
     let rows_affected = conn.execute(
-        "UPDATE work_sessions SET is_active = 0, ended_at = CURRENT_TIMESTAMP 
+        "UPDATE work_sessions SET is_active = 0, ended_at = ?1 
          WHERE is_active = 1",
-        [],
+        params![clock::now()],
     )?;
     
     if rows_affected > 0 {
+        crate::events::session_ended();
     } else {
         log::warn!("No active work session to end");
     }
@@ -91,11 +132,12 @@ pub async fn is_session_active() -> Result<bool> {
 #[allow(dead_code)]
 pub async fn clear_all_active_sessions() -> Result<()> {
     let conn = database::get_connection()?;
+    super::pause_segments::end()?;
     
     let rows_affected = conn.execute(
-        "UPDATE work_sessions SET is_active = 0, ended_at = CURRENT_TIMESTAMP 
+        "UPDATE work_sessions SET is_active = 0, ended_at = ?1 
          WHERE is_active = 1",
-        [],
+        params![clock::now()],
     )?;
     
     if rows_affected > 0 {
@@ -113,6 +155,14 @@ pub async fn get_current_session_id() -> Result<Option<i64>> {
     Ok(session.map(|s| s.id))
 }
 
+/// Paused time of the active session (0 without one)
+pub async fn get_session_paused_seconds() -> Result<i64> {
+    match get_current_session_id().await? {
+        Some(session_id) => super::pause_segments::session_paused_seconds(session_id),
+        None => Ok(0),
+    }
+}
+
 pub async fn get_session_start_time() -> Result<DateTime<Utc>> {
     let conn = database::get_connection()?;
     
@@ -129,7 +179,7 @@ pub async fn get_session_start_time() -> Result<DateTime<Utc>> {
         Ok(start_time) => Ok(start_time),
         Err(rusqlite::Error::QueryReturnedNoRows) => {
             // No active session, return current time
-            Ok(Utc::now())
+            Ok(clock::now())
         },
         Err(e) => Err(e.into()),
     }
@@ -137,6 +187,7 @@ pub async fn get_session_start_time() -> Result<DateTime<Utc>> {
 
 pub async fn get_today_time_totals() -> Result<(i64, i64)> {
     let conn = database::get_connection()?;
+    let now = clock::now();
     
     // Phase 2 Spec: Total Work = Σ(session clock_in→clock_out) in range
     let mut work_stmt = conn.prepare(
@@ -145,16 +196,18 @@ pub async fn get_today_time_totals() -> Result<(i64, i64)> {
                 WHEN ended_at IS NOT NULL THEN 
                     (strftime('%s', ended_at) - strftime('%s', started_at))
                 ELSE 
-                    (strftime('%s', 'now') - strftime('%s', started_at))
+                    (strftime('%s', ?1) - strftime('%s', started_at))
             END
         ), 0) as total_work_time
          FROM work_sessions 
-         WHERE DATE(started_at) = DATE('now')"
+         WHERE DATE(started_at) = DATE(?1)"
     )?;
     
-    let total_work_time: i64 = work_stmt.query_row([], |row| {
+    let total_work_time: i64 = work_stmt.query_row(params![now], |row| {
         Ok(row.get::<_, i64>(0)?)
     })?;
+    // Paused intervals aren't work
+    let total_work_time = total_work_time - super::pause_segments::today_paused_seconds()?;
     
     // Phase 2 Spec: Idle = minutes with no input ≥ threshold while clocked in
     let mut idle_stmt = conn.prepare(
@@ -163,14 +216,14 @@ pub async fn get_today_time_totals() -> Result<(i64, i64)> {
                 WHEN end_time IS NOT NULL THEN 
                     (strftime('%s', end_time) - strftime('%s', start_time))
                 ELSE 
-                    (strftime('%s', 'now') - strftime('%s', start_time))
+                    (strftime('%s', ?1) - strftime('%s', start_time))
             END
         ), 0) as total_idle_time
          FROM app_usage_sessions 
-         WHERE DATE(start_time) = DATE('now') AND is_idle = 1"
+         WHERE DATE(start_time) = DATE(?1) AND is_idle = 1"
     )?;
     
-    let idle_time: i64 = idle_stmt.query_row([], |row| {
+    let idle_time: i64 = idle_stmt.query_row(params![now], |row| {
         Ok(row.get::<_, i64>(0)?)
     })?;
     
@@ -184,3 +237,289 @@ pub async fn get_today_time_totals() -> Result<(i64, i64)> {
     Ok((active_time, idle_time))
 }
 
+/// work_sessions tables created before session notes lack this column
+pub fn ensure_note_column(conn: &rusqlite::Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('work_sessions') WHERE name = 'note'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_column {
+        conn.execute("ALTER TABLE work_sessions ADD COLUMN note TEXT", [])?;
+    }
+    Ok(())
+}
+
+/// work_sessions tables created before recent-session merging lack this column
+pub fn ensure_backend_id_column(conn: &rusqlite::Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('work_sessions') WHERE name = 'backend_id'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_column {
+        conn.execute("ALTER TABLE work_sessions ADD COLUMN backend_id TEXT", [])?;
+    }
+    Ok(())
+}
+
+/// Remember the backend's id for a local session once the backend has it
+pub fn set_backend_id(session_id: i64, backend_id: &str) -> Result<()> {
+    let conn = database::get_connection()?;
+    conn.execute(
+        "UPDATE work_sessions SET backend_id = ?1 WHERE id = ?2",
+        params![backend_id, session_id],
+    )?;
+    Ok(())
+}
+
+/// Attach a free-text note to a work session (an empty note clears it);
+/// notes are searchable with `search_sessions`
+pub async fn set_note(session_id: i64, note: &str) -> Result<()> {
+    let conn = database::get_connection()?;
+    let started_at: DateTime<Utc> = conn
+        .query_row("SELECT started_at FROM work_sessions WHERE id = ?1", params![session_id], |row| row.get(0))
+        .map_err(|_| anyhow::anyhow!("Work session {} not found", session_id))?;
+    let note = note.trim();
+    conn.execute(
+        "UPDATE work_sessions SET note = ?1 WHERE id = ?2",
+        params![(!note.is_empty()).then_some(note), session_id],
+    )?;
+    super::session_search::index_note(&conn, session_id, started_at, note)
+}
+
+/// Seconds of `[from, to)` that fall inside `[start, end)`
+pub(crate) fn overlap_seconds(from: DateTime<Utc>, to: DateTime<Utc>, start: DateTime<Utc>, end: DateTime<Utc>) -> i64 {
+    (to.min(end) - from.max(start)).num_seconds().max(0)
+}
+
+/// Worked seconds between `start` and `end`: sessions are clipped to the
+/// window (one running past midnight counts on both days), open sessions
+/// count up to now and paused time is left out
+pub async fn worked_seconds_between(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<i64> {
+    worked_seconds_with(&database::get_connection()?, start, end, clock::now())
+}
+
+pub(crate) fn worked_seconds_with(conn: &Connection, start: DateTime<Utc>, end: DateTime<Utc>, now: DateTime<Utc>) -> Result<i64> {
+    let mut stmt = conn.prepare(
+        "SELECT started_at, ended_at FROM work_sessions
+         WHERE started_at < ?2 AND (ended_at IS NULL OR ended_at > ?1)",
+    )?;
+    let sessions = stmt
+        .query_map(params![start, end], |row| {
+            Ok((row.get::<_, DateTime<Utc>>(0)?, row.get::<_, Option<DateTime<Utc>>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let clocked_in: i64 = sessions
+        .into_iter()
+        .map(|(started_at, ended_at)| overlap_seconds(started_at, ended_at.unwrap_or(now), start, end))
+        .sum();
+    let paused = super::pause_segments::paused_seconds_between_with(conn, start, end, now)?;
+    Ok((clocked_in - paused).max(0))
+}
+
+/// Time between consecutive work sessions on the same day (breaks), for
+/// sessions starting between `start` and `end`
+pub async fn break_seconds_between(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<i64> {
+    break_seconds_with(&database::get_connection()?, start, end)
+}
+
+pub(crate) fn break_seconds_with(conn: &Connection, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<i64> {
+    let mut stmt = conn.prepare(
+        "SELECT started_at, ended_at FROM work_sessions
+         WHERE started_at >= ?1 AND started_at < ?2
+         ORDER BY started_at ASC",
+    )?;
+    let sessions = stmt
+        .query_map(params![start, end], |row| {
+            Ok((row.get::<_, DateTime<Utc>>(0)?, row.get::<_, Option<DateTime<Utc>>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(sessions
+        .windows(2)
+        .filter_map(|pair| {
+            let ((_, previous_end), (next_start, _)) = (pair[0], pair[1]);
+            let previous_end = previous_end?;
+            let same_day = previous_end.with_timezone(&chrono::Local).date_naive()
+                == next_start.with_timezone(&chrono::Local).date_naive();
+            (same_day && next_start > previous_end).then(|| (next_start - previous_end).num_seconds())
+        })
+        .sum())
+}
+
+/// Work session state as reported by the backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendSessionState {
+    pub is_active: bool,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Fetch today's active session from the backend
+pub async fn fetch_backend_session() -> Result<BackendSessionState> {
+    let client = crate::api::client::ApiClient::new().await?;
+    
+    // Get today's (UTC) date range in Z format (easier to parse)
+    let today = clock::now().date_naive();
+    let start_date = crate::utils::time::format_timestamp(today.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    let end_date = crate::utils::time::format_timestamp(today.and_hms_opt(23, 59, 59).unwrap_or_default().and_utc());
+    let url_with_params = format!("/api/devices/sessions?startDate={}&endDate={}", start_date, end_date);
+    
+    let response = client.get_with_auth(&url_with_params).await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Session request failed with status: {}", response.status()));
+    }
+    
+    let sessions_data = response.json::<serde_json::Value>().await?;
+    let sessions = sessions_data
+        .get("sessions")
+        .and_then(|s| s.as_array())
+        .cloned()
+        .unwrap_or_default();
+    
+    // Active session = no clock_out
+    let active = sessions
+        .iter()
+        .find(|session| session.get("clockOut").and_then(|v| v.as_str()).is_none());
+    
+    Ok(match active {
+        Some(session) => BackendSessionState {
+            is_active: true,
+            started_at: session
+                .get("clockIn")
+                .and_then(|v| v.as_str())
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
+        },
+        None => BackendSessionState { is_active: false, started_at: None },
+    })
+}
+
+/// Whether the local active session (its start time, if any) disagrees with the backend
+pub fn session_diverges(local_started_at: Option<DateTime<Utc>>, backend: &BackendSessionState) -> bool {
+    match (local_started_at, backend.is_active) {
+        (None, false) => false,
+        (Some(local), true) => match backend.started_at {
+            Some(remote) => (local - remote).num_seconds().abs() > START_TIME_TOLERANCE_SECS,
+            None => false,
+        },
+        _ => true,
+    }
+}
+
+/// Compare the local session with the backend and emit `session-diverged` if they
+/// disagree. Nothing is changed locally: a local-only session's clock-in may still
+/// be waiting in the offline queue, and a backend-only session (clocked in
+/// elsewhere, local database cleared) is only adopted once the user confirms
+/// (`commands::adopt_backend_session`).
+async fn reconcile_with_backend() -> Result<()> {
+    let backend = fetch_backend_session().await?;
+    let local = get_current_session().await?;
+    let local_started_at = local.as_ref().map(|s| s.started_at);
+    
+    if !session_diverges(local_started_at, &backend) {
+        return Ok(());
+    }
+    
+    log::warn!(
+        "Local work session ({:?}) diverges from backend ({:?})",
+        local_started_at, backend
+    );
+    
+    crate::events::session_diverged(local_started_at, backend.is_active, backend.started_at);
+    Ok(())
+}
+
+/// Reconcile with the backend in the background, at most once per interval
+pub fn schedule_reconciliation() {
+    let now = clock::now().timestamp();
+    if now - LAST_RECONCILE.load(Ordering::Relaxed) < RECONCILE_INTERVAL_SECS {
+        return;
+    }
+    if RECONCILING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    LAST_RECONCILE.store(now, Ordering::Relaxed);
+    
+    tokio::spawn(async {
+        if let Err(e) = reconcile_with_backend().await {
+            log::debug!("Work session reconciliation skipped: {}", e);
+        }
+        RECONCILING.store(false, Ordering::SeqCst);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    
+    #[test]
+    fn test_session_divergence() {
+        let start = Utc::now();
+        let backend_active = |started_at| BackendSessionState { is_active: true, started_at: Some(started_at) };
+        let backend_inactive = BackendSessionState { is_active: false, started_at: None };
+        
+        assert!(!session_diverges(None, &backend_inactive));
+        assert!(!session_diverges(Some(start), &backend_active(start + Duration::seconds(30))));
+        assert!(session_diverges(Some(start), &backend_active(start + Duration::hours(1))));
+        assert!(session_diverges(Some(start), &backend_inactive));
+        assert!(session_diverges(None, &backend_active(start)));
+    }
+
+    #[tokio::test]
+    async fn test_today_totals_roll_over_at_midnight() {
+        use crate::test_support::{agent_env, MockBackend};
+        use crate::utils::clock::ManualClock;
+        use chrono::TimeZone;
+
+        let backend = MockBackend::start().await;
+        let _env = agent_env(&backend).await;
+        let manual = std::sync::Arc::new(ManualClock::new(Utc.with_ymd_and_hms(2024, 3, 4, 22, 0, 0).unwrap()));
+        let _clock = clock::install(manual.clone());
+
+        start_session().await.unwrap();
+        manual.advance(Duration::minutes(90));
+        assert_eq!(get_today_time_totals().await.unwrap(), (90 * 60, 0));
+
+        // Past midnight the session that started yesterday no longer counts
+        manual.advance(Duration::minutes(60));
+        end_session().await.unwrap();
+        assert_eq!(get_today_time_totals().await.unwrap(), (0, 0));
+        start_session().await.unwrap();
+        manual.advance(Duration::minutes(15));
+        assert_eq!(get_today_time_totals().await.unwrap(), (15 * 60, 0));
+    }
+
+    #[test]
+    fn test_worked_seconds_clip_sessions_to_the_window() {
+        use chrono::TimeZone;
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE work_sessions (id INTEGER PRIMARY KEY, started_at DATETIME NOT NULL, ended_at DATETIME, is_active BOOLEAN)",
+            [],
+        )
+        .unwrap();
+        crate::storage::pause_segments::init_table(&conn).unwrap();
+        let at = |day: u32, hour: u32, minute: u32| Utc.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap();
+
+        // 22:00 - 02:00 with a pause from 23:30 to 00:30
+        conn.execute(
+            "INSERT INTO work_sessions (id, started_at, ended_at, is_active) VALUES (1, ?1, ?2, 0)",
+            params![at(4, 22, 0), at(5, 2, 0)],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO pause_segments (session_id, reason, started_at, ended_at) VALUES (1, 'manual', ?1, ?2)",
+            params![at(4, 23, 30), at(5, 0, 30)],
+        )
+        .unwrap();
+
+        let now = at(6, 12, 0);
+        assert_eq!(worked_seconds_with(&conn, at(4, 0, 0), at(5, 0, 0), now).unwrap(), 90 * 60);
+        assert_eq!(worked_seconds_with(&conn, at(5, 0, 0), at(6, 0, 0), now).unwrap(), 90 * 60);
+        assert_eq!(worked_seconds_with(&conn, at(6, 0, 0), at(7, 0, 0), now).unwrap(), 0);
+    }
+}
@@ -0,0 +1,138 @@
+//! Test fixtures for flows that talk to the backend
+//!
+//! `MockBackend` is an in-process HTTP server (wiremock) answering the
+//! endpoints used by login, clock in/out and queue processing, and records
+//! what the agent sent. `agent_env` points the agent at a temporary data
+//! directory and a logged-in app state for a given backend, so tests can run
+//! the real code paths without a live server or the user's keychain.
+//!
+//! The agent keeps its database path and app state in globals, so tests using
+//! `agent_env` are serialized by the guard it returns.
+
+use serde_json::{json, Value};
+use tokio::sync::{Mutex, MutexGuard};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::storage::{SessionCredentials, StateHandle};
+
+/// Device token the mock backend accepts
+pub const TEST_TOKEN: &str = "test-device-token";
+
+pub struct MockBackend {
+    pub server: MockServer,
+}
+
+impl MockBackend {
+    /// Start a backend accepting `TEST_TOKEN` on the auth, ingest and
+    /// active-session endpoints
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        let bearer = format!("Bearer {}", TEST_TOKEN);
+
+        Mock::given(method("GET"))
+            .and(path("/api/auth/validate"))
+            .and(header("Authorization", bearer.as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "valid": true })))
+            .mount(&server)
+            .await;
+        for endpoint in ["/api/ingest/events", "/api/ingest/heartbeat"] {
+            Mock::given(method("POST"))
+                .and(path(endpoint))
+                .and(header("Authorization", bearer.as_str()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/api/devices/active-session"))
+            .and(header("Authorization", bearer.as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "hasActiveSession": false })))
+            .mount(&server)
+            .await;
+        // Anything else, including a wrong token
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({ "error": "Unauthorized" })))
+            .with_priority(u8::MAX)
+            .mount(&server)
+            .await;
+
+        Self { server }
+    }
+
+    /// Start a backend answering every request with `status`
+    pub async fn failing(status: u16) -> Self {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(status).set_body_json(json!({ "error": "Mock failure" })))
+            .mount(&server)
+            .await;
+        Self { server }
+    }
+
+    pub fn url(&self) -> String {
+        self.server.uri()
+    }
+
+    /// JSON bodies received on `endpoint`, oldest first
+    pub async fn received(&self, endpoint: &str) -> Vec<Value> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter(|request| request.url.path() == endpoint)
+            .filter_map(|request| serde_json::from_slice(&request.body).ok())
+            .collect()
+    }
+
+    /// Event types received on `/api/ingest/events`, oldest first
+    pub async fn received_event_types(&self) -> Vec<String> {
+        self.received("/api/ingest/events")
+            .await
+            .iter()
+            .filter_map(|body| body["events"].as_array().cloned())
+            .flatten()
+            .filter_map(|event| event["type"].as_str().map(str::to_string))
+            .collect()
+    }
+}
+
+static ENV_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Point the agent at a fresh temporary database and log in against
+/// `backend`. Hold the returned guard for the duration of the test.
+pub async fn agent_env(backend: &MockBackend) -> MutexGuard<'static, ()> {
+    let guard = ENV_LOCK.lock().await;
+
+    // Test builds resolve data paths under a temporary root (see profile::data_dir)
+    if let Ok(path) = crate::storage::database::get_db_path() {
+        assert!(
+            path.starts_with(crate::profile::test_data_root()),
+            "refusing to delete {} outside the test data dir",
+            path.display()
+        );
+        let _ = std::fs::remove_file(path);
+    }
+    crate::storage::database::init().await.expect("init test database");
+
+    let state = match crate::storage::get_state_service() {
+        Ok(state) => state,
+        Err(_) => {
+            crate::storage::set_state_service(StateHandle::spawn());
+            crate::storage::get_state_service().expect("state service")
+        }
+    };
+    state
+        .set_session(SessionCredentials {
+            device_token: TEST_TOKEN.to_string(),
+            device_id: "test-device".to_string(),
+            email: "employee@example.com".to_string(),
+            server_url: backend.url(),
+            employee_id: Some("test-employee".to_string()),
+        })
+        .await
+        .expect("set test session");
+
+    guard
+}
@@ -0,0 +1,251 @@
+//! Import from other time trackers
+//!
+//! Parses Toggl Track and Clockify "detailed report" CSV exports into local
+//! work sessions, so teams switching to TrackEx keep their history. Each
+//! imported entry is also queued as an `imported_session` event carrying the
+//! original start and end, so the backend can record it as historical time.
+//!
+//! Both exports have start/end date and time columns (local time); they
+//! differ in column naming and date format, and the format is detected from
+//! the header row.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::error::AgentError;
+use crate::storage::audit_log;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSource {
+    Toggl,
+    Clockify,
+}
+
+impl ImportSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ImportSource::Toggl => "toggl",
+            ImportSource::Clockify => "clockify",
+        }
+    }
+}
+
+/// One time entry from an export
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedEntry {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub project: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSummary {
+    pub source: ImportSource,
+    pub imported: usize,
+    /// Entries whose session already exists locally (e.g. imported before)
+    pub duplicates: usize,
+    /// Rows that could not be parsed, with the reason
+    pub errors: Vec<String>,
+}
+
+/// Split CSV text into records, handling quoted fields with commas, escaped
+/// quotes and line breaks
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let text = text.trim_start_matches('\u{feff}');
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+fn detect_source(header: &[String]) -> ImportSource {
+    // Clockify qualifies its duration columns: "Duration (h)", "Duration (decimal)"
+    if header.iter().any(|h| h.to_lowercase().starts_with("duration (")) {
+        ImportSource::Clockify
+    } else {
+        ImportSource::Toggl
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%m/%d/%Y", "%d.%m.%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value.trim(), format).ok())
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(value.trim(), format).ok())
+}
+
+fn local_to_utc(date: &str, time: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::new(parse_date(date)?, parse_time(time)?);
+    Local.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Parse an export into entries; unparseable rows are reported, not fatal
+pub fn parse_export(text: &str) -> Result<(ImportSource, Vec<ImportedEntry>, Vec<String>), String> {
+    let mut records = parse_csv(text).into_iter();
+    let header = records.next().ok_or("The file is empty")?;
+    let source = detect_source(&header);
+
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let (Some(start_date), Some(start_time), Some(end_date), Some(end_time)) =
+        (column("Start date"), column("Start time"), column("End date"), column("End time"))
+    else {
+        return Err("Not a Toggl or Clockify export: start/end date and time columns are missing".to_string());
+    };
+    let project = column("Project");
+    let description = column("Description");
+
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for (index, record) in records.enumerate() {
+        // Header is line 1
+        let line = index + 2;
+        let field = |i: usize| record.get(i).map(String::as_str).unwrap_or("");
+        let optional = |i: Option<usize>| i.map(field).map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+
+        let (Some(started_at), Some(ended_at)) = (
+            local_to_utc(field(start_date), field(start_time)),
+            local_to_utc(field(end_date), field(end_time)),
+        ) else {
+            errors.push(format!("Line {}: invalid start or end time", line));
+            continue;
+        };
+        if ended_at <= started_at {
+            errors.push(format!("Line {}: entry ends before it starts", line));
+            continue;
+        }
+
+        entries.push(ImportedEntry {
+            started_at,
+            ended_at,
+            project: optional(project),
+            description: optional(description),
+        });
+    }
+
+    Ok((source, entries, errors))
+}
+
+async fn import_entries(source: ImportSource, entries: Vec<ImportedEntry>, errors: Vec<String>) -> anyhow::Result<ImportSummary> {
+    let mut summary = ImportSummary { source, imported: 0, duplicates: 0, errors };
+
+    for entry in entries {
+        let Some(session_id) =
+            crate::storage::work_session::insert_completed_session(entry.started_at, entry.ended_at).await?
+        else {
+            summary.duplicates += 1;
+            continue;
+        };
+
+        let event_data = serde_json::json!({
+            "source": source.as_str(),
+            "localSessionId": session_id,
+            "startedAt": crate::utils::time::format_timestamp(entry.started_at),
+            "endedAt": crate::utils::time::format_timestamp(entry.ended_at),
+            "durationSec": (entry.ended_at - entry.started_at).num_seconds(),
+            "project": entry.project,
+            "description": entry.description,
+        });
+        if let Err(e) = crate::storage::offline_queue::queue_event("imported_session", &event_data).await {
+            log::warn!("Failed to queue imported session {}: {}", session_id, e);
+        }
+        summary.imported += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Import a Toggl or Clockify CSV export into local work sessions
+#[tauri::command]
+pub async fn import_time_entries(path: String) -> Result<ImportSummary, AgentError> {
+    let result = async {
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| AgentError::Internal(format!("Failed to read {}: {}", path, e)))?;
+        let (source, entries, errors) = parse_export(&text).map_err(AgentError::Internal)?;
+        import_entries(source, entries, errors)
+            .await
+            .map_err(|e| AgentError::Storage(e.to_string()))
+    }
+    .await;
+
+    audit_log::record("import_time_entries", &result);
+    if let Ok(summary) = &result {
+        log::info!(
+            "Imported {} {} entries ({} duplicates, {} errors)",
+            summary.imported, summary.source.as_str(), summary.duplicates, summary.errors.len()
+        );
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toggl_export() {
+        let csv = "User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags\n\
+                   Ana,ana@example.com,,Website,,\"Fix header, footer\",No,2024-03-01,09:00:00,2024-03-01,10:30:00,01:30:00,\n\
+                   Ana,ana@example.com,,,,,No,2024-03-01,bad,2024-03-01,11:00:00,,\n";
+        let (source, entries, errors) = parse_export(csv).unwrap();
+        assert_eq!(source, ImportSource::Toggl);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].project.as_deref(), Some("Website"));
+        assert_eq!(entries[0].description.as_deref(), Some("Fix header, footer"));
+        assert_eq!((entries[0].ended_at - entries[0].started_at).num_minutes(), 90);
+        assert_eq!(errors, vec!["Line 3: invalid start or end time".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_clockify_export() {
+        let csv = "\u{feff}Project,Client,Description,Task,User,Email,Tags,Billable,Start Date,Start Time,End Date,End Time,Duration (h),Duration (decimal)\r\n\
+                   Support,,Tickets,,Ana,ana@example.com,,No,03/01/2024,11:00:00 PM,03/02/2024,01:00:00 AM,02:00:00,2.00\r\n";
+        let (source, entries, errors) = parse_export(csv).unwrap();
+        assert_eq!(source, ImportSource::Clockify);
+        assert!(errors.is_empty());
+        assert_eq!((entries[0].ended_at - entries[0].started_at).num_hours(), 2);
+    }
+
+    #[test]
+    fn test_rejects_unknown_csv() {
+        assert!(parse_export("name,value\na,1\n").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Tray tooltip status
+//!
+//! The tray tooltip shows the app name plus whatever the user should know
+//! at a glance: a running snooze countdown or paused tracking, and an
+//! offline warning when the backend has been unreachable for a while. Each
+//! source sets its part here and the tooltip is re-rendered from all of
+//! them; the pause follows the app state service's events.
+
+use std::sync::{Mutex, OnceLock};
+use tauri::tray::TrayIcon;
+use tauri::Wry;
+
+static TRAY: OnceLock<TrayIcon<Wry>> = OnceLock::new();
+static STATUS: Mutex<TrayStatus> = Mutex::new(TrayStatus {
+    snooze: None,
+    paused: false,
+    offline: false,
+});
+
+#[derive(Debug, Clone, PartialEq)]
+struct TrayStatus {
+    /// Snooze countdown, replaces the plain tooltip
+    snooze: Option<String>,
+    /// Tracking is paused (a snooze countdown says more, so it wins)
+    paused: bool,
+    /// Backend unreachable for longer than the alert threshold
+    offline: bool,
+}
+
+/// Keep the tray icon for tooltip updates (called after building the tray)
+pub fn set_tray(tray: TrayIcon<Wry>) {
+    if TRAY.set(tray).is_err() {
+        log::warn!("Tray already initialized");
+    }
+    refresh();
+}
+
+fn tooltip(status: &TrayStatus) -> String {
+    let mut text = status.snooze.clone().unwrap_or_else(|| {
+        crate::i18n::t(if status.paused { "tray.tooltip_paused" } else { "tray.tooltip" })
+    });
+    if status.offline {
+        text.push('\n');
+        text.push_str(&crate::i18n::t("tray.tooltip_offline"));
+    }
+    text
+}
+
+fn update(change: impl FnOnce(&mut TrayStatus)) {
+    let mut status = match STATUS.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    change(&mut status);
+    let text = tooltip(&status);
+    drop(status);
+
+    if let Some(tray) = TRAY.get() {
+        if let Err(e) = tray.set_tooltip(Some(text)) {
+            log::warn!("Failed to update tray tooltip: {}", e);
+        }
+    }
+}
+
+/// Re-render the tooltip, e.g. after the language changed
+pub fn refresh() {
+    update(|_| {});
+}
+
+/// Show (or clear) the snooze countdown
+pub fn set_snooze(text: Option<String>) {
+    update(|status| status.snooze = text);
+}
+
+/// Show (or clear) the offline warning
+pub fn set_offline(offline: bool) {
+    update(|status| status.offline = offline);
+}
+
+/// Show the pause state, following the app state service's events
+pub fn follow_app_state() {
+    use crate::storage::state_service::StateEvent;
+    use tokio::sync::broadcast::error::RecvError;
+
+    let service = match crate::storage::get_state_service() {
+        Ok(service) => service,
+        Err(e) => {
+            log::warn!("Tray can't follow the app state: {}", e);
+            return;
+        }
+    };
+    let mut events = service.subscribe();
+    update(|status| status.paused = service.snapshot().is_paused);
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(StateEvent::PauseChanged { is_paused }) => update(|status| status.paused = is_paused),
+                Ok(StateEvent::LoggedOut) => update(|status| status.paused = false),
+                Ok(_) => {}
+                // Missed events: catch up from the latest state
+                Err(RecvError::Lagged(_)) => update(|status| status.paused = service.snapshot().is_paused),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tooltip_combines_status() {
+        let mut status = TrayStatus { snooze: None, paused: false, offline: false };
+        assert_eq!(tooltip(&status), "TrackEx Agent");
+
+        status.paused = true;
+        assert_eq!(tooltip(&status), "TrackEx Agent – tracking paused");
+        status.paused = false;
+
+        status.offline = true;
+        assert_eq!(tooltip(&status), "TrackEx Agent\nTracking offline — data will sync later");
+
+        status.snooze = Some("Snoozed".to_string());
+        assert!(tooltip(&status).starts_with("Snoozed\n"));
+    }
+}
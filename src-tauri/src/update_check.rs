@@ -0,0 +1,176 @@
+//! Periodic background update check
+//!
+//! Runs `check_for_updates` on an interval so users learn about new versions
+//! without opening the settings page. The latest result is surfaced in the
+//! tray menu ("Update available"), as a window badge, and through the
+//! `update-available` event. The time of the last check is persisted so it
+//! survives restarts.
+
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::menu::MenuItem;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::update_manager::UpdateInfo;
+
+/// Delay before the first background check, so startup isn't slowed down
+const INITIAL_DELAY_SECS: u64 = 120;
+
+/// Interval between background checks
+const CHECK_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+/// Secure store key for the last check time
+const LAST_CHECK_KEY: &str = "last_update_check";
+
+/// Emitted when a check finds an update
+pub const UPDATE_AVAILABLE_EVENT: &str = "update-available";
+
+/// Emitted when the tray's update item is clicked
+pub const UPDATE_CHECK_REQUESTED_EVENT: &str = "update-check-requested";
+
+/// Tray menu item id
+pub const TRAY_ITEM_ID: &str = "update";
+
+static TRAY_ITEM: OnceLock<MenuItem<Wry>> = OnceLock::new();
+static STATUS: Mutex<UpdateCheckStatus> = Mutex::new(UpdateCheckStatus {
+    last_checked_at: None,
+    available: false,
+    version: None,
+    mandatory: false,
+});
+
+/// Result of the most recent update check
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UpdateCheckStatus {
+    /// RFC 3339 time of the last successful check
+    pub last_checked_at: Option<String>,
+    pub available: bool,
+    pub version: Option<String>,
+    pub mandatory: bool,
+}
+
+/// Tray menu item text for a check result
+fn tray_text(status: &UpdateCheckStatus) -> String {
+    match (&status.version, status.available) {
+        (Some(version), true) => crate::i18n::t_args("tray.update_available", &[("version", version.as_str())]),
+        _ => crate::i18n::t("tray.check_updates"),
+    }
+}
+
+/// Create the tray menu item (called while building the tray)
+pub fn tray_item(app: &AppHandle) -> tauri::Result<MenuItem<Wry>> {
+    let item = MenuItem::with_id(app, TRAY_ITEM_ID, crate::i18n::t("tray.check_updates"), true, None::<&str>)?;
+    if TRAY_ITEM.set(item.clone()).is_err() {
+        log::warn!("Update tray item already initialized");
+    }
+    Ok(item)
+}
+
+fn set_tray_text(status: &UpdateCheckStatus) {
+    if let Some(item) = TRAY_ITEM.get() {
+        if let Err(e) = item.set_text(tray_text(status)) {
+            log::warn!("Failed to update tray item: {}", e);
+        }
+    }
+}
+
+/// Re-render the tray item text, e.g. after the language changed
+pub fn refresh_tray_text() {
+    let status = STATUS.lock().map(|s| s.clone()).unwrap_or_else(|e| e.into_inner().clone());
+    set_tray_text(&status);
+}
+
+fn update_indicators(app: &AppHandle, status: &UpdateCheckStatus) {
+    set_tray_text(status);
+
+    // Badges are not supported on every platform, so failures are expected
+    if let Some(window) = app.get_webview_window("main") {
+        let count = if status.available { Some(1) } else { None };
+        if let Err(e) = window.set_badge_count(count) {
+            log::debug!("Failed to set window badge: {}", e);
+        }
+    }
+}
+
+/// Record the result of an update check (manual or background)
+pub async fn record(app: &AppHandle, info: &UpdateInfo) {
+    // Failed checks keep the previous result
+    if info.error.is_some() {
+        return;
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let status = UpdateCheckStatus {
+        last_checked_at: Some(now.clone()),
+        available: info.available,
+        version: info.version.clone(),
+        mandatory: info.mandatory,
+    };
+    if let Ok(mut current) = STATUS.lock() {
+        *current = status.clone();
+    }
+
+    if let Err(e) = crate::storage::secure_store::store_setting(LAST_CHECK_KEY, &now).await {
+        log::warn!("Failed to persist last update check time: {}", e);
+    }
+
+    update_indicators(app, &status);
+    if status.available {
+        if let Err(e) = app.emit(UPDATE_AVAILABLE_EVENT, &status) {
+            log::warn!("Failed to emit update available event: {}", e);
+        }
+    }
+}
+
+/// Result of the most recent update check
+#[tauri::command]
+pub async fn get_update_check_status() -> UpdateCheckStatus {
+    let mut status = STATUS.lock().map(|s| s.clone()).unwrap_or_else(|e| e.into_inner().clone());
+    if status.last_checked_at.is_none() {
+        // Nothing checked yet in this run: report the persisted time
+        status.last_checked_at = crate::storage::secure_store::get_setting(LAST_CHECK_KEY)
+            .await
+            .ok()
+            .flatten();
+    }
+    status
+}
+
+/// Check for updates on an interval for the lifetime of the app
+pub async fn start_periodic_check(app: AppHandle) {
+    tokio::time::sleep(Duration::from_secs(INITIAL_DELAY_SECS)).await;
+
+    let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        log::info!("Running background update check");
+        match crate::update_manager::check_for_updates(app.clone()).await {
+            Ok(info) if info.available => {
+                log::info!("Background check found update {:?}", info.version);
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Background update check failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tray_text() {
+        let mut status = UpdateCheckStatus {
+            last_checked_at: None,
+            available: false,
+            version: None,
+            mandatory: false,
+        };
+        assert_eq!(tray_text(&status), "Check for Updates");
+
+        status.available = true;
+        status.version = Some("1.4.0".to_string());
+        assert_eq!(tray_text(&status), "Update Available (v1.4.0)");
+    }
+}
@@ -0,0 +1,242 @@
+//! Mandatory-update lockout
+//!
+//! A mandatory update gets a grace period from the moment this device first
+//! sees it. Once the grace period is over the agent enters a restricted mode:
+//! clock-in is refused with `AgentError::UpdateRequired`, install deferral
+//! (see update_schedule.rs) no longer applies and the UI shows the update
+//! screen. The restriction lifts when the update is installed: a pending
+//! record for a version at or below the running one is dropped when loaded
+//! (at startup and on every check), without waiting for the next online
+//! update check.
+//!
+//! The first-seen time is persisted so restarting the agent doesn't reset the
+//! grace period.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+
+use crate::error::AgentError;
+use crate::update_manager::UpdateInfo;
+
+/// Grace period when the update server doesn't send one
+pub const DEFAULT_GRACE_PERIOD_HOURS: u32 = 72;
+
+/// Secure store key for the pending mandatory update ("version|first_seen|grace_hours")
+const PENDING_KEY: &str = "mandatory_update_pending";
+
+/// Emitted when the agent enters or leaves the restricted mode
+pub const LOCKOUT_CHANGED_EVENT: &str = "update-lockout-changed";
+
+/// A mandatory update this device has been offered but not installed yet
+#[derive(Debug, Clone, PartialEq)]
+struct PendingMandatory {
+    version: String,
+    first_seen: DateTime<Utc>,
+    grace_hours: u32,
+}
+
+impl PendingMandatory {
+    fn deadline(&self) -> DateTime<Utc> {
+        self.first_seen + Duration::hours(self.grace_hours as i64)
+    }
+
+    fn encode(&self) -> String {
+        format!("{}|{}|{}", self.version, self.first_seen.to_rfc3339(), self.grace_hours)
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        let mut parts = value.split('|');
+        let version = parts.next()?.to_string();
+        let first_seen = DateTime::parse_from_rfc3339(parts.next()?).ok()?.with_timezone(&Utc);
+        let grace_hours = parts.next()?.parse().ok()?;
+        if version.is_empty() {
+            return None;
+        }
+        Some(Self { version, first_seen, grace_hours })
+    }
+}
+
+/// Numeric parts of a version ("1.2.10-beta.1" -> [1, 2, 10]); pre-release
+/// and build suffixes are ignored
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Whether `version` is already installed, i.e. at or below `running`
+fn is_installed(version: &str, running: &str) -> bool {
+    let (mut pending, mut running) = (version_parts(version), version_parts(running));
+    let len = pending.len().max(running.len());
+    pending.resize(len, 0);
+    running.resize(len, 0);
+    pending <= running
+}
+
+/// `None` until loaded from the secure store
+static PENDING: Mutex<Option<Option<PendingMandatory>>> = Mutex::const_new(None);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LockoutStatus {
+    /// Restricted mode is active
+    pub locked: bool,
+    /// Pending mandatory update version
+    pub version: Option<String>,
+    /// When the grace period ends (or ended)
+    pub deadline: Option<String>,
+}
+
+fn status_at(pending: Option<&PendingMandatory>, now: DateTime<Utc>) -> LockoutStatus {
+    match pending {
+        Some(pending) => LockoutStatus {
+            locked: now >= pending.deadline(),
+            version: Some(pending.version.clone()),
+            deadline: Some(pending.deadline().to_rfc3339()),
+        },
+        None => LockoutStatus { locked: false, version: None, deadline: None },
+    }
+}
+
+async fn load_pending() -> Option<PendingMandatory> {
+    let pending = {
+        let mut guard = PENDING.lock().await;
+        if guard.is_none() {
+            let stored = crate::storage::secure_store::get_setting(PENDING_KEY).await.ok().flatten();
+            *guard = Some(stored.as_deref().and_then(PendingMandatory::decode));
+        }
+        guard.clone().flatten()
+    };
+    match pending {
+        Some(pending) if is_installed(&pending.version, env!("CARGO_PKG_VERSION")) => {
+            log::info!("Mandatory update {} is installed, leaving restricted mode", pending.version);
+            save_pending(None).await;
+            None
+        }
+        pending => pending,
+    }
+}
+
+async fn save_pending(pending: Option<PendingMandatory>) {
+    let encoded = pending.as_ref().map(PendingMandatory::encode).unwrap_or_default();
+    if let Err(e) = crate::storage::secure_store::store_setting(PENDING_KEY, &encoded).await {
+        log::warn!("Failed to persist pending mandatory update: {}", e);
+    }
+    *PENDING.lock().await = Some(pending);
+}
+
+/// Drop the pending record if its update was installed since the last run
+/// (called at startup)
+pub async fn init() {
+    load_pending().await;
+}
+
+/// Current lockout state
+pub async fn current_status() -> LockoutStatus {
+    status_at(load_pending().await.as_ref(), Utc::now())
+}
+
+/// Whether the restricted mode is active
+pub async fn is_locked() -> bool {
+    current_status().await.locked
+}
+
+/// Refuse actions that are blocked while a mandatory update is overdue
+pub async fn ensure_not_locked() -> Result<(), AgentError> {
+    let status = current_status().await;
+    if status.locked {
+        return Err(AgentError::UpdateRequired(crate::i18n::t_args(
+            "error.update_required",
+            &[("version", status.version.as_deref().unwrap_or_default())],
+        )));
+    }
+    Ok(())
+}
+
+/// Track mandatory updates from an update check result
+pub async fn observe(app: &AppHandle, info: &UpdateInfo) {
+    // Failed checks say nothing about pending updates
+    if info.error.is_some() {
+        return;
+    }
+
+    let before = current_status().await;
+    let existing = load_pending().await;
+    let pending = match (&info.version, info.available && info.mandatory) {
+        (Some(version), true) => match existing.clone() {
+            // Keep the original first-seen time for the same release
+            Some(existing) if &existing.version == version => Some(existing),
+            _ => {
+                log::info!("Mandatory update {} offered, grace period started", version);
+                Some(PendingMandatory {
+                    version: version.clone(),
+                    first_seen: Utc::now(),
+                    grace_hours: info.grace_period_hours.unwrap_or(DEFAULT_GRACE_PERIOD_HOURS),
+                })
+            }
+        },
+        _ => None,
+    };
+    if pending != existing {
+        save_pending(pending).await;
+    }
+
+    let after = current_status().await;
+    if after.locked != before.locked {
+        if after.locked {
+            log::warn!("Mandatory update {:?} is overdue, entering restricted mode", after.version);
+        }
+        if let Err(e) = app.emit(LOCKOUT_CHANGED_EVENT, &after) {
+            log::warn!("Failed to emit lockout event: {}", e);
+        }
+    }
+}
+
+/// Whether a mandatory update is overdue and the agent is restricted
+#[tauri::command]
+pub async fn get_update_lockout_status() -> LockoutStatus {
+    current_status().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_after_grace_period() {
+        let first_seen = Utc::now() - Duration::hours(10);
+        let pending = PendingMandatory { version: "2.0.0".to_string(), first_seen, grace_hours: 24 };
+
+        assert!(!status_at(Some(&pending), Utc::now()).locked);
+        assert!(status_at(Some(&pending), first_seen + Duration::hours(24)).locked);
+        assert!(!status_at(None, Utc::now()).locked);
+    }
+
+    #[test]
+    fn test_installed_update_is_not_pending() {
+        assert!(is_installed("2.0.0", "2.0.0"));
+        assert!(is_installed("1.9.3", "2.0.0"));
+        assert!(is_installed("2.0", "2.0.0"));
+        assert!(is_installed("v2.0.0-beta.1", "2.0.0"));
+        assert!(!is_installed("2.0.1", "2.0.0"));
+        assert!(!is_installed("2.10.0", "2.9.0"));
+    }
+
+    #[test]
+    fn test_pending_round_trip() {
+        let pending = PendingMandatory {
+            version: "2.0.0".to_string(),
+            first_seen: DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z").unwrap().with_timezone(&Utc),
+            grace_hours: 48,
+        };
+        assert_eq!(PendingMandatory::decode(&pending.encode()), Some(pending));
+        assert_eq!(PendingMandatory::decode(""), None);
+    }
+}
@@ -5,20 +5,155 @@
 //! - Check if updates are available
 //! - Download and install updates
 //! - Get update progress events
+//! - Choose the update channel (stable/beta/canary)
 
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tauri::Emitter;
-use tauri_plugin_updater::UpdaterExt;
+use tauri_plugin_updater::{Updater, UpdaterExt};
+
+use crate::error::AgentError;
+use crate::storage::audit_log::{self, AuditOutcome};
+
+/// Update servers, in the order they are tried (mirrors tauri.conf.json)
+const UPDATE_SERVERS: [&str; 2] = ["http://localhost:3000", "https://trackex.app"];
+
+/// Release channel the agent receives updates from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+    Canary,
+}
+
+impl UpdateChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+            UpdateChannel::Canary => "canary",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "stable" => Some(UpdateChannel::Stable),
+            "beta" => Some(UpdateChannel::Beta),
+            "canary" => Some(UpdateChannel::Canary),
+            _ => None,
+        }
+    }
+
+    /// Path prefix of the update endpoint. Stable keeps the original path so
+    /// existing servers keep working; other channels get their own segment.
+    fn endpoint_prefix(&self) -> String {
+        match self {
+            UpdateChannel::Stable => "/api/desktop/updates".to_string(),
+            channel => format!("/api/desktop/updates/{}", channel.as_str()),
+        }
+    }
+}
+
+/// Update manifest URLs for a channel, with the updater's placeholders left in
+fn endpoint_urls(channel: UpdateChannel) -> Vec<String> {
+    UPDATE_SERVERS
+        .iter()
+        .map(|server| {
+            format!(
+                "{}{}/{{{{target}}}}-{{{{arch}}}}/{{{{current_version}}}}",
+                server,
+                channel.endpoint_prefix()
+            )
+        })
+        .collect()
+}
+
+/// The configured update channel (stable unless changed)
+pub async fn current_channel() -> UpdateChannel {
+    match crate::storage::secure_store::get_update_channel().await {
+        Ok(Some(value)) => UpdateChannel::parse(&value).unwrap_or_default(),
+        Ok(None) => UpdateChannel::Stable,
+        Err(e) => {
+            log::warn!("Failed to read update channel, using stable: {}", e);
+            UpdateChannel::Stable
+        }
+    }
+}
+
+/// Build an updater pointed at the configured channel
+async fn channel_updater(app: &tauri::AppHandle) -> Result<Updater, String> {
+    let channel = current_channel().await;
+    let endpoints = endpoint_urls(channel)
+        .iter()
+        .map(|url| tauri::Url::parse(url).map_err(|e| format!("Invalid update endpoint {}: {}", url, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    app.updater_builder()
+        .endpoints(endpoints)
+        .and_then(|builder| builder.build())
+        .map_err(|e| format!("Failed to initialize updater for {} channel: {}", channel.as_str(), e))
+}
 
 /// Custom update response from our server that includes mandatory field
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 #[allow(dead_code)]
 struct CustomUpdateResponse {
     version: String,
     #[serde(default)]
     mandatory: bool,
+    /// Staged rollout: percentage (0-100) of devices that should be offered
+    /// this release. Missing means everyone.
+    #[serde(default, alias = "rolloutPercentage")]
+    rollout_percentage: Option<f64>,
+    /// Hours a mandatory update may stay uninstalled before the agent is restricted
+    #[serde(default, alias = "gracePeriodHours")]
+    grace_period_hours: Option<u32>,
+}
+
+/// Stable rollout bucket in [0, 100) for this device and release.
+/// The release version is mixed in so the same devices aren't always first.
+pub fn rollout_bucket(device_uuid: &str, version: &str) -> f64 {
+    // FNV-1a: stable across Rust versions and platforms (unlike DefaultHasher)
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in device_uuid.bytes().chain(std::iter::once(b':')).chain(version.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (hash % 10_000) as f64 / 100.0
+}
+
+/// Whether a device in `bucket` falls within the advertised rollout
+fn in_rollout(bucket: f64, rollout_percentage: Option<f64>) -> bool {
+    match rollout_percentage {
+        None => true,
+        Some(percentage) => bucket < percentage.clamp(0.0, 100.0),
+    }
+}
+
+/// Whether this device should be offered `version` under the given rollout
+fn device_in_rollout(version: &str, rollout_percentage: Option<f64>) -> bool {
+    if rollout_percentage.is_none() {
+        return true;
+    }
+    match crate::storage::database::get_or_create_device_uuid() {
+        Ok(device_uuid) => {
+            let bucket = rollout_bucket(&device_uuid, version);
+            let eligible = in_rollout(bucket, rollout_percentage);
+            log::info!(
+                "Staged rollout for {}: {:?}% of devices, this device is in bucket {:.2} (eligible: {})",
+                version, rollout_percentage, bucket, eligible
+            );
+            eligible
+        }
+        Err(e) => {
+            // Without a stable identity we can't take part in staged rollouts
+            log::warn!("Failed to get device UUID for staged rollout, skipping update: {}", e);
+            false
+        }
+    }
 }
 
 /// Information about an available update
@@ -36,6 +171,9 @@ pub struct UpdateInfo {
     pub release_date: Option<String>,
     /// Whether this update is mandatory (non-skippable)
     pub mandatory: bool,
+    /// Grace period for a mandatory update before the agent is restricted
+    #[serde(default)]
+    pub grace_period_hours: Option<u32>,
     /// Error message if the check failed
     pub error: Option<String>,
     /// Additional diagnostic information
@@ -56,14 +194,22 @@ pub struct UpdateProgress {
 /// Check if an update is available
 /// 
 /// This command contacts the update server to check if a newer version
-/// of the agent is available for download.
+/// of the agent is available for download. The result is recorded for the
+/// tray indicator (see update_check.rs).
 #[tauri::command]
 pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, String> {
+    let info = query_update_server(app.clone()).await?;
+    crate::update_check::record(&app, &info).await;
+    crate::update_lockout::observe(&app, &info).await;
+    Ok(info)
+}
+
+async fn query_update_server(app: tauri::AppHandle) -> Result<UpdateInfo, String> {
     let current_version = env!("CARGO_PKG_VERSION").to_string();
     log::info!("Checking for updates... Current version: {}", current_version);
     
-    // Get the updater from the app handle
-    let updater = match app.updater() {
+    // Get the updater for the configured channel
+    let updater = match channel_updater(&app).await {
         Ok(u) => u,
         Err(e) => {
             log::error!("Failed to get updater: {}", e);
@@ -74,7 +220,8 @@ pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, Stri
                 current_version,
                 release_date: None,
                 mandatory: false,
-                error: Some(format!("Failed to initialize updater: {}", e)),
+                grace_period_hours: None,
+                error: Some(e),
                 diagnostic_info: Some("The updater plugin may not be properly configured in tauri.conf.json".to_string()),
             });
         }
@@ -85,10 +232,29 @@ pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, Stri
         Ok(Some(update)) => {
             log::info!("Update available: {} -> {}", current_version, update.version);
             
-            // Fetch the mandatory flag from our custom endpoint
-            let mandatory = fetch_mandatory_flag(&current_version).await;
+            // Fetch the mandatory flag and rollout percentage from our custom endpoint
+            let metadata = fetch_update_metadata(&current_version).await;
+            let mandatory = metadata.mandatory;
             log::info!("Update mandatory flag: {}", mandatory);
             
+            if !device_in_rollout(&update.version, metadata.rollout_percentage) {
+                return Ok(UpdateInfo {
+                    available: false,
+                    version: None,
+                    notes: None,
+                    current_version,
+                    release_date: None,
+                    mandatory: false,
+                    grace_period_hours: None,
+                    error: None,
+                    diagnostic_info: Some(format!(
+                        "Version {} is being rolled out gradually ({:.0}% of devices); this device is not included yet",
+                        update.version,
+                        metadata.rollout_percentage.unwrap_or(100.0)
+                    )),
+                });
+            }
+            
             Ok(UpdateInfo {
                 available: true,
                 version: Some(update.version.clone()),
@@ -96,6 +262,7 @@ pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, Stri
                 current_version,
                 release_date: update.date.map(|d| d.to_string()),
                 mandatory,
+                grace_period_hours: metadata.grace_period_hours,
                 error: None,
                 diagnostic_info: None,
             })
@@ -109,6 +276,7 @@ pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, Stri
                 current_version,
                 release_date: None,
                 mandatory: false,
+                grace_period_hours: None,
                 error: None,
                 diagnostic_info: Some("Already on the latest version".to_string()),
             })
@@ -144,6 +312,7 @@ pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, Stri
                 current_version,
                 release_date: None,
                 mandatory: false,
+                grace_period_hours: None,
                 error: Some(error_msg),
                 diagnostic_info: Some(diagnostic),
             })
@@ -151,11 +320,11 @@ pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, Stri
     }
 }
 
-/// Fetch the mandatory flag from the update server
+/// Fetch the mandatory flag and rollout percentage from the update server
 /// 
-/// This makes a separate HTTP request to get the mandatory field since
+/// This makes a separate HTTP request to get the custom fields since
 /// the Tauri updater plugin doesn't pass through custom fields.
-async fn fetch_mandatory_flag(current_version: &str) -> bool {
+async fn fetch_update_metadata(current_version: &str) -> CustomUpdateResponse {
     // Determine platform and arch
     let target = if cfg!(target_os = "macos") {
         "darwin"
@@ -174,25 +343,29 @@ async fn fetch_mandatory_flag(current_version: &str) -> bool {
     };
     
     // Try production first, then localhost for development
+    let prefix = current_channel().await.endpoint_prefix();
     let endpoints = [
-        format!("https://trackex.app/api/desktop/updates/{}-{}/{}", target, arch, current_version),
-        format!("http://localhost:3000/api/desktop/updates/{}-{}/{}", target, arch, current_version),
+        format!("https://trackex.app{}/{}-{}/{}", prefix, target, arch, current_version),
+        format!("http://localhost:3000{}/{}-{}/{}", prefix, target, arch, current_version),
     ];
     
     for endpoint in endpoints {
-        match reqwest::get(&endpoint).await {
+        match crate::api::client::shared_client().get(&endpoint).send().await {
             Ok(response) if response.status().is_success() => {
                 if let Ok(data) = response.json::<CustomUpdateResponse>().await {
-                    log::info!("Fetched mandatory flag from {}: {}", endpoint, data.mandatory);
-                    return data.mandatory;
+                    log::info!(
+                        "Fetched update metadata from {}: mandatory={}, rollout={:?}",
+                        endpoint, data.mandatory, data.rollout_percentage
+                    );
+                    return data;
                 }
             }
             _ => continue,
         }
     }
     
-    log::warn!("Could not fetch mandatory flag, defaulting to false");
-    false
+    log::warn!("Could not fetch update metadata, defaulting to non-mandatory full rollout");
+    CustomUpdateResponse::default()
 }
 
 /// Download and install an available update
@@ -205,12 +378,27 @@ async fn fetch_mandatory_flag(current_version: &str) -> bool {
 /// - Payload: UpdateProgress { downloaded, total, percentage }
 #[tauri::command]
 pub async fn install_update(app: tauri::AppHandle) -> Result<(), String> {
+    // Recorded up-front: on macOS a successful install relaunches the app and never returns
+    audit_log::record_outcome("install_update", AuditOutcome::Started, None);
+
+    let result = install_update_impl(app).await;
+    match &result {
+        Ok(()) => audit_log::record_outcome("install_update", AuditOutcome::Success, None),
+        Err(message) => {
+            let error = AgentError::Internal(message.clone());
+            audit_log::record_outcome("install_update", AuditOutcome::Failure, Some(&error));
+        }
+    }
+    result
+}
+
+async fn install_update_impl(app: tauri::AppHandle) -> Result<(), String> {
     log::info!("Starting update installation...");
     
-    // Get the updater
-    let updater = app.updater().map_err(|e| {
+    // Get the updater for the configured channel
+    let updater = channel_updater(&app).await.map_err(|e| {
         log::error!("Failed to get updater: {}", e);
-        format!("Failed to initialize updater: {}", e)
+        e
     })?;
     
     // Check for the update first
@@ -226,6 +414,20 @@ pub async fn install_update(app: tauri::AppHandle) -> Result<(), String> {
             "No update available".to_string()
         })?;
     
+    // Respect staged rollouts even if the UI calls install directly
+    let metadata = fetch_update_metadata(env!("CARGO_PKG_VERSION")).await;
+    if !device_in_rollout(&update.version, metadata.rollout_percentage) {
+        return Err(format!(
+            "Version {} is not yet available for this device (staged rollout)",
+            update.version
+        ));
+    }
+    
+    // Keep the running version so a crashing update can be rolled back
+    if let Err(e) = crate::update_rollback::stage_rollback(&update.version) {
+        log::warn!("Failed to stage rollback backup (continuing with update): {}", e);
+    }
+    
     log::info!("Downloading update version {}...", update.version);
     log::info!("Update download URL: {}", update.download_url);
     
@@ -449,6 +651,32 @@ pub fn get_current_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
+/// Get the update channel this device receives builds from
+#[tauri::command]
+pub async fn get_update_channel() -> UpdateChannel {
+    current_channel().await
+}
+
+/// Switch the update channel (e.g. enroll a pilot user in beta builds)
+#[tauri::command]
+pub async fn set_update_channel(channel: String) -> Result<UpdateChannel, AgentError> {
+    let result = set_update_channel_impl(channel).await;
+    audit_log::record("set_update_channel", &result);
+    result
+}
+
+async fn set_update_channel_impl(channel: String) -> Result<UpdateChannel, AgentError> {
+    let channel = UpdateChannel::parse(&channel)
+        .ok_or_else(|| AgentError::Internal(format!("Unknown update channel: {}", channel)))?;
+
+    crate::storage::secure_store::store_update_channel(channel.as_str())
+        .await
+        .map_err(|e| AgentError::Storage(format!("Failed to store update channel: {}", e)))?;
+
+    log::info!("Update channel set to {}", channel.as_str());
+    Ok(channel)
+}
+
 /// Test the update endpoint connectivity and configuration
 /// 
 /// This diagnostic command checks if the update server is accessible
@@ -459,8 +687,8 @@ pub async fn test_update_endpoint(app: tauri::AppHandle) -> Result<String, Strin
     log::info!("Testing update endpoint connectivity...");
     
     // Try to get the updater configuration
-    let updater = app.updater().map_err(|e| {
-        format!("Failed to initialize updater: {}. Check tauri.conf.json configuration.", e)
+    let updater = channel_updater(&app).await.map_err(|e| {
+        format!("{}. Check tauri.conf.json configuration.", e)
     })?;
     
     // Attempt to check for updates
@@ -521,3 +749,45 @@ pub async fn test_update_endpoint(app: tauri::AppHandle) -> Result<String, Strin
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_endpoints() {
+        assert_eq!(UpdateChannel::parse(" Beta "), Some(UpdateChannel::Beta));
+        assert_eq!(UpdateChannel::parse("nightly"), None);
+
+        let stable = endpoint_urls(UpdateChannel::Stable);
+        assert_eq!(
+            stable[1],
+            "https://trackex.app/api/desktop/updates/{{target}}-{{arch}}/{{current_version}}"
+        );
+        let canary = endpoint_urls(UpdateChannel::Canary);
+        assert_eq!(
+            canary[1],
+            "https://trackex.app/api/desktop/updates/canary/{{target}}-{{arch}}/{{current_version}}"
+        );
+    }
+
+    #[test]
+    fn test_rollout_bucket() {
+        let bucket = rollout_bucket("3f1c2a9e-0000-4000-8000-000000000001", "1.2.0");
+        assert!((0.0..100.0).contains(&bucket));
+        // Stable for the same device and release
+        assert_eq!(bucket, rollout_bucket("3f1c2a9e-0000-4000-8000-000000000001", "1.2.0"));
+
+        assert!(in_rollout(bucket, None));
+        assert!(in_rollout(bucket, Some(100.0)));
+        assert!(!in_rollout(bucket, Some(0.0)));
+        assert!(in_rollout(42.5, Some(50.0)));
+        assert!(!in_rollout(62.5, Some(50.0)));
+
+        // Roughly the advertised fraction of devices is eligible
+        let eligible = (0..1000)
+            .filter(|i| in_rollout(rollout_bucket(&format!("device-{}", i), "1.2.0"), Some(10.0)))
+            .count();
+        assert!((50..150).contains(&eligible), "eligible = {}", eligible);
+    }
+}
@@ -0,0 +1,400 @@
+//! Rollback of updates that crash on startup
+//!
+//! Before an update is installed, the current installation (app bundle on
+//! macOS, executable elsewhere) is copied to a rollback directory and a small
+//! state file is written. Each launch of the new version arms a marker that is
+//! cleared once the app has stayed up for `HEALTH_WINDOW_SECS` or exits
+//! cleanly. A marker still armed on the next launch means the previous launch
+//! crashed.
+//!
+//! After the first crash the failure is reported to the backend (so the
+//! rollout can be halted) and the UI is offered a rollback; after
+//! `AUTO_ROLLBACK_THRESHOLD` crashes the rollback happens automatically.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::error::AgentError;
+use crate::storage::audit_log::{self, AuditOutcome};
+
+/// A new version that stays up this long is considered healthy
+const HEALTH_WINDOW_SECS: u64 = 60;
+
+/// Crashed launches after which the previous version is restored automatically
+const AUTO_ROLLBACK_THRESHOLD: u32 = 3;
+
+/// Event emitted when a rollback can be offered to the user
+pub const ROLLBACK_AVAILABLE_EVENT: &str = "update-rollback-available";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct RollbackState {
+    previous_version: String,
+    target_version: String,
+    /// Copy of the previous installation
+    backup_path: PathBuf,
+    /// Where the installation lives (restored in place on rollback)
+    install_path: PathBuf,
+    staged_at: DateTime<Utc>,
+    /// Launches of the target version that crashed within the health window
+    failed_launches: u32,
+    /// A launch of the target version hasn't passed the health window yet
+    launch_in_progress: bool,
+    failure_reported: bool,
+}
+
+/// Rollback status exposed to the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct RollbackStatus {
+    pub available: bool,
+    pub previous_version: Option<String>,
+    pub target_version: Option<String>,
+    pub failed_launches: u32,
+}
+
+fn rollback_dir() -> Result<PathBuf> {
+    let mut path = crate::profile::data_dir().ok_or_else(|| anyhow::anyhow!("No data directory"))?;
+    path.push("rollback");
+    Ok(path)
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(rollback_dir()?.join("state.json"))
+}
+
+fn load_state() -> Option<RollbackState> {
+    let data = std::fs::read_to_string(state_path().ok()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn save_state(state: &RollbackState) -> Result<()> {
+    let path = state_path()?;
+    std::fs::create_dir_all(rollback_dir()?)?;
+    std::fs::write(path, serde_json::to_string_pretty(state)?)?;
+    Ok(())
+}
+
+/// Remove the backup and the state file
+fn discard_backup() {
+    if let Ok(dir) = rollback_dir() {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove rollback backup: {}", e);
+            }
+        }
+    }
+}
+
+/// Path of the current installation: the .app bundle on macOS, the executable elsewhere
+fn install_path() -> Result<PathBuf> {
+    let exe_path = std::env::current_exe()?;
+
+    #[cfg(target_os = "macos")]
+    {
+        // TrackEx Agent.app/Contents/MacOS/trackex-agent -> TrackEx Agent.app
+        exe_path
+            .parent()
+            .and_then(|p| p.parent())
+            .and_then(|p| p.parent())
+            .map(|p| p.to_path_buf())
+            .ok_or_else(|| anyhow::anyhow!("Failed to determine app bundle path"))
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Ok(exe_path)
+    }
+}
+
+fn copy_installation(from: &Path, to: &Path) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        // ditto preserves the bundle's symlinks, permissions and code signature
+        let status = std::process::Command::new("ditto").arg(from).arg(to).status()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("ditto exited with {}", status));
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        std::fs::copy(from, to)?;
+    }
+
+    Ok(())
+}
+
+/// Keep a copy of the running version before installing `target_version`
+pub fn stage_rollback(target_version: &str) -> Result<()> {
+    discard_backup();
+
+    let install_path = install_path()?;
+    let file_name = install_path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid install path: {:?}", install_path))?;
+    let backup_path = rollback_dir()?.join(env!("CARGO_PKG_VERSION")).join(file_name);
+    if let Some(parent) = backup_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    copy_installation(&install_path, &backup_path)?;
+
+    save_state(&RollbackState {
+        previous_version: env!("CARGO_PKG_VERSION").to_string(),
+        target_version: target_version.to_string(),
+        backup_path,
+        install_path,
+        staged_at: Utc::now(),
+        failed_launches: 0,
+        launch_in_progress: false,
+        failure_reported: false,
+    })?;
+
+    log::info!("Staged rollback to {} before installing {}", env!("CARGO_PKG_VERSION"), target_version);
+    Ok(())
+}
+
+/// Count a crashed launch if the previous launch never passed the health window,
+/// then arm the marker for this launch
+fn register_launch(state: &mut RollbackState) {
+    if state.launch_in_progress {
+        state.failed_launches += 1;
+    }
+    state.launch_in_progress = true;
+}
+
+/// Called early on every startup. Returns the number of crashed launches of
+/// the freshly installed version (0 if there is nothing to watch).
+pub fn on_startup() -> u32 {
+    let Some(mut state) = load_state() else {
+        return 0;
+    };
+
+    if state.target_version != env!("CARGO_PKG_VERSION") {
+        // Update never applied, or we already rolled back
+        log::info!(
+            "Running {} (staged rollback was for {}), discarding backup",
+            env!("CARGO_PKG_VERSION"), state.target_version
+        );
+        discard_backup();
+        return 0;
+    }
+
+    register_launch(&mut state);
+    if state.failed_launches > 0 {
+        log::warn!(
+            "Version {} crashed during {} previous launch(es)",
+            state.target_version, state.failed_launches
+        );
+    }
+    if let Err(e) = save_state(&state) {
+        log::warn!("Failed to save rollback state: {}", e);
+    }
+    state.failed_launches
+}
+
+/// Clear the launch marker on a clean shutdown (quitting early isn't a crash)
+pub fn record_clean_exit() {
+    if let Some(mut state) = load_state() {
+        if state.launch_in_progress {
+            state.launch_in_progress = false;
+            if let Err(e) = save_state(&state) {
+                log::warn!("Failed to save rollback state: {}", e);
+            }
+        }
+    }
+}
+
+/// Tell the backend this release crashes so the rollout can be halted
+async fn report_failure(state: &RollbackState) -> Result<()> {
+    let client = crate::api::client::ApiClient::new().await?;
+    let payload = serde_json::json!({
+        "version": state.target_version,
+        "previousVersion": state.previous_version,
+        "failedLaunches": state.failed_launches,
+        "platform": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+    });
+    let response = client.post_with_auth("/api/desktop/updates/failures", &payload).await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Failure report rejected with status: {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Watch the new version: report/offer/perform a rollback after crashes,
+/// and discard the backup once the health window has passed
+pub async fn start_health_watch(app: tauri::AppHandle) {
+    let Some(mut state) = load_state() else {
+        return;
+    };
+    if state.target_version != env!("CARGO_PKG_VERSION") {
+        return;
+    }
+
+    if state.failed_launches > 0 {
+        let error = AgentError::Internal(format!(
+            "Version {} crashed {} time(s) on startup",
+            state.target_version, state.failed_launches
+        ));
+        audit_log::record_outcome("update_startup_check", AuditOutcome::Failure, Some(&error));
+
+        if !state.failure_reported {
+            match report_failure(&state).await {
+                Ok(()) => {
+                    state.failure_reported = true;
+                    if let Err(e) = save_state(&state) {
+                        log::warn!("Failed to save rollback state: {}", e);
+                    }
+                }
+                Err(e) => log::warn!("Failed to report update failure: {}", e),
+            }
+        }
+
+        if state.failed_launches >= AUTO_ROLLBACK_THRESHOLD {
+            log::error!("Automatically rolling back to {}", state.previous_version);
+            if let Err(e) = perform_rollback(&state) {
+                log::error!("Automatic rollback failed: {}", e);
+            }
+            return;
+        }
+
+        if let Err(e) = app.emit(ROLLBACK_AVAILABLE_EVENT, status_from(Some(&state))) {
+            log::warn!("Failed to emit rollback event: {}", e);
+        }
+    }
+
+    tokio::time::sleep(Duration::from_secs(HEALTH_WINDOW_SECS)).await;
+
+    // Still running after the health window: this launch didn't crash
+    let Some(mut state) = load_state().filter(|s| s.target_version == env!("CARGO_PKG_VERSION")) else {
+        return;
+    };
+    if state.failed_launches == 0 {
+        log::info!("Version {} passed the startup health check", env!("CARGO_PKG_VERSION"));
+        discard_backup();
+    } else {
+        // Earlier launches crashed: keep the backup so the user can still roll back
+        state.launch_in_progress = false;
+        if let Err(e) = save_state(&state) {
+            log::warn!("Failed to save rollback state: {}", e);
+        }
+    }
+}
+
+fn status_from(state: Option<&RollbackState>) -> RollbackStatus {
+    match state {
+        Some(state) if state.target_version == env!("CARGO_PKG_VERSION") && state.backup_path.exists() => {
+            RollbackStatus {
+                available: true,
+                previous_version: Some(state.previous_version.clone()),
+                target_version: Some(state.target_version.clone()),
+                failed_launches: state.failed_launches,
+            }
+        }
+        _ => RollbackStatus {
+            available: false,
+            previous_version: None,
+            target_version: None,
+            failed_launches: 0,
+        },
+    }
+}
+
+/// Restore the backup over the current installation and relaunch.
+/// The copy runs in a detached shell after this process has exited,
+/// because a running executable can't be replaced on Windows.
+fn perform_rollback(state: &RollbackState) -> Result<()> {
+    if !state.backup_path.exists() {
+        return Err(anyhow::anyhow!("Rollback backup is missing: {:?}", state.backup_path));
+    }
+
+    audit_log::record_outcome("update_rollback", AuditOutcome::Started, None);
+
+    #[cfg(target_os = "windows")]
+    {
+        let script = format!(
+            "timeout /t 3 /nobreak >nul & copy /Y \"{}\" \"{}\" & start \"\" \"{}\"",
+            state.backup_path.display(),
+            state.install_path.display(),
+            state.install_path.display()
+        );
+        std::process::Command::new("cmd").args(["/C", &script]).spawn()?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!(
+            "sleep 3; rm -rf \"{install}\" && ditto \"{backup}\" \"{install}\" && open -n \"{install}\"",
+            install = state.install_path.display(),
+            backup = state.backup_path.display()
+        );
+        std::process::Command::new("sh").args(["-c", &script]).spawn()?;
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let script = format!(
+            "sleep 3; cp -f \"{backup}\" \"{install}\" && \"{install}\" &",
+            install = state.install_path.display(),
+            backup = state.backup_path.display()
+        );
+        std::process::Command::new("sh").args(["-c", &script]).spawn()?;
+    }
+
+    // The restored version discards the backup on its next startup
+    log::info!("Rolling back to {}, exiting", state.previous_version);
+    std::process::exit(0);
+}
+
+/// Whether a rollback to the previous version is possible
+#[tauri::command]
+pub fn get_rollback_status() -> RollbackStatus {
+    status_from(load_state().as_ref())
+}
+
+/// Restore the version that was running before the last update
+#[tauri::command]
+pub async fn rollback_update() -> Result<(), AgentError> {
+    let result = match load_state() {
+        Some(state) if status_from(Some(&state)).available => perform_rollback(&state)
+            .map_err(|e| AgentError::Internal(format!("Rollback failed: {}", e))),
+        _ => Err(AgentError::Internal("No rollback available".to_string())),
+    };
+    audit_log::record("rollback_update", &result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crashed_launches_are_counted() {
+        let mut state = RollbackState {
+            previous_version: "1.0.0".to_string(),
+            target_version: "1.1.0".to_string(),
+            backup_path: PathBuf::from("/tmp/backup"),
+            install_path: PathBuf::from("/tmp/install"),
+            staged_at: Utc::now(),
+            failed_launches: 0,
+            launch_in_progress: false,
+            failure_reported: false,
+        };
+
+        // First launch after the install
+        register_launch(&mut state);
+        assert_eq!(state.failed_launches, 0);
+
+        // The marker was never cleared: the previous launch crashed
+        register_launch(&mut state);
+        assert_eq!(state.failed_launches, 1);
+
+        // A clean exit in between doesn't count as a crash
+        state.launch_in_progress = false;
+        register_launch(&mut state);
+        assert_eq!(state.failed_launches, 1);
+    }
+}
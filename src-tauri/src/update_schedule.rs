@@ -0,0 +1,209 @@
+//! Update installation scheduling
+//!
+//! Installing an update restarts the agent, which would interrupt a clocked-in
+//! employee mid-shift. The employee policy can defer automatic installs to
+//! outside the configured work hours, or to when no work session is active.
+//! Deferred installs (mandatory updates in particular) are queued and picked up
+//! by a background scheduler as soon as the policy allows.
+//!
+//! Installs the user starts explicitly from the update dialog are not deferred.
+
+use chrono::{DateTime, Datelike, Local, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::api::employee_settings::{self, PolicySettings};
+
+/// How often a queued install re-checks the policy
+const SCHEDULER_INTERVAL_SECS: u64 = 60;
+
+/// Event emitted when an install was queued instead of run
+pub const INSTALL_DEFERRED_EVENT: &str = "update-install-deferred";
+
+static INSTALL_QUEUED: AtomicBool = AtomicBool::new(false);
+
+/// When updates may be installed without asking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateInstallMode {
+    /// Install as soon as the update is available
+    #[default]
+    Immediate,
+    /// Only outside the policy's work hours
+    OutsideWorkHours,
+    /// Only while no work session is active
+    WhenClockedOut,
+}
+
+/// Whether an install may run now
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InstallSchedule {
+    pub allowed: bool,
+    /// Why the install is deferred
+    pub reason: Option<String>,
+    /// Start of the next allowed window (outside-work-hours mode only)
+    pub next_window: Option<String>,
+    /// An install is queued and will run when allowed
+    pub queued: bool,
+}
+
+fn parse_time(value: Option<&str>) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value?.trim(), "%H:%M").ok()
+}
+
+/// Whether `now` falls within the policy's work hours.
+/// Windows may wrap midnight (e.g. 22:00-06:00). `work_days` are ISO weekdays
+/// (1 = Monday); empty means every day.
+fn within_work_hours(policy: &PolicySettings, now: DateTime<Local>) -> bool {
+    let (Some(start), Some(end)) = (
+        parse_time(policy.work_hours_start.as_deref()),
+        parse_time(policy.work_hours_end.as_deref()),
+    ) else {
+        // No work hours configured: nothing to protect
+        return false;
+    };
+
+    let weekday = now.weekday().number_from_monday();
+    if !policy.work_days.is_empty() && !policy.work_days.contains(&weekday) {
+        return false;
+    }
+
+    let time = NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap_or_default();
+    if start <= end {
+        time >= start && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+fn allowed() -> InstallSchedule {
+    InstallSchedule { allowed: true, reason: None, next_window: None, queued: false }
+}
+
+/// Decide whether an automatic install may run now
+pub fn evaluate(policy: &PolicySettings, now: DateTime<Local>, clocked_in: bool) -> InstallSchedule {
+    let deferred = |reason: &str, next_window: Option<String>| InstallSchedule {
+        allowed: false,
+        reason: Some(reason.to_string()),
+        next_window,
+        queued: false,
+    };
+
+    match policy.update_install_mode {
+        UpdateInstallMode::Immediate => allowed(),
+        UpdateInstallMode::WhenClockedOut if clocked_in => {
+            deferred("The update will be installed after you clock out", None)
+        }
+        UpdateInstallMode::OutsideWorkHours if within_work_hours(policy, now) => deferred(
+            "The update will be installed outside work hours",
+            policy.work_hours_end.clone(),
+        ),
+        _ => allowed(),
+    }
+}
+
+async fn current_schedule() -> InstallSchedule {
+    let policy = employee_settings::get_policy_settings().await;
+    let clocked_in = crate::sampling::is_clocked_in().await;
+    let mut schedule = evaluate(&policy, Local::now(), clocked_in);
+    // An overdue mandatory update is no longer deferred (see update_lockout.rs)
+    if !schedule.allowed && crate::update_lockout::is_locked().await {
+        schedule = allowed();
+    }
+    schedule.queued = INSTALL_QUEUED.load(Ordering::SeqCst);
+    schedule
+}
+
+/// Whether an automatic update install may run now
+#[tauri::command]
+pub async fn get_install_schedule() -> InstallSchedule {
+    current_schedule().await
+}
+
+/// Install an update now if the policy allows it, otherwise queue it for the
+/// next allowed window. Used for automatic (e.g. mandatory) installs.
+#[tauri::command]
+pub async fn queue_update_install(app: tauri::AppHandle) -> Result<InstallSchedule, String> {
+    let schedule = current_schedule().await;
+    if schedule.allowed {
+        INSTALL_QUEUED.store(false, Ordering::SeqCst);
+        crate::update_manager::install_update(app).await?;
+        return Ok(schedule);
+    }
+
+    INSTALL_QUEUED.store(true, Ordering::SeqCst);
+    log::info!("Update install deferred: {}", schedule.reason.as_deref().unwrap_or("policy"));
+    let schedule = InstallSchedule { queued: true, ..schedule };
+    if let Err(e) = app.emit(INSTALL_DEFERRED_EVENT, &schedule) {
+        log::warn!("Failed to emit install deferred event: {}", e);
+    }
+    Ok(schedule)
+}
+
+/// Run queued installs once the policy allows them
+pub async fn start_install_scheduler(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(Duration::from_secs(SCHEDULER_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        if !INSTALL_QUEUED.load(Ordering::SeqCst) {
+            continue;
+        }
+        if !current_schedule().await.allowed {
+            continue;
+        }
+
+        log::info!("Installing queued update now that the policy allows it");
+        INSTALL_QUEUED.store(false, Ordering::SeqCst);
+        if let Err(e) = crate::update_manager::install_update(app.clone()).await {
+            log::error!("Queued update install failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn policy_with_mode(mode: UpdateInstallMode, start: &str, end: &str) -> PolicySettings {
+        PolicySettings {
+            update_install_mode: mode,
+            work_hours_start: Some(start.to_string()),
+            work_hours_end: Some(end.to_string()),
+            work_days: vec![1, 2, 3, 4, 5],
+            ..PolicySettings::default()
+        }
+    }
+
+    #[test]
+    fn test_outside_work_hours() {
+        let policy = policy_with_mode(UpdateInstallMode::OutsideWorkHours, "09:00", "17:00");
+        // 2024-05-01 is a Wednesday, 2024-05-04 a Saturday
+        let wednesday_noon = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let wednesday_evening = Local.with_ymd_and_hms(2024, 5, 1, 18, 30, 0).unwrap();
+        let saturday_noon = Local.with_ymd_and_hms(2024, 5, 4, 12, 0, 0).unwrap();
+
+        assert!(!evaluate(&policy, wednesday_noon, false).allowed);
+        assert!(evaluate(&policy, wednesday_evening, true).allowed);
+        assert!(evaluate(&policy, saturday_noon, true).allowed);
+
+        // Night shift wrapping midnight
+        let night = PolicySettings {
+            work_days: vec![],
+            ..policy_with_mode(UpdateInstallMode::OutsideWorkHours, "22:00", "06:00")
+        };
+        assert!(!evaluate(&night, Local.with_ymd_and_hms(2024, 5, 1, 2, 0, 0).unwrap(), false).allowed);
+        assert!(evaluate(&night, wednesday_noon, false).allowed);
+    }
+
+    #[test]
+    fn test_when_clocked_out() {
+        let policy = policy_with_mode(UpdateInstallMode::WhenClockedOut, "09:00", "17:00");
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        assert!(!evaluate(&policy, now, true).allowed);
+        assert!(evaluate(&policy, now, false).allowed);
+        assert!(evaluate(&PolicySettings::default(), now, true).allowed);
+    }
+}
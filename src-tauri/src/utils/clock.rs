@@ -0,0 +1,140 @@
+//! Injectable time source
+//!
+//! Idle, expiry and day-rollover decisions used to call `Utc::now()` directly,
+//! so they could only be tested in real time. Sampling and work sessions read
+//! the time through `clock::now()`, and `ReportGenerator` holds a `Clock`.
+//! Production runs on `SystemClock`; tests install a `ManualClock` and move it
+//! forward explicitly.
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The real wall clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+#[allow(dead_code)]
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(now) }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.lock() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.lock();
+        *now += by;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DateTime<Utc>> {
+        match self.now.lock() {
+            Ok(guard) => guard,
+            Err(e) => e.into_inner(),
+        }
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.lock()
+    }
+}
+
+/// Installed clock; `None` is the system clock
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+/// Serializes `install` so two tests can't swap clocks under each other
+static INSTALL_LOCK: Mutex<()> = Mutex::new(());
+
+/// The process clock
+pub fn current() -> Arc<dyn Clock> {
+    let installed = match CLOCK.read() {
+        Ok(guard) => guard.clone(),
+        Err(e) => e.into_inner().clone(),
+    };
+    installed.unwrap_or_else(|| Arc::new(SystemClock))
+}
+
+/// Current time on the process clock
+pub fn now() -> DateTime<Utc> {
+    match CLOCK.read() {
+        Ok(guard) => guard.as_ref().map_or_else(Utc::now, |clock| clock.now()),
+        Err(e) => e.into_inner().as_ref().map_or_else(Utc::now, |clock| clock.now()),
+    }
+}
+
+/// Current local time on the process clock
+pub fn local_now() -> DateTime<Local> {
+    now().with_timezone(&Local)
+}
+
+/// Today's local date on the process clock
+pub fn today() -> NaiveDate {
+    local_now().date_naive()
+}
+
+fn set(clock: Option<Arc<dyn Clock>>) {
+    match CLOCK.write() {
+        Ok(mut guard) => *guard = clock,
+        Err(e) => *e.into_inner() = clock,
+    }
+}
+
+/// Restores the system clock when dropped
+pub struct ClockGuard {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for ClockGuard {
+    fn drop(&mut self) {
+        set(None);
+    }
+}
+
+/// Make `clock` the process clock until the guard is dropped
+#[allow(dead_code)]
+pub fn install(clock: Arc<dyn Clock>) -> ClockGuard {
+    let lock = match INSTALL_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(e) => e.into_inner(),
+    };
+    set(Some(clock));
+    ClockGuard { _lock: lock }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_manual_clock_drives_process_clock() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 4, 23, 59, 0).unwrap();
+        let clock = Arc::new(ManualClock::new(start));
+        {
+            let _guard = install(clock.clone());
+            assert_eq!(now(), start);
+            clock.advance(Duration::minutes(2));
+            assert_eq!(now().date_naive(), NaiveDate::from_ymd_opt(2024, 3, 5).unwrap());
+            assert_eq!(current().now(), start + Duration::minutes(2));
+        }
+        // Back on the system clock
+        assert!(now() > start + Duration::days(365));
+    }
+}
@@ -1,12 +1,112 @@
 use env_logger::{Builder, Target};
+use lazy_static::lazy_static;
 use log::LevelFilter;
+use regex::Regex;
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
 use std::io::Write;
+use std::path::PathBuf;
+
+/// Log lines are prefixed with this UTC timestamp format
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// The log file is rotated to `agent.log.1` on startup once it exceeds this size
+const MAX_LOG_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+lazy_static! {
+    static ref BEARER_TOKEN: Regex = Regex::new(r"(?i)\b(bearer\s+)[A-Za-z0-9._~+/=-]+").unwrap();
+    /// Path, query and fragment of http(s) URLs; the host is kept
+    static ref URL_PATH: Regex = Regex::new(r#"(?i)\b(https?://[^\s/?#"']+)[/?#][^\s"']*"#).unwrap();
+}
+
+pub fn log_dir() -> Option<PathBuf> {
+    let mut path = crate::profile::data_dir()?;
+    path.push("logs");
+    Some(path)
+}
+
+/// Log files, oldest first
+pub fn log_files() -> Vec<PathBuf> {
+    let Some(dir) = log_dir() else {
+        return Vec::new();
+    };
+    vec![dir.join("agent.log.1"), dir.join("agent.log")]
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+}
+
+fn open_log_file() -> Option<File> {
+    let dir = log_dir()?;
+    std::fs::create_dir_all(&dir).ok()?;
+    let path = dir.join("agent.log");
+    if std::fs::metadata(&path).map(|m| m.len() > MAX_LOG_FILE_BYTES).unwrap_or(false) {
+        let _ = std::fs::rename(&path, dir.join("agent.log.1"));
+    }
+    OpenOptions::new().create(true).append(true).open(path).ok()
+}
+
+/// Strip bearer tokens and URL paths/queries from a log line. Logs are
+/// persisted and uploaded with diagnostics, so they must not carry
+/// credentials or browsing detail that the URL policy would have removed.
+pub fn redact(line: &str) -> Cow<'_, str> {
+    let line = match BEARER_TOKEN.replace_all(line, "${1}[redacted]") {
+        Cow::Borrowed(_) => Cow::Borrowed(line),
+        Cow::Owned(redacted) => Cow::Owned(redacted),
+    };
+    match URL_PATH.replace_all(&line, "${1}/[redacted]") {
+        Cow::Borrowed(_) => line,
+        Cow::Owned(redacted) => Cow::Owned(redacted),
+    }
+}
+
+/// Writes every log record, redacted, to stdout and, when available, the log file
+struct TeeWriter {
+    file: Option<File>,
+}
+
+impl Write for TeeWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let record = String::from_utf8_lossy(buf);
+        let redacted = redact(&record);
+        let _ = std::io::stdout().write_all(redacted.as_bytes());
+        if let Some(file) = self.file.as_mut() {
+            let _ = file.write_all(redacted.as_bytes());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let _ = std::io::stdout().flush();
+        if let Some(file) = self.file.as_mut() {
+            let _ = file.flush();
+        }
+        Ok(())
+    }
+}
+
+/// Lines logged at or after `since`. Lines without a timestamp (multi-line
+/// messages) follow the line they continue.
+pub fn lines_since(contents: &str, since: chrono::DateTime<chrono::Utc>) -> Vec<&str> {
+    let mut keep = false;
+    contents
+        .lines()
+        .filter(|line| {
+            if let Some(timestamp) = line.strip_prefix('[').and_then(|rest| rest.get(..23)) {
+                if let Ok(time) = chrono::NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT) {
+                    keep = time.and_utc() >= since;
+                }
+            }
+            keep
+        })
+        .collect()
+}
 
 pub fn init() {
     let mut builder = Builder::from_default_env();
-    
+
     builder
-        .target(Target::Stdout)
+        .target(Target::Pipe(Box::new(TeeWriter { file: open_log_file() })))
         .filter_level(if cfg!(debug_assertions) {
             LevelFilter::Debug
         } else {
@@ -16,11 +116,41 @@ pub fn init() {
             writeln!(
                 buf,
                 "[{}] [{}] {}",
-                chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                chrono::Utc::now().format(TIMESTAMP_FORMAT),
                 record.level(),
                 record.args()
             )
         })
         .init();
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_lines_since() {
+        let contents = "[2024-03-01 09:00:00.000] [INFO] old\n\
+                        [2024-03-01 11:00:00.000] [INFO] recent\n\
+                        continued\n\
+                        [2024-03-01 12:30:00.500] [WARN] newest";
+        let since = chrono::Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+        assert_eq!(
+            lines_since(contents, since),
+            vec!["[2024-03-01 11:00:00.000] [INFO] recent", "continued", "[2024-03-01 12:30:00.500] [WARN] newest"]
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact("Authorization: Bearer abc.DEF-123"), "Authorization: Bearer [redacted]");
+        assert_eq!(
+            redact("Got URL https://mail.example.com/inbox?q=salary#msg, title 'x'"),
+            "Got URL https://mail.example.com/[redacted] title 'x'"
+        );
+        assert_eq!(redact("Using server endpoint https://api.trackex.app"), "Using server endpoint https://api.trackex.app");
+        assert!(matches!(redact("nothing to hide"), Cow::Borrowed(_)));
+    }
+}
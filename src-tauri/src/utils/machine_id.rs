@@ -0,0 +1,103 @@
+//! Hardware-derived device identifier
+//!
+//! The OS machine identifier (`/etc/machine-id` on Linux, `IOPlatformUUID` on
+//! macOS, `MachineGuid` on Windows) survives reinstalling the agent, unlike a
+//! random UUID kept in the local database. It is never sent as is: it is
+//! hashed with an app-specific salt so the value can't be correlated with
+//! other software reading the same identifier.
+//!
+//! The machine identifier is shared by every account on the machine, so the
+//! agent's data directory (which is per OS user and per profile) is mixed in
+//! too: two employees sharing a computer get separate devices.
+
+use sha2::{Digest, Sha256};
+
+const SALT: &str = "trackex-device-uuid-v1";
+
+#[cfg(target_os = "linux")]
+fn raw_machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn raw_machine_id() -> Option<String> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    parse_ioreg_uuid(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `"IOPlatformUUID" = "564D7F3E-..."`
+#[cfg(any(target_os = "macos", test))]
+fn parse_ioreg_uuid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find(|line| line.contains("\"IOPlatformUUID\""))?
+        .split('"')
+        .nth(3)
+        .map(str::to_string)
+}
+
+#[cfg(target_os = "windows")]
+fn raw_machine_id() -> Option<String> {
+    let output = std::process::Command::new("reg")
+        .args(["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"])
+        .output()
+        .ok()?;
+    // "    MachineGuid    REG_SZ    0b8c...-..."
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("MachineGuid"))?
+        .split_whitespace()
+        .last()
+        .map(str::to_string)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn raw_machine_id() -> Option<String> {
+    None
+}
+
+/// Salted hash of a machine identifier and the install `scope` (data
+/// directory), formatted as a (version 8) UUID
+pub fn derive_device_uuid(machine_id: &str, scope: &str) -> String {
+    let digest = Sha256::digest(format!("{}:{}:{}", SALT, machine_id.to_lowercase(), scope).as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x80;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    uuid::Uuid::from_bytes(bytes).to_string()
+}
+
+/// Stable device UUID for this machine, if the OS identifier can be read
+pub fn hardware_device_uuid() -> Option<String> {
+    let machine_id = raw_machine_id().filter(|id| !id.is_empty())?;
+    let scope = crate::profile::data_dir()?;
+    Some(derive_device_uuid(&machine_id, &scope.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_uuid_is_stable_and_salted() {
+        let a = derive_device_uuid("564D7F3E-1234-5678-9ABC-DEF012345678", "/Users/ana/TrackEx");
+        assert_eq!(a, derive_device_uuid("564d7f3e-1234-5678-9abc-def012345678", "/Users/ana/TrackEx"));
+        assert_ne!(a, derive_device_uuid("other-machine", "/Users/ana/TrackEx"));
+        // Another account on the same machine
+        assert_ne!(a, derive_device_uuid("564D7F3E-1234-5678-9ABC-DEF012345678", "/Users/ben/TrackEx"));
+        assert!(!a.contains("564d7f3e"));
+        assert_eq!(uuid::Uuid::parse_str(&a).unwrap().get_version_num(), 8);
+    }
+
+    #[test]
+    fn test_parse_ioreg_uuid() {
+        let output = "  | \"IOPlatformSerialNumber\" = \"C02XX\"\n  | \"IOPlatformUUID\" = \"564D7F3E-1234-5678-9ABC-DEF012345678\"\n";
+        assert_eq!(parse_ioreg_uuid(output).as_deref(), Some("564D7F3E-1234-5678-9ABC-DEF012345678"));
+    }
+}
@@ -1,6 +1,11 @@
 pub mod logging;
+pub mod machine_id;
 pub mod productivity;
 pub mod privacy;
+pub mod public_suffix;
+pub mod session_environment;
+pub mod clock;
+pub mod time;
 
 #[cfg(target_os = "windows")]
 pub mod windows_imports {
@@ -6,11 +6,15 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::public_suffix;
 
 lazy_static! {
-    /// Known site names to domain mapping
+    /// Bundled known site names to domain mapping (fallback until the
+    /// server-synced mapping is available, see `api::known_sites`)
     /// This maps common site names that appear in browser window titles to their actual domains
-    static ref KNOWN_SITES: HashMap<&'static str, &'static str> = {
+    static ref BUNDLED_KNOWN_SITES: HashMap<&'static str, &'static str> = {
         let mut m = HashMap::new();
         // Developer tools & platforms
         m.insert("github", "github.com");
@@ -243,6 +247,38 @@ lazy_static! {
         
         m
     };
+    
+    /// Active known site mapping, sorted by name length (longest first) so
+    /// more specific names match before shorter ones
+    static ref KNOWN_SITES: RwLock<Vec<(String, String)>> = RwLock::new(sort_known_sites(
+        BUNDLED_KNOWN_SITES
+            .iter()
+            .map(|(name, domain)| (name.to_string(), domain.to_string()))
+            .collect(),
+    ));
+}
+
+fn sort_known_sites(mut sites: Vec<(String, String)>) -> Vec<(String, String)> {
+    sites.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    sites
+}
+
+/// Replace the known site mapping (server-synced). An empty mapping keeps the current one.
+pub fn set_known_sites(sites: Vec<(String, String)>) {
+    if sites.is_empty() {
+        return;
+    }
+    let sites = sites
+        .into_iter()
+        .map(|(name, domain)| (name.trim().to_lowercase(), domain.trim().to_lowercase()))
+        .filter(|(name, domain)| !name.is_empty() && !domain.is_empty())
+        .collect();
+    *KNOWN_SITES.write().unwrap() = sort_known_sites(sites);
+}
+
+/// Number of entries in the active known site mapping
+pub fn known_sites_count() -> usize {
+    KNOWN_SITES.read().unwrap().len()
 }
 
 /// List of known browser process names (Windows)
@@ -320,6 +356,40 @@ pub fn is_browser_by_name(app_name: &str) -> bool {
     browser_names.iter().any(|&name| name_lower.contains(name))
 }
 
+/// A domain in both of its IDN forms
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedDomain {
+    /// Unicode form for display, e.g. "münchen.de"
+    pub display: String,
+    /// Canonical punycode form for storage and grouping, e.g. "xn--mnchen-3ya.de"
+    pub canonical: String,
+}
+
+/// Normalize an internationalized domain so "münchen.de" and "xn--mnchen-3ya.de"
+/// end up as the same entry. Invalid names fall back to their lowercase form.
+pub fn normalize_domain(domain: &str) -> NormalizedDomain {
+    let lower = domain.trim().trim_end_matches('.').to_lowercase();
+    let canonical = idna::domain_to_ascii(&lower).unwrap_or_else(|_| lower.clone());
+    let (display, result) = idna::domain_to_unicode(&canonical);
+    let display = if result.is_ok() { display } else { canonical.clone() };
+    NormalizedDomain { display, canonical }
+}
+
+/// Unicode display form of a stored (punycode) domain
+#[allow(dead_code)]
+pub fn display_domain(domain: &str) -> String {
+    normalize_domain(domain).display
+}
+
+/// Canonical form of a URL: an internationalized host is replaced by its
+/// punycode form, everything else is kept as-is
+fn canonicalize_url(url: &str, host: &str) -> String {
+    if host.is_ascii() {
+        return url.to_string();
+    }
+    url.replacen(host, &normalize_domain(host).canonical, 1)
+}
+
 /// Extract domain from a full URL
 /// 
 /// Examples:
@@ -328,9 +398,9 @@ pub fn is_browser_by_name(app_name: &str) -> bool {
 /// - "github.com" -> "github.com"
 pub fn extract_domain_from_url(url: &str) -> Option<String> {
     lazy_static! {
-        // Match URLs with protocol
+        // Match URLs with protocol (labels may be internationalized)
         static ref URL_REGEX: Regex = Regex::new(
-            r"^(?:https?://)?(?:www\.)?([a-zA-Z0-9][-a-zA-Z0-9]*(?:\.[a-zA-Z0-9][-a-zA-Z0-9]*)+)"
+            r"^(?:https?://)?(?:www\.)?([\p{L}\p{N}][-\p{L}\p{N}]*(?:\.[\p{L}\p{N}][-\p{L}\p{N}]*)+)"
         ).unwrap();
     }
     
@@ -338,15 +408,19 @@ pub fn extract_domain_from_url(url: &str) -> Option<String> {
     if let Some(captures) = URL_REGEX.captures(url) {
         if let Some(domain_match) = captures.get(1) {
             let domain = domain_match.as_str().to_lowercase();
-            // Remove www. prefix if present
-            let cleaned = domain.strip_prefix("www.").unwrap_or(&domain);
-            return Some(cleaned.to_string());
+            // Without a scheme, only accept hosts with a real public suffix (not "main.rs")
+            if url.contains("://") || public_suffix::looks_like_domain(&domain) {
+                // Remove www. prefix if present
+                let cleaned = domain.strip_prefix("www.").unwrap_or(&domain);
+                return Some(cleaned.to_string());
+            }
         }
     }
     
     // If no match, try to extract domain from simple format
     let trimmed = url.trim();
-    if !trimmed.contains('/') && trimmed.contains('.') && !trimmed.contains(' ') {
+    if !trimmed.contains('/') && trimmed.contains('.') && !trimmed.contains(' ')
+        && public_suffix::looks_like_domain(trimmed) {
         let cleaned = trimmed.to_lowercase();
         let cleaned = cleaned.strip_prefix("www.").unwrap_or(&cleaned);
         return Some(cleaned.to_string());
@@ -364,10 +438,10 @@ pub fn extract_domain_from_url(url: &str) -> Option<String> {
 /// - "Trackex - Time & Productivity Tracking - Google Chrome"
 pub fn extract_domain_from_window_title(title: &str) -> Option<String> {
     lazy_static! {
-        // Match domain-like patterns in window titles
-        // Comprehensive TLD list including ccTLDs and new gTLDs
+        // Match domain-like tokens in window titles; candidates are validated
+        // against the Public Suffix List (handles new gTLDs and ccSLDs like .co.uk)
         static ref DOMAIN_IN_TITLE: Regex = Regex::new(
-            r"([a-zA-Z0-9][-a-zA-Z0-9]*\.(?:com|org|net|io|co|dev|app|ai|edu|gov|me|us|uk|de|fr|jp|cn|in|au|ca|br|ru|es|it|nl|se|no|fi|dk|pl|ch|at|be|nz|za|mx|ar|cl|kr|sg|hk|tw|my|th|ph|id|vn|pk|bd|lk|np|live|tv|gg|bar|xyz|club|online|site|tech|space|fun|store|shop|blog|info|biz|cc|ws|to|fm|am|so|vc|ly|gl|im|is|ms|sh|ac|la|pw|tf|tk|gq|cf|ga|ml|top|work|world|one|pro|media|news|social|network|agency|design|digital|video|watch|movie|stream|games|game|rocks|cool|ninja|life|today|zone|systems|solutions|services|group|cloud|download|email|link|click|website|web|page|host|domains|center|company|support|studio|tools|software|ventures|enterprises|international|global|team|partners|holdings|investments|capital|finance|consulting|management|marketing|technology|industries|productions|creative|academy|institute|university|education|school|training|learning|courses|health|healthcare|medical|dental|fitness|sports|travel|tours|holidays|vacation|hotel|hotels|restaurant|food|cafe|coffee|bar|pub|beer|wine|pizza|kitchen|recipes|cooking|fashion|style|beauty|salon|spa|jewelry|watches|shoes|clothing|boutique|wedding|events|party|gifts|flowers|cards|toys|baby|kids|family|pets|dating|singles|photos|photography|gallery|art|music|band|audio|video|radio|film|movies|theater|tickets|show|entertainment|casino|poker|bet|betting|lottery|games|toys|play|fun|discount|deals|coupons|sale|promo|cheap|express|direct|delivery|shipping|supply|parts|auto|car|cars|bike|motorcycle|taxi|rent|rental|realestate|property|properties|house|home|homes|apartment|land|construction|build|builders|plumber|electric|solar|energy|green|eco|organic|natural|garden|farm|agriculture|legal|law|lawyer|attorney|accountant|insurance|loans|mortgage|credit|bank|cash|money|pay|exchange|trade|forex|crypto|bitcoin|invest|gold|diamonds|security|safe|storage|cleaning|repair|maintenance|moving|movers|contractors|builders|roofing|painting|flooring|hvac|plumbing|electrical|landscaping|fencing|pool|pest|locksmith|glass|windows|doors|furniture|kitchen|bath|interior|exterior))"
+            r"(?:^|[^-\p{L}\p{N}.])((?:[\p{L}\p{N}][-\p{L}\p{N}]*\.)+\p{L}[-\p{L}\p{N}]*)"
         ).unwrap();
         
         // Alternative: match URLs directly in title
@@ -383,10 +457,13 @@ pub fn extract_domain_from_window_title(title: &str) -> Option<String> {
         }
     }
     
-    // Then try to find domain pattern (like "github.com" directly in title)
-    if let Some(captures) = DOMAIN_IN_TITLE.captures(title) {
+    // Then try to find domain pattern (like "github.com" or "bbc.co.uk" directly in title)
+    for captures in DOMAIN_IN_TITLE.captures_iter(title) {
         if let Some(domain_match) = captures.get(1) {
             let domain = domain_match.as_str().to_lowercase();
+            if !public_suffix::looks_like_domain(&domain) {
+                continue;
+            }
             // Remove www. prefix if somehow present
             let cleaned = domain.strip_prefix("www.").unwrap_or(&domain);
             return Some(cleaned.to_string());
@@ -425,11 +502,11 @@ pub fn extract_domain_from_window_title(title: &str) -> Option<String> {
         .trim();
     
     // Check for known site names - longer matches first to avoid partial matches
-    // Sort by key length descending for more specific matches
-    let mut sorted_sites: Vec<_> = KNOWN_SITES.iter().collect();
-    sorted_sites.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+    // (the mapping is kept sorted by name length descending)
+    let known_sites = KNOWN_SITES.read().unwrap();
     
-    for (name, domain) in sorted_sites {
+    for (name, domain) in known_sites.iter() {
+        let name = name.as_str();
         // Check if the site name appears at the start of the title (most common)
         // or as a word boundary (to avoid matching "github" in "notgithub")
         if title_without_browser.starts_with(name) 
@@ -438,7 +515,7 @@ pub fn extract_domain_from_window_title(title: &str) -> Option<String> {
             || title_without_browser.contains(&format!("{} -", name))
             || title_without_browser.ends_with(&format!(" {}", name))
         {
-            log::debug!("Matched known site '{}' -> '{}' in title: {}", name, domain, title);
+            log::debug!("Matched known site '{}' -> '{}' in title", name, domain);
             return Some(domain.to_string());
         }
     }
@@ -458,33 +535,156 @@ pub fn apply_domain_only_policy(url: Option<&str>, domain_only: bool) -> Option<
     }
 }
 
+/// Placeholder stored instead of URLs/domains that are not on the allowlist
+pub const OTHER_DOMAIN: &str = "other";
+
 /// Sanitize URL/domain for storage based on policy
 pub struct UrlSanitizer {
     pub browser_domain_only: bool,
+    /// Allowlist-only mode: when set, only these domains (and their subdomains)
+    /// are recorded, everything else is stored as "other"
+    pub allowlist: Option<Vec<String>>,
 }
 
 impl UrlSanitizer {
     pub fn new(browser_domain_only: bool) -> Self {
-        Self { browser_domain_only }
+        Self { browser_domain_only, allowlist: None }
+    }
+    
+    /// Enable allowlist-only mode with the org-approved domains
+    pub fn with_allowlist(mut self, domains: Vec<String>) -> Self {
+        let domains = domains
+            .iter()
+            .map(|d| d.trim().to_lowercase())
+            .map(|d| d.trim_start_matches("*.").trim_start_matches("www.").to_string())
+            .filter(|d| !d.is_empty())
+            .map(|d| normalize_domain(&d).canonical)
+            .collect();
+        self.allowlist = Some(domains);
+        self
+    }
+    
+    /// Whether a domain may be recorded under the current policy
+    pub fn is_allowed(&self, domain: &str) -> bool {
+        match &self.allowlist {
+            None => true,
+            Some(allowed) => {
+                let domain = domain.to_lowercase();
+                allowed.iter().any(|a| domain == *a || domain.ends_with(&format!(".{}", a)))
+            }
+        }
+    }
+    
+    /// Sanitize a domain that was detected without a URL (e.g. from the window title)
+    /// Returns (url_to_store, domain) with the domain in canonical punycode form
+    pub fn sanitize_domain(&self, domain: &str) -> (Option<String>, Option<String>) {
+        let domain = normalize_domain(domain).canonical;
+        if !self.is_allowed(&domain) {
+            return (Some(OTHER_DOMAIN.to_string()), Some(OTHER_DOMAIN.to_string()));
+        }
+        (Some(domain.clone()), Some(domain))
     }
     
     /// Sanitize the URL based on the policy
-    /// Returns (url_to_store, domain)
+    /// Returns (url_to_store, domain); internationalized hosts are stored in
+    /// canonical punycode form, use `display_domain` to show them
     pub fn sanitize(&self, url: Option<&str>) -> (Option<String>, Option<String>) {
-        let domain = url.and_then(extract_domain_from_url);
+        let raw_domain = url.and_then(extract_domain_from_url);
+        let domain = raw_domain.as_deref().map(|d| normalize_domain(d).canonical);
+        
+        if self.allowlist.is_some() && url.is_some() {
+            match domain.as_deref() {
+                Some(d) if self.is_allowed(d) => {}
+                _ => return (Some(OTHER_DOMAIN.to_string()), Some(OTHER_DOMAIN.to_string())),
+            }
+        }
         
         let url_to_store = if self.browser_domain_only {
             // When domain-only mode is enabled, store domain as URL
             domain.clone()
         } else {
             // Store full URL
-            url.map(|u| u.to_string())
+            url.map(|u| match raw_domain.as_deref() {
+                Some(host) => canonicalize_url(u, host),
+                None => u.to_string(),
+            })
         };
         
         (url_to_store, domain)
     }
 }
 
+/// Window title to record for a (sanitized) domain. The title of a site
+/// stored as "other" names the page and often the domain, so only the
+/// browser name is kept.
+pub fn recorded_window_title(title: Option<&str>, domain: Option<&str>, app_name: &str) -> Option<String> {
+    if domain == Some(OTHER_DOMAIN) {
+        Some(app_name.to_string())
+    } else {
+        title.map(str::to_string)
+    }
+}
+
+/// A regex-based window title scrubbing rule (synced from the backend)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TitleScrubRule {
+    pub id: String,
+    /// Regular expression matched against the window title
+    pub pattern: String,
+    /// Replacement text (supports `$1`-style capture references)
+    #[serde(default = "default_scrub_replacement")]
+    pub replacement: String,
+    #[serde(default = "default_true")]
+    pub is_active: bool,
+}
+
+fn default_scrub_replacement() -> String {
+    "[redacted]".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Scrubs sensitive parts of window titles (email subjects, patient IDs,
+/// ticket numbers, ...) before they are stored or sent
+#[derive(Debug, Default, Clone)]
+pub struct TitleScrubber {
+    rules: Vec<(Regex, String)>,
+}
+
+impl TitleScrubber {
+    /// Compile the active rules. Invalid patterns are skipped and logged.
+    pub fn new(rules: &[TitleScrubRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter(|rule| rule.is_active)
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Some((regex, rule.replacement.clone())),
+                Err(e) => {
+                    log::warn!("Skipping invalid title scrub rule {}: {}", rule.id, e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+    
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+    
+    /// Apply all rules in order
+    pub fn scrub(&self, title: &str) -> String {
+        let mut scrubbed = title.to_string();
+        for (regex, replacement) in &self.rules {
+            scrubbed = regex.replace_all(&scrubbed, replacement.as_str()).into_owned();
+        }
+        scrubbed
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -527,6 +727,16 @@ mod tests {
             Some("stackoverflow.com".to_string())
         );
         
+        // ccSLDs and new gTLDs are recognized via the Public Suffix List
+        assert_eq!(
+            extract_domain_from_window_title("news.bbc.co.uk - Mozilla Firefox"),
+            Some("news.bbc.co.uk".to_string())
+        );
+        assert_eq!(
+            extract_domain_from_window_title("watchseries.bar - Google Chrome"),
+            Some("watchseries.bar".to_string())
+        );
+        
         // Test known site name mapping
         assert_eq!(
             extract_domain_from_window_title("GitHub - Where software is built - Google Chrome"),
@@ -570,4 +780,84 @@ mod tests {
         assert_eq!(url, Some("https://github.com/user/repo".to_string()));
         assert_eq!(domain, Some("github.com".to_string()));
     }
+    
+    #[test]
+    fn test_title_scrubber() {
+        let rule = |id: &str, pattern: &str, replacement: &str| TitleScrubRule {
+            id: id.to_string(),
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            is_active: true,
+        };
+        let scrubber = TitleScrubber::new(&[
+            rule("ticket", r"\b[A-Z]{2,10}-\d+\b", "[ticket]"),
+            rule("patient", r"(?i)\bMRN[:# ]*\d+", "MRN [redacted]"),
+            rule("mail", r"^.* - (Inbox|Mail) - (.+)$", "[subject] - $1 - $2"),
+            rule("broken", r"(", ""),
+        ]);
+        
+        // The invalid rule is skipped
+        assert_eq!(scrubber.rule_count(), 3);
+        assert_eq!(scrubber.scrub("PROJ-1234 Fix login - Jira"), "[ticket] Fix login - Jira");
+        assert_eq!(scrubber.scrub("Chart MRN: 889123 - Epic"), "Chart MRN [redacted] - Epic");
+        assert_eq!(
+            scrubber.scrub("Quarterly layoffs - Inbox - jane@acme.com"),
+            "[subject] - Inbox - jane@acme.com"
+        );
+        assert_eq!(scrubber.scrub("main.rs - VSCode"), "main.rs - VSCode");
+    }
+    
+    #[test]
+    fn test_url_sanitizer_allowlist_only() {
+        let sanitizer = UrlSanitizer::new(false)
+            .with_allowlist(vec!["github.com".to_string(), "*.atlassian.net".to_string()]);
+        
+        let (url, domain) = sanitizer.sanitize(Some("https://github.com/user/repo"));
+        assert_eq!(url, Some("https://github.com/user/repo".to_string()));
+        assert_eq!(domain, Some("github.com".to_string()));
+        
+        let (_, domain) = sanitizer.sanitize(Some("https://acme.atlassian.net/browse/X-1"));
+        assert_eq!(domain, Some("acme.atlassian.net".to_string()));
+        
+        let (url, domain) = sanitizer.sanitize(Some("https://reddit.com/r/rust"));
+        assert_eq!(url, Some(OTHER_DOMAIN.to_string()));
+        assert_eq!(domain, Some(OTHER_DOMAIN.to_string()));
+        
+        // Look-alike domains are not allowed
+        assert!(!sanitizer.is_allowed("notgithub.com"));
+        assert_eq!(sanitizer.sanitize_domain("youtube.com").1, Some(OTHER_DOMAIN.to_string()));
+    }
+    
+    #[test]
+    fn test_title_of_other_domain_is_redacted() {
+        let sanitizer = UrlSanitizer::new(false).with_allowlist(vec!["github.com".to_string()]);
+        let title = "r/rust - Reddit - Google Chrome";
+        
+        let (_, domain) = sanitizer.sanitize(Some("https://reddit.com/r/rust"));
+        assert_eq!(
+            recorded_window_title(Some(title), domain.as_deref(), "Google Chrome"),
+            Some("Google Chrome".to_string())
+        );
+        
+        let (_, domain) = sanitizer.sanitize(Some("https://github.com/user/repo"));
+        assert_eq!(recorded_window_title(Some(title), domain.as_deref(), "Google Chrome"), Some(title.to_string()));
+        assert_eq!(recorded_window_title(Some("main.rs"), None, "Code"), Some("main.rs".to_string()));
+    }
+    
+    #[test]
+    fn test_idn_normalization() {
+        let normalized = normalize_domain("xn--mnchen-3ya.de");
+        assert_eq!(normalized.display, "münchen.de");
+        assert_eq!(normalized.canonical, "xn--mnchen-3ya.de");
+        assert_eq!(normalize_domain("München.de"), normalized);
+        
+        // Unicode and punycode spellings of the same site are stored identically
+        let sanitizer = UrlSanitizer::new(false);
+        let (url, domain) = sanitizer.sanitize(Some("https://münchen.de/rathaus"));
+        assert_eq!(url, Some("https://xn--mnchen-3ya.de/rathaus".to_string()));
+        assert_eq!(domain, Some("xn--mnchen-3ya.de".to_string()));
+        let (_, domain) = sanitizer.sanitize(Some("https://xn--mnchen-3ya.de/rathaus"));
+        assert_eq!(domain, Some("xn--mnchen-3ya.de".to_string()));
+        assert_eq!(display_domain(domain.as_deref().unwrap()), "münchen.de");
+    }
 }
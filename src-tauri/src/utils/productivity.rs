@@ -24,13 +24,19 @@ impl std::fmt::Display for ProductivityCategory {
     }
 }
 
+/// Default rules compiled into the binary
+const BUNDLED_RULES_JSON: &str = include_str!("default_app_rules.json");
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppRule {
-    pub matcher_type: String, // EXACT, GLOB, REGEX, DOMAIN
+    pub matcher_type: String, // EXACT, GLOB, REGEX, DOMAIN, PATH
     pub value: String,
     pub category: ProductivityCategory,
     pub priority: i32,
     pub is_active: bool,
+    /// No screenshots while a matching app is focused (e.g. HR or medical systems)
+    #[serde(default)]
+    pub suppress_screenshots: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -73,21 +79,44 @@ impl ProductivityClassifier {
     /// * `window_title` - Optional window title
     /// * `domain` - Optional domain extracted from browser URL (takes priority for DOMAIN rules)
     pub fn classify_app(&self, app_name: &str, app_id: &str, window_title: Option<&str>, domain: Option<&str>) -> ProductivityCategory {
-        for rule in &self.rules {
-            if !rule.is_active {
-                continue;
-            }
+        self.classify_app_with_path(app_name, app_id, window_title, domain, None)
+    }
 
-            if self.matches_rule(&rule, app_name, app_id, window_title, domain) {
-                return rule.category.clone();
-            }
-        }
+    /// Like `classify_app`, also evaluating PATH rules against the
+    /// (privacy-filtered) executable path
+    pub fn classify_app_with_path(&self, app_name: &str, app_id: &str, window_title: Option<&str>, domain: Option<&str>, exe_path: Option<&str>) -> ProductivityCategory {
+        self.matching_rule(app_name, app_id, window_title, domain, exe_path)
+            .map(|(_, rule)| rule.category.clone())
+            .unwrap_or_else(|| self.default_category.clone())
+    }
+
+    /// The rule (and its index in `get_rules`) that decides the category, if
+    /// any; None means the default category applies
+    pub fn matching_rule(&self, app_name: &str, app_id: &str, window_title: Option<&str>, domain: Option<&str>, exe_path: Option<&str>) -> Option<(usize, &AppRule)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.is_active)
+            .find(|(_, rule)| self.matches_rule(rule, app_name, app_id, window_title, domain, exe_path))
+    }
 
-        self.default_category.clone()
+    /// The highest priority active rule matching the app that suppresses
+    /// screenshots, whatever rule decides its category
+    pub fn screenshot_suppression_rule(&self, app_name: &str, app_id: &str, window_title: Option<&str>, domain: Option<&str>, exe_path: Option<&str>) -> Option<&AppRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.is_active && rule.suppress_screenshots)
+            .find(|rule| self.matches_rule(rule, app_name, app_id, window_title, domain, exe_path))
     }
 
-    fn matches_rule(&self, rule: &AppRule, app_name: &str, app_id: &str, window_title: Option<&str>, domain: Option<&str>) -> bool {
+    fn matches_rule(&self, rule: &AppRule, app_name: &str, app_id: &str, window_title: Option<&str>, domain: Option<&str>, exe_path: Option<&str>) -> bool {
         match rule.matcher_type.as_str() {
+            "PATH" => {
+                // Glob over the executable path; paths are case-insensitive on macOS and Windows
+                exe_path.map_or(false, |path| {
+                    self.matches_glob(&rule.value.to_lowercase(), &path.to_lowercase())
+                })
+            }
             "EXACT" => {
                 app_name.eq_ignore_ascii_case(&rule.value) || 
                 app_id.eq_ignore_ascii_case(&rule.value) ||
@@ -168,262 +197,16 @@ impl ProductivityClassifier {
         crate::utils::privacy::extract_domain_from_window_title(title)
     }
 
+    /// Rules shipped with the agent, used until the first rules sync
+    pub fn bundled_rules() -> Vec<AppRule> {
+        serde_json::from_str(BUNDLED_RULES_JSON).unwrap_or_else(|e| {
+            log::error!("Invalid bundled app rules: {}", e);
+            Vec::new()
+        })
+    }
+
     fn add_default_rules(&mut self) {
-        // Productive applications
-        let productive_rules = vec![
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "code.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "devenv.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "notepad++.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "sublime_text.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "atom.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "vscode.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "excel.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "winword.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "powerpnt.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "outlook.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "teams.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "slack.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "discord.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "zoom.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "skype.exe".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "GLOB".to_string(),
-                value: "*browser*.exe".to_string(),
-                category: ProductivityCategory::NEUTRAL,
-                priority: 50,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "github.com".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "stackoverflow.com".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "docs.microsoft.com".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "developer.mozilla.org".to_string(),
-                category: ProductivityCategory::PRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-        ];
-
-        // Unproductive applications
-        let unproductive_rules = vec![
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "steam.exe".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "epicgameslauncher.exe".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "battle.net.exe".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "origin.exe".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "uplay.exe".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "netflix.exe".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "EXACT".to_string(),
-                value: "spotify.exe".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 100,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "youtube.com".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "facebook.com".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "twitter.com".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "instagram.com".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "tiktok.com".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "reddit.com".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "netflix.com".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-            AppRule {
-                matcher_type: "DOMAIN".to_string(),
-                value: "twitch.tv".to_string(),
-                category: ProductivityCategory::UNPRODUCTIVE,
-                priority: 90,
-                is_active: true,
-            },
-        ];
-
-        self.add_rules(productive_rules);
-        self.add_rules(unproductive_rules);
+        self.add_rules(Self::bundled_rules());
     }
 
     pub fn get_rules(&self) -> &Vec<AppRule> {
@@ -461,6 +244,13 @@ mod tests {
         assert_eq!(category, ProductivityCategory::UNPRODUCTIVE);
     }
 
+    #[test]
+    fn test_bundled_rules_parse() {
+        let rules = ProductivityClassifier::bundled_rules();
+        assert!(!rules.is_empty());
+        assert!(rules.iter().any(|rule| rule.matcher_type == "DOMAIN"));
+    }
+
     #[test]
     fn test_glob_match() {
         let mut classifier = ProductivityClassifier::new();
@@ -470,6 +260,7 @@ mod tests {
             category: ProductivityCategory::NEUTRAL,
             priority: 50,
             is_active: true,
+            suppress_screenshots: false,
         });
         
         let category = classifier.classify_app("chrome.exe", "C:\\Program Files\\Google\\Chrome\\Application\\chrome.exe", None, None);
@@ -499,6 +290,7 @@ mod tests {
             category: ProductivityCategory::NEUTRAL,
             priority: 50,
             is_active: true,
+            suppress_screenshots: false,
         });
         
         // Add higher priority rule
@@ -508,9 +300,46 @@ mod tests {
             category: ProductivityCategory::PRODUCTIVE,
             priority: 100,
             is_active: true,
+            suppress_screenshots: false,
         });
         
         let category = classifier.classify_app("chrome.exe", "chrome.exe", None, None);
         assert_eq!(category, ProductivityCategory::PRODUCTIVE);
     }
+
+    #[test]
+    fn test_path_match() {
+        let mut classifier = ProductivityClassifier::new();
+        classifier.add_rule(AppRule {
+            matcher_type: "PATH".to_string(),
+            value: "~/Applications/*".to_string(),
+            category: ProductivityCategory::UNPRODUCTIVE,
+            priority: 50,
+            is_active: true,
+            suppress_screenshots: false,
+        });
+
+        let category = classifier.classify_app_with_path("Tool", "com.example.tool", None, None, Some("~/Applications/Tool.app/Contents/MacOS/Tool"));
+        assert_eq!(category, ProductivityCategory::UNPRODUCTIVE);
+        // Without a path PATH rules never match
+        assert_eq!(classifier.classify_app("Tool", "com.example.tool", None, None), ProductivityCategory::NEUTRAL);
+    }
+
+    #[test]
+    fn test_screenshot_suppression_rule() {
+        let mut classifier = ProductivityClassifier::with_default_rules();
+        classifier.add_rule(AppRule {
+            matcher_type: "DOMAIN".to_string(),
+            value: "hr.example.com".to_string(),
+            category: ProductivityCategory::PRODUCTIVE,
+            priority: 1,
+            is_active: true,
+            suppress_screenshots: true,
+        });
+
+        // Applies even though a higher priority rule decides the category
+        let rule = classifier.screenshot_suppression_rule("chrome.exe", "chrome.exe", None, Some("payroll.hr.example.com"), None);
+        assert_eq!(rule.map(|rule| rule.value.as_str()), Some("hr.example.com"));
+        assert!(classifier.screenshot_suppression_rule("chrome.exe", "chrome.exe", None, Some("github.com"), None).is_none());
+    }
 }
@@ -0,0 +1,208 @@
+//! Public Suffix List based domain handling
+//!
+//! Decides whether a host name ends in a real public suffix and computes its
+//! registrable domain (eTLD+1), e.g. `news.bbc.co.uk` -> `bbc.co.uk`. This
+//! replaces the hand-written TLD regex, which missed new TLDs and mis-handled
+//! ccSLDs such as `.co.uk`.
+//!
+//! A trimmed snapshot of the list is bundled. The full list is downloaded
+//! from publicsuffix.org, cached in the data directory and refreshed weekly;
+//! the cache file's modification time is the last refresh, so the schedule
+//! carries over restarts.
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use publicsuffix::{List, Psl};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+const BUNDLED_LIST: &str = include_str!("../../resources/public_suffix_list.dat");
+
+const LIST_URL: &str = "https://publicsuffix.org/list/public_suffix_list.dat";
+
+/// How often the cached list is refreshed
+const REFRESH_INTERVAL_SECS: u64 = 7 * 24 * 3600; // Weekly
+
+/// ccTLDs that are far more often a file extension in a window title
+/// (`main.rs`, `README.md`) than a domain
+const FILE_EXTENSION_TLDS: [&str; 4] = ["rs", "md", "py", "sh"];
+
+lazy_static! {
+    static ref PUBLIC_SUFFIX_LIST: RwLock<List> = RwLock::new(
+        BUNDLED_LIST.parse().expect("bundled public suffix list is valid")
+    );
+}
+
+/// Whether the host is an IP address or a local name that has no public suffix
+fn is_local_host(host: &str) -> bool {
+    host == "localhost" || host.parse::<IpAddr>().is_ok()
+}
+
+/// The list stores internationalized suffixes in Unicode, so punycode hosts
+/// (`xn--...`) are converted before the lookup
+fn lookup_form(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_lowercase();
+    if host.contains("xn--") {
+        let (unicode, result) = idna::domain_to_unicode(&host);
+        if result.is_ok() {
+            return unicode;
+        }
+    }
+    host
+}
+
+/// Whether the host ends in a known public suffix (new gTLDs, ccSLDs, ...)
+/// and has a registrable label in front of it
+pub fn has_known_suffix(host: &str) -> bool {
+    let host = lookup_form(host);
+    let list = PUBLIC_SUFFIX_LIST.read().unwrap();
+    list.domain(host.as_bytes())
+        .map(|domain| domain.suffix().is_known())
+        .unwrap_or(false)
+}
+
+/// Whether a host found without a scheme (in a title or a bare address) is
+/// a domain: it needs a known public suffix, and a single label in front of
+/// a file-extension ccTLD is taken as a file name
+pub fn looks_like_domain(host: &str) -> bool {
+    if !has_known_suffix(host) {
+        return false;
+    }
+    let host = lookup_form(host);
+    let labels: Vec<&str> = host.split('.').collect();
+    !(labels.len() == 2 && FILE_EXTENSION_TLDS.contains(&labels[1]))
+}
+
+/// Registrable domain (eTLD+1) of a host, e.g. `mail.google.com` -> `google.com`,
+/// `sub.example.co.uk` -> `example.co.uk`. Local hosts are returned unchanged.
+pub fn registrable_domain(host: &str) -> Option<String> {
+    let host = lookup_form(host);
+    if is_local_host(&host) {
+        return Some(host);
+    }
+
+    let list = PUBLIC_SUFFIX_LIST.read().unwrap();
+    let domain = list.domain(host.as_bytes())?;
+    if !domain.suffix().is_known() {
+        return None;
+    }
+    std::str::from_utf8(domain.as_bytes()).ok().map(str::to_string)
+}
+
+fn cache_path() -> Option<PathBuf> {
+    let mut path = crate::profile::data_dir()?;
+    path.push("public_suffix_list.dat");
+    Some(path)
+}
+
+fn load_list(data: &str) -> Result<()> {
+    let list: List = data
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid public suffix list: {:?}", e))?;
+    *PUBLIC_SUFFIX_LIST.write().unwrap() = list;
+    Ok(())
+}
+
+/// Download the full list, cache it and make it active
+pub async fn refresh_list() -> Result<()> {
+    let client = crate::api::client::shared_client();
+    let response = client.get(LIST_URL).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Public suffix list download failed: {}", response.status()));
+    }
+
+    let data = response.text().await?;
+    load_list(&data)?;
+
+    if let Some(path) = cache_path() {
+        if let Err(e) = std::fs::write(&path, &data) {
+            log::warn!("Failed to cache public suffix list: {}", e);
+        }
+    }
+
+    log::info!("Public suffix list refreshed ({} bytes)", data.len());
+    Ok(())
+}
+
+/// Time until the next refresh, given the age of the cached list
+fn refresh_delay(cache_age: Option<std::time::Duration>) -> std::time::Duration {
+    let interval = std::time::Duration::from_secs(REFRESH_INTERVAL_SECS);
+    cache_age.map_or(std::time::Duration::ZERO, |age| interval.saturating_sub(age))
+}
+
+/// Load the cached full list (if any) and keep it up to date
+pub async fn initialize() {
+    let cache_age = cache_path()
+        .and_then(|path| std::fs::metadata(path).ok())
+        .and_then(|metadata| metadata.modified().ok())
+        .map(|modified| modified.elapsed().unwrap_or_default());
+    let cached = cache_path().and_then(|path| std::fs::read_to_string(path).ok());
+    let loaded_cache = match cached.map(|data| load_list(&data)) {
+        Some(Ok(())) => {
+            log::info!("Loaded cached public suffix list");
+            true
+        }
+        Some(Err(e)) => {
+            log::warn!("Ignoring cached public suffix list: {}", e);
+            false
+        }
+        None => false,
+    };
+
+    // An unusable cache is refreshed right away
+    let first_refresh = refresh_delay(cache_age.filter(|_| loaded_cache));
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + first_refresh,
+            tokio::time::Duration::from_secs(REFRESH_INTERVAL_SECS),
+        );
+        loop {
+            interval.tick().await;
+            if let Err(e) = refresh_list().await {
+                log::warn!("Failed to refresh public suffix list, keeping current one: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain() {
+        assert_eq!(registrable_domain("mail.google.com"), Some("google.com".to_string()));
+        assert_eq!(registrable_domain("sub.domain.example.co.uk"), Some("example.co.uk".to_string()));
+        assert_eq!(registrable_domain("acme.github.io"), Some("acme.github.io".to_string()));
+        assert_eq!(registrable_domain("localhost"), Some("localhost".to_string()));
+        // A bare suffix has no registrable domain
+        assert_eq!(registrable_domain("co.uk"), None);
+    }
+
+    #[test]
+    fn test_known_suffix() {
+        assert!(has_known_suffix("watchseries.bar"));
+        assert!(has_known_suffix("bbc.co.uk"));
+        assert!(!has_known_suffix("script.js"));
+        assert!(!has_known_suffix("example.notarealtld"));
+    }
+
+    #[test]
+    fn test_file_names_are_not_domains() {
+        assert!(!looks_like_domain("main.rs"));
+        assert!(!looks_like_domain("README.md"));
+        assert!(looks_like_domain("www.b92.rs"));
+        assert!(looks_like_domain("bbc.co.uk"));
+        assert!(!looks_like_domain("script.js"));
+    }
+
+    #[test]
+    fn test_refresh_delay_follows_cache_age() {
+        let day = std::time::Duration::from_secs(24 * 3600);
+        assert_eq!(refresh_delay(None), std::time::Duration::ZERO);
+        assert_eq!(refresh_delay(Some(day)), 6 * day);
+        assert_eq!(refresh_delay(Some(30 * day)), std::time::Duration::ZERO);
+    }
+}
@@ -0,0 +1,167 @@
+//! Remote session and virtual machine detection
+//!
+//! Idle time and screenshots mean something different when the agent runs in
+//! an RDP session (the console may be locked while the user works remotely)
+//! or inside a VM (the "screen" is a window on another machine), so both are
+//! reported with device registration and every heartbeat.
+//!
+//! The VM check reads SMBIOS vendor/product strings once per process; the
+//! remote-session check is cheap and re-evaluated each time since RDP
+//! sessions connect and disconnect while the agent runs.
+
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// SMBIOS vendor/product substrings (lowercase) and the hypervisor they identify
+const HYPERVISOR_SIGNATURES: &[(&str, &str)] = &[
+    ("vmware", "VMware"),
+    ("virtualbox", "VirtualBox"),
+    ("innotek", "VirtualBox"),
+    ("parallels", "Parallels"),
+    ("qemu", "QEMU"),
+    ("kvm", "KVM"),
+    ("bochs", "Bochs"),
+    ("xen", "Xen"),
+    ("virtual machine", "Hyper-V"),
+    ("amazon ec2", "Amazon EC2"),
+    ("google compute engine", "Google Compute Engine"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionEnvironment {
+    /// Running in a remote desktop session rather than at the console
+    pub is_remote_session: bool,
+    pub is_virtual_machine: bool,
+    /// Hypervisor name when it could be identified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hypervisor: Option<String>,
+}
+
+static VM_DETECTION: OnceLock<(bool, Option<String>)> = OnceLock::new();
+
+/// Hypervisor named by SMBIOS vendor/product strings
+fn match_hypervisor(hints: &[String]) -> Option<&'static str> {
+    hints.iter().find_map(|hint| {
+        let hint = hint.to_lowercase();
+        HYPERVISOR_SIGNATURES
+            .iter()
+            .find(|(signature, _)| hint.contains(signature))
+            .map(|(_, name)| *name)
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn smbios_hints() -> Vec<String> {
+    ["sys_vendor", "product_name", "board_vendor", "bios_vendor"]
+        .iter()
+        .filter_map(|file| std::fs::read_to_string(format!("/sys/class/dmi/id/{}", file)).ok())
+        .map(|value| value.trim().to_string())
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn smbios_hints() -> Vec<String> {
+    // hw.model is e.g. "VMware20,1" or "Parallels-ARM" inside a VM
+    ["hw.model", "machdep.cpu.brand_string"]
+        .iter()
+        .filter_map(|key| std::process::Command::new("sysctl").args(["-n", key]).output().ok())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn smbios_hints() -> Vec<String> {
+    let Ok(output) = std::process::Command::new("reg")
+        .args(["query", r"HKLM\HARDWARE\DESCRIPTION\System\BIOS"])
+        .output()
+    else {
+        return Vec::new();
+    };
+    // "    SystemManufacturer    REG_SZ    VMware, Inc."
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains("SystemManufacturer") || line.contains("SystemProductName"))
+        .filter_map(|line| line.split("REG_SZ").nth(1))
+        .map(|value| value.trim().to_string())
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn smbios_hints() -> Vec<String> {
+    Vec::new()
+}
+
+/// Whether the hypervisor CPU flag is set, even if the vendor is unknown
+#[cfg(target_os = "macos")]
+fn hypervisor_flag() -> bool {
+    std::process::Command::new("sysctl")
+        .args(["-n", "kern.hv_vmm_present"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "1")
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+fn hypervisor_flag() -> bool {
+    std::fs::read_to_string("/proc/cpuinfo")
+        .map(|cpuinfo| cpuinfo.lines().any(|line| line.starts_with("flags") && line.contains(" hypervisor")))
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn hypervisor_flag() -> bool {
+    false
+}
+
+#[cfg(target_os = "windows")]
+fn is_remote_session() -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_REMOTESESSION};
+    unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+}
+
+#[cfg(target_os = "linux")]
+fn is_remote_session() -> bool {
+    std::env::var_os("XRDP_SESSION").is_some()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn is_remote_session() -> bool {
+    // macOS has no separate remote desktop sessions; Screen Sharing mirrors the console
+    false
+}
+
+/// Remote session and VM flags for this process
+pub fn detect() -> SessionEnvironment {
+    let (is_virtual_machine, hypervisor) = VM_DETECTION
+        .get_or_init(|| {
+            let hypervisor = match_hypervisor(&smbios_hints()).map(str::to_string);
+            let is_vm = hypervisor.is_some() || hypervisor_flag();
+            if is_vm {
+                log::info!("Running in a virtual machine ({})", hypervisor.as_deref().unwrap_or("unknown hypervisor"));
+            }
+            (is_vm, hypervisor)
+        })
+        .clone();
+
+    SessionEnvironment {
+        is_remote_session: is_remote_session(),
+        is_virtual_machine,
+        hypervisor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_hypervisor() {
+        let hints = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        assert_eq!(match_hypervisor(&hints(&["VMware, Inc.", "VMware7,1"])), Some("VMware"));
+        assert_eq!(match_hypervisor(&hints(&["innotek GmbH"])), Some("VirtualBox"));
+        assert_eq!(match_hypervisor(&hints(&["Microsoft Corporation", "Virtual Machine"])), Some("Hyper-V"));
+        assert_eq!(match_hypervisor(&hints(&["Dell Inc.", "Latitude 7440"])), None);
+        assert_eq!(match_hypervisor(&hints(&["Microsoft Corporation", "Surface Laptop 5"])), None);
+    }
+}
@@ -0,0 +1,53 @@
+//! Timestamp formatting and day boundaries
+//!
+//! The backend validates ingest timestamps as ISO 8601 with millisecond
+//! precision and a `Z` suffix (Zod `datetime()`), which `to_rfc3339()` doesn't
+//! produce. Everything sent to it goes through `format_timestamp`.
+
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+
+/// `2024-03-04T09:15:00.000Z`
+pub fn format_timestamp(at: DateTime<Utc>) -> String {
+    at.format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// Current time on the process clock, formatted for an event
+pub fn event_timestamp() -> String {
+    format_timestamp(super::clock::now())
+}
+
+/// Start of a local calendar day, in UTC
+pub fn local_midnight(day: NaiveDate) -> DateTime<Utc> {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        // Midnight skipped by a DST change
+        .unwrap_or_else(|| DateTime::from_naive_utc_and_offset(midnight, Utc))
+}
+
+/// `[start, end)` of a local calendar day, in UTC. Not always 24 hours
+/// apart across DST changes.
+pub fn day_bounds(day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    (local_midnight(day), local_midnight(day + Duration::days(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_and_bounds() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 4, 9, 15, 0).unwrap() + Duration::milliseconds(7);
+        assert_eq!(format_timestamp(at), "2024-03-04T09:15:00.007Z");
+
+        let day = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let (start, end) = day_bounds(day);
+        assert_eq!(start.with_timezone(&Local).date_naive(), day);
+        assert_eq!(end.with_timezone(&Local).date_naive(), day + Duration::days(1));
+        assert!(end - start >= Duration::hours(23) && end - start <= Duration::hours(25));
+    }
+}
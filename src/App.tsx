@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import LoginScreen from "./components/LoginScreen";
 import ConsentWizard from "./components/ConsentWizard";
 import MainView from "./components/MainView";
@@ -7,6 +8,7 @@ import UpdateDialog from "./components/UpdateDialog";
 import { SSEConnectionStatus } from "./components/SSEConnectionStatus";
 import { clearCachedToken } from "./utils/license-listener";
 import PermissionsHelper from "./components/PermissionsHelper";
+import ScreenshotReview from "./components/ScreenshotReview";
 import "./App.css";
 
 interface AuthStatus {
@@ -24,6 +26,11 @@ interface ConsentStatus {
 interface PermissionsStatus {
   screen_recording: boolean;
   accessibility: boolean;
+  /** Automation permission per browser bundle id (macOS) */
+  automation?: Record<string, "unknown" | "granted" | "denied">;
+  /** Some browsers refused Automation, so their URLs are not captured */
+  url_capture_limited?: boolean;
+  window_titles_available?: boolean;
 }
 
 function App() {
@@ -33,11 +40,40 @@ function App() {
   const [_licenseValid, setLicenseValid] = useState<boolean | null>(null);
   const [permissionsStatus, setPermissionsStatus] = useState<PermissionsStatus | null>(null);
   const [loading, setLoading] = useState(true);
+  // Set when the backend deactivated this device (credentials are already cleared)
+  const [deactivationMessage, setDeactivationMessage] = useState<string | null>(null);
+  // Set when the device record was lost and couldn't be re-registered; local data is kept
+  const [reauthRequired, setReauthRequired] = useState(false);
 
   useEffect(() => {
     checkStatus();
   }, []);
 
+  useEffect(() => {
+    const unlisten = listen<{ message?: string }>("device-deactivated", (event) => {
+      clearCachedToken();
+      setDeactivationMessage(event.payload.message || "This device was deactivated by your administrator.");
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen("reauth-required", () => {
+      clearCachedToken();
+      setReauthRequired(true);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const handleDeactivationAcknowledged = async () => {
+    setDeactivationMessage(null);
+    await handleLogout();
+  };
+
   const checkStatus = async () => {
     try {
       // Add timeout to prevent hanging
@@ -71,6 +107,7 @@ function App() {
 
   const handleLogin = async () => {
     // Force refresh auth status after login
+    setReauthRequired(false);
     setLoading(true);
     await checkStatus();
   };
@@ -113,6 +150,42 @@ function App() {
     );
   }
 
+  // Device deactivated: explain instead of silently failing with a dead token
+  if (deactivationMessage) {
+    return (
+      <>
+        {updateDialog}
+        <div className="permissions-container">
+          <div className="permissions-helper">
+            <div className="permissions-header">
+              <h1>Device Deactivated</h1>
+              <p>{deactivationMessage}</p>
+              <p>Time tracking has stopped and you have been signed out. Contact your administrator if this is unexpected.</p>
+            </div>
+            <button className="request-permissions-button primary" onClick={handleDeactivationAcknowledged}>
+              Back to Sign In
+            </button>
+          </div>
+        </div>
+      </>
+    );
+  }
+
+  // Device record lost: sign in again without clearing the session
+  if (reauthRequired) {
+    return (
+      <>
+        {updateDialog}
+        {connectionStatus}
+        <LoginScreen
+          onLogin={handleLogin}
+          initialEmail={authStatus?.email}
+          notice="This device needs to be reconnected. Sign in again to keep syncing; your tracked activity is saved."
+        />
+      </>
+    );
+  }
+
   // Show login if not authenticated
   if (!authStatus?.is_authenticated) {
     return (
@@ -152,6 +225,7 @@ function App() {
       {updateDialog}
       {connectionStatus}
       <MainView authStatus={authStatus} onLogout={handleLogout} />
+      <ScreenshotReview />
     </>
   );
 }
@@ -1,6 +1,7 @@
 import { useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import VersionBadge from "./VersionBadge";
+import { getErrorMessage } from "../utils/agent-error";
 
 interface ConsentWizardProps {
   onConsent: () => void;
@@ -89,7 +90,7 @@ function ConsentWizard({ onConsent }: ConsentWizardProps) {
       await invoke("accept_consent", { version: CONSENT_VERSION });
       onConsent();
     } catch (error) {
-      setError(error as string);
+      setError(getErrorMessage(error));
     } finally {
       setLoading(false);
     }
@@ -1,24 +1,57 @@
 import { useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import VersionBadge from './VersionBadge'
+import { getErrorMessage } from '../utils/agent-error'
 
 interface LoginScreenProps {
   onLogin: () => void
+  /** Shown above the form, e.g. why the user has to sign in again */
+  notice?: string
+  initialEmail?: string
 }
 
 interface LoginRequest {
   email: string
   password: string
   server_url: string
+  challenge_id?: string
+  two_factor_code?: string
+  remember_device?: boolean
 }
 
-function LoginScreen({ onLogin }: LoginScreenProps) {
-  const [email, setEmail] = useState('')
+interface TwoFactorChallenge {
+  challenge_id: string
+  method: string
+  destination: string | null
+  attempts_remaining: number | null
+}
+
+interface PairingInfo {
+  code: string
+  approval_url: string
+  qr_svg: string
+  expires_at: string
+}
+
+interface AuthStatus {
+  is_authenticated: boolean
+  two_factor: TwoFactorChallenge | null
+}
+
+function LoginScreen({ onLogin, notice, initialEmail }: LoginScreenProps) {
+  const [email, setEmail] = useState(initialEmail ?? '')
   const [password, setPassword] = useState('')
   // Environment-aware server URL - uses localhost in dev, production URL in builds
   const serverUrl = import.meta.env.VITE_SERVER_URL || 'http://localhost:3000'
   const [loading, setLoading] = useState(false)
   const [error, setError] = useState('')
+  // Set when the account needs a second factor after the password step
+  const [challenge, setChallenge] = useState<TwoFactorChallenge | null>(null)
+  const [code, setCode] = useState('')
+  const [rememberDevice, setRememberDevice] = useState(false)
+  const [browserPending, setBrowserPending] = useState(false)
+  // Shared-machine pairing: code shown until an admin approves the device
+  const [pairing, setPairing] = useState<PairingInfo | null>(null)
 
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault()
@@ -30,22 +63,83 @@ function LoginScreen({ onLogin }: LoginScreenProps) {
         email,
         password,
         server_url: serverUrl,
+        ...(challenge && {
+          challenge_id: challenge.challenge_id,
+          two_factor_code: code,
+          remember_device: rememberDevice,
+        }),
       }
 
-      await invoke('login', { request: loginRequest })
+      const status = await invoke<AuthStatus>('login', { request: loginRequest })
+      if (status.two_factor) {
+        setChallenge(status.two_factor)
+        setCode('')
+        return
+      }
 
       // DO NOT start background services after login
       // Services should only start when user clocks in
 
       onLogin()
     } catch (error) {
-      const errorMessage = error as string
+      const errorMessage = getErrorMessage(error)
       setError(errorMessage)
     } finally {
       setLoading(false)
     }
   }
 
+  const handleBrowserLogin = async () => {
+    setLoading(true)
+    setBrowserPending(true)
+    setError('')
+    try {
+      await invoke<AuthStatus>('start_browser_login', { serverUrl })
+      onLogin()
+    } catch (error) {
+      setError(getErrorMessage(error))
+    } finally {
+      setLoading(false)
+      setBrowserPending(false)
+    }
+  }
+
+  const handleStartPairing = async () => {
+    setError('')
+    try {
+      const info = await invoke<PairingInfo>('start_pairing', { serverUrl })
+      setPairing(info)
+      await invoke<AuthStatus>('complete_pairing')
+      onLogin()
+    } catch (error) {
+      setError(getErrorMessage(error))
+    } finally {
+      setPairing(null)
+    }
+  }
+
+  const handleCancelPairing = async () => {
+    await invoke('cancel_pairing')
+    setPairing(null)
+    setError('')
+  }
+
+  const handleResend = async () => {
+    if (!challenge) return
+    setError('')
+    try {
+      await invoke('resend_two_factor_code', { serverUrl, challengeId: challenge.challenge_id })
+    } catch (error) {
+      setError(getErrorMessage(error))
+    }
+  }
+
+  const handleBack = () => {
+    setChallenge(null)
+    setCode('')
+    setError('')
+  }
+
   return (
     <div className='login-container'>
       <VersionBadge position='bottom-right' />
@@ -55,25 +149,98 @@ function LoginScreen({ onLogin }: LoginScreenProps) {
           <p>Welcome back! Please sign in to continue</p>
         </div>
 
+        {notice && !error && <div className='error-message'>{notice}</div>}
+
         {/* Error Display */}
         {error && <div className='error-message'>{error}</div>}
 
         {/* Login Form */}
-        <form onSubmit={handleSubmit}>
-          <div className='form-group'>
-            <label htmlFor='email'>Email</label>
-            <input id='email' type='email' value={email} onChange={(e) => setEmail(e.target.value)} placeholder='your@email.com' required autoFocus />
+        {pairing ? (
+          <div className='pairing-panel'>
+            <p className='form-hint'>Ask your administrator to approve this device in the TrackEx dashboard.</p>
+            <div className='pairing-qr' dangerouslySetInnerHTML={{ __html: pairing.qr_svg }} />
+            <div className='pairing-code'>{pairing.code}</div>
+            <p className='form-hint'>Expires {new Date(pairing.expires_at).toLocaleTimeString()}</p>
+            <button type='button' className='link-button' onClick={handleCancelPairing}>
+              Cancel
+            </button>
           </div>
+        ) : challenge ? (
+          <form onSubmit={handleSubmit}>
+            <div className='form-group'>
+              <label htmlFor='code'>Verification code</label>
+              <p className='form-hint'>
+                {challenge.method === 'email'
+                  ? `Enter the code we sent to ${challenge.destination ?? 'your email'}`
+                  : 'Enter the code from your authenticator app'}
+              </p>
+              <input
+                id='code'
+                type='text'
+                inputMode='numeric'
+                autoComplete='one-time-code'
+                value={code}
+                onChange={(e) => setCode(e.target.value)}
+                placeholder='123456'
+                required
+                autoFocus
+              />
+            </div>
 
-          <div className='form-group'>
-            <label htmlFor='password'>Password</label>
-            <input id='password' type='password' value={password} onChange={(e) => setPassword(e.target.value)} placeholder='••••••••' required />
-          </div>
+            <div className='form-group checkbox-group'>
+              <label htmlFor='remember-device'>
+                <input id='remember-device' type='checkbox' checked={rememberDevice} onChange={(e) => setRememberDevice(e.target.checked)} />
+                Don't ask again on this device
+              </label>
+            </div>
+
+            <button type='submit' className='login-button' disabled={loading}>
+              {loading ? 'Verifying...' : 'Verify'}
+            </button>
+
+            <div className='login-secondary-actions'>
+              {challenge.method === 'email' && (
+                <button type='button' className='link-button' onClick={handleResend} disabled={loading}>
+                  Resend code
+                </button>
+              )}
+              <button type='button' className='link-button' onClick={handleBack} disabled={loading}>
+                Back
+              </button>
+            </div>
+          </form>
+        ) : (
+          <form onSubmit={handleSubmit}>
+            <div className='form-group'>
+              <label htmlFor='email'>Email</label>
+              <input id='email' type='email' value={email} onChange={(e) => setEmail(e.target.value)} placeholder='your@email.com' required autoFocus />
+            </div>
+
+            <div className='form-group'>
+              <label htmlFor='password'>Password</label>
+              <input id='password' type='password' value={password} onChange={(e) => setPassword(e.target.value)} placeholder='••••••••' required />
+            </div>
+
+            <button type='submit' className='login-button' disabled={loading}>
+              {loading ? 'Signing in...' : 'Sign In'}
+            </button>
 
-          <button type='submit' className='login-button' disabled={loading}>
-            {loading ? 'Signing in...' : 'Sign In'}
-          </button>
-        </form>
+            <div className='login-secondary-actions'>
+              {browserPending ? (
+                <button type='button' className='link-button' onClick={() => invoke('cancel_browser_login')}>
+                  Cancel browser sign-in
+                </button>
+              ) : (
+                <button type='button' className='link-button' onClick={handleBrowserLogin} disabled={loading}>
+                  Sign in with your browser
+                </button>
+              )}
+              <button type='button' className='link-button' onClick={handleStartPairing} disabled={loading}>
+                Pair a shared device
+              </button>
+            </div>
+          </form>
+        )}
 
         <div className='login-footer'>
           <p>Secure connection to TrackEx server</p>
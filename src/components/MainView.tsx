@@ -1,4 +1,4 @@
-import { useState, useEffect, useCallback } from "react";
+import { useState, useEffect, useCallback, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { confirm } from "@tauri-apps/plugin-dialog";
 import VersionBadge from "./VersionBadge";
@@ -8,6 +8,8 @@ import {
   addLicenseListener,
   LicenseNotification 
 } from "../utils/license-listener";
+import { getErrorMessage } from "../utils/agent-error";
+import { listenToTrackingEvents, SessionDivergedEvent } from "../utils/tracking-events";
 
 interface AuthStatus {
     is_authenticated: boolean;
@@ -20,7 +22,11 @@ interface WorkSessionInfo {
     started_at?: string;
     current_app?: string;
     idle_time_seconds: number;
+    /** Length of the current idle stretch, 0 while active */
+    current_idle_seconds?: number;
     is_paused: boolean;
+    /** Paused time of the session, not counted as worked */
+    paused_seconds?: number;
 }
 
 interface TrackingStatus {
@@ -28,6 +34,8 @@ interface TrackingStatus {
     is_paused: boolean;
     current_app?: string;
     idle_time_seconds: number;
+    /** Length of the current idle stretch, 0 while active */
+    current_idle_seconds?: number;
 }
 
 interface AppInfo {
@@ -38,6 +46,19 @@ interface AppInfo {
     domain?: string;
 }
 
+const SCREEN_RECORDING_REVOKED = "Screen Recording permission was revoked. Screenshots are paused until you re-enable it in System Settings > Privacy & Security.";
+const STORAGE_CRITICAL = "Your disk is almost full. Screenshots are paused until space is freed or the agent can sync.";
+const STORAGE_WARNING = "Disk space is running low. Connect to the internet so the agent can sync its local queue.";
+
+/** Remembers per account that the location consent question was answered */
+const LOCATION_PROMPT_KEY = "trackex.locationConsentAsked";
+
+interface LocationConsent {
+    /** The organization enabled location on clock events */
+    requested: boolean;
+    granted: boolean;
+}
+
 interface MainViewProps {
     authStatus: AuthStatus;
     onLogout: () => Promise<void>;
@@ -51,6 +72,15 @@ interface RecentSession {
     date: string;
 }
 
+interface RecentSessionsPage {
+    sessions: RecentSession[];
+    page: number;
+    page_size: number;
+    total: number;
+    has_more: boolean;
+    source: "backend" | "cache";
+}
+
 // Removed unused SessionHistory interface
 
 interface LicenseCheckResult {
@@ -189,7 +219,7 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
             }
             // If valid, licenseError will remain null and UI will show normally
         } catch (error) {
-            const errorMessage = error as string;
+            const errorMessage = getErrorMessage(error);
             const licenseErr = parseLicenseError(errorMessage);
             if (licenseErr) {
                 // This is a license error - show the license overlay
@@ -221,6 +251,36 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
         }
     }, [authStatus?.is_authenticated, handleLicenseUpdate]);
 
+    // Ask once for the location consent the organization requested
+    useEffect(() => {
+        if (!authStatus?.is_authenticated) {
+            return;
+        }
+        const askForLocationConsent = async () => {
+            try {
+                const consent = await invoke<LocationConsent>("get_location_consent");
+                const askedKey = `${LOCATION_PROMPT_KEY}:${authStatus.email ?? ""}`;
+                if (!consent.requested || consent.granted || localStorage.getItem(askedKey)) {
+                    return;
+                }
+                const granted = await confirm(
+                    "Your organization would like clock-in and clock-out to show whether you worked from the office or remotely. Only your approximate city is derived from your network address; GPS is never used.",
+                    {
+                        title: "Work Location",
+                        kind: "info",
+                        okLabel: "Allow",
+                        cancelLabel: "Don't Allow"
+                    }
+                );
+                await invoke("set_location_consent", { granted });
+                localStorage.setItem(askedKey, granted ? "granted" : "declined");
+            } catch (error) {
+                console.error("Failed to ask for location consent:", error);
+            }
+        };
+        askForLocationConsent();
+    }, [authStatus?.is_authenticated, authStatus?.email]);
+
     // Check license status on component mount
     useEffect(() => {
         const checkLicense = async () => {
@@ -238,7 +298,7 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
             } catch (error) {
                 console.error("Failed to check license status:", error);
                 // If license check fails, parse and set the error
-                const errorMessage = String(error);
+                const errorMessage = getErrorMessage(error);
                 const licenseErr = parseLicenseError(errorMessage);
                 if (licenseErr) {
                     setLicenseError(licenseErr);
@@ -264,33 +324,64 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
         // Note: do not schedule heartbeats or DB clearing from the UI.
         // Backend services handle heartbeats/app focus/job polling when clocked in.
 
-        // Update current app every 3 seconds for live tracking when clocked in
-        const appUpdateInterval = setInterval(async () => {
-            if (authStatus.is_authenticated && workSession?.is_active) {
-                try {
-                    const app = await invoke<AppInfo | null>("get_current_app");
-                    setCurrentApp(app);
-                } catch (error) {
-                    console.error('Failed to get current app:', error);
-                }
-            }
-        }, 3000);
-
-        // Update full status every 10 seconds
-        const statusUpdateInterval = setInterval(() => {
-            if (authStatus.is_authenticated) {
+        // Session, app and idle changes are pushed by the backend as events
+        let unlistenTracking: (() => void) | null = null;
+        let disposed = false;
+        listenToTrackingEvents({
+            onSessionStarted: () => fetchStatus(),
+            onSessionEnded: () => fetchStatus(),
+            onSessionDiverged: (event) => {
                 fetchStatus();
+                offerBackendSession(event);
+            },
+            onAppChanged: (app) => {
+                setCurrentApp({
+                    name: app.name,
+                    app_id: app.app_id,
+                    window_title: app.window_title,
+                    url: app.url,
+                    domain: app.domain,
+                });
+            },
+            onIdleChanged: (idle) => {
+                // idle_time_seconds stays the session total; this event only
+                // describes the stretch that just started or ended
+                const current_idle_seconds = idle.is_idle ? idle.idle_time_seconds : 0;
+                setTrackingStatus((prev) => prev ? { ...prev, current_idle_seconds } : prev);
+                setWorkSession((prev) => prev ? { ...prev, current_idle_seconds } : prev);
+            },
+            onPermissionLost: (event) => {
+                if (event.permission === "screen_recording") {
+                    setError(SCREEN_RECORDING_REVOKED);
+                }
+            },
+            // Only clear the messages these events set, not unrelated errors
+            onPermissionRestored: () => setError((prev) => prev === SCREEN_RECORDING_REVOKED ? "" : prev),
+            onStoragePressure: (event) => {
+                if (event.pressure === "critical") {
+                    setError(STORAGE_CRITICAL);
+                } else if (event.pressure === "warning") {
+                    setError(STORAGE_WARNING);
+                } else {
+                    setError((prev) => prev === STORAGE_CRITICAL || prev === STORAGE_WARNING ? "" : prev);
+                }
+            },
+        }).then((unlisten) => {
+            if (disposed) {
+                unlisten();
+            } else {
+                unlistenTracking = unlisten;
             }
-        }, 10000);
+        });
 
         // Note: Heartbeat, app focus, job polling, and idle detection are now handled by backend services
         // They automatically start when user clocks in and stop when user clocks out
         // No need for frontend intervals - backend services handle this efficiently
 
         return () => {
+            disposed = true;
             clearInterval(timeInterval);
-            clearInterval(appUpdateInterval);
-            clearInterval(statusUpdateInterval);
+            unlistenTracking?.();
         };
     }, [authStatus.is_authenticated, workSession?.is_active]);
 
@@ -300,8 +391,7 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
                 invoke<WorkSessionInfo>("get_work_session"),
                 invoke<TrackingStatus>("get_tracking_status"),
                 invoke<AppInfo | null>("get_current_app"),
-                // eslint-disable-next-line @typescript-eslint/no-explicit-any
-                invoke<any>("get_recent_sessions")
+                invoke<RecentSessionsPage>("get_recent_sessions", { page: 1, pageSize: 5 })
             ]);
             setWorkSession(session);
             setTrackingStatus(tracking);
@@ -324,7 +414,7 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
             await invoke("clock_in");
             await fetchStatus();
         } catch (error) {
-            setError(error as string);
+            setError(getErrorMessage(error));
         } finally {
             setLoading(false);
         }
@@ -338,12 +428,46 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
             await invoke("clock_out");
             await fetchStatus();
         } catch (error) {
-            setError(error as string);
+            setError(getErrorMessage(error));
         } finally {
             setLoading(false);
         }
     };
 
+    // Backend session start the user already declined to continue here
+    const declinedBackendSession = useRef<string | null>(null);
+
+    /** Offer to continue a session that is active on the server but not here */
+    const offerBackendSession = async (event: SessionDivergedEvent) => {
+        if (!event.backend_active || event.local_active) {
+            return;
+        }
+        const startedAt = event.backend_started_at ?? "";
+        if (declinedBackendSession.current === startedAt) {
+            return;
+        }
+        const since = startedAt ? ` since ${new Date(startedAt).toLocaleTimeString()}` : "";
+        const confirmed = await confirm(
+            `You are clocked in on the server${since}, but not on this device. Continue that session here?`,
+            {
+                title: "Continue Session",
+                kind: "info",
+                okLabel: "Continue Session",
+                cancelLabel: "Not Now"
+            }
+        );
+        if (!confirmed) {
+            declinedBackendSession.current = startedAt;
+            return;
+        }
+        try {
+            await invoke("adopt_backend_session");
+            await fetchStatus();
+        } catch (error) {
+            setError(getErrorMessage(error));
+        }
+    };
+
     const handleLogout = async () => {
         // If user is clocked in, show confirmation dialog
         if (workSession?.is_active) {
@@ -367,16 +491,16 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
             // Use the onLogout prop to properly reset the app state
             await onLogout();
         } catch (error) {
-            setError(error as string);
+            setError(getErrorMessage(error));
         } finally {
             setLoading(false);
         }
     };
 
-    const formatTimer = (start?: string) => {
+    const formatTimer = (start?: string, pausedSeconds = 0) => {
         if (!start) return "00:00:00";
         const startTime = new Date(start);
-        const diff = currentTime.getTime() - startTime.getTime();
+        const diff = Math.max(0, currentTime.getTime() - startTime.getTime() - pausedSeconds * 1000);
         const hours = Math.floor(diff / (1000 * 60 * 60));
         const minutes = Math.floor((diff % (1000 * 60 * 60)) / (1000 * 60));
         const seconds = Math.floor((diff % (1000 * 60)) / 1000);
@@ -469,7 +593,7 @@ function MainView({ authStatus, onLogout }: Readonly<MainViewProps>) {
                 {workSession?.is_active ? (
                     <>
                         <div className="timer-display">
-                            {formatTimer(workSession.started_at)} <span className="timer-unit">h</span>
+                            {formatTimer(workSession.started_at, workSession.paused_seconds)} <span className="timer-unit">h</span>
                         </div>
                         <div className="timer-subtitle">
                             Started at {formatStartTime(workSession.started_at)}
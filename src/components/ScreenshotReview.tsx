@@ -0,0 +1,129 @@
+import { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { getErrorMessage } from "../utils/agent-error";
+import "./ScreenshotReview.css";
+
+/** A screenshot held for the employee's review (see screenshots/review.rs) */
+interface ScreenshotAwaitingReview {
+  id: number;
+  taken_at: string;
+  /** Uploaded as captured if not reviewed by then */
+  review_deadline: string;
+}
+
+type ReviewDecision = "accept" | "blur" | "reject";
+
+/**
+ * Screenshot Review
+ *
+ * Shown when the org policy holds screenshots for review: previews the
+ * oldest pending screenshot and lets the employee upload it as is, upload
+ * a blurred copy or withhold it before its deadline.
+ */
+function ScreenshotReview() {
+  const [pending, setPending] = useState<ScreenshotAwaitingReview[]>([]);
+  const [preview, setPreview] = useState<string | null>(null);
+  const [busy, setBusy] = useState(false);
+  const [error, setError] = useState("");
+  const [now, setNow] = useState(Date.now());
+
+  const current = pending[0];
+
+  const refresh = useCallback(async () => {
+    try {
+      setPending(await invoke<ScreenshotAwaitingReview[]>("get_screenshots_for_review"));
+    } catch (err) {
+      console.error("Failed to load screenshots awaiting review:", err);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+    const unlisten = listen("screenshot-review-requested", () => {
+      refresh();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [refresh]);
+
+  // Countdown, and drop screenshots whose deadline passed (they upload as captured)
+  useEffect(() => {
+    if (!current) return;
+    const timer = setInterval(() => {
+      setNow(Date.now());
+      if (new Date(current.review_deadline).getTime() <= Date.now()) {
+        refresh();
+      }
+    }, 1000);
+    return () => clearInterval(timer);
+  }, [current, refresh]);
+
+  useEffect(() => {
+    setPreview(null);
+    setError("");
+    if (!current) return;
+    let cancelled = false;
+    invoke<string>("get_screenshot_preview", { id: current.id })
+      .then((dataUrl) => {
+        if (!cancelled) setPreview(dataUrl);
+      })
+      .catch((err) => {
+        if (!cancelled) setError(getErrorMessage(err));
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [current?.id]);
+
+  const decide = async (decision: ReviewDecision) => {
+    if (!current) return;
+    setBusy(true);
+    setError("");
+    try {
+      await invoke("review_screenshot", { id: current.id, decision });
+    } catch (err) {
+      setError(getErrorMessage(err));
+    } finally {
+      setBusy(false);
+      refresh();
+    }
+  };
+
+  if (!current) {
+    return null;
+  }
+
+  const remainingSeconds = Math.max(0, Math.floor((new Date(current.review_deadline).getTime() - now) / 1000));
+  const remaining = `${Math.floor(remainingSeconds / 60)}:${String(remainingSeconds % 60).padStart(2, "0")}`;
+
+  return (
+    <div className="screenshot-review-overlay">
+      <div className="screenshot-review">
+        <h3 className="screenshot-review-title">Review screenshot</h3>
+        <p className="screenshot-review-message">
+          Taken at {new Date(current.taken_at).toLocaleTimeString()}. It will be uploaded as is in {remaining}
+          {pending.length > 1 ? ` (${pending.length - 1} more waiting)` : ""}.
+        </p>
+        <div className="screenshot-review-preview">
+          {preview ? <img src={preview} alt="Screenshot awaiting review" /> : <span className="screenshot-review-loading">Loading preview...</span>}
+        </div>
+        {error && <p className="screenshot-review-error">{error}</p>}
+        <div className="screenshot-review-actions">
+          <button className="update-btn secondary" disabled={busy} onClick={() => decide("reject")}>
+            Don't upload
+          </button>
+          <button className="update-btn secondary" disabled={busy} onClick={() => decide("blur")}>
+            Upload blurred
+          </button>
+          <button className="update-btn primary" disabled={busy} onClick={() => decide("accept")}>
+            Upload
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
+
+export default ScreenshotReview;
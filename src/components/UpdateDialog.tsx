@@ -1,6 +1,7 @@
 import { useState, useEffect, useCallback, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
+import { confirm } from "@tauri-apps/plugin-dialog";
 import { 
   connectToUpdateStream, 
   disconnectFromUpdateStream, 
@@ -26,6 +27,28 @@ interface UpdateProgress {
   percentage: number;
 }
 
+interface RollbackStatus {
+  available: boolean;
+  previous_version?: string;
+  target_version?: string;
+  failed_launches: number;
+}
+
+/** Whether an automatic install may run now (see update_schedule.rs) */
+interface InstallSchedule {
+  allowed: boolean;
+  reason?: string;
+  next_window?: string;
+  queued: boolean;
+}
+
+/** Restricted mode while a mandatory update is overdue (see update_lockout.rs) */
+interface LockoutStatus {
+  locked: boolean;
+  version?: string;
+  deadline?: string;
+}
+
 interface UpdateDialogProps {
   /** Whether to show the dialog automatically when an update is found */
   autoCheck?: boolean;
@@ -33,13 +56,14 @@ interface UpdateDialogProps {
   onDismiss?: () => void;
 }
 
-type UpdateState = "idle" | "checking" | "available" | "downloading" | "error" | "mandatory-blocked";
+type UpdateState = "idle" | "checking" | "available" | "downloading" | "scheduled" | "error" | "mandatory-blocked";
 
 function UpdateDialog({ autoCheck = true, onDismiss }: Readonly<UpdateDialogProps>) {
   const [state, setState] = useState<UpdateState>("idle");
   const [updateInfo, setUpdateInfo] = useState<UpdateInfo | null>(null);
   const [progress, setProgress] = useState<UpdateProgress | null>(null);
   const [error, setError] = useState<string>("");
+  const [schedule, setSchedule] = useState<InstallSchedule | null>(null);
   const [dismissed, setDismissed] = useState(false);
 
   // Check if current update is mandatory
@@ -58,17 +82,26 @@ function UpdateDialog({ autoCheck = true, onDismiss }: Readonly<UpdateDialogProp
         setError(info.diagnostic_info || info.error);
         setState("error");
       } else if (info.available) {
-        // If mandatory, auto-start download immediately
+        // If mandatory, install immediately unless the policy defers it
+        // (e.g. the employee is clocked in during work hours)
         if (info.mandatory) {
-          console.log("[UpdateDialog] Mandatory update detected, auto-starting download...");
-          setState("downloading");
-          setProgress({ downloaded: 0, total: 0, percentage: 0 });
+          console.log("[UpdateDialog] Mandatory update detected, requesting install...");
           try {
-            await invoke("install_update");
+            const current = await invoke<InstallSchedule>("get_install_schedule");
+            if (current.allowed) {
+              setState("downloading");
+              setProgress({ downloaded: 0, total: 0, percentage: 0 });
+              await invoke("install_update");
+            } else {
+              // Queued: the backend installs it once the install window opens
+              setSchedule(await invoke<InstallSchedule>("queue_update_install"));
+              setState("scheduled");
+            }
           } catch (err) {
             console.error("Failed to auto-install mandatory update:", err);
             setError(err as string);
-            setState("error");
+            const lockout = await invoke<LockoutStatus>("get_update_lockout_status").catch(() => null);
+            setState(lockout?.locked ? "mandatory-blocked" : "error");
           }
         } else {
           setState("available");
@@ -101,6 +134,44 @@ function UpdateDialog({ autoCheck = true, onDismiss }: Readonly<UpdateDialogProp
     }
   }, [autoCheck, checkForUpdates]);
 
+  // Background checks (update_check.rs) and the tray's update item
+  const stateRef = useRef(state);
+  useEffect(() => {
+    stateRef.current = state;
+  }, [state]);
+
+  useEffect(() => {
+    let unlisteners: UnlistenFn[] = [];
+
+    const setupUpdateCheckListeners = async () => {
+      unlisteners = await Promise.all([
+        listen("update-check-requested", () => {
+          setDismissed(false);
+          checkForUpdatesRef.current();
+        }),
+        // Only pick up updates found while the dialog was idle; checks started
+        // here emit this event too
+        listen("update-available", () => {
+          if (stateRef.current === "idle") {
+            checkForUpdatesRef.current();
+          }
+        }),
+        // Grace period of a mandatory update ran out: install it now
+        listen<LockoutStatus>("update-lockout-changed", (event) => {
+          if (event.payload.locked) {
+            checkForUpdatesRef.current();
+          }
+        }),
+      ]);
+    };
+
+    setupUpdateCheckListeners();
+
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten());
+    };
+  }, []);
+
   // Real-time update notification listener (SSE)
   // CRITICAL: Empty dependency array prevents reconnections during state changes
   useEffect(() => {
@@ -131,6 +202,52 @@ function UpdateDialog({ autoCheck = true, onDismiss }: Readonly<UpdateDialogProp
     };
   }, []); // Empty deps - SSE connection persists for entire component lifetime
 
+  // Offer a rollback when the freshly installed version keeps crashing on startup
+  useEffect(() => {
+    let unlisten: UnlistenFn | null = null;
+    let prompted = false;
+
+    const offerRollback = async (status: RollbackStatus) => {
+      if (prompted || !status.available || status.failed_launches === 0) {
+        return;
+      }
+      prompted = true;
+      const confirmed = await confirm(
+        `Version ${status.target_version} closed unexpectedly ${status.failed_launches} time(s) after starting. Do you want to go back to version ${status.previous_version}?`,
+        {
+          title: "Update Problem Detected",
+          kind: "warning",
+          okLabel: "Roll Back",
+          cancelLabel: "Keep Current Version"
+        }
+      );
+      if (confirmed) {
+        try {
+          await invoke("rollback_update");
+        } catch (err) {
+          console.error("[UpdateDialog] Rollback failed:", err);
+        }
+      }
+    };
+
+    const setupRollbackListener = async () => {
+      unlisten = await listen<RollbackStatus>("update-rollback-available", (event) => {
+        offerRollback(event.payload);
+      });
+      // The event may have fired before the listener was registered
+      const status = await invoke<RollbackStatus>("get_rollback_status");
+      offerRollback(status);
+    };
+
+    setupRollbackListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, []);
+
   useEffect(() => {
     let unlisten: UnlistenFn | null = null;
 
@@ -231,6 +348,31 @@ function UpdateDialog({ autoCheck = true, onDismiss }: Readonly<UpdateDialogProp
     );
   }
 
+  // Mandatory update overdue and its install failed: the agent stays restricted
+  if (state === "mandatory-blocked") {
+    return (
+      <div className="update-dialog-overlay mandatory">
+        <div className="update-dialog">
+          <div className="update-dialog-header">
+            <span className="update-icon error">!</span>
+            <h2>Update Required</h2>
+          </div>
+          <div className="update-dialog-body">
+            <div className="mandatory-banner">
+              ⚠️ Version {updateInfo?.version} is required. Time tracking is disabled until it is installed.
+            </div>
+            {error && <p className="error-message">{error}</p>}
+          </div>
+          <div className="update-dialog-actions">
+            <button className="update-btn primary" onClick={handleInstall}>
+              Install Required Update
+            </button>
+          </div>
+        </div>
+      </div>
+    );
+  }
+
   // Checking state - show small indicator
   if (state === "checking") {
     return (
@@ -278,6 +420,22 @@ function UpdateDialog({ autoCheck = true, onDismiss }: Readonly<UpdateDialogProp
     );
   }
 
+  // Mandatory update queued until the policy's install window
+  if (state === "scheduled") {
+    return (
+      <div className="update-checking">
+        <span>
+          Required update v{updateInfo?.version} is scheduled.{" "}
+          {schedule?.reason}
+          {schedule?.next_window ? ` (after ${schedule.next_window})` : ""}
+        </span>
+        <button className="update-btn secondary" onClick={handleInstall}>
+          Install now
+        </button>
+      </div>
+    );
+  }
+
   // Update available state
   if (state === "available" && updateInfo) {
     return (
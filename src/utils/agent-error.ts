@@ -0,0 +1,56 @@
+/**
+ * Agent Error Helpers
+ *
+ * Tauri commands reject with a structured `{ code, message }` object
+ * (see `AgentError` in src-tauri/src/error.rs). Commands that have not been
+ * migrated (e.g. the updater) still reject with a plain string, so these
+ * helpers accept both shapes.
+ */
+
+export type AgentErrorCode =
+  | 'NOT_AUTHENTICATED'
+  | 'AUTH_EXPIRED'
+  | 'INVALID_CREDENTIALS'
+  | 'LOGIN_THROTTLED'
+  | 'ACCOUNT_LOCKED'
+  | 'LICENSE_REQUIRED'
+  | 'OFFLINE'
+  | 'NETWORK'
+  | 'SERVER'
+  | 'PERMISSION_DENIED'
+  | 'STORAGE'
+  | 'TIMEOUT'
+  | 'UPDATE_REQUIRED'
+  | 'INTERNAL'
+
+export interface AgentError {
+  code: AgentErrorCode
+  message: string
+  /** Set with LOGIN_THROTTLED: seconds until the next login attempt is allowed */
+  retryAfterSeconds?: number
+}
+
+export function isAgentError(error: unknown): error is AgentError {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    typeof (error as AgentError).code === 'string' &&
+    typeof (error as AgentError).message === 'string'
+  )
+}
+
+/** Human-readable message for any command rejection */
+export function getErrorMessage(error: unknown): string {
+  if (isAgentError(error)) {
+    return error.message
+  }
+  if (error instanceof Error) {
+    return error.message
+  }
+  return String(error)
+}
+
+/** Error code for a command rejection, or null for legacy string errors */
+export function getErrorCode(error: unknown): AgentErrorCode | null {
+  return isAgentError(error) ? error.code : null
+}
@@ -0,0 +1,95 @@
+/**
+ * Tracking Events
+ *
+ * The Rust backend pushes tracking state changes as Tauri events
+ * (see src-tauri/src/events.rs) so the UI doesn't need to poll
+ * get_tracking_status / get_work_session.
+ */
+
+import { listen, UnlistenFn } from "@tauri-apps/api/event"
+
+export interface SessionStartedEvent {
+  session_id: number
+  started_at: string
+}
+
+export interface SessionEndedEvent {
+  ended_at: string
+}
+
+/** The local work session disagrees with the backend */
+export interface SessionDivergedEvent {
+  local_active: boolean
+  local_started_at?: string
+  backend_active: boolean
+  backend_started_at?: string
+}
+
+export interface IdleChangedEvent {
+  is_idle: boolean
+  idle_time_seconds: number
+  threshold_seconds: number
+  timestamp: string
+}
+
+export interface AppChangedEvent {
+  name: string
+  app_id: string
+  window_title?: string
+  url?: string
+  domain?: string
+  category: string
+  timestamp: string
+}
+
+export interface SyncProgressEvent {
+  total: number
+  synced: number
+  failed: number
+  completed: boolean
+}
+
+/** An OS permission was revoked or granted again while the app was running */
+export interface PermissionChangedEvent {
+  permission: string
+  timestamp: string
+}
+
+/** Local storage pressure changed (database size / free disk space) */
+export interface StoragePressureEvent {
+  pressure: "ok" | "warning" | "critical"
+  database_bytes: number
+  free_disk_bytes?: number
+  timestamp: string
+}
+
+export interface TrackingEventHandlers {
+  onSessionStarted?: (event: SessionStartedEvent) => void
+  onSessionEnded?: (event: SessionEndedEvent) => void
+  onSessionDiverged?: (event: SessionDivergedEvent) => void
+  onIdleChanged?: (event: IdleChangedEvent) => void
+  onAppChanged?: (event: AppChangedEvent) => void
+  onSyncProgress?: (event: SyncProgressEvent) => void
+  onPermissionLost?: (event: PermissionChangedEvent) => void
+  onPermissionRestored?: (event: PermissionChangedEvent) => void
+  onStoragePressure?: (event: StoragePressureEvent) => void
+}
+
+/** Subscribe to all tracking events. Returns a function that unsubscribes. */
+export async function listenToTrackingEvents(handlers: TrackingEventHandlers): Promise<UnlistenFn> {
+  const unlisteners = await Promise.all([
+    listen<SessionStartedEvent>("session-started", (e) => handlers.onSessionStarted?.(e.payload)),
+    listen<SessionEndedEvent>("session-ended", (e) => handlers.onSessionEnded?.(e.payload)),
+    listen<SessionDivergedEvent>("session-diverged", (e) => handlers.onSessionDiverged?.(e.payload)),
+    listen<IdleChangedEvent>("idle-changed", (e) => handlers.onIdleChanged?.(e.payload)),
+    listen<AppChangedEvent>("app-changed", (e) => handlers.onAppChanged?.(e.payload)),
+    listen<SyncProgressEvent>("sync-progress", (e) => handlers.onSyncProgress?.(e.payload)),
+    listen<PermissionChangedEvent>("permission-lost", (e) => handlers.onPermissionLost?.(e.payload)),
+    listen<PermissionChangedEvent>("permission-restored", (e) => handlers.onPermissionRestored?.(e.payload)),
+    listen<StoragePressureEvent>("storage-pressure", (e) => handlers.onStoragePressure?.(e.payload)),
+  ])
+
+  return () => {
+    unlisteners.forEach((unlisten) => unlisten())
+  }
+}